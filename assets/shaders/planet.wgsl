@@ -0,0 +1,42 @@
+#import bevy_pbr::mesh_view_bindings
+
+struct PlanetMaterial {
+    color: vec4<f32>,
+    // Transparent for bodies without atmosphere
+    atmosphere: vec4<f32>,
+    // Towards the sun in world space
+    sun_direction: vec3<f32>,
+};
+
+@group(1) @binding(0)
+var<uniform> material: PlanetMaterial;
+@group(1) @binding(1)
+var base_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var base_sampler: sampler;
+
+@fragment
+fn fragment(
+    #import bevy_pbr::mesh_vertex_output
+) -> @location(0) vec4<f32> {
+    let normal = normalize(world_normal);
+    let to_camera = normalize(view.world_position.xyz - world_position.xyz);
+    let sun = dot(normal, material.sun_direction);
+
+#ifdef VERTEX_UVS
+    let surface = textureSample(base_texture, base_sampler, uv).rgb * material.color.rgb;
+#else
+    let surface = material.color.rgb;
+#endif
+    // Soft terminator, the night side is barely lit by the stars
+    let daylight = smoothstep(-0.1, 0.2, sun);
+    var color = surface * (0.02 + daylight);
+
+    // Sunlight scattered in the atmosphere, thicker towards the rim and reaching a bit over the
+    // terminator into the night
+    let rim = pow(1.0 - max(dot(normal, to_camera), 0.0), 3.0);
+    let scattering = rim * smoothstep(-0.3, 0.3, sun) * material.atmosphere.a;
+    color = color + material.atmosphere.rgb * scattering;
+
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,81 @@
+#import bevy_sprite::mesh2d_view_bindings
+
+struct PostProcessMaterial {
+    // Center in UV, radius in screen heights and strength
+    lens: vec4<f32>,
+    // Heat haze spots in the same format, the biggest first, zero strength for unused ones
+    haze: array<vec4<f32>, 8>,
+    vignette: f32,
+    aberration: f32,
+    distortion: f32,
+    speed_lines: f32,
+    time: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> material: PostProcessMaterial;
+@group(1) @binding(1)
+var source_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var source_sampler: sampler;
+
+// Pseudo-random value from 0 to 1
+fn hash(value: f32) -> f32 {
+    return fract(sin(value * 12.9898) * 43758.5453);
+}
+
+@fragment
+fn fragment(
+    #import bevy_sprite::mesh2d_vertex_output
+) -> @location(0) vec4<f32> {
+    // Gravity lens pulling the view towards the well, the strongest at the edge of its core
+    let size = vec2<f32>(textureDimensions(source_texture));
+    let aspect = vec2<f32>(size.x / size.y, 1.0);
+    let to_lens = (material.lens.xy - uv) * aspect;
+    let lens_distance = length(to_lens) / max(material.lens.z, 0.0001);
+    let bend = material.lens.w * material.lens.z / max(lens_distance * lens_distance, 1.0);
+    let lensed = uv + to_lens / max(length(to_lens), 0.0001) * bend / aspect;
+
+    let offset = uv - vec2<f32>(0.5, 0.5);
+    let radius = length(offset);
+    let direction = offset / max(radius, 0.0001);
+
+    // Shock wave ripples running from the center of the screen
+    let ripple = sin(radius * 60.0 - material.time * 40.0) * material.distortion * 0.006;
+    var distorted = lensed + direction * ripple;
+
+    // Hot air shimmering over the haze spots, the strongest in their centers
+    for (var i = 0; i < 8; i = i + 1) {
+        let spot = material.haze[i];
+        if (spot.w <= 0.0) {
+            break;
+        }
+        let spot_distance = length((uv - spot.xy) * aspect) / max(spot.z, 0.0001);
+        let falloff = pow(max(1.0 - spot_distance, 0.0), 2.0) * spot.w;
+        let noise = vec2<f32>(
+            sin(uv.y * 140.0 + material.time * 30.0 + hash(f32(i)) * 6.28),
+            cos(uv.x * 120.0 - material.time * 26.0)
+        );
+        distorted = distorted + noise * falloff * 0.004;
+    }
+
+    // Color channels split towards the edges
+    let split = offset * material.aberration * 0.03;
+    var color = vec3<f32>(
+        textureSample(source_texture, source_sampler, distorted + split).r,
+        textureSample(source_texture, source_sampler, distorted).g,
+        textureSample(source_texture, source_sampler, distorted - split).b
+    );
+
+    // Thin radial streaks at the edges, flickering to look like passing dust
+    let angle = atan2(offset.y, offset.x);
+    let streak = step(0.9, hash(floor(angle * 80.0) + floor(material.time * 15.0)));
+    let lines = streak * smoothstep(0.25, 0.7, radius) * material.speed_lines;
+    color = mix(color, vec3<f32>(1.0, 1.0, 1.0), lines * 0.25);
+
+    // Dark red edges closing in with the hull damage
+    let edge = smoothstep(0.75 - 0.4 * material.vignette, 0.8, radius) * material.vignette;
+    color = mix(color, vec3<f32>(0.25, 0.0, 0.0), edge * 0.8);
+
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,37 @@
+#import bevy_pbr::mesh_view_bindings
+
+struct ShieldMaterial {
+    color: vec4<f32>,
+    // Directions to recent hits from the center in world space, with the ripple progress in w,
+    // from 0 right after the hit to 1 once faded out
+    impacts: array<vec4<f32>, 4>,
+    // Shield charge, from 0 to 1
+    charge: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> material: ShieldMaterial;
+
+@fragment
+fn fragment(
+    #import bevy_pbr::mesh_vertex_output
+) -> @location(0) vec4<f32> {
+    let normal = normalize(world_normal);
+    let to_camera = normalize(view.world_position.xyz - world_position.xyz);
+    let fresnel = pow(1.0 - abs(dot(normal, to_camera)), 4.0);
+
+    // Rings spreading from every hit over the surface, fading as they go
+    var ripples = 0.0;
+    for (var i = 0; i < 4; i = i + 1) {
+        let impact = material.impacts[i];
+        if (impact.w < 1.0) {
+            let distance = acos(clamp(dot(normal, impact.xyz), -1.0, 1.0));
+            // In radians of the arc: the ring spreads up to 1.5 from the hit and is 0.2 wide
+            let ring = (distance - 1.5 * impact.w) / 0.2;
+            ripples = ripples + exp(-ring * ring) * (1.0 - impact.w);
+        }
+    }
+
+    let alpha = clamp(material.color.a * fresnel * material.charge + ripples, 0.0, 1.0);
+    return vec4<f32>(material.color.rgb * (1.0 + ripples), alpha);
+}
@@ -0,0 +1,204 @@
+//! Entities far from the player and every camera are put on hold: their rigid bodies sleep,
+//! particle emitters attached to them pause and their gun layers update less often, see
+//! `AimingSettings::dormant_update_rate`. They wake up once anyone comes close again.
+
+use bevy::prelude::*;
+use bevy_hanabi::ParticleEffect;
+use bevy_rapier3d::prelude::*;
+
+use crate::{aiming::GunLayer, components::Damage, effect_culling::CullRadius, player::Player};
+
+/// Distances to the observers are checked this often, in seconds
+const CHECK_INTERVAL: f32 = 0.5;
+/// Dormant bodies are put to sleep once both their linear and angular speed are below this,
+/// in m/s and rad/s
+const SLEEP_SPEED: f32 = 0.1;
+
+/// Runtime tunables of the activity management.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct ActivitySettings {
+    /// Entities farther than this from the player and every camera go dormant, in meters
+    pub dormant_distance: f32,
+    /// Dormant entities wake up this much closer, so they don't flip back and forth at the
+    /// border, in meters
+    pub wake_margin: f32,
+}
+
+impl Default for ActivitySettings {
+    fn default() -> Self {
+        Self {
+            dormant_distance: 2500.0,
+            wake_margin: 250.0,
+        }
+    }
+}
+
+impl ActivitySettings {
+    /// Whether an entity `distance` away from the nearest observer is dormant, given whether it's
+    /// `dormant` now
+    fn is_dormant(&self, distance: f32, dormant: bool) -> bool {
+        if dormant {
+            distance > self.dormant_distance - self.wake_margin
+        } else {
+            distance > self.dormant_distance
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ActivitySettings;
+
+    #[test]
+    fn test_is_dormant() {
+        let settings = ActivitySettings {
+            dormant_distance: 1000.0,
+            wake_margin: 100.0,
+        };
+        assert!(!settings.is_dormant(500.0, false));
+        assert!(settings.is_dormant(1100.0, false));
+        // Dormant entities keep sleeping within the margin
+        assert!(!settings.is_dormant(950.0, false));
+        assert!(settings.is_dormant(950.0, true));
+        assert!(!settings.is_dormant(850.0, true));
+    }
+}
+
+/// Entity far from the player and every camera, simulated in less detail.
+#[derive(Component, Default)]
+pub struct Dormant;
+
+/// Body put to sleep for being dormant
+#[derive(Component)]
+struct Asleep;
+
+/// Emitter paused for being dormant
+#[derive(Component)]
+struct Paused;
+
+/// Moving bodies, gun layers and emitters attached to other entities go dormant, while projectiles
+/// are short-lived anyway and pooled effects are managed by their pools.
+type Managed = (
+    Or<(
+        With<RigidBody>,
+        With<GunLayer>,
+        (With<ParticleEffect>, With<Parent>),
+    )>,
+    Without<Damage>,
+    Without<Player>,
+);
+
+fn update_activity(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<ActivitySettings>,
+    mut next_check: Local<f32>,
+    observers: Query<&GlobalTransform, Or<(With<Player>, With<Camera3d>)>>,
+    entities: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            Option<&CullRadius>,
+            Option<&Dormant>,
+        ),
+        Managed,
+    >,
+) {
+    *next_check -= time.delta_seconds();
+    if *next_check > 0.0 {
+        return;
+    }
+    *next_check = CHECK_INTERVAL;
+
+    let observers: Vec<_> = observers.iter().map(|t| t.translation()).collect();
+    // Nothing is far from nobody, e.g. in a headless app
+    if observers.is_empty() {
+        return;
+    }
+    for (entity, transform, cull_radius, dormant) in entities.iter() {
+        let position = transform.translation();
+        // Large effects are active while some of their particles are close enough
+        let reach = cull_radius.map_or(0.0, |r| r.0);
+        let distance = observers
+            .iter()
+            .map(|observer| observer.distance(position))
+            .fold(f32::INFINITY, f32::min);
+        let is_dormant = settings.is_dormant(distance - reach, dormant.is_some());
+        if is_dormant == dormant.is_some() {
+            continue;
+        }
+        if is_dormant {
+            commands.entity(entity).insert(Dormant);
+        } else {
+            commands.entity(entity).remove::<Dormant>();
+        }
+    }
+}
+
+// Moving bodies keep flying, as sleep would stop them. A sleeping body is woken by any force
+// or contact anyway.
+fn enter_dormancy(
+    mut commands: Commands,
+    mut bodies: Query<
+        (Entity, &RigidBody, Option<&Velocity>, Option<&mut Sleeping>),
+        Added<Dormant>,
+    >,
+    mut emitters: Query<(Entity, &mut ParticleEffect), Added<Dormant>>,
+) {
+    for (entity, body, velocity, sleeping) in bodies.iter_mut() {
+        let moving = velocity.map_or(false, |velocity| {
+            velocity.linvel.length() > SLEEP_SPEED || velocity.angvel.length() > SLEEP_SPEED
+        });
+        if *body != RigidBody::Dynamic || moving {
+            continue;
+        }
+        match sleeping {
+            Some(mut sleeping) => sleeping.sleeping = true,
+            None => {
+                commands.entity(entity).insert(Sleeping {
+                    sleeping: true,
+                    ..default()
+                });
+            }
+        }
+        commands.entity(entity).insert(Asleep);
+    }
+
+    for (entity, mut effect) in emitters.iter_mut() {
+        let Some(spawner) = effect.maybe_spawner() else {
+            continue;
+        };
+        if spawner.is_active() {
+            spawner.set_active(false);
+            commands.entity(entity).insert(Paused);
+        }
+    }
+}
+
+fn leave_dormancy(
+    mut commands: Commands,
+    mut bodies: Query<(Entity, &mut Sleeping), (With<Asleep>, Without<Dormant>)>,
+    mut emitters: Query<(Entity, &mut ParticleEffect), (With<Paused>, Without<Dormant>)>,
+) {
+    for (entity, mut sleeping) in bodies.iter_mut() {
+        sleeping.sleeping = false;
+        commands.entity(entity).remove::<Asleep>();
+    }
+    for (entity, mut effect) in emitters.iter_mut() {
+        if let Some(spawner) = effect.maybe_spawner() {
+            spawner.set_active(true);
+        }
+        commands.entity(entity).remove::<Paused>();
+    }
+}
+
+/// Marks entities far from the player and every camera as `Dormant`, and puts them on hold.
+pub struct ActivityPlugin;
+impl Plugin for ActivityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActivitySettings>()
+            .add_system(update_activity)
+            .add_system(enter_dormancy.after(update_activity))
+            .add_system(leave_dormancy.after(update_activity));
+    }
+}
@@ -1,64 +1,770 @@
-use bevy::prelude::*;
+use bevy::{
+    ecs::{
+        entity::{EntityMap, MapEntities, MapEntitiesError},
+        reflect::ReflectMapEntities,
+    },
+    prelude::*,
+};
 use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    activity::Dormant,
+    components::Fraction,
+    gimbal::Gimbal,
+    gun::{scatter, Charge, Gun, Trigger},
+    ion_storm::{self, InIonStorm},
+};
+
+/// How well a gunner leads moving targets, from the cheapest to the most accurate prediction.
+/// Cheap fodder aims naively, while elite units predict perfectly.
+#[derive(
+    Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Reflect, FromReflect,
+)]
+#[cfg_attr(feature = "devtools", derive(bevy_inspector_egui::Inspectable))]
+pub enum LeadQuality {
+    /// Aims at the current target position
+    None,
+    /// Leads by the target velocity over the time of flight at the current distance
+    FirstOrder,
+    /// Solves the interception with the target velocity
+    Quadratic,
+    /// Solves the interception with the velocity relative to the moving shooter, whose
+    /// projectiles inherit its velocity, and with the ballistic drop
+    #[default]
+    Full,
+}
+
+impl LeadQuality {
+    pub const ALL: [LeadQuality; 4] = [
+        LeadQuality::None,
+        LeadQuality::FirstOrder,
+        LeadQuality::Quadratic,
+        LeadQuality::Full,
+    ];
+
+    /// Aiming vector to the target `to_target` away, moving with `target_vel` while the shooter
+    /// moves with `own_vel`. Projectiles with `projectile_speed` drop with `gravity`.
+    fn aim(
+        self,
+        to_target: Vec3,
+        target_vel: Vec3,
+        own_vel: Vec3,
+        projectile_speed: f32,
+        gravity: Vec3,
+    ) -> Vec3 {
+        match self {
+            LeadQuality::None => to_target,
+            LeadQuality::FirstOrder => {
+                to_target + target_vel * to_target.length() / projectile_speed.max(f32::EPSILON)
+            }
+            LeadQuality::Quadratic => aiming_vector(
+                Vec3::ZERO,
+                to_target,
+                target_vel,
+                projectile_speed,
+                Vec3::ZERO,
+            ),
+            LeadQuality::Full => aiming_vector(
+                Vec3::ZERO,
+                to_target,
+                target_vel - own_vel,
+                projectile_speed,
+                gravity,
+            ),
+        }
+    }
+}
 
 /// Annotates an entity to be used for building direction vector to the specified target.
-#[derive(Component, Default)]
+/// Aiming solution is recalculated by the gun layer itself, so it's read-only in the inspector.
+#[derive(Component, Default, Reflect)]
+#[reflect(Component, MapEntities)]
+#[cfg_attr(feature = "devtools", derive(bevy_inspector_egui::Inspectable))]
 pub struct GunLayer {
     target: Option<Entity>,
+    /// Target designated by an ally, overrides autonomous target selection while it's valid
+    #[reflect(ignore)]
+    #[cfg_attr(feature = "devtools", inspectable(ignore))]
+    priority: Option<PriorityTarget>,
+    #[cfg_attr(feature = "devtools", inspectable(read_only))]
     pub axis: Vec3,
+    #[cfg_attr(feature = "devtools", inspectable(read_only))]
     pub angle: f32,
+    #[cfg_attr(feature = "devtools", inspectable(read_only))]
     pub distance: f32,
+    /// Aiming direction cached from the last update, zero if there is no target
+    #[cfg_attr(feature = "devtools", inspectable(read_only))]
+    direction: Vec3,
+    /// Interception solved for the target, reused by updates while it's still accurate
+    #[reflect(ignore)]
+    #[cfg_attr(feature = "devtools", inspectable(ignore))]
+    solution: Option<AimingSolution>,
+    /// Prediction of the target movement, limited by `AimingSettings::max_lead`
+    pub lead: LeadQuality,
+    /// Largest random aiming error in radians, rolled anew with every update while
+    /// `AimingSettings::aim_errors` is on
+    #[cfg_attr(feature = "devtools", inspectable(min = 0.0, max = 0.2))]
+    pub aim_error: f32,
+    /// Seconds before opening fire at a newly selected target
+    #[cfg_attr(feature = "devtools", inspectable(min = 0.0, max = 5.0))]
+    pub reaction_delay: f32,
+    /// Seconds left before opening fire at the current target
+    #[cfg_attr(feature = "devtools", inspectable(read_only))]
+    reaction_left: f32,
+    /// Seconds left before the next update, initialized on the first tick
+    #[reflect(ignore)]
+    #[cfg_attr(feature = "devtools", inspectable(ignore))]
+    update_timer: Option<f32>,
+    /// Whether target and aiming direction are updated this frame
+    #[reflect(ignore)]
+    #[cfg_attr(feature = "devtools", inspectable(ignore))]
+    update_due: bool,
 }
 
-#[derive(Component, Copy, Clone, PartialEq, Eq)]
-pub enum Fraction {
-    Drones,
-    Turrets,
+impl MapEntities for GunLayer {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        // Targets outside of the scene are dropped, so a new one is selected on the next update
+        self.target = self.target.and_then(|e| entity_map.get(e).ok());
+        self.solution = None;
+        Ok(())
+    }
 }
 
-fn aiming_vector(origin: Vec3, target_pos: Vec3, relative_vel: Vec3) -> Vec3 {
-    // todo: get from parameter
-    let projectile_speed = 200.0;
+struct PriorityTarget {
+    target: Entity,
+    /// Seconds left before returning to autonomous target selection
+    time_left: f32,
+}
 
-    let to_target = target_pos - origin;
+/// Interception of the target, solved for its position and velocity relative to the gun layer.
+#[derive(Clone, Debug)]
+struct AimingSolution {
+    target: Entity,
+    lead: LeadQuality,
+    to_target: Vec3,
+    relative_vel: Vec3,
+    projectile_speed: f32,
+    /// Aiming vector to the interception point
+    aim: Vec3,
+    /// `Time::elapsed_seconds` when solved
+    solved_at: f32,
+}
+
+impl AimingSolution {
+    /// Aiming vector to the `target` now `to_target` away and moving with `relative_vel`, shifted
+    /// from the solved one by the target displacement since then. `None` if the solution is made
+    /// for another target, projectile or lead quality, is too old, or the target has maneuvered.
+    fn reuse(
+        &self,
+        target: Entity,
+        lead: LeadQuality,
+        to_target: Vec3,
+        relative_vel: Vec3,
+        projectile_speed: f32,
+        now: f32,
+    ) -> Option<Vec3> {
+        let age = now - self.solved_at;
+        if self.target != target
+            || self.lead != lead
+            || self.projectile_speed != projectile_speed
+            || !(0.0..=MAX_SOLUTION_AGE).contains(&age)
+            || relative_vel.distance(self.relative_vel) > VELOCITY_TOLERANCE
+        {
+            return None;
+        }
+        // The target has drifted off the course the solution was made for
+        let expected = self.to_target + self.relative_vel * age;
+        if to_target.distance(expected) > POSITION_TOLERANCE * to_target.length() {
+            return None;
+        }
+        Some(self.aim + (to_target - self.to_target))
+    }
+}
+
+impl GunLayer {
+    /// Currently aimed target, if any
+    pub fn target(&self) -> Option<Entity> {
+        self.target
+    }
+
+    /// Aiming direction in world space, zero without a target
+    pub fn direction(&self) -> Vec3 {
+        self.direction
+    }
+
+    /// Overrides autonomous target selection with `target` for `duration` seconds
+    pub fn designate(&mut self, target: Entity, duration: f32) {
+        self.priority = Some(PriorityTarget {
+            target,
+            time_left: duration,
+        });
+    }
+
+    /// Drops the `target` once it's out of the battle, e.g. returned to the pool, so another one
+    /// is selected on the next update
+    pub fn forget(&mut self, target: Entity) {
+        if self.target == Some(target) {
+            self.set_target(None);
+        }
+        if matches!(&self.priority, Some(priority) if priority.target == target) {
+            self.priority = None;
+        }
+    }
+
+    /// Whether target and aiming direction were updated this frame.
+    /// Steering systems follow the same schedule, instead of reacting every frame.
+    pub fn updated(&self) -> bool {
+        self.update_due
+    }
+
+    /// Switches to the `target`, a new one is engaged only after the reaction delay
+    fn set_target(&mut self, target: Option<Entity>) {
+        if target != self.target {
+            self.reaction_left = self.reaction_delay;
+        }
+        self.target = target;
+    }
+
+    /// Advances the update timer, returns true if the update is due
+    fn tick(&mut self, entity: Entity, delta: f32, interval: f32) -> bool {
+        // Gun layers spawned at once get different first delays, so updates are spread over frames
+        let timer = self.update_timer.get_or_insert_with(|| {
+            (entity.index() % STAGGER_SLOTS) as f32 / STAGGER_SLOTS as f32 * interval
+        });
+        *timer -= delta;
+        if *timer > 0.0 {
+            return false;
+        }
+        // Missed updates are not accumulated after long frames
+        *timer = (*timer + interval).max(0.0);
+        true
+    }
+}
+
+/// Firing in bursts: triggers are held for `burst` seconds, then released for `pause` seconds.
+#[derive(Clone, Debug, Default, PartialEq, Reflect, FromReflect)]
+pub struct BurstDiscipline {
+    /// In seconds
+    pub burst: f32,
+    /// In seconds
+    pub pause: f32,
+}
+
+/// Largest aiming error to open fire with, depending on the distance to the target.
+#[derive(Clone, Debug, PartialEq, Reflect, FromReflect)]
+pub enum MaxAngleFn {
+    /// The same error at any distance, in radians
+    Constant(f32),
+    /// The error a target of `size` meters is seen at, but never below `min` radians
+    TargetSize { size: f32, min: f32 },
+}
+
+impl MaxAngleFn {
+    /// Largest aiming error in radians at a target `distance` meters away
+    pub fn max_angle(&self, distance: f32) -> f32 {
+        match *self {
+            Self::Constant(angle) => angle,
+            Self::TargetSize { size, min } => (size / distance).max(min),
+        }
+    }
+}
+
+/// Decides when guns of the `GunLayer` on the same entity open fire. Guns might be attached to
+/// the gun layer itself or to its children, like drone barrels, their triggers are pulled while
+/// the target is within the range and the aiming error is small enough to hit it.
+/// Each gun also holds fire beyond its own effective range.
+#[derive(Component, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct FireControl {
+    /// Aiming error at which guns open fire
+    pub max_angle_fn: MaxAngleFn,
+    /// Targets closer than this are not engaged, in meters
+    pub min_range: f32,
+    /// Targets farther than this are not engaged, in meters
+    pub max_range: f32,
+    /// Fire in bursts instead of holding the triggers all the time
+    pub burst_discipline: Option<BurstDiscipline>,
+    /// Don't fire regardless of the target, like a stowed turret
+    pub hold_fire: bool,
+    /// Seconds of firing within the current burst cycle
+    #[reflect(ignore)]
+    cycle: f32,
+}
+
+impl Default for FireControl {
+    fn default() -> Self {
+        Self {
+            max_angle_fn: MaxAngleFn::TargetSize {
+                size: 7.0,
+                min: 0.1,
+            },
+            min_range: 0.0,
+            max_range: 3000.0,
+            burst_discipline: None,
+            hold_fire: false,
+            cycle: 0.0,
+        }
+    }
+}
+
+impl FireControl {
+    /// Whether a target `distance` meters away is hit with the aiming error of `angle` radians.
+    /// Zero distance means no target.
+    pub fn in_envelope(&self, angle: f32, distance: f32) -> bool {
+        distance != 0.0
+            && (self.min_range..self.max_range).contains(&distance)
+            && angle < self.max_angle_fn.max_angle(distance)
+    }
+
+    /// Whether triggers are pulled this frame, advancing the burst cycle by `delta` seconds.
+    /// The cycle is paused while the target is out of the envelope, so losing and reacquiring
+    /// it doesn't start a new burst.
+    fn update(&mut self, angle: f32, distance: f32, delta: f32) -> bool {
+        if self.hold_fire || !self.in_envelope(angle, distance) {
+            return false;
+        }
+        let Some(burst) = &self.burst_discipline else {
+            return true;
+        };
+        let firing = self.cycle < burst.burst;
+        let period = (burst.burst + burst.pause).max(f32::EPSILON);
+        self.cycle = (self.cycle + delta) % period;
+        firing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        aiming_vector, closest_target, interception_time, AimingSolution, BurstDiscipline,
+        FireControl, Fraction, GunLayer, LeadQuality, MaxAngleFn, TargetSnapshot, STAGGER_SLOTS,
+    };
+    use bevy::prelude::{Entity, Vec3};
 
+    #[test]
+    fn test_staggered_updates() {
+        // values are exact in binary, so no rounding errors
+        let interval = 1.0;
+        let frame = interval / STAGGER_SLOTS as f32 / 2.0;
+        let mut first = GunLayer::default();
+        let mut second = GunLayer::default();
+
+        // the first update of neighbour entities happens at different frames
+        assert!(first.tick(Entity::from_raw(0), frame, interval));
+        assert!(!second.tick(Entity::from_raw(1), frame, interval));
+        assert!(second.tick(Entity::from_raw(1), frame, interval));
+
+        // and then once per interval
+        let updates = (0..STAGGER_SLOTS * 4)
+            .filter(|_| first.tick(Entity::from_raw(0), frame, interval))
+            .count();
+        assert_eq!(updates, 2);
+    }
+
+    #[test]
+    fn test_reaction_delay() {
+        let mut gun_layer = GunLayer {
+            reaction_delay: 1.0,
+            ..Default::default()
+        };
+        gun_layer.set_target(Some(Entity::from_raw(0)));
+        assert_eq!(gun_layer.reaction_left, 1.0);
+
+        // keeping the same target doesn't delay the fire again
+        gun_layer.reaction_left = 0.0;
+        gun_layer.set_target(Some(Entity::from_raw(0)));
+        assert_eq!(gun_layer.reaction_left, 0.0);
+        gun_layer.set_target(Some(Entity::from_raw(1)));
+        assert_eq!(gun_layer.reaction_left, 1.0);
+    }
+
+    #[test]
+    fn test_closest_target() {
+        let snapshot = |index, position, fraction| TargetSnapshot {
+            entity: Entity::from_raw(index),
+            position,
+            velocity: Vec3::ZERO,
+            fraction,
+        };
+        let targets = [
+            snapshot(0, Vec3::new(0.0, 0.0, -10.0), Some(Fraction::Drones)),
+            snapshot(1, Vec3::new(10.0, 0.0, -10.0), Some(Fraction::Turrets)),
+            snapshot(2, Vec3::new(0.0, 0.0, 10.0), None),
+        ];
+        let select = |fraction| {
+            closest_target(
+                &targets,
+                Vec3::ZERO,
+                Vec3::NEG_Z,
+                Vec3::ZERO,
+                fraction,
+                f32::INFINITY,
+            )
+        };
+
+        // the one straight ahead is preferred, unless it's friendly
+        assert_eq!(select(None), Some(Entity::from_raw(0)));
+        assert_eq!(select(Some(Fraction::Turrets)), Some(Entity::from_raw(0)));
+        assert_eq!(select(Some(Fraction::Drones)), Some(Entity::from_raw(1)));
+        assert_eq!(
+            closest_target(
+                &[],
+                Vec3::ZERO,
+                Vec3::NEG_Z,
+                Vec3::ZERO,
+                None,
+                f32::INFINITY
+            ),
+            None
+        );
+        // only the closest one is seen with a short sensor range
+        assert_eq!(
+            closest_target(&targets, Vec3::ZERO, Vec3::Z, Vec3::ZERO, None, 11.0),
+            Some(Entity::from_raw(2))
+        );
+        assert_eq!(
+            closest_target(&targets, Vec3::ZERO, Vec3::Z, Vec3::ZERO, None, 5.0),
+            None
+        );
+    }
+
+    #[test]
+    fn test_interception_time() {
+        let to_target = Vec3::new(0.0, 0.0, -100.0);
+        assert_eq!(interception_time(to_target, Vec3::ZERO, 20.0), Some(5.0));
+        // approaching target is met earlier, while a receding one later
+        assert_eq!(
+            interception_time(to_target, Vec3::Z * 30.0, 20.0),
+            Some(2.0)
+        );
+        assert_eq!(
+            interception_time(to_target, Vec3::NEG_Z * 10.0, 20.0),
+            Some(10.0)
+        );
+        // and a faster one can't be caught at all
+        assert_eq!(interception_time(to_target, Vec3::NEG_Z * 30.0, 20.0), None);
+    }
+
+    #[test]
+    fn test_ballistic_aiming() {
+        let gravity = Vec3::NEG_Y * 10.0;
+        let speed = 100.0;
+        // Flies the projectile along the aimed arc and measures the miss at the interception time
+        let miss = |to_target: Vec3, target_vel: Vec3| {
+            let aim = aiming_vector(Vec3::ZERO, to_target, target_vel, speed, gravity);
+            let time = aim.length() / speed;
+            let projectile = aim.normalize() * speed * time + 0.5 * gravity * time * time;
+            projectile.distance(to_target + target_vel * time)
+        };
+        assert!(miss(Vec3::new(0.0, 0.0, -300.0), Vec3::ZERO) < 0.1);
+        assert!(miss(Vec3::new(200.0, 50.0, -300.0), Vec3::X * 20.0) < 0.1);
+
+        // aimed above the target to compensate the drop
+        let aim = aiming_vector(Vec3::ZERO, Vec3::NEG_Z * 300.0, Vec3::ZERO, speed, gravity);
+        assert!(aim.y > 0.0);
+        // and straight at it without gravity
+        let aim = aiming_vector(
+            Vec3::ZERO,
+            Vec3::NEG_Z * 300.0,
+            Vec3::ZERO,
+            speed,
+            Vec3::ZERO,
+        );
+        assert_eq!(aim, Vec3::NEG_Z * 300.0);
+    }
+
+    #[test]
+    fn test_solution_reuse() {
+        let target = Entity::from_raw(1);
+        let full = LeadQuality::Full;
+        let to_target = Vec3::new(0.0, 0.0, -500.0);
+        let velocity = Vec3::X * 50.0;
+        let solution = AimingSolution {
+            target,
+            lead: full,
+            to_target,
+            relative_vel: velocity,
+            projectile_speed: 100.0,
+            aim: aiming_vector(Vec3::ZERO, to_target, velocity, 100.0, Vec3::ZERO),
+            solved_at: 10.0,
+        };
+
+        // shifted along with the target moving as predicted
+        let moved = to_target + velocity * 0.5;
+        assert_eq!(
+            solution.reuse(target, full, moved, velocity, 100.0, 10.5),
+            Some(solution.aim + velocity * 0.5)
+        );
+        // another target, lead quality or projectile
+        let other = Entity::from_raw(2);
+        assert_eq!(
+            solution.reuse(other, full, moved, velocity, 100.0, 10.5),
+            None
+        );
+        let naive = LeadQuality::FirstOrder;
+        assert_eq!(
+            solution.reuse(target, naive, moved, velocity, 100.0, 10.5),
+            None
+        );
+        assert_eq!(
+            solution.reuse(target, full, moved, velocity, 200.0, 10.5),
+            None
+        );
+        // too old
+        let moved = to_target + velocity * 2.0;
+        assert_eq!(
+            solution.reuse(target, full, moved, velocity, 100.0, 12.0),
+            None
+        );
+        // maneuvering target
+        assert_eq!(
+            solution.reuse(target, full, to_target, -velocity, 100.0, 10.5),
+            None
+        );
+        let off_course = to_target + Vec3::Y * 20.0;
+        assert_eq!(
+            solution.reuse(target, full, off_course, velocity, 100.0, 10.0),
+            None
+        );
+    }
+
+    #[test]
+    fn test_lead_quality() {
+        let to_target = Vec3::new(0.0, 0.0, -100.0);
+        let target_vel = Vec3::X * 10.0;
+        let own_vel = Vec3::X * 10.0;
+        let aim = |lead: LeadQuality| lead.aim(to_target, target_vel, own_vel, 20.0, Vec3::ZERO);
+
+        assert_eq!(aim(LeadQuality::None), to_target);
+        // leads by 5 seconds of flight at the current distance
+        assert_eq!(aim(LeadQuality::FirstOrder), Vec3::new(50.0, 0.0, -100.0));
+        // the interception is farther away than the current distance
+        assert!(aim(LeadQuality::Quadratic).x > 50.0);
+        // shooter flies along with the target, so its projectiles need no lead
+        assert_eq!(aim(LeadQuality::Full), to_target);
+    }
+
+    #[test]
+    fn test_fire_control_envelope() {
+        let fire_control = FireControl {
+            min_range: 50.0,
+            ..Default::default()
+        };
+        // no target
+        assert!(!fire_control.in_envelope(0.0, 0.0));
+        // too close and too far
+        assert!(!fire_control.in_envelope(0.0, 10.0));
+        assert!(!fire_control.in_envelope(0.0, 5000.0));
+        // close targets are large enough to hit with a bigger error
+        assert!(fire_control.in_envelope(0.11, 60.0));
+        assert!(!fire_control.in_envelope(0.11, 1000.0));
+        assert!(fire_control.in_envelope(0.09, 1000.0));
+
+        let fixed = FireControl {
+            max_angle_fn: MaxAngleFn::Constant(0.05),
+            ..Default::default()
+        };
+        assert!(!fixed.in_envelope(0.06, 60.0));
+        assert!(fixed.in_envelope(0.04, 1000.0));
+    }
+
+    #[test]
+    fn test_burst_discipline() {
+        let mut fire_control = FireControl {
+            burst_discipline: Some(BurstDiscipline {
+                burst: 1.0,
+                pause: 2.0,
+            }),
+            ..Default::default()
+        };
+        // values are exact in binary, so no rounding errors
+        let mut fire = |angle| fire_control.update(angle, 100.0, 0.5);
+        let pattern: Vec<_> = (0..8).map(|_| fire(0.0)).collect();
+        assert_eq!(
+            pattern,
+            [true, true, false, false, false, false, true, true]
+        );
+        // the cycle doesn't advance while the target is out of the envelope
+        assert!(!fire(1.0));
+        assert!(!fire(0.0));
+
+        fire_control.hold_fire = true;
+        assert!(!fire_control.update(0.0, 100.0, 0.5));
+    }
+}
+
+/// Emit this event to designate `target` as a priority one for all gun layers of the `fraction`
+pub struct DesignateTargetEvent {
+    pub target: Entity,
+    pub fraction: Fraction,
+    /// Seconds before gun layers return to autonomous target selection
+    pub duration: f32,
+}
+
+/// Projectile speed used for prediction when gun layer has no guns
+const DEFAULT_PROJECTILE_SPEED: f32 = 200.0;
+/// Aiming solutions are solved anew at least this often, in seconds
+const MAX_SOLUTION_AGE: f32 = 1.0;
+/// Change of the target relative velocity that requires a new aiming solution, in m/s
+const VELOCITY_TOLERANCE: f32 = 2.0;
+/// Deviation of the target from the predicted position that requires a new aiming solution,
+/// relative to the distance to it
+const POSITION_TOLERANCE: f32 = 0.01;
+/// Number of frame offsets to distribute gun layer updates between
+const STAGGER_SLOTS: u32 = 8;
+
+/// Runtime tunables of the aiming.
+#[derive(Resource, Clone, Debug)]
+pub struct AimingSettings {
+    /// Best lead quality of all gun layers, lowered to save CPU in large battles.
+    /// `LeadQuality::None` aims at the current target positions.
+    pub max_lead: LeadQuality,
+    /// How often gun layers select targets and update aiming solutions, in Hz
+    pub update_rate: f32,
+    /// Update rate of `Dormant` gun layers far from the player, in Hz
+    pub dormant_update_rate: f32,
+    /// Gun layers miss by their random `GunLayer::aim_error`, which shrinks as AI gunners earn
+    /// veterancy ranks. Without it they aim precisely, however well they lead the target.
+    pub aim_errors: bool,
+}
+
+impl AimingSettings {
+    /// Seconds between gun layer updates, longer for dormant ones
+    pub fn update_interval(&self, dormant: bool) -> f32 {
+        let rate = if dormant {
+            self.dormant_update_rate.min(self.update_rate)
+        } else {
+            self.update_rate
+        };
+        rate.max(f32::EPSILON).recip()
+    }
+}
+
+impl Default for AimingSettings {
+    fn default() -> Self {
+        Self {
+            max_lead: LeadQuality::Full,
+            update_rate: 10.0,
+            dormant_update_rate: 2.0,
+            aim_errors: true,
+        }
+    }
+}
+
+/// Time in seconds for a projectile with `projectile_speed` to meet a target `to_target` away,
+/// moving with `relative_vel`. `None` if the projectile can't catch up with it.
+pub fn interception_time(
+    to_target: Vec3,
+    relative_vel: Vec3,
+    projectile_speed: f32,
+) -> Option<f32> {
     // solve quadratic equation around interception time
     // with known distance, target's velocity, projectile's velocity
     let squared_speed_diff = projectile_speed * projectile_speed - relative_vel.length_squared();
     let squared_distance = to_target.length_squared();
     let b = to_target.dot(relative_vel);
     let discriminant = b * b + squared_speed_diff * squared_distance;
+    if discriminant < 0.0 {
+        return None;
+    }
 
-    // if we found quadratic equation root(s) - use it, otherwise take zero
-    // as zero is safe - no prediction is made
-    let time = if discriminant >= 0.0 {
-        let sqrt = discriminant.sqrt();
-        let first_root = (b + sqrt) / squared_speed_diff;
-        let second_root = (b - sqrt) / squared_speed_diff;
-        if first_root > 0.0 && second_root > 0.0 {
-            // if both times are valid - take the smallest one
-            first_root.min(second_root)
-        } else if first_root > 0.0 {
-            first_root
-        } else if second_root > 0.0 {
-            second_root
-        } else {
-            0.0
-        }
+    let sqrt = discriminant.sqrt();
+    let first_root = (b + sqrt) / squared_speed_diff;
+    let second_root = (b - sqrt) / squared_speed_diff;
+    if first_root > 0.0 && second_root > 0.0 {
+        // if both times are valid - take the smallest one
+        Some(first_root.min(second_root))
+    } else if first_root > 0.0 {
+        Some(first_root)
+    } else if second_root > 0.0 {
+        Some(second_root)
     } else {
-        0.0
-    };
+        None
+    }
+}
+
+/// Refinements of the interception time for projectiles dropping under gravity
+const BALLISTIC_ITERATIONS: usize = 4;
 
-    to_target + relative_vel * time
+/// Direction to fire a projectile with `projectile_speed` to hit the target, scaled to the
+/// distance to the interception point. The projectile drops with `gravity` acceleration,
+/// so it's aimed above the target.
+fn aiming_vector(
+    origin: Vec3,
+    target_pos: Vec3,
+    relative_vel: Vec3,
+    projectile_speed: f32,
+    gravity: Vec3,
+) -> Vec3 {
+    let to_target = target_pos - origin;
+    // if there is no interception, take zero as it's safe - no prediction is made
+    let mut time = interception_time(to_target, relative_vel, projectile_speed).unwrap_or(0.0);
+    if gravity != Vec3::ZERO {
+        // Aiming above the target compensates the drop, but makes the flight a bit longer, so
+        // the interception time is refined against the raised aim point
+        for _ in 0..BALLISTIC_ITERATIONS {
+            let drop = 0.5 * gravity * time * time;
+            let Some(refined) = interception_time(to_target - drop, relative_vel, projectile_speed)
+            else {
+                break;
+            };
+            time = refined;
+        }
+    }
+    to_target + relative_vel * time - 0.5 * gravity * time * time
 }
 
-fn select_target(
-    mut query: Query<(
-        &GlobalTransform,
-        Option<&Velocity>,
-        Option<&Fraction>,
-        &mut GunLayer,
-    )>,
+fn designate_target(
+    mut ev_designate: EventReader<DesignateTargetEvent>,
+    mut gun_layers: Query<(&Fraction, &mut GunLayer)>,
+    fractions: Query<&Fraction>,
+) {
+    for ev in ev_designate.iter() {
+        // Friendly fire is not an option
+        if fractions.get(ev.target).ok() == Some(&ev.fraction) {
+            continue;
+        }
+        for (fraction, mut gun_layer) in gun_layers.iter_mut() {
+            if *fraction == ev.fraction {
+                gun_layer.designate(ev.target, ev.duration);
+            }
+        }
+    }
+}
+
+fn schedule_updates(
+    time: Res<Time>,
+    settings: Res<AimingSettings>,
+    mut gun_layers: Query<(Entity, &mut GunLayer, Option<&Dormant>)>,
+) {
+    for (entity, mut gun_layer, dormant) in gun_layers.iter_mut() {
+        let interval = settings.update_interval(dormant.is_some());
+        gun_layer.update_due = gun_layer.tick(entity, time.delta_seconds(), interval);
+        if gun_layer.reaction_left > 0.0 {
+            gun_layer.reaction_left = (gun_layer.reaction_left - time.delta_seconds()).max(0.0);
+        }
+    }
+}
+
+/// State of a potential target, shared by all gun layers during target selection
+struct TargetSnapshot {
+    entity: Entity,
+    position: Vec3,
+    velocity: Vec3,
+    fraction: Option<Fraction>,
+}
+
+/// Potential targets collected once per frame, so gun layers don't query them one by one.
+/// The buffer is reused between frames to avoid allocations.
+#[derive(Resource, Default)]
+struct TargetSnapshots(Vec<TargetSnapshot>);
+
+/// Gun layers per task during parallel target selection
+const SELECT_TARGET_BATCH_SIZE: usize = 32;
+
+fn collect_targets(
+    mut snapshots: ResMut<TargetSnapshots>,
     targets: Query<
         (
             Entity,
@@ -69,73 +775,256 @@ fn select_target(
         (With<Collider>, Without<Sensor>),
     >,
 ) {
-    for (transform, own_velocity, own_fraction, mut gun_layer) in query.iter_mut() {
-        if !matches!(gun_layer.target, Some(target) if targets.contains(target)) {
-            let forward_direction = transform.forward();
+    snapshots.0.clear();
+    snapshots.0.extend(
+        targets
+            .iter()
+            .map(|(entity, transform, velocity, fraction)| TargetSnapshot {
+                entity,
+                position: transform.translation(),
+                velocity: velocity.map(|v| v.linvel).unwrap_or_default(),
+                fraction: fraction.copied(),
+            }),
+    );
+}
+
+/// Chooses a target within the sensor `range` that requires the least rotation from
+/// `forward_direction`
+fn closest_target(
+    targets: &[TargetSnapshot],
+    origin: Vec3,
+    forward_direction: Vec3,
+    own_vel: Vec3,
+    own_fraction: Option<Fraction>,
+    range: f32,
+) -> Option<Entity> {
+    targets
+        .iter()
+        // Don't select targets with the same fraction
+        .filter(|target| own_fraction.is_none() || target.fraction != own_fraction)
+        .filter(|target| target.position.distance_squared(origin) <= range * range)
+        .map(|target| {
+            // Rough prediction is enough to choose a target
+            let to_target = aiming_vector(
+                origin,
+                target.position,
+                target.velocity - own_vel,
+                DEFAULT_PROJECTILE_SPEED,
+                Vec3::ZERO,
+            );
+            (target.entity, to_target, to_target.length_squared())
+        })
+        // todo: consider spatial optimizations to speed up lookup
+        .filter(|(_, _, sqrared_distance)| {
+            // todo: Fix visibility distance once drones become smart enough not to fly away without a target
+            // const DEFAULT_VISIBILITY_SQARED_RANGE: f32 = 1000.0 * 1000.0;
+            0.0 < *sqrared_distance // && *sqrared_distance < DEFAULT_VISIBILITY_SQARED_RANGE
+        })
+        // find closest target to `forward_direction` to reduce required rotations
+        // convert to integer with 2 digits precision to workaround that f32 is not Ord
+        .max_by_key(|(_, to_target, sqrared_distance)| {
+            (to_target.dot(forward_direction) / sqrared_distance.sqrt() * 100.0) as i32
+        })
+        .map(|(entity, _, _)| entity)
+}
+
+fn select_target(
+    time: Res<Time>,
+    snapshots: Res<TargetSnapshots>,
+    mut query: Query<(
+        &GlobalTransform,
+        Option<&Velocity>,
+        Option<&Fraction>,
+        &mut GunLayer,
+        Option<&InIonStorm>,
+    )>,
+    targets: Query<(), (With<Collider>, Without<Sensor>)>,
+) {
+    let delta = time.delta_seconds();
+    query.par_for_each_mut(
+        SELECT_TARGET_BATCH_SIZE,
+        |(transform, own_velocity, own_fraction, mut gun_layer, in_storm)| {
+            if let Some(priority) = gun_layer.priority.as_mut() {
+                priority.time_left -= delta;
+                if priority.time_left > 0.0 && targets.contains(priority.target) {
+                    let target = priority.target;
+                    gun_layer.set_target(Some(target));
+                    return;
+                }
+                gun_layer.priority = None;
+            }
+            if !gun_layer.update_due {
+                return;
+            }
+
+            // Jammed sensors lose track of targets beyond their reduced range
             let origin = transform.translation();
-            let own_vel = own_velocity.map(|v| v.linvel).unwrap_or_default();
-
-            gun_layer.target = targets
-                .iter()
-                .filter(|(_, _, _, target_fraction)| {
-                    // Don't select targets with the same fraction
-                    !matches!((own_fraction, target_fraction), (Some(&own), Some(&target)) if own == target)
-                })
-                .map(|(entity, transform, velocity, _)| {
-                    let target_vel = velocity.map(|v| v.linvel).unwrap_or_default();
-                    let to_target =
-                        aiming_vector(origin, transform.translation(), target_vel - own_vel);
-                    (entity, to_target, to_target.length_squared())
-                })
-                // todo: consider spatial optimizations to speed up lookup
-                .filter(|(_, _, sqrared_distance)| {
-                    // todo: Fix visibility distance once drones become smart enough not to fly away without a target
-                    // const DEFAULT_VISIBILITY_SQARED_RANGE: f32 = 1000.0 * 1000.0;
-                    0.0 < *sqrared_distance // && *sqrared_distance < DEFAULT_VISIBILITY_SQARED_RANGE
-                })
-                // find closest target to `forward_direction` to reduce required rotations
-                // convert to integer with 2 digits precision to workaround that f32 is not Ord
-                .max_by_key(|(_, to_target, sqrared_distance)| {
-                    (to_target.dot(forward_direction) / sqrared_distance.sqrt() * 100.0) as i32
-                })
-                .map(|(entity, _, _)| entity);
-        }
-    }
+            let range = match in_storm {
+                Some(_) => ion_storm::SENSOR_RANGE,
+                None => f32::INFINITY,
+            };
+            let tracked = |target: Entity| {
+                targets.contains(target)
+                    && (in_storm.is_none()
+                        || snapshots.0.iter().any(|s| {
+                            s.entity == target
+                                && s.position.distance_squared(origin) <= range * range
+                        }))
+            };
+            if !matches!(gun_layer.target, Some(target) if tracked(target)) {
+                let target = closest_target(
+                    &snapshots.0,
+                    origin,
+                    transform.forward(),
+                    own_velocity.map(|v| v.linvel).unwrap_or_default(),
+                    own_fraction.copied(),
+                    range,
+                );
+                gun_layer.set_target(target);
+            }
+        },
+    );
 }
 
 pub fn gun_layer(
-    mut query: Query<(&GlobalTransform, Option<&Velocity>, &mut GunLayer)>,
+    time: Res<Time>,
+    settings: Res<AimingSettings>,
+    rapier_config: Option<Res<RapierConfiguration>>,
+    mut query: Query<(Entity, &GlobalTransform, Option<&Velocity>, &mut GunLayer)>,
     targets: Query<(&GlobalTransform, Option<&Velocity>)>,
+    guns: Query<&Gun>,
+    children: Query<&Children>,
 ) {
-    for (transform, own_velocity, mut gun_layer) in query.iter_mut() {
-        let Some((target, target_velocity)) = gun_layer.target.and_then(|e| targets.get(e).ok()) else {
-            // Target is not selected or not exists anymore - nothing to do.
-            gun_layer.angle = 0.0;
-            gun_layer.distance = 0.0;
-            continue;
-        };
-
-        let own_vel = own_velocity.map(|v| v.linvel).unwrap_or_default();
-        let target_vel = target_velocity.map(|v| v.linvel).unwrap_or_default();
+    // Ballistic projectiles drop under the physics gravity, zero in open space
+    let gravity = rapier_config.map_or(Vec3::ZERO, |config| config.gravity);
+    for (entity, transform, own_velocity, mut gun_layer) in query.iter_mut() {
+        if gun_layer.update_due {
+            let target = gun_layer
+                .target
+                .and_then(|e| targets.get(e).ok().map(|target| (e, target)));
+            (gun_layer.direction, gun_layer.distance) = match target {
+                Some((target_entity, (target, target_velocity))) => {
+                    let own_vel = own_velocity.map(|v| v.linvel).unwrap_or_default();
+                    let target_vel = target_velocity.map(|v| v.linvel).unwrap_or_default();
 
-        let to_target = aiming_vector(
-            transform.translation(),
-            target.translation(),
-            target_vel - own_vel,
-        );
-        let distance = to_target.length();
-        let direction = to_target * distance.recip();
+                    let lead = gun_layer.lead.min(settings.max_lead);
+                    let origin = transform.translation();
+                    let to_target = target.translation() - origin;
+                    let relative_vel = target_vel - own_vel;
+                    let now = time.elapsed_seconds();
+                    // Guns might be attached to the gun layer itself or to its children, like drone barrels
+                    let gun = std::iter::once(entity)
+                        .chain(children.iter_descendants(entity))
+                        .find_map(|e| guns.get(e).ok());
+                    let projectile_speed = gun.map_or(DEFAULT_PROJECTILE_SPEED, Gun::speed);
+                    let cached = gun_layer.solution.as_ref().and_then(|solution| {
+                        solution.reuse(
+                            target_entity,
+                            lead,
+                            to_target,
+                            relative_vel,
+                            projectile_speed,
+                            now,
+                        )
+                    });
+                    let aim = match cached {
+                        Some(aim) => aim,
+                        None if lead == LeadQuality::None => to_target,
+                        None => {
+                            // Unpropelled projectiles drop, so they are aimed along a ballistic arc
+                            let gravity = match gun {
+                                Some(gun) if gun.projectile().ballistic() => gravity,
+                                _ => Vec3::ZERO,
+                            };
+                            let aim =
+                                lead.aim(to_target, target_vel, own_vel, projectile_speed, gravity);
+                            gun_layer.solution = Some(AimingSolution {
+                                target: target_entity,
+                                lead,
+                                to_target,
+                                relative_vel,
+                                projectile_speed,
+                                aim,
+                                solved_at: now,
+                            });
+                            aim
+                        }
+                    };
+                    let distance = aim.length();
+                    let error = if settings.aim_errors {
+                        gun_layer.aim_error
+                    } else {
+                        0.0
+                    };
+                    let direction = scatter(aim * distance.recip(), error);
+                    (direction, distance)
+                }
+                // Target is not selected or not exists anymore - nothing to aim at.
+                None => (Vec3::ZERO, 0.0),
+            };
+        }
 
-        gun_layer.distance = distance;
+        if gun_layer.direction == Vec3::ZERO {
+            gun_layer.angle = 0.0;
+            continue;
+        }
+        // Following the cached direction is cheap, so gun layers keep turning smoothly between updates.
         // Required rotation to align gun layer orientation with `direction`
         (gun_layer.axis, gun_layer.angle) =
-            Quat::from_rotation_arc(transform.forward(), direction).to_axis_angle();
+            Quat::from_rotation_arc(transform.forward(), gun_layer.direction).to_axis_angle();
+    }
+}
+
+pub fn fire_control(
+    time: Res<Time>,
+    mut gun_layers: Query<(Entity, &GunLayer, &mut FireControl)>,
+    children: Query<&Children>,
+    mut triggers: Query<(&mut Trigger, Option<&Gun>, Option<&Charge>)>,
+    gimbals: Query<&Gimbal>,
+) {
+    for (entity, gun_layer, mut fire_control) in gun_layers.iter_mut() {
+        // Gimballed guns reach targets off the gun layer axis by their own deflection
+        let reach = std::iter::once(entity)
+            .chain(children.iter_descendants(entity))
+            .filter_map(|e| gimbals.get(e).ok())
+            .fold(0.0, |reach, gimbal| gimbal.max_angle.max(reach));
+        let angle = (gun_layer.angle - reach).max(0.0);
+        // The burst cycle doesn't advance until the gunner reacts to the new target
+        if gun_layer.reaction_left > 0.0
+            || !fire_control.update(angle, gun_layer.distance, time.delta_seconds())
+        {
+            continue;
+        }
+        for e in std::iter::once(entity).chain(children.iter_descendants(entity)) {
+            let Ok((mut trigger, gun, charge)) = triggers.get_mut(e) else {
+                continue;
+            };
+            // Charged shots are fired on the release of the trigger, right once they are charged
+            if charge.map_or(false, |charge| charge.level() >= 1.0) {
+                continue;
+            }
+            // Shots beyond the effective range only miss or expire before reaching the target
+            if gun.map_or(true, |gun| gun_layer.distance < gun.effective_range()) {
+                trigger.pull();
+            }
+        }
     }
 }
 
+/// Target selection and aiming of `GunLayer`s, leading moving targets, and their fire control.
 pub struct AimingPlugin;
 impl Plugin for AimingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(select_target).add_system(gun_layer);
+        app.init_resource::<AimingSettings>()
+            .init_resource::<TargetSnapshots>()
+            .register_type::<GunLayer>()
+            .register_type::<FireControl>()
+            .add_event::<DesignateTargetEvent>()
+            .add_system(schedule_updates.before(designate_target))
+            .add_system(designate_target.before(select_target))
+            .add_system(collect_targets)
+            .add_system(select_target.after(schedule_updates).after(collect_targets))
+            .add_system(gun_layer.after(select_target))
+            .add_system(fire_control.after(gun_layer));
     }
 }
@@ -0,0 +1,148 @@
+//! Gameplay events around the player that are noticed by ear, like a missile warning or a nearby
+//! explosion. They are reported as `AudioCueEvent`s, so sound effects and on-screen captions for
+//! the hard of hearing are driven by the same events. The game ships no sound effects for them yet,
+//! so for now the cues are only captioned.
+
+use bevy::prelude::*;
+
+use crate::{
+    gun::Heat,
+    jump_gate::JumpInEvent,
+    player::{Player, PrimaryWeapon},
+    projectile::{DamageEvent, DamageSystems, Homing, Shield},
+};
+
+/// Destroyed entities farther from the player aren't heard, in meters
+const EXPLOSION_RADIUS: f32 = 300.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AudioCue {
+    /// A homing missile is launched at the player
+    IncomingMissile,
+    /// The player's shield is depleted
+    ShieldDown,
+    /// Primary weapons of the player are overheated
+    Overheat,
+    /// Something is destroyed nearby
+    Explosion,
+    /// The player is about to crash into a large obstacle
+    CollisionWarning,
+    /// Reinforcements are jumping in
+    JumpIn,
+}
+
+impl AudioCue {
+    /// Localization key of the caption
+    pub fn key(self) -> &'static str {
+        match self {
+            AudioCue::IncomingMissile => "cue-incoming-missile",
+            AudioCue::ShieldDown => "cue-shield-down",
+            AudioCue::Overheat => "cue-overheat",
+            AudioCue::Explosion => "cue-explosion",
+            AudioCue::CollisionWarning => "cue-collision-warning",
+            AudioCue::JumpIn => "cue-jump-in",
+        }
+    }
+}
+
+pub struct AudioCueEvent {
+    pub cue: AudioCue,
+    /// Source of the sound in world coordinates, if it has a direction
+    pub position: Option<Vec3>,
+}
+
+fn incoming_missiles(
+    player: Query<Entity, With<Player>>,
+    missiles: Query<(&Homing, &Transform), Added<Homing>>,
+    mut ev_cue: EventWriter<AudioCueEvent>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    for (homing, transform) in missiles.iter() {
+        if homing.target == player {
+            ev_cue.send(AudioCueEvent {
+                cue: AudioCue::IncomingMissile,
+                position: Some(transform.translation),
+            });
+        }
+    }
+}
+
+fn shield_down(
+    player: Query<&Shield, With<Player>>,
+    mut was_down: Local<bool>,
+    mut ev_cue: EventWriter<AudioCueEvent>,
+) {
+    let Ok(shield) = player.get_single() else {
+        return;
+    };
+    let down = shield.percent() == 0;
+    if down && !*was_down {
+        ev_cue.send(AudioCueEvent {
+            cue: AudioCue::ShieldDown,
+            position: None,
+        });
+    }
+    *was_down = down;
+}
+
+fn overheat(
+    guns: Query<&Heat, With<PrimaryWeapon>>,
+    mut was_overheated: Local<bool>,
+    mut ev_cue: EventWriter<AudioCueEvent>,
+) {
+    let overheated = guns.iter().any(Heat::overheated);
+    if overheated && !*was_overheated {
+        ev_cue.send(AudioCueEvent {
+            cue: AudioCue::Overheat,
+            position: None,
+        });
+    }
+    *was_overheated = overheated;
+}
+
+fn explosions(
+    mut ev_damage: EventReader<DamageEvent>,
+    player: Query<(Entity, &GlobalTransform), With<Player>>,
+    mut ev_cue: EventWriter<AudioCueEvent>,
+) {
+    let Ok((player, transform)) = player.get_single() else {
+        return;
+    };
+    // ships consist of several parts, so a single explosion is heard per frame
+    let nearby = ev_damage.iter().find(|ev| {
+        ev.destroyed
+            && ev.target != player
+            && ev.position.distance(transform.translation()) < EXPLOSION_RADIUS
+    });
+    if let Some(ev) = nearby {
+        ev_cue.send(AudioCueEvent {
+            cue: AudioCue::Explosion,
+            position: Some(ev.position),
+        });
+    }
+}
+
+fn jump_ins(mut ev_jump_in: EventReader<JumpInEvent>, mut ev_cue: EventWriter<AudioCueEvent>) {
+    for ev in ev_jump_in.iter() {
+        ev_cue.send(AudioCueEvent {
+            cue: AudioCue::JumpIn,
+            position: Some(ev.position),
+        });
+    }
+}
+
+/// Detects audible events around the player and emits them as `AudioCueEvent`s.
+pub struct AudioCuesPlugin;
+impl Plugin for AudioCuesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AudioCueEvent>()
+            .add_event::<JumpInEvent>()
+            .add_system(incoming_missiles)
+            .add_system(shield_down.after(DamageSystems))
+            .add_system(overheat)
+            .add_system(explosions.after(DamageSystems))
+            .add_system(jump_ins);
+    }
+}
@@ -0,0 +1,631 @@
+//! Boss encounter: a dreadnought placed by the `Scenario`, armed with turrets and covered by the
+//! bubble shield of its generators. The fight goes in phases as its subsystems fall: shots from
+//! outside can't reach the hull while any generator stands, so they are taken out from inside the
+//! bubble, losing them all makes the dreadnought call in drones, and once its turrets are gone too
+//! it turns its spinal beam on the player. The beam emitter glows
+//! brighter while it charges, so there is time to get out of its way. The hull and the phase are
+//! shown by the boss bar at the top of the HUD.
+
+use bevy::{
+    pbr::{NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+};
+use bevy_rapier3d::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    bubble_shield::{BubbleShieldPlugin, ShieldProjector},
+    components::{Fraction, HitPoints, Lifetime},
+    drone::Drone,
+    jump_gate::{JumpGatePlugin, OpenJumpGateEvent},
+    player::Player,
+    projectile::{DamageEvent, DamageSystems, ExplosionEffect, Hitscan, Owner},
+    scenario::Scenario,
+    skirmish,
+    trigger::ScenarioMessageEvent,
+    turret::{SpawnTurretEvent, TurretKind},
+};
+
+/// Drones called in by the dreadnought jump in this far from it, in meters
+const WAVE_DISTANCE: f32 = 300.0;
+/// In meters
+const GENERATOR_RADIUS: f32 = 3.0;
+const GENERATOR_HIT_POINTS: u32 = 400;
+/// Radius of the bubble shield projected over the hull while any generator stands, in meters
+const BUBBLE_RADIUS: f32 = 90.0;
+/// The bow turns to the player this fast while the beam is online, in radians per second
+const TURN_RATE: f32 = 0.1;
+/// The beam starts charging once the player is within this angle off the bow, in radians
+const BEAM_CONE: f32 = 0.35;
+/// Number of colliders the beam passes through
+const BEAM_PENETRATION: usize = 5;
+/// The beam remains visible for this time, in seconds
+const BEAM_LIFETIME: f32 = 0.6;
+/// Radius of the glow at the emitter by the end of the charge, in meters
+const GLOW_RADIUS: f32 = 6.0;
+
+/// Dreadnought placed by the `Scenario`.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct BossConfig {
+    /// Shown on the boss bar, and referred to by `Destroyed` conditions of triggers
+    pub name: String,
+    pub fraction: Fraction,
+    /// World position in meters
+    pub position: [f32; 3],
+    /// The bow faces this point, the origin by default
+    pub look_at: [f32; 3],
+    pub hit_points: u32,
+    pub turrets: Vec<BossTurretConfig>,
+    /// Offsets of shield generators relative to the hull, in meters
+    pub shield_generators: Vec<[f32; 3]>,
+    /// Drones called in once the shield falls, and again once the turrets are gone
+    pub wave: Vec<Drone>,
+    pub beam: BeamConfig,
+}
+
+impl Default for BossConfig {
+    fn default() -> Self {
+        let turret = |kind, offset| BossTurretConfig { kind, offset };
+        Self {
+            name: String::from("Dreadnought"),
+            fraction: Fraction::Drones,
+            position: [0.0, 50.0, -1200.0],
+            look_at: [0.0; 3],
+            hit_points: 6000,
+            turrets: vec![
+                turret(TurretKind::Flak, [-25.0, -3.0, -25.0]),
+                turret(TurretKind::Railgun, [25.0, -3.0, -25.0]),
+                turret(TurretKind::MissileBattery, [-25.0, -3.0, 25.0]),
+                turret(TurretKind::Flak, [25.0, -3.0, 25.0]),
+            ],
+            shield_generators: vec![[0.0, 10.0, 30.0], [-15.0, 10.0, 0.0], [15.0, 10.0, 0.0]],
+            wave: vec![Drone::Praetor, Drone::Infiltrator, Drone::Infiltrator],
+            beam: BeamConfig::default(),
+        }
+    }
+}
+
+impl BossConfig {
+    pub fn transform(&self) -> Transform {
+        let position = Vec3::from(self.position);
+        let look_at = Vec3::from(self.look_at);
+        if position == look_at {
+            return Transform::from_translation(position);
+        }
+        Transform::from_translation(position).looking_at(look_at, Vec3::Y)
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct BossTurretConfig {
+    pub kind: TurretKind,
+    /// Relative to the hull, in meters
+    pub offset: [f32; 3],
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct BeamConfig {
+    pub damage: u32,
+    /// In meters
+    pub range: f32,
+    /// Seconds the beam charges before firing
+    pub charge_time: f32,
+    /// Seconds between the shot and the next charge
+    pub cooldown: f32,
+    /// Offset of the emitter relative to the hull, in meters
+    pub emitter: [f32; 3],
+}
+
+impl Default for BeamConfig {
+    fn default() -> Self {
+        Self {
+            damage: 400,
+            range: 3000.0,
+            charge_time: 4.0,
+            cooldown: 6.0,
+            emitter: [0.0, 0.0, -60.0],
+        }
+    }
+}
+
+/// Stage of the boss fight, advanced by subsystems falling.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BossPhase {
+    /// Shield generators keep all damage off the hull
+    #[default]
+    Shielded,
+    /// The hull is open to fire, drones are called in
+    Exposed,
+    /// Turrets are gone too, the spinal beam is online
+    Desperate,
+}
+
+impl BossPhase {
+    /// Phase of the fight with the number of shield `generators` and `turrets` left
+    fn new(generators: usize, turrets: usize) -> Self {
+        if generators > 0 {
+            BossPhase::Shielded
+        } else if turrets > 0 {
+            BossPhase::Exposed
+        } else {
+            BossPhase::Desperate
+        }
+    }
+
+    /// Localization key of the phase name
+    pub fn key(self) -> &'static str {
+        match self {
+            BossPhase::Shielded => "boss-shielded",
+            BossPhase::Exposed => "boss-exposed",
+            BossPhase::Desperate => "boss-desperate",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BeamState {
+    /// Seconds before the next charge may start
+    Cooling(f32),
+    /// Seconds before the beam fires
+    Charging(f32),
+}
+
+/// Weapon along the keel of the dreadnought, it fires a long beam forward after a charge.
+#[derive(Component, Clone, Debug)]
+pub struct SpinalBeam {
+    pub damage: u32,
+    pub range: f32,
+    pub charge_time: f32,
+    pub cooldown: f32,
+    /// The beam stays offline until the last phase of the fight
+    pub online: bool,
+    state: BeamState,
+}
+
+impl SpinalBeam {
+    pub fn new(config: &BeamConfig) -> Self {
+        Self {
+            damage: config.damage,
+            range: config.range,
+            charge_time: config.charge_time,
+            cooldown: config.cooldown,
+            online: false,
+            state: BeamState::Cooling(0.0),
+        }
+    }
+
+    /// Charge progress from 0 to 1, `None` unless the beam charges
+    pub fn charge(&self) -> Option<f32> {
+        match self.state {
+            BeamState::Charging(time_left) => {
+                Some(1.0 - time_left / self.charge_time.max(f32::EPSILON))
+            }
+            BeamState::Cooling(_) => None,
+        }
+    }
+
+    /// Advances the beam by `seconds`. A charge starts once the beam is cooled down and the
+    /// target is `in_sight`, and goes on until the shot anyway. Returns whether the beam fires.
+    fn tick(&mut self, seconds: f32, in_sight: bool) -> bool {
+        if !self.online {
+            return false;
+        }
+        match &mut self.state {
+            BeamState::Cooling(time_left) => {
+                *time_left = (*time_left - seconds).max(0.0);
+                if *time_left <= 0.0 && in_sight {
+                    self.state = BeamState::Charging(self.charge_time);
+                }
+                false
+            }
+            BeamState::Charging(time_left) => {
+                *time_left -= seconds;
+                if *time_left > 0.0 {
+                    return false;
+                }
+                self.state = BeamState::Cooling(self.cooldown);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BeamConfig, BossPhase, SpinalBeam};
+
+    #[test]
+    fn test_phase() {
+        assert_eq!(BossPhase::new(2, 4), BossPhase::Shielded);
+        assert_eq!(BossPhase::new(0, 4), BossPhase::Exposed);
+        assert_eq!(BossPhase::new(0, 0), BossPhase::Desperate);
+        // turrets don't count while the shield is up
+        assert_eq!(BossPhase::new(1, 0), BossPhase::Shielded);
+        assert!(BossPhase::Shielded < BossPhase::Exposed);
+    }
+
+    #[test]
+    fn test_beam_cycle() {
+        let mut beam = SpinalBeam::new(&BeamConfig {
+            charge_time: 2.0,
+            cooldown: 3.0,
+            ..Default::default()
+        });
+        assert!(!beam.tick(10.0, true));
+        assert_eq!(beam.charge(), None);
+
+        beam.online = true;
+        // waits for the target
+        assert!(!beam.tick(1.0, false));
+        assert_eq!(beam.charge(), None);
+        assert!(!beam.tick(1.0, true));
+        assert_eq!(beam.charge(), Some(0.0));
+        assert!(!beam.tick(1.0, true));
+        assert!((beam.charge().unwrap() - 0.5).abs() < 1e-3);
+        // fires even if the target has left
+        assert!(beam.tick(1.0, false));
+        assert_eq!(beam.charge(), None);
+        // and cools down before the next charge
+        assert!(!beam.tick(2.0, true));
+        assert_eq!(beam.charge(), None);
+        assert!(!beam.tick(1.0, true));
+        assert!(beam.charge().is_some());
+    }
+}
+
+/// Dreadnought fought in phases, see `BossPhase`.
+#[derive(Component, Clone, Debug)]
+pub struct Boss {
+    pub phase: BossPhase,
+    /// Shield generators left
+    pub generators: usize,
+    /// Turrets left
+    pub turrets: usize,
+    /// Drones called in on every phase change
+    wave: Vec<Drone>,
+}
+
+/// Subsystem projecting the shield over the hull of the dreadnought.
+#[derive(Component)]
+pub struct ShieldGenerator;
+
+/// Grows at the beam emitter while the beam charges
+#[derive(Component)]
+struct BeamGlow;
+
+#[derive(Resource)]
+struct BeamAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+fn setup_beam(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(BeamAssets {
+        // Unit length along Y axis, stretched to the beam length by `Hitscan`
+        mesh: meshes.add(Mesh::from(shape::Box::new(2.0, 1.0, 2.0))),
+        material: materials.add(StandardMaterial {
+            base_color: Color::BLACK,
+            emissive: Color::rgb_linear(12.0, 2.0, 1.0),
+            ..default()
+        }),
+    });
+}
+
+fn spawn_boss(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    scenario: Option<Res<Scenario>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut ev_spawn_turret: EventWriter<SpawnTurretEvent>,
+) {
+    let Some(config) = scenario.as_ref().and_then(|s| s.boss.as_ref()) else {
+        return;
+    };
+    let phase = BossPhase::new(config.shield_generators.len(), config.turrets.len());
+
+    // Subsystems are placed in meters and keep their size, while the hull model is scaled up
+    let scale = skirmish::CAPITAL_SHIP_SCALE;
+    let local = |offset: [f32; 3]| {
+        Transform::from_translation(Vec3::from(offset) / scale)
+            .with_scale(Vec3::splat(scale.recip()))
+    };
+
+    let hull = skirmish::spawn_capital_ship(&mut commands, &assets, config.transform());
+    commands
+        .entity(hull)
+        .insert(RigidBody::KinematicPositionBased)
+        .insert(Velocity::default())
+        .insert(HitPoints::new(config.hit_points))
+        .insert(config.fraction)
+        .insert(Boss {
+            phase,
+            generators: config.shield_generators.len(),
+            turrets: config.turrets.len(),
+            wave: config.wave.clone(),
+        })
+        .insert(Name::new(config.name.clone()));
+
+    let generator_mesh = meshes.add(Mesh::from(shape::UVSphere {
+        radius: GENERATOR_RADIUS,
+        sectors: 32,
+        stacks: 16,
+    }));
+    let generator_material = materials.add(StandardMaterial {
+        base_color: Color::BLACK,
+        emissive: Color::rgb_linear(2.0, 4.0, 8.0),
+        ..default()
+    });
+    let mut beam = SpinalBeam::new(&config.beam);
+    beam.online = phase == BossPhase::Desperate;
+
+    commands.entity(hull).with_children(|parent| {
+        for (index, &offset) in config.shield_generators.iter().enumerate() {
+            parent
+                .spawn(PbrBundle {
+                    mesh: generator_mesh.clone(),
+                    material: generator_material.clone(),
+                    transform: local(offset),
+                    ..default()
+                })
+                .insert(Collider::ball(GENERATOR_RADIUS))
+                .insert(HitPoints::new(GENERATOR_HIT_POINTS))
+                .insert(ShieldGenerator)
+                .insert(Name::new(format!("Shield Generator #{}", index + 1)));
+        }
+
+        parent
+            .spawn(SpatialBundle::from_transform(local(config.beam.emitter)))
+            .insert(beam)
+            .insert(Name::new("Spinal Beam"))
+            .with_children(|emitter| {
+                emitter
+                    .spawn(PbrBundle {
+                        mesh: meshes.add(Mesh::from(shape::UVSphere {
+                            radius: GLOW_RADIUS,
+                            sectors: 32,
+                            stacks: 16,
+                        })),
+                        material: materials.add(StandardMaterial {
+                            base_color: Color::BLACK,
+                            emissive: Color::rgb_linear(12.0, 2.0, 1.0),
+                            ..default()
+                        }),
+                        transform: Transform::from_scale(Vec3::ZERO),
+                        ..default()
+                    })
+                    .insert(BeamGlow)
+                    .insert(NotShadowCaster)
+                    .insert(NotShadowReceiver);
+            });
+    });
+    if phase == BossPhase::Shielded {
+        commands
+            .entity(hull)
+            .insert(ShieldProjector::new(BUBBLE_RADIUS));
+    }
+
+    for turret in config.turrets.iter() {
+        ev_spawn_turret.send(SpawnTurretEvent {
+            kind: turret.kind,
+            prefab: None,
+            transform: local(turret.offset),
+            parent: Some(hull),
+            rotation_speed: 120_f32.to_radians(),
+            fraction: config.fraction,
+        });
+    }
+}
+
+/// Counts shield generators and turrets that dreadnoughts lose. Turrets are lost with their head
+/// or their body, whichever goes first.
+fn lose_subsystems(
+    mut ev_damage: EventReader<DamageEvent>,
+    generators: Query<(), With<ShieldGenerator>>,
+    turrets: Query<(), With<TurretKind>>,
+    parents: Query<&Parent>,
+    children: Query<&Children>,
+    mut bosses: Query<&mut Boss>,
+) {
+    for ev in ev_damage.iter().filter(|ev| ev.destroyed) {
+        let generator = generators.contains(ev.target);
+        let turret = std::iter::once(ev.target)
+            .chain(children.iter_descendants(ev.target))
+            .any(|e| turrets.contains(e));
+        if !generator && !turret {
+            continue;
+        }
+        let Some(boss) = parents
+            .iter_ancestors(ev.target)
+            .find(|&e| bosses.contains(e))
+        else {
+            continue;
+        };
+        let mut boss = bosses.get_mut(boss).unwrap();
+        if generator {
+            boss.generators = boss.generators.saturating_sub(1);
+        } else {
+            boss.turrets = boss.turrets.saturating_sub(1);
+        }
+    }
+}
+
+/// Drops the shield once the last generator falls, brings the beam online once the turrets are
+/// gone, and calls in drones on every phase change
+fn change_phase(
+    mut commands: Commands,
+    mut bosses: Query<(Entity, &mut Boss, &GlobalTransform, &Fraction), Changed<Boss>>,
+    children: Query<&Children>,
+    mut beams: Query<&mut SpinalBeam>,
+    mut ev_open_gate: EventWriter<OpenJumpGateEvent>,
+    mut ev_message: EventWriter<ScenarioMessageEvent>,
+) {
+    let mut rng = rand::thread_rng();
+    for (entity, mut boss, transform, &fraction) in bosses.iter_mut() {
+        let phase = BossPhase::new(boss.generators, boss.turrets);
+        if phase <= boss.phase {
+            continue;
+        }
+
+        if boss.phase == BossPhase::Shielded {
+            // the bubble comes down with the projector
+            commands.entity(entity).remove::<ShieldProjector>();
+            ev_message.send(ScenarioMessageEvent(String::from("boss-shield-down")));
+        }
+        if phase == BossPhase::Desperate {
+            for emitter in children.iter_descendants(entity) {
+                if let Ok(mut beam) = beams.get_mut(emitter) {
+                    beam.online = true;
+                }
+            }
+            ev_message.send(ScenarioMessageEvent(String::from("boss-beam-online")));
+        }
+        boss.phase = phase;
+
+        let (_, rotation, position) = transform.to_scale_rotation_translation();
+        for &drone in boss.wave.iter() {
+            let offset = Vec3::new(
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+            );
+            let offset = offset.try_normalize().unwrap_or(Vec3::Y) * WAVE_DISTANCE;
+            ev_open_gate.send(OpenJumpGateEvent {
+                drone,
+                fraction,
+                transform: Transform::from_translation(position + offset).with_rotation(rotation),
+            });
+        }
+    }
+}
+
+/// Dreadnoughts with the beam online turn their bow to the player. They hold the heading while
+/// the beam charges, so the player can dodge it.
+fn turn_to_player(
+    time: Res<Time>,
+    player: Query<&GlobalTransform, With<Player>>,
+    mut bosses: Query<(&mut Transform, &Boss, &Children)>,
+    beams: Query<&SpinalBeam>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    for (mut transform, boss, children) in bosses.iter_mut() {
+        if boss.phase != BossPhase::Desperate {
+            continue;
+        }
+        let charging = children
+            .iter()
+            .filter_map(|&child| beams.get(child).ok())
+            .any(|beam| beam.charge().is_some());
+        if charging {
+            continue;
+        }
+        let Some(direction) = (player.translation() - transform.translation).try_normalize() else {
+            continue;
+        };
+        let forward = transform.forward();
+        let angle = forward.angle_between(direction);
+        let max_step = TURN_RATE * time.delta_seconds();
+        if angle <= f32::EPSILON {
+            continue;
+        }
+        let arc = Quat::from_rotation_arc(forward, direction);
+        let arc = Quat::IDENTITY.slerp(arc, (max_step / angle).min(1.0));
+        transform.rotation = arc * transform.rotation;
+    }
+}
+
+/// Charges online beams once the player is in front of them, and fires them
+fn fire_beams(
+    mut commands: Commands,
+    time: Res<Time>,
+    beam_assets: Res<BeamAssets>,
+    player: Query<&GlobalTransform, With<Player>>,
+    mut beams: Query<(&GlobalTransform, &Parent, &mut SpinalBeam)>,
+) {
+    let player = player.get_single().ok().map(|t| t.translation());
+    for (transform, parent, mut beam) in beams.iter_mut() {
+        let origin = transform.translation();
+        let direction = transform.forward();
+        let in_sight = player.map_or(false, |player| {
+            let to_player = player - origin;
+            to_player.length() < beam.range && direction.angle_between(to_player) < BEAM_CONE
+        });
+        if !beam.tick(time.delta_seconds(), in_sight) {
+            continue;
+        }
+
+        commands
+            .spawn(PbrBundle {
+                mesh: beam_assets.mesh.clone(),
+                material: beam_assets.material.clone(),
+                transform: Transform {
+                    translation: origin,
+                    rotation: Quat::from_rotation_arc(Vec3::Y, direction),
+                    scale: Vec3::ONE,
+                },
+                ..default()
+            })
+            .insert(Hitscan {
+                range: beam.range,
+                damage: beam.damage,
+                penetration: BEAM_PENETRATION,
+            })
+            // the beam passes through the hull and subsystems of the dreadnought itself
+            .insert(Owner(parent.get()))
+            .insert(ExplosionEffect::Big)
+            .insert(Lifetime(BEAM_LIFETIME))
+            .insert(NotShadowCaster)
+            .insert(NotShadowReceiver)
+            .insert(Name::new("Spinal Beam Shot"));
+    }
+}
+
+/// Emitters glow brighter while their beams charge
+fn glow_emitters(
+    beams: Query<(&SpinalBeam, &Children)>,
+    mut glows: Query<&mut Transform, With<BeamGlow>>,
+) {
+    for (beam, children) in beams.iter() {
+        let scale = Vec3::splat(beam.charge().unwrap_or(0.0));
+        for &child in children.iter() {
+            if let Ok(mut transform) = glows.get_mut(child) {
+                if transform.scale != scale {
+                    transform.scale = scale;
+                }
+            }
+        }
+    }
+}
+
+/// Dreadnought from the `Scenario`, fought in phases. `HudPlugin` shows its boss bar.
+pub struct BossPlugin;
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<JumpGatePlugin>() {
+            app.add_plugin(JumpGatePlugin);
+        }
+        // Projects the shield of the generators
+        if !app.is_plugin_added::<BubbleShieldPlugin>() {
+            app.add_plugin(BubbleShieldPlugin);
+        }
+        app.add_event::<DamageEvent>()
+            .add_event::<SpawnTurretEvent>()
+            .add_event::<ScenarioMessageEvent>()
+            .add_startup_system(setup_beam)
+            .add_startup_system(spawn_boss)
+            .add_system(lose_subsystems.after(DamageSystems))
+            .add_system(change_phase.after(lose_subsystems))
+            .add_system(turn_to_player)
+            .add_system(fire_beams.after(turn_to_player))
+            .add_system(glow_emitters.after(fire_beams));
+    }
+}
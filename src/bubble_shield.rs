@@ -0,0 +1,253 @@
+//! Bubble shields projected by shield generators over their allies. Shots of other fractions are
+//! absorbed on the bubble until its generator is destroyed, so the generator is the target to take
+//! out first, or to fly inside the bubble for. Shots fired from inside pass, both ways.
+//!
+//! The bubble is a sphere checked against shots directly rather than a physics collider, so ships
+//! fly through it and it doesn't get in the way of sensors and target locks. It's drawn with the
+//! `ShieldMaterial` of energy shields, rippling where it absorbs shots.
+
+use bevy::{
+    ecs::system::SystemParam,
+    pbr::{NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    components::{Damage, Fraction, HitPoints},
+    projectile::{DamageSystems, Hitscan, Owner},
+    shield_effect::{ShieldEffectPlugin, ShieldImpacts, ShieldMaterial},
+};
+
+/// Generator projecting a bubble shield around itself, a ship or a subsystem.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct ShieldProjector {
+    /// In meters
+    pub radius: f32,
+}
+
+impl ShieldProjector {
+    pub fn new(radius: f32) -> Self {
+        Self { radius }
+    }
+}
+
+/// Point where the segment from `start` to `end` enters the sphere, `None` if the segment starts
+/// inside the sphere or misses it
+fn entry_point(center: Vec3, radius: f32, start: Vec3, end: Vec3) -> Option<Vec3> {
+    let offset = start - center;
+    let c = offset.length_squared() - radius * radius;
+    let segment = end - start;
+    let a = segment.length_squared();
+    if c <= 0.0 || a <= f32::EPSILON {
+        return None;
+    }
+    // The closer root of |offset + segment * t|² = radius²
+    let b = offset.dot(segment);
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let t = (-b - discriminant.sqrt()) / a;
+    (0.0..=1.0).contains(&t).then(|| start + segment * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::entry_point;
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_entry_point() {
+        let center = Vec3::new(0.0, 0.0, -100.0);
+        let point = entry_point(center, 10.0, Vec3::ZERO, Vec3::new(0.0, 0.0, -200.0)).unwrap();
+        assert!(point.distance(Vec3::new(0.0, 0.0, -90.0)) < 1e-3);
+        // stops short of the bubble
+        assert_eq!(entry_point(center, 10.0, Vec3::ZERO, Vec3::Z * -50.0), None);
+        // passes by
+        let start = Vec3::new(20.0, 0.0, 0.0);
+        assert_eq!(
+            entry_point(center, 10.0, start, start + Vec3::Z * -200.0),
+            None
+        );
+        // leaves the bubble from inside
+        assert_eq!(entry_point(center, 10.0, center, Vec3::ZERO), None);
+    }
+}
+
+/// Bubble projected by the `ShieldProjector` it's attached to.
+#[derive(Component)]
+struct Bubble;
+
+#[derive(Resource)]
+struct BubbleAssets {
+    mesh: Handle<Mesh>,
+}
+
+fn setup_bubbles(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    commands.insert_resource(BubbleAssets {
+        // Unit sphere, scaled to the radius of each bubble
+        mesh: meshes.add(Mesh::from(shape::UVSphere {
+            radius: 1.0,
+            sectors: 64,
+            stacks: 32,
+        })),
+    });
+}
+
+/// Raises a bubble over every new projector, and brings it down once the projector is gone,
+/// e.g. with a destroyed drone returned to the pool
+fn raise_bubbles(
+    mut commands: Commands,
+    bubble_assets: Res<BubbleAssets>,
+    mut materials: ResMut<Assets<ShieldMaterial>>,
+    projectors: Query<(Entity, &ShieldProjector, &GlobalTransform), Added<ShieldProjector>>,
+    bubbles: Query<(Entity, &Parent), With<Bubble>>,
+    has_projector: Query<(), With<ShieldProjector>>,
+) {
+    for (bubble, parent) in bubbles.iter() {
+        if !has_projector.contains(parent.get()) {
+            commands.entity(bubble).despawn_recursive();
+        }
+    }
+
+    for (entity, projector, transform) in projectors.iter() {
+        // Projectors are inserted anew when a pooled drone is reused, while its bubble is kept
+        if bubbles.iter().any(|(_, parent)| parent.get() == entity) {
+            continue;
+        }
+        // The bubble keeps its radius in meters on scaled ships
+        let (scale, _, _) = transform.to_scale_rotation_translation();
+        let bubble = commands
+            .spawn(MaterialMeshBundle {
+                mesh: bubble_assets.mesh.clone(),
+                // Own material per bubble for its own ripples
+                material: materials.add(ShieldMaterial::new(Color::rgba(0.3, 0.7, 1.0, 0.6))),
+                transform: Transform::from_scale(Vec3::splat(projector.radius) / scale),
+                ..default()
+            })
+            .insert(Bubble)
+            .insert(ShieldImpacts::new())
+            .insert(NotShadowCaster)
+            .insert(NotShadowReceiver)
+            .insert(Name::new("Bubble Shield"))
+            .id();
+        commands.entity(entity).add_child(bubble);
+    }
+}
+
+/// Bubbles that absorb shots, with their center, radius, fraction and the bubble entity
+type ActiveBubbles = Vec<(Vec3, f32, Option<Fraction>, Entity)>;
+
+#[derive(SystemParam)]
+struct Bubbles<'w, 's> {
+    bubbles: Query<'w, 's, (Entity, &'static Parent), With<Bubble>>,
+    projectors: Query<
+        'w,
+        's,
+        (
+            &'static ShieldProjector,
+            &'static GlobalTransform,
+            &'static HitPoints,
+        ),
+    >,
+    parents: Query<'w, 's, &'static Parent>,
+    fractions: Query<'w, 's, &'static Fraction>,
+}
+
+impl Bubbles<'_, '_> {
+    /// Bubbles are up while their projectors are alive, those of parked drones aren't
+    fn active(&self) -> ActiveBubbles {
+        self.bubbles
+            .iter()
+            .filter_map(|(bubble, parent)| {
+                let (projector, transform, hp) = self.projectors.get(parent.get()).ok()?;
+                let fraction = self.fraction(parent.get());
+                (!hp.dead()).then(|| {
+                    let center = transform.translation();
+                    (center, projector.radius, fraction, bubble)
+                })
+            })
+            .collect()
+    }
+
+    /// Fraction of the entity itself or of its closest ancestor
+    fn fraction(&self, entity: Entity) -> Option<Fraction> {
+        std::iter::once(entity)
+            .chain(self.parents.iter_ancestors(entity))
+            .find_map(|e| self.fractions.get(e).ok())
+            .copied()
+    }
+}
+
+/// The closest bubble of other fractions that the segment enters, with the entry point
+fn blocking_bubble(
+    bubbles: &ActiveBubbles,
+    shooter: Option<Fraction>,
+    start: Vec3,
+    end: Vec3,
+) -> Option<(Vec3, Entity)> {
+    bubbles
+        .iter()
+        .filter(|(_, _, fraction, _)| fraction.is_none() || *fraction != shooter)
+        .filter_map(|&(center, radius, _, bubble)| {
+            entry_point(center, radius, start, end).map(|point| (point, bubble))
+        })
+        .min_by(|a, b| a.0.distance(start).total_cmp(&b.0.distance(start)))
+}
+
+/// Projectiles of other fractions entering a bubble are absorbed on it, railgun beams stop there.
+/// Either starts a ripple at the entry point.
+fn absorb_shots(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut impacts: Query<(&GlobalTransform, &mut ShieldImpacts), With<Bubble>>,
+    bubbles: Bubbles,
+    shots: Query<(Entity, &Transform, &Velocity, Option<&Owner>), With<Damage>>,
+    mut beams: Query<(&Transform, &mut Hitscan, Option<&Owner>)>,
+) {
+    let shooter = |owner: Option<&Owner>| owner.and_then(|o| bubbles.fraction(o.0));
+    let bubbles = bubbles.active();
+    if bubbles.is_empty() {
+        return;
+    }
+
+    // Shots are checked along the way they have flown since the last frame
+    for (shot, transform, velocity, owner) in shots.iter() {
+        let end = transform.translation;
+        let start = end - velocity.linvel * time.delta_seconds();
+        if let Some((point, bubble)) = blocking_bubble(&bubbles, shooter(owner), start, end) {
+            commands.entity(shot).despawn_recursive();
+            if let Ok((transform, mut impacts)) = impacts.get_mut(bubble) {
+                impacts.hit(transform, point);
+            }
+        }
+    }
+    for (transform, mut hitscan, owner) in beams.iter_mut() {
+        let start = transform.translation;
+        let end = start + transform.up() * hitscan.range;
+        if let Some((point, bubble)) = blocking_bubble(&bubbles, shooter(owner), start, end) {
+            hitscan.range = point.distance(start);
+            if let Ok((transform, mut impacts)) = impacts.get_mut(bubble) {
+                impacts.hit(transform, point);
+            }
+        }
+    }
+}
+
+/// Bubble shields of `ShieldProjector`s absorbing shots of other fractions, drawn with
+/// `ShieldMaterial`.
+pub struct BubbleShieldPlugin;
+impl Plugin for BubbleShieldPlugin {
+    fn build(&self, app: &mut App) {
+        // Draws the bubbles and their ripples
+        if !app.is_plugin_added::<ShieldEffectPlugin>() {
+            app.add_plugin(ShieldEffectPlugin);
+        }
+        app.register_type::<ShieldProjector>()
+            .add_startup_system(setup_bubbles)
+            .add_system(raise_bubbles)
+            .add_system(absorb_shots.before(DamageSystems));
+    }
+}
@@ -0,0 +1,96 @@
+//! Callsigns of drones like "Praetor-07 'Vulture'", so they can be told apart in the target panel,
+//! kill feed and inspector. They are rolled from a seed, which can be fixed for reproducible
+//! sessions with `--callsign-seed=N`.
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::drone::Drone;
+
+const NICKNAMES: [&str; 24] = [
+    "Vulture", "Raven", "Viper", "Jackal", "Hornet", "Specter", "Mantis", "Cobra", "Wraith",
+    "Talon", "Reaper", "Banshee", "Hyena", "Scorpion", "Shrike", "Warden", "Ghost", "Havoc",
+    "Lynx", "Nomad", "Onyx", "Razor", "Sable", "Tempest",
+];
+
+/// Generator of drone callsigns. Serial numbers are counted per drone type, while nicknames are
+/// picked at random.
+#[derive(Resource)]
+pub struct Callsigns {
+    rng: StdRng,
+    /// Last serial number of each drone type
+    serials: Vec<(Drone, u32)>,
+}
+
+impl Default for Callsigns {
+    fn default() -> Self {
+        Self::new(StdRng::from_entropy())
+    }
+}
+
+impl Callsigns {
+    fn new(rng: StdRng) -> Self {
+        Self {
+            rng,
+            serials: Vec::new(),
+        }
+    }
+
+    /// Same seed gives the same callsigns in the same order
+    pub fn from_seed(seed: u64) -> Self {
+        Self::new(StdRng::seed_from_u64(seed))
+    }
+
+    /// Seeded with `--callsign-seed=N` if it's among the `args`, random otherwise
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        args.into_iter()
+            .find_map(|arg| arg.strip_prefix("--callsign-seed=")?.parse().ok())
+            .map_or_else(Self::default, Self::from_seed)
+    }
+
+    /// Callsign for the next drone of the `drone` type
+    pub fn next(&mut self, drone: Drone) -> String {
+        let serial = match self.serials.iter_mut().find(|(d, _)| *d == drone) {
+            Some((_, serial)) => {
+                *serial += 1;
+                *serial
+            }
+            None => {
+                self.serials.push((drone, 1));
+                1
+            }
+        };
+        let nickname = NICKNAMES.choose(&mut self.rng).copied().unwrap_or_default();
+        format!("{drone:?}-{serial:02} '{nickname}'")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Callsigns;
+    use crate::drone::Drone;
+
+    #[test]
+    fn test_serials() {
+        let mut callsigns = Callsigns::default();
+        assert!(callsigns.next(Drone::Praetor).starts_with("Praetor-01 '"));
+        assert!(callsigns
+            .next(Drone::Infiltrator)
+            .starts_with("Infiltrator-01 '"));
+        assert!(callsigns.next(Drone::Praetor).starts_with("Praetor-02 '"));
+    }
+
+    #[test]
+    fn test_seed() {
+        let roll = |mut callsigns: Callsigns| -> Vec<_> {
+            (0..10).map(|_| callsigns.next(Drone::Praetor)).collect()
+        };
+        assert_eq!(roll(Callsigns::from_seed(7)), roll(Callsigns::from_seed(7)));
+
+        let args = ["--skirmish", "--callsign-seed=7"].map(String::from);
+        assert_eq!(
+            roll(Callsigns::from_args(args)),
+            roll(Callsigns::from_seed(7))
+        );
+    }
+}
@@ -4,7 +4,9 @@ use bevy::{
     prelude::*,
     render::mesh::{Indices, VertexAttributeValues},
 };
-use bevy_rapier3d::prelude::{Collider, VHACDParameters};
+use bevy_rapier3d::prelude::{Collider, ColliderMassProperties, VHACDParameters};
+
+use crate::scene_setup::GltfExtrasApp;
 
 /// Annotates an entity where a new collider should be added.
 /// A new collider is computed as a compound of convex hulls that covers each mesh in `collider_parts` or it's
@@ -19,6 +21,42 @@ impl ConvexHull {
     }
 }
 
+/// Shape used to approximate a single part of the [`CompoundCollider`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PartShape {
+    ConvexHull,
+    /// Box, aligned with axes of the collider's entity
+    Cuboid,
+    /// Box, aligned with principal axes of the part's vertices
+    OrientedCuboid,
+    Ball,
+    /// Capsule along the longest axis of the part's bounding box
+    Capsule,
+}
+
+/// Annotates an entity where a new collider should be added.
+/// A new collider is computed as a compound of shapes, one per each entity in `collider_parts`, where each part is
+/// collected the same way as for `ConvexHull`. A few primitives fit L-shaped models much better than a single hull.
+/// Note that all parts move together with the annotated entity, so articulated parts (like turret heads) should
+/// have their own collider.
+#[derive(Component)]
+pub struct CompoundCollider(Vec<(Entity, PartShape)>);
+
+impl CompoundCollider {
+    pub fn new(collider_parts: Vec<(Entity, PartShape)>) -> Self {
+        Self(collider_parts)
+    }
+}
+
+/// Annotates an entity where a new collider should be added.
+/// A new collider is a single primitive, fitted to the mesh of `mesh_source` or it's direct children.
+/// Primitives are way cheaper for physics than convex hulls, which matters with many entities.
+#[derive(Component)]
+pub struct FitPrimitive {
+    pub mesh_source: Entity,
+    pub shape: PartShape,
+}
+
 /// Annotates an entity where a new collider should be added.
 /// A new collider is computed as a convex decomposition from mesh, taken from referenced entity.
 /// This component use entity instead of Handle<Mesh> to resolve transform, applied to the mesh.
@@ -28,6 +66,57 @@ pub struct ConvexDecomposition {
     pub parameters: VHACDParameters,
 }
 
+/// Overrides mass of the collider, built by one of the collider setup components.
+/// Otherwise Rapier derives mass from the collider volume with density 1.0, so tiny drones and huge platforms
+/// respond to forces in a similar way.
+#[derive(Component, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub enum MassOverride {
+    /// Density in kg/m³
+    Density(f32),
+    /// Total mass in kg, regardless of the collider volume
+    Mass(f32),
+}
+
+impl Default for MassOverride {
+    fn default() -> Self {
+        MassOverride::Density(1.0)
+    }
+}
+
+impl MassOverride {
+    /// `scale` is required as collider is scaled by Rapier only after it's inserted to the entity
+    fn to_mass_properties(self, collider: &Collider, scale: Vec3) -> ColliderMassProperties {
+        match self {
+            MassOverride::Density(density) => ColliderMassProperties::Density(density),
+            MassOverride::Mass(mass) => {
+                let volume = collider.raw.mass_properties(1.0).mass() * scale.x * scale.y * scale.z;
+                ColliderMassProperties::Density(mass / volume.max(f32::EPSILON))
+            }
+        }
+    }
+}
+
+/// Marks entities which collider was successfully built by one of the collider setup components.
+#[derive(Component)]
+pub struct PhysicsReady;
+
+/// Emitted once collider is built and added to the entity.
+pub struct ColliderReady(pub Entity);
+
+/// Emitted if collider can't be built for the entity. The setup component is removed anyway.
+pub struct ColliderFailed(pub Entity, pub ColliderError);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColliderError {
+    /// Entity references a mesh that is not loaded
+    MissingMesh(Entity),
+    /// Mesh has no vertices or indices in supported format
+    UnsupportedMesh(Entity),
+    /// No shape was produced out of the collider parts, e.g. all of them are degenerate
+    NoShapes,
+}
+
 fn extract_mesh_vertices(mesh: &Mesh) -> Option<Vec<Vec3>> {
     match mesh.attribute(Mesh::ATTRIBUTE_POSITION)? {
         VertexAttributeValues::Float32(vtx) => {
@@ -40,6 +129,213 @@ fn extract_mesh_vertices(mesh: &Mesh) -> Option<Vec<Vec3>> {
     }
 }
 
+/// Axis-aligned bounding box as (center, half extents)
+fn fit_cuboid(vertices: &[Vec3]) -> Option<(Vec3, Vec3)> {
+    let first = *vertices.first()?;
+    let (min, max) = vertices
+        .iter()
+        .fold((first, first), |(min, max), v| (min.min(*v), max.max(*v)));
+    Some(((min + max) * 0.5, (max - min) * 0.5))
+}
+
+/// Eigenvectors (as columns) of the symmetric matrix, found with the Jacobi eigenvalue algorithm
+fn symmetric_eigenvectors(m: Mat3) -> Mat3 {
+    let mut a = m.transpose().to_cols_array_2d(); // row-major, a[row][col]
+    let mut v = Mat3::IDENTITY.to_cols_array_2d();
+    for _ in 0..32 {
+        // Eliminate the largest off-diagonal element on each step
+        let (p, q) = [(0, 1), (0, 2), (1, 2)]
+            .into_iter()
+            .max_by(|&(i, j), &(k, l)| a[i][j].abs().total_cmp(&a[k][l].abs()))
+            .unwrap();
+        if a[p][q].abs() < 1e-9 {
+            break;
+        }
+
+        let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+        let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+        let c = (t * t + 1.0).sqrt().recip();
+        let s = t * c;
+        // A' = J^T * A * J, V' = V * J
+        for row in a.iter_mut() {
+            let (ap, aq) = (row[p], row[q]);
+            row[p] = c * ap - s * aq;
+            row[q] = s * ap + c * aq;
+        }
+        let (row_p, row_q) = (a[p], a[q]);
+        a[p] = std::array::from_fn(|k| c * row_p[k] - s * row_q[k]);
+        a[q] = std::array::from_fn(|k| s * row_p[k] + c * row_q[k]);
+        for row in v.iter_mut() {
+            let (vp, vq) = (row[p], row[q]);
+            row[p] = c * vp - s * vq;
+            row[q] = s * vp + c * vq;
+        }
+    }
+    Mat3::from_cols_array_2d(&v).transpose()
+}
+
+/// Oriented bounding box along principal axes of vertices as (center, rotation, half extents)
+fn fit_oriented_cuboid(vertices: &[Vec3]) -> Option<(Vec3, Quat, Vec3)> {
+    if vertices.is_empty() {
+        return None;
+    }
+    let mean = vertices.iter().copied().sum::<Vec3>() / vertices.len() as f32;
+    let covariance = vertices.iter().fold(Mat3::ZERO, |acc, v| {
+        let d = *v - mean;
+        acc + Mat3::from_cols(d * d.x, d * d.y, d * d.z)
+    });
+
+    let axes = symmetric_eigenvectors(covariance);
+    // Keep the basis right-handed to make it a valid rotation
+    let axes = Mat3::from_cols(axes.x_axis, axes.y_axis, axes.x_axis.cross(axes.y_axis));
+    let rotation = Quat::from_mat3(&axes).normalize();
+
+    let local: Vec<_> = vertices.iter().map(|v| axes.transpose() * *v).collect();
+    let (local_center, half_extents) = fit_cuboid(&local)?;
+    Some((axes * local_center, rotation, half_extents))
+}
+
+/// Bounding sphere around the bounding box center as (center, radius)
+fn fit_ball(vertices: &[Vec3]) -> Option<(Vec3, f32)> {
+    let (center, _) = fit_cuboid(vertices)?;
+    let radius = vertices
+        .iter()
+        .map(|v| v.distance_squared(center))
+        .fold(0.0, f32::max)
+        .sqrt();
+    Some((center, radius))
+}
+
+/// Capsule along the longest axis of the bounding box as (segment start, segment end, radius)
+fn fit_capsule(vertices: &[Vec3]) -> Option<(Vec3, Vec3, f32)> {
+    let (center, half_extents) = fit_cuboid(vertices)?;
+    let axis = if half_extents.x >= half_extents.y && half_extents.x >= half_extents.z {
+        Vec3::X
+    } else if half_extents.y >= half_extents.z {
+        Vec3::Y
+    } else {
+        Vec3::Z
+    };
+
+    let (radius, half_length) =
+        vertices
+            .iter()
+            .fold((0.0_f32, 0.0_f32), |(radius, half_length), v| {
+                let offset = *v - center;
+                let along = offset.dot(axis);
+                let across = (offset - axis * along).length();
+                (radius.max(across), half_length.max(along.abs()))
+            });
+    let half_segment = (half_length - radius).max(0.0);
+    Some((
+        center - axis * half_segment,
+        center + axis * half_segment,
+        radius,
+    ))
+}
+
+fn fit_part(shape: PartShape, vertices: &[Vec3]) -> Option<(Vec3, Quat, Collider)> {
+    match shape {
+        PartShape::ConvexHull => {
+            Collider::convex_hull(vertices).map(|collider| (Vec3::ZERO, Quat::IDENTITY, collider))
+        }
+        PartShape::Cuboid => {
+            let (center, half) = fit_cuboid(vertices)?;
+            let collider = Collider::cuboid(half.x, half.y, half.z);
+            Some((center, Quat::IDENTITY, collider))
+        }
+        PartShape::OrientedCuboid => {
+            let (center, rotation, half) = fit_oriented_cuboid(vertices)?;
+            let collider = Collider::cuboid(half.x, half.y, half.z);
+            Some((center, rotation, collider))
+        }
+        PartShape::Ball => {
+            let (center, radius) = fit_ball(vertices)?;
+            Some((center, Quat::IDENTITY, Collider::ball(radius)))
+        }
+        PartShape::Capsule => {
+            let (start, end, radius) = fit_capsule(vertices)?;
+            let collider = Collider::capsule(start, end, radius);
+            Some((Vec3::ZERO, Quat::IDENTITY, collider))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_cuboid() {
+        assert_eq!(fit_cuboid(&[]), None);
+        assert_eq!(
+            fit_cuboid(&[Vec3::ONE, Vec3::ZERO]),
+            Some((Vec3::splat(0.5), Vec3::splat(0.5)))
+        );
+        assert_eq!(
+            fit_cuboid(&[Vec3::new(-1.0, 2.0, 0.0), Vec3::new(3.0, 4.0, 0.0)]),
+            Some((Vec3::new(1.0, 3.0, 0.0), Vec3::new(2.0, 1.0, 0.0)))
+        );
+    }
+
+    #[test]
+    fn test_fit_oriented_cuboid() {
+        assert_eq!(fit_oriented_cuboid(&[]), None);
+
+        // 4x2x1 box, rotated and moved
+        let transform = Transform::from_xyz(1.0, 2.0, 3.0).with_rotation(Quat::from_euler(
+            EulerRot::XYZ,
+            0.3,
+            0.7,
+            0.5,
+        ));
+        let mut vertices = vec![];
+        for x in [-2.0, 2.0] {
+            for y in [-1.0, 1.0] {
+                for z in [-0.5, 0.5] {
+                    vertices.push(transform.transform_point(Vec3::new(x, y, z)));
+                }
+            }
+        }
+
+        let (center, rotation, half_extents) = fit_oriented_cuboid(&vertices).unwrap();
+        assert!(center.abs_diff_eq(transform.translation, 1e-4));
+        let mut extents = half_extents.to_array();
+        extents.sort_by(f32::total_cmp);
+        assert!(Vec3::from(extents).abs_diff_eq(Vec3::new(0.5, 1.0, 2.0), 1e-4));
+        // all vertices are on the box surface
+        for v in vertices {
+            let local = rotation.inverse() * (v - center);
+            assert!(local.abs().abs_diff_eq(half_extents, 1e-4));
+        }
+    }
+
+    #[test]
+    fn test_fit_ball() {
+        assert_eq!(fit_ball(&[]), None);
+        let (center, radius) = fit_ball(&[Vec3::X, -Vec3::X, Vec3::Y]).unwrap();
+        assert_eq!(center, Vec3::new(0.0, 0.5, 0.0));
+        assert!((radius - 1.25_f32.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_fit_capsule() {
+        assert_eq!(fit_capsule(&[]), None);
+        // 4x1x1 box along X axis
+        let vertices = [
+            Vec3::new(-2.0, -0.5, -0.5),
+            Vec3::new(2.0, 0.5, 0.5),
+            Vec3::new(2.0, -0.5, 0.5),
+            Vec3::new(-2.0, 0.5, -0.5),
+        ];
+        let (start, end, radius) = fit_capsule(&vertices).unwrap();
+        let expected_radius = 0.5_f32.sqrt();
+        assert!((radius - expected_radius).abs() < 1e-6);
+        assert!(start.abs_diff_eq(Vec3::new(-2.0 + expected_radius, 0.0, 0.0), 1e-6));
+        assert!(end.abs_diff_eq(Vec3::new(2.0 - expected_radius, 0.0, 0.0), 1e-6));
+    }
+}
+
 fn extract_mesh_indices(mesh: &Mesh) -> Option<Vec<[u32; 3]>> {
     match mesh.indices() {
         Some(Indices::U16(idx)) => Some(
@@ -52,90 +348,252 @@ fn extract_mesh_indices(mesh: &Mesh) -> Option<Vec<[u32; 3]>> {
     }
 }
 
-fn convex_hull(
-    mut commands: Commands,
-    meshes: ResMut<Assets<Mesh>>,
-    to_setup: Query<(Entity, &ConvexHull, &GlobalTransform)>,
-    with_children: Query<&Children>,
-    with_meshes: Query<(&Handle<Mesh>, &GlobalTransform)>,
-) {
-    let extract_vertices = |mesh, affine: Affine3A| {
+/// Collects vertices of the `part` mesh or it's direct children meshes in the world's transform.
+/// Parts with neither mesh nor children give no vertices.
+fn collect_part_vertices(
+    part: Entity,
+    meshes: &Assets<Mesh>,
+    with_children: &Query<&Children>,
+    with_meshes: &Query<(&Handle<Mesh>, &GlobalTransform)>,
+) -> Result<Vec<Vec3>, ColliderError> {
+    let extract_vertices = |entity, mesh, affine: Affine3A| -> Result<_, ColliderError> {
+        let mesh = meshes.get(mesh).ok_or(ColliderError::MissingMesh(entity))?;
         // todo: consider Vec3 -> Vec3A and sort() + dedup() to speed up verices processing
-        extract_mesh_vertices(meshes.get(mesh).unwrap())
-            .unwrap_or_default()
+        let vertices = extract_mesh_vertices(mesh).ok_or(ColliderError::UnsupportedMesh(entity))?;
+        Ok(vertices
             .into_iter()
-            .map(move |v| affine.transform_point3(v))
+            .map(move |v| affine.transform_point3(v)))
     };
 
-    for (entity, collider_parts, transform) in to_setup.iter() {
-        // Collect all vertices in the world's transform
-        let mut parts = vec![];
-        for part in collider_parts.0.iter() {
-            // Try to get mesh from `part` entity
-            if let Ok((mesh, transform)) = with_meshes.get(*part) {
-                let part_vertices: Vec<_> = extract_vertices(mesh, transform.affine()).collect();
-                parts.push(part_vertices);
-            } else {
-                // Traverse `part` children and get meshes if any to combine them into a single part
-                if let Ok(children) = with_children.get(*part) {
-                    let mut part_vertices = vec![];
-                    for child in children.iter() {
-                        if let Ok((mesh, transform)) = with_meshes.get(*child) {
-                            part_vertices.extend(extract_vertices(mesh, transform.affine()));
-                        }
-                    }
-                    parts.push(part_vertices);
-                }
+    // Try to get mesh from `part` entity
+    if let Ok((mesh, transform)) = with_meshes.get(part) {
+        return Ok(extract_vertices(part, mesh, transform.affine())?.collect());
+    }
+
+    // Traverse `part` children and get meshes if any to combine them into a single part
+    let mut part_vertices = vec![];
+    if let Ok(children) = with_children.get(part) {
+        for child in children.iter() {
+            if let Ok((mesh, transform)) = with_meshes.get(*child) {
+                part_vertices.extend(extract_vertices(*child, mesh, transform.affine())?);
             }
         }
+    }
+    Ok(part_vertices)
+}
 
-        // With inverse transform, collider will match to the entity's shape
-        let affine = transform.affine().inverse();
-        let colliders: Vec<_> = parts
-            .into_iter()
-            .filter_map(|mut vertices| {
-                vertices
-                    .iter_mut()
-                    .for_each(|v| *v = affine.transform_point3(*v));
-                Collider::convex_hull(&vertices)
-            })
-            .map(|collider| (Vec3::ZERO, Quat::IDENTITY, collider))
-            .collect();
-        let collider = Collider::compound(colliders);
-        commands
-            .entity(entity)
-            .insert(collider)
-            .insert(RecalculateTransform);
+/// Builds a compound collider in the local space of the entity with `transform`
+fn fit_compound(
+    parts: impl Iterator<Item = (Entity, PartShape)>,
+    transform: &GlobalTransform,
+    meshes: &Assets<Mesh>,
+    with_children: &Query<&Children>,
+    with_meshes: &Query<(&Handle<Mesh>, &GlobalTransform)>,
+) -> Result<Collider, ColliderError> {
+    // With inverse transform, collider will match to the entity's shape
+    let affine = transform.affine().inverse();
+    let mut colliders = vec![];
+    for (part, shape) in parts {
+        let mut vertices = collect_part_vertices(part, meshes, with_children, with_meshes)?;
+        vertices
+            .iter_mut()
+            .for_each(|v| *v = affine.transform_point3(*v));
+        colliders.extend(fit_part(shape, &vertices));
+    }
+
+    if colliders.is_empty() {
+        return Err(ColliderError::NoShapes);
+    }
+    Ok(Collider::compound(colliders))
+}
+
+/// Inserts collider with all required components or reports an error
+fn finish_setup(
+    commands: &mut Commands,
+    entity: Entity,
+    transform: &GlobalTransform,
+    mass: Option<&MassOverride>,
+    collider: Result<Collider, ColliderError>,
+    ev_ready: &mut EventWriter<ColliderReady>,
+    ev_failed: &mut EventWriter<ColliderFailed>,
+) {
+    match collider {
+        Ok(collider) => {
+            if let Some(mass) = mass {
+                let (scale, _, _) = transform.to_scale_rotation_translation();
+                let mass_properties = mass.to_mass_properties(&collider, scale);
+                commands.entity(entity).insert(mass_properties);
+            }
+            commands
+                .entity(entity)
+                .insert(collider)
+                .insert(PhysicsReady)
+                .insert(RecalculateTransform);
+            ev_ready.send(ColliderReady(entity));
+        }
+        Err(error) => {
+            warn!("Failed to build collider for {entity:?}: {error:?}");
+            ev_failed.send(ColliderFailed(entity, error));
+        }
+    }
+}
+
+fn convex_hull(
+    mut commands: Commands,
+    meshes: Res<Assets<Mesh>>,
+    to_setup: Query<(Entity, &ConvexHull, &GlobalTransform, Option<&MassOverride>)>,
+    with_children: Query<&Children>,
+    with_meshes: Query<(&Handle<Mesh>, &GlobalTransform)>,
+    mut ev_ready: EventWriter<ColliderReady>,
+    mut ev_failed: EventWriter<ColliderFailed>,
+) {
+    for (entity, collider_parts, transform, mass) in to_setup.iter() {
+        let parts = collider_parts
+            .0
+            .iter()
+            .map(|part| (*part, PartShape::ConvexHull));
+        let collider = fit_compound(parts, transform, &meshes, &with_children, &with_meshes);
+        finish_setup(
+            &mut commands,
+            entity,
+            transform,
+            mass,
+            collider,
+            &mut ev_ready,
+            &mut ev_failed,
+        );
         commands.entity(entity).remove::<ConvexHull>();
     }
 }
 
+fn compound_collider(
+    mut commands: Commands,
+    meshes: Res<Assets<Mesh>>,
+    to_setup: Query<(
+        Entity,
+        &CompoundCollider,
+        &GlobalTransform,
+        Option<&MassOverride>,
+    )>,
+    with_children: Query<&Children>,
+    with_meshes: Query<(&Handle<Mesh>, &GlobalTransform)>,
+    mut ev_ready: EventWriter<ColliderReady>,
+    mut ev_failed: EventWriter<ColliderFailed>,
+) {
+    for (entity, collider_parts, transform, mass) in to_setup.iter() {
+        let parts = collider_parts.0.iter().copied();
+        let collider = fit_compound(parts, transform, &meshes, &with_children, &with_meshes);
+        finish_setup(
+            &mut commands,
+            entity,
+            transform,
+            mass,
+            collider,
+            &mut ev_ready,
+            &mut ev_failed,
+        );
+        commands.entity(entity).remove::<CompoundCollider>();
+    }
+}
+
+fn fit_primitive(
+    mut commands: Commands,
+    meshes: Res<Assets<Mesh>>,
+    to_setup: Query<(
+        Entity,
+        &FitPrimitive,
+        &GlobalTransform,
+        Option<&MassOverride>,
+    )>,
+    with_children: Query<&Children>,
+    with_meshes: Query<(&Handle<Mesh>, &GlobalTransform)>,
+    mut ev_ready: EventWriter<ColliderReady>,
+    mut ev_failed: EventWriter<ColliderFailed>,
+) {
+    for (entity, primitive, transform, mass) in to_setup.iter() {
+        let affine = transform.affine().inverse();
+        let collider =
+            collect_part_vertices(primitive.mesh_source, &meshes, &with_children, &with_meshes)
+                .and_then(|mut vertices| {
+                    vertices
+                        .iter_mut()
+                        .for_each(|v| *v = affine.transform_point3(*v));
+                    fit_part(primitive.shape, &vertices).ok_or(ColliderError::NoShapes)
+                })
+                .map(|(translation, rotation, collider)| {
+                    // Avoid compound overhead if primitive is already centered
+                    if translation.abs_diff_eq(Vec3::ZERO, 1e-4)
+                        && rotation.abs_diff_eq(Quat::IDENTITY, 1e-4)
+                    {
+                        collider
+                    } else {
+                        Collider::compound(vec![(translation, rotation, collider)])
+                    }
+                });
+        finish_setup(
+            &mut commands,
+            entity,
+            transform,
+            mass,
+            collider,
+            &mut ev_ready,
+            &mut ev_failed,
+        );
+        commands.entity(entity).remove::<FitPrimitive>();
+    }
+}
+
+fn decompose(
+    decomposition: &ConvexDecomposition,
+    transform: &GlobalTransform,
+    meshes: &Assets<Mesh>,
+    with_meshes: &Query<(&Handle<Mesh>, &GlobalTransform)>,
+) -> Result<Collider, ColliderError> {
+    let source = decomposition.mesh_source;
+    let (mesh, source_transform) = with_meshes
+        .get(source)
+        .map_err(|_| ColliderError::MissingMesh(source))?;
+    let mesh = meshes.get(mesh).ok_or(ColliderError::MissingMesh(source))?;
+    let mut vertices = extract_mesh_vertices(mesh).ok_or(ColliderError::UnsupportedMesh(source))?;
+    let indices = extract_mesh_indices(mesh).ok_or(ColliderError::UnsupportedMesh(source))?;
+
+    let to_global = source_transform.affine();
+    let to_local = transform.affine().inverse();
+    for v in vertices.iter_mut() {
+        *v = to_local.transform_point3(to_global.transform_point3(*v));
+    }
+
+    Ok(Collider::convex_decomposition_with_params(
+        &vertices,
+        &indices,
+        &decomposition.parameters,
+    ))
+}
+
 fn convex_decomposition(
     mut commands: Commands,
-    meshes: ResMut<Assets<Mesh>>,
-    to_setup: Query<(Entity, &ConvexDecomposition, &GlobalTransform)>,
+    meshes: Res<Assets<Mesh>>,
+    to_setup: Query<(
+        Entity,
+        &ConvexDecomposition,
+        &GlobalTransform,
+        Option<&MassOverride>,
+    )>,
     with_meshes: Query<(&Handle<Mesh>, &GlobalTransform)>,
+    mut ev_ready: EventWriter<ColliderReady>,
+    mut ev_failed: EventWriter<ColliderFailed>,
 ) {
-    for (entity, decomposition, transform) in to_setup.iter() {
-        let (mesh, source_transform) = with_meshes.get(decomposition.mesh_source).unwrap();
-        let mesh = meshes.get(mesh).unwrap();
-        let mut vertices = extract_mesh_vertices(mesh).unwrap();
-        let indices = extract_mesh_indices(mesh).unwrap();
-
-        let to_global = source_transform.affine();
-        let to_local = transform.affine().inverse();
-        for v in vertices.iter_mut() {
-            *v = to_local.transform_point3(to_global.transform_point3(*v));
-        }
-
-        commands
-            .entity(entity)
-            .insert(Collider::convex_decomposition_with_params(
-                &vertices,
-                &indices,
-                &decomposition.parameters,
-            ))
-            .insert(RecalculateTransform);
+    for (entity, decomposition, transform, mass) in to_setup.iter() {
+        let collider = decompose(decomposition, transform, &meshes, &with_meshes);
+        finish_setup(
+            &mut commands,
+            entity,
+            transform,
+            mass,
+            collider,
+            &mut ev_ready,
+            &mut ev_failed,
+        );
         commands.entity(entity).remove::<ConvexDecomposition>();
     }
 }
@@ -156,11 +614,36 @@ fn recalculate_transform(
     }
 }
 
+/// Builds colliders of loaded models from their meshes, as requested by components or glTF extras.
 pub struct ColliderSetupPlugin;
 impl Plugin for ColliderSetupPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(convex_hull)
+        app.add_event::<ColliderReady>()
+            .register_type::<MassOverride>()
+            .add_event::<ColliderFailed>()
+            .add_system(convex_hull)
+            .add_system(compound_collider)
+            .add_system(fit_primitive)
             .add_system(convex_decomposition)
-            .add_system(recalculate_transform);
+            .add_system(recalculate_transform)
+            // `{"collider": "convex_hull"}` or `{"collider": "ball"}` builds a collider from the node's own meshes
+            .register_gltf_property("collider", |commands, value| {
+                let entity = commands.id();
+                let shape = match value.as_str() {
+                    Some("convex_hull") => {
+                        commands.insert(ConvexHull::new(vec![entity]));
+                        return;
+                    }
+                    Some("cuboid") => PartShape::Cuboid,
+                    Some("oriented_cuboid") => PartShape::OrientedCuboid,
+                    Some("ball") => PartShape::Ball,
+                    Some("capsule") => PartShape::Capsule,
+                    _ => return,
+                };
+                commands.insert(FitPrimitive {
+                    mesh_source: entity,
+                    shape,
+                });
+            });
     }
 }
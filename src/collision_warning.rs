@@ -0,0 +1,163 @@
+//! Collision warning for the player: a sphere is swept ahead along the velocity to find large
+//! obstacles like the capital ship. Once the impact is close, the HUD and a sound cue warn about
+//! it and the optional auto-brake limits the speed towards the obstacle.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    audio_cues::{AudioCue, AudioCueEvent, AudioCuesPlugin},
+    player::Player,
+};
+
+/// The warning is raised once the time to impact at the current velocity drops below it, in seconds
+const WARNING_TIME: f32 = 3.0;
+/// Auto-brake keeps the time to impact above it, in seconds
+const BRAKE_TIME: f32 = 1.5;
+/// Obstacles are tracked at least this far ahead even at low speed, so the auto-brake holds
+/// the ship near them, in meters
+const MIN_PROBE_DISTANCE: f32 = 30.0;
+/// Radius of the swept sphere, a bit larger than the player to keep some clearance, in meters
+const PROBE_RADIUS: f32 = 2.0;
+/// Colliders with a smaller bounding sphere, like drones or projectiles, aren't obstacles, in meters
+const OBSTACLE_RADIUS: f32 = 8.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Obstacle {
+    pub entity: Entity,
+    /// Direction of the movement towards the obstacle
+    pub direction: Vec3,
+    /// In meters
+    pub distance: f32,
+    /// At the current velocity, in seconds
+    pub time_to_impact: f32,
+}
+
+/// Large obstacle ahead of the player along its velocity. Auto-brake is an assist the player
+/// opts in to with B.
+#[derive(Component, Default)]
+pub struct CollisionWarning {
+    pub obstacle: Option<Obstacle>,
+    pub auto_brake: bool,
+}
+
+impl CollisionWarning {
+    pub fn is_active(&self) -> bool {
+        matches!(self.obstacle, Some(obstacle) if obstacle.time_to_impact < WARNING_TIME)
+    }
+
+    pub fn is_braking(&self) -> bool {
+        self.auto_brake
+            && matches!(self.obstacle, Some(obstacle) if obstacle.time_to_impact < BRAKE_TIME)
+    }
+
+    /// `velocity` with the auto-brake applied, if it's enabled
+    pub fn brake(&self, velocity: Vec3) -> Vec3 {
+        match self.obstacle {
+            Some(obstacle) if self.auto_brake => {
+                brake(velocity, obstacle.direction, obstacle.distance)
+            }
+            _ => velocity,
+        }
+    }
+}
+
+/// Limits the `velocity` towards the obstacle in the `direction` at the `distance`, so the time
+/// to impact never drops below `BRAKE_TIME`. The ship slows down while approaching and stops
+/// right before the obstacle, while moving along or away from it is not affected.
+fn brake(velocity: Vec3, direction: Vec3, distance: f32) -> Vec3 {
+    let max_speed = distance / BRAKE_TIME;
+    let closing_speed = velocity.dot(direction);
+    if closing_speed > max_speed {
+        velocity - direction * (closing_speed - max_speed)
+    } else {
+        velocity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{brake, BRAKE_TIME};
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_brake() {
+        let direction = Vec3::NEG_Z;
+        // far enough
+        let velocity = Vec3::new(5.0, 0.0, -10.0);
+        assert_eq!(brake(velocity, direction, 100.0), velocity);
+        // moving away
+        assert_eq!(brake(-velocity, direction, 1.0), -velocity);
+
+        let braked = brake(velocity, direction, 6.0);
+        assert_eq!(braked, Vec3::new(5.0, 0.0, -6.0 / BRAKE_TIME));
+        assert_eq!(brake(velocity, direction, 0.0), Vec3::X * 5.0);
+    }
+}
+
+fn detect_obstacles(
+    rapier_context: Res<RapierContext>,
+    keys: Res<Input<KeyCode>>,
+    mut player: Query<(Entity, &Transform, &Velocity, &mut CollisionWarning), With<Player>>,
+    colliders: Query<&Collider>,
+    mut ev_cue: EventWriter<AudioCueEvent>,
+) {
+    let Ok((player, transform, velocity, mut warning)) = player.get_single_mut() else {
+        return;
+    };
+    if keys.just_pressed(KeyCode::B) {
+        warning.auto_brake = !warning.auto_brake;
+        info!("Auto-brake: {}", warning.auto_brake);
+    }
+
+    let speed = velocity.linvel.length();
+    let is_obstacle = |entity: Entity| {
+        colliders.get(entity).map_or(false, |collider| {
+            collider.raw.compute_local_bounding_sphere().radius() >= OBSTACLE_RADIUS
+        })
+    };
+    let filter = QueryFilter::new()
+        .exclude_sensors()
+        .exclude_collider(player)
+        .predicate(&is_obstacle);
+    let obstacle = velocity.linvel.try_normalize().and_then(|direction| {
+        let probe_distance = (speed * WARNING_TIME).max(MIN_PROBE_DISTANCE);
+        rapier_context
+            .cast_shape(
+                transform.translation,
+                transform.rotation,
+                direction,
+                &Collider::ball(PROBE_RADIUS),
+                probe_distance,
+                filter,
+            )
+            .map(|(entity, toi)| Obstacle {
+                entity,
+                direction,
+                // the direction is normalized, so the time of impact is the distance
+                distance: toi.toi,
+                time_to_impact: toi.toi / speed,
+            })
+    });
+
+    let was_active = warning.is_active();
+    warning.obstacle = obstacle;
+    if warning.is_active() && !was_active {
+        ev_cue.send(AudioCueEvent {
+            cue: AudioCue::CollisionWarning,
+            position: obstacle.map(|o| transform.translation + o.direction * o.distance),
+        });
+    }
+}
+
+/// Warns the player about the collision with large obstacles ahead and brakes before them.
+pub struct CollisionWarningPlugin;
+impl Plugin for CollisionWarningPlugin {
+    fn build(&self, app: &mut App) {
+        // Plays the warning cue
+        if !app.is_plugin_added::<AudioCuesPlugin>() {
+            app.add_plugin(AudioCuesPlugin);
+        }
+        app.add_system(detect_obstacles);
+    }
+}
@@ -0,0 +1,330 @@
+//! Combat log of timestamped events like kills, lost subsystems, player deaths and arriving waves.
+//! It's shown with J and scrolled with the arrow keys, F12 exports it to `COMBAT_LOG_PATH` for
+//! post-game analysis.
+
+use bevy::prelude::*;
+
+use crate::{
+    components::Fraction,
+    jump_gate::JumpInEvent,
+    localization::Localization,
+    player::Player,
+    projectile::{DamageEvent, DamageSystems},
+    reinforcements::BattleEndedEvent,
+    scenario::ScenarioPicker,
+};
+
+const COMBAT_LOG_PATH: &str = "combat_log.txt";
+/// The oldest entries are dropped once there are more
+const MAX_ENTRIES: usize = 1000;
+/// Entries shown at once, the rest is scrolled
+const VISIBLE_ENTRIES: usize = 20;
+const SCREEN_WIDTH: f32 = 640.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LogEvent {
+    Kill {
+        attacker: Option<String>,
+        target: String,
+    },
+    SubsystemLost {
+        unit: String,
+        subsystem: String,
+    },
+    PlayerDeath {
+        attacker: Option<String>,
+    },
+    WaveArrived {
+        fraction: Fraction,
+    },
+    BattleEnded {
+        defeated: Fraction,
+    },
+}
+
+impl LogEvent {
+    fn describe(&self, localization: &Localization) -> String {
+        let unknown = || localization.get("log-unknown").to_owned();
+        match self {
+            LogEvent::Kill { attacker, target } => {
+                let attacker = attacker.clone().unwrap_or_else(unknown);
+                localization.format("log-kill", &[("attacker", &attacker), ("target", target)])
+            }
+            LogEvent::SubsystemLost { unit, subsystem } => localization.format(
+                "log-subsystem-lost",
+                &[("unit", unit), ("subsystem", subsystem)],
+            ),
+            LogEvent::PlayerDeath { attacker } => {
+                let attacker = attacker.clone().unwrap_or_else(unknown);
+                localization.format("log-player-death", &[("attacker", &attacker)])
+            }
+            LogEvent::WaveArrived { fraction } => {
+                let faction = localization.get(fraction.key());
+                localization.format("log-wave", &[("faction", &faction)])
+            }
+            LogEvent::BattleEnded { defeated } => {
+                let faction = localization.get(defeated.key());
+                localization.format("log-battle-ended", &[("faction", &faction)])
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogEntry {
+    /// Seconds since startup
+    pub time: f32,
+    pub event: LogEvent,
+}
+
+/// Combat events of the session, the oldest first.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct CombatLog {
+    entries: Vec<LogEntry>,
+}
+
+impl CombatLog {
+    pub fn push(&mut self, time: f32, event: LogEvent) {
+        if self.entries.len() >= MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        self.entries.push(LogEntry { time, event });
+    }
+
+    pub fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+}
+
+impl LogEntry {
+    /// Line of text like `[01:05] ...`
+    fn line(&self, localization: &Localization) -> String {
+        let time = timestamp(self.time);
+        format!("[{time}] {}", self.event.describe(localization))
+    }
+}
+
+/// `seconds` as minutes and seconds, like `01:05`
+fn timestamp(seconds: f32) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{timestamp, CombatLog, LogEvent, MAX_ENTRIES};
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(0.0), "00:00");
+        assert_eq!(timestamp(65.9), "01:05");
+        assert_eq!(timestamp(6000.0), "100:00");
+    }
+
+    #[test]
+    fn test_max_entries() {
+        let mut log = CombatLog::default();
+        for i in 0..MAX_ENTRIES + 10 {
+            let event = LogEvent::PlayerDeath { attacker: None };
+            log.push(i as f32, event);
+        }
+        assert_eq!(log.entries().len(), MAX_ENTRIES);
+        // the oldest ones are dropped
+        assert_eq!(log.entries()[0].time, 10.0);
+    }
+}
+
+#[derive(Component, Default)]
+struct CombatLogScreen {
+    /// Entries scrolled back from the latest one
+    scroll: usize,
+}
+
+#[derive(Component)]
+struct CombatLogText;
+
+fn setup_combat_log_screen(mut commands: Commands, assets: Res<AssetServer>) {
+    let font = assets.load("fonts/FiraMono-Medium.ttf");
+
+    // Hidden panel in the middle of the screen, shown on demand
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(SCREEN_WIDTH), Val::Auto),
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Percent(50.0),
+                    top: Val::Percent(10.0),
+                    ..default()
+                },
+                margin: UiRect {
+                    left: Val::Px(-SCREEN_WIDTH / 2.0),
+                    ..default()
+                },
+                padding: UiRect::all(Val::Px(10.0)),
+                display: Display::None,
+                ..default()
+            },
+            background_color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+            ..default()
+        })
+        .insert(CombatLogScreen::default())
+        .insert(Name::new("Combat Log"))
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font,
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                ))
+                .insert(CombatLogText);
+        });
+}
+
+/// Name of the root entity the `entity` belongs to
+fn root_name(
+    entity: Entity,
+    names: &Query<&Name>,
+    parent_query: &Query<&Parent>,
+) -> Option<String> {
+    let root = parent_query.iter_ancestors(entity).last().unwrap_or(entity);
+    names.get(root).ok().map(|name| name.as_str().to_owned())
+}
+
+fn record_damage(
+    time: Res<Time>,
+    mut log: ResMut<CombatLog>,
+    mut ev_damage: EventReader<DamageEvent>,
+    player: Query<(), With<Player>>,
+    names: Query<&Name>,
+    parent_query: Query<&Parent>,
+) {
+    for ev in ev_damage.iter().filter(|ev| ev.destroyed) {
+        let attacker = ev
+            .attacker
+            .and_then(|e| root_name(e, &names, &parent_query));
+        let event = if player.contains(ev.target) {
+            LogEvent::PlayerDeath { attacker }
+        } else if let Ok(name) = names.get(ev.target) {
+            let name = name.as_str().to_owned();
+            // Parts of turrets and ships are lost one by one, before the whole unit is gone
+            match parent_query.get(ev.target) {
+                Ok(_) => LogEvent::SubsystemLost {
+                    unit: root_name(ev.target, &names, &parent_query).unwrap_or_default(),
+                    subsystem: name,
+                },
+                Err(_) => LogEvent::Kill {
+                    attacker,
+                    target: name,
+                },
+            }
+        } else {
+            continue;
+        };
+        log.push(time.elapsed_seconds(), event);
+    }
+}
+
+fn record_battle(
+    time: Res<Time>,
+    mut log: ResMut<CombatLog>,
+    mut ev_jump_in: EventReader<JumpInEvent>,
+    mut ev_battle_ended: EventReader<BattleEndedEvent>,
+) {
+    for ev in ev_jump_in.iter() {
+        let fraction = ev.fraction;
+        log.push(time.elapsed_seconds(), LogEvent::WaveArrived { fraction });
+    }
+    for ev in ev_battle_ended.iter() {
+        let defeated = ev.defeated;
+        log.push(time.elapsed_seconds(), LogEvent::BattleEnded { defeated });
+    }
+}
+
+fn combat_log_controls(
+    keys: Res<Input<KeyCode>>,
+    log: Res<CombatLog>,
+    localization: Res<Localization>,
+    picker: Option<Res<ScenarioPicker>>,
+    mut screen: Query<(&mut Style, &mut CombatLogScreen)>,
+) {
+    if keys.just_pressed(KeyCode::F12) {
+        let text: String = log
+            .entries()
+            .iter()
+            .map(|entry| entry.line(&localization) + "\n")
+            .collect();
+        match std::fs::write(COMBAT_LOG_PATH, text) {
+            Ok(()) => info!("Combat log exported to {COMBAT_LOG_PATH}"),
+            Err(err) => error!("Failed to export combat log to {COMBAT_LOG_PATH}: {err}"),
+        }
+    }
+
+    let Ok((mut style, mut screen)) = screen.get_single_mut() else {
+        return;
+    };
+    if keys.just_pressed(KeyCode::J) {
+        style.display = match style.display {
+            Display::None => Display::Flex,
+            Display::Flex => Display::None,
+        };
+        screen.scroll = 0;
+    }
+    // The picker on top takes the arrow keys
+    if style.display == Display::None || picker.map_or(false, |picker| picker.open) {
+        return;
+    }
+    let max_scroll = log.entries().len().saturating_sub(VISIBLE_ENTRIES);
+    if keys.just_pressed(KeyCode::Up) && screen.scroll < max_scroll {
+        screen.scroll += 1;
+    }
+    if keys.just_pressed(KeyCode::Down) && screen.scroll > 0 {
+        screen.scroll -= 1;
+    }
+}
+
+fn update_combat_log_screen(
+    log: Res<CombatLog>,
+    localization: Res<Localization>,
+    screen: Query<(&Style, &CombatLogScreen)>,
+    mut text: Query<&mut Text, With<CombatLogText>>,
+) {
+    let Ok((style, screen)) = screen.get_single() else {
+        return;
+    };
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+    if style.display == Display::None {
+        return;
+    }
+
+    let end = log.entries().len().saturating_sub(screen.scroll);
+    let start = end.saturating_sub(VISIBLE_ENTRIES);
+    let mut lines = vec![localization.get("log-title").to_owned()];
+    lines.extend(
+        log.entries()[start..end]
+            .iter()
+            .map(|e| e.line(&localization)),
+    );
+    lines.push(localization.get("log-hint").to_owned());
+    text.sections[0].value = lines.join("\n");
+}
+
+/// `CombatLog` of the session with the panel to browse it and the export to a text file.
+pub struct CombatLogPlugin;
+impl Plugin for CombatLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CombatLog>()
+            .add_event::<JumpInEvent>()
+            .add_event::<BattleEndedEvent>()
+            .add_startup_system(setup_combat_log_screen)
+            .add_system(record_damage.after(DamageSystems))
+            .add_system(record_battle)
+            .add_system(combat_log_controls)
+            .add_system(update_combat_log_screen.after(combat_log_controls));
+    }
+}
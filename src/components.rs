@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::scene_setup::GltfExtrasApp;
+
+/// Entity lifetime in seconds, after which entity should be destroyed
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+#[cfg_attr(feature = "devtools", derive(bevy_inspector_egui::Inspectable))]
+pub struct Lifetime(#[cfg_attr(feature = "devtools", inspectable(min = 0.0))] pub f32);
+
+fn lifetime(mut commands: Commands, time: Res<Time>, mut query: Query<(Entity, &mut Lifetime)>) {
+    for (entity, mut lifetime) in query.iter_mut() {
+        lifetime.0 -= time.delta_seconds();
+        if lifetime.0 <= 0.0 {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Damage dealt by a projectile on hit
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct Damage(pub u32);
+
+/// Entity is destroyed once its hit points drop to zero
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+#[cfg_attr(feature = "devtools", derive(bevy_inspector_egui::Inspectable))]
+pub struct HitPoints {
+    #[cfg_attr(feature = "devtools", inspectable(min = 1))]
+    maximum: u32,
+    current: u32,
+}
+
+impl HitPoints {
+    pub fn new(maximum: u32) -> Self {
+        HitPoints {
+            maximum,
+            current: maximum,
+        }
+    }
+    pub fn maximum(&self) -> u32 {
+        self.maximum
+    }
+    pub fn percent(&self) -> u32 {
+        100 * self.current / self.maximum
+    }
+    pub fn dead(&self) -> bool {
+        self.current == 0
+    }
+    pub fn hit(&mut self, damage: u32) -> &mut Self {
+        self.current = self.current.saturating_sub(damage);
+        self
+    }
+    /// Back to the maximum, e.g. on respawn
+    pub fn restore(&mut self) {
+        self.current = self.maximum;
+    }
+    /// Restores up to `amount` points without exceeding the maximum
+    pub fn heal(&mut self, amount: u32) -> &mut Self {
+        self.current = self.current.saturating_add(amount).min(self.maximum);
+        self
+    }
+}
+
+/// Destroyed entity isn't despawned, because its death is handled separately, e.g. the player
+/// watches the kill-cam and respawns.
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct Respawnable;
+
+/// Destroyed entity isn't despawned, because it's returned to a pool to be reused, like drones.
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct Pooled;
+
+/// Velocity in m/s pushed onto a kinematic body from the outside, e.g. by the recoil of its guns.
+/// Rapier applies impulses to dynamic bodies only, so the system moving the kinematic body adds
+/// the push to its velocity.
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct KinematicPush(pub Vec3);
+
+#[cfg(test)]
+mod tests {
+    use super::HitPoints;
+
+    #[test]
+    fn test_new_hp_always_100() {
+        assert_eq!(HitPoints::new(1).percent(), 100);
+        assert_eq!(HitPoints::new(2).percent(), 100);
+        assert_eq!(HitPoints::new(111).percent(), 100);
+    }
+
+    #[test]
+    fn test_hp_hit() {
+        assert_eq!(HitPoints::new(1).hit(0).percent(), 100);
+        assert_eq!(HitPoints::new(1).hit(1).percent(), 0);
+        assert_eq!(HitPoints::new(1).hit(10).percent(), 0);
+        assert_eq!(HitPoints::new(50).hit(25).percent(), 50);
+        assert_eq!(HitPoints::new(100).hit(0).percent(), 100);
+        assert_eq!(HitPoints::new(100).hit(1).percent(), 99);
+        assert_eq!(HitPoints::new(100).hit(99).percent(), 1);
+        assert_eq!(HitPoints::new(100).hit(100).percent(), 0);
+        assert_eq!(HitPoints::new(100).hit(101).percent(), 0);
+
+        assert!(!HitPoints::new(1).hit(0).dead());
+        assert!(HitPoints::new(1).hit(1).dead());
+        assert!(HitPoints::new(1).hit(10).dead());
+        assert!(!HitPoints::new(50).hit(25).dead());
+        assert!(!HitPoints::new(100).hit(0).dead());
+        assert!(!HitPoints::new(100).hit(1).dead());
+        assert!(!HitPoints::new(100).hit(99).dead());
+        assert!(HitPoints::new(100).hit(100).dead());
+        assert!(HitPoints::new(100).hit(101).dead());
+    }
+
+    #[test]
+    fn test_hp_heal() {
+        let mut hp = HitPoints::new(100);
+        assert_eq!(hp.hit(50).heal(20).percent(), 70);
+        // clamped at the maximum
+        assert_eq!(hp.heal(50).percent(), 100);
+        hp.heal(u32::MAX);
+        assert_eq!(hp.current, hp.maximum());
+    }
+
+    #[test]
+    fn test_hp_restore() {
+        let mut hp = HitPoints::new(10);
+        hp.hit(10).restore();
+        assert_eq!(hp.percent(), 100);
+        assert!(!hp.dead());
+    }
+}
+
+/// Side of the conflict. Gun layers never target entities of their own fraction.
+#[derive(
+    Component, Deserialize, Copy, Clone, PartialEq, Eq, Debug, Default, Reflect, FromReflect,
+)]
+#[reflect(Component)]
+#[serde(rename_all = "snake_case")]
+pub enum Fraction {
+    #[default]
+    Drones,
+    Turrets,
+}
+
+impl Fraction {
+    pub const ALL: [Fraction; 2] = [Fraction::Drones, Fraction::Turrets];
+
+    /// Localization key of the faction name
+    pub fn key(self) -> &'static str {
+        match self {
+            Fraction::Drones => "faction-drones",
+            Fraction::Turrets => "faction-turrets",
+        }
+    }
+}
+
+/// Lifetime of entities and components shared by other gameplay plugins.
+pub struct ComponentsPlugin;
+impl Plugin for ComponentsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(lifetime)
+            .register_type::<Lifetime>()
+            .register_type::<Damage>()
+            .register_type::<HitPoints>()
+            .register_type::<Respawnable>()
+            .register_type::<Pooled>()
+            .register_type::<KinematicPush>()
+            .register_type::<Fraction>()
+            .register_gltf_property("hp", |commands, value| {
+                if let Some(hp) = value.as_u64() {
+                    commands.insert(HitPoints::new(hp as u32));
+                }
+            });
+    }
+}
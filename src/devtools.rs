@@ -0,0 +1,253 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::{
+    bevy_egui::{egui, EguiContext},
+    RegisterInspectable, WorldInspectorPlugin,
+};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    aiming::{AimingSettings, GunLayer, LeadQuality, MaxAngleFn},
+    components::{Fraction, HitPoints, Lifetime},
+    drone::{Drone, DroneTuning, SpawnDroneEvent},
+    gun::{Gun, Heat},
+    hud::{HudLayout, HudWidget},
+    player::{ResponseCurve, UiPointer},
+    projectile::Shield,
+    settings::Settings,
+    turret::{Deployment, SpawnTurretEvent, TurretKind},
+};
+
+/// Shares egui pointer state with gameplay systems, which don't depend on egui directly
+fn update_ui_pointer(mut egui: ResMut<EguiContext>, mut pointer: ResMut<UiPointer>) {
+    let ctx = egui.ctx_mut();
+    pointer.wants_input = ctx.wants_pointer_input();
+    pointer.is_used = ctx.is_using_pointer();
+}
+
+fn aiming_tuner(
+    mut egui: ResMut<EguiContext>,
+    mut settings: ResMut<AimingSettings>,
+    mut guns: Query<(Entity, Option<&Name>, &mut Gun)>,
+) {
+    egui::Window::new("Aiming").show(egui.ctx_mut(), |ui| {
+        ui.label("Max lead quality");
+        ui.horizontal(|ui| {
+            for lead in LeadQuality::ALL {
+                ui.radio_value(&mut settings.max_lead, lead, format!("{lead:?}"));
+            }
+        });
+        ui.add(egui::Slider::new(&mut settings.update_rate, 1.0..=60.0).text("Update rate, Hz"));
+        ui.checkbox(&mut settings.aim_errors, "Aim errors");
+        ui.separator();
+        ui.label("Projectile speed, m/s");
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (entity, name, mut gun) in guns.iter_mut() {
+                let mut speed = gun.speed();
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut speed).clamp_range(1.0..=2000.0));
+                    ui.label(match name {
+                        Some(name) => format!("{name} ({})", entity.index()),
+                        None => format!("{entity:?}"),
+                    });
+                });
+                // Compare to not trigger change detection every frame
+                if speed != gun.speed() {
+                    gun.set_speed(speed);
+                }
+            }
+        });
+    });
+}
+
+fn drone_tuner(mut egui: ResMut<EguiContext>, mut tuning: ResMut<DroneTuning>) {
+    let mut edited = tuning.clone();
+    egui::Window::new("Drone AI").show(egui.ctx_mut(), |ui| {
+        ui.add(egui::Slider::new(&mut edited.thrust_scale, 0.0..=5.0).text("Thrust scale"));
+        ui.add(
+            egui::Slider::new(&mut edited.approach_distance, 0.0..=1000.0)
+                .text("Approach distance, m"),
+        );
+        ui.add(
+            egui::Slider::new(&mut edited.boost_distance, 0.0..=2000.0).text("Boost distance, m"),
+        );
+        ui.add(egui::Slider::new(&mut edited.break_off_health, 0.0..=1.0).text("Break off health"));
+        ui.add(
+            egui::Slider::new(&mut edited.dodge_distance, 0.0..=500.0).text("Dodge distance, m"),
+        );
+        let fire_control = &mut edited.fire_control;
+        ui.add(
+            egui::Slider::new(&mut fire_control.max_range, 0.0..=5000.0)
+                .text("Engagement range, m"),
+        );
+        match &mut fire_control.max_angle_fn {
+            MaxAngleFn::Constant(angle) => {
+                ui.add(egui::Slider::new(angle, 0.0..=1.0).text("Fire threshold, rad"));
+            }
+            MaxAngleFn::TargetSize { size, min } => {
+                ui.add(egui::Slider::new(size, 0.0..=50.0).text("Target size, m"));
+                ui.add(egui::Slider::new(min, 0.0..=1.0).text("Min fire threshold, rad"));
+            }
+        }
+    });
+    // Drones pick up the fire control on change, so compare to not reset it every frame
+    if edited != *tuning {
+        *tuning = edited;
+    }
+}
+
+fn mouse_tuner(mut egui: ResMut<EguiContext>, mut settings: ResMut<Settings>) {
+    let mut mouse = settings.mouse.clone();
+    egui::Window::new("Mouse").show(egui.ctx_mut(), |ui| {
+        ui.add(egui::Slider::new(&mut mouse.sensitivity, 0.001..=0.02).text("Sensitivity, deg/px"));
+        ui.add(egui::Slider::new(&mut mouse.deadzone, 0.0..=100.0).text("Deadzone, px"));
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut mouse.curve, ResponseCurve::Linear, "Linear");
+            ui.radio_value(&mut mouse.curve, ResponseCurve::Exponential, "Exponential");
+        });
+        ui.checkbox(&mut mouse.invert_x, "Invert X");
+        ui.checkbox(&mut mouse.invert_y, "Invert Y");
+    });
+    // Compare to not trigger change detection every frame
+    if mouse != settings.mouse {
+        settings.mouse = mouse;
+    }
+}
+
+fn flight_tuner(mut egui: ResMut<EguiContext>, mut settings: ResMut<Settings>) {
+    let mut flight = settings.flight.clone();
+    egui::Window::new("Flight").show(egui.ctx_mut(), |ui| {
+        ui.checkbox(&mut flight.orbit_strafe, "Orbit strafe around the target");
+        ui.add(egui::Slider::new(&mut flight.roll_to_yaw, -1.0..=1.0).text("Roll to yaw"));
+        ui.add(egui::Slider::new(&mut flight.yaw_to_roll, -1.0..=1.0).text("Yaw to roll"));
+    });
+    // Compare to not trigger change detection every frame
+    if flight != settings.flight {
+        settings.flight = flight;
+    }
+}
+
+fn hud_tuner(
+    mut egui: ResMut<EguiContext>,
+    mut settings: ResMut<Settings>,
+    widgets: Query<&Name, With<HudWidget>>,
+) {
+    let mut hud = settings.hud.clone();
+    let mut names: Vec<_> = widgets.iter().map(Name::as_str).collect();
+    names.sort_unstable();
+    names.dedup();
+    egui::Window::new("HUD").show(egui.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            for layout in HudLayout::ALL {
+                ui.radio_value(&mut hud.layout, layout, format!("{layout:?}"));
+            }
+        });
+        ui.checkbox(&mut hud.auto_fade, "Fade out of combat");
+        ui.separator();
+        ui.label("Opacity");
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for name in names {
+                let mut opacity = hud.opacity(name);
+                ui.add(egui::Slider::new(&mut opacity, 0.0..=1.0).text(name));
+                if opacity != hud.opacity(name) {
+                    hud.widget_opacity.insert(name.to_owned(), opacity);
+                }
+            }
+        });
+    });
+    // Compare to not trigger change detection every frame
+    if hud.layout != settings.hud.layout
+        || hud.auto_fade != settings.hud.auto_fade
+        || hud.widget_opacity != settings.hud.widget_opacity
+    {
+        settings.hud = hud;
+    }
+}
+
+fn spawner(
+    mut egui: ResMut<EguiContext>,
+    mut ev_spawn_drone: EventWriter<SpawnDroneEvent>,
+    mut ev_spawn_turret: EventWriter<SpawnTurretEvent>,
+    mut position: Local<Vec3>,
+) {
+    egui::Window::new("Spawner").show(egui.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut position.x).prefix("x: "));
+            ui.add(egui::DragValue::new(&mut position.y).prefix("y: "));
+            ui.add(egui::DragValue::new(&mut position.z).prefix("z: "));
+        });
+
+        let transform = Transform::from_translation(*position);
+        for (drone, label) in [
+            (Drone::Praetor, "Spawn Praetor"),
+            (Drone::Infiltrator, "Spawn Infiltrator"),
+            (Drone::Warden, "Spawn Warden"),
+        ] {
+            if ui.button(label).clicked() {
+                ev_spawn_drone.send(SpawnDroneEvent {
+                    drone,
+                    fraction: Fraction::Drones,
+                    transform,
+                    prefab: None,
+                    entry_speed: 0.0,
+                    modifiers: default(),
+                    hardpoints: Vec::new(),
+                });
+            }
+        }
+        for kind in TurretKind::ALL {
+            if ui.button(format!("Spawn {}", kind.name())).clicked() {
+                ev_spawn_turret.send(SpawnTurretEvent {
+                    kind,
+                    prefab: None,
+                    transform,
+                    parent: None,
+                    rotation_speed: 120_f32.to_radians(),
+                    fraction: Fraction::Turrets,
+                });
+            }
+        }
+    });
+}
+
+fn turret_orders(mut egui: ResMut<EguiContext>, mut turrets: Query<&mut Deployment>) {
+    egui::Window::new("Turrets").show(egui.ctx_mut(), |ui| {
+        let deployed = turrets.iter().filter(|d| d.is_deployed()).count();
+        ui.label(format!(
+            "Deployed: {deployed} of {}",
+            turrets.iter().count()
+        ));
+        ui.horizontal(|ui| {
+            if ui.button("Stow all").clicked() {
+                turrets.iter_mut().for_each(|mut d| d.stow());
+            }
+            if ui.button("Deploy all").clicked() {
+                turrets.iter_mut().for_each(|mut d| d.deploy());
+            }
+        });
+    });
+}
+
+/// World inspector, physics debug render and purpose-built windows for tuning gameplay at runtime.
+pub struct DevToolsPlugin;
+impl Plugin for DevToolsPlugin {
+    fn build(&self, app: &mut App) {
+        // Pointer state is shared with the player, which doesn't exist in the observer mode
+        app.init_resource::<UiPointer>()
+            .add_plugin(WorldInspectorPlugin::new())
+            // Components with value ranges and read-only fields, the rest are shown via `Reflect`
+            .register_inspectable::<GunLayer>()
+            .register_inspectable::<Heat>()
+            .register_inspectable::<HitPoints>()
+            .register_inspectable::<Shield>()
+            .register_inspectable::<Lifetime>()
+            .add_plugin(RapierDebugRenderPlugin::default())
+            .add_system_to_stage(CoreStage::PreUpdate, update_ui_pointer)
+            .add_system(aiming_tuner)
+            .add_system(drone_tuner)
+            .add_system(mouse_tuner)
+            .add_system(flight_tuner)
+            .add_system(hud_tuner)
+            .add_system(spawner)
+            .add_system(turret_orders);
+    }
+}
@@ -0,0 +1,618 @@
+use bevy::{
+    core_pipeline::{bloom::BloomSettings, clear_color::ClearColorConfig},
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_resource::{
+            Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+        },
+    },
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+    window::{PresentMode, WindowId, WindowMode, WindowResized},
+};
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::{
+    post_process::{PostEffectSettings, PostProcessMaterial, PostProcessPlugin},
+    settings::Settings,
+};
+
+/// Display settings file is stored in the working directory
+const DISPLAY_PATH: &str = "display.json";
+/// Resolutions to choose from, in physical pixels
+const RESOLUTIONS: [[u32; 2]; 6] = [
+    [1280, 720],
+    [1600, 900],
+    [1920, 1080],
+    [2560, 1080],
+    [2560, 1440],
+    [3840, 2160],
+];
+const MIN_RENDER_SCALE: f32 = 0.5;
+const RENDER_SCALE_STEP: f32 = 0.1;
+const MAX_BLOOM_INTENSITY: f32 = 1.0;
+const BLOOM_STEP: f32 = 0.1;
+/// Physics steps per second with the physics interpolation, and the lowest rate without it
+const PHYSICS_RATE: f32 = 60.0;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum DisplayMode {
+    #[default]
+    Windowed,
+    /// Fullscreen window at the desktop resolution
+    Borderless,
+    /// Fullscreen with the video mode closest to the selected resolution
+    Exclusive,
+}
+
+impl DisplayMode {
+    fn next(self) -> Self {
+        match self {
+            DisplayMode::Windowed => DisplayMode::Borderless,
+            DisplayMode::Borderless => DisplayMode::Exclusive,
+            DisplayMode::Exclusive => DisplayMode::Windowed,
+        }
+    }
+
+    fn window_mode(self) -> WindowMode {
+        match self {
+            DisplayMode::Windowed => WindowMode::Windowed,
+            DisplayMode::Borderless => WindowMode::BorderlessFullscreen,
+            DisplayMode::Exclusive => WindowMode::SizedFullscreen,
+        }
+    }
+}
+
+/// Graphics tier of shadows, from the cheapest to the most expensive one.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ShadowQuality {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl ShadowQuality {
+    fn next(self) -> Self {
+        match self {
+            ShadowQuality::Low => ShadowQuality::Medium,
+            ShadowQuality::Medium => ShadowQuality::High,
+            ShadowQuality::High => ShadowQuality::Low,
+        }
+    }
+
+    /// Max number of point lights casting shadows at once, the closest ones are picked
+    pub fn max_shadow_lights(self) -> usize {
+        match self {
+            ShadowQuality::Low => 0,
+            ShadowQuality::Medium => 2,
+            ShadowQuality::High => 6,
+        }
+    }
+
+    /// Size of the point light shadow cubemap faces, in texels
+    pub fn shadow_map_size(self) -> usize {
+        match self {
+            ShadowQuality::Low => 256,
+            ShadowQuality::Medium => 1024,
+            ShadowQuality::High => 2048,
+        }
+    }
+
+    /// Whether short-living lights, like the ones of projectiles, may cast shadows
+    pub fn dynamic_shadows(self) -> bool {
+        self == ShadowQuality::High
+    }
+}
+
+/// Window settings, persisted separately from the profile as they depend on the machine.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub mode: DisplayMode,
+    pub vsync: bool,
+    /// Window size in physical pixels, ignored by the borderless mode
+    pub resolution: [u32; 2],
+    /// 3D scene is rendered at this fraction of the window resolution and upscaled to it,
+    /// while the HUD stays sharp. From 0.5 to 1.
+    pub render_scale: f32,
+    /// Physics is stepped at the fixed `PHYSICS_RATE` and dynamic bodies are interpolated
+    /// between the steps, so they move smoothly at any frame rate. Otherwise physics is stepped
+    /// once per frame. Opt-in, toggled with Home.
+    pub physics_interpolation: bool,
+    /// Dynamic lights farther from the camera than this are turned off, in meters
+    pub light_distance: f32,
+    /// Particle effects farther from the camera than this are hidden, in meters
+    pub effect_distance: f32,
+    pub shadows: ShadowQuality,
+    /// Glow around bright emissive materials like projectiles and engine plumes, from 0 to 1.
+    /// The scene is rendered in HDR with bloom only when it's above 0.
+    pub bloom_intensity: f32,
+    pub post_effects: PostEffectSettings,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            mode: DisplayMode::default(),
+            vsync: true,
+            resolution: [1280, 720],
+            render_scale: 1.0,
+            physics_interpolation: false,
+            light_distance: 2000.0,
+            effect_distance: 3000.0,
+            shadows: ShadowQuality::default(),
+            bloom_intensity: 0.3,
+            post_effects: PostEffectSettings::default(),
+        }
+    }
+}
+
+impl DisplaySettings {
+    /// Loads settings from the `path` or uses defaults if it doesn't exist or is broken
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        let mut settings: Self = serde_json::from_str(&content).unwrap_or_else(|err| {
+            warn!(
+                "Using default display settings, {} is broken: {err}",
+                path.display()
+            );
+            Self::default()
+        });
+        settings.render_scale = settings.render_scale.clamp(MIN_RENDER_SCALE, 1.0);
+        settings.bloom_intensity = settings.bloom_intensity.clamp(0.0, MAX_BLOOM_INTENSITY);
+        settings
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    fn timestep_mode(&self) -> TimestepMode {
+        if self.physics_interpolation {
+            TimestepMode::Interpolated {
+                dt: PHYSICS_RATE.recip(),
+                time_scale: 1.0,
+                substeps: 1,
+            }
+        } else {
+            // Rapier's default, frames longer than the step slow the simulation down
+            TimestepMode::Variable {
+                max_dt: PHYSICS_RATE.recip(),
+                time_scale: 1.0,
+                substeps: 1,
+            }
+        }
+    }
+
+    /// The 3D scene is rendered to a texture when it's upscaled or post-processed
+    fn offscreen(&self) -> bool {
+        self.render_scale < 1.0 || self.post_effects.any()
+    }
+
+    fn present_mode(&self) -> PresentMode {
+        if self.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        }
+    }
+}
+
+/// The next resolution from `RESOLUTIONS` after the `current` one, starting over after the last
+fn next_resolution(current: [u32; 2]) -> [u32; 2] {
+    let area = |[width, height]: [u32; 2]| width * height;
+    RESOLUTIONS
+        .into_iter()
+        .find(|&resolution| area(resolution) > area(current))
+        .unwrap_or(RESOLUTIONS[0])
+}
+
+/// Size of the texture the 3D scene is rendered to for the window of `physical` size
+fn render_size(physical: UVec2, scale: f32) -> UVec2 {
+    (physical.as_vec2() * scale)
+        .round()
+        .as_uvec2()
+        .max(UVec2::ONE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{next_resolution, render_size, DisplayMode, DisplaySettings, ShadowQuality};
+    use crate::post_process::PostEffectSettings;
+    use bevy::prelude::UVec2;
+
+    #[test]
+    fn test_next_resolution() {
+        assert_eq!(next_resolution([1280, 720]), [1600, 900]);
+        assert_eq!(next_resolution([2560, 1080]), [2560, 1440]);
+        assert_eq!(next_resolution([3840, 2160]), [1280, 720]);
+        // custom resolutions continue from the closest bigger one
+        assert_eq!(next_resolution([1366, 768]), [1600, 900]);
+    }
+
+    #[test]
+    fn test_render_size() {
+        let window = UVec2::new(1920, 1080);
+        assert_eq!(render_size(window, 1.0), window);
+        assert_eq!(render_size(window, 0.5), UVec2::new(960, 540));
+        // minimized window
+        assert_eq!(render_size(UVec2::ZERO, 0.7), UVec2::ONE);
+    }
+
+    #[test]
+    fn test_settings_serialization() {
+        let settings = DisplaySettings {
+            mode: DisplayMode::Exclusive,
+            vsync: false,
+            resolution: [2560, 1440],
+            render_scale: 0.7,
+            physics_interpolation: true,
+            light_distance: 500.0,
+            effect_distance: 1000.0,
+            shadows: ShadowQuality::High,
+            bloom_intensity: 0.5,
+            post_effects: PostEffectSettings {
+                vignette: true,
+                ..PostEffectSettings::default()
+            },
+        };
+        let json = serde_json::to_string(&settings).unwrap();
+        assert_eq!(
+            serde_json::from_str::<DisplaySettings>(&json).unwrap(),
+            settings
+        );
+
+        let settings: DisplaySettings = serde_json::from_str(r#"{"vsync":false}"#).unwrap();
+        assert_eq!(settings.mode, DisplayMode::Windowed);
+        assert_eq!(settings.render_scale, 1.0);
+        assert!(!settings.physics_interpolation);
+        assert_eq!(settings.effect_distance, 3000.0);
+        assert_eq!(settings.shadows, ShadowQuality::Medium);
+        assert_eq!(settings.bloom_intensity, 0.3);
+        assert!(!settings.post_effects.any());
+    }
+}
+
+/// Quad with the 3D scene upscaled to the window and post-processed, when it's rendered offscreen
+#[derive(Component)]
+struct Upscaled {
+    texture: Handle<Image>,
+}
+
+/// 3D camera rendering to its own texture, like a picture-in-picture view. The render scale
+/// doesn't retarget it, and systems looking for the main view, like culling, skip it.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct SecondaryCamera;
+
+/// Texture for a camera to render to, and to be shown on the screen afterwards
+pub(crate) fn render_texture(size: UVec2) -> Image {
+    let size = Extent3d {
+        width: size.x,
+        height: size.y,
+        ..default()
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: None,
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+        },
+        ..default()
+    };
+    image.resize(size);
+    image
+}
+
+fn setup_upscaling(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<PostProcessMaterial>>,
+) {
+    // Resized to the render scale once the window is known
+    let image = render_texture(UVec2::ONE);
+
+    // Drawn over 3D cameras, so it also renders the HUD at the full window resolution
+    commands
+        .spawn(Camera2dBundle {
+            camera: Camera {
+                priority: 1,
+                ..default()
+            },
+            camera_2d: Camera2d {
+                clear_color: ClearColorConfig::None,
+            },
+            ..default()
+        })
+        .insert(Name::new("Upscaling Camera"));
+    let texture = images.add(image);
+    commands
+        .spawn(MaterialMesh2dBundle {
+            // Unit quad, scaled to the window size
+            mesh: Mesh2dHandle(meshes.add(Mesh::from(shape::Quad::new(Vec2::ONE)))),
+            material: materials.add(PostProcessMaterial::new(texture.clone())),
+            visibility: Visibility::INVISIBLE,
+            ..default()
+        })
+        .insert(Upscaled { texture })
+        .insert(Name::new("Upscaled Scene"));
+}
+
+/// HUD is rendered by the upscaling camera only.
+fn hide_hud_on_3d_cameras(mut commands: Commands, cameras: Query<Entity, Added<Camera3d>>) {
+    for camera in cameras.iter() {
+        commands
+            .entity(camera)
+            .insert(UiCameraConfig { show_ui: false });
+    }
+}
+
+fn apply_render_scale(
+    settings: Res<Settings>,
+    windows: Res<Windows>,
+    mut ev_resized: EventReader<WindowResized>,
+    mut images: ResMut<Assets<Image>>,
+    mut cameras: Query<&mut Camera, (With<Camera3d>, Without<SecondaryCamera>)>,
+    added: Query<(), Added<Camera3d>>,
+    mut upscaled: Query<(&Upscaled, &mut Transform, &mut Visibility)>,
+) {
+    let resized = ev_resized.iter().count() > 0;
+    if !settings.is_changed() && !resized && added.is_empty() {
+        return;
+    }
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+    let Ok((Upscaled { texture }, mut transform, mut visibility)) = upscaled.get_single_mut()
+    else {
+        return;
+    };
+
+    let offscreen = settings.display.offscreen();
+    let target = if offscreen {
+        let physical = UVec2::new(window.physical_width(), window.physical_height());
+        let size = render_size(physical, settings.display.render_scale);
+        if let Some(image) = images.get_mut(texture) {
+            if image.size() != size.as_vec2() {
+                image.resize(Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    ..default()
+                });
+            }
+        }
+        RenderTarget::Image(texture.clone())
+    } else {
+        RenderTarget::Window(WindowId::primary())
+    };
+    transform.scale = Vec3::new(window.width(), window.height(), 1.0);
+    visibility.is_visible = offscreen;
+
+    for mut camera in cameras.iter_mut() {
+        if camera.target != target {
+            camera.target = target.clone();
+        }
+    }
+}
+
+/// Applies window mode, vsync and resolution when they are changed and saves them.
+fn apply_window_settings(
+    settings: Res<Settings>,
+    mut windows: ResMut<Windows>,
+    mut ev_resized: EventReader<WindowResized>,
+    mut applied: Local<Option<DisplaySettings>>,
+    mut pending_fullscreen: Local<bool>,
+) {
+    let Some(window) = windows.get_primary_mut() else {
+        return;
+    };
+    // Exclusive fullscreen picks the video mode closest to the window size,
+    // so the window is resized first and goes fullscreen afterwards
+    if ev_resized.iter().count() > 0 && *pending_fullscreen {
+        *pending_fullscreen = false;
+        window.set_mode(DisplayMode::Exclusive.window_mode());
+    }
+
+    let display = &settings.display;
+    if applied.as_ref() == Some(display) {
+        return;
+    }
+
+    let previous = applied.as_ref();
+    if previous.map(|p| p.vsync) != Some(display.vsync) {
+        window.set_present_mode(display.present_mode());
+    }
+    if previous.map(|p| (p.mode, p.resolution)) != Some((display.mode, display.resolution)) {
+        let [width, height] = display.resolution;
+        let scale_factor = window.scale_factor() as f32;
+        let resized = window.physical_width() != width || window.physical_height() != height;
+        if display.mode != DisplayMode::Borderless {
+            window.set_resolution(width as f32 / scale_factor, height as f32 / scale_factor);
+        }
+        *pending_fullscreen = display.mode == DisplayMode::Exclusive && resized;
+        if *pending_fullscreen {
+            window.set_mode(WindowMode::Windowed);
+        } else {
+            window.set_mode(display.mode.window_mode());
+        }
+    }
+
+    // The first settings are loaded from the file, so there is nothing new to save
+    if applied.is_some() {
+        if let Err(err) = display.save(DISPLAY_PATH) {
+            error!("Failed to save display settings to {DISPLAY_PATH}: {err}");
+        }
+    }
+    *applied = Some(display.clone());
+}
+
+/// 3D cameras are switched to HDR with bloom, or back to LDR when bloom is disabled
+fn apply_bloom(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut cameras: Query<(Entity, &mut Camera, Option<&mut BloomSettings>), With<Camera3d>>,
+    added: Query<(), Added<Camera3d>>,
+) {
+    if !settings.is_changed() && added.is_empty() {
+        return;
+    }
+
+    let intensity = settings.display.bloom_intensity;
+    let hdr = intensity > 0.0;
+    for (entity, mut camera, bloom) in cameras.iter_mut() {
+        if camera.hdr != hdr {
+            camera.hdr = hdr;
+        }
+        match bloom {
+            Some(mut bloom) if hdr => {
+                if bloom.intensity != intensity {
+                    bloom.intensity = intensity;
+                }
+            }
+            Some(_) => {
+                commands.entity(entity).remove::<BloomSettings>();
+            }
+            None if hdr => {
+                commands.entity(entity).insert(BloomSettings {
+                    intensity,
+                    ..default()
+                });
+            }
+            None => {}
+        }
+    }
+}
+
+fn apply_physics_settings(settings: Res<Settings>, mut config: ResMut<RapierConfiguration>) {
+    if settings.is_changed() {
+        config.timestep_mode = settings.display.timestep_mode();
+    }
+}
+
+/// Dynamic bodies are interpolated only with `TransformInterpolation`, which is ignored by other
+/// timestep modes. Kinematic ones are moved by their transforms, so they are left as is.
+fn interpolate_bodies(
+    mut commands: Commands,
+    bodies: Query<(Entity, &RigidBody), (Added<RigidBody>, Without<TransformInterpolation>)>,
+) {
+    for (entity, body) in bodies.iter() {
+        if matches!(body, RigidBody::Dynamic) {
+            commands
+                .entity(entity)
+                .insert(TransformInterpolation::default());
+        }
+    }
+}
+
+fn display_controls(keys: Res<Input<KeyCode>>, mut settings: ResMut<Settings>) {
+    if keys.just_pressed(KeyCode::F9) {
+        settings.display.resolution = next_resolution(settings.display.resolution);
+        info!("Resolution: {:?}", settings.display.resolution);
+    }
+    if keys.just_pressed(KeyCode::F10) {
+        settings.display.vsync = !settings.display.vsync;
+        info!("VSync: {}", settings.display.vsync);
+    }
+    if keys.just_pressed(KeyCode::F11) {
+        settings.display.mode = settings.display.mode.next();
+        info!("Display mode: {:?}", settings.display.mode);
+    }
+    if keys.just_pressed(KeyCode::End) {
+        settings.display.shadows = settings.display.shadows.next();
+        info!("Shadows: {:?}", settings.display.shadows);
+    }
+    if keys.just_pressed(KeyCode::Home) {
+        settings.display.physics_interpolation = !settings.display.physics_interpolation;
+        info!(
+            "Physics interpolation: {}",
+            settings.display.physics_interpolation
+        );
+    }
+    if keys.just_pressed(KeyCode::Insert) {
+        let intensity = settings.display.bloom_intensity + BLOOM_STEP;
+        settings.display.bloom_intensity = intensity.min(MAX_BLOOM_INTENSITY);
+        info!("Bloom intensity: {:.1}", settings.display.bloom_intensity);
+    }
+    if keys.just_pressed(KeyCode::Delete) {
+        let intensity = settings.display.bloom_intensity - BLOOM_STEP;
+        // snapped to 0 despite rounding errors, so bloom and HDR are actually disabled
+        settings.display.bloom_intensity = if intensity < BLOOM_STEP / 2.0 {
+            0.0
+        } else {
+            intensity
+        };
+        info!("Bloom intensity: {:.1}", settings.display.bloom_intensity);
+    }
+    if keys.just_pressed(KeyCode::PageUp) {
+        let scale = settings.display.render_scale + RENDER_SCALE_STEP;
+        settings.display.render_scale = scale.min(1.0);
+        info!("Render scale: {:.1}", settings.display.render_scale);
+    }
+    if keys.just_pressed(KeyCode::PageDown) {
+        let scale = settings.display.render_scale - RENDER_SCALE_STEP;
+        settings.display.render_scale = scale.max(MIN_RENDER_SCALE);
+        info!("Render scale: {:.1}", settings.display.render_scale);
+    }
+}
+
+/// Window mode, vsync, resolution, render scale, physics interpolation, shadow quality, bloom and
+/// post effects from `Settings::display`, applied at runtime and saved to a file in the working
+/// directory.
+/// Expects the `RapierPhysicsPlugin` to be added.
+pub struct DisplayPlugin {
+    settings: DisplaySettings,
+}
+
+impl DisplayPlugin {
+    /// Loads display settings saved by the previous session
+    pub fn load() -> Self {
+        Self {
+            settings: DisplaySettings::load(DISPLAY_PATH),
+        }
+    }
+
+    /// Window plugin creating the window with the loaded settings right away
+    pub fn window_plugin(&self) -> WindowPlugin {
+        let [width, height] = self.settings.resolution;
+        WindowPlugin {
+            window: WindowDescriptor {
+                width: width as f32,
+                height: height as f32,
+                mode: self.settings.mode.window_mode(),
+                present_mode: self.settings.present_mode(),
+                ..default()
+            },
+            ..default()
+        }
+    }
+}
+
+impl Plugin for DisplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Settings>();
+        app.world.resource_mut::<Settings>().display = self.settings.clone();
+        if !app.is_plugin_added::<PostProcessPlugin>() {
+            app.add_plugin(PostProcessPlugin);
+        }
+        app.add_startup_system(setup_upscaling)
+            .add_system(hide_hud_on_3d_cameras)
+            .add_system(apply_render_scale)
+            .add_system(apply_bloom)
+            .add_system(apply_window_settings)
+            .add_system(apply_physics_settings)
+            .add_system(interpolate_bodies)
+            .add_system(display_controls);
+    }
+}
@@ -1,47 +1,213 @@
-use bevy::{prelude::*, scene::SceneInstance};
+use bevy::{ecs::system::SystemParam, prelude::*};
 use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
 use std::ops::{Index, IndexMut};
 
-use crate::{aiming, collider_setup, gun, projectile, scene_setup, weapon};
+use crate::{
+    activity::Dormant,
+    aiming,
+    bubble_shield::ShieldProjector,
+    callsign::Callsigns,
+    collider_setup, components,
+    evasion::{self, Evasion, EvasionPlugin, ThreatWarning},
+    gun,
+    modifier::Modifiers,
+    navigation::{NavPath, NavigationPlugin},
+    player::{Player, TargetQueue},
+    prefab::{
+        ExtraHardpoints, Hardpoint, MaxRotationSpeed, MaxThrust, PrefabPlugin, ShipBundle,
+        ShipPrefab,
+    },
+    projectile::{DamageEvent, DamageSystems, Shield},
+    shield_effect::ShieldEffect,
+    ship_switch,
+    thruster::{self, FlightControl},
+    veterancy::{Veterancy, VeterancyPlugin},
+};
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(Component, Deserialize, Copy, Clone, PartialEq, Eq, Debug, Default, Reflect)]
+#[reflect(Component)]
+#[serde(rename_all = "snake_case")]
 pub enum Drone {
     /// Drone with 3 guns
+    #[default]
     Praetor,
     /// Drone with 2 guns
     Infiltrator,
+    /// Support drone with a gun, covering drones around with a bubble shield
+    Warden,
+}
+
+impl Drone {
+    /// Name shared by all drones of the type, unlike their callsigns
+    pub fn class_name(self) -> &'static str {
+        match self {
+            Drone::Praetor => "Drone::Praetor",
+            Drone::Infiltrator => "Drone::Infiltrator",
+            Drone::Warden => "Drone::Warden",
+        }
+    }
 }
 
 /// Emit this event to spawn a drone with specified parameters
 pub struct SpawnDroneEvent {
     pub drone: Drone,
+    pub fraction: components::Fraction,
     pub transform: Transform,
+    /// Overrides the prefab of the `drone` type
+    pub prefab: Option<Handle<ShipPrefab>>,
+    /// Drone arrives at this speed along the `transform` forward, and slows down after a brief
+    /// entry cruise, in m/s. Zero for drones appearing in place.
+    pub entry_speed: f32,
+    /// Stat modifiers on top of the prefab, like tougher drones of later waves
+    pub modifiers: Modifiers,
+    /// Weapons mounted on top of the prefab ones
+    pub hardpoints: Vec<Hardpoint>,
 }
 
-#[derive(Bundle, Clone, Default)]
-struct DroneBundle {
-    scene: Handle<Scene>,
-    name: Name,
-    hitpoints: projectile::HitPoints,
-    rotation_speed: MaxRotationSpeed,
+/// Attitude thrusters close the gap to the commanded spin in about this time, in seconds
+const ATTITUDE_RESPONSE: f32 = 0.25;
+
+/// Angular velocity the drone is turned at by its thrusters, commanded on gun layer updates
+#[derive(Component, Default)]
+struct Spin(Vec3);
+
+/// Components of the drone AI flying the ship, inserted along with the `Drone` type. Ships the
+/// player leaves get them too, so the AI takes over.
+#[derive(Bundle, Default)]
+pub struct DronePilot {
+    nav_path: NavPath,
+    mass: ReadMassProperties,
+    spin: Spin,
+    control: FlightControl,
+    force: ExternalForce,
+    thrusters: thruster::Thrusters,
+    afterburner: thruster::Afterburner,
+    maneuver: Maneuver,
+    evasion: Evasion,
+    threat_warning: ThreatWarning,
 }
 
+/// Duration of the entry cruise for drones arriving at high speed, in seconds
+const ENTRY_CRUISE_TIME: f32 = 2.0;
+/// Drones slow down to this speed at the end of the entry cruise, in m/s
+const ENTRY_EXIT_SPEED: f32 = 40.0;
+
+/// Drone arriving at high speed, e.g. jumping in through a gate, drops to `ENTRY_EXIT_SPEED`
+/// once the time is out.
 #[derive(Component)]
-struct Guns(Vec<Entity>);
+struct EntryCruise {
+    time_left: f32,
+}
+
+/// Runtime tunables of the drone AI.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct DroneTuning {
+    /// Multiplier for the main engine thrust of all drones
+    pub thrust_scale: f32,
+    /// Drones approach the target until this distance, in meters
+    pub approach_distance: f32,
+    /// When drones open fire, applied to all of them
+    pub fire_control: aiming::FireControl,
+    /// Drones closing in after spawning burn the afterburner until this distance, in meters
+    pub boost_distance: f32,
+    /// Drones break off once their hit points drop below this part of the maximum
+    pub break_off_health: f32,
+    /// Drones breaking off get this far from the target before returning, in meters
+    pub break_off_distance: f32,
+    /// Drones dodge homing rockets closer than this, in meters
+    pub dodge_distance: f32,
+}
+
+impl Default for DroneTuning {
+    fn default() -> Self {
+        Self {
+            thrust_scale: 1.0,
+            approach_distance: 100.0,
+            fire_control: aiming::FireControl::default(),
+            boost_distance: 400.0,
+            break_off_health: 0.25,
+            break_off_distance: 600.0,
+            dodge_distance: 150.0,
+        }
+    }
+}
+
+/// What the drone is up to, it decides when the drone burns its afterburner.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug, Default)]
+enum Maneuver {
+    /// Just spawned, boosting towards the target while it's far
+    #[default]
+    Closing,
+    Engaging,
+    /// Badly damaged, boosting away from the target
+    BreakingOff,
+    /// Back in the fight after breaking off, the drone doesn't break off again
+    Regrouped,
+}
+
+impl Maneuver {
+    /// Next maneuver of the drone `distance` away from its target, zero without one, with `health`
+    /// from 0 to 1
+    fn next(self, tuning: &DroneTuning, distance: f32, health: f32) -> Self {
+        match self {
+            Self::Closing | Self::Engaging if health < tuning.break_off_health => Self::BreakingOff,
+            Self::Closing if distance > 0.0 && distance <= tuning.boost_distance => Self::Engaging,
+            Self::BreakingOff if distance == 0.0 || distance > tuning.break_off_distance => {
+                Self::Regrouped
+            }
+            maneuver => maneuver,
+        }
+    }
 
-/// Angular velocity limit
-#[derive(Component, Clone, Default)]
-struct MaxRotationSpeed(f32);
+    /// Whether the drone burns its afterburner `distance` away from its target
+    fn boost(self, tuning: &DroneTuning, distance: f32) -> bool {
+        match self {
+            Self::Closing => distance > tuning.boost_distance,
+            Self::BreakingOff => true,
+            Self::Engaging | Self::Regrouped => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DroneTuning, Maneuver};
+
+    #[test]
+    fn test_maneuver() {
+        let tuning = DroneTuning::default();
+        let far = tuning.boost_distance + 100.0;
+        let maneuver = Maneuver::Closing;
+        assert!(maneuver.boost(&tuning, far));
+        // no target yet
+        assert_eq!(maneuver.next(&tuning, 0.0, 1.0), Maneuver::Closing);
+        assert!(!maneuver.boost(&tuning, 0.0));
+
+        let maneuver = maneuver.next(&tuning, tuning.boost_distance, 1.0);
+        assert_eq!(maneuver, Maneuver::Engaging);
+        assert!(!maneuver.boost(&tuning, far));
+
+        let maneuver = maneuver.next(&tuning, 50.0, tuning.break_off_health / 2.0);
+        assert_eq!(maneuver, Maneuver::BreakingOff);
+        assert!(maneuver.boost(&tuning, 50.0));
+        let maneuver = maneuver.next(&tuning, tuning.break_off_distance + 1.0, 0.1);
+        assert_eq!(maneuver, Maneuver::Regrouped);
+        // breaks off only once
+        assert_eq!(maneuver.next(&tuning, 50.0, 0.1), Maneuver::Regrouped);
+    }
+}
 
 #[derive(Resource, Default)]
-struct DroneResources([DroneBundle; 2]);
+struct DroneResources([Handle<ShipPrefab>; 3]);
 
 impl Index<Drone> for DroneResources {
-    type Output = DroneBundle;
+    type Output = Handle<ShipPrefab>;
     fn index(&self, index: Drone) -> &Self::Output {
         match index {
             Drone::Praetor => &self.0[0],
             Drone::Infiltrator => &self.0[1],
+            Drone::Warden => &self.0[2],
         }
     }
 }
@@ -51,130 +217,505 @@ impl IndexMut<Drone> for DroneResources {
         match index {
             Drone::Praetor => &mut self.0[0],
             Drone::Infiltrator => &mut self.0[1],
+            Drone::Warden => &mut self.0[2],
         }
     }
 }
 
+/// Drones of every type built at startup, so that battles don't stall on instancing and setting up
+/// their models. The pool grows on demand beyond it.
+const POOL_SIZE: usize = 8;
+/// Idle drones of the pool wait here, far out of any sensor range
+const PARKING: Vec3 = Vec3::new(0.0, -100_000.0, 0.0);
+
+/// Type of the drone with the default prefab, returned to the pool once destroyed along with
+/// `components::Pooled`.
+#[derive(Component)]
+struct PooledDrone(Drone);
+
+/// Idle drone of the pool, hidden and without colliders, hit points or any role components.
+#[derive(Component)]
+struct Parked;
+
+/// Collider of a parked drone or of its part, restored once the drone is taken from the pool
+#[derive(Component)]
+struct StashedCollider(Collider);
+
+/// Idle drones to take instead of spawning new ones
+#[derive(SystemParam)]
+struct DronePool<'w, 's> {
+    parked:
+        Query<'w, 's, (Entity, &'static PooledDrone, &'static Handle<ShipPrefab>), With<Parked>>,
+    stashed: Query<'w, 's, &'static StashedCollider>,
+    children: Query<'w, 's, &'static Children>,
+    prefabs: Res<'w, Assets<ShipPrefab>>,
+}
+
+impl DronePool<'_, '_> {
+    /// Idle drone of the type reset to the `transform`, skipping the `taken` ones
+    fn take(
+        &self,
+        commands: &mut Commands,
+        drone: Drone,
+        transform: Transform,
+        taken: &mut Vec<Entity>,
+    ) -> Option<Entity> {
+        let (entity, _, prefab) = self
+            .parked
+            .iter()
+            .find(|&(entity, pooled, _)| pooled.0 == drone && !taken.contains(&entity))?;
+        taken.push(entity);
+
+        for part in std::iter::once(entity).chain(self.children.iter_descendants(entity)) {
+            if let Ok(StashedCollider(collider)) = self.stashed.get(part) {
+                commands
+                    .entity(part)
+                    .remove::<StashedCollider>()
+                    .insert(collider.clone());
+            }
+        }
+        let mut ship = commands.entity(entity);
+        ship.remove::<Parked>()
+            .insert(transform)
+            .insert(Visibility::VISIBLE);
+        // Unbuilt drones get their stats once the prefab is loaded
+        if let Some(prefab) = self.prefabs.get(prefab) {
+            prefab.insert_stats(&mut ship);
+        }
+        Some(entity)
+    }
+}
+
 fn load_drone_resources(mut commands: Commands, assets: Res<AssetServer>) {
     let mut resources = DroneResources::default();
-    resources[Drone::Praetor] = DroneBundle {
-        scene: assets.load("models/praetor.glb#Scene0"),
-        name: Name::new("Drone::Praetor"),
-        hitpoints: projectile::HitPoints::new(300),
-        rotation_speed: MaxRotationSpeed(60_f32.to_radians()),
-    };
-    resources[Drone::Infiltrator] = DroneBundle {
-        scene: assets.load("models/infiltrator.glb#Scene0"),
-        name: Name::new("Drone::Infiltrator"),
-        hitpoints: projectile::HitPoints::new(200),
-        rotation_speed: MaxRotationSpeed(90_f32.to_radians()),
-    };
+    resources[Drone::Praetor] = assets.load("ships/praetor.ship.ron");
+    resources[Drone::Infiltrator] = assets.load("ships/infiltrator.ship.ron");
+    resources[Drone::Warden] = assets.load("ships/warden.ship.ron");
+
+    for drone in [Drone::Praetor, Drone::Infiltrator, Drone::Warden] {
+        for _ in 0..POOL_SIZE {
+            let mut ship = ShipBundle::new(
+                resources[drone].clone(),
+                Transform::from_translation(PARKING),
+            );
+            ship.spatial.visibility = Visibility::INVISIBLE;
+            commands
+                .spawn(ship)
+                .insert(PooledDrone(drone))
+                .insert(components::Pooled)
+                .insert(Parked)
+                .insert(Name::new(drone.class_name()));
+        }
+    }
     commands.insert_resource(resources);
 }
 
+// Hit points, guns, thrusters and the gun layer come from the prefab once it's loaded
 fn spawn_drone(
     mut commands: Commands,
     resources: Res<DroneResources>,
+    tuning: Res<DroneTuning>,
+    mut callsigns: ResMut<Callsigns>,
+    pool: DronePool,
     mut ev_spawn_drone: EventReader<SpawnDroneEvent>,
 ) {
+    let mut taken = Vec::new();
     for ev in ev_spawn_drone.iter() {
-        commands
-            .spawn(resources[ev.drone].clone())
-            .insert(SpatialBundle::from_transform(ev.transform))
-            .insert(aiming::GunLayer::default())
-            .insert(aiming::Fraction::Drones)
+        // The pool holds drones with the prefab weapons only, so ones with extra weapons are new
+        let pooled = ev.prefab.is_none() && ev.hardpoints.is_empty();
+        let mut drone = match &ev.prefab {
+            Some(prefab) => commands.spawn(ShipBundle::new(prefab.clone(), ev.transform)),
+            None if !pooled => {
+                commands.spawn(ShipBundle::new(resources[ev.drone].clone(), ev.transform))
+            }
+            None => match pool.take(&mut commands, ev.drone, ev.transform, &mut taken) {
+                Some(entity) => commands.entity(entity),
+                None => commands.spawn(ShipBundle::new(resources[ev.drone].clone(), ev.transform)),
+            },
+        };
+        if pooled {
+            drone
+                .insert(PooledDrone(ev.drone))
+                .insert(components::Pooled);
+        }
+        if !ev.hardpoints.is_empty() {
+            drone.insert(ExtraHardpoints(ev.hardpoints.clone()));
+        }
+        if ev.entry_speed > 0.0 {
+            drone.insert(EntryCruise {
+                time_left: ENTRY_CRUISE_TIME,
+            });
+        }
+        drone
+            .insert(ev.drone)
+            .insert(Name::new(callsigns.next(ev.drone)))
+            .insert(tuning.fire_control.clone())
+            .insert(ev.modifiers.clone())
+            .insert(Veterancy::default())
+            .insert(ev.fraction)
+            .insert(ship_switch::Controllable)
             .insert(RigidBody::Dynamic)
-            .insert(Velocity::default())
-            .insert(ExternalForce {
-                force: Vec3::new(0.0, 0.0, 0.0),
-                torque: Vec3::ZERO,
-            })
-            .insert(scene_setup::SetupRequired::new(
-                move |commands, entities| {
-                    let root = entities.iter().find(|e| e.contains::<SceneInstance>());
-
-                    let collider_parts: Vec<_> = entities
-                        .iter()
-                        // Skip entities with `Handle<Mesh>` to operate only with GLTF's Nodes
-                        .filter(|e| !e.contains::<Handle<Mesh>>())
-                        .filter(
-                            |e| matches!(e.get::<Name>(), Some(name) if name.starts_with("body")),
-                        )
-                        .map(|entity| entity.id())
-                        .collect();
-
-                    // Assign guns to entities named "barrel"
-                    let guns: Vec<_> = entities
-                        .iter()
-                        // Skip entities with `Handle<Mesh>` to operate only with GLTF's Nodes
-                        .filter(|e| !e.contains::<Handle<Mesh>>())
-                        .filter(
-                            |e| matches!(e.get::<Name>(), Some(name) if name.starts_with("barrel")),
-                        )
-                        .map(|e| {
-                            commands.entity(e.id()).insert(weapon::MachineGun::new(5.0));
-                            e.id()
-                        })
-                        .collect();
-
-                    commands
-                        .entity(root.unwrap().id())
-                        .insert(collider_setup::ConvexHull::new(collider_parts))
-                        .insert(Guns(guns));
-                },
-            ));
-    }
-}
-
-fn orientation(mut drones: Query<(&aiming::GunLayer, &MaxRotationSpeed, &mut Velocity)>) {
-    for (gun_layer, max_rotation_speed, mut velocity) in drones.iter_mut() {
-        let speed = (gun_layer.angle * 100.0).clamp(-max_rotation_speed.0, max_rotation_speed.0);
-        velocity.angvel = gun_layer.axis * speed;
-    }
-}
-
-fn movement(mut drones: Query<(&aiming::GunLayer, &GlobalTransform, &mut ExternalForce)>) {
-    for (gun_layer, transform, mut force) in drones.iter_mut() {
+            .insert(Velocity::linear(ev.transform.forward() * ev.entry_speed))
+            .insert(DronePilot::default());
+    }
+}
+
+/// Returns destroyed drones to the pool. They are parked through commands like they'd be
+/// despawned, so the damage is handled with the drone still in place in the same frame.
+/// Parked drones are unlocked by the player and dropped by gun layers aiming at them.
+fn park_destroyed(
+    mut commands: Commands,
+    mut ev_damage: EventReader<DamageEvent>,
+    drones: Query<(), (With<components::Pooled>, Without<Parked>, Without<Player>)>,
+    children: Query<&Children>,
+    colliders: Query<&Collider>,
+    mut target_queues: Query<&mut TargetQueue>,
+    mut gun_layers: Query<&mut aiming::GunLayer>,
+) {
+    for ev in ev_damage.iter().filter(|ev| ev.destroyed) {
+        if !drones.contains(ev.target) {
+            continue;
+        }
+        for mut queue in target_queues.iter_mut() {
+            if queue.iter().any(|e| e == ev.target) {
+                queue.unlock(ev.target);
+            }
+        }
+        for mut gun_layer in gun_layers.iter_mut() {
+            if gun_layer.target() == Some(ev.target) {
+                gun_layer.forget(ev.target);
+            }
+        }
+        for part in std::iter::once(ev.target).chain(children.iter_descendants(ev.target)) {
+            if let Ok(collider) = colliders.get(part) {
+                commands
+                    .entity(part)
+                    .remove::<Collider>()
+                    .insert(StashedCollider(collider.clone()));
+            }
+        }
+        commands
+            .entity(ev.target)
+            .remove::<(
+                Drone,
+                components::Fraction,
+                ship_switch::Controllable,
+                RigidBody,
+                Velocity,
+                ExternalForce,
+                Sleeping,
+                Dormant,
+                FlightControl,
+                Spin,
+                thruster::Thrusters,
+                thruster::Afterburner,
+                EntryCruise,
+                Maneuver,
+                Evasion,
+            )>()
+            .remove::<(
+                ThreatWarning,
+                aiming::GunLayer,
+                aiming::FireControl,
+                Veterancy,
+                NavPath,
+                components::HitPoints,
+                Shield,
+                ShieldEffect,
+                ShieldProjector,
+            )>()
+            .insert(Parked)
+            .insert(Transform::from_translation(PARKING))
+            .insert(Visibility::INVISIBLE);
+    }
+}
+
+/// Parked drones don't take part in the battle even once their prefab and model are set up:
+/// colliders are stashed and the stats are removed
+fn keep_parked(
+    mut commands: Commands,
+    colliders: Query<(Entity, &Collider), Added<Collider>>,
+    parked: Query<
+        Entity,
+        (
+            With<Parked>,
+            Or<(With<components::HitPoints>, With<aiming::GunLayer>)>,
+        ),
+    >,
+    is_parked: Query<(), With<Parked>>,
+    parent_query: Query<&Parent>,
+) {
+    for (entity, collider) in colliders.iter() {
+        let mut ancestors = std::iter::once(entity).chain(parent_query.iter_ancestors(entity));
+        if ancestors.any(|e| is_parked.contains(e)) {
+            commands
+                .entity(entity)
+                .remove::<Collider>()
+                .insert(StashedCollider(collider.clone()));
+        }
+    }
+    for entity in parked.iter() {
+        commands.entity(entity).remove::<(
+            components::HitPoints,
+            Shield,
+            ShieldEffect,
+            ShieldProjector,
+            aiming::GunLayer,
+        )>();
+    }
+}
+
+/// Rotation axis and angle to turn a drone at `transform` towards the next waypoint of its
+/// `path`, or towards the target of its `gun_layer` once it's in direct sight. Drones breaking off
+/// turn away from the target.
+fn heading(
+    gun_layer: &aiming::GunLayer,
+    path: &NavPath,
+    transform: &GlobalTransform,
+    maneuver: Maneuver,
+) -> (Vec3, f32) {
+    let away = -gun_layer.direction();
+    if maneuver == Maneuver::BreakingOff && away != Vec3::ZERO {
+        return Quat::from_rotation_arc(transform.forward(), away).to_axis_angle();
+    }
+    match path.waypoint() {
+        Some(waypoint) => {
+            let direction = (waypoint - transform.translation()).normalize_or_zero();
+            Quat::from_rotation_arc(transform.forward(), direction).to_axis_angle()
+        }
+        None => (gun_layer.axis, gun_layer.angle),
+    }
+}
+
+// Drones are steered only once they have a collider, as rigid body without it has no mass.
+// Steering follows gun layer updates, the thrusters keep the spin in between.
+fn orientation(
+    settings: Res<aiming::AimingSettings>,
+    mut drones: Query<
+        (
+            &aiming::GunLayer,
+            &NavPath,
+            &GlobalTransform,
+            &MaxRotationSpeed,
+            &mut Spin,
+            Option<&Dormant>,
+            &Maneuver,
+        ),
+        With<collider_setup::PhysicsReady>,
+    >,
+) {
+    for (gun_layer, path, transform, max_rotation_speed, mut spin, dormant, &maneuver) in
+        drones.iter_mut()
+    {
+        if !gun_layer.updated() {
+            continue;
+        }
+        // Don't turn further than required until the next update
+        let max_speed_to_align = settings.update_interval(dormant.is_some()).recip();
+        let (axis, angle) = heading(gun_layer, path, transform, maneuver);
+        let max_speed = max_rotation_speed.0.min(angle.abs() * max_speed_to_align);
+        let speed = (angle * 100.0).clamp(-max_speed, max_speed);
+        spin.0 = axis * speed;
+    }
+}
+
+/// Torque the thrusters apply to reach the commanded spin in `ATTITUDE_RESPONSE`
+fn attitude(
+    mut drones: Query<
+        (
+            &Spin,
+            &Velocity,
+            &ReadMassProperties,
+            &GlobalTransform,
+            &mut FlightControl,
+        ),
+        With<collider_setup::PhysicsReady>,
+    >,
+) {
+    for (spin, velocity, mass, transform, mut control) in drones.iter_mut() {
+        let (_, rotation, _) = transform.to_scale_rotation_translation();
+        let frame = rotation * mass.0.principal_inertia_local_frame;
+        let acceleration = frame.inverse() * (spin.0 - velocity.angvel) / ATTITUDE_RESPONSE;
+        let torque = frame * (mass.0.principal_inertia * acceleration);
+        if control.torque != torque {
+            control.torque = torque;
+        }
+    }
+}
+
+fn movement(
+    tuning: Res<DroneTuning>,
+    mut drones: Query<
+        (
+            Entity,
+            &aiming::GunLayer,
+            &NavPath,
+            &GlobalTransform,
+            &MaxThrust,
+            &mut FlightControl,
+            &Maneuver,
+        ),
+        With<collider_setup::PhysicsReady>,
+    >,
+    children: Query<&Children>,
+    guns: Query<&gun::Gun>,
+) {
+    for (entity, gun_layer, path, transform, thrust, mut control, &maneuver) in drones.iter_mut() {
+        if !gun_layer.updated() {
+            continue;
+        }
         // no target - stop
         if gun_layer.distance == 0.0 {
-            force.force = Vec3::ZERO;
+            control.force = Vec3::ZERO;
         }
 
-        const THRUST: f32 = 3000.0;
+        // close in until all guns reach the target, as they hold fire beyond their range
+        let approach_distance = children
+            .iter_descendants(entity)
+            .filter_map(|e| guns.get(e).ok())
+            .map(gun::Gun::effective_range)
+            .fold(tuning.approach_distance, f32::min);
 
-        // if distance too big and we oriented towards our target - move forward
-        if gun_layer.distance > 100.0 && gun_layer.angle <= std::f32::consts::FRAC_PI_4 {
-            force.force = transform.forward() * THRUST;
+        // if on a detour, too far or breaking off, and oriented towards the way - move forward
+        let (_, angle) = heading(gun_layer, path, transform, maneuver);
+        let far = path.waypoint().is_some()
+            || gun_layer.distance > approach_distance
+            || maneuver == Maneuver::BreakingOff;
+        if far && angle <= std::f32::consts::FRAC_PI_4 {
+            control.force = transform.forward() * thrust.0 * tuning.thrust_scale;
         } else {
-            force.force = Vec3::ZERO;
+            control.force = Vec3::ZERO;
         }
     }
 }
 
-fn fire_control(drones: Query<(&aiming::GunLayer, &Guns)>, mut triggers: Query<&mut gun::Trigger>) {
-    for (gun_layer, guns) in drones.iter() {
-        // let's say for simplicity that target is 7m size
-        let threshold = (7.0 / gun_layer.distance).max(0.1);
-        let range = 3000.0;
+/// Drones burn the afterburner to close in after spawning and to break off once badly damaged.
+/// Rockets closing in, as told by the `ThreatWarning`, make the drone dodge across their path at
+/// full thrust with the afterburner.
+fn use_afterburners(
+    tuning: Res<DroneTuning>,
+    mut drones: Query<
+        (
+            &aiming::GunLayer,
+            &GlobalTransform,
+            &components::HitPoints,
+            &MaxThrust,
+            &ThreatWarning,
+            &mut Maneuver,
+            &mut FlightControl,
+        ),
+        With<collider_setup::PhysicsReady>,
+    >,
+) {
+    for (gun_layer, transform, hp, thrust, warning, mut maneuver, mut control) in drones.iter_mut()
+    {
+        let health = hp.percent() as f32 / 100.0;
+        let next = maneuver.next(&tuning, gun_layer.distance, health);
+        if *maneuver != next {
+            *maneuver = next;
+        }
 
-        if gun_layer.distance != 0.0 && gun_layer.angle < threshold && gun_layer.distance < range {
-            for gun in guns.0.iter() {
-                if let Ok(mut gun_trigger) = triggers.get_mut(*gun) {
-                    gun_trigger.pull();
-                }
+        let position = transform.translation();
+        let incoming = warning
+            .rocket
+            .filter(|rocket| rocket.position.distance(position) < tuning.dodge_distance);
+        if let Some(rocket) = incoming {
+            // Rockets outturn drones flying straight away, but overshoot ones crossing their path
+            control.force = rocket.dodge_direction(position) * thrust.0 * tuning.thrust_scale;
+        }
+        let boost = incoming.is_some() || maneuver.boost(&tuning, gun_layer.distance);
+        if control.boost != boost {
+            control.boost = boost;
+        }
+    }
+}
+
+/// Rockets of drones home on the target of their gun layer
+fn missile_guidance(
+    drones: Query<(Entity, &aiming::GunLayer), (With<Drone>, Changed<aiming::GunLayer>)>,
+    children: Query<&Children>,
+    mut launchers: Query<&mut gun::Guidance>,
+) {
+    for (entity, gun_layer) in drones.iter() {
+        let mut iter = launchers.iter_many_mut(children.iter_descendants(entity));
+        while let Some(mut guidance) = iter.fetch_next() {
+            if guidance.0 != gun_layer.target() {
+                guidance.0 = gun_layer.target();
             }
         }
     }
 }
 
+fn entry_cruise(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut drones: Query<(Entity, &mut EntryCruise, &mut Velocity)>,
+) {
+    for (entity, mut cruise, mut velocity) in drones.iter_mut() {
+        cruise.time_left -= time.delta_seconds();
+        if cruise.time_left <= 0.0 {
+            velocity.linvel = velocity.linvel.clamp_length_max(ENTRY_EXIT_SPEED);
+            commands.entity(entity).remove::<EntryCruise>();
+        }
+    }
+}
+
+fn apply_tuning(
+    tuning: Res<DroneTuning>,
+    mut drones: Query<&mut aiming::FireControl, With<Drone>>,
+) {
+    if !tuning.is_changed() {
+        return;
+    }
+    for mut fire_control in drones.iter_mut() {
+        *fire_control = tuning.fire_control.clone();
+    }
+}
+
+/// Drones spawned by `SpawnDroneEvent`, flying towards their targets around large obstacles and
+/// shooting at them.
 pub struct DronePlugin;
 impl Plugin for DronePlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(load_drone_resources)
+        if !app.is_plugin_added::<thruster::ThrusterPlugin>() {
+            app.add_plugin(thruster::ThrusterPlugin);
+        }
+        if !app.is_plugin_added::<NavigationPlugin>() {
+            app.add_plugin(NavigationPlugin);
+        }
+        if !app.is_plugin_added::<VeterancyPlugin>() {
+            app.add_plugin(VeterancyPlugin);
+        }
+        if !app.is_plugin_added::<PrefabPlugin>() {
+            app.add_plugin(PrefabPlugin);
+        }
+        if !app.is_plugin_added::<EvasionPlugin>() {
+            app.add_plugin(EvasionPlugin);
+        }
+        app.init_resource::<DroneTuning>()
+            .init_resource::<Callsigns>()
+            .register_type::<Drone>()
+            .add_startup_system(load_drone_resources)
             .add_event::<SpawnDroneEvent>()
+            .add_event::<DamageEvent>()
             .add_system(spawn_drone)
+            .add_system(park_destroyed.after(DamageSystems))
+            .add_system(keep_parked.before(spawn_drone))
+            .add_system(entry_cruise)
+            .add_system(missile_guidance.after(aiming::gun_layer))
             .add_system(orientation.after(aiming::gun_layer))
-            .add_system(movement.after(aiming::gun_layer))
-            .add_system(fire_control);
+            .add_system(attitude.after(orientation).before(thruster::flight_control))
+            .add_system(
+                movement
+                    .after(aiming::gun_layer)
+                    .before(thruster::flight_control),
+            )
+            .add_system(
+                use_afterburners
+                    .after(movement)
+                    .after(evasion::warn_of_rockets)
+                    .before(thruster::burn_afterburners),
+            )
+            .add_system(apply_tuning.before(aiming::fire_control));
     }
 }
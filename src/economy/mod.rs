@@ -0,0 +1,324 @@
+//! Credits earned in battle and spent in the shop between waves on rockets, hull repairs and
+//! upgrades, which are just sets of `Modifier`s added to the player's ship and weapons.
+
+use bevy::prelude::*;
+
+use crate::{
+    components::HitPoints,
+    drone::Drone,
+    gun::Ammo,
+    modifier::{Modifier, Modifiers, Op, Stat},
+    player::{Player, PrimaryWeapon, SecondaryWeapon},
+    projectile::{DamageEvent, DamageSystems},
+    salvage::SalvageCollectedEvent,
+};
+
+mod shop;
+
+/// Credits for every drone destroyed by the player
+const KILL_REWARD: u32 = 50;
+/// Hit points restored by a single hull repair
+const REPAIR_AMOUNT: u32 = 100;
+const MAX_UPGRADE_LEVEL: u32 = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Item {
+    /// Refills the rocket launchers
+    Rockets,
+    /// Restores `REPAIR_AMOUNT` hit points
+    HullRepair,
+    /// Primary weapons fire faster
+    RateOfFire,
+    /// Primary weapons fire faster projectiles, which reach farther
+    Range,
+    /// Primary weapons heat up slower
+    Cooling,
+    /// All weapons of the ship deal more damage
+    Warheads,
+}
+
+impl Item {
+    pub const ALL: [Item; 6] = [
+        Item::Rockets,
+        Item::HullRepair,
+        Item::RateOfFire,
+        Item::Range,
+        Item::Cooling,
+        Item::Warheads,
+    ];
+
+    /// Localization key of the item name
+    pub fn key(self) -> &'static str {
+        match self {
+            Item::Rockets => "shop-rockets",
+            Item::HullRepair => "shop-hull-repair",
+            Item::RateOfFire => "shop-rate-of-fire",
+            Item::Range => "shop-range",
+            Item::Cooling => "shop-cooling",
+            Item::Warheads => "shop-warheads",
+        }
+    }
+
+    /// Modifiers added by every level of an upgrade, empty for consumables
+    pub fn modifiers(self) -> &'static [Modifier] {
+        const fn percent(stat: Stat, percent: f32) -> Modifier {
+            Modifier {
+                stat,
+                op: Op::Percent(percent),
+            }
+        }
+        const RATE_OF_FIRE: [Modifier; 1] = [percent(Stat::RateOfFire, 15.0)];
+        const RANGE: [Modifier; 2] = [
+            percent(Stat::ProjectileSpeed, 15.0),
+            percent(Stat::EffectiveRange, 15.0),
+        ];
+        const COOLING: [Modifier; 1] = [percent(Stat::HeatPerShot, -15.0)];
+        const WARHEADS: [Modifier; 1] = [percent(Stat::Damage, 20.0)];
+
+        match self {
+            Item::Rockets | Item::HullRepair => &[],
+            Item::RateOfFire => &RATE_OF_FIRE,
+            Item::Range => &RANGE,
+            Item::Cooling => &COOLING,
+            Item::Warheads => &WARHEADS,
+        }
+    }
+
+    /// Price of the consumable or the first upgrade level
+    fn base_price(self) -> u32 {
+        match self {
+            Item::Rockets => 100,
+            Item::HullRepair => 150,
+            Item::RateOfFire => 300,
+            Item::Range => 250,
+            Item::Cooling => 200,
+            Item::Warheads => 400,
+        }
+    }
+}
+
+/// Credits of the player and upgrades bought so far.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct Inventory {
+    pub credits: u32,
+    /// Every upgrade level bought, in order of purchase
+    upgrades: Vec<Item>,
+}
+
+impl Inventory {
+    /// Upgrade level bought so far, `None` for consumables
+    pub fn level(&self, item: Item) -> Option<u32> {
+        if item.modifiers().is_empty() {
+            return None;
+        }
+        Some(self.upgrades.iter().filter(|&&i| i == item).count() as u32)
+    }
+
+    /// Price of the next purchase, upgrades get pricier with every level.
+    /// `None` once the upgrade is at its maximum level.
+    pub fn price(&self, item: Item) -> Option<u32> {
+        match self.level(item) {
+            Some(level) if level >= MAX_UPGRADE_LEVEL => None,
+            level => Some(item.base_price() * (level.unwrap_or(0) + 1)),
+        }
+    }
+
+    /// Spends credits on the `item`, returns false if it's unaffordable or unavailable
+    fn buy(&mut self, item: Item) -> bool {
+        let Some(price) = self.price(item).filter(|&price| price <= self.credits) else {
+            return false;
+        };
+        self.credits -= price;
+        if self.level(item).is_some() {
+            self.upgrades.push(item);
+        }
+        true
+    }
+
+    /// Modifiers of the upgrades bought after the first `fitted` ones. Damage upgrades go to the
+    /// `ship` itself, so they apply to all its weapons, the rest go to its primary weapons.
+    fn upgrade_modifiers(&self, fitted: usize, ship: bool) -> impl Iterator<Item = Modifier> + '_ {
+        self.upgrades[fitted.min(self.upgrades.len())..]
+            .iter()
+            .flat_map(|item| item.modifiers().iter().copied())
+            .filter(move |modifier| (modifier.stat == Stat::Damage) == ship)
+    }
+}
+
+/// Number of `Inventory` upgrades already added to the `Modifiers` of a ship or a gun. They are
+/// kept once the player leaves the ship, so they aren't added twice if the player comes back.
+#[derive(Component)]
+struct FittedUpgrades(usize);
+
+/// Emitted by the shop when the player picks an `Item`
+pub struct PurchaseEvent(pub Item);
+
+#[cfg(test)]
+mod tests {
+    use super::{Inventory, Item, MAX_UPGRADE_LEVEL};
+
+    #[test]
+    fn test_buy() {
+        let mut inventory = Inventory {
+            credits: 500,
+            ..Default::default()
+        };
+        assert!(inventory.buy(Item::HullRepair));
+        assert_eq!(inventory.credits, 350);
+        assert!(inventory.buy(Item::Range));
+        // not enough credits left
+        assert!(!inventory.buy(Item::HullRepair));
+        assert_eq!(inventory.credits, 100);
+        assert_eq!(inventory.level(Item::Range), Some(1));
+        assert_eq!(inventory.level(Item::Rockets), None);
+    }
+
+    #[test]
+    fn test_upgrade_prices() {
+        let mut inventory = Inventory {
+            credits: 10_000,
+            ..Default::default()
+        };
+        assert_eq!(inventory.price(Item::RateOfFire), Some(300));
+        assert!(inventory.buy(Item::RateOfFire));
+        assert_eq!(inventory.price(Item::RateOfFire), Some(600));
+        for _ in 1..MAX_UPGRADE_LEVEL {
+            assert!(inventory.buy(Item::RateOfFire));
+        }
+        // maxed out
+        assert_eq!(inventory.price(Item::RateOfFire), None);
+        assert!(!inventory.buy(Item::RateOfFire));
+        assert_eq!(inventory.credits, 10_000 - 300 - 600 - 900);
+        // other upgrades are priced separately
+        assert_eq!(inventory.price(Item::Cooling), Some(200));
+    }
+
+    #[test]
+    fn test_upgrade_modifiers() {
+        let mut inventory = Inventory {
+            credits: 10_000,
+            ..Default::default()
+        };
+        assert!(inventory.buy(Item::Warheads));
+        assert!(inventory.buy(Item::Rockets));
+        assert!(inventory.buy(Item::Cooling));
+
+        let ship: Vec<_> = inventory.upgrade_modifiers(0, true).collect();
+        assert_eq!(ship, Item::Warheads.modifiers());
+        let weapons: Vec<_> = inventory.upgrade_modifiers(0, false).collect();
+        assert_eq!(weapons, Item::Cooling.modifiers());
+        // only the upgrades bought since the last fitting
+        assert_eq!(inventory.upgrade_modifiers(1, true).count(), 0);
+        assert_eq!(inventory.upgrade_modifiers(1, false).count(), 1);
+        assert_eq!(inventory.upgrade_modifiers(2, false).count(), 0);
+    }
+}
+
+fn earn_credits(
+    mut inventory: ResMut<Inventory>,
+    mut ev_damage: EventReader<DamageEvent>,
+    mut ev_salvage: EventReader<SalvageCollectedEvent>,
+    player: Query<Entity, With<Player>>,
+    drones: Query<(), With<Drone>>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    let kills = ev_damage
+        .iter()
+        .filter(|ev| ev.destroyed && ev.attacker == Some(player) && drones.contains(ev.target))
+        .count() as u32;
+    let salvage: u32 = ev_salvage.iter().map(|ev| ev.value).sum();
+    if kills > 0 || salvage > 0 {
+        inventory.credits += kills * KILL_REWARD + salvage;
+    }
+}
+
+/// Applies items bought in the shop to the player's ship, only the ones it needs are paid for.
+/// Upgrades are kept in the `Inventory` and fitted by `fit_upgrades`.
+fn apply_purchases(
+    mut inventory: ResMut<Inventory>,
+    mut ev_purchase: EventReader<PurchaseEvent>,
+    mut player: Query<&mut HitPoints, With<Player>>,
+    primary: Query<(), With<PrimaryWeapon>>,
+    mut launchers: Query<&mut Ammo, With<SecondaryWeapon>>,
+) {
+    let Ok(mut hp) = player.get_single_mut() else {
+        return;
+    };
+    for &PurchaseEvent(item) in ev_purchase.iter() {
+        let needed = match item {
+            Item::Rockets => launchers.iter().any(|ammo| !ammo.is_full()),
+            Item::HullRepair => hp.percent() < 100,
+            _ => !primary.is_empty(),
+        };
+        if !needed || !inventory.buy(item) {
+            info!("Can't buy {item:?} for {} credits", inventory.credits);
+            continue;
+        }
+
+        match item {
+            Item::Rockets => launchers.for_each_mut(|mut ammo| ammo.refill()),
+            Item::HullRepair => {
+                hp.heal(REPAIR_AMOUNT);
+            }
+            _ => {}
+        }
+        info!("Bought {item:?}, {} credits left", inventory.credits);
+    }
+}
+
+/// Adds the upgrades bought so far to the `Modifiers` of the player's ship and its primary
+/// weapons. Ships the player switches to and weapons bound later get all of them at once.
+fn fit_upgrades(
+    mut commands: Commands,
+    inventory: Res<Inventory>,
+    mut fittings: Query<
+        (
+            Entity,
+            Option<&mut Modifiers>,
+            Option<&mut FittedUpgrades>,
+            Option<&Player>,
+        ),
+        Or<(With<Player>, With<PrimaryWeapon>)>,
+    >,
+) {
+    let bought = inventory.upgrades.len();
+    for (entity, modifiers, fitted, player) in fittings.iter_mut() {
+        let fitted_before = fitted.as_ref().map_or(0, |fitted| fitted.0);
+        if fitted.is_some() && fitted_before >= bought {
+            continue;
+        }
+        if fitted_before < bought {
+            let upgrades = inventory.upgrade_modifiers(fitted_before, player.is_some());
+            match modifiers {
+                Some(mut modifiers) => modifiers.extend(upgrades),
+                None => {
+                    commands
+                        .entity(entity)
+                        .insert(upgrades.collect::<Modifiers>());
+                }
+            }
+        }
+        match fitted {
+            Some(mut fitted) => fitted.0 = bought,
+            None => {
+                commands.entity(entity).insert(FittedUpgrades(bought));
+            }
+        }
+    }
+}
+
+/// Credits for kills and salvage, spent in the shop opened with P once no hostiles are around.
+pub struct EconomyPlugin;
+impl Plugin for EconomyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Inventory>()
+            .add_event::<SalvageCollectedEvent>()
+            .add_event::<PurchaseEvent>()
+            .add_plugin(shop::ShopPlugin)
+            .add_system(earn_credits.after(DamageSystems))
+            .add_system(apply_purchases)
+            .add_system(fit_upgrades.after(apply_purchases));
+    }
+}
@@ -0,0 +1,167 @@
+use bevy::prelude::*;
+
+use super::{Inventory, Item, PurchaseEvent, MAX_UPGRADE_LEVEL};
+use crate::{components::Fraction, drone::Drone, localization::Localization, player::Player};
+
+const SCREEN_WIDTH: f32 = 480.0;
+/// The shop is closed while hostile drones are closer than this, in meters
+const SAFE_DISTANCE: f32 = 1500.0;
+/// Keys to buy the `Item::ALL` in the same order
+const ITEM_KEYS: [KeyCode; Item::ALL.len()] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+];
+
+#[derive(Component)]
+struct ShopScreen;
+
+#[derive(Component)]
+struct ShopText;
+
+fn setup_shop_screen(mut commands: Commands, assets: Res<AssetServer>) {
+    let font = assets.load("fonts/FiraMono-Medium.ttf");
+
+    // Hidden panel in the middle of the screen, opened between waves
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(SCREEN_WIDTH), Val::Auto),
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Percent(50.0),
+                    top: Val::Percent(20.0),
+                    ..default()
+                },
+                margin: UiRect {
+                    left: Val::Px(-SCREEN_WIDTH / 2.0),
+                    ..default()
+                },
+                padding: UiRect::all(Val::Px(10.0)),
+                display: Display::None,
+                ..default()
+            },
+            background_color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+            ..default()
+        })
+        .insert(ShopScreen)
+        .insert(Name::new("Shop Screen"))
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font,
+                        font_size: 24.0,
+                        color: Color::WHITE,
+                    },
+                ))
+                .insert(ShopText);
+        });
+}
+
+/// Opens the shop with P once no hostiles are around, and closes it when they arrive
+fn toggle_shop_screen(
+    keys: Res<Input<KeyCode>>,
+    player: Query<(&GlobalTransform, &Fraction), With<Player>>,
+    drones: Query<(&GlobalTransform, &Fraction), With<Drone>>,
+    mut screen: Query<&mut Style, With<ShopScreen>>,
+) {
+    let Ok(mut style) = screen.get_single_mut() else {
+        return;
+    };
+    let safe = player.get_single().map_or(false, |(transform, fraction)| {
+        let position = transform.translation();
+        drones.iter().all(|(drone, drone_fraction)| {
+            drone_fraction == fraction || drone.translation().distance(position) > SAFE_DISTANCE
+        })
+    });
+
+    let open = match (
+        style.display == Display::Flex,
+        keys.just_pressed(KeyCode::P),
+    ) {
+        (open, false) => open && safe,
+        (true, true) => false,
+        (false, true) => {
+            if !safe {
+                info!("The shop is closed while hostiles are within {SAFE_DISTANCE}m");
+            }
+            safe
+        }
+    };
+    let display = if open { Display::Flex } else { Display::None };
+    if style.display != display {
+        style.display = display;
+    }
+}
+
+fn buy_items(
+    keys: Res<Input<KeyCode>>,
+    screen: Query<&Style, With<ShopScreen>>,
+    mut ev_purchase: EventWriter<PurchaseEvent>,
+) {
+    if !matches!(screen.get_single(), Ok(style) if style.display == Display::Flex) {
+        return;
+    }
+    for (&key, &item) in ITEM_KEYS.iter().zip(Item::ALL.iter()) {
+        if keys.just_pressed(key) {
+            ev_purchase.send(PurchaseEvent(item));
+        }
+    }
+}
+
+fn update_shop_screen(
+    inventory: Res<Inventory>,
+    localization: Res<Localization>,
+    screen: Query<&Style, With<ShopScreen>>,
+    mut text: Query<&mut Text, With<ShopText>>,
+) {
+    let Ok(style) = screen.get_single() else {
+        return;
+    };
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+    if style.display == Display::None {
+        return;
+    }
+
+    let mut lines = vec![localization.format("shop-title", &[("credits", &inventory.credits)])];
+    lines.extend(Item::ALL.iter().enumerate().map(|(index, &item)| {
+        let mut name = localization.get(item.key()).to_owned();
+        if let Some(level) = inventory.level(item) {
+            name = localization.format(
+                "shop-upgrade",
+                &[
+                    ("name", &name),
+                    ("level", &level),
+                    ("max", &MAX_UPGRADE_LEVEL),
+                ],
+            );
+        }
+        let index = index + 1;
+        match inventory.price(item) {
+            Some(price) => localization.format(
+                "shop-item",
+                &[("index", &index), ("name", &name), ("price", &price)],
+            ),
+            None => localization.format("shop-item-maxed", &[("index", &index), ("name", &name)]),
+        }
+    }));
+    lines.push(localization.get("shop-hint").to_owned());
+    text.sections[0].value = lines.join("\n");
+}
+
+pub(super) struct ShopPlugin;
+impl Plugin for ShopPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_shop_screen)
+            .add_system(toggle_shop_screen)
+            .add_system(buy_items.after(toggle_shop_screen))
+            .add_system(update_shop_screen.after(toggle_shop_screen));
+    }
+}
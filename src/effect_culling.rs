@@ -0,0 +1,69 @@
+use bevy::{
+    prelude::*,
+    render::{
+        primitives::{Frustum, Sphere},
+        view::VisibilitySystems,
+    },
+    transform::TransformSystem,
+};
+use bevy_hanabi::ParticleEffect;
+
+use crate::{display::SecondaryCamera, settings::Settings};
+
+/// Particles are expected to stay within this distance from their emitter, in meters
+const EFFECT_RADIUS: f32 = 50.0;
+
+/// Particles of the effect spread farther than `EFFECT_RADIUS` from the emitter, in meters.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct CullRadius(pub f32);
+
+/// Effects that are never culled, like trails whose particles stretch far behind the emitter and
+/// would pop in and out as it leaves the view.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct NoEffectCulling;
+
+/// Hides effects out of the view or farther than `DisplaySettings::effect_distance`
+fn cull_effects(
+    settings: Res<Settings>,
+    // Either the player or the observer camera
+    camera: Query<(&GlobalTransform, &Frustum), (With<Camera3d>, Without<SecondaryCamera>)>,
+    mut effects: Query<
+        (&GlobalTransform, &mut Visibility, Option<&CullRadius>),
+        (With<ParticleEffect>, Without<NoEffectCulling>),
+    >,
+) {
+    let Ok((camera_transform, frustum)) = camera.get_single() else {
+        return;
+    };
+
+    for (transform, mut visibility, cull_radius) in effects.iter_mut() {
+        // Large effects stay visible while some of their particles are close enough
+        let (radius, reach) = cull_radius.map_or((EFFECT_RADIUS, 0.0), |r| (r.0, r.0));
+        let sphere = Sphere {
+            center: transform.translation().into(),
+            radius,
+        };
+        let distance = transform
+            .translation()
+            .distance(camera_transform.translation());
+        let visible = distance - reach <= settings.display.effect_distance
+            && frustum.intersects_sphere(&sphere, false);
+        if visibility.is_visible != visible {
+            visibility.is_visible = visible;
+        }
+    }
+}
+
+/// Particle effects out of the view or beyond the distance from the display settings are not
+/// rendered. Effects are pooled and moved around, so their emitters are kept, only hidden.
+pub struct EffectCullingPlugin;
+impl Plugin for EffectCullingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Settings>().add_system_to_stage(
+            CoreStage::PostUpdate,
+            cull_effects
+                .after(TransformSystem::TransformPropagate)
+                .before(VisibilitySystems::VisibilityPropagate),
+        );
+    }
+}
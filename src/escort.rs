@@ -0,0 +1,431 @@
+//! Escort mission: the capital ship cruises along its route to the destination, while waves of
+//! drones jump in from spawn gates to destroy it. The player joins the escort.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+use serde::Deserialize;
+
+use crate::{
+    components::{Fraction, HitPoints},
+    drone::{Drone, SpawnDroneEvent},
+    hud::{HudAnchor, HudWidget, Indicator, PanelBackground},
+    localization::Localization,
+    modifier::{Modifier, Modifiers},
+    player::PlayerCamera,
+    prefab::Hardpoint,
+    route::Route,
+    settings::Settings,
+    skirmish,
+};
+
+/// Seconds before the first wave, so the player has time to look around
+const FIRST_WAVE_DELAY: f32 = 15.0;
+/// Drones of a wave appear at random positions within this radius around the gate, in meters
+const GATE_RADIUS: f32 = 100.0;
+/// The player starts this far behind the capital ship, in meters
+const PLAYER_OFFSET: f32 = 150.0;
+
+/// Escort mission parameters, usually a part of the `Scenario`.
+#[derive(Resource, Deserialize, Clone, Debug, PartialEq)]
+pub struct EscortConfig {
+    /// Route of the capital ship from the start to the destination, world positions in meters
+    pub waypoints: Vec<[f32; 3]>,
+    /// Cruise speed of the capital ship in m/s
+    pub speed: f32,
+    /// Positions drones jump in at, in meters
+    pub gates: Vec<[f32; 3]>,
+    /// Seconds between waves
+    pub wave_interval: f32,
+    /// Drones in the first wave, every next wave brings one more
+    pub wave_size: u32,
+    /// Seconds to reach the destination, unlimited if not set
+    pub time_limit: Option<f32>,
+    /// Drones of later waves get tougher step by step
+    #[serde(default)]
+    pub difficulty: Vec<DifficultyStep>,
+}
+
+/// Upgrade of the drones of every wave from `from_wave` on, the steps reached so far stack.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct DifficultyStep {
+    /// Waves are counted from 1
+    pub from_wave: u32,
+    /// Drone type that is upgraded, all of them if not set
+    #[serde(default)]
+    pub drone: Option<Drone>,
+    #[serde(default)]
+    pub modifiers: Vec<Modifier>,
+    /// Weapons mounted on top of the prefab ones
+    #[serde(default)]
+    pub hardpoints: Vec<Hardpoint>,
+}
+
+/// Stacked modifiers and extra weapons of the `drone` in the `wave`
+fn upgrades(steps: &[DifficultyStep], wave: u32, drone: Drone) -> (Modifiers, Vec<Hardpoint>) {
+    let reached = || {
+        steps
+            .iter()
+            .filter(move |step| step.from_wave <= wave && step.drone.map_or(true, |d| d == drone))
+    };
+    let modifiers = reached().flat_map(|step| step.modifiers.iter().copied());
+    let hardpoints = reached().flat_map(|step| step.hardpoints.iter().cloned());
+    (modifiers.collect(), hardpoints.collect())
+}
+
+impl EscortConfig {
+    pub fn route(&self) -> Route {
+        let waypoints = self.waypoints.iter().copied().map(Vec3::from).collect();
+        Route::new(waypoints, self.speed)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MissionOutcome {
+    Arrived,
+    ShipDestroyed,
+    OutOfTime,
+    /// Ended by a scenario trigger
+    Completed,
+    /// Ended by a scenario trigger
+    Failed,
+}
+
+impl MissionOutcome {
+    pub fn is_success(self) -> bool {
+        matches!(self, Self::Arrived | Self::Completed)
+    }
+
+    /// Localization key of the outcome message
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Arrived => "escort-arrived",
+            Self::ShipDestroyed => "escort-ship-destroyed",
+            Self::OutOfTime => "escort-out-of-time",
+            Self::Completed => "mission-completed",
+            Self::Failed => "mission-failed",
+        }
+    }
+}
+
+/// Emitted once when the escort mission, or the one scripted with triggers, is over.
+pub struct MissionEndedEvent(pub MissionOutcome);
+
+/// State of the running escort mission.
+#[derive(Resource)]
+pub struct EscortMission {
+    /// Escorted capital ship, it has the `Route` to follow
+    pub ship: Entity,
+    /// Seconds since the mission start
+    pub elapsed: f32,
+    /// Number of waves sent so far
+    pub wave: u32,
+    pub outcome: Option<MissionOutcome>,
+    /// Seconds left before the next wave
+    next_wave: f32,
+}
+
+/// Mission outcome, if it's decided already. Losing the ship fails the mission even if it was
+/// destroyed right at the destination.
+fn decide_outcome(
+    ship_alive: bool,
+    arrived: bool,
+    elapsed: f32,
+    time_limit: Option<f32>,
+) -> Option<MissionOutcome> {
+    if !ship_alive {
+        Some(MissionOutcome::ShipDestroyed)
+    } else if arrived {
+        Some(MissionOutcome::Arrived)
+    } else if matches!(time_limit, Some(limit) if elapsed > limit) {
+        Some(MissionOutcome::OutOfTime)
+    } else {
+        None
+    }
+}
+
+/// Time like `05:07` for `seconds`
+fn clock(seconds: f32) -> String {
+    let seconds = seconds.max(0.0) as u32;
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clock, decide_outcome, upgrades, DifficultyStep, MissionOutcome};
+    use crate::{
+        drone::Drone,
+        modifier::{Modifier, Op, Stat},
+        prefab::{Hardpoint, Mount, WeaponConfig},
+    };
+
+    #[test]
+    fn test_decide_outcome() {
+        assert_eq!(decide_outcome(true, false, 100.0, None), None);
+        assert_eq!(decide_outcome(true, false, 100.0, Some(200.0)), None);
+        assert_eq!(
+            decide_outcome(true, false, 201.0, Some(200.0)),
+            Some(MissionOutcome::OutOfTime)
+        );
+        assert_eq!(
+            decide_outcome(true, true, 100.0, Some(200.0)),
+            Some(MissionOutcome::Arrived)
+        );
+        assert_eq!(
+            decide_outcome(false, true, 100.0, Some(200.0)),
+            Some(MissionOutcome::ShipDestroyed)
+        );
+    }
+
+    #[test]
+    fn test_clock() {
+        assert_eq!(clock(0.0), "00:00");
+        assert_eq!(clock(307.9), "05:07");
+        assert_eq!(clock(-1.0), "00:00");
+    }
+
+    #[test]
+    fn test_upgrades() {
+        let tougher = Modifier {
+            stat: Stat::HitPoints,
+            op: Op::Percent(20.0),
+        };
+        let steps = [
+            DifficultyStep {
+                from_wave: 5,
+                drone: None,
+                modifiers: vec![tougher],
+                hardpoints: Vec::new(),
+            },
+            DifficultyStep {
+                from_wave: 8,
+                drone: Some(Drone::Praetor),
+                modifiers: vec![tougher],
+                hardpoints: vec![Hardpoint {
+                    mount: Mount::Offset([0.0, 0.0, -1.0]),
+                    weapon: WeaponConfig::RocketLauncher {
+                        rate_of_fire: 0.5,
+                        ammo: None,
+                    },
+                    gimbal: None,
+                }],
+            },
+        ];
+        let (modifiers, hardpoints) = upgrades(&steps, 4, Drone::Praetor);
+        assert_eq!(modifiers.iter().count(), 0);
+        assert!(hardpoints.is_empty());
+
+        let (modifiers, hardpoints) = upgrades(&steps, 8, Drone::Infiltrator);
+        assert!((modifiers.apply(Stat::HitPoints, 100.0) - 120.0).abs() < 1e-3);
+        assert!(hardpoints.is_empty());
+        // the steps stack
+        let (modifiers, hardpoints) = upgrades(&steps, 9, Drone::Praetor);
+        assert!((modifiers.apply(Stat::HitPoints, 100.0) - 144.0).abs() < 1e-3);
+        assert_eq!(hardpoints.len(), 1);
+    }
+}
+
+fn setup_escort(mut commands: Commands, assets: Res<AssetServer>, config: Res<EscortConfig>) {
+    let route = config.route();
+    let start = route.start().unwrap_or_default();
+
+    let ship = skirmish::spawn_capital_ship(&mut commands, &assets, start);
+    commands
+        .entity(ship)
+        .insert(Fraction::Turrets)
+        .insert(RigidBody::KinematicPositionBased)
+        .insert(Velocity::default())
+        .insert(route);
+    commands.insert_resource(EscortMission {
+        ship,
+        elapsed: 0.0,
+        wave: 0,
+        outcome: None,
+        next_wave: FIRST_WAVE_DELAY,
+    });
+
+    // Gates are only marked in the world for now, drones just appear there
+    for &gate in config.gates.iter() {
+        commands
+            .spawn(TransformBundle::from(Transform::from_translation(
+                gate.into(),
+            )))
+            .insert(Name::new("Spawn Gate"));
+    }
+}
+
+/// Moves the player behind the capital ship and makes them join its side
+fn join_escort(
+    config: Res<EscortConfig>,
+    camera: Query<&PlayerCamera, Added<PlayerCamera>>,
+    mut ships: Query<(&mut Transform, &mut Fraction)>,
+) {
+    // The camera rig is spawned once with the player, while the ship may change later
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    let Ok((mut transform, mut fraction)) = ships.get_mut(camera.target) else {
+        return;
+    };
+    let Some(start) = config.route().start() else {
+        return;
+    };
+
+    *fraction = Fraction::Turrets;
+    let position = start.translation - start.forward() * PLAYER_OFFSET;
+    *transform = Transform::from_translation(position).looking_at(start.translation, Vec3::Y);
+}
+
+fn spawn_waves(
+    time: Res<Time>,
+    config: Res<EscortConfig>,
+    mut mission: ResMut<EscortMission>,
+    ships: Query<&GlobalTransform>,
+    mut ev_spawn_drone: EventWriter<SpawnDroneEvent>,
+) {
+    if mission.outcome.is_some() {
+        return;
+    }
+    mission.next_wave -= time.delta_seconds();
+    if mission.next_wave > 0.0 {
+        return;
+    }
+    mission.next_wave = config.wave_interval;
+    mission.wave += 1;
+
+    let mut rng = rand::thread_rng();
+    let Some(&gate) = config.gates.choose(&mut rng) else {
+        return;
+    };
+    let gate = Vec3::from(gate);
+    let ship = ships
+        .get(mission.ship)
+        .map_or(Vec3::ZERO, |t| t.translation());
+    let drones = config.wave_size + mission.wave - 1;
+    info!("Escort wave {} of {drones} drones", mission.wave);
+    for _ in 0..drones {
+        let drone = *[Drone::Infiltrator, Drone::Praetor]
+            .choose(&mut rng)
+            .unwrap();
+        let (modifiers, hardpoints) = upgrades(&config.difficulty, mission.wave, drone);
+        let offset = Vec3::new(
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+        ) * GATE_RADIUS;
+        let position = gate + offset;
+        ev_spawn_drone.send(SpawnDroneEvent {
+            drone,
+            fraction: Fraction::Drones,
+            transform: Transform::from_translation(position).looking_at(ship, Vec3::Y),
+            prefab: None,
+            entry_speed: 0.0,
+            modifiers,
+            hardpoints,
+        });
+    }
+}
+
+fn update_mission(
+    time: Res<Time>,
+    config: Res<EscortConfig>,
+    mut mission: ResMut<EscortMission>,
+    routes: Query<&Route>,
+    mut ev_mission_ended: EventWriter<MissionEndedEvent>,
+) {
+    if mission.outcome.is_some() {
+        return;
+    }
+    mission.elapsed += time.delta_seconds();
+
+    let route = routes.get(mission.ship).ok();
+    let arrived = route.map_or(false, Route::is_finished);
+    let outcome = decide_outcome(route.is_some(), arrived, mission.elapsed, config.time_limit);
+    if let Some(outcome) = outcome {
+        info!("Escort mission is over: {outcome:?}");
+        mission.outcome = Some(outcome);
+        ev_mission_ended.send(MissionEndedEvent(outcome));
+    }
+}
+
+#[derive(Component)]
+struct MissionText;
+
+fn setup_mission_hud(mut commands: Commands, assets: Res<AssetServer>) {
+    let background = Color::rgba(0.1, 0.1, 0.1, 0.6);
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            background_color: background.into(),
+            ..default()
+        })
+        .insert(HudWidget::essential(HudAnchor::TopLeft))
+        .insert(PanelBackground(background))
+        .insert(Name::new("Escort Mission"))
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: assets.load("fonts/FiraMono-Medium.ttf"),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                ))
+                .insert(MissionText);
+        });
+}
+
+fn update_mission_hud(
+    config: Res<EscortConfig>,
+    mission: Res<EscortMission>,
+    localization: Res<Localization>,
+    settings: Res<Settings>,
+    ships: Query<(&HitPoints, &Route, &GlobalTransform)>,
+    mut text: Query<&mut Text, With<MissionText>>,
+) {
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+
+    let mut lines = vec![localization.get("escort-title").to_owned()];
+    if let Ok((hp, route, transform)) = ships.get(mission.ship) {
+        let distance = route.remaining_distance(transform.translation());
+        lines.push(localization.format(
+            "escort-status",
+            &[("hull", &hp.percent()), ("distance", &(distance as u32))],
+        ));
+    }
+    let time = match config.time_limit {
+        Some(limit) => clock(limit - mission.elapsed),
+        None => clock(mission.elapsed),
+    };
+    lines.push(localization.format("escort-time", &[("time", &time), ("wave", &mission.wave)]));
+    if let Some(outcome) = mission.outcome {
+        lines.push(localization.get(outcome.key()).to_owned());
+        let indicator = if outcome.is_success() {
+            Indicator::Good
+        } else {
+            Indicator::Danger
+        };
+        text.sections[0].style.color = settings.accessibility.color(indicator);
+    }
+    text.sections[0].value = lines.join("\n");
+}
+
+/// Escort mission from the `EscortConfig` resource, with its state shown on the HUD.
+pub struct EscortPlugin;
+impl Plugin for EscortPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<MissionEndedEvent>()
+            .add_startup_system(setup_escort)
+            .add_startup_system(setup_mission_hud)
+            .add_system(join_escort)
+            .add_system(spawn_waves)
+            .add_system(update_mission)
+            .add_system(update_mission_hud.after(update_mission));
+    }
+}
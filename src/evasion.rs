@@ -0,0 +1,301 @@
+//! Evasive jinks of ships being shot at. Shots passing close by or hits make the ship roll or
+//! burst aside for a moment, so it doesn't fly straight into a stream of bullets. Jinks follow
+//! each other with a cooldown, to keep the ship on its course most of the time.
+//!
+//! Ships with a `ThreatWarning` are also warned of rockets homing on them, for the AI to dodge.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use rand::Rng;
+
+use crate::{
+    components::{Damage, Fraction},
+    player::Player,
+    projectile::{DamageEvent, DamageSystems, Homing, Owner},
+};
+
+/// Shots passing closer than this are noticed, in meters
+const NEAR_MISS: f32 = 20.0;
+/// Shots are noticed this long before they pass by, in seconds
+const SHOT_HORIZON: f32 = 2.0;
+/// Seconds between jinks, at least and at most
+const COOLDOWN: (f32, f32) = (2.0, 4.0);
+/// Duration in seconds and roll speed in radians per second of a barrel roll
+const ROLL_TIME: f32 = 1.0;
+const ROLL_RATE: f32 = std::f32::consts::TAU;
+/// Barrel roll pushes the ship along its up with this acceleration, so it corkscrews, in m/s²
+const ROLL_ACCELERATION: f32 = 15.0;
+/// Duration in seconds and acceleration in m/s² of a lateral burst
+const BURST_TIME: f32 = 0.5;
+const BURST_ACCELERATION: f32 = 30.0;
+
+/// Evasive maneuver of a ship being shot at
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Jink {
+    /// Rolls around the forward axis with `rate`, clockwise if it's positive
+    BarrelRoll { time_left: f32, rate: f32 },
+    /// Pushed aside along the `direction` in the ship's local space
+    LateralBurst { time_left: f32, direction: Vec3 },
+}
+
+impl Jink {
+    fn random(rng: &mut impl Rng) -> Self {
+        let side = if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+        if rng.gen_bool(0.5) {
+            Jink::BarrelRoll {
+                time_left: ROLL_TIME,
+                rate: ROLL_RATE * side,
+            }
+        } else {
+            // sideways or up and down, as drones don't turn while jinking
+            let direction = if rng.gen_bool(0.5) { Vec3::X } else { Vec3::Y };
+            Jink::LateralBurst {
+                time_left: BURST_TIME,
+                direction: direction * side,
+            }
+        }
+    }
+
+    fn time_left(&mut self) -> &mut f32 {
+        match self {
+            Jink::BarrelRoll { time_left, .. } | Jink::LateralBurst { time_left, .. } => time_left,
+        }
+    }
+}
+
+/// Ship that jinks once it's shot at.
+#[derive(Component, Clone, Debug, Default)]
+pub struct Evasion {
+    /// Seconds before the ship jinks again
+    cooldown: f32,
+    jink: Option<Jink>,
+}
+
+impl Evasion {
+    pub fn jinking(&self) -> bool {
+        self.jink.is_some()
+    }
+
+    /// Starts a random jink unless the ship is jinking already or just did
+    fn threatened(&mut self, rng: &mut impl Rng) {
+        if self.jink.is_none() && self.cooldown <= 0.0 {
+            self.jink = Some(Jink::random(rng));
+        }
+    }
+
+    /// Advances the current jink by `seconds`, the cooldown starts once it's over
+    fn tick(&mut self, seconds: f32, rng: &mut impl Rng) -> Option<Jink> {
+        let Some(jink) = &mut self.jink else {
+            self.cooldown = (self.cooldown - seconds).max(0.0);
+            return None;
+        };
+        let current = *jink;
+        let time_left = jink.time_left();
+        *time_left -= seconds;
+        if *time_left <= 0.0 {
+            self.jink = None;
+            self.cooldown = rng.gen_range(COOLDOWN.0..=COOLDOWN.1);
+        }
+        Some(current)
+    }
+}
+
+/// Homing rocket closing in on the ship.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IncomingRocket {
+    pub position: Vec3,
+    /// In m/s
+    pub velocity: Vec3,
+}
+
+impl IncomingRocket {
+    /// Direction to dodge the rocket from `position`: across its path and away from it, or to any
+    /// side if it's flying right at the ship
+    pub fn dodge_direction(&self, position: Vec3) -> Vec3 {
+        let offset = position - self.position;
+        let Some(path) = self.velocity.try_normalize() else {
+            return offset.normalize_or_zero();
+        };
+        offset
+            .reject_from_normalized(path)
+            .try_normalize()
+            .unwrap_or_else(|| path.any_orthonormal_vector())
+    }
+}
+
+/// Threats the ship is warned of, kept up to date for ships with it.
+#[derive(Component, Clone, Debug, Default)]
+pub struct ThreatWarning {
+    /// The closest rocket homing on the ship
+    pub rocket: Option<IncomingRocket>,
+}
+
+/// Whether a shot from `origin` flying with `velocity` passes within `NEAR_MISS` of the
+/// `target` during the `SHOT_HORIZON`
+fn in_line_of_fire(origin: Vec3, velocity: Vec3, target: Vec3) -> bool {
+    let speed_squared = velocity.length_squared();
+    if speed_squared <= f32::EPSILON {
+        return false;
+    }
+    let to_target = target - origin;
+    let time = to_target.dot(velocity) / speed_squared;
+    (0.0..=SHOT_HORIZON).contains(&time)
+        && (origin + velocity * time).distance_squared(target) < NEAR_MISS * NEAR_MISS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{in_line_of_fire, Evasion, IncomingRocket, COOLDOWN, NEAR_MISS};
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_in_line_of_fire() {
+        let target = Vec3::new(0.0, 0.0, -200.0);
+        let velocity = Vec3::new(0.0, 0.0, -400.0);
+        assert!(in_line_of_fire(Vec3::ZERO, velocity, target));
+        assert!(in_line_of_fire(Vec3::X * NEAR_MISS * 0.5, velocity, target));
+        assert!(!in_line_of_fire(
+            Vec3::X * NEAR_MISS * 2.0,
+            velocity,
+            target
+        ));
+        // flying away or too slow to reach the target soon
+        assert!(!in_line_of_fire(Vec3::ZERO, -velocity, target));
+        assert!(!in_line_of_fire(Vec3::ZERO, velocity * 0.1, target));
+    }
+
+    #[test]
+    fn test_dodge_direction() {
+        let rocket = IncomingRocket {
+            position: Vec3::ZERO,
+            velocity: Vec3::new(0.0, 0.0, -100.0),
+        };
+        // across the path, to the side the ship is already on
+        let dodge = rocket.dodge_direction(Vec3::new(5.0, 0.0, -100.0));
+        assert!(dodge.distance(Vec3::X) < 1e-5);
+        // flying right at the ship
+        let dodge = rocket.dodge_direction(Vec3::new(0.0, 0.0, -100.0));
+        assert!(dodge.is_normalized() && dodge.z.abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_jink_cooldown() {
+        let mut rng = rand::thread_rng();
+        let mut evasion = Evasion::default();
+        assert_eq!(evasion.tick(0.1, &mut rng), None);
+        evasion.threatened(&mut rng);
+        assert!(evasion.jinking());
+        let jink = evasion.jink;
+        // keeps jinking the same way
+        evasion.threatened(&mut rng);
+        assert_eq!(evasion.jink, jink);
+        while evasion.tick(0.1, &mut rng).is_some() {}
+        assert!(!evasion.jinking());
+        // and doesn't jink again until the cooldown is over
+        evasion.threatened(&mut rng);
+        assert!(!evasion.jinking());
+        evasion.tick(COOLDOWN.1, &mut rng);
+        evasion.threatened(&mut rng);
+        assert!(evasion.jinking());
+    }
+}
+
+/// Ships notice shots flying their way from other fractions and hits on them
+fn detect_fire(
+    mut ev_damage: EventReader<DamageEvent>,
+    mut ships: Query<(&GlobalTransform, Option<&Fraction>, &mut Evasion), Without<Player>>,
+    shots: Query<(&Transform, &Velocity, Option<&Owner>), Added<Damage>>,
+    fractions: Query<&Fraction>,
+) {
+    let mut rng = rand::thread_rng();
+    for ev in ev_damage.iter() {
+        if let Ok((_, _, mut evasion)) = ships.get_mut(ev.target) {
+            evasion.threatened(&mut rng);
+        }
+    }
+    if shots.is_empty() {
+        return;
+    }
+    for (transform, fraction, mut evasion) in ships.iter_mut() {
+        if evasion.jinking() {
+            continue;
+        }
+        let position = transform.translation();
+        let shot_at = shots.iter().any(|(shot, velocity, owner)| {
+            let shooter = owner.and_then(|owner| fractions.get(owner.0).ok());
+            let friendly = fraction.is_some() && shooter == fraction;
+            !friendly && in_line_of_fire(shot.translation, velocity.linvel, position)
+        });
+        if shot_at {
+            evasion.threatened(&mut rng);
+        }
+    }
+}
+
+/// Ships with a `ThreatWarning` are warned of the closest rocket homing on them
+pub fn warn_of_rockets(
+    mut ships: Query<(Entity, &GlobalTransform, &mut ThreatWarning)>,
+    rockets: Query<(&Homing, &GlobalTransform, &Velocity)>,
+) {
+    let rockets: Vec<_> = rockets
+        .iter()
+        .map(|(homing, transform, velocity)| {
+            let rocket = IncomingRocket {
+                position: transform.translation(),
+                velocity: velocity.linvel,
+            };
+            (homing.target, rocket)
+        })
+        .collect();
+    for (entity, transform, mut warning) in ships.iter_mut() {
+        let position = transform.translation();
+        let closest = rockets
+            .iter()
+            .filter(|(target, _)| *target == entity)
+            .map(|&(_, rocket)| rocket)
+            .min_by(|a, b| {
+                let a = a.position.distance_squared(position);
+                a.total_cmp(&b.position.distance_squared(position))
+            });
+        if warning.rocket != closest {
+            warning.rocket = closest;
+        }
+    }
+}
+
+/// Rolls and pushes jinking ships on top of their steering
+fn jink(
+    time: Res<Time>,
+    mut ships: Query<(&GlobalTransform, &mut Velocity, &mut Evasion), Without<Player>>,
+) {
+    let mut rng = rand::thread_rng();
+    let seconds = time.delta_seconds();
+    for (transform, mut velocity, mut evasion) in ships.iter_mut() {
+        let Some(jink) = evasion.tick(seconds, &mut rng) else {
+            continue;
+        };
+        match jink {
+            Jink::BarrelRoll { rate, .. } => {
+                let forward = transform.forward();
+                velocity.angvel = velocity.angvel.reject_from_normalized(forward) + forward * rate;
+                velocity.linvel += transform.up() * ROLL_ACCELERATION * seconds;
+            }
+            Jink::LateralBurst { direction, .. } => {
+                let direction = transform.affine().transform_vector3(direction).normalize();
+                velocity.linvel += direction * BURST_ACCELERATION * seconds;
+            }
+        }
+    }
+}
+
+/// Ships with `Evasion` jink once they are shot at, and ones with `ThreatWarning` are warned of
+/// rockets homing on them.
+pub struct EvasionPlugin;
+impl Plugin for EvasionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .add_system(detect_fire.after(DamageSystems))
+            .add_system(jink.after(detect_fire))
+            .add_system(warn_of_rockets);
+    }
+}
@@ -0,0 +1,163 @@
+//! Gimballed weapon mounts, tracking the target within a small cone on their own, so guns keep
+//! on it while the ship itself turns slower or not at all, like the player's guns or drone chin
+//! turrets. Mounts of AI ships follow the direction of their `GunLayer`, while the player's ones
+//! lead the primary locked target.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    aiming::{self, GunLayer},
+    gun::Gun,
+    player::{LockedTarget, Player},
+};
+
+/// Weapon mount turning towards the target independently of the ship orientation. The rotation
+/// it has once mounted is the rest one, that it returns to without a target.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct Gimbal {
+    /// Largest deflection from the rest rotation, in radians
+    pub max_angle: f32,
+    /// In radians per second
+    pub speed: f32,
+    #[reflect(ignore)]
+    rest: Option<Quat>,
+}
+
+impl Gimbal {
+    pub fn new(max_angle: f32, speed: f32) -> Self {
+        Self {
+            max_angle,
+            speed,
+            rest: None,
+        }
+    }
+}
+
+/// Rotation of a mount turned from `current` towards the `direction` in the parent space by at
+/// most `max_step` radians, and not farther than `max_angle` from the `rest` rotation. Mounts
+/// without a direction return to rest.
+fn track(rest: Quat, current: Quat, direction: Vec3, max_angle: f32, max_step: f32) -> Quat {
+    let goal = match direction.try_normalize() {
+        Some(direction) => {
+            let forward = rest * Vec3::NEG_Z;
+            let mut arc = Quat::from_rotation_arc(forward, direction);
+            // Targets outside the cone are tracked up to its edge
+            let angle = forward.angle_between(direction);
+            if angle > max_angle {
+                arc = Quat::IDENTITY.slerp(arc, max_angle / angle);
+            }
+            arc * rest
+        }
+        None => rest,
+    };
+    let angle = current.angle_between(goal);
+    if angle <= max_step {
+        goal
+    } else {
+        current.slerp(goal, max_step / angle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::track;
+    use bevy::prelude::{Quat, Vec3};
+
+    #[test]
+    fn test_track() {
+        let rest = Quat::IDENTITY;
+        let max_angle = 0.2;
+        // turns by the step
+        let rotation = track(rest, rest, Vec3::new(0.1, 0.0, -1.0), max_angle, 0.05);
+        assert!((rotation.angle_between(rest) - 0.05).abs() < 1e-3);
+        // reaches targets within the cone
+        let direction = Vec3::new(0.1, 0.0, -1.0).normalize();
+        let rotation = track(rest, rotation, direction, max_angle, 1.0);
+        assert!((rotation * Vec3::NEG_Z).distance(direction) < 1e-3);
+        // stops at the edge of the cone
+        let rotation = track(rest, rotation, Vec3::X, max_angle, 1.0);
+        assert!((rotation.angle_between(rest) - max_angle).abs() < 1e-3);
+        assert!((rotation * Vec3::NEG_Z).x > 0.0);
+        // and returns to rest without a target
+        let rotation = track(rest, rotation, Vec3::ZERO, max_angle, 1.0);
+        assert!(rotation.angle_between(rest) < 1e-3);
+    }
+}
+
+/// Direction in world space to lead the `target` from the `origin` moving with `own_vel`
+fn lead_direction(
+    origin: Vec3,
+    own_vel: Vec3,
+    target: (&GlobalTransform, Option<&Velocity>),
+    gun: Option<&Gun>,
+) -> Vec3 {
+    let (target, target_velocity) = target;
+    let to_target = target.translation() - origin;
+    let relative_vel = target_velocity.map(|v| v.linvel).unwrap_or_default() - own_vel;
+    let time = gun
+        .and_then(|gun| aiming::interception_time(to_target, relative_vel, gun.speed()))
+        .unwrap_or(0.0);
+    to_target + relative_vel * time
+}
+
+fn update_gimbals(
+    time: Res<Time>,
+    mut gimbals: Query<(Entity, &mut Gimbal, &mut Transform, &Parent, Option<&Gun>)>,
+    transforms: Query<&GlobalTransform>,
+    parents: Query<&Parent>,
+    gun_layers: Query<&GunLayer>,
+    player: Query<Option<&Velocity>, With<Player>>,
+    locked: Query<(&GlobalTransform, Option<&Velocity>), With<LockedTarget>>,
+) {
+    let locked = locked.get_single().ok();
+    for (entity, mut gimbal, mut transform, parent, gun) in gimbals.iter_mut() {
+        let rest = match gimbal.rest {
+            Some(rest) => rest,
+            None => *gimbal.rest.insert(transform.rotation),
+        };
+        let (Ok(own), Ok(parent_transform)) =
+            (transforms.get(entity), transforms.get(parent.get()))
+        else {
+            continue;
+        };
+
+        // The closest gun layer up the hierarchy aims the mount, or the player leads the locked target
+        let mut direction = Vec3::ZERO;
+        for e in std::iter::once(entity).chain(parents.iter_ancestors(entity)) {
+            if let Ok(gun_layer) = gun_layers.get(e) {
+                direction = gun_layer.direction();
+                break;
+            }
+            if let Ok(own_velocity) = player.get(e) {
+                let own_vel = own_velocity.map(|v| v.linvel).unwrap_or_default();
+                direction = locked.map_or(Vec3::ZERO, |target| {
+                    lead_direction(own.translation(), own_vel, target, gun)
+                });
+                break;
+            }
+        }
+
+        let (_, parent_rotation, _) = parent_transform.to_scale_rotation_translation();
+        let rotation = track(
+            rest,
+            transform.rotation,
+            parent_rotation.inverse() * direction,
+            gimbal.max_angle,
+            gimbal.speed * time.delta_seconds(),
+        );
+        if transform.rotation != rotation {
+            transform.rotation = rotation;
+        }
+    }
+}
+
+/// Turns `Gimbal` mounts towards their targets, after the gun layers are aimed.
+pub struct GimbalPlugin;
+impl Plugin for GimbalPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Gimbal>()
+            .add_system(update_gimbals.after(aiming::gun_layer));
+    }
+}
@@ -1,60 +1,670 @@
-use bevy::prelude::*;
+use bevy::{
+    ecs::{
+        entity::{EntityMap, MapEntities, MapEntitiesError},
+        reflect::ReflectMapEntities,
+    },
+    pbr::{NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+};
 use bevy_rapier3d::prelude::*;
+use rand::Rng;
 
-use crate::projectile;
+use crate::{
+    components::{self, Fraction, KinematicPush},
+    ion_storm::{self, InIonStorm},
+    light_budget::DynamicLight,
+    modifier::{ModifierPlugin, Modifiers, Stat},
+    projectile,
+    scene_setup::GltfExtrasApp,
+    trail::Trail,
+};
 
-#[derive(Component, Default)]
+/// Trigger of a gun, pulled by input and AI systems every frame it should be held. The gun
+/// latches it once a frame in `TriggerSystems`, and the edges below are as of the last latch, so
+/// they stay consistent for the systems running after it. Guns fire while it's held, semi-automatic
+/// ones once per press, and guns with a `Charge` on its release.
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 pub struct Trigger {
+    /// Fires a single shot on every press instead of firing while held
+    semi_automatic: bool,
     is_pulled: bool,
+    held: bool,
+    was_held: bool,
 }
 
 impl Trigger {
+    /// Trigger firing a single shot on every press, like the salvo of a missile launcher
+    pub fn semi_automatic() -> Self {
+        Self {
+            semi_automatic: true,
+            ..default()
+        }
+    }
+
+    /// Holds the trigger for this frame, it's released once not pulled for a frame
     pub fn pull(&mut self) {
         self.is_pulled = true;
     }
+
+    pub fn held(&self) -> bool {
+        self.held
+    }
+
+    /// Whether the trigger has just been pulled, after being released
+    pub fn pressed(&self) -> bool {
+        self.held && !self.was_held
+    }
+
+    /// Whether the trigger has just been released, after being held
+    pub fn released(&self) -> bool {
+        !self.held && self.was_held
+    }
+
+    /// Takes the pulls since the last latch, returns whether the trigger is held
+    fn latch(&mut self) -> bool {
+        self.was_held = self.held;
+        self.held = std::mem::take(&mut self.is_pulled);
+        self.held
+    }
+}
+
+/// Label of the system latching `Trigger`s, guns fire after it
+#[derive(SystemLabel)]
+pub struct TriggerSystems;
+
+/// Label of systems firing projectiles from guns
+#[derive(SystemLabel)]
+pub struct FireSystems;
+
+/// Emitted for every projectile fired by a gun.
+/// `barrel` is the entity the projectile is fired from, that is the gun itself unless it has
+/// several barrels.
+pub struct ShotFiredEvent {
+    pub gun: Entity,
+    pub barrel: Entity,
+}
+
+/// Target for homing projectiles fired by the gun, unguided projectiles are fired if not set
+#[derive(Component, Default, Reflect)]
+#[reflect(Component, MapEntities)]
+pub struct Guidance(pub Option<Entity>);
+
+impl MapEntities for Guidance {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        // Target might be outside of the scene, then it's kept as is
+        if let Some(target) = self.0.and_then(|e| entity_map.get(e).ok()) {
+            self.0 = Some(target);
+        }
+        Ok(())
+    }
 }
 
+#[derive(Clone, Copy, Default, Reflect, FromReflect)]
 pub enum Projectile {
+    #[default]
     Bullet,
+    /// Instantaneous railgun shot that passes through several targets
+    Slug,
+    /// Proximity-fused shell that bursts into shrapnel near hostiles
+    FlakShell,
     Rocket,
 }
 
-#[derive(Component)]
+impl Projectile {
+    /// Bullets and shells drop under the physics gravity, while rockets are propelled and
+    /// railgun slugs hit instantly
+    pub fn ballistic(self) -> bool {
+        matches!(self, Projectile::Bullet | Projectile::FlakShell)
+    }
+}
+
+/// Stage of the gun between shots.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, FromReflect)]
+pub enum FireCycle {
+    /// Fires once the trigger is pulled, or starts charging the shot
+    #[default]
+    Ready,
+    /// Waits for the next shot after the previous one, held trigger fires again right after
+    Cooldown,
+    /// Charges the shot while the trigger is held, see `Charge`
+    Charging,
+    /// Holds fire until the barrel cools down, see `Heat`
+    Overheated,
+}
+
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 pub struct Gun {
-    rate_of_fire_timer: Timer,
+    cycle: FireCycle,
+    /// Seconds left before the next shot in `FireCycle::Cooldown`
+    cooldown: f32,
+    /// Whether the gun has fired this frame
+    fired: bool,
     projectile: Projectile,
+    /// Base stats, the modifiers are applied on top of them
+    rate_of_fire: f32,
     speed: f32,
+    effective_range: f32,
+    /// Modifiers of the gun and its ancestors, kept up to date by the `ModifierPlugin`
+    #[reflect(ignore)]
+    modifiers: Modifiers,
 }
 
 impl Gun {
-    pub fn new(rate_of_fire: f32, projectile: Projectile, speed: f32) -> Self {
+    pub fn new(
+        rate_of_fire: f32,
+        projectile: Projectile,
+        speed: f32,
+        effective_range: f32,
+    ) -> Self {
         Self {
-            rate_of_fire_timer: Timer::from_seconds(1.0 / rate_of_fire, TimerMode::Repeating),
+            cycle: FireCycle::Ready,
+            cooldown: 0.0,
+            fired: false,
             projectile,
+            rate_of_fire,
             speed,
+            effective_range,
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    /// Projectile muzzle speed in m/s
+    pub fn speed(&self) -> f32 {
+        self.modifiers.apply(Stat::ProjectileSpeed, self.speed)
+    }
+
+    /// Sets the base speed, modifiers still apply on top of it
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    /// Distance in meters at which the gun is still accurate enough to hit a ship-sized target
+    pub fn effective_range(&self) -> f32 {
+        self.modifiers
+            .apply(Stat::EffectiveRange, self.effective_range)
+    }
+
+    pub fn projectile(&self) -> Projectile {
+        self.projectile
+    }
+
+    /// Shots per second
+    pub fn rate_of_fire(&self) -> f32 {
+        self.modifiers.apply(Stat::RateOfFire, self.rate_of_fire)
+    }
+
+    pub(crate) fn set_modifiers(&mut self, modifiers: Modifiers) {
+        self.modifiers = modifiers;
+    }
+
+    pub fn cycle(&self) -> FireCycle {
+        self.cycle
+    }
+
+    /// Whether the gun has fired this frame
+    pub fn fired(&self) -> bool {
+        self.fired
+    }
+
+    /// From 0 right after a shot to 1 once the cooldown is over
+    pub fn readiness(&self) -> f32 {
+        match self.cycle {
+            FireCycle::Cooldown => 1.0 - (self.cooldown * self.rate_of_fire()).clamp(0.0, 1.0),
+            _ => 1.0,
+        }
+    }
+
+    /// Advances the fire cycle by `seconds` with the trigger `held`, or just `released`. Guns with
+    /// a `charge` fire once it's released fully charged, `overheated` ones hold fire until they
+    /// cool down.
+    fn advance(
+        &mut self,
+        seconds: f32,
+        held: bool,
+        released: bool,
+        overheated: bool,
+        charge: Option<&mut Charge>,
+    ) {
+        self.fired = false;
+        if overheated {
+            self.cycle = FireCycle::Overheated;
+            self.cooldown = 0.0;
+            if let Some(charge) = charge {
+                charge.release();
+            }
+            return;
+        }
+
+        if self.cycle == FireCycle::Cooldown {
+            self.cooldown -= seconds;
+            if self.cooldown > 0.0 {
+                return;
+            }
+            // Held trigger keeps firing on schedule, as the time past the cooldown counts
+            // towards the next one. Charged shots start charging only after the cooldown.
+            if held && charge.is_none() {
+                self.fire(-self.cooldown);
+                return;
+            }
+            self.cooldown = 0.0;
+        }
+
+        let ready = match charge {
+            Some(charge) if released => charge.release(),
+            Some(charge) => {
+                charge.hold(held, seconds);
+                false
+            }
+            None => held,
+        };
+        if ready {
+            self.fire(0.0);
+        } else if held {
+            // Only guns with a charge keep charging while the trigger is held
+            self.cycle = FireCycle::Charging;
+        } else {
+            self.cycle = FireCycle::Ready;
         }
     }
+
+    /// Fires and starts the cooldown, shortened by the `overdue` time the shot is late
+    fn fire(&mut self, overdue: f32) {
+        self.fired = true;
+        self.cycle = FireCycle::Cooldown;
+        // Missed shots are not accumulated after long frames
+        self.cooldown = (self.rate_of_fire().max(f32::EPSILON).recip() - overdue).max(0.0);
+    }
+}
+
+/// Limited rounds of the gun, it stops firing once they run out.
+/// Guns firing salvos spend one round per salvo.
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct Ammo {
+    rounds: u32,
+    capacity: u32,
 }
 
-fn check_trigger(mut guns: Query<(&mut Trigger, &mut Gun)>, time: Res<Time>) {
-    for (mut trigger, mut gun) in guns.iter_mut() {
-        gun.rate_of_fire_timer.tick(time.delta());
+impl Ammo {
+    /// Fully loaded
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            rounds: capacity,
+            capacity,
+        }
+    }
 
-        if trigger.is_pulled {
-            trigger.is_pulled = false;
+    pub fn rounds(&self) -> u32 {
+        self.rounds
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rounds == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.rounds == self.capacity
+    }
+
+    pub fn refill(&mut self) {
+        self.rounds = self.capacity;
+    }
+}
 
-            if gun.rate_of_fire_timer.paused() {
-                gun.rate_of_fire_timer.unpause();
-                let duration = gun.rate_of_fire_timer.duration();
-                gun.rate_of_fire_timer.tick(duration);
+/// Charge-up before every shot: the trigger has to be held for the whole charge time, and the
+/// shot is fired on its release. Releasing it earlier loses the charge.
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct Charge {
+    /// Seconds to charge a shot
+    duration: f32,
+    progress: f32,
+}
+
+impl Charge {
+    pub fn new(duration: f32) -> Self {
+        Self {
+            duration,
+            progress: 0.0,
+        }
+    }
+
+    /// From 0 for an empty charge to 1 for a charged shot
+    pub fn level(&self) -> f32 {
+        if self.duration > 0.0 {
+            (self.progress / self.duration).min(1.0)
+        } else {
+            1.0
+        }
+    }
+
+    /// Charges while the trigger is `held` up to a full charge, loses it otherwise
+    fn hold(&mut self, held: bool, seconds: f32) {
+        if held {
+            self.progress = (self.progress + seconds).min(self.duration);
+        } else {
+            self.progress = 0.0;
+        }
+    }
+
+    /// Spends the charge, returns whether the shot was fully charged
+    fn release(&mut self) -> bool {
+        let charged = self.level() >= 1.0;
+        self.progress = 0.0;
+        charged
+    }
+}
+
+/// Impulse in N·s that pushes the gun's body backwards with every shot.
+/// Kinematic bodies with `KinematicPush` are pushed as if they weighed `KINEMATIC_MASS`,
+/// and turrets without a body have their aim knocked off instead.
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct Recoil(pub f32);
+
+/// Kinematic bodies have no mass for rapier, recoil pushes them as if they had this one, in kg
+const KINEMATIC_MASS: f32 = 1000.0;
+
+/// Heat level at which overheated gun is able to shoot again
+const HEAT_RECOVERY_LEVEL: f32 = 0.3;
+
+/// Barrel heat that increases with every shot and dissipates over time.
+/// Hot barrel increases projectile spread and blocks shooting once overheated.
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+#[cfg_attr(feature = "devtools", derive(bevy_inspector_egui::Inspectable))]
+pub struct Heat {
+    /// From 0 for a cold barrel to 1 for overheated one
+    #[cfg_attr(feature = "devtools", inspectable(min = 0.0, max = 1.0))]
+    value: f32,
+    #[cfg_attr(feature = "devtools", inspectable(min = 0.0, max = 1.0))]
+    per_shot: f32,
+    /// `per_shot` with the modifiers applied
+    #[cfg_attr(feature = "devtools", inspectable(read_only))]
+    modified_per_shot: f32,
+    /// Heat dissipation per second
+    #[cfg_attr(feature = "devtools", inspectable(min = 0.0))]
+    cooling_rate: f32,
+    /// Half-angle of the spread cone at maximum heat, in radians
+    #[cfg_attr(feature = "devtools", inspectable(min = 0.0, max = 0.5))]
+    max_spread: f32,
+    #[cfg_attr(feature = "devtools", inspectable(read_only))]
+    overheated: bool,
+}
+
+impl Heat {
+    pub fn new(per_shot: f32, cooling_rate: f32, max_spread: f32) -> Self {
+        Self {
+            value: 0.0,
+            per_shot,
+            modified_per_shot: per_shot,
+            cooling_rate,
+            max_spread,
+            overheated: false,
+        }
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// Current half-angle of the spread cone, in radians
+    pub fn spread(&self) -> f32 {
+        self.max_spread * self.value
+    }
+
+    pub fn overheated(&self) -> bool {
+        self.overheated
+    }
+
+    pub(crate) fn set_modifiers(&mut self, modifiers: &Modifiers) {
+        self.modified_per_shot = modifiers.apply(Stat::HeatPerShot, self.per_shot);
+    }
+
+    fn shot(&mut self) -> &mut Self {
+        self.value = (self.value + self.modified_per_shot).min(1.0);
+        if self.value >= 1.0 {
+            self.overheated = true;
+        }
+        self
+    }
+
+    fn cool_down(&mut self, seconds: f32) -> &mut Self {
+        self.value = (self.value - self.cooling_rate * seconds).max(0.0);
+        if self.overheated && self.value <= HEAT_RECOVERY_LEVEL {
+            self.overheated = false;
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Charge, FireCycle, Gun, Heat, Projectile, Trigger};
+
+    fn gun(rate_of_fire: f32) -> Gun {
+        Gun::new(rate_of_fire, Projectile::Bullet, 100.0, 100.0)
+    }
+
+    #[test]
+    fn test_rate_of_fire_with_frame_jitter() {
+        let frames = [0.008, 0.025, 0.016, 0.011, 0.033];
+        for rate_of_fire in [5.0, 13.0] {
+            let mut gun = gun(rate_of_fire);
+            let (mut time, mut shots) = (0.0, 0);
+            for frame in frames.iter().cycle() {
+                if time >= 10.0 {
+                    break;
+                }
+                gun.advance(*frame, true, false, false, None);
+                if gun.fired() {
+                    shots += 1;
+                }
+                time += frame;
             }
-        } else if gun.rate_of_fire_timer.just_finished() {
-            gun.rate_of_fire_timer.reset();
-            gun.rate_of_fire_timer.pause();
+            // The first shot is fired right away
+            let expected = rate_of_fire * 10.0 + 1.0;
+            assert!(
+                (shots as f32 - expected).abs() <= 1.0,
+                "{shots} shots at {rate_of_fire}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_fire_cycle() {
+        let mut gun = gun(5.0);
+        gun.advance(0.01, true, false, false, None);
+        assert!(gun.fired());
+        assert_eq!(gun.cycle(), FireCycle::Cooldown);
+
+        // tapping the trigger doesn't fire faster
+        gun.advance(0.05, false, false, false, None);
+        gun.advance(0.1, true, false, false, None);
+        assert!(!gun.fired());
+        assert!(gun.readiness() > 0.5 && gun.readiness() < 1.0);
+        gun.advance(0.1, true, false, false, None);
+        assert!(gun.fired());
+
+        gun.advance(0.3, false, false, false, None);
+        assert_eq!(gun.cycle(), FireCycle::Ready);
+        assert_eq!(gun.readiness(), 1.0);
+
+        // holds fire while overheated and fires right after cooling down
+        gun.advance(0.1, true, false, true, None);
+        assert!(!gun.fired());
+        assert_eq!(gun.cycle(), FireCycle::Overheated);
+        gun.advance(0.1, true, false, false, None);
+        assert!(gun.fired());
+    }
+
+    #[test]
+    fn test_fire_cycle_charging() {
+        let mut gun = gun(1.0);
+        let mut charge = Charge::new(1.0);
+        gun.advance(0.6, true, false, false, Some(&mut charge));
+        assert!(!gun.fired());
+        assert_eq!(gun.cycle(), FireCycle::Charging);
+        // fully charged, the shot waits for the release
+        gun.advance(0.6, true, false, false, Some(&mut charge));
+        assert!(!gun.fired());
+        assert_eq!(charge.level(), 1.0);
+        gun.advance(0.01, false, true, false, Some(&mut charge));
+        assert!(gun.fired());
+
+        // the next charge starts only after the cooldown
+        gun.advance(0.9, true, false, false, Some(&mut charge));
+        assert_eq!(gun.cycle(), FireCycle::Cooldown);
+        assert_eq!(charge.level(), 0.0);
+        gun.advance(0.2, true, false, false, Some(&mut charge));
+        assert_eq!(gun.cycle(), FireCycle::Charging);
+
+        // releasing the trigger early loses the charge
+        gun.advance(0.1, false, true, false, Some(&mut charge));
+        assert!(!gun.fired());
+        assert_eq!(gun.cycle(), FireCycle::Ready);
+        assert_eq!(charge.level(), 0.0);
+    }
+
+    #[test]
+    fn test_heat_overheat_and_recovery() {
+        let mut heat = Heat::new(0.5, 0.1, 0.2);
+        assert!(!heat.shot().overheated());
+        assert_eq!(heat.spread(), 0.1);
+        assert!(heat.shot().overheated());
+        assert_eq!(heat.value(), 1.0);
+
+        // stays overheated until cooled down to the recovery level
+        assert!(heat.cool_down(5.0).overheated());
+        assert!(!heat.cool_down(3.0).overheated());
+        assert!(!heat.cool_down(100.0).overheated());
+        assert_eq!(heat.value(), 0.0);
+    }
+
+    #[test]
+    fn test_charge() {
+        let mut charge = Charge::new(1.0);
+        charge.hold(true, 0.6);
+        assert_eq!(charge.level(), 0.6);
+        // letting go of the trigger loses the charge
+        charge.hold(false, 0.6);
+        assert_eq!(charge.level(), 0.0);
+        charge.hold(true, 0.6);
+        assert!(!charge.release());
+        assert_eq!(charge.level(), 0.0);
+
+        charge.hold(true, 0.6);
+        charge.hold(true, 0.6);
+        assert_eq!(charge.level(), 1.0);
+        assert!(charge.release());
+        // every shot needs a new charge
+        assert_eq!(charge.level(), 0.0);
+    }
+
+    #[test]
+    fn test_trigger_edges() {
+        let mut trigger = Trigger::default();
+        assert!(!trigger.latch());
+        assert!(!trigger.pressed() && !trigger.released());
+
+        trigger.pull();
+        trigger.pull();
+        assert!(trigger.latch());
+        assert!(trigger.pressed());
+        // pulled again the next frame, still held
+        trigger.pull();
+        assert!(trigger.latch());
+        assert!(trigger.held() && !trigger.pressed() && !trigger.released());
+        // not pulled for a frame
+        assert!(!trigger.latch());
+        assert!(trigger.released());
+        assert!(!trigger.latch());
+        assert!(!trigger.released());
+    }
+}
+
+fn check_trigger(
+    mut guns: Query<(
+        &mut Trigger,
+        &mut Gun,
+        Option<&Heat>,
+        Option<&mut Charge>,
+        Option<&Ammo>,
+    )>,
+    time: Res<Time>,
+) {
+    for (mut trigger, mut gun, heat, charge, ammo) in guns.iter_mut() {
+        let empty = ammo.map_or(false, Ammo::is_empty);
+        trigger.latch();
+        let held = if trigger.semi_automatic {
+            trigger.pressed()
+        } else {
+            trigger.held()
+        };
+        let overheated = heat.map_or(false, Heat::overheated);
+        gun.advance(
+            time.delta_seconds(),
+            held && !empty,
+            trigger.released() && !empty,
+            overheated,
+            charge.map(Mut::into_inner),
+        );
+    }
+}
+
+fn barrel_heat(mut guns: Query<(&Gun, &mut Heat)>, time: Res<Time>) {
+    for (gun, mut heat) in guns.iter_mut() {
+        if gun.fired() {
+            heat.shot();
+        }
+        heat.cool_down(time.delta_seconds());
+    }
+}
+
+fn spend_ammo(mut guns: Query<(&Gun, &mut Ammo)>) {
+    for (gun, mut ammo) in guns.iter_mut() {
+        if gun.fired() {
+            ammo.rounds = ammo.rounds.saturating_sub(1);
         }
     }
 }
 
+/// Deviates `direction` by a random angle within the cone with `spread` half-angle
+pub(crate) fn scatter(direction: Vec3, spread: f32) -> Vec3 {
+    if spread <= 0.0 {
+        return direction;
+    }
+
+    let mut rng = rand::thread_rng();
+    // sqrt makes hits uniformly distributed over the cone cross-section
+    let angle = spread * rng.gen::<f32>().sqrt();
+    let axis = Quat::from_axis_angle(direction, rng.gen_range(0.0..std::f32::consts::TAU))
+        * direction.any_orthonormal_vector();
+    Quat::from_axis_angle(axis, angle) * direction
+}
+
+/// Half-angle of the spread cone of a gun with the `heat`, wider inside an ion storm
+fn spread(heat: Option<&Heat>, in_storm: Option<&InIonStorm>) -> f32 {
+    let storm = in_storm.map_or(0.0, |_| ion_storm::AIM_ERROR);
+    heat.map_or(0.0, Heat::spread) + storm
+}
+
+/// Material of projectiles, glowing with the HDR `emissive` color in linear space.
+/// Values well above 1 are picked up by the bloom, unlit materials would ignore them.
+fn glowing_material(emissive: Color) -> StandardMaterial {
+    StandardMaterial {
+        base_color: Color::BLACK,
+        emissive,
+        ..default()
+    }
+}
+
 /// Annotates entities that are used as projectile spawn bullets for FlakCannon
 #[derive(Component)]
 pub struct Barrel;
@@ -69,16 +679,16 @@ impl MultiBarrel {
     }
 }
 
-#[derive(Resource)]
 struct Bullet {
     collider: Collider,
     mesh: Handle<Mesh>,
     material: Handle<StandardMaterial>,
 
-    lifetime: projectile::Lifetime,
+    lifetime: components::Lifetime,
 
     explosion: projectile::ExplosionEffect,
-    damage: projectile::Damage,
+    damage: components::Damage,
+    trail: Option<Trail>,
 }
 
 impl Bullet {
@@ -94,20 +704,23 @@ impl Bullet {
                 depth: 16.0 * radius,
                 ..default()
             })),
-            material: materials.add(StandardMaterial {
-                base_color: Color::WHITE,
-                unlit: true,
-                // exclude this material from shadows calculations
-                ..default()
-            }),
-            lifetime: projectile::Lifetime(15.0),
+            material: materials.add(glowing_material(Color::rgb_linear(6.0, 6.0, 5.0))),
+            lifetime: components::Lifetime(15.0),
             explosion: projectile::ExplosionEffect::Small,
-            damage: projectile::Damage(1),
+            damage: components::Damage(1),
+            trail: Some(Trail::Tracer),
         }
     }
 
-    fn spawn(&self, commands: &mut Commands, position: Vec3, direction: Vec3, velocity: Vec3) {
-        commands.spawn(projectile::ProjectileBundle {
+    fn spawn(
+        &self,
+        commands: &mut Commands,
+        position: Vec3,
+        direction: Vec3,
+        velocity: Vec3,
+        owner: Entity,
+    ) -> Entity {
+        let mut bullet = commands.spawn(projectile::ProjectileBundle {
             mesh_material: PbrBundle {
                 mesh: self.mesh.clone(),
                 material: self.material.clone(),
@@ -129,21 +742,111 @@ impl Bullet {
             damage: self.damage.clone(),
             ..default()
         });
+        bullet.insert(projectile::Owner(owner));
+        if let Some(trail) = self.trail {
+            bullet.insert(trail);
+        }
+        bullet.id()
     }
 }
 
-#[derive(Resource)]
+struct FlakShell {
+    collider: Collider,
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+
+    lifetime: components::Lifetime,
+
+    explosion: projectile::ExplosionEffect,
+    damage: components::Damage,
+    trail: Option<Trail>,
+
+    fuse_radius: f32,
+    blast_radius: f32,
+    shrapnel_damage: u32,
+}
+
+impl FlakShell {
+    fn new(
+        meshes: &mut ResMut<Assets<Mesh>>,
+        materials: &mut ResMut<Assets<StandardMaterial>>,
+    ) -> Self {
+        let radius = 0.15;
+        Self {
+            collider: Collider::ball(radius),
+            mesh: meshes.add(Mesh::from(shape::UVSphere {
+                radius,
+                sectors: 16,
+                stacks: 8,
+            })),
+            material: materials.add(glowing_material(Color::rgb_linear(6.0, 4.0, 1.5))),
+            lifetime: components::Lifetime(8.0),
+            explosion: projectile::ExplosionEffect::Small,
+            damage: components::Damage(2),
+            trail: Some(Trail::Tracer),
+            fuse_radius: 5.0,
+            blast_radius: 10.0,
+            shrapnel_damage: 20,
+        }
+    }
+
+    fn spawn(
+        &self,
+        commands: &mut Commands,
+        position: Vec3,
+        velocity: Vec3,
+        owner: Entity,
+        friendly: Option<Fraction>,
+    ) -> Entity {
+        let mut shell = commands.spawn(projectile::ProjectileBundle {
+            mesh_material: PbrBundle {
+                mesh: self.mesh.clone(),
+                material: self.material.clone(),
+                transform: Transform::from_translation(position),
+                ..default()
+            },
+            collider: self.collider.clone(),
+            velocity: Velocity {
+                linvel: velocity,
+                ..default()
+            },
+            lifetime: self.lifetime.clone(),
+            explosion: self.explosion,
+            damage: self.damage.clone(),
+            ..default()
+        });
+        shell
+            .insert(projectile::Owner(owner))
+            .insert(projectile::ProximityFuse {
+                radius: self.fuse_radius,
+                blast_radius: self.blast_radius,
+                damage: self.shrapnel_damage,
+                friendly,
+            });
+        if let Some(trail) = self.trail {
+            shell.insert(trail);
+        }
+        shell.id()
+    }
+}
+
+/// Rockets self-destruct after flying for this long, in seconds
+pub const ROCKET_LIFETIME: f32 = 30.0;
+
 struct Rocket {
     collider: Collider,
     mesh: Handle<Mesh>,
     material: Handle<StandardMaterial>,
 
-    lifetime: projectile::Lifetime,
+    lifetime: components::Lifetime,
 
     explosion: projectile::ExplosionEffect,
-    damage: projectile::Damage,
+    damage: components::Damage,
+    trail: Option<Trail>,
 
     light: PointLight,
+    /// Homing rocket turn rate in rad/s
+    turn_rate: f32,
 }
 
 impl Rocket {
@@ -159,76 +862,200 @@ impl Rocket {
                 sectors: 64,
                 stacks: 32,
             })),
-            material: materials.add(StandardMaterial {
-                base_color: Color::rgb(1.0, 0.5, 0.5),
-                unlit: true,
-                ..default()
-            }),
-            lifetime: projectile::Lifetime(30.0),
+            material: materials.add(glowing_material(Color::rgb_linear(10.0, 3.0, 3.0))),
+            lifetime: components::Lifetime(ROCKET_LIFETIME),
             explosion: projectile::ExplosionEffect::Big,
-            damage: projectile::Damage(99),
+            damage: components::Damage(99),
+            trail: Some(Trail::Smoke),
             light: PointLight {
                 intensity: 1500.0,
                 radius,
                 color: Color::rgb(1.0, 0.2, 0.2),
                 ..default()
             },
+            turn_rate: 90_f32.to_radians(),
         }
     }
 
-    fn spawn(&self, commands: &mut Commands, position: Vec3, direction: Vec3, velocity: Vec3) {
-        commands
-            .spawn(projectile::ProjectileBundle {
-                mesh_material: PbrBundle {
-                    mesh: self.mesh.clone(),
-                    material: self.material.clone(),
-                    transform: Transform {
-                        translation: position,
-                        // `Collider::capsule_y` and `shape::Capsule` are both aligned with Vec3::Y axis
-                        rotation: Quat::from_rotation_arc(Vec3::Y, direction),
-                        scale: Vec3::ONE,
-                    },
-                    ..default()
-                },
-                collider: self.collider.clone(),
-                velocity: Velocity {
-                    linvel: velocity,
-                    ..default()
+    fn spawn(
+        &self,
+        commands: &mut Commands,
+        position: Vec3,
+        direction: Vec3,
+        velocity: Vec3,
+        owner: Entity,
+        target: Option<Entity>,
+    ) -> Entity {
+        let mut rocket = commands.spawn(projectile::ProjectileBundle {
+            mesh_material: PbrBundle {
+                mesh: self.mesh.clone(),
+                material: self.material.clone(),
+                transform: Transform {
+                    translation: position,
+                    // `Collider::capsule_y` and `shape::Capsule` are both aligned with Vec3::Y axis
+                    rotation: Quat::from_rotation_arc(Vec3::Y, direction),
+                    scale: Vec3::ONE,
                 },
-                lifetime: self.lifetime.clone(),
-                explosion: self.explosion,
-                damage: self.damage.clone(),
                 ..default()
-            })
+            },
+            collider: self.collider.clone(),
+            velocity: Velocity {
+                linvel: velocity,
+                ..default()
+            },
+            lifetime: self.lifetime.clone(),
+            explosion: self.explosion,
+            damage: self.damage.clone(),
+            ..default()
+        });
+        rocket
+            .insert(projectile::Owner(owner))
+            .insert(projectile::Rocket)
             .with_children(|children| {
-                children.spawn(PointLightBundle {
-                    point_light: self.light.clone(),
-                    ..default()
-                });
+                children
+                    .spawn(PointLightBundle {
+                        point_light: self.light.clone(),
+                        ..default()
+                    })
+                    .insert(DynamicLight);
+            });
+        if let Some(trail) = self.trail {
+            rocket.insert(trail);
+        }
+        if let Some(target) = target {
+            rocket.insert(projectile::Homing {
+                target,
+                turn_rate: self.turn_rate,
             });
+        }
+        rocket.id()
+    }
+}
+
+/// Railgun shot, that is a beam that damages targets along its way as soon as it's fired
+struct Slug {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+
+    /// Beam remains visible for this time
+    lifetime: components::Lifetime,
+
+    explosion: projectile::ExplosionEffect,
+    damage: u32,
+    penetration: usize,
+}
+
+impl Slug {
+    fn new(
+        meshes: &mut ResMut<Assets<Mesh>>,
+        materials: &mut ResMut<Assets<StandardMaterial>>,
+    ) -> Self {
+        Self {
+            // Unit length along Y axis, stretched to the beam length by `projectile::Hitscan`
+            mesh: meshes.add(Mesh::from(shape::Box::new(0.3, 1.0, 0.3))),
+            material: materials.add(glowing_material(Color::rgb_linear(3.0, 7.0, 12.0))),
+            lifetime: components::Lifetime(0.3),
+            explosion: projectile::ExplosionEffect::Big,
+            damage: 60,
+            penetration: 3,
+        }
+    }
+
+    fn spawn(
+        &self,
+        commands: &mut Commands,
+        position: Vec3,
+        direction: Vec3,
+        range: f32,
+        owner: Entity,
+    ) -> Entity {
+        commands
+            .spawn(PbrBundle {
+                mesh: self.mesh.clone(),
+                material: self.material.clone(),
+                transform: Transform {
+                    translation: position,
+                    rotation: Quat::from_rotation_arc(Vec3::Y, direction),
+                    scale: Vec3::ONE,
+                },
+                ..default()
+            })
+            .insert(projectile::Hitscan {
+                range,
+                damage: self.damage,
+                penetration: self.penetration,
+            })
+            .insert(projectile::Owner(owner))
+            .insert(self.explosion)
+            .insert(self.lifetime.clone())
+            .insert(NotShadowCaster)
+            .insert(NotShadowReceiver)
+            .insert(Name::new("Railgun Beam"))
+            .id()
     }
 }
 
+/// Prototypes of all projectiles fired by guns
+#[derive(Resource)]
+struct Projectiles {
+    bullet: Bullet,
+    slug: Slug,
+    flak_shell: FlakShell,
+    rocket: Rocket,
+}
+
 fn setup_projectile(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    commands.insert_resource(Bullet::new(&mut meshes, &mut materials));
-    commands.insert_resource(Rocket::new(&mut meshes, &mut materials));
+    commands.insert_resource(Projectiles {
+        bullet: Bullet::new(&mut meshes, &mut materials),
+        slug: Slug::new(&mut meshes, &mut materials),
+        flak_shell: FlakShell::new(&mut meshes, &mut materials),
+        rocket: Rocket::new(&mut meshes, &mut materials),
+    });
+}
+
+/// Fraction of the gun itself or of its closest ancestor, used to not detonate shells near allies
+fn gun_fraction(
+    entity: Entity,
+    parent_query: &Query<&Parent>,
+    fractions: &Query<&Fraction>,
+) -> Option<Fraction> {
+    std::iter::once(entity)
+        .chain(parent_query.iter_ancestors(entity))
+        .find_map(|e| fractions.get(e).ok())
+        .copied()
 }
 
 fn single_barrel(
     mut commands: Commands,
-    guns: Query<(&GlobalTransform, &Gun, Entity), Without<MultiBarrel>>,
-    bullet: Res<Bullet>,
-    rocket: Res<Rocket>,
+    guns: Query<
+        (
+            &GlobalTransform,
+            &Gun,
+            Option<&Heat>,
+            Option<&Guidance>,
+            Entity,
+            Option<&InIonStorm>,
+        ),
+        Without<MultiBarrel>,
+    >,
+    projectiles: Res<Projectiles>,
     velocity_query: Query<&Velocity>,
     parent_query: Query<&Parent>,
+    fractions: Query<&Fraction>,
+    mut ev_shot_fired: EventWriter<ShotFiredEvent>,
 ) {
-    for (barrel, gun, entity) in guns.iter() {
-        if gun.rate_of_fire_timer.just_finished() {
-            let direction = barrel.forward();
+    for (barrel, gun, heat, guidance, entity, in_storm) in guns.iter() {
+        if gun.fired() {
+            ev_shot_fired.send(ShotFiredEvent {
+                gun: entity,
+                barrel: entity,
+            });
+
+            let direction = scatter(barrel.forward(), spread(heat, in_storm));
 
             // resolve own velocity from parent if any
             let mut gun_velocity = Vec3::ZERO;
@@ -238,49 +1065,168 @@ fn single_barrel(
                     break;
                 }
             }
-            let velocity = direction * gun.speed + gun_velocity;
+            let velocity = direction * gun.speed() + gun_velocity;
+            let owner = parent_query.iter_ancestors(entity).last().unwrap_or(entity);
 
             // todo: move this code somewhere and make it possible to add more different projectiles
-            match gun.projectile {
-                Projectile::Bullet => {
-                    bullet.spawn(&mut commands, barrel.translation(), direction, velocity)
-                }
-                Projectile::Rocket => {
-                    rocket.spawn(&mut commands, barrel.translation(), direction, velocity)
-                }
+            let shot = match gun.projectile {
+                Projectile::Bullet => projectiles.bullet.spawn(
+                    &mut commands,
+                    barrel.translation(),
+                    direction,
+                    velocity,
+                    owner,
+                ),
+                Projectile::Slug => projectiles.slug.spawn(
+                    &mut commands,
+                    barrel.translation(),
+                    direction,
+                    gun.effective_range(),
+                    owner,
+                ),
+                Projectile::FlakShell => projectiles.flak_shell.spawn(
+                    &mut commands,
+                    barrel.translation(),
+                    velocity,
+                    owner,
+                    gun_fraction(entity, &parent_query, &fractions),
+                ),
+                Projectile::Rocket => projectiles.rocket.spawn(
+                    &mut commands,
+                    barrel.translation(),
+                    direction,
+                    velocity,
+                    owner,
+                    guidance.and_then(|guidance| guidance.0),
+                ),
             };
+            if gun.projectile.ballistic() {
+                commands.entity(shot).insert(projectile::Ballistic);
+            }
         }
     }
 }
 
 fn multi_barrel(
     mut commands: Commands,
-    guns: Query<(&Gun, &MultiBarrel)>,
+    guns: Query<(
+        &Gun,
+        &MultiBarrel,
+        Option<&Heat>,
+        Option<&Guidance>,
+        Entity,
+        Option<&InIonStorm>,
+    )>,
     barrel_transforms: Query<&GlobalTransform, With<Barrel>>,
-    projectile: Res<Bullet>,
+    projectiles: Res<Projectiles>,
+    parent_query: Query<&Parent>,
+    fractions: Query<&Fraction>,
+    mut ev_shot_fired: EventWriter<ShotFiredEvent>,
 ) {
-    for (gun, barrels) in guns.iter() {
-        if gun.rate_of_fire_timer.just_finished() {
-            for barrel in barrels.0.iter() {
-                let barrel = barrel_transforms.get(*barrel).unwrap();
-                let direction = barrel.forward();
-                projectile.spawn(
-                    &mut commands,
-                    barrel.translation(),
-                    direction,
-                    direction * gun.speed,
-                );
+    for (gun, barrels, heat, guidance, entity, in_storm) in guns.iter() {
+        if gun.fired() {
+            let owner = parent_query.iter_ancestors(entity).last().unwrap_or(entity);
+            let friendly = gun_fraction(entity, &parent_query, &fractions);
+            // every barrel fires at once, so rockets are launched in salvos
+            for &barrel_entity in barrels.0.iter() {
+                let barrel = barrel_transforms.get(barrel_entity).unwrap();
+                ev_shot_fired.send(ShotFiredEvent {
+                    gun: entity,
+                    barrel: barrel_entity,
+                });
+                let direction = scatter(barrel.forward(), spread(heat, in_storm));
+                let velocity = direction * gun.speed();
+                let shot = match gun.projectile {
+                    Projectile::Bullet => projectiles.bullet.spawn(
+                        &mut commands,
+                        barrel.translation(),
+                        direction,
+                        velocity,
+                        owner,
+                    ),
+                    Projectile::Slug => projectiles.slug.spawn(
+                        &mut commands,
+                        barrel.translation(),
+                        direction,
+                        gun.effective_range(),
+                        owner,
+                    ),
+                    Projectile::FlakShell => projectiles.flak_shell.spawn(
+                        &mut commands,
+                        barrel.translation(),
+                        velocity,
+                        owner,
+                        friendly,
+                    ),
+                    Projectile::Rocket => projectiles.rocket.spawn(
+                        &mut commands,
+                        barrel.translation(),
+                        direction,
+                        velocity,
+                        owner,
+                        guidance.and_then(|guidance| guidance.0),
+                    ),
+                };
+                if gun.projectile.ballistic() {
+                    commands.entity(shot).insert(projectile::Ballistic);
+                }
+            }
+        }
+    }
+}
+
+fn recoil(
+    mut commands: Commands,
+    guns: Query<(&Gun, &Recoil, &GlobalTransform, Entity)>,
+    parent_query: Query<&Parent>,
+    mut bodies: Query<(&RigidBody, Option<&mut KinematicPush>)>,
+) {
+    for (gun, recoil, transform, entity) in guns.iter() {
+        if gun.fired() {
+            // the closest body up the hierarchy takes the whole impulse
+            let body = std::iter::once(entity)
+                .chain(parent_query.iter_ancestors(entity))
+                .find(|&e| bodies.contains(e));
+            let Some(body) = body else {
+                continue;
+            };
+            let impulse = -transform.forward() * recoil.0;
+            match bodies.get_mut(body) {
+                Ok((RigidBody::Dynamic, _)) => {
+                    commands.entity(body).insert(ExternalImpulse {
+                        impulse,
+                        torque_impulse: Vec3::ZERO,
+                    });
+                }
+                Ok((_, Some(mut push))) => push.0 += impulse / KINEMATIC_MASS,
+                _ => {}
             }
         }
     }
 }
 
+/// Guns firing projectiles while their `Trigger` is pulled, limited by rate of fire and heat.
 pub struct GunPlugin;
 impl Plugin for GunPlugin {
     fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<ModifierPlugin>() {
+            app.add_plugin(ModifierPlugin);
+        }
         app.add_startup_system(setup_projectile)
-            .add_system(check_trigger)
-            .add_system(single_barrel)
-            .add_system(multi_barrel);
+            .add_event::<ShotFiredEvent>()
+            .register_type::<Trigger>()
+            .register_type::<Guidance>()
+            .register_type::<Gun>()
+            .register_type::<Heat>()
+            .register_type::<Ammo>()
+            .register_type::<Charge>()
+            .register_type::<Recoil>()
+            .register_gltf_component("Barrel", || Barrel)
+            .add_system(check_trigger.label(TriggerSystems))
+            .add_system(single_barrel.label(FireSystems).after(TriggerSystems))
+            .add_system(multi_barrel.label(FireSystems).after(TriggerSystems))
+            .add_system(recoil.after(TriggerSystems))
+            .add_system(barrel_heat.after(single_barrel).after(multi_barrel))
+            .add_system(spend_ammo.after(single_barrel).after(multi_barrel));
     }
 }
@@ -0,0 +1,348 @@
+//! Environmental hazards pulling or pushing everything around, like black holes and magnetic
+//! anomalies placed by the `Scenario`, or blasts of destroyed reactors. Their cores damage ships
+//! inside, so drones plan their paths around them, and they bend the view around them with the
+//! post-processing lens.
+
+use bevy::{
+    pbr::{NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+};
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    components::{HitPoints, KinematicPush, Lifetime, Pooled, Respawnable},
+    display::SecondaryCamera,
+    post_process::{self, PostEffects},
+    projectile::{self, Armor, DamageEvent, DamageSystems, Shield},
+    scenario::Scenario,
+};
+
+/// Blast of a destroyed reactor fades out in this time, in seconds
+const BLAST_TIME: f32 = 0.5;
+/// Lens strength per meter of the core radius, so bigger wells bend the view more
+const LENS_STRENGTH: f32 = 0.002;
+
+/// Radial force field. Everything within the `radius` is accelerated towards the center, or away
+/// from it with a negative `strength`, and ships in the core take damage.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct GravityWell {
+    /// Acceleration at the center in m/s², fading out linearly towards the `radius`.
+    /// Negative values push away.
+    pub strength: f32,
+    /// In meters
+    pub radius: f32,
+    /// Ships closer than this to the center take `core_damage`, in meters
+    pub core_radius: f32,
+    /// Damage per second
+    pub core_damage: f32,
+    /// Fraction of damage accumulated between frames
+    #[reflect(ignore)]
+    pending_damage: f32,
+}
+
+impl GravityWell {
+    pub fn new(strength: f32, radius: f32) -> Self {
+        Self {
+            strength,
+            radius,
+            ..default()
+        }
+    }
+
+    pub fn with_core(self, core_radius: f32, core_damage: f32) -> Self {
+        Self {
+            core_radius,
+            core_damage,
+            ..self
+        }
+    }
+
+    /// Acceleration of a body at the `offset` from the center
+    pub fn acceleration(&self, offset: Vec3) -> Vec3 {
+        let distance = offset.length();
+        if distance >= self.radius || distance <= f32::EPSILON {
+            return Vec3::ZERO;
+        }
+        -offset / distance * self.strength * (1.0 - distance / self.radius)
+    }
+
+    /// Drones keep out of this sphere, `None` for wells pushing away
+    pub fn avoid_radius(&self) -> Option<f32> {
+        (self.strength > 0.0).then_some(2.0 * self.core_radius)
+    }
+}
+
+/// Hazard presets for scenarios.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HazardKind {
+    /// Strong pull with a deadly core
+    #[default]
+    BlackHole,
+    /// Weaker pull over a wide area, the core only burns shields and hulls slowly
+    MagneticAnomaly,
+}
+
+impl HazardKind {
+    fn well(self) -> GravityWell {
+        match self {
+            HazardKind::BlackHole => GravityWell::new(40.0, 600.0).with_core(40.0, 50.0),
+            HazardKind::MagneticAnomaly => GravityWell::new(15.0, 800.0).with_core(60.0, 5.0),
+        }
+    }
+
+    /// Color of the core, HDR for the bloom
+    fn core_color(self) -> Color {
+        match self {
+            HazardKind::BlackHole => Color::BLACK,
+            HazardKind::MagneticAnomaly => Color::rgb_linear(1.0, 2.0, 6.0),
+        }
+    }
+}
+
+/// Hazard placed by the `Scenario`.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct HazardConfig {
+    #[serde(default)]
+    pub kind: HazardKind,
+    /// World position in meters
+    pub position: [f32; 3],
+}
+
+/// Ship with a reactor, that blows up with a blast pushing everything around once destroyed.
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct Reactor;
+
+#[cfg(test)]
+mod tests {
+    use super::{GravityWell, HazardConfig, HazardKind};
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_acceleration() {
+        let pull = GravityWell::new(10.0, 100.0);
+        assert_eq!(pull.acceleration(Vec3::X * 50.0), Vec3::NEG_X * 5.0);
+        assert_eq!(pull.acceleration(Vec3::X * 150.0), Vec3::ZERO);
+        // nowhere to pull in the very center
+        assert_eq!(pull.acceleration(Vec3::ZERO), Vec3::ZERO);
+
+        let push = GravityWell::new(-10.0, 100.0);
+        assert_eq!(push.acceleration(Vec3::Y * 50.0), Vec3::Y * 5.0);
+        assert_eq!(push.avoid_radius(), None);
+    }
+
+    #[test]
+    fn test_parse_hazard() {
+        let hazard: HazardConfig =
+            serde_json::from_str(r#"{"kind": "magnetic_anomaly", "position": [0, 10, 0]}"#)
+                .unwrap();
+        assert_eq!(hazard.kind, HazardKind::MagneticAnomaly);
+        let hazard: HazardConfig = serde_json::from_str(r#"{"position": [0, 0, 0]}"#).unwrap();
+        assert_eq!(hazard.kind, HazardKind::BlackHole);
+    }
+}
+
+fn spawn_hazards(
+    mut commands: Commands,
+    scenario: Option<Res<Scenario>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let Some(scenario) = scenario else {
+        return;
+    };
+    // Unit sphere, scaled to the size of each hazard
+    let sphere = meshes.add(Mesh::from(shape::UVSphere {
+        radius: 1.0,
+        sectors: 32,
+        stacks: 16,
+    }));
+    for hazard in scenario.hazards.iter() {
+        let well = hazard.kind.well();
+        let core = StandardMaterial {
+            base_color: Color::BLACK,
+            emissive: hazard.kind.core_color(),
+            ..default()
+        };
+        // Translucent shell around the core, where the pull is the strongest
+        let shell = StandardMaterial {
+            base_color: Color::rgba(0.4, 0.5, 1.0, 0.08),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        };
+        commands
+            .spawn(SpatialBundle::from_transform(Transform::from_translation(
+                Vec3::from(hazard.position),
+            )))
+            .insert(Name::new(format!("{:?}", hazard.kind)))
+            .with_children(|parent| {
+                for (material, radius) in
+                    [(core, well.core_radius), (shell, 3.0 * well.core_radius)]
+                {
+                    parent
+                        .spawn(PbrBundle {
+                            mesh: sphere.clone(),
+                            material: materials.add(material),
+                            transform: Transform::from_scale(Vec3::splat(radius)),
+                            ..default()
+                        })
+                        .insert(NotShadowCaster)
+                        .insert(NotShadowReceiver);
+                }
+            })
+            .insert(well);
+    }
+}
+
+/// Wells pull dynamic bodies, including projectiles, and kinematic ones taking a
+/// `KinematicPush`, like the player. Other kinematic bodies follow their own routes.
+fn apply_wells(
+    time: Res<Time>,
+    wells: Query<(&GravityWell, &GlobalTransform)>,
+    mut bodies: Query<(
+        &GlobalTransform,
+        &RigidBody,
+        Option<&mut Velocity>,
+        Option<&mut KinematicPush>,
+    )>,
+) {
+    if wells.is_empty() {
+        return;
+    }
+    let dt = time.delta_seconds();
+    for (transform, body, velocity, push) in bodies.iter_mut() {
+        let position = transform.translation();
+        let acceleration: Vec3 = wells
+            .iter()
+            .map(|(well, center)| well.acceleration(position - center.translation()))
+            .sum();
+        if acceleration == Vec3::ZERO {
+            continue;
+        }
+        match (body, velocity, push) {
+            (RigidBody::Dynamic, Some(mut velocity), _) => velocity.linvel += acceleration * dt,
+            // The flight assist of the player overwrites the velocity every frame
+            (_, _, Some(mut push)) => push.0 += acceleration * dt,
+            _ => (),
+        }
+    }
+}
+
+fn core_damage(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut wells: Query<(&mut GravityWell, &GlobalTransform)>,
+    mut ships: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &mut HitPoints,
+            Option<&mut Shield>,
+            Option<&Armor>,
+            Option<&Respawnable>,
+            Option<&Pooled>,
+        ),
+        Without<Parent>,
+    >,
+    mut ev_damage: EventWriter<DamageEvent>,
+) {
+    for (mut well, center) in wells.iter_mut() {
+        well.pending_damage += well.core_damage * time.delta_seconds();
+        let damage = well.pending_damage.floor();
+        if damage < 1.0 {
+            continue;
+        }
+        well.pending_damage -= damage;
+
+        let center = center.translation();
+        for (target, transform, hp, shield, armor, respawnable, pooled) in ships.iter_mut() {
+            let position = transform.translation();
+            if hp.dead() || position.distance(center) > well.core_radius {
+                continue;
+            }
+            let kept = respawnable.is_some() || pooled.is_some();
+            let (damage, absorbed, destroyed) = projectile::apply_damage(
+                &mut commands,
+                target,
+                damage as u32,
+                Some(hp),
+                shield,
+                armor,
+                kept,
+            );
+            ev_damage.send(DamageEvent {
+                target,
+                attacker: None,
+                attacker_position: center,
+                position,
+                damage,
+                absorbed,
+                destroyed,
+            });
+        }
+    }
+}
+
+/// Destroyed reactors leave a short blast behind
+fn reactor_blasts(
+    mut commands: Commands,
+    mut ev_damage: EventReader<DamageEvent>,
+    reactors: Query<&GlobalTransform, With<Reactor>>,
+) {
+    for ev in ev_damage.iter().filter(|ev| ev.destroyed) {
+        let Ok(transform) = reactors.get(ev.target) else {
+            continue;
+        };
+        commands
+            .spawn(TransformBundle::from(Transform::from_translation(
+                transform.translation(),
+            )))
+            .insert(GravityWell::new(-60.0, 150.0).with_core(15.0, 40.0))
+            .insert(Lifetime(BLAST_TIME))
+            .insert(Name::new("Reactor Blast"));
+    }
+}
+
+/// The view is bent around the well that looks the biggest on the screen
+fn lens_wells(
+    mut effects: ResMut<PostEffects>,
+    camera: Query<(&Camera, &GlobalTransform), (With<Camera3d>, Without<SecondaryCamera>)>,
+    wells: Query<(&GravityWell, &GlobalTransform)>,
+) {
+    let Ok((camera, camera_transform)) = camera.get_single() else {
+        return;
+    };
+    let lens = wells
+        .iter()
+        .filter(|(well, _)| well.strength > 0.0)
+        .filter_map(|(well, transform)| {
+            let center = transform.translation();
+            let strength = well.core_radius * LENS_STRENGTH;
+            post_process::screen_spot(camera, camera_transform, center, well.core_radius, strength)
+        })
+        .max_by(|a, b| a.z.total_cmp(&b.z))
+        .unwrap_or(Vec4::ZERO);
+    if effects.lens != lens {
+        effects.lens = lens;
+    }
+}
+
+/// `GravityWell`s from the `Scenario` and blasts of destroyed reactors.
+pub struct HazardPlugin;
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<GravityWell>()
+            .register_type::<Reactor>()
+            .init_resource::<PostEffects>()
+            .add_event::<DamageEvent>()
+            .add_startup_system(spawn_hazards)
+            .add_system(apply_wells)
+            .add_system(core_damage.label(DamageSystems))
+            .add_system(reactor_blasts.after(DamageSystems))
+            .add_system(lens_wells);
+    }
+}
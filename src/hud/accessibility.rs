@@ -0,0 +1,217 @@
+use bevy::prelude::*;
+
+use crate::{components::Fraction, settings::Settings};
+
+/// Panel background in the high contrast mode
+const HIGH_CONTRAST_BACKGROUND: Color = Color::rgba(0.0, 0.0, 0.0, 0.9);
+
+/// Color vision deficiency the HUD colors are adapted to.
+/// Adapted palettes are based on the Okabe-Ito colors, which stay distinguishable for it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Palette {
+    #[default]
+    Standard,
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+/// Meaning of a HUD color, the actual color is picked by the [`Palette`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Indicator {
+    Hostile,
+    Friendly,
+    PrimaryTarget,
+    QueuedTarget,
+    Warning,
+    Danger,
+    Good,
+}
+
+impl Palette {
+    fn next(self) -> Self {
+        match self {
+            Palette::Standard => Palette::Protanopia,
+            Palette::Protanopia => Palette::Deuteranopia,
+            Palette::Deuteranopia => Palette::Tritanopia,
+            Palette::Tritanopia => Palette::Standard,
+        }
+    }
+
+    pub fn color(self, indicator: Indicator) -> Color {
+        use Indicator::*;
+        let orange = Color::rgb_u8(230, 159, 0);
+        let sky_blue = Color::rgb_u8(86, 180, 233);
+        let bluish_green = Color::rgb_u8(0, 158, 115);
+        let yellow = Color::rgb_u8(240, 228, 66);
+        let blue = Color::rgb_u8(0, 114, 178);
+        let vermillion = Color::rgb_u8(213, 94, 0);
+        let reddish_purple = Color::rgb_u8(204, 121, 167);
+        match self {
+            Palette::Standard => match indicator {
+                Hostile | Danger => Color::RED,
+                Friendly | Good => Color::GREEN,
+                PrimaryTarget => Color::YELLOW,
+                QueuedTarget => Color::CYAN,
+                Warning => Color::ORANGE,
+            },
+            // red looks dark and close to green, so hostiles are orange and allies are blue
+            Palette::Protanopia => match indicator {
+                Hostile => orange,
+                Friendly | Good | QueuedTarget => sky_blue,
+                PrimaryTarget | Warning => yellow,
+                Danger => blue,
+            },
+            Palette::Deuteranopia => match indicator {
+                Hostile => vermillion,
+                Friendly | Good => sky_blue,
+                PrimaryTarget => yellow,
+                QueuedTarget => reddish_purple,
+                Warning => orange,
+                Danger => blue,
+            },
+            // blue is close to green and yellow to violet, while red stays distinct
+            Palette::Tritanopia => match indicator {
+                Hostile | Danger => Color::rgb_u8(220, 50, 32),
+                Friendly | Good | QueuedTarget => bluish_green,
+                PrimaryTarget => reddish_purple,
+                Warning => vermillion,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AccessibilitySettings {
+    pub palette: Palette,
+    /// Opaque panels and thicker reticle
+    pub high_contrast: bool,
+    /// Factions are told apart by shapes of brackets and bars, not only by colors
+    pub shape_coding: bool,
+    /// Captions for important sounds, like a missile warning
+    pub captions: bool,
+}
+
+impl AccessibilitySettings {
+    pub fn color(&self, indicator: Indicator) -> Color {
+        self.palette.color(indicator)
+    }
+
+    /// Color of the `fraction` from the point of view of `own` one
+    pub fn faction_color(&self, fraction: Fraction, own: Option<Fraction>) -> Color {
+        self.color(if Some(fraction) == own {
+            Indicator::Friendly
+        } else {
+            Indicator::Hostile
+        })
+    }
+
+    /// Bracket around a target of the `fraction`
+    pub fn bracket(&self, fraction: Option<Fraction>) -> &'static str {
+        match fraction {
+            Some(Fraction::Drones) if self.shape_coding => "<  >",
+            Some(Fraction::Turrets) if self.shape_coding => "{  }",
+            _ => "[  ]",
+        }
+    }
+
+    /// Filled cell of bars like the target hull
+    pub fn bar_fill(&self, fraction: Option<Fraction>) -> char {
+        match fraction {
+            Some(Fraction::Turrets) if self.shape_coding => '=',
+            _ => '#',
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccessibilitySettings, Indicator, Palette};
+    use crate::components::Fraction;
+
+    #[test]
+    fn test_palettes_keep_indicators_apart() {
+        let mut palette = Palette::Standard;
+        loop {
+            let color = |indicator| palette.color(indicator);
+            assert_ne!(color(Indicator::Hostile), color(Indicator::Friendly));
+            assert_ne!(
+                color(Indicator::PrimaryTarget),
+                color(Indicator::QueuedTarget)
+            );
+            assert_ne!(color(Indicator::Warning), color(Indicator::Danger));
+            assert_ne!(color(Indicator::Good), color(Indicator::Warning));
+
+            palette = palette.next();
+            if palette == Palette::Standard {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_shape_coding() {
+        let mut settings = AccessibilitySettings::default();
+        let drones = Some(Fraction::Drones);
+        let turrets = Some(Fraction::Turrets);
+        assert_eq!(settings.bracket(drones), settings.bracket(turrets));
+        assert_eq!(settings.bar_fill(drones), settings.bar_fill(turrets));
+
+        settings.shape_coding = true;
+        assert_ne!(settings.bracket(drones), settings.bracket(turrets));
+        assert_ne!(settings.bar_fill(drones), settings.bar_fill(turrets));
+        assert_eq!(settings.bracket(None), "[  ]");
+    }
+}
+
+/// Background of a HUD panel, replaced with an opaque one in the high contrast mode.
+#[derive(Component)]
+pub struct PanelBackground(pub Color);
+
+fn apply_contrast(
+    settings: Res<Settings>,
+    mut panels: Query<(&PanelBackground, &mut BackgroundColor)>,
+    added: Query<(), Added<PanelBackground>>,
+) {
+    if !settings.is_changed() && added.is_empty() {
+        return;
+    }
+
+    for (panel, mut background) in panels.iter_mut() {
+        background.0 = if settings.accessibility.high_contrast {
+            HIGH_CONTRAST_BACKGROUND
+        } else {
+            panel.0
+        };
+    }
+}
+
+fn accessibility_controls(keys: Res<Input<KeyCode>>, mut settings: ResMut<Settings>) {
+    if keys.just_pressed(KeyCode::F5) {
+        settings.accessibility.palette = settings.accessibility.palette.next();
+        info!("HUD palette: {:?}", settings.accessibility.palette);
+    }
+    if keys.just_pressed(KeyCode::F6) {
+        settings.accessibility.high_contrast = !settings.accessibility.high_contrast;
+        info!(
+            "HUD high contrast: {}",
+            settings.accessibility.high_contrast
+        );
+    }
+    if keys.just_pressed(KeyCode::F7) {
+        settings.accessibility.shape_coding = !settings.accessibility.shape_coding;
+        info!("HUD shape coding: {}", settings.accessibility.shape_coding);
+    }
+    if keys.just_pressed(KeyCode::F8) {
+        settings.accessibility.captions = !settings.accessibility.captions;
+        info!("HUD captions: {}", settings.accessibility.captions);
+    }
+}
+
+pub(super) struct AccessibilityPlugin;
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(apply_contrast)
+            .add_system(accessibility_controls);
+    }
+}
@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+
+use super::{text_bar, HudAnchor, HudWidget, Indicator, PanelBackground};
+use crate::{
+    boss::{Boss, SpinalBeam},
+    components::{Fraction, HitPoints},
+    localization::Localization,
+    player::Player,
+    settings::Settings,
+};
+
+/// Number of cells in the hull bar, it's wider than the target panel ones
+const BAR_CELLS: usize = 40;
+/// Number of cells in the beam charge bar
+const CHARGE_CELLS: usize = 10;
+
+/// Panel of the boss bar, collapsed while there is no boss around
+#[derive(Component)]
+struct BossPanel;
+
+#[derive(Component)]
+struct BossText;
+
+fn setup_boss_bar(mut commands: Commands, assets: Res<AssetServer>) {
+    let background = Color::rgba(0.1, 0.1, 0.1, 0.6);
+    // The widget itself is shown by the layout, so the panel inside it is collapsed instead
+    commands
+        .spawn(NodeBundle {
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(HudWidget::essential(HudAnchor::Top))
+        .insert(Name::new("Boss Bar"))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(10.0)),
+                        display: Display::None,
+                        ..default()
+                    },
+                    background_color: background.into(),
+                    ..default()
+                })
+                .insert(PanelBackground(background))
+                .insert(BossPanel)
+                .with_children(|parent| {
+                    parent
+                        .spawn(TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font: assets.load("fonts/FiraMono-Medium.ttf"),
+                                font_size: 20.0,
+                                color: Color::WHITE,
+                            },
+                        ))
+                        .insert(BossText);
+                });
+        });
+}
+
+fn update_boss_bar(
+    localization: Res<Localization>,
+    settings: Res<Settings>,
+    player: Query<&Fraction, With<Player>>,
+    bosses: Query<(&Name, &Boss, &HitPoints, &Fraction, &Children)>,
+    beams: Query<&SpinalBeam>,
+    mut panel: Query<&mut Style, With<BossPanel>>,
+    mut text: Query<&mut Text, With<BossText>>,
+) {
+    let (Ok(mut style), Ok(mut text)) = (panel.get_single_mut(), text.get_single_mut()) else {
+        return;
+    };
+    let boss = bosses.iter().next();
+    let display = if boss.is_some() {
+        Display::Flex
+    } else {
+        Display::None
+    };
+    if style.display != display {
+        style.display = display;
+    }
+    let Some((name, boss, hp, &fraction, children)) = boss else {
+        return;
+    };
+
+    let accessibility = &settings.accessibility;
+    let phase = localization.get(boss.phase.key());
+    let bar = text_bar(
+        hp.percent() as f32 / 100.0,
+        BAR_CELLS,
+        accessibility.bar_fill(Some(fraction)),
+    );
+    let mut lines = vec![
+        localization.format("boss-title", &[("name", &name.as_str()), ("phase", &phase)]),
+        localization.format("boss-hull", &[("bar", &bar), ("hp", &hp.percent())]),
+        localization.format(
+            "boss-subsystems",
+            &[("generators", &boss.generators), ("turrets", &boss.turrets)],
+        ),
+    ];
+    let charge = children
+        .iter()
+        .filter_map(|&child| beams.get(child).ok())
+        .find_map(SpinalBeam::charge);
+    if let Some(charge) = charge {
+        let bar = text_bar(charge, CHARGE_CELLS, '#');
+        lines.push(localization.format("boss-beam-charging", &[("bar", &bar)]));
+    }
+    text.sections[0].value = lines.join("\n");
+
+    // the whole bar turns into a warning while the beam charges
+    text.sections[0].style.color = match charge {
+        Some(_) => accessibility.color(Indicator::Danger),
+        None => accessibility.faction_color(fraction, player.get_single().ok().copied()),
+    };
+}
+
+pub(super) struct BossBarPlugin;
+impl Plugin for BossBarPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_boss_bar)
+            .add_system(update_boss_bar);
+    }
+}
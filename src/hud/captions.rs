@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+
+use super::{HudAnchor, HudWidget, PanelBackground};
+use crate::{
+    audio_cues::{AudioCue, AudioCueEvent},
+    localization::Localization,
+    player::Player,
+    settings::Settings,
+};
+
+/// Time the caption is shown, in seconds
+const CAPTION_TIME: f32 = 2.5;
+/// Caption fades out during this last part of its lifetime, in seconds
+const FADE_TIME: f32 = 0.5;
+
+#[derive(Component)]
+struct Captions {
+    font: Handle<Font>,
+}
+
+/// On-screen cue for a sound, a repeated cue replaces the previous caption.
+#[derive(Component)]
+struct Caption {
+    cue: AudioCue,
+    time_left: f32,
+}
+
+/// Localization key of the direction to `local` point in the camera space, where the camera
+/// looks along -Z with Y up
+fn direction_key(local: Vec3) -> &'static str {
+    let abs = local.abs();
+    if abs.x >= abs.y && abs.x >= abs.z {
+        if local.x > 0.0 {
+            "cue-right"
+        } else {
+            "cue-left"
+        }
+    } else if abs.y >= abs.z {
+        if local.y > 0.0 {
+            "cue-above"
+        } else {
+            "cue-below"
+        }
+    } else if local.z > 0.0 {
+        "cue-behind"
+    } else {
+        "cue-ahead"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::direction_key;
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_direction_key() {
+        assert_eq!(direction_key(Vec3::new(0.0, 0.0, -10.0)), "cue-ahead");
+        assert_eq!(direction_key(Vec3::new(1.0, 0.0, 10.0)), "cue-behind");
+        assert_eq!(direction_key(Vec3::new(-5.0, 1.0, -2.0)), "cue-left");
+        assert_eq!(direction_key(Vec3::new(5.0, 1.0, 2.0)), "cue-right");
+        assert_eq!(direction_key(Vec3::new(1.0, 8.0, -2.0)), "cue-above");
+        assert_eq!(direction_key(Vec3::new(1.0, -8.0, 2.0)), "cue-below");
+    }
+}
+
+fn setup_captions(mut commands: Commands, assets: Res<AssetServer>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                margin: UiRect {
+                    bottom: Val::Px(80.0),
+                    ..default()
+                },
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(HudWidget::essential(HudAnchor::Bottom))
+        .insert(Captions {
+            font: assets.load("fonts/FiraMono-Medium.ttf"),
+        })
+        .insert(Name::new("Captions"));
+}
+
+fn spawn_captions(
+    mut commands: Commands,
+    localization: Res<Localization>,
+    settings: Res<Settings>,
+    mut ev_cue: EventReader<AudioCueEvent>,
+    player: Query<&GlobalTransform, With<Player>>,
+    captions: Query<(Entity, &Caption)>,
+    container: Query<(Entity, &Captions)>,
+) {
+    if !settings.accessibility.captions {
+        ev_cue.clear();
+        return;
+    }
+    let Ok((container, Captions { font })) = container.get_single() else {
+        return;
+    };
+    let player = player.get_single().ok();
+
+    let mut shown = Vec::new();
+    for ev in ev_cue.iter() {
+        if shown.contains(&ev.cue) {
+            continue;
+        }
+        shown.push(ev.cue);
+
+        let direction = ev.position.zip(player).map(|(position, player)| {
+            let (_, rotation, translation) = player.to_scale_rotation_translation();
+            localization.get(direction_key(rotation.inverse() * (position - translation)))
+        });
+        let text = match direction {
+            Some(direction) => localization.format(
+                &format!("{}-from", ev.cue.key()),
+                &[("direction", &direction)],
+            ),
+            None => localization.get(ev.cue.key()).to_owned(),
+        };
+
+        for (entity, caption) in captions.iter() {
+            if caption.cue == ev.cue {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+
+        let background = Color::rgba(0.0, 0.0, 0.0, 0.6);
+        commands.entity(container).with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::all(Val::Px(2.0)),
+                        padding: UiRect::all(Val::Px(5.0)),
+                        ..default()
+                    },
+                    background_color: background.into(),
+                    ..default()
+                })
+                .insert(PanelBackground(background))
+                .insert(Caption {
+                    cue: ev.cue,
+                    time_left: CAPTION_TIME,
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        text,
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 22.0,
+                            color: Color::WHITE,
+                        },
+                    ));
+                });
+        });
+    }
+}
+
+fn update_captions(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut captions: Query<(Entity, &mut Caption, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    for (entity, mut caption, children) in captions.iter_mut() {
+        caption.time_left -= time.delta_seconds();
+        if caption.time_left <= 0.0 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let alpha = (caption.time_left / FADE_TIME).min(1.0);
+        for &child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(child) {
+                text.sections[0].style.color.set_a(alpha);
+            }
+        }
+    }
+}
+
+/// Captions of `AudioCueEvent`s, shown with the captions accessibility option.
+pub(super) struct CaptionsPlugin;
+impl Plugin for CaptionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AudioCueEvent>()
+            .add_startup_system(setup_captions)
+            .add_system(spawn_captions)
+            .add_system(update_captions.after(spawn_captions));
+    }
+}
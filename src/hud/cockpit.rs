@@ -0,0 +1,600 @@
+use bevy::{
+    core_pipeline::clear_color::ClearColorConfig,
+    pbr::{NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+    render::{camera::RenderTarget, view::RenderLayers},
+};
+
+use super::{fire_cycle_key, fire_cycle_level, text_bar};
+use crate::{
+    components::{Fraction, HitPoints},
+    display::render_texture,
+    drone::Drone,
+    gun::{Charge, Gun, Heat},
+    ion_storm::{self, InIonStorm},
+    localization::Localization,
+    player::{
+        self, FocusFireEvent, HeavyWeapon, LockedTarget, Player, PlayerCamera, PrimaryWeapon,
+        Throttle, UiPointer,
+    },
+    projectile::Shield,
+    settings::Settings,
+    turret::TurretKind,
+};
+
+/// MFD textures are square, with this side in pixels
+const MFD_RESOLUTION: u32 = 256;
+/// Side of the MFD screens, in meters
+const MFD_SIZE: f32 = 0.16;
+const FONT_SIZE: f32 = 16.0;
+/// Number of cells in the MFD bars
+const BAR_CELLS: usize = 8;
+/// Contacts farther than this aren't shown on the radar, in meters
+const RADAR_RANGE: f32 = 1500.0;
+/// Radius of the radar plot on the MFD texture, in pixels
+const RADAR_RADIUS: f32 = 110.0;
+/// The farthest contacts are left out once there are more
+const MAX_CONTACTS: usize = 64;
+/// Clicks on the radar pick contacts up to this far from the cursor, in MFD pixels
+const CLICK_RADIUS: f32 = 12.0;
+
+/// Multi-function display on the cockpit dashboard.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+enum Mfd {
+    Radar,
+    Systems,
+    Target,
+}
+
+impl Mfd {
+    const ALL: [Mfd; 3] = [Mfd::Radar, Mfd::Systems, Mfd::Target];
+
+    /// Render layer with the content of the screen, as the main view uses the first one
+    fn layer(self) -> RenderLayers {
+        RenderLayers::layer(1 + self as u8)
+    }
+
+    /// Position of the screen relative to the camera, in meters
+    fn position(self) -> Vec3 {
+        let x = match self {
+            Mfd::Radar => -0.3,
+            Mfd::Systems => 0.0,
+            Mfd::Target => 0.3,
+        };
+        Vec3::new(x, -0.22, -0.7)
+    }
+}
+
+/// Cockpit interior, a child of the player camera, so it moves along to another ship
+#[derive(Component)]
+struct Cockpit;
+
+/// Text of an MFD page
+#[derive(Component)]
+struct MfdText(Mfd);
+
+/// Screen quad showing an MFD
+#[derive(Component)]
+struct MfdScreen(Mfd);
+
+/// Radar blip of the entity
+#[derive(Component)]
+struct RadarContact(Option<Entity>);
+
+/// Kind of a radar contact, each one plotted with its own marker shape
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ContactKind {
+    /// Drones and other small ships
+    Fighter,
+    Turret,
+    /// Anything bigger, like the capital ship or the artillery platform
+    Capital,
+}
+
+impl ContactKind {
+    /// Size of the marker sprite in MFD pixels and its rotation, locked contacts are bigger
+    fn marker(self, locked: bool) -> (Vec2, f32) {
+        let scale = if locked { 2.0 } else { 1.0 };
+        let (size, rotation) = match self {
+            // Diamond
+            ContactKind::Fighter => (Vec2::splat(5.0), std::f32::consts::FRAC_PI_4),
+            ContactKind::Turret => (Vec2::splat(5.0), 0.0),
+            // Bar across the plot
+            ContactKind::Capital => (Vec2::new(10.0, 4.0), 0.0),
+        };
+        (size * scale, rotation)
+    }
+}
+
+/// Position of a contact at `local` position relative to the player on the radar plot, with the
+/// forward direction up. `None` if it's out of the radar range.
+fn radar_position(local: Vec3) -> Option<Vec2> {
+    let plane = Vec2::new(local.x, -local.z);
+    (plane.length() <= RADAR_RANGE).then(|| plane / RADAR_RANGE * RADAR_RADIUS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{radar_position, ContactKind, RADAR_RADIUS, RADAR_RANGE};
+    use bevy::prelude::{Vec2, Vec3};
+
+    #[test]
+    fn test_radar_position() {
+        // ahead is up, regardless of the altitude
+        let ahead = radar_position(Vec3::new(0.0, 50.0, -RADAR_RANGE / 2.0)).unwrap();
+        assert_eq!(ahead, Vec2::new(0.0, RADAR_RADIUS / 2.0));
+        let right = radar_position(Vec3::new(RADAR_RANGE, 0.0, 0.0)).unwrap();
+        assert_eq!(right, Vec2::new(RADAR_RADIUS, 0.0));
+        assert_eq!(radar_position(Vec3::Z * 2.0 * RADAR_RANGE), None);
+    }
+
+    #[test]
+    fn test_markers() {
+        let kinds = [
+            ContactKind::Fighter,
+            ContactKind::Turret,
+            ContactKind::Capital,
+        ];
+        // every kind is told apart by the shape, locked or not
+        for (i, a) in kinds.iter().enumerate() {
+            for b in &kinds[i + 1..] {
+                assert_ne!(a.marker(false), b.marker(false));
+                assert_ne!(a.marker(true), b.marker(true));
+            }
+            assert!(a.marker(true).0.x > a.marker(false).0.x);
+        }
+    }
+}
+
+fn spawn_cockpit(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    camera: Query<Entity, Added<PlayerCamera>>,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    let font = assets.load("fonts/FiraMono-Medium.ttf");
+    let text_style = TextStyle {
+        font,
+        font_size: FONT_SIZE,
+        color: Color::rgb(0.3, 1.0, 0.4),
+    };
+    let frame = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.08, 0.08, 0.1),
+        perceptual_roughness: 0.6,
+        ..default()
+    });
+    let screen = meshes.add(Mesh::from(shape::Quad::new(Vec2::splat(MFD_SIZE))));
+
+    let mut screens = Vec::new();
+    for mfd in Mfd::ALL {
+        let image = images.add(render_texture(UVec2::splat(MFD_RESOLUTION)));
+        // MFD content is drawn by its own camera to the screen texture
+        commands
+            .spawn(Camera2dBundle {
+                camera: Camera {
+                    priority: -1,
+                    is_active: false,
+                    target: RenderTarget::Image(image.clone()),
+                    ..default()
+                },
+                camera_2d: Camera2d {
+                    clear_color: ClearColorConfig::Custom(Color::rgb(0.0, 0.05, 0.02)),
+                },
+                ..default()
+            })
+            .insert(UiCameraConfig { show_ui: false })
+            .insert(mfd.layer())
+            .insert(mfd)
+            .insert(Name::new(format!("MFD Camera {mfd:?}")));
+
+        let top_left = Vec3::new(-0.45, 0.45, 0.0) * MFD_RESOLUTION as f32;
+        commands
+            .spawn(Text2dBundle {
+                text: Text {
+                    alignment: TextAlignment::TOP_LEFT,
+                    ..Text::from_section("", text_style.clone())
+                },
+                transform: Transform::from_translation(top_left),
+                ..default()
+            })
+            .insert(mfd.layer())
+            .insert(MfdText(mfd));
+
+        // Faces the camera
+        let position = mfd.position();
+        screens.push((
+            PbrBundle {
+                mesh: screen.clone(),
+                material: materials.add(StandardMaterial {
+                    base_color_texture: Some(image),
+                    unlit: true,
+                    ..default()
+                }),
+                transform: Transform::from_translation(position)
+                    .looking_at(2.0 * position, Vec3::Y),
+                ..default()
+            },
+            Name::new(format!("MFD {mfd:?}")),
+            MfdScreen(mfd),
+        ));
+    }
+
+    // Radar plot with the player in the center and contacts around
+    let layer = Mfd::Radar.layer();
+    let line = |size: Vec2| SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgba(0.3, 1.0, 0.4, 0.3),
+            custom_size: Some(size),
+            ..default()
+        },
+        ..default()
+    };
+    commands
+        .spawn(line(Vec2::new(2.0 * RADAR_RADIUS, 1.0)))
+        .insert(layer);
+    commands
+        .spawn(line(Vec2::new(1.0, 2.0 * RADAR_RADIUS)))
+        .insert(layer);
+    for _ in 0..MAX_CONTACTS {
+        commands
+            .spawn(SpriteBundle {
+                visibility: Visibility::INVISIBLE,
+                ..default()
+            })
+            .insert(layer)
+            .insert(RadarContact(None));
+    }
+
+    let parts = [
+        // Dashboard under the screens
+        (
+            shape::Box::new(1.2, 0.24, 0.1),
+            Transform::from_xyz(0.0, -0.24, -0.78),
+        ),
+        // Canopy struts on the sides
+        (
+            shape::Box::new(0.03, 0.8, 0.03),
+            Transform::from_xyz(-0.5, 0.0, -0.75).with_rotation(Quat::from_rotation_z(-0.3)),
+        ),
+        (
+            shape::Box::new(0.03, 0.8, 0.03),
+            Transform::from_xyz(0.5, 0.0, -0.75).with_rotation(Quat::from_rotation_z(0.3)),
+        ),
+    ];
+    commands.entity(camera).with_children(|parent| {
+        parent
+            .spawn(SpatialBundle::INVISIBLE_IDENTITY)
+            .insert(Cockpit)
+            .insert(Name::new("Cockpit"))
+            .with_children(|parent| {
+                for (part, transform) in parts {
+                    parent
+                        .spawn(PbrBundle {
+                            mesh: meshes.add(Mesh::from(part)),
+                            material: frame.clone(),
+                            transform,
+                            ..default()
+                        })
+                        .insert(NotShadowCaster)
+                        .insert(NotShadowReceiver);
+                }
+                for screen in screens {
+                    parent
+                        .spawn(screen)
+                        .insert(NotShadowCaster)
+                        .insert(NotShadowReceiver);
+                }
+            });
+    });
+}
+
+/// Cockpit is shown while the player is alive, MFDs are rendered only when it's visible
+fn apply_cockpit(
+    settings: Res<Settings>,
+    player: Query<&HitPoints, With<Player>>,
+    mut cockpit: Query<&mut Visibility, With<Cockpit>>,
+    mut cameras: Query<&mut Camera, With<Mfd>>,
+) {
+    let alive = player.get_single().map_or(false, |hp| !hp.dead());
+    let visible = settings.hud.cockpit && alive;
+    for mut visibility in cockpit.iter_mut() {
+        if visibility.is_visible != visible {
+            visibility.is_visible = visible;
+        }
+    }
+    for mut camera in cameras.iter_mut() {
+        if camera.is_active != visible {
+            camera.is_active = visible;
+        }
+    }
+}
+
+fn update_radar(
+    settings: Res<Settings>,
+    localization: Res<Localization>,
+    player: Query<(&GlobalTransform, Option<&Fraction>, Option<&InIonStorm>), With<Player>>,
+    contacts: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &Fraction,
+            Option<&LockedTarget>,
+            Option<&Drone>,
+            Option<&TurretKind>,
+            Option<&Parent>,
+        ),
+        (With<HitPoints>, Without<Player>),
+    >,
+    mut sprites: Query<(
+        &mut RadarContact,
+        &mut Transform,
+        &mut Sprite,
+        &mut Visibility,
+    )>,
+    mut texts: Query<(&MfdText, &mut Text)>,
+    mut plotted: Local<Vec<(Entity, Vec2, f32, Color, ContactKind, bool)>>,
+) {
+    if !settings.hud.cockpit {
+        return;
+    }
+    let Ok((player_transform, own_fraction, in_storm)) = player.get_single() else {
+        return;
+    };
+    // Ion storms jam the radar beyond the sensor range
+    let range = in_storm.map_or(RADAR_RANGE, |_| RADAR_RANGE.min(ion_storm::SENSOR_RANGE));
+
+    let to_local = player_transform.affine().inverse();
+    plotted.clear();
+    plotted.extend(contacts.iter().filter_map(
+        |(entity, transform, &fraction, locked, drone, turret, parent)| {
+            // Parts of ships are plotted with the ship, only turrets stand on their own
+            if parent.is_some() && turret.is_none() {
+                return None;
+            }
+            let local = to_local.transform_point3(transform.translation());
+            if local.length() > range {
+                return None;
+            }
+            let position = radar_position(local)?;
+            let color = settings
+                .accessibility
+                .faction_color(fraction, own_fraction.copied());
+            let kind = match (drone, turret) {
+                (Some(_), _) => ContactKind::Fighter,
+                (_, Some(_)) => ContactKind::Turret,
+                _ => ContactKind::Capital,
+            };
+            let distance = local.length();
+            Some((entity, position, distance, color, kind, locked.is_some()))
+        },
+    ));
+    plotted.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+    let mut plotted = plotted.iter();
+    for (mut contact, mut transform, mut sprite, mut visibility) in sprites.iter_mut() {
+        let Some(&(entity, position, _, color, kind, locked)) = plotted.next() else {
+            if visibility.is_visible {
+                visibility.is_visible = false;
+                contact.0 = None;
+            }
+            continue;
+        };
+        visibility.is_visible = true;
+        contact.0 = Some(entity);
+        let (size, rotation) = kind.marker(locked);
+        transform.translation = position.extend(1.0);
+        transform.rotation = Quat::from_rotation_z(rotation);
+        sprite.color = color;
+        sprite.custom_size = Some(size);
+    }
+
+    for (mfd, mut text) in texts.iter_mut() {
+        if mfd.0 == Mfd::Radar {
+            let range = range as u32;
+            text.sections[0].value = localization.format("mfd-radar", &[("range", &range)]);
+        }
+    }
+}
+
+/// Position of the `cursor` in NDC on the radar `screen`, in MFD pixels from its center. `None`
+/// if the cursor is off the screen.
+fn radar_pixel(
+    cursor: Vec2,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    screen: &GlobalTransform,
+) -> Option<Vec2> {
+    // The screen is small and faces the camera, so it's flat enough on the screen to map the
+    // cursor to it by its center and the edges
+    let project = |point: Vec3| Some(camera.world_to_ndc(camera_transform, point)?.truncate());
+    let center = screen.translation();
+    let half = MFD_SIZE / 2.0;
+    let ndc = project(center)?;
+    let right = project(center + screen.right() * half)?;
+    let up = project(center + screen.up() * half)?;
+    let axes = Mat2::from_cols(right - ndc, up - ndc);
+    if axes.determinant().abs() <= f32::EPSILON {
+        return None;
+    }
+    let local = axes.inverse() * (cursor - ndc);
+    (local.abs().max_element() <= 1.0).then(|| local * MFD_RESOLUTION as f32 / 2.0)
+}
+
+/// Clicking a blip on the radar screen orders allies to focus fire on the contact. The radar
+/// takes the clicks on its screen, so they don't steer the ship.
+fn click_radar(
+    mouse: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    camera: Query<(&Camera, &GlobalTransform), With<PlayerCamera>>,
+    screens: Query<(&MfdScreen, &GlobalTransform, &ComputedVisibility)>,
+    contacts: Query<(&RadarContact, &Transform, &Visibility)>,
+    mut ui_pointer: ResMut<UiPointer>,
+    mut ev_focus: EventWriter<FocusFireEvent>,
+) {
+    let cursor = windows.get_primary().and_then(|window| {
+        let size = Vec2::new(window.width(), window.height());
+        // cursor origin is in the bottom left corner, same direction as NDC
+        Some(window.cursor_position()? / size * 2.0 - 1.0)
+    });
+    let screen = screens
+        .iter()
+        .find(|(mfd, _, visibility)| mfd.0 == Mfd::Radar && visibility.is_visible())
+        .map(|(_, screen, _)| screen);
+    let pixel = match (cursor, camera.get_single(), screen) {
+        (Some(cursor), Ok((camera, camera_transform)), Some(screen)) => {
+            radar_pixel(cursor, camera, camera_transform, screen)
+        }
+        _ => None,
+    };
+    if ui_pointer.over_hud != pixel.is_some() {
+        ui_pointer.over_hud = pixel.is_some();
+    }
+    let Some(pixel) = pixel.filter(|_| mouse.just_pressed(MouseButton::Left)) else {
+        return;
+    };
+
+    let picked = contacts
+        .iter()
+        .filter(|(_, _, visibility)| visibility.is_visible)
+        .filter_map(|(contact, transform, _)| {
+            let distance = transform.translation.truncate().distance(pixel);
+            Some((contact.0?, distance))
+        })
+        .filter(|&(_, distance)| distance <= CLICK_RADIUS)
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+    if let Some((target, _)) = picked {
+        ev_focus.send(FocusFireEvent(target));
+    }
+}
+
+fn update_systems_mfd(
+    settings: Res<Settings>,
+    localization: Res<Localization>,
+    player: Query<(&HitPoints, Option<&Shield>, Option<&Throttle>), With<Player>>,
+    guns: Query<&Heat, With<PrimaryWeapon>>,
+    heavy: Query<(&Gun, Option<&Charge>), With<HeavyWeapon>>,
+    mut texts: Query<(&MfdText, &mut Text)>,
+) {
+    if !settings.hud.cockpit {
+        return;
+    }
+    let Ok((hp, shield, throttle)) = player.get_single() else {
+        return;
+    };
+
+    let percent = |fraction: f32| (fraction * 100.0).round() as i32;
+    let bar = |fraction: f32| text_bar(fraction, BAR_CELLS, '#');
+    let mut lines = vec![localization.get("mfd-systems").to_owned()];
+    let hull = hp.percent() as f32 / 100.0;
+    lines.push(localization.format("hud-hull", &[("bar", &bar(hull)), ("hp", &hp.percent())]));
+    if let Some(shield) = shield {
+        let charge = shield.percent() as f32 / 100.0;
+        lines.push(localization.format(
+            "hud-shield",
+            &[("bar", &bar(charge)), ("shield", &shield.percent())],
+        ));
+    }
+    // the hottest gun limits the fire
+    let heat = guns.iter().map(Heat::value).fold(0.0, f32::max);
+    lines.push(localization.format("mfd-heat", &[("bar", &bar(heat)), ("heat", &percent(heat))]));
+    // the heavy weapon shows its charge, or how soon it's ready again
+    if let Some((gun, charge)) = heavy.iter().next() {
+        let level = fire_cycle_level(gun, charge);
+        let state = localization.get(fire_cycle_key(gun.cycle()));
+        lines.push(localization.format("mfd-heavy", &[("bar", &bar(level)), ("state", &state)]));
+    }
+    if let Some(&Throttle(throttle)) = throttle {
+        let bar = bar(throttle.abs());
+        lines.push(localization.format(
+            "hud-throttle",
+            &[("bar", &bar), ("throttle", &percent(throttle))],
+        ));
+    }
+
+    for (mfd, mut text) in texts.iter_mut() {
+        if mfd.0 == Mfd::Systems {
+            text.sections[0].value = lines.join("\n");
+        }
+    }
+}
+
+fn update_target_mfd(
+    settings: Res<Settings>,
+    localization: Res<Localization>,
+    player: Query<&GlobalTransform, With<Player>>,
+    target: Query<
+        (
+            Option<&Name>,
+            &GlobalTransform,
+            Option<&HitPoints>,
+            Option<&Shield>,
+        ),
+        With<LockedTarget>,
+    >,
+    mut texts: Query<(&MfdText, &mut Text)>,
+) {
+    if !settings.hud.cockpit {
+        return;
+    }
+    let Ok(player_transform) = player.get_single() else {
+        return;
+    };
+
+    let bar = |percent: u32| text_bar(percent as f32 / 100.0, BAR_CELLS, '#');
+    let mut lines = vec![localization.get("mfd-target").to_owned()];
+    match target.get_single() {
+        Ok((name, transform, hp, shield)) => {
+            let name = name.map_or(localization.get("hud-unknown-target"), |n| n.as_str());
+            lines.push(name.to_owned());
+            let distance = transform
+                .translation()
+                .distance(player_transform.translation());
+            let distance = format!("{distance:.0}");
+            lines.push(localization.format("hud-distance", &[("distance", &distance)]));
+            if let Some(hp) = hp {
+                let hull = hp.percent();
+                lines.push(localization.format("hud-hull", &[("bar", &bar(hull)), ("hp", &hull)]));
+            }
+            if let Some(shield) = shield {
+                let charge = shield.percent();
+                lines.push(
+                    localization
+                        .format("hud-shield", &[("bar", &bar(charge)), ("shield", &charge)]),
+                );
+            }
+        }
+        Err(_) => lines.push(localization.get("mfd-no-target").to_owned()),
+    }
+
+    for (mfd, mut text) in texts.iter_mut() {
+        if mfd.0 == Mfd::Target {
+            text.sections[0].value = lines.join("\n");
+        }
+    }
+}
+
+/// Optional first-person cockpit around the player camera, toggled with I. Its MFD screens show
+/// the radar, the ship systems and the locked target instead of the flat target panel. Clicking
+/// a radar blip orders allies to focus fire on it.
+pub(super) struct CockpitPlugin;
+impl Plugin for CockpitPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FocusFireEvent>()
+            .init_resource::<UiPointer>()
+            .add_system(spawn_cockpit)
+            .add_system(apply_cockpit)
+            .add_system(update_radar)
+            .add_system(
+                click_radar
+                    .after(update_radar)
+                    .before(player::steer_with_mouse),
+            )
+            .add_system(update_systems_mfd)
+            .add_system(update_target_mfd);
+    }
+}
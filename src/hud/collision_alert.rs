@@ -0,0 +1,92 @@
+use bevy::prelude::*;
+
+use super::{HudAnchor, HudWidget, Indicator};
+use crate::{
+    collision_warning::CollisionWarning, localization::Localization, player::Player,
+    settings::Settings,
+};
+
+const ALERT_WIDTH: f32 = 400.0;
+/// Distance from the screen center down to the alert, so it doesn't cover the aim
+const ALERT_OFFSET: f32 = 80.0;
+/// Blinks per second
+const BLINK_RATE: f32 = 2.0;
+
+#[derive(Component)]
+struct CollisionAlert;
+
+fn setup_alert(mut commands: Commands, assets: Res<AssetServer>) {
+    // Placed with an absolute position below the reticle, so it doesn't move the aim
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(ALERT_WIDTH), Val::Auto),
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Percent(50.0),
+                    top: Val::Percent(50.0),
+                    ..default()
+                },
+                margin: UiRect {
+                    left: Val::Px(-ALERT_WIDTH / 2.0),
+                    top: Val::Px(ALERT_OFFSET),
+                    ..default()
+                },
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(HudWidget::essential(HudAnchor::Center))
+        .insert(Name::new("Collision Alert"))
+        .with_children(|parent| {
+            parent
+                .spawn(
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: assets.load("fonts/FiraMono-Medium.ttf"),
+                            font_size: 28.0,
+                            color: Color::WHITE,
+                        },
+                    )
+                    .with_text_alignment(TextAlignment::CENTER),
+                )
+                .insert(CollisionAlert);
+        });
+}
+
+fn update_alert(
+    time: Res<Time>,
+    localization: Res<Localization>,
+    settings: Res<Settings>,
+    player: Query<&CollisionWarning, With<Player>>,
+    mut alert: Query<(&mut Text, &mut Visibility), With<CollisionAlert>>,
+) {
+    let Ok((mut text, mut visibility)) = alert.get_single_mut() else {
+        return;
+    };
+    let warning = player.get_single().ok().filter(|w| w.is_active());
+    let Some(obstacle) = warning.and_then(|w| w.obstacle) else {
+        visibility.is_visible = false;
+        return;
+    };
+
+    visibility.is_visible = (time.elapsed_seconds() * BLINK_RATE).fract() < 0.5;
+    let time_to_impact = format!("{:.1}", obstacle.time_to_impact);
+    let mut value = localization.format("hud-collision-warning", &[("time", &time_to_impact)]);
+    if warning.map_or(false, CollisionWarning::is_braking) {
+        value.push('\n');
+        value.push_str(localization.get("hud-auto-brake"));
+    }
+    text.sections[0].value = value;
+    text.sections[0].style.color = settings.accessibility.color(Indicator::Danger);
+}
+
+pub(super) struct CollisionAlertPlugin;
+impl Plugin for CollisionAlertPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_alert).add_system(update_alert);
+    }
+}
@@ -0,0 +1,138 @@
+use bevy::prelude::*;
+
+use super::{HudAnchor, HudWidget, Indicator};
+use crate::{player::Player, projectile::DamageEvent, settings::Settings};
+
+/// Size of the square area around the reticle where pips are shown
+const INDICATOR_SIZE: f32 = 160.0;
+/// Distance from the screen center to the pip
+const PIP_RADIUS: f32 = 60.0;
+const PIP_SIZE: f32 = 12.0;
+/// Time for the pip to fade out completely, in seconds
+const FADE_TIME: f32 = 1.0;
+
+#[derive(Component)]
+struct DamageIndicator;
+
+/// Points in the direction of the attacker around the reticle.
+#[derive(Component)]
+struct DamagePip {
+    attacker_position: Vec3,
+    time_left: f32,
+}
+
+/// Position of the pip inside the indicator, pointing to the attacker from the player's point of view
+fn pip_position(player: &GlobalTransform, attacker_position: Vec3) -> UiRect {
+    let (_, rotation, translation) = player.to_scale_rotation_translation();
+
+    // Bearing in the screen plane, camera looks along -Z with Y up
+    let local = rotation.inverse() * (attacker_position - translation);
+    let bearing = if local.truncate().length_squared() > f32::EPSILON {
+        local.y.atan2(local.x)
+    } else {
+        // attacker is right ahead or behind - point down
+        -std::f32::consts::FRAC_PI_2
+    };
+
+    // UI Y axis points down
+    let center = INDICATOR_SIZE / 2.0 - PIP_SIZE / 2.0;
+    UiRect {
+        left: Val::Px(center + PIP_RADIUS * bearing.cos()),
+        top: Val::Px(center - PIP_RADIUS * bearing.sin()),
+        ..default()
+    }
+}
+
+fn setup_indicator(mut commands: Commands) {
+    // Pips are placed with absolute positions, so the indicator is centered manually to not move the aim
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(INDICATOR_SIZE), Val::Px(INDICATOR_SIZE)),
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Percent(50.0),
+                    top: Val::Percent(50.0),
+                    ..default()
+                },
+                margin: UiRect {
+                    left: Val::Px(-INDICATOR_SIZE / 2.0),
+                    top: Val::Px(-INDICATOR_SIZE / 2.0),
+                    ..default()
+                },
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(HudWidget::essential(HudAnchor::Center))
+        .insert(DamageIndicator)
+        .insert(Name::new("Damage Indicator"));
+}
+
+fn spawn_pips(
+    mut commands: Commands,
+    mut ev_damage: EventReader<DamageEvent>,
+    player: Query<(Entity, &GlobalTransform), With<Player>>,
+    indicator: Query<Entity, With<DamageIndicator>>,
+    settings: Res<Settings>,
+) {
+    let Ok((player, transform)) = player.get_single() else {
+        return;
+    };
+    let Ok(indicator) = indicator.get_single() else {
+        return;
+    };
+
+    for ev in ev_damage.iter().filter(|ev| ev.target == player) {
+        commands.entity(indicator).with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(PIP_SIZE), Val::Px(PIP_SIZE)),
+                        position_type: PositionType::Absolute,
+                        position: pip_position(transform, ev.attacker_position),
+                        ..default()
+                    },
+                    background_color: settings.accessibility.color(Indicator::Danger).into(),
+                    ..default()
+                })
+                .insert(DamagePip {
+                    attacker_position: ev.attacker_position,
+                    time_left: FADE_TIME,
+                });
+        });
+    }
+}
+
+/// Keeps pips pointing to attackers while the player turns and fades them out.
+fn update_pips(
+    mut commands: Commands,
+    time: Res<Time>,
+    player: Query<&GlobalTransform, With<Player>>,
+    mut pips: Query<(Entity, &mut DamagePip, &mut Style, &mut BackgroundColor)>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+
+    for (entity, mut pip, mut style, mut color) in pips.iter_mut() {
+        pip.time_left -= time.delta_seconds();
+        if pip.time_left <= 0.0 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        style.position = pip_position(player, pip.attacker_position);
+        color.0.set_a(pip.time_left / FADE_TIME);
+    }
+}
+
+pub(super) struct DamageIndicatorPlugin;
+impl Plugin for DamageIndicatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_indicator)
+            .add_system(spawn_pips)
+            .add_system(update_pips.after(spawn_pips));
+    }
+}
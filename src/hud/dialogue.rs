@@ -0,0 +1,347 @@
+//! Radio chatter and mission banners at the top of the HUD. Messages are queued and shown one at
+//! a time, long enough to be read, with the speaker's name and portrait and an optional voice line.
+//! No voice lines are shipped with the game yet, so messages whose voice isn't in `assets` are
+//! shown silently.
+//! They come from scenario triggers and from the battle itself, like a lost wingman.
+
+use std::{collections::VecDeque, path::Path};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::{HudAnchor, HudWidget, Indicator, PanelBackground};
+use crate::{
+    components::Fraction,
+    localization::Localization,
+    player::Player,
+    projectile::{DamageEvent, DamageSystems},
+    settings::Settings,
+    ship_switch::Controllable,
+};
+
+/// Every message is shown at least this long, in seconds
+const MIN_TIME: f32 = 2.0;
+/// and at most this long without an explicit duration, in seconds
+const MAX_TIME: f32 = 8.0;
+/// Characters read per second, for the time to show a message
+const READING_SPEED: f32 = 15.0;
+/// The oldest pending messages are dropped once there are more, as they are stale anyway
+const MAX_QUEUED: usize = 5;
+/// In UI pixels
+const PORTRAIT_SIZE: f32 = 64.0;
+
+/// Radio line, or a banner without a speaker. Texts are localization keys, or shown as they are
+/// without a translation.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RadioMessage {
+    #[serde(default)]
+    pub speaker: Option<String>,
+    pub text: String,
+    /// Image in `assets`, the speaker's initial is shown without one
+    #[serde(default)]
+    pub portrait: Option<String>,
+    /// Audio in `assets` played once the message is shown
+    #[serde(default)]
+    pub voice: Option<String>,
+    /// Seconds to show the message, estimated from the text length by default
+    #[serde(default)]
+    pub duration: Option<f32>,
+}
+
+impl RadioMessage {
+    fn display_time(&self) -> f32 {
+        self.duration.unwrap_or_else(|| {
+            let reading = self.text.chars().count() as f32 / READING_SPEED;
+            (MIN_TIME + reading).min(MAX_TIME)
+        })
+    }
+}
+
+/// Queues the message to be shown at the top of the HUD.
+pub struct RadioMessageEvent(pub RadioMessage);
+
+#[derive(Resource, Default)]
+struct RadioQueue {
+    pending: VecDeque<RadioMessage>,
+    current: Option<RadioMessage>,
+    /// Seconds left to show the current message
+    time_left: f32,
+}
+
+impl RadioQueue {
+    fn push(&mut self, message: RadioMessage) {
+        if self.pending.len() >= MAX_QUEUED {
+            self.pending.pop_front();
+        }
+        self.pending.push_back(message);
+    }
+
+    /// Moves on to the next message once the current one times out, returns true if the shown
+    /// message has changed
+    fn update(&mut self, delta: f32) -> bool {
+        self.time_left = (self.time_left - delta).max(0.0);
+        if self.current.is_some() && self.time_left > 0.0 {
+            return false;
+        }
+        let was_shown = self.current.is_some();
+        self.current = self.pending.pop_front();
+        self.time_left = self
+            .current
+            .as_ref()
+            .map_or(0.0, RadioMessage::display_time);
+        was_shown || self.current.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RadioMessage, RadioQueue, MAX_QUEUED, MAX_TIME, MIN_TIME};
+
+    fn message(text: &str) -> RadioMessage {
+        RadioMessage {
+            text: text.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_display_time() {
+        assert!(message("Go!").display_time() >= MIN_TIME);
+        assert!(message("Go!").display_time() < message("Hold the line!").display_time());
+        assert_eq!(message(&"a".repeat(1000)).display_time(), MAX_TIME);
+        let explicit = RadioMessage {
+            duration: Some(1.0),
+            ..message("Go!")
+        };
+        assert_eq!(explicit.display_time(), 1.0);
+    }
+
+    #[test]
+    fn test_queue() {
+        let mut queue = RadioQueue::default();
+        assert!(!queue.update(0.1));
+
+        queue.push(message("first"));
+        queue.push(message("second"));
+        assert!(queue.update(0.1));
+        assert_eq!(queue.current, Some(message("first")));
+        // the second one waits for the first one to time out
+        assert!(!queue.update(MIN_TIME / 2.0));
+        assert!(queue.update(MAX_TIME));
+        assert_eq!(queue.current, Some(message("second")));
+        assert!(queue.update(MAX_TIME));
+        assert_eq!(queue.current, None);
+        assert!(!queue.update(MAX_TIME));
+
+        for index in 0..=MAX_QUEUED {
+            queue.push(message(&index.to_string()));
+        }
+        assert_eq!(queue.pending.len(), MAX_QUEUED);
+        assert_eq!(queue.pending[0], message("1"));
+    }
+}
+
+#[derive(Component)]
+struct RadioBanner;
+
+/// Image of the speaker, tinted with the friendly color without one.
+#[derive(Component)]
+struct Portrait;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum RadioText {
+    Speaker,
+    Line,
+    /// Initial of the speaker on the portrait placeholder
+    Initial,
+}
+
+fn setup_banner(mut commands: Commands, assets: Res<AssetServer>) {
+    let font = assets.load("fonts/FiraMono-Medium.ttf");
+    let text = |size: f32| TextStyle {
+        font: font.clone(),
+        font_size: size,
+        color: Color::WHITE,
+    };
+
+    let background = Color::rgba(0.0, 0.0, 0.0, 0.6);
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                align_items: AlignItems::Center,
+                margin: UiRect::all(Val::Px(5.0)),
+                padding: UiRect::all(Val::Px(5.0)),
+                ..default()
+            },
+            background_color: background.into(),
+            visibility: Visibility::INVISIBLE,
+            ..default()
+        })
+        .insert(HudWidget::essential(HudAnchor::Top))
+        .insert(PanelBackground(background))
+        .insert(RadioBanner)
+        .insert(Name::new("Radio"))
+        .with_children(|parent| {
+            parent
+                .spawn(ImageBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(PORTRAIT_SIZE), Val::Px(PORTRAIT_SIZE)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        margin: UiRect::all(Val::Px(5.0)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .insert(Portrait)
+                .with_children(|parent| {
+                    parent
+                        .spawn(TextBundle::from_section("", text(40.0)))
+                        .insert(RadioText::Initial);
+                });
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        max_size: Size::new(Val::Px(600.0), Val::Undefined),
+                        ..default()
+                    },
+                    background_color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn(TextBundle::from_section("", text(18.0)))
+                        .insert(RadioText::Speaker);
+                    parent
+                        .spawn(TextBundle::from_section("", text(22.0)))
+                        .insert(RadioText::Line);
+                });
+        });
+}
+
+/// Messages are translated once queued, so the time to show them fits the text
+fn queue_messages(
+    localization: Res<Localization>,
+    mut queue: ResMut<RadioQueue>,
+    mut ev_radio: EventReader<RadioMessageEvent>,
+) {
+    for RadioMessageEvent(message) in ev_radio.iter() {
+        let speaker = message.speaker.as_deref();
+        queue.push(RadioMessage {
+            speaker: speaker.map(|speaker| localization.get(speaker).to_owned()),
+            text: localization.get(&message.text).to_owned(),
+            ..message.clone()
+        });
+    }
+}
+
+/// Shows the next message once the current one times out and plays its voice line
+fn advance_queue(
+    time: Res<Time>,
+    assets: Res<AssetServer>,
+    audio: Res<Audio>,
+    mut queue: ResMut<RadioQueue>,
+) {
+    // The time left ticks every frame, while the banner is only updated with the message
+    if !queue.bypass_change_detection().update(time.delta_seconds()) {
+        return;
+    }
+    queue.set_changed();
+    let voice = queue.current.as_ref().and_then(|m| m.voice.as_ref());
+    if let Some(voice) = voice.filter(|voice| Path::new("assets").join(voice).exists()) {
+        audio.play(assets.load(voice.as_str()));
+    }
+}
+
+fn update_banner(
+    queue: Res<RadioQueue>,
+    assets: Res<AssetServer>,
+    settings: Res<Settings>,
+    mut banner: Query<&mut Visibility, With<RadioBanner>>,
+    mut portrait: Query<(&mut Style, &mut UiImage, &mut BackgroundColor), With<Portrait>>,
+    mut texts: Query<(&RadioText, &mut Text)>,
+) {
+    if !queue.is_changed() {
+        return;
+    }
+    let (Ok(mut visibility), Ok((mut style, mut image, mut tint))) =
+        (banner.get_single_mut(), portrait.get_single_mut())
+    else {
+        return;
+    };
+    visibility.is_visible = queue.current.is_some();
+    let Some(message) = &queue.current else {
+        return;
+    };
+
+    let speaker = message.speaker.as_deref();
+    // Banners without a speaker have no portrait
+    style.display = match speaker {
+        Some(_) => Display::Flex,
+        None => Display::None,
+    };
+    match &message.portrait {
+        Some(path) => {
+            *image = UiImage(assets.load(path.as_str()));
+            tint.0 = Color::WHITE;
+        }
+        None => {
+            *image = UiImage::default();
+            tint.0 = settings.accessibility.color(Indicator::Friendly);
+        }
+    }
+
+    for (kind, mut text) in texts.iter_mut() {
+        text.sections[0].value = match kind {
+            RadioText::Speaker => speaker.unwrap_or_default().to_owned(),
+            RadioText::Line => message.text.clone(),
+            RadioText::Initial if message.portrait.is_none() => speaker
+                .and_then(|s| s.chars().next())
+                .map(String::from)
+                .unwrap_or_default(),
+            RadioText::Initial => String::new(),
+        };
+    }
+}
+
+/// Command reports friendly ships of the player's team lost in the battle
+fn wingman_lost(
+    localization: Res<Localization>,
+    mut ev_damage: EventReader<DamageEvent>,
+    player: Query<&Fraction, With<Player>>,
+    wingmen: Query<(&Name, &Fraction), (With<Controllable>, Without<Player>)>,
+    mut ev_radio: EventWriter<RadioMessageEvent>,
+) {
+    let Ok(fraction) = player.get_single() else {
+        return;
+    };
+    for ev in ev_damage.iter().filter(|ev| ev.destroyed) {
+        let Ok((name, wingman)) = wingmen.get(ev.target) else {
+            continue;
+        };
+        if wingman != fraction {
+            continue;
+        }
+        ev_radio.send(RadioMessageEvent(RadioMessage {
+            speaker: Some("radio-command".to_owned()),
+            text: localization.format("radio-wingman-lost", &[("name", &name.as_str())]),
+            ..default()
+        }));
+    }
+}
+
+/// Radio messages at the top of the HUD, sent as `RadioMessageEvent`s.
+pub(super) struct DialoguePlugin;
+impl Plugin for DialoguePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RadioQueue>()
+            .add_event::<RadioMessageEvent>()
+            .add_event::<DamageEvent>()
+            .add_startup_system(setup_banner)
+            .add_system(wingman_lost.after(DamageSystems))
+            .add_system(queue_messages.after(wingman_lost))
+            .add_system(advance_queue.after(queue_messages))
+            .add_system(update_banner.after(advance_queue));
+    }
+}
@@ -0,0 +1,216 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::{world_to_ui, HudAnchor, HudWidget, Indicator, PanelBackground};
+use crate::{
+    localization::Localization,
+    player::{Cruise, FlightAssist, Player, PlayerCamera, Throttle},
+    settings::Settings,
+};
+
+/// Velocity markers are hidden below this speed, as direction is too noisy
+const MIN_MARKER_SPEED: f32 = 0.1;
+/// Number of cells in the throttle bar
+const THROTTLE_BAR_CELLS: usize = 10;
+
+/// Screen-space marker for the player's velocity direction.
+#[derive(Component, Clone, Copy)]
+enum VelocityMarker {
+    Prograde,
+    Retrograde,
+}
+
+#[derive(Component)]
+struct FlightText;
+
+fn setup_flight_hud(mut commands: Commands, assets: Res<AssetServer>) {
+    let font = assets.load("fonts/FiraMono-Medium.ttf");
+
+    // Markers are positioned in screen coordinates, so their widget covers the whole screen
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(HudWidget::essential(HudAnchor::TopLeft))
+        .insert(Name::new("Velocity Markers"))
+        .with_children(|parent| {
+            for (marker, label) in [
+                (VelocityMarker::Prograde, "(o)"),
+                (VelocityMarker::Retrograde, "(x)"),
+            ] {
+                parent
+                    .spawn(
+                        TextBundle::from_section(
+                            label,
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 20.0,
+                                color: Color::WHITE,
+                            },
+                        )
+                        .with_style(Style {
+                            position_type: PositionType::Absolute,
+                            ..default()
+                        }),
+                    )
+                    .insert(marker);
+            }
+        });
+
+    // Semi-transparent section in the left bottom corner for flight instruments
+    let background = Color::rgba(0.7, 0.7, 0.7, 0.3);
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(360.0), Val::Px(100.0)),
+                align_items: AlignItems::FlexStart, // vertical alignment to the top
+                justify_content: JustifyContent::FlexStart, // horizontal alignment to the left
+                padding: UiRect::all(Val::Px(5.0)),
+                ..default()
+            },
+            background_color: background.into(),
+            ..default()
+        })
+        .insert(HudWidget::new(HudAnchor::BottomLeft))
+        .insert(PanelBackground(background))
+        .insert(Name::new("Flight Instruments"))
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font,
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                ))
+                .insert(FlightText);
+        });
+}
+
+fn update_velocity_markers(
+    player: Query<&Velocity, With<Player>>,
+    camera: Query<(&Camera, &GlobalTransform), With<PlayerCamera>>,
+    windows: Res<Windows>,
+    ui_scale: Res<UiScale>,
+    settings: Res<Settings>,
+    mut markers: Query<(
+        &VelocityMarker,
+        &Node,
+        &mut Style,
+        &mut Visibility,
+        &mut Text,
+    )>,
+) {
+    let Ok(velocity) = player.get_single() else {
+        return;
+    };
+    let Ok((camera, transform)) = camera.get_single() else {
+        return;
+    };
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+
+    for (&marker, node, mut style, mut visibility, mut text) in markers.iter_mut() {
+        let (direction, indicator) = match marker {
+            VelocityMarker::Prograde => (velocity.linvel, Indicator::Good),
+            VelocityMarker::Retrograde => (-velocity.linvel, Indicator::Warning),
+        };
+
+        // Project a far point along the velocity, so the marker doesn't depend on the camera position
+        let ui_pos = (direction.length() > MIN_MARKER_SPEED)
+            .then(|| transform.translation() + direction.normalize() * 1000.0)
+            .and_then(|point| world_to_ui(camera, transform, window, &ui_scale, point));
+
+        visibility.is_visible = ui_pos.is_some();
+        if let Some(ui_pos) = ui_pos {
+            let pos = ui_pos - node.size() / 2.0;
+            style.position = UiRect {
+                left: Val::Px(pos.x),
+                top: Val::Px(pos.y),
+                ..default()
+            };
+            text.sections[0].style.color = settings.accessibility.color(indicator);
+        }
+    }
+}
+
+fn update_instruments(
+    player: Query<
+        (
+            &GlobalTransform,
+            &Velocity,
+            &Throttle,
+            &FlightAssist,
+            &Cruise,
+        ),
+        With<Player>,
+    >,
+    localization: Res<Localization>,
+    mut text: Query<&mut Text, With<FlightText>>,
+) {
+    let Ok((transform, velocity, throttle, assist, cruise)) = player.get_single() else {
+        return;
+    };
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+
+    let speed = velocity.linvel.length();
+    let cells =
+        ((throttle.0.abs() * THROTTLE_BAR_CELLS as f32).round() as usize).min(THROTTLE_BAR_CELLS);
+    let bar = format!(
+        "[{}{}]",
+        "#".repeat(cells),
+        "-".repeat(THROTTLE_BAR_CELLS - cells)
+    );
+    // Angular velocity in the player's frame: X - pitch, Y - yaw, Z - roll
+    let (_, rotation, _) = transform.to_scale_rotation_translation();
+    let angvel = rotation.inverse() * velocity.angvel;
+
+    let mut lines = vec![
+        localization.format("hud-speed", &[("speed", &format!("{speed:.1}"))]),
+        localization.format(
+            "hud-throttle",
+            &[
+                ("bar", &bar),
+                ("throttle", &((throttle.0 * 100.0).round() as i32)),
+            ],
+        ),
+        localization.format(
+            "hud-rotation",
+            &[
+                ("pitch", &format!("{:+.0}", angvel.x.to_degrees())),
+                ("yaw", &format!("{:+.0}", angvel.y.to_degrees())),
+                ("roll", &format!("{:+.0}", angvel.z.to_degrees())),
+            ],
+        ),
+        localization
+            .get(match assist {
+                FlightAssist::Coupled => "hud-flight-assist-on",
+                FlightAssist::Decoupled => "hud-flight-assist-off",
+            })
+            .to_owned(),
+    ];
+    if let Some(cruise) = cruise.0 {
+        let speed = format!("{cruise:.1}");
+        lines.push(localization.format("hud-cruise", &[("speed", &speed)]));
+    }
+    text.sections[0].value = lines.join("\n");
+}
+
+pub(super) struct FlightHudPlugin;
+impl Plugin for FlightHudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_flight_hud)
+            .add_system(update_velocity_markers)
+            .add_system(update_instruments);
+    }
+}
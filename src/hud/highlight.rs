@@ -0,0 +1,77 @@
+//! Pulsing highlight over a HUD widget, pointing the player at an indicator, e.g. in the tutorial.
+
+use bevy::prelude::*;
+
+use super::{HudWidget, Indicator};
+use crate::settings::Settings;
+
+/// Pulses per second
+const PULSE_RATE: f32 = 1.5;
+/// Highlight alpha swings between these
+const MIN_ALPHA: f32 = 0.1;
+const MAX_ALPHA: f32 = 0.4;
+
+/// Highlights the HUD widget with the name, like "Flight Instruments", or clears the highlight.
+pub struct HighlightWidgetEvent(pub Option<String>);
+
+/// Overlay covering the highlighted widget.
+#[derive(Component)]
+struct Highlight;
+
+fn highlight_widget(
+    mut commands: Commands,
+    mut ev_highlight: EventReader<HighlightWidgetEvent>,
+    widgets: Query<(Entity, &Name), With<HudWidget>>,
+    highlights: Query<Entity, With<Highlight>>,
+) {
+    let Some(HighlightWidgetEvent(name)) = ev_highlight.iter().last() else {
+        return;
+    };
+    for highlight in highlights.iter() {
+        commands.entity(highlight).despawn_recursive();
+    }
+    let Some(name) = name else {
+        return;
+    };
+    let Some((widget, _)) = widgets.iter().find(|(_, widget)| widget.as_str() == name) else {
+        warn!("No HUD widget {name} to highlight");
+        return;
+    };
+
+    let highlight = commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(Highlight)
+        .insert(Name::new("Highlight"))
+        .id();
+    commands.entity(widget).add_child(highlight);
+}
+
+fn pulse_highlight(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut highlights: Query<&mut BackgroundColor, With<Highlight>>,
+) {
+    let pulse = (time.elapsed_seconds() * PULSE_RATE * std::f32::consts::TAU).sin() * 0.5 + 0.5;
+    let mut color = settings.accessibility.color(Indicator::Warning);
+    color.set_a(MIN_ALPHA + (MAX_ALPHA - MIN_ALPHA) * pulse);
+    for mut background in highlights.iter_mut() {
+        background.0 = color;
+    }
+}
+
+pub(super) struct HighlightPlugin;
+impl Plugin for HighlightPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<HighlightWidgetEvent>()
+            .add_system(highlight_widget)
+            .add_system(pulse_highlight.after(highlight_widget));
+    }
+}
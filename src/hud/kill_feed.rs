@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+
+use super::{HudAnchor, HudWidget};
+use crate::{
+    components::Fraction,
+    localization::Localization,
+    player::Player,
+    projectile::{DamageEvent, DamageSystems},
+    settings::Settings,
+};
+
+/// Time the line is shown, in seconds
+const LINE_TIME: f32 = 6.0;
+/// Line fades out during this last part of its lifetime, in seconds
+const FADE_TIME: f32 = 1.0;
+/// The oldest lines are dropped once there are more
+const MAX_LINES: usize = 6;
+
+#[derive(Component)]
+struct KillFeed {
+    font: Handle<Font>,
+}
+
+#[derive(Component)]
+struct KillLine {
+    time_left: f32,
+}
+
+fn setup_kill_feed(mut commands: Commands, assets: Res<AssetServer>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexEnd,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(HudWidget::new(HudAnchor::TopRight))
+        .insert(KillFeed {
+            font: assets.load("fonts/FiraMono-Medium.ttf"),
+        })
+        .insert(Name::new("Kill Feed"));
+}
+
+/// Adds a line for every destroyed unit, i.e. a root entity with a fraction like a drone or the
+/// player, naming its killer
+fn spawn_kill_lines(
+    mut commands: Commands,
+    localization: Res<Localization>,
+    settings: Res<Settings>,
+    mut ev_damage: EventReader<DamageEvent>,
+    units: Query<(&Name, Option<&Fraction>, Option<&Parent>)>,
+    player: Query<&Fraction, With<Player>>,
+    feed: Query<(Entity, &KillFeed)>,
+) {
+    let Ok((feed, KillFeed { font })) = feed.get_single() else {
+        return;
+    };
+    let own_fraction = player.get_single().ok().copied();
+
+    for ev in ev_damage.iter().filter(|ev| ev.destroyed) {
+        let Ok((target, Some(&fraction), None)) = units.get(ev.target) else {
+            continue;
+        };
+        let target = target.as_str();
+        let text = match ev.attacker.and_then(|e| units.get(e).ok()) {
+            Some((attacker, _, _)) => localization.format(
+                "hud-kill",
+                &[("attacker", &attacker.as_str()), ("target", &target)],
+            ),
+            None => localization.format("hud-kill-unknown", &[("target", &target)]),
+        };
+
+        commands.entity(feed).with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    text,
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 18.0,
+                        color: settings.accessibility.faction_color(fraction, own_fraction),
+                    },
+                ))
+                .insert(KillLine {
+                    time_left: LINE_TIME,
+                });
+        });
+    }
+}
+
+fn update_kill_lines(
+    mut commands: Commands,
+    time: Res<Time>,
+    feed: Query<&Children, With<KillFeed>>,
+    mut lines: Query<(&mut KillLine, &mut Text)>,
+) {
+    let Ok(children) = feed.get_single() else {
+        return;
+    };
+    let excess = children.len().saturating_sub(MAX_LINES);
+    for (index, &entity) in children.iter().enumerate() {
+        let Ok((mut line, mut text)) = lines.get_mut(entity) else {
+            continue;
+        };
+        line.time_left -= time.delta_seconds();
+        if index < excess || line.time_left <= 0.0 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let alpha = (line.time_left / FADE_TIME).min(1.0);
+        text.sections[0].style.color.set_a(alpha);
+    }
+}
+
+/// Recent kills with the names of units and their killers, tinted by the faction of the
+/// destroyed unit.
+pub(super) struct KillFeedPlugin;
+impl Plugin for KillFeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_kill_feed)
+            .add_system(spawn_kill_lines.after(DamageSystems))
+            .add_system(update_kill_lines.after(spawn_kill_lines));
+    }
+}
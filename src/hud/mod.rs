@@ -0,0 +1,384 @@
+use bevy::{prelude::*, utils::HashMap, window::WindowResized};
+
+use crate::{
+    gun::{Charge, FireCycle, Gun},
+    settings::Settings,
+};
+
+mod accessibility;
+mod boss_bar;
+mod captions;
+mod cockpit;
+mod collision_alert;
+mod damage_indicator;
+mod dialogue;
+mod flight;
+mod highlight;
+mod kill_feed;
+mod notifications;
+mod opacity;
+mod picture_in_picture;
+mod pipper;
+mod reticle;
+mod rocket_prediction;
+mod target;
+mod target_brackets;
+mod target_track;
+mod weapons;
+
+pub use accessibility::{AccessibilitySettings, Indicator, Palette, PanelBackground};
+pub use dialogue::{RadioMessage, RadioMessageEvent};
+pub use highlight::HighlightWidgetEvent;
+
+/// HUD is designed for this window height and scaled proportionally for other resolutions.
+const REFERENCE_HEIGHT: f32 = 1080.0;
+
+/// Which HUD widgets are visible.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum HudLayout {
+    /// Only essential widgets, like aim
+    Minimal,
+    /// All widgets
+    #[default]
+    Full,
+    /// No widgets at all, for screenshots
+    Hidden,
+}
+
+impl HudLayout {
+    pub const ALL: [HudLayout; 3] = [HudLayout::Full, HudLayout::Minimal, HudLayout::Hidden];
+
+    fn next(self) -> Self {
+        match self {
+            HudLayout::Full => HudLayout::Minimal,
+            HudLayout::Minimal => HudLayout::Hidden,
+            HudLayout::Hidden => HudLayout::Full,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HudSettings {
+    /// User-defined scale applied on top of the resolution-based one
+    pub scale: f32,
+    pub layout: HudLayout,
+    /// First-person cockpit with MFD screens around the camera
+    pub cockpit: bool,
+    /// Picture-in-picture view behind the ship
+    pub rear_view: bool,
+    /// Picture-in-picture view following the last rocket launched by the player
+    pub missile_cam: bool,
+    /// Opacity of widgets by their names, from 0 to 1. Widgets not listed are opaque.
+    pub widget_opacity: HashMap<String, f32>,
+    /// Non-essential widgets fade out when there is no combat around the player for a while
+    pub auto_fade: bool,
+}
+
+impl HudSettings {
+    /// Opacity of the widget with the `name` set by the user
+    pub fn opacity(&self, name: &str) -> f32 {
+        self.widget_opacity.get(name).copied().unwrap_or(1.0)
+    }
+}
+
+impl Default for HudSettings {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            layout: HudLayout::default(),
+            cockpit: false,
+            rear_view: false,
+            missile_cam: true,
+            widget_opacity: HashMap::default(),
+            auto_fade: true,
+        }
+    }
+}
+
+/// Screen point HUD widget is attached to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HudAnchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl HudAnchor {
+    const ALL: [HudAnchor; 9] = [
+        HudAnchor::TopLeft,
+        HudAnchor::Top,
+        HudAnchor::TopRight,
+        HudAnchor::Left,
+        HudAnchor::Center,
+        HudAnchor::Right,
+        HudAnchor::BottomLeft,
+        HudAnchor::Bottom,
+        HudAnchor::BottomRight,
+    ];
+
+    /// Vertical and horizontal alignment of the column container for this anchor
+    fn alignment(self) -> (JustifyContent, AlignItems) {
+        use HudAnchor::*;
+        let vertical = match self {
+            TopLeft | Top | TopRight => JustifyContent::FlexStart,
+            Left | Center | Right => JustifyContent::Center,
+            BottomLeft | Bottom | BottomRight => JustifyContent::FlexEnd,
+        };
+        let horizontal = match self {
+            TopLeft | Left | BottomLeft => AlignItems::FlexStart,
+            Top | Center | Bottom => AlignItems::Center,
+            TopRight | Right | BottomRight => AlignItems::FlexEnd,
+        };
+        (vertical, horizontal)
+    }
+}
+
+/// Annotates root UI node of a HUD widget. Such nodes are attached to the container of their
+/// anchor, so widgets could be spawned from any plugin without knowing the HUD structure.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct HudWidget {
+    pub anchor: HudAnchor,
+    /// Essential widgets are visible even with [`HudLayout::Minimal`]
+    pub essential: bool,
+    /// Widgets shown on the cockpit MFDs are hidden while the cockpit is on
+    pub on_mfd: bool,
+}
+
+impl HudWidget {
+    pub fn new(anchor: HudAnchor) -> Self {
+        Self {
+            anchor,
+            essential: false,
+            on_mfd: false,
+        }
+    }
+
+    pub fn essential(anchor: HudAnchor) -> Self {
+        Self {
+            anchor,
+            essential: true,
+            on_mfd: false,
+        }
+    }
+
+    /// Marks the widget as replaced by a cockpit MFD
+    pub fn on_mfd(self) -> Self {
+        Self {
+            on_mfd: true,
+            ..self
+        }
+    }
+}
+
+#[derive(Component)]
+struct AnchorContainer(HudAnchor);
+
+fn setup_hud(mut commands: Commands) {
+    // root UI node that covers all screen with a container per anchor
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            for anchor in HudAnchor::ALL {
+                let (justify_content, align_items) = anchor.alignment();
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                            position_type: PositionType::Absolute,
+                            flex_direction: FlexDirection::Column,
+                            justify_content,
+                            align_items,
+                            padding: UiRect::all(Val::Px(10.0)),
+                            ..default()
+                        },
+                        background_color: Color::NONE.into(),
+                        ..default()
+                    })
+                    .insert(AnchorContainer(anchor))
+                    .insert(Name::new(format!("{anchor:?}")));
+            }
+        })
+        .insert(Name::new("UI"));
+}
+
+/// Text progress bar like `[#####-----]` for `fraction` in range from 0 to 1, filled with `fill`
+pub(crate) fn text_bar(fraction: f32, cells: usize, fill: char) -> String {
+    let filled = ((fraction * cells as f32).round().max(0.0) as usize).min(cells);
+    let fill: String = std::iter::repeat(fill).take(filled).collect();
+    format!("[{fill}{}]", "-".repeat(cells - filled))
+}
+
+/// Localization key of the fire cycle stage
+fn fire_cycle_key(cycle: FireCycle) -> &'static str {
+    match cycle {
+        FireCycle::Ready => "fire-cycle-ready",
+        FireCycle::Cooldown => "fire-cycle-cooldown",
+        FireCycle::Charging => "fire-cycle-charging",
+        FireCycle::Overheated => "fire-cycle-overheated",
+    }
+}
+
+/// Progress of the fire cycle from 0 to 1: the charge while charging, how soon the gun is ready
+/// again otherwise, and nothing while overheated
+fn fire_cycle_level(gun: &Gun, charge: Option<&Charge>) -> f32 {
+    match gun.cycle() {
+        FireCycle::Charging => charge.map_or(0.0, Charge::level),
+        FireCycle::Overheated => 0.0,
+        FireCycle::Ready | FireCycle::Cooldown => gun.readiness(),
+    }
+}
+
+/// Projects `point` to the screen and returns its position in UI coordinates (top-left origin, scaled pixels).
+/// The camera might render to a downscaled texture, so the position is mapped to the `window` instead of its target.
+fn world_to_ui(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    window: &Window,
+    ui_scale: &UiScale,
+    point: Vec3,
+) -> Option<Vec2> {
+    let ndc = camera.world_to_ndc(camera_transform, point)?;
+    // Points out of the depth range are behind the camera or beyond its far plane
+    if !(0.0..=1.0).contains(&ndc.z) {
+        return None;
+    }
+    // NDC origin is in the screen center with Y up, while UI one is in the top left corner
+    let window_size = Vec2::new(window.width(), window.height());
+    let screen_pos = Vec2::new(ndc.x + 1.0, 1.0 - ndc.y) / 2.0 * window_size;
+    Some(screen_pos / ui_scale.scale as f32)
+}
+
+/// Moves newly spawned widgets into containers of their anchors.
+fn attach_widgets(
+    mut commands: Commands,
+    widgets: Query<(Entity, &HudWidget), Without<Parent>>,
+    containers: Query<(Entity, &AnchorContainer)>,
+) {
+    for (entity, widget) in widgets.iter() {
+        if let Some((container, _)) = containers.iter().find(|(_, c)| c.0 == widget.anchor) {
+            commands.entity(container).add_child(entity);
+        }
+    }
+}
+
+fn apply_layout(
+    settings: Res<Settings>,
+    mut widgets: Query<(&HudWidget, &mut Style)>,
+    added: Query<(), Added<HudWidget>>,
+) {
+    if !settings.is_changed() && added.is_empty() {
+        return;
+    }
+
+    let layout = settings.hud.layout;
+    for (widget, mut style) in widgets.iter_mut() {
+        let visible = layout != HudLayout::Hidden
+            && (widget.essential || layout == HudLayout::Full)
+            && !(widget.on_mfd && settings.hud.cockpit);
+        let display = if visible {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        if style.display != display {
+            style.display = display;
+        }
+    }
+}
+
+/// Keeps HUD size proportional to the window height, so it looks the same from 720p to 4K and ultrawide.
+fn apply_scale(
+    settings: Res<Settings>,
+    windows: Res<Windows>,
+    mut ev_resized: EventReader<WindowResized>,
+    mut ui_scale: ResMut<UiScale>,
+) {
+    let resized = ev_resized.iter().count() > 0;
+    if !settings.is_changed() && !resized {
+        return;
+    }
+
+    if let Some(window) = windows.get_primary() {
+        let scale = settings.hud.scale * window.height() / REFERENCE_HEIGHT;
+        ui_scale.scale = scale as f64;
+    }
+}
+
+fn hud_controls(keys: Res<Input<KeyCode>>, mut settings: ResMut<Settings>) {
+    if keys.just_pressed(KeyCode::H) {
+        settings.hud.layout = settings.hud.layout.next();
+        info!("HUD layout: {:?}", settings.hud.layout);
+    }
+    if keys.just_pressed(KeyCode::I) {
+        settings.hud.cockpit = !settings.hud.cockpit;
+        info!("Cockpit: {}", settings.hud.cockpit);
+    }
+    if keys.just_pressed(KeyCode::U) {
+        settings.hud.rear_view = !settings.hud.rear_view;
+        info!("Rear view: {}", settings.hud.rear_view);
+    }
+    if keys.just_pressed(KeyCode::K) {
+        settings.hud.missile_cam = !settings.hud.missile_cam;
+        info!("Missile cam: {}", settings.hud.missile_cam);
+    }
+    if keys.just_pressed(KeyCode::Equals) {
+        settings.hud.scale = (settings.hud.scale + 0.1).min(2.0);
+        info!("HUD scale: {:.1}", settings.hud.scale);
+    }
+    if keys.just_pressed(KeyCode::Minus) {
+        settings.hud.scale = (settings.hud.scale - 0.1).max(0.5);
+        info!("HUD scale: {:.1}", settings.hud.scale);
+    }
+}
+
+/// Player's heads-up display: flight info, fire cycle of each weapon, reticle with the gun
+/// convergence pipper, rocket impact prediction, target panel with the target track, boss bar,
+/// damage indicators, collision alert, kill feed, notifications, radio messages and sound captions,
+/// with a switchable layout scaled to the window size, per-widget opacity fading out out of combat,
+/// and accessibility palettes.
+/// Optionally there is a cockpit around the camera, with MFD screens replacing the target panel,
+/// and picture-in-picture rear view and missile cam.
+pub struct HudPlugin;
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Settings>()
+            .add_plugin(accessibility::AccessibilityPlugin)
+            .add_plugin(boss_bar::BossBarPlugin)
+            .add_plugin(captions::CaptionsPlugin)
+            .add_plugin(cockpit::CockpitPlugin)
+            .add_plugin(collision_alert::CollisionAlertPlugin)
+            .add_plugin(damage_indicator::DamageIndicatorPlugin)
+            .add_plugin(dialogue::DialoguePlugin)
+            .add_plugin(flight::FlightHudPlugin)
+            .add_plugin(highlight::HighlightPlugin)
+            .add_plugin(kill_feed::KillFeedPlugin)
+            .add_plugin(notifications::NotificationsPlugin)
+            .add_plugin(opacity::OpacityPlugin)
+            .add_plugin(picture_in_picture::PictureInPicturePlugin)
+            .add_plugin(pipper::PipperPlugin)
+            .add_plugin(reticle::ReticlePlugin)
+            .add_plugin(rocket_prediction::RocketPredictionPlugin)
+            .add_plugin(target::TargetPanelPlugin)
+            .add_plugin(target_brackets::TargetBracketsPlugin)
+            .add_plugin(target_track::TargetTrackPlugin)
+            .add_plugin(weapons::WeaponsHudPlugin)
+            .add_startup_system(setup_hud)
+            .add_system(attach_widgets)
+            .add_system(apply_layout)
+            .add_system(apply_scale)
+            .add_system(hud_controls);
+    }
+}
@@ -0,0 +1,241 @@
+use bevy::prelude::*;
+
+use super::{HudAnchor, HudWidget};
+use crate::{
+    aiming::DesignateTargetEvent, components::Fraction, ion_storm::InIonStorm,
+    jump_gate::JumpInEvent, localization::Localization, player::Player,
+    profile::achievements::AchievementUnlockedEvent, reinforcements::BattleEndedEvent,
+    trigger::ScenarioMessageEvent,
+};
+
+/// Time the toast is shown, in seconds
+const TOAST_TIME: f32 = 5.0;
+/// Toast fades out during this last part of its lifetime, in seconds
+const FADE_TIME: f32 = 1.0;
+
+#[derive(Component)]
+struct Notifications;
+
+/// Temporary notification, like an unlocked achievement or the battle outcome.
+#[derive(Component)]
+struct Toast {
+    time_left: f32,
+}
+
+fn setup_notifications(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(HudWidget::essential(HudAnchor::Top))
+        .insert(Notifications)
+        .insert(Name::new("Notifications"));
+}
+
+fn spawn_toast(commands: &mut Commands, notifications: Entity, font: Handle<Font>, text: String) {
+    commands.entity(notifications).with_children(|parent| {
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    margin: UiRect::all(Val::Px(5.0)),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+                ..default()
+            })
+            .insert(Toast {
+                time_left: TOAST_TIME,
+            })
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    text,
+                    TextStyle {
+                        font,
+                        font_size: 24.0,
+                        color: Color::GOLD,
+                    },
+                ));
+            });
+    });
+}
+
+/// Bearing of the `local` position in degrees, clockwise from the nose in the horizontal plane
+fn bearing(local: Vec3) -> u32 {
+    let degrees = local.x.atan2(-local.z).to_degrees().round();
+    degrees.rem_euclid(360.0) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::bearing;
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_bearing() {
+        assert_eq!(bearing(Vec3::NEG_Z), 0);
+        assert_eq!(bearing(Vec3::X), 90);
+        assert_eq!(bearing(Vec3::Z), 180);
+        assert_eq!(bearing(Vec3::NEG_X), 270);
+        // height doesn't matter
+        assert_eq!(bearing(Vec3::new(-1.0, 5.0, 1.0)), 225);
+        // slightly to the left of the nose
+        assert_eq!(bearing(Vec3::new(-0.001, 0.0, -1.0)), 0);
+    }
+}
+
+fn spawn_toasts(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    localization: Res<Localization>,
+    mut ev_unlocked: EventReader<AchievementUnlockedEvent>,
+    mut ev_battle_ended: EventReader<BattleEndedEvent>,
+    entered_storm: Query<(), (With<Player>, Added<InIonStorm>)>,
+    notifications: Query<Entity, With<Notifications>>,
+) {
+    let Ok(notifications) = notifications.get_single() else {
+        return;
+    };
+    let font = assets.load("fonts/FiraMono-Medium.ttf");
+
+    for ev in ev_unlocked.iter() {
+        let key = ev.0.key();
+        let text = localization.format(
+            "hud-achievement-unlocked",
+            &[
+                ("name", &localization.get(key)),
+                ("description", &localization.get(&format!("{key}-desc"))),
+            ],
+        );
+        spawn_toast(&mut commands, notifications, font.clone(), text);
+    }
+    for ev in ev_battle_ended.iter() {
+        let faction = localization.get(ev.defeated.key());
+        let text = localization.format("hud-battle-over", &[("faction", &faction)]);
+        spawn_toast(&mut commands, notifications, font.clone(), text);
+    }
+    if !entered_storm.is_empty() {
+        let text = localization.get("hud-ion-storm").to_owned();
+        spawn_toast(&mut commands, notifications, font, text);
+    }
+}
+
+fn jump_in_toasts(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    localization: Res<Localization>,
+    mut ev_jump_in: EventReader<JumpInEvent>,
+    player: Query<(&GlobalTransform, Option<&Fraction>), With<Player>>,
+    notifications: Query<Entity, With<Notifications>>,
+) {
+    let (Ok(notifications), Ok((transform, fraction))) =
+        (notifications.get_single(), player.get_single())
+    else {
+        return;
+    };
+    let font = assets.load("fonts/FiraMono-Medium.ttf");
+
+    let (_, rotation, translation) = transform.to_scale_rotation_translation();
+    for ev in ev_jump_in.iter() {
+        let key = if fraction == Some(&ev.fraction) {
+            "hud-jump-in-friendly"
+        } else {
+            "hud-jump-in-hostile"
+        };
+        let bearing = bearing(rotation.inverse() * (ev.position - translation));
+        let text = localization.format(key, &[("bearing", &format!("{bearing:03}"))]);
+        spawn_toast(&mut commands, notifications, font.clone(), text);
+    }
+}
+
+/// Confirms focus fire orders of the player to allies
+fn focus_fire_toasts(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    localization: Res<Localization>,
+    mut ev_designate: EventReader<DesignateTargetEvent>,
+    player: Query<&Fraction, With<Player>>,
+    targets: Query<&Name>,
+    notifications: Query<Entity, With<Notifications>>,
+) {
+    let (Ok(notifications), Ok(fraction)) = (notifications.get_single(), player.get_single())
+    else {
+        return;
+    };
+    let font = assets.load("fonts/FiraMono-Medium.ttf");
+
+    for ev in ev_designate.iter().filter(|ev| ev.fraction == *fraction) {
+        let name = targets.get(ev.target).map_or_else(
+            |_| localization.get("hud-unknown-target"),
+            |name| name.as_str(),
+        );
+        let text = localization.format("hud-focus-fire", &[("target", &name)]);
+        spawn_toast(&mut commands, notifications, font.clone(), text);
+    }
+}
+
+/// Messages of scenario triggers
+fn message_toasts(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    localization: Res<Localization>,
+    mut ev_message: EventReader<ScenarioMessageEvent>,
+    notifications: Query<Entity, With<Notifications>>,
+) {
+    let Ok(notifications) = notifications.get_single() else {
+        return;
+    };
+    let font = assets.load("fonts/FiraMono-Medium.ttf");
+
+    for ScenarioMessageEvent(text) in ev_message.iter() {
+        let text = localization.get(text).to_owned();
+        spawn_toast(&mut commands, notifications, font.clone(), text);
+    }
+}
+
+fn update_toasts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut toasts: Query<(Entity, &mut Toast, &mut BackgroundColor, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    for (entity, mut toast, mut color, children) in toasts.iter_mut() {
+        toast.time_left -= time.delta_seconds();
+        if toast.time_left <= 0.0 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let alpha = (toast.time_left / FADE_TIME).min(1.0);
+        color.0.set_a(0.8 * alpha);
+        for &child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(child) {
+                text.sections[0].style.color.set_a(alpha);
+            }
+        }
+    }
+}
+
+pub(super) struct NotificationsPlugin;
+impl Plugin for NotificationsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_notifications)
+            .add_system(spawn_toasts)
+            .add_system(jump_in_toasts)
+            .add_system(focus_fire_toasts)
+            .add_system(message_toasts)
+            .add_system(
+                update_toasts
+                    .after(spawn_toasts)
+                    .after(jump_in_toasts)
+                    .after(focus_fire_toasts)
+                    .after(message_toasts),
+            );
+    }
+}
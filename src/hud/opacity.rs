@@ -0,0 +1,171 @@
+//! Opacity of HUD widgets: set per widget by the user, and faded out together when there is no
+//! combat around the player for a while. Widgets keep setting their colors as usual, the opacity
+//! is applied on top of their alpha after all of them.
+
+use bevy::prelude::*;
+
+use super::HudWidget;
+use crate::{music::ThreatLevel, player::Player, projectile::DamageEvent, settings::Settings};
+
+/// Seconds without combat before the HUD starts to fade out
+const FADE_DELAY: f32 = 10.0;
+/// Seconds to fade out, combat brings the HUD back at once
+const FADE_TIME: f32 = 2.0;
+/// Opacity of faded widgets, so they are still readable with a closer look
+const FADED_OPACITY: f32 = 0.2;
+
+/// Calm around the player, driving the automatic HUD fade.
+#[derive(Resource, Default)]
+struct HudFade {
+    /// Seconds since the player last dealt or took damage, or was targeted
+    since_combat: f32,
+}
+
+impl HudFade {
+    /// Opacity of non-essential widgets, from `FADED_OPACITY` to 1
+    fn opacity(&self) -> f32 {
+        let fade = ((self.since_combat - FADE_DELAY) / FADE_TIME).clamp(0.0, 1.0);
+        1.0 - fade * (1.0 - FADED_OPACITY)
+    }
+}
+
+/// Alpha of node colors as set by their widget, each paired with the last faded one applied.
+/// Alpha different from the applied one means the widget set a new color.
+#[derive(Component, Default)]
+struct BaseAlpha {
+    text: Vec<(f32, f32)>,
+    background: (f32, f32),
+}
+
+/// Alpha to apply to a color with the `current` one, updating its `base` and `applied` pair
+fn faded_alpha(current: f32, (base, applied): &mut (f32, f32), opacity: f32) -> f32 {
+    if current != *applied {
+        *base = current;
+    }
+    *applied = *base * opacity;
+    *applied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{faded_alpha, HudFade, FADED_OPACITY, FADE_DELAY, FADE_TIME};
+
+    #[test]
+    fn test_fade() {
+        let mut fade = HudFade::default();
+        assert_eq!(fade.opacity(), 1.0);
+        fade.since_combat = FADE_DELAY + FADE_TIME / 2.0;
+        assert!(fade.opacity() < 1.0 && fade.opacity() > FADED_OPACITY);
+        fade.since_combat = 2.0 * (FADE_DELAY + FADE_TIME);
+        assert_eq!(fade.opacity(), FADED_OPACITY);
+    }
+
+    #[test]
+    fn test_faded_alpha() {
+        let mut alpha = (1.0, 1.0);
+        assert_eq!(faded_alpha(1.0, &mut alpha, 0.5), 0.5);
+        // doesn't fade the faded color again
+        assert_eq!(faded_alpha(0.5, &mut alpha, 0.5), 0.5);
+        assert_eq!(faded_alpha(0.5, &mut alpha, 1.0), 1.0);
+        // widget sets a new color
+        assert_eq!(faded_alpha(0.8, &mut alpha, 0.5), 0.4);
+        assert_eq!(alpha, (0.8, 0.4));
+    }
+}
+
+fn track_combat(
+    time: Res<Time>,
+    threat: Res<ThreatLevel>,
+    mut fade: ResMut<HudFade>,
+    mut ev_damage: EventReader<DamageEvent>,
+    player: Query<Entity, With<Player>>,
+) {
+    let player = player.get_single().ok();
+    let hits = ev_damage
+        .iter()
+        .filter(|ev| player.is_some() && (Some(ev.target) == player || ev.attacker == player))
+        .count();
+    if hits > 0 || threat.value > 0.0 {
+        fade.since_combat = 0.0;
+    } else {
+        fade.since_combat += time.delta_seconds();
+    }
+}
+
+/// Applies the opacity to texts and backgrounds of every widget and its descendants
+fn apply_opacity(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    fade: Res<HudFade>,
+    widgets: Query<(Entity, &HudWidget, Option<&Name>)>,
+    children: Query<&Children>,
+    mut nodes: Query<(
+        Option<&mut Text>,
+        Option<&mut BackgroundColor>,
+        Option<&mut BaseAlpha>,
+    )>,
+) {
+    let calm = if settings.hud.auto_fade {
+        fade.opacity()
+    } else {
+        1.0
+    };
+    let mut stack = Vec::new();
+    for (widget, hud_widget, name) in widgets.iter() {
+        let mut opacity = name.map_or(1.0, |name| settings.hud.opacity(name));
+        // Aim stays clear even when nothing happens
+        if !hud_widget.essential {
+            opacity *= calm;
+        }
+
+        stack.push(widget);
+        while let Some(entity) = stack.pop() {
+            if let Ok(children) = children.get(entity) {
+                stack.extend(children.iter());
+            }
+            let Ok((text, background, base)) = nodes.get_mut(entity) else {
+                continue;
+            };
+            let Some(mut base) = base else {
+                // Opaque nodes are left alone until the first fade
+                if opacity < 1.0 {
+                    commands.entity(entity).insert(BaseAlpha::default());
+                }
+                continue;
+            };
+            // Colors are extracted for rendering every frame, so the change detection is bypassed
+            // to not relayout texts
+            if let Some(mut text) = text {
+                let sections = &mut text.bypass_change_detection().sections;
+                base.text.resize(sections.len(), (1.0, 1.0));
+                for (section, alpha) in sections.iter_mut().zip(base.text.iter_mut()) {
+                    let color = &mut section.style.color;
+                    let faded = faded_alpha(color.a(), alpha, opacity);
+                    if color.a() != faded {
+                        color.set_a(faded);
+                    }
+                }
+            }
+            if let Some(mut background) = background {
+                let color = &mut background.bypass_change_detection().0;
+                let faded = faded_alpha(color.a(), &mut base.background, opacity);
+                if color.a() != faded {
+                    color.set_a(faded);
+                }
+            }
+        }
+    }
+}
+
+/// Per-widget opacity and the automatic fade out of combat.
+pub(super) struct OpacityPlugin;
+impl Plugin for OpacityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HudFade>()
+            .init_resource::<ThreatLevel>()
+            .add_event::<DamageEvent>()
+            .add_system(track_combat)
+            // After widgets update their colors
+            .add_system_to_stage(CoreStage::PostUpdate, apply_opacity);
+    }
+}
@@ -0,0 +1,300 @@
+//! Picture-in-picture views rendered by secondary cameras to small textures: a rear-view mirror
+//! at the top of the screen, and a missile cam chasing the last rocket launched by the player
+//! until a moment after its impact. They are rendered only while shown, at a reduced resolution
+//! and frame rate, so they cost a fraction of the main view.
+
+use bevy::{prelude::*, render::camera::RenderTarget};
+use bevy_rapier3d::prelude::*;
+
+use super::{HudAnchor, HudLayout, HudSettings, HudWidget};
+use crate::{
+    components::HitPoints,
+    display::{render_texture, SecondaryCamera},
+    localization::LocalizedText,
+    player::{Player, PlayerCamera},
+    projectile::{Owner, Rocket},
+    settings::Settings,
+};
+
+/// Resolution of the rendered views, in pixels
+const PIP_RESOLUTION: UVec2 = UVec2::new(384, 216);
+/// Size of the views on the HUD, in UI pixels
+const PIP_SIZE: Vec2 = Vec2::new(320.0, 180.0);
+/// Views are rendered at most this many times per second
+const PIP_FRAME_RATE: f32 = 20.0;
+/// Missile cam keeps showing the impact point for this time, in seconds
+const IMPACT_LINGER: f32 = 1.0;
+/// Missile cam follows rockets this far behind, in meters
+const CHASE_DISTANCE: f32 = 8.0;
+/// and this high above, in meters
+const CHASE_HEIGHT: f32 = 1.5;
+/// Missile cam looks at the point this far ahead of the rocket, in meters
+const LOOK_AHEAD: f32 = 30.0;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+enum PipView {
+    RearView,
+    MissileCam,
+}
+
+impl PipView {
+    const ALL: [PipView; 2] = [PipView::RearView, PipView::MissileCam];
+
+    fn enabled(self, settings: &HudSettings) -> bool {
+        match self {
+            PipView::RearView => settings.rear_view,
+            PipView::MissileCam => settings.missile_cam,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            PipView::RearView => "hud-rear-view",
+            PipView::MissileCam => "hud-missile-cam",
+        }
+    }
+
+    fn anchor(self) -> HudAnchor {
+        match self {
+            // Where a mirror would be
+            PipView::RearView => HudAnchor::Top,
+            PipView::MissileCam => HudAnchor::Right,
+        }
+    }
+}
+
+/// Secondary camera of a view, active only for the frames it renders.
+#[derive(Component)]
+struct PipCamera {
+    view: PipView,
+    /// Seconds since the last rendered frame
+    since_render: f32,
+}
+
+/// Missile cam state, on its camera.
+#[derive(Component, Default)]
+struct MissileCam {
+    rocket: Option<Entity>,
+    /// Seconds left to show the impact point
+    linger: f32,
+}
+
+impl MissileCam {
+    fn is_active(&self) -> bool {
+        self.rocket.is_some() || self.linger > 0.0
+    }
+}
+
+/// Root UI node of a view.
+#[derive(Component)]
+struct PipWidget(PipView);
+
+/// Camera chasing a rocket at the `position` flying along the `direction`, with the `up` of the player
+fn chase_transform(position: Vec3, direction: Vec3, up: Vec3) -> Transform {
+    let camera = position - direction * CHASE_DISTANCE + up * CHASE_HEIGHT;
+    Transform::from_translation(camera).looking_at(position + direction * LOOK_AHEAD, up)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chase_transform, MissileCam, CHASE_DISTANCE};
+    use bevy::prelude::{Entity, Vec3};
+
+    #[test]
+    fn test_chase_transform() {
+        let transform = chase_transform(Vec3::ZERO, Vec3::NEG_Z, Vec3::Y);
+        assert!(transform.translation.z > CHASE_DISTANCE - 0.1);
+        assert!(transform.translation.y > 0.0);
+        // looks along the rocket, slightly down
+        let forward = transform.forward();
+        assert!(forward.z < -0.99);
+        assert!(forward.y < 0.0);
+    }
+
+    #[test]
+    fn test_missile_cam_active() {
+        let mut cam = MissileCam::default();
+        assert!(!cam.is_active());
+        cam.rocket = Some(Entity::from_raw(1));
+        assert!(cam.is_active());
+        cam.rocket = None;
+        cam.linger = 0.5;
+        assert!(cam.is_active());
+    }
+}
+
+fn spawn_views(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    camera: Query<Entity, Added<PlayerCamera>>,
+) {
+    let Ok(player_camera) = camera.get_single() else {
+        return;
+    };
+    let font = assets.load("fonts/FiraMono-Medium.ttf");
+
+    for view in PipView::ALL {
+        let image = images.add(render_texture(PIP_RESOLUTION));
+        let mut camera = commands.spawn(Camera3dBundle {
+            camera: Camera {
+                priority: -1,
+                is_active: false,
+                target: RenderTarget::Image(image.clone()),
+                ..default()
+            },
+            ..default()
+        });
+        camera
+            .insert(SecondaryCamera)
+            .insert(UiCameraConfig { show_ui: false })
+            .insert(PipCamera {
+                view,
+                since_render: f32::INFINITY,
+            })
+            .insert(Name::new(format!("{view:?} Camera")));
+        match view {
+            PipView::RearView => {
+                // Looks back from the player camera, following it from ship to ship
+                camera.insert(Transform::from_rotation(Quat::from_rotation_y(
+                    std::f32::consts::PI,
+                )));
+                let camera = camera.id();
+                commands.entity(player_camera).add_child(camera);
+            }
+            PipView::MissileCam => {
+                camera.insert(MissileCam::default());
+            }
+        }
+
+        commands
+            .spawn(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    margin: UiRect::all(Val::Px(5.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+                visibility: Visibility::INVISIBLE,
+                ..default()
+            })
+            .insert(HudWidget::new(view.anchor()))
+            .insert(PipWidget(view))
+            .insert(Name::new(format!("{view:?}")))
+            .with_children(|parent| {
+                parent
+                    .spawn(TextBundle::from_section(
+                        view.label(),
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 16.0,
+                            color: Color::WHITE,
+                        },
+                    ))
+                    .insert(LocalizedText::new(view.label()));
+                parent.spawn(ImageBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(PIP_SIZE.x), Val::Px(PIP_SIZE.y)),
+                        ..default()
+                    },
+                    image: UiImage(image),
+                    ..default()
+                });
+            });
+    }
+}
+
+/// Picks up rockets launched by the player and keeps the missile cam behind the latest one
+fn follow_missile(
+    time: Res<Time>,
+    player: Query<(Entity, &GlobalTransform), With<Player>>,
+    launched: Query<(Entity, &Owner), Added<Rocket>>,
+    rockets: Query<(&GlobalTransform, &Velocity), With<Rocket>>,
+    mut cameras: Query<(&mut MissileCam, &mut Transform)>,
+) {
+    let Ok((player, player_transform)) = player.get_single() else {
+        return;
+    };
+    let latest = launched
+        .iter()
+        .filter(|(_, owner)| owner.0 == player)
+        .map(|(rocket, _)| rocket)
+        .last();
+
+    for (mut cam, mut transform) in cameras.iter_mut() {
+        if let Some(rocket) = latest {
+            cam.rocket = Some(rocket);
+            cam.linger = 0.0;
+        }
+        let Some(rocket) = cam.rocket else {
+            if cam.linger > 0.0 {
+                cam.linger = (cam.linger - time.delta_seconds()).max(0.0);
+            }
+            continue;
+        };
+        let Ok((rocket_transform, velocity)) = rockets.get(rocket) else {
+            // Exploded, the camera stays in place to show the impact
+            cam.rocket = None;
+            cam.linger = IMPACT_LINGER;
+            continue;
+        };
+        // Rockets are aligned with their Y axis
+        let direction = velocity
+            .linvel
+            .try_normalize()
+            .unwrap_or_else(|| rocket_transform.up());
+        *transform = chase_transform(
+            rocket_transform.translation(),
+            direction,
+            player_transform.up(),
+        );
+    }
+}
+
+/// Views are rendered while shown, but no more often than `PIP_FRAME_RATE`
+fn render_views(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    player: Query<&HitPoints, With<Player>>,
+    mut cameras: Query<(&mut Camera, &mut PipCamera, Option<&MissileCam>)>,
+    mut widgets: Query<(&PipWidget, &mut Visibility)>,
+) {
+    let alive = player.get_single().map_or(false, |hp| !hp.dead());
+    let full = settings.hud.layout == HudLayout::Full;
+    let shown = |view: PipView, cam: Option<&MissileCam>| {
+        alive && full && view.enabled(&settings.hud) && cam.map_or(true, MissileCam::is_active)
+    };
+
+    let mut visible = Vec::new();
+    for (mut camera, mut pip, cam) in cameras.iter_mut() {
+        pip.since_render += time.delta_seconds();
+        let shown = shown(pip.view, cam);
+        let render = shown && pip.since_render >= 1.0 / PIP_FRAME_RATE;
+        if render {
+            pip.since_render = 0.0;
+        }
+        if camera.is_active != render {
+            camera.is_active = render;
+        }
+        if shown {
+            visible.push(pip.view);
+        }
+    }
+    for (widget, mut visibility) in widgets.iter_mut() {
+        let shown = visible.contains(&widget.0);
+        if visibility.is_visible != shown {
+            visibility.is_visible = shown;
+        }
+    }
+}
+
+/// Rear view and missile cam on the HUD, toggled with U and K.
+pub(super) struct PictureInPicturePlugin;
+impl Plugin for PictureInPicturePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(spawn_views)
+            .add_system(follow_missile)
+            .add_system(render_views.after(follow_missile));
+    }
+}
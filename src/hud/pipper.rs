@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::{world_to_ui, HudAnchor, HudWidget, Indicator};
+use crate::{
+    gun::Gun,
+    player::{PlayerCamera, PrimaryWeapon},
+    settings::Settings,
+};
+
+/// Where the primary weapons actually hit: barrels are offset from the camera, so their shots
+/// converge off the screen center at short distances.
+#[derive(Component)]
+struct Pipper;
+
+fn setup_pipper(mut commands: Commands, assets: Res<AssetServer>) {
+    // Pipper is positioned in screen coordinates, so its widget covers the whole screen
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(HudWidget::essential(HudAnchor::TopLeft))
+        .insert(Name::new("Pipper"))
+        .with_children(|parent| {
+            parent
+                .spawn(
+                    TextBundle::from_section(
+                        "(·)",
+                        TextStyle {
+                            font: assets.load("fonts/FiraMono-Medium.ttf"),
+                            font_size: 18.0,
+                            color: Color::WHITE,
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        ..default()
+                    }),
+                )
+                .insert(Pipper);
+        });
+}
+
+fn update_pipper(
+    rapier_context: Res<RapierContext>,
+    windows: Res<Windows>,
+    ui_scale: Res<UiScale>,
+    settings: Res<Settings>,
+    camera: Query<(&Camera, &GlobalTransform, &PlayerCamera)>,
+    barrels: Query<(&GlobalTransform, &Gun), With<PrimaryWeapon>>,
+    mut pipper: Query<(&Node, &mut Style, &mut Visibility, &mut Text), With<Pipper>>,
+) {
+    let Ok((node, mut style, mut visibility, mut text)) = pipper.get_single_mut() else {
+        return;
+    };
+    let Ok((camera, transform, player_camera)) = camera.get_single() else {
+        visibility.is_visible = false;
+        return;
+    };
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+
+    // First hit along each barrel within the weapon range, or the farthest point it reaches
+    let filter = QueryFilter::new()
+        .exclude_sensors()
+        .exclude_collider(player_camera.target);
+    let mut convergence = Vec3::ZERO;
+    let mut count = 0;
+    let mut on_target = false;
+    for (barrel, gun) in barrels.iter() {
+        let origin = barrel.translation();
+        let direction = barrel.forward();
+        let range = gun.effective_range();
+        let hit = rapier_context.cast_ray(origin, direction, range, true, filter);
+        on_target |= hit.is_some();
+        convergence += origin + direction * hit.map_or(range, |(_, toi)| toi);
+        count += 1;
+    }
+
+    let ui_pos = (count > 0)
+        .then(|| convergence / count as f32)
+        .and_then(|point| world_to_ui(camera, transform, window, &ui_scale, point));
+    visibility.is_visible = ui_pos.is_some();
+    if let Some(ui_pos) = ui_pos {
+        let pos = ui_pos - node.size() / 2.0;
+        style.position = UiRect {
+            left: Val::Px(pos.x),
+            top: Val::Px(pos.y),
+            ..default()
+        };
+        text.sections[0].style.color = if on_target {
+            settings.accessibility.color(Indicator::Good)
+        } else {
+            Color::WHITE
+        };
+    }
+}
+
+pub(super) struct PipperPlugin;
+impl Plugin for PipperPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_pipper)
+            .add_system(update_pipper);
+    }
+}
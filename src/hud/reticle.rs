@@ -0,0 +1,146 @@
+use bevy::{prelude::*, render::camera::Projection};
+
+use super::{HudAnchor, HudWidget, Indicator};
+use crate::{
+    gun::{Gun, Heat},
+    player::{LockedTarget, Player, PlayerCamera, PrimaryWeapon},
+    settings::Settings,
+};
+
+/// Reticle size for the accurate gun
+const BASE_SIZE: f32 = 40.0;
+/// Reticle blinks with this frequency when the locked target is within effective range
+const FLASH_FREQUENCY: f32 = 4.0;
+/// Offset of the reticle copies that thicken its lines in the high contrast mode, in UI pixels
+const STROKE_OFFSET: f32 = 1.5;
+
+#[derive(Component)]
+struct Reticle;
+
+/// Shifted copy of the reticle, shown in the high contrast mode
+#[derive(Component)]
+struct ReticleStroke;
+
+fn setup_reticle(mut commands: Commands, assets: Res<AssetServer>) {
+    // Aim in the middle of the screen
+    let image = assets.load("UI/aim.png");
+    commands
+        .spawn(ImageBundle {
+            style: Style {
+                size: Size::new(Val::Px(BASE_SIZE), Val::Px(BASE_SIZE)),
+                ..default()
+            },
+            image: image.clone().into(),
+            ..default()
+        })
+        .insert(HudWidget::essential(HudAnchor::Center))
+        .insert(Reticle)
+        .insert(Name::new("Aim"))
+        .with_children(|parent| {
+            for (x, y) in [(-1.0, 0.0), (1.0, 0.0), (0.0, -1.0), (0.0, 1.0)] {
+                parent
+                    .spawn(ImageBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                            position_type: PositionType::Absolute,
+                            position: UiRect {
+                                left: Val::Px(x * STROKE_OFFSET),
+                                top: Val::Px(y * STROKE_OFFSET),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        image: image.clone().into(),
+                        visibility: Visibility::INVISIBLE,
+                        ..default()
+                    })
+                    .insert(ReticleStroke);
+            }
+        });
+}
+
+/// Expands reticle to cover the spread cone of primary weapons.
+fn update_reticle(
+    windows: Res<Windows>,
+    ui_scale: Res<UiScale>,
+    camera: Query<&Projection, With<PlayerCamera>>,
+    guns: Query<&Heat, With<PrimaryWeapon>>,
+    mut reticle: Query<&mut Style, With<Reticle>>,
+) {
+    let Ok(Projection::Perspective(projection)) = camera.get_single() else {
+        return;
+    };
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+    let Ok(mut style) = reticle.get_single_mut() else {
+        return;
+    };
+
+    let spread = guns
+        .iter()
+        .fold(0.0_f32, |spread, heat| spread.max(heat.spread()));
+
+    // Radius of the spread cone on the screen, in UI pixels
+    let half_height = 0.5 * window.height() / ui_scale.scale as f32;
+    let spread_radius = spread.tan() / (0.5 * projection.fov).tan() * half_height;
+    let size = Val::Px(BASE_SIZE + 2.0 * spread_radius);
+    if style.size.width != size {
+        style.size = Size::new(size, size);
+    }
+}
+
+/// Tints reticle by the state of primary weapons and thickens it in the high contrast mode
+fn tint_reticle(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    player: Query<&GlobalTransform, With<Player>>,
+    guns: Query<(&Gun, &Heat), With<PrimaryWeapon>>,
+    target: Query<&GlobalTransform, With<LockedTarget>>,
+    mut reticle: Query<&mut BackgroundColor, (With<Reticle>, Without<ReticleStroke>)>,
+    mut strokes: Query<(&mut BackgroundColor, &mut Visibility), With<ReticleStroke>>,
+) {
+    let Ok(transform) = player.get_single() else {
+        return;
+    };
+    let Ok(mut color) = reticle.get_single_mut() else {
+        return;
+    };
+
+    let (overheated, range) =
+        guns.iter()
+            .fold((false, 0.0_f32), |(overheated, range), (gun, heat)| {
+                (
+                    overheated || heat.overheated(),
+                    range.max(gun.effective_range()),
+                )
+            });
+
+    let in_range = target.get_single().map_or(false, |target| {
+        target.translation().distance(transform.translation()) < range
+    });
+    let accessibility = &settings.accessibility;
+    color.0 = if overheated {
+        accessibility.color(Indicator::Warning)
+    } else if in_range && (time.elapsed_seconds() * FLASH_FREQUENCY).fract() < 0.5 {
+        accessibility.color(Indicator::Danger)
+    } else {
+        Color::WHITE
+    };
+
+    for (mut stroke_color, mut visibility) in strokes.iter_mut() {
+        stroke_color.0 = color.0;
+        if visibility.is_visible != accessibility.high_contrast {
+            visibility.is_visible = accessibility.high_contrast;
+        }
+    }
+}
+
+pub(super) struct ReticlePlugin;
+impl Plugin for ReticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_reticle)
+            .add_system(update_reticle)
+            .add_system(tint_reticle);
+    }
+}
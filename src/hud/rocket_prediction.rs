@@ -0,0 +1,219 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::{world_to_ui, HudAnchor, HudWidget, Indicator};
+use crate::{
+    aiming::interception_time,
+    gun::{Gun, ROCKET_LIFETIME},
+    localization::Localization,
+    player::{LockedTarget, Player, PlayerCamera, SecondaryWeapon},
+    settings::Settings,
+};
+
+/// Rocket prediction: the marker and the readout below it
+#[derive(Component)]
+struct RocketPrediction;
+
+/// Where an unguided rocket fired right now ends up once its lifetime expires
+#[derive(Component)]
+struct ImpactMarker;
+
+/// Time for a rocket to reach the locked target
+#[derive(Component)]
+struct TimeToTarget;
+
+/// Launch point and velocity of a rocket fired from the `launchers`, averaged between them.
+/// Rockets inherit the ship `velocity`.
+fn launch<'a>(
+    launchers: impl Iterator<Item = (&'a GlobalTransform, &'a Gun)>,
+    velocity: Vec3,
+) -> Option<(Vec3, Vec3)> {
+    let mut origin = Vec3::ZERO;
+    let mut rocket_velocity = Vec3::ZERO;
+    let mut count = 0;
+    for (launcher, gun) in launchers {
+        origin += launcher.translation();
+        rocket_velocity += launcher.forward() * gun.speed() + velocity;
+        count += 1;
+    }
+    (count > 0).then(|| (origin / count as f32, rocket_velocity / count as f32))
+}
+
+/// Seconds for a rocket launched with `rocket_velocity` to reach the target `to_target` away,
+/// moving with `target_velocity`. Homing keeps the rocket speed, so only its length matters.
+/// `None` if the rocket expires before that.
+fn time_to_target(to_target: Vec3, target_velocity: Vec3, rocket_velocity: Vec3) -> Option<f32> {
+    interception_time(to_target, target_velocity, rocket_velocity.length())
+        .filter(|&time| time <= ROCKET_LIFETIME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::time_to_target;
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_time_to_target() {
+        let rocket = Vec3::NEG_Z * 20.0;
+        assert_eq!(
+            time_to_target(Vec3::NEG_Z * 100.0, Vec3::ZERO, rocket),
+            Some(5.0)
+        );
+        // the direction of the launch doesn't matter for homing rockets
+        assert_eq!(
+            time_to_target(Vec3::X * 100.0, Vec3::ZERO, rocket),
+            Some(5.0)
+        );
+        // too far away or too fast to catch up
+        assert_eq!(
+            time_to_target(Vec3::NEG_Z * 1000.0, Vec3::ZERO, rocket),
+            None
+        );
+        let receding = Vec3::NEG_Z * 19.0;
+        assert_eq!(time_to_target(Vec3::NEG_Z * 100.0, receding, rocket), None);
+    }
+}
+
+fn setup_rocket_prediction(mut commands: Commands, assets: Res<AssetServer>) {
+    let font = assets.load("fonts/FiraMono-Medium.ttf");
+
+    // Marker is positioned in screen coordinates, so its widget covers the whole screen
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(HudWidget::essential(HudAnchor::TopLeft))
+        .insert(Name::new("Rocket Prediction"))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: Color::NONE.into(),
+                    ..default()
+                })
+                .insert(RocketPrediction)
+                .with_children(|parent| {
+                    parent
+                        .spawn(TextBundle::from_section(
+                            "(   )",
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 24.0,
+                                color: Color::WHITE,
+                            },
+                        ))
+                        .insert(ImpactMarker);
+                    parent
+                        .spawn(TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font,
+                                font_size: 16.0,
+                                color: Color::WHITE,
+                            },
+                        ))
+                        .insert(TimeToTarget);
+                });
+        });
+}
+
+fn update_impact_marker(
+    windows: Res<Windows>,
+    ui_scale: Res<UiScale>,
+    player: Query<Option<&Velocity>, With<Player>>,
+    camera: Query<(&Camera, &GlobalTransform), With<PlayerCamera>>,
+    launchers: Query<(&GlobalTransform, &Gun), With<SecondaryWeapon>>,
+    mut prediction: Query<(&Node, &mut Style, &mut Visibility), With<RocketPrediction>>,
+    marker: Query<&Node, With<ImpactMarker>>,
+) {
+    let Ok((node, mut style, mut visibility)) = prediction.get_single_mut() else {
+        return;
+    };
+    let Ok(velocity) = player.get_single() else {
+        visibility.is_visible = false;
+        return;
+    };
+    let Ok((camera, transform)) = camera.get_single() else {
+        visibility.is_visible = false;
+        return;
+    };
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+
+    let velocity = velocity.map(|v| v.linvel).unwrap_or_default();
+    let ui_pos = launch(launchers.iter(), velocity).and_then(|(origin, rocket_velocity)| {
+        let impact = origin + rocket_velocity * ROCKET_LIFETIME;
+        world_to_ui(camera, transform, window, &ui_scale, impact)
+    });
+    visibility.is_visible = ui_pos.is_some();
+    if let Some(ui_pos) = ui_pos {
+        // the marker itself is centered on the impact point, with the readout below it
+        let marker_height = marker.get_single().map_or(0.0, |marker| marker.size().y);
+        style.position = UiRect {
+            left: Val::Px(ui_pos.x - node.size().x / 2.0),
+            top: Val::Px(ui_pos.y - marker_height / 2.0),
+            ..default()
+        };
+    }
+}
+
+fn update_time_to_target(
+    localization: Res<Localization>,
+    settings: Res<Settings>,
+    player: Query<Option<&Velocity>, With<Player>>,
+    launchers: Query<(&GlobalTransform, &Gun), With<SecondaryWeapon>>,
+    target: Query<(&GlobalTransform, Option<&Velocity>), With<LockedTarget>>,
+    mut readout: Query<(&mut Text, &mut Style), With<TimeToTarget>>,
+) {
+    let Ok((mut text, mut style)) = readout.get_single_mut() else {
+        return;
+    };
+    let velocity = player.get_single().ok().flatten();
+    let velocity = velocity.map(|v| v.linvel).unwrap_or_default();
+    let prediction = launch(launchers.iter(), velocity).zip(target.get_single().ok());
+    let Some(((origin, rocket_velocity), (target, target_velocity))) = prediction else {
+        if style.display != Display::None {
+            style.display = Display::None;
+        }
+        return;
+    };
+    if style.display != Display::Flex {
+        style.display = Display::Flex;
+    }
+
+    let to_target = target.translation() - origin;
+    let target_velocity = target_velocity.map(|v| v.linvel).unwrap_or_default();
+    let section = &mut text.sections[0];
+    match time_to_target(to_target, target_velocity, rocket_velocity) {
+        Some(time) => {
+            let time = format!("{time:.1}");
+            section.value = localization.format("hud-rocket-time", &[("time", &time)]);
+            section.style.color = settings.accessibility.color(Indicator::Good);
+        }
+        None => {
+            section.value = localization.get("hud-rocket-out-of-range").to_owned();
+            section.style.color = settings.accessibility.color(Indicator::Danger);
+        }
+    }
+}
+
+pub(super) struct RocketPredictionPlugin;
+impl Plugin for RocketPredictionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_rocket_prediction)
+            .add_system(update_impact_marker)
+            .add_system(update_time_to_target);
+    }
+}
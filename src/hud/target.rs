@@ -0,0 +1,213 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::{text_bar, HudAnchor, HudWidget, PanelBackground};
+use crate::{
+    components::{Fraction, HitPoints},
+    localization::Localization,
+    player::{LockedTarget, Player},
+    projectile::Shield,
+    settings::Settings,
+    veterancy::Veterancy,
+};
+
+/// Number of cells in hull and shield bars
+const BAR_CELLS: usize = 10;
+
+/// Line of the target panel. Lines without data for the current target are collapsed.
+#[derive(Component, Clone, Copy)]
+enum TargetRow {
+    Name,
+    Veterancy,
+    Faction,
+    Hull,
+    Shield,
+    Speed,
+    Distance,
+    ClosingRate,
+    Subsystems,
+}
+
+impl TargetRow {
+    const ALL: [TargetRow; 9] = [
+        TargetRow::Name,
+        TargetRow::Veterancy,
+        TargetRow::Faction,
+        TargetRow::Hull,
+        TargetRow::Shield,
+        TargetRow::Speed,
+        TargetRow::Distance,
+        TargetRow::ClosingRate,
+        TargetRow::Subsystems,
+    ];
+}
+
+fn setup_target_panel(mut commands: Commands, assets: Res<AssetServer>) {
+    let font = assets.load("fonts/FiraMono-Medium.ttf");
+
+    // Semi-transparent section in the right bottom corner for the locked target information
+    let background = Color::rgba(0.7, 0.7, 0.7, 0.3);
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(480.0), Val::Auto),
+                min_size: Size::new(Val::Auto, Val::Px(40.0)),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexStart,
+                padding: UiRect::all(Val::Px(5.0)),
+                ..default()
+            },
+            background_color: background.into(),
+            ..default()
+        })
+        .insert(HudWidget::new(HudAnchor::BottomRight).on_mfd())
+        .insert(PanelBackground(background))
+        .insert(Name::new("Target Panel"))
+        .with_children(|parent| {
+            for row in TargetRow::ALL {
+                parent
+                    .spawn(TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 20.0,
+                            color: Color::WHITE,
+                        },
+                    ))
+                    .insert(row);
+            }
+        });
+}
+
+fn update_target_panel(
+    player: Query<(&GlobalTransform, Option<&Velocity>, Option<&Fraction>), With<Player>>,
+    target: Query<
+        (
+            Entity,
+            Option<&Name>,
+            &GlobalTransform,
+            Option<&Velocity>,
+            Option<&HitPoints>,
+            Option<&Shield>,
+            Option<&Fraction>,
+            Option<&Veterancy>,
+        ),
+        With<LockedTarget>,
+    >,
+    children: Query<&Children>,
+    subsystems: Query<(&Name, &HitPoints)>,
+    localization: Res<Localization>,
+    settings: Res<Settings>,
+    mut rows: Query<(&TargetRow, &mut Text, &mut Style)>,
+) {
+    let Ok((player_transform, player_velocity, own_fraction)) = player.get_single() else {
+        return;
+    };
+    let target = target.get_single().ok();
+    let accessibility = &settings.accessibility;
+
+    for (&row, mut text, mut style) in rows.iter_mut() {
+        let value = match target {
+            None => matches!(row, TargetRow::Name)
+                .then(|| localization.get("hud-select-target").to_owned()),
+            Some((entity, name, transform, velocity, hp, shield, fraction, veterancy)) => {
+                let to_target = transform.translation() - player_transform.translation();
+                let relative_velocity = velocity.map(|v| v.linvel).unwrap_or_default()
+                    - player_velocity.map(|v| v.linvel).unwrap_or_default();
+
+                match row {
+                    TargetRow::Name => {
+                        let name =
+                            name.map_or(localization.get("hud-unknown-target"), |n| n.as_str());
+                        Some(localization.format("hud-selected", &[("name", &name)]))
+                    }
+                    // Rookies have no chevrons to show
+                    TargetRow::Veterancy => veterancy.filter(|v| v.rank() > 0).map(|veterancy| {
+                        let chevrons = "^".repeat(veterancy.rank());
+                        localization.format(
+                            "hud-veterancy",
+                            &[("chevrons", &chevrons), ("kills", &veterancy.kills())],
+                        )
+                    }),
+                    TargetRow::Faction => fraction.map(|fraction| {
+                        let faction = localization.get(fraction.key());
+                        localization.format("hud-faction", &[("faction", &faction)])
+                    }),
+                    TargetRow::Hull => hp.map(|hp| {
+                        let fill = accessibility.bar_fill(fraction.copied());
+                        let bar = text_bar(hp.percent() as f32 / 100.0, BAR_CELLS, fill);
+                        localization.format("hud-hull", &[("bar", &bar), ("hp", &hp.percent())])
+                    }),
+                    TargetRow::Shield => shield.map(|shield| {
+                        let fill = accessibility.bar_fill(fraction.copied());
+                        let bar = text_bar(shield.percent() as f32 / 100.0, BAR_CELLS, fill);
+                        localization.format(
+                            "hud-shield",
+                            &[("bar", &bar), ("shield", &shield.percent())],
+                        )
+                    }),
+                    TargetRow::Speed => velocity.map(|velocity| {
+                        let speed = format!("{:.1}", velocity.linvel.length());
+                        localization.format("hud-target-speed", &[("speed", &speed)])
+                    }),
+                    TargetRow::Distance => {
+                        let distance = format!("{:.2}", to_target.length());
+                        Some(localization.format("hud-distance", &[("distance", &distance)]))
+                    }
+                    TargetRow::ClosingRate => {
+                        // Positive when the distance decreases
+                        let rate = -relative_velocity.dot(to_target.normalize_or_zero());
+                        let rate = format!("{rate:+.1}");
+                        Some(localization.format("hud-closing-rate", &[("rate", &rate)]))
+                    }
+                    TargetRow::Subsystems => {
+                        let lines: Vec<_> = children
+                            .iter_descendants(entity)
+                            .filter_map(|e| subsystems.get(e).ok())
+                            .map(|(name, hp)| {
+                                localization.format(
+                                    "hud-subsystem",
+                                    &[("name", &name.as_str()), ("hp", &hp.percent())],
+                                )
+                            })
+                            .collect();
+                        (!lines.is_empty()).then(|| {
+                            std::iter::once(localization.get("hud-subsystems").to_owned())
+                                .chain(lines)
+                                .collect::<Vec<_>>()
+                                .join("\n")
+                        })
+                    }
+                }
+            }
+        };
+
+        let display = if value.is_some() {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        if style.display != display {
+            style.display = display;
+        }
+        if let Some(value) = value {
+            text.sections[0].value = value;
+        }
+        // faction and its hull are tinted as hostile or friendly
+        let fraction = target.and_then(|(_, _, _, _, _, _, fraction, _)| fraction);
+        text.sections[0].style.color = match (row, fraction) {
+            (TargetRow::Faction | TargetRow::Hull, Some(&fraction)) => {
+                accessibility.faction_color(fraction, own_fraction.copied())
+            }
+            _ => Color::WHITE,
+        };
+    }
+}
+
+pub(super) struct TargetPanelPlugin;
+impl Plugin for TargetPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_target_panel)
+            .add_system(update_target_panel);
+    }
+}
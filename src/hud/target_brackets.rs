@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+
+use super::{world_to_ui, HudAnchor, HudWidget, Indicator};
+use crate::{
+    components::Fraction,
+    player::{FocusFireEvent, Player, PlayerCamera, TargetQueue, MAX_LOCKED_TARGETS},
+    settings::Settings,
+};
+
+/// Screen-space bracket around the locked target with the given index in the `TargetQueue`.
+/// Clicking it orders allies to focus fire on the target.
+#[derive(Component)]
+struct TargetBracket(usize);
+
+fn setup_brackets(mut commands: Commands, assets: Res<AssetServer>) {
+    let font = assets.load("fonts/FiraMono-Medium.ttf");
+
+    // Brackets are positioned in screen coordinates, so their widget covers the whole screen
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(HudWidget::essential(HudAnchor::TopLeft))
+        .insert(Name::new("Target Brackets"))
+        .with_children(|parent| {
+            for index in 0..MAX_LOCKED_TARGETS {
+                parent
+                    .spawn(
+                        TextBundle::from_section(
+                            "[  ]",
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 32.0,
+                                color: Color::WHITE,
+                            },
+                        )
+                        .with_style(Style {
+                            position_type: PositionType::Absolute,
+                            ..default()
+                        }),
+                    )
+                    .insert(Interaction::default())
+                    .insert(TargetBracket(index));
+            }
+        });
+}
+
+fn update_brackets(
+    player: Query<&TargetQueue, With<Player>>,
+    camera: Query<(&Camera, &GlobalTransform), With<PlayerCamera>>,
+    targets: Query<(&GlobalTransform, Option<&Fraction>)>,
+    windows: Res<Windows>,
+    ui_scale: Res<UiScale>,
+    settings: Res<Settings>,
+    mut brackets: Query<(
+        &TargetBracket,
+        &Node,
+        &mut Style,
+        &mut Visibility,
+        &mut Text,
+    )>,
+) {
+    let Ok(queue) = player.get_single() else {
+        return;
+    };
+    let Ok((camera, transform)) = camera.get_single() else {
+        return;
+    };
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+
+    let accessibility = &settings.accessibility;
+    for (bracket, node, mut style, mut visibility, mut text) in brackets.iter_mut() {
+        let target = queue.iter().nth(bracket.0);
+        let target_state = target.and_then(|target| targets.get(target).ok());
+        let ui_pos = target_state.and_then(|(target, _)| {
+            world_to_ui(camera, transform, window, &ui_scale, target.translation())
+        });
+
+        visibility.is_visible = ui_pos.is_some();
+        if let Some(ui_pos) = ui_pos {
+            let pos = ui_pos - node.size() / 2.0;
+            style.position = UiRect {
+                left: Val::Px(pos.x),
+                top: Val::Px(pos.y),
+                ..default()
+            };
+            text.sections[0].style.color = accessibility.color(if target == queue.primary() {
+                Indicator::PrimaryTarget
+            } else {
+                Indicator::QueuedTarget
+            });
+            // with shape coding the bracket shape tells factions apart
+            let shape = accessibility.bracket(target_state.and_then(|(_, f)| f.copied()));
+            if text.sections[0].value != shape {
+                text.sections[0].value = shape.to_owned();
+            }
+        }
+    }
+}
+
+fn click_brackets(
+    player: Query<&TargetQueue, With<Player>>,
+    brackets: Query<(&TargetBracket, &Interaction), Changed<Interaction>>,
+    mut ev_focus: EventWriter<FocusFireEvent>,
+) {
+    let Ok(queue) = player.get_single() else {
+        return;
+    };
+    for (bracket, interaction) in brackets.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        if let Some(target) = queue.iter().nth(bracket.0) {
+            ev_focus.send(FocusFireEvent(target));
+        }
+    }
+}
+
+pub(super) struct TargetBracketsPlugin;
+impl Plugin for TargetBracketsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FocusFireEvent>()
+            .add_startup_system(setup_brackets)
+            .add_system(update_brackets)
+            .add_system(click_brackets);
+    }
+}
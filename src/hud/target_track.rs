@@ -0,0 +1,223 @@
+//! Track of the primary locked target on the screen: a fading dot trail of its recent positions
+//! and dots along its predicted path, extrapolated with the velocity and the acceleration
+//! estimated from recent samples.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::{world_to_ui, HudAnchor, HudWidget, Indicator};
+use crate::{
+    player::{Player, PlayerCamera, TargetQueue},
+    settings::Settings,
+};
+
+/// Seconds between samples of the target position
+const SAMPLE_INTERVAL: f32 = 0.2;
+/// Samples in the trail, so it covers the last 4 seconds
+const HISTORY_DOTS: usize = 20;
+/// Dots along the predicted path, one per `SAMPLE_INTERVAL`
+const PREDICTION_DOTS: usize = 10;
+/// Weight of the newest sample in the smoothed acceleration, to not follow every jitter
+const ACCELERATION_SMOOTHING: f32 = 0.5;
+/// In UI pixels
+const DOT_SIZE: f32 = 4.0;
+
+/// Recent samples of the primary target, the newest first.
+#[derive(Resource, Default)]
+struct TargetTrack {
+    target: Option<Entity>,
+    samples: VecDeque<Vec3>,
+    velocity: Vec3,
+    acceleration: Vec3,
+    since_sample: f32,
+}
+
+impl TargetTrack {
+    /// Starts over with a new target
+    fn reset(&mut self, target: Option<Entity>) {
+        *self = Self {
+            target,
+            ..default()
+        };
+    }
+
+    /// Adds the sample and updates the acceleration estimated from the change of the `velocity`
+    fn sample(&mut self, position: Vec3, velocity: Vec3) {
+        if !self.samples.is_empty() {
+            let acceleration = (velocity - self.velocity) / SAMPLE_INTERVAL;
+            self.acceleration = self.acceleration.lerp(acceleration, ACCELERATION_SMOOTHING);
+        }
+        self.velocity = velocity;
+        self.samples.push_front(position);
+        self.samples.truncate(HISTORY_DOTS);
+    }
+}
+
+/// Position of a body in `time` seconds, if it keeps accelerating the same way
+fn predict(position: Vec3, velocity: Vec3, acceleration: Vec3, time: f32) -> Vec3 {
+    position + velocity * time + acceleration * time * time / 2.0
+}
+
+/// Dot of the trail with the index from the newest sample, or of the predicted path from now.
+#[derive(Component, Clone, Copy)]
+enum TrackDot {
+    History(usize),
+    Prediction(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{predict, TargetTrack, HISTORY_DOTS, SAMPLE_INTERVAL};
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_predict() {
+        assert_eq!(predict(Vec3::ZERO, Vec3::X, Vec3::ZERO, 2.0), Vec3::X * 2.0);
+        assert_eq!(predict(Vec3::ZERO, Vec3::ZERO, Vec3::Y, 2.0), Vec3::Y * 2.0);
+    }
+
+    #[test]
+    fn test_track() {
+        let mut track = TargetTrack::default();
+        track.sample(Vec3::ZERO, Vec3::ZERO);
+        // the first sample knows nothing about the acceleration
+        assert_eq!(track.acceleration, Vec3::ZERO);
+        track.sample(Vec3::ZERO, Vec3::X * SAMPLE_INTERVAL);
+        assert_eq!(track.acceleration, Vec3::X / 2.0);
+
+        for _ in 0..2 * HISTORY_DOTS {
+            track.sample(Vec3::Y, Vec3::ZERO);
+        }
+        assert_eq!(track.samples.len(), HISTORY_DOTS);
+        assert_eq!(track.samples[0], Vec3::Y);
+    }
+}
+
+fn setup_track(mut commands: Commands) {
+    // Dots are positioned in screen coordinates, so their widget covers the whole screen
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            ..default()
+        })
+        .insert(HudWidget::new(HudAnchor::TopLeft))
+        .insert(Name::new("Target Track"))
+        .with_children(|parent| {
+            let dots = (0..HISTORY_DOTS)
+                .map(TrackDot::History)
+                .chain((0..PREDICTION_DOTS).map(TrackDot::Prediction));
+            for dot in dots {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(DOT_SIZE), Val::Px(DOT_SIZE)),
+                            position_type: PositionType::Absolute,
+                            ..default()
+                        },
+                        visibility: Visibility::INVISIBLE,
+                        ..default()
+                    })
+                    .insert(dot);
+            }
+        });
+}
+
+fn track_target(
+    time: Res<Time>,
+    mut track: ResMut<TargetTrack>,
+    player: Query<&TargetQueue, With<Player>>,
+    targets: Query<(&GlobalTransform, Option<&Velocity>)>,
+) {
+    let primary = player.get_single().ok().and_then(TargetQueue::primary);
+    if track.target != primary {
+        track.reset(primary);
+    }
+    let Some((transform, velocity)) = primary.and_then(|target| targets.get(target).ok()) else {
+        return;
+    };
+
+    track.since_sample += time.delta_seconds();
+    if !track.samples.is_empty() && track.since_sample < SAMPLE_INTERVAL {
+        return;
+    }
+    track.since_sample = 0.0;
+    let velocity = velocity.map(|v| v.linvel).unwrap_or_default();
+    track.sample(transform.translation(), velocity);
+}
+
+fn update_dots(
+    windows: Res<Windows>,
+    ui_scale: Res<UiScale>,
+    settings: Res<Settings>,
+    track: Res<TargetTrack>,
+    camera: Query<(&Camera, &GlobalTransform), With<PlayerCamera>>,
+    targets: Query<&GlobalTransform>,
+    mut dots: Query<(&TrackDot, &mut Style, &mut Visibility, &mut BackgroundColor)>,
+) {
+    let Ok((camera, transform)) = camera.get_single() else {
+        return;
+    };
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+    let position = track.target.and_then(|target| targets.get(target).ok());
+    let color = settings.accessibility.color(Indicator::PrimaryTarget);
+
+    for (dot, mut style, mut visibility, mut background) in dots.iter_mut() {
+        // Both the trail and the path fade out away from the target
+        let (point, alpha) = match (*dot, position) {
+            (TrackDot::History(index), Some(_)) => (
+                track.samples.get(index).copied(),
+                1.0 - index as f32 / HISTORY_DOTS as f32,
+            ),
+            (TrackDot::Prediction(index), Some(position)) => {
+                let time = (index + 1) as f32 * SAMPLE_INTERVAL;
+                let point = predict(
+                    position.translation(),
+                    track.velocity,
+                    track.acceleration,
+                    time,
+                );
+                (
+                    Some(point),
+                    0.8 - 0.6 * index as f32 / PREDICTION_DOTS as f32,
+                )
+            }
+            (_, None) => (None, 0.0),
+        };
+        let ui_pos =
+            point.and_then(|point| world_to_ui(camera, transform, window, &ui_scale, point));
+
+        let visible = ui_pos.is_some();
+        if visibility.is_visible != visible {
+            visibility.is_visible = visible;
+        }
+        if let Some(ui_pos) = ui_pos {
+            let pos = ui_pos - Vec2::splat(DOT_SIZE / 2.0);
+            style.position = UiRect {
+                left: Val::Px(pos.x),
+                top: Val::Px(pos.y),
+                ..default()
+            };
+            let mut color = color;
+            background.0 = *color.set_a(alpha);
+        }
+    }
+}
+
+pub(super) struct TargetTrackPlugin;
+impl Plugin for TargetTrackPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TargetTrack>()
+            .add_startup_system(setup_track)
+            .add_system(track_target)
+            .add_system(update_dots.after(track_target));
+    }
+}
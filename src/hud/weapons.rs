@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+
+use super::{fire_cycle_key, fire_cycle_level, text_bar, HudAnchor, HudWidget, PanelBackground};
+use crate::{
+    gun::{Ammo, Charge, FireCycle, Gun},
+    localization::Localization,
+    player::{HeavyWeapon, PrimaryWeapon, SecondaryWeapon},
+};
+
+/// Number of cells in the fire cycle bars
+const CYCLE_BAR_CELLS: usize = 10;
+
+#[derive(Component)]
+struct WeaponText;
+
+fn setup_weapons_hud(mut commands: Commands, assets: Res<AssetServer>) {
+    // Semi-transparent section above the flight instruments for the fire cycle of each weapon
+    let background = Color::rgba(0.7, 0.7, 0.7, 0.3);
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(360.0), Val::Px(80.0)),
+                align_items: AlignItems::FlexStart, // vertical alignment to the top
+                justify_content: JustifyContent::FlexStart, // horizontal alignment to the left
+                padding: UiRect::all(Val::Px(5.0)),
+                ..default()
+            },
+            background_color: background.into(),
+            ..default()
+        })
+        .insert(HudWidget::new(HudAnchor::BottomLeft))
+        .insert(PanelBackground(background))
+        .insert(Name::new("Weapon Status"))
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: assets.load("fonts/FiraMono-Medium.ttf"),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                ))
+                .insert(WeaponText);
+        });
+}
+
+fn update_weapon_status(
+    primary: Query<&Gun, With<PrimaryWeapon>>,
+    secondary: Query<(&Gun, Option<&Ammo>), With<SecondaryWeapon>>,
+    heavy: Query<(&Gun, Option<&Charge>), With<HeavyWeapon>>,
+    localization: Res<Localization>,
+    mut text: Query<&mut Text, With<WeaponText>>,
+) {
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+
+    let cycle = |key: &str, gun: &Gun, charge: Option<&Charge>| {
+        let bar = text_bar(fire_cycle_level(gun, charge), CYCLE_BAR_CELLS, '#');
+        let state = localization.get(fire_cycle_key(gun.cycle()));
+        localization.format(key, &[("bar", &bar), ("state", &state)])
+    };
+
+    let mut lines = Vec::new();
+    // Guns fire in turns, so show the one that holds the whole group back
+    let slowest = primary.iter().min_by(|a, b| {
+        let overheated = |gun: &Gun| gun.cycle() == FireCycle::Overheated;
+        overheated(b)
+            .cmp(&overheated(a))
+            .then(a.readiness().total_cmp(&b.readiness()))
+    });
+    if let Some(gun) = slowest {
+        lines.push(cycle("hud-weapon-primary", gun, None));
+    }
+    if let Some((gun, ammo)) = secondary.iter().next() {
+        let mut line = cycle("hud-weapon-secondary", gun, None);
+        if let Some(ammo) = ammo {
+            line.push_str(&format!(" {}/{}", ammo.rounds(), ammo.capacity()));
+        }
+        lines.push(line);
+    }
+    if let Some((gun, charge)) = heavy.iter().next() {
+        lines.push(cycle("hud-weapon-heavy", gun, charge));
+    }
+    text.sections[0].value = lines.join("\n");
+}
+
+pub(super) struct WeaponsHudPlugin;
+impl Plugin for WeaponsHudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_weapons_hud)
+            .add_system(update_weapon_status);
+    }
+}
@@ -0,0 +1,182 @@
+use bevy::prelude::*;
+use bevy_hanabi::*;
+
+use crate::projectile::DamageEvent;
+
+/// Number of effect instances of each kind, so several hits at the same time are all visible
+const EMITTERS_PER_EFFECT: usize = 8;
+
+/// Defines how the surface reacts on projectile hits. Entities without it are treated as `Metal`.
+#[derive(Component, Copy, Clone, PartialEq, Eq, Default, Reflect)]
+#[reflect(Component)]
+pub enum SurfaceMaterial {
+    #[default]
+    Metal,
+    /// Soft surface like balloon skin
+    Fabric,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum ImpactEffect {
+    Sparks,
+    Puff,
+    ShieldRipple,
+}
+
+impl From<SurfaceMaterial> for ImpactEffect {
+    fn from(material: SurfaceMaterial) -> Self {
+        match material {
+            SurfaceMaterial::Metal => ImpactEffect::Sparks,
+            SurfaceMaterial::Fabric => ImpactEffect::Puff,
+        }
+    }
+}
+
+#[derive(Component)]
+struct ImpactEmitter {
+    effect: ImpactEffect,
+    /// Time of the last use, the oldest emitter is reused first
+    last_used: f32,
+}
+
+fn setup_impacts(mut commands: Commands, mut effects: ResMut<Assets<EffectAsset>>) {
+    let mut gradient = Gradient::new();
+    gradient.add_key(0.0, Color::WHITE.into());
+    gradient.add_key(0.3, Color::YELLOW.into());
+    gradient.add_key(1.0, Vec4::new(1.0, 0.3, 0.0, 0.0));
+
+    let sparks = effects.add(
+        EffectAsset {
+            capacity: 256,
+            spawner: Spawner::once(32.0.into(), false),
+            ..default()
+        }
+        .init(PositionSphereModifier {
+            radius: 0.05,
+            speed: 8.0.into(),
+            dimension: ShapeDimension::Surface,
+            ..default()
+        })
+        .init(ParticleLifetimeModifier { lifetime: 0.4 })
+        .render(BillboardModifier)
+        .render(SizeOverLifetimeModifier {
+            gradient: Gradient::constant(Vec2::splat(0.05)),
+        })
+        .render(ColorOverLifetimeModifier { gradient }),
+    );
+
+    let mut gradient = Gradient::new();
+    gradient.add_key(0.0, Vec4::new(0.9, 0.9, 0.9, 0.6));
+    gradient.add_key(1.0, Vec4::new(0.7, 0.7, 0.7, 0.0));
+
+    let mut size_gradient = Gradient::new();
+    size_gradient.add_key(0.0, Vec2::splat(0.3));
+    size_gradient.add_key(1.0, Vec2::splat(0.8));
+
+    let puff = effects.add(
+        EffectAsset {
+            capacity: 128,
+            spawner: Spawner::once(16.0.into(), false),
+            ..default()
+        }
+        .init(PositionSphereModifier {
+            radius: 0.1,
+            speed: 1.0.into(),
+            dimension: ShapeDimension::Volume,
+            ..default()
+        })
+        .init(ParticleLifetimeModifier { lifetime: 0.8 })
+        .render(BillboardModifier)
+        .render(SizeOverLifetimeModifier {
+            gradient: size_gradient,
+        })
+        .render(ColorOverLifetimeModifier { gradient }),
+    );
+
+    let mut gradient = Gradient::new();
+    gradient.add_key(0.0, Vec4::new(0.4, 0.8, 1.0, 0.8));
+    gradient.add_key(1.0, Vec4::new(0.2, 0.4, 1.0, 0.0));
+
+    let shield_ripple = effects.add(
+        EffectAsset {
+            capacity: 512,
+            spawner: Spawner::once(64.0.into(), false),
+            ..default()
+        }
+        .init(PositionSphereModifier {
+            radius: 0.5,
+            speed: 3.0.into(),
+            dimension: ShapeDimension::Surface,
+            ..default()
+        })
+        .init(ParticleLifetimeModifier { lifetime: 0.3 })
+        .render(BillboardModifier)
+        .render(SizeOverLifetimeModifier {
+            gradient: Gradient::constant(Vec2::splat(0.1)),
+        })
+        .render(ColorOverLifetimeModifier { gradient }),
+    );
+
+    for (effect, handle) in [
+        (ImpactEffect::Sparks, sparks),
+        (ImpactEffect::Puff, puff),
+        (ImpactEffect::ShieldRipple, shield_ripple),
+    ] {
+        for _ in 0..EMITTERS_PER_EFFECT {
+            commands
+                .spawn(ParticleEffectBundle::new(handle.clone()))
+                .insert(ImpactEmitter {
+                    effect,
+                    last_used: 0.0,
+                })
+                .insert(Name::new("Impact Emitter"));
+        }
+    }
+}
+
+/// Plays an impact effect at every hit point, selected by the shield state or the surface material
+fn play_impacts(
+    time: Res<Time>,
+    mut ev_damage: EventReader<DamageEvent>,
+    surfaces: Query<&SurfaceMaterial>,
+    parent_query: Query<&Parent>,
+    mut emitters: Query<(&mut ImpactEmitter, &mut ParticleEffect, &mut Transform)>,
+) {
+    for ev in ev_damage.iter() {
+        let effect = if ev.absorbed > 0 {
+            ImpactEffect::ShieldRipple
+        } else {
+            // Colliders might be attached to children, so the material is looked up in ancestors too
+            std::iter::once(ev.target)
+                .chain(parent_query.iter_ancestors(ev.target))
+                .find_map(|e| surfaces.get(e).ok())
+                .copied()
+                .unwrap_or_default()
+                .into()
+        };
+
+        let emitter = emitters
+            .iter_mut()
+            .filter(|(emitter, _, _)| emitter.effect == effect)
+            .min_by(|(a, _, _), (b, _, _)| a.last_used.total_cmp(&b.last_used));
+        let Some((mut emitter, mut particles, mut transform)) = emitter else {
+            continue;
+        };
+
+        emitter.last_used = time.elapsed_seconds();
+        transform.translation = ev.position;
+        if let Some(spawner) = particles.maybe_spawner() {
+            spawner.reset();
+        }
+    }
+}
+
+/// Hit effects depending on the target's shield and `SurfaceMaterial`.
+pub struct ImpactPlugin;
+impl Plugin for ImpactPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_impacts)
+            .register_type::<SurfaceMaterial>()
+            .add_system(play_impacts);
+    }
+}
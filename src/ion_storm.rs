@@ -0,0 +1,187 @@
+//! Ion storms drifting through empty space, placed by the `Scenario`. Everything inside a storm
+//! is marked with `InIonStorm`: its sensors see only up to `SENSOR_RANGE`, shields don't recharge,
+//! and guns fire with the extra `AIM_ERROR`, the same for the player and the AI.
+
+use bevy::prelude::*;
+use bevy_hanabi::*;
+use serde::Deserialize;
+
+use crate::{
+    aiming::GunLayer, effect_culling::CullRadius, gun::Gun, player::Player, projectile::Shield,
+    scenario::Scenario,
+};
+
+/// Range of target selection and radars inside a storm, in meters
+pub const SENSOR_RANGE: f32 = 400.0;
+/// Half-angle of the extra spread cone of guns inside a storm, in radians
+pub const AIM_ERROR: f32 = 0.05;
+/// Seconds for the storm particles to fade in and out, so the field drifts along with the storm
+const PARTICLE_LIFETIME: f32 = 4.0;
+/// Particles spawned per second per square kilometer of the storm cross-section
+const PARTICLE_DENSITY: f32 = 5000.0;
+
+/// Spherical volume jamming sensors, shields and fire control of everything inside.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct IonStorm {
+    /// In meters
+    pub radius: f32,
+    /// Velocity of the storm, in m/s
+    pub drift: Vec3,
+}
+
+impl IonStorm {
+    /// Whether the `point` is inside the storm centered at the `center`
+    pub fn contains(&self, center: Vec3, point: Vec3) -> bool {
+        point.distance_squared(center) <= self.radius * self.radius
+    }
+}
+
+/// Ship, turret or gun inside an `IonStorm`, kept up to date every frame.
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct InIonStorm;
+
+/// Ion storm placed by the `Scenario`.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct IonStormConfig {
+    /// World position in meters
+    pub position: [f32; 3],
+    /// In meters
+    pub radius: f32,
+    /// Velocity in m/s, the storm stays in place without one
+    #[serde(default)]
+    pub drift: [f32; 3],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IonStorm, IonStormConfig};
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_contains() {
+        let storm = IonStorm {
+            radius: 100.0,
+            drift: Vec3::X,
+        };
+        let center = Vec3::new(0.0, 0.0, -500.0);
+        assert!(storm.contains(center, center));
+        assert!(storm.contains(center, center + Vec3::Y * 100.0));
+        assert!(!storm.contains(center, Vec3::ZERO));
+    }
+
+    #[test]
+    fn test_parse_storm() {
+        let storm: IonStormConfig =
+            serde_json::from_str(r#"{"position": [0, 0, -500], "radius": 300}"#).unwrap();
+        assert_eq!(storm.radius, 300.0);
+        // stays in place by default
+        assert_eq!(storm.drift, [0.0; 3]);
+    }
+}
+
+/// Field of flickering charged particles filling the storm
+fn storm_effect(radius: f32) -> EffectAsset {
+    let mut gradient = Gradient::new();
+    gradient.add_key(0.0, Vec4::new(0.4, 0.5, 1.0, 0.0));
+    gradient.add_key(0.3, Vec4::new(0.5, 0.6, 1.0, 0.3));
+    gradient.add_key(0.7, Vec4::new(0.7, 0.5, 1.0, 0.3));
+    gradient.add_key(1.0, Vec4::new(0.7, 0.5, 1.0, 0.0));
+
+    let area = std::f32::consts::PI * (radius / 1000.0).powi(2);
+    let rate = (PARTICLE_DENSITY * area).max(50.0);
+    EffectAsset {
+        capacity: (rate * PARTICLE_LIFETIME).ceil() as u32,
+        spawner: Spawner::rate(rate.into()),
+        ..default()
+    }
+    .init(PositionSphereModifier {
+        radius,
+        speed: 2.0.into(),
+        dimension: ShapeDimension::Volume,
+        ..default()
+    })
+    .init(ParticleLifetimeModifier {
+        lifetime: PARTICLE_LIFETIME,
+    })
+    .render(BillboardModifier)
+    .render(SizeOverLifetimeModifier {
+        gradient: Gradient::constant(Vec2::splat(radius / 50.0)),
+    })
+    .render(ColorOverLifetimeModifier { gradient })
+}
+
+fn spawn_storms(
+    mut commands: Commands,
+    scenario: Option<Res<Scenario>>,
+    mut effects: ResMut<Assets<EffectAsset>>,
+) {
+    let Some(scenario) = scenario else {
+        return;
+    };
+    for config in scenario.storms.iter() {
+        let effect = effects.add(storm_effect(config.radius));
+        commands
+            .spawn(SpatialBundle::from_transform(Transform::from_translation(
+                Vec3::from(config.position),
+            )))
+            .insert(IonStorm {
+                radius: config.radius,
+                drift: Vec3::from(config.drift),
+            })
+            .insert(Name::new("Ion Storm"))
+            .with_children(|parent| {
+                parent
+                    .spawn(ParticleEffectBundle::new(effect))
+                    .insert(CullRadius(config.radius));
+            });
+    }
+}
+
+fn drift_storms(time: Res<Time>, mut storms: Query<(&IonStorm, &mut Transform)>) {
+    for (storm, mut transform) in storms.iter_mut() {
+        if storm.drift != Vec3::ZERO {
+            transform.translation += storm.drift * time.delta_seconds();
+        }
+    }
+}
+
+/// Marks everything with sensors, shields or guns inside storms with `InIonStorm`
+fn track_exposure(
+    mut commands: Commands,
+    storms: Query<(&IonStorm, &GlobalTransform)>,
+    exposed: Query<
+        (Entity, &GlobalTransform, Option<&InIonStorm>),
+        Or<(With<GunLayer>, With<Gun>, With<Shield>, With<Player>)>,
+    >,
+) {
+    for (entity, transform, marked) in exposed.iter() {
+        let position = transform.translation();
+        let inside = storms
+            .iter()
+            .any(|(storm, center)| storm.contains(center.translation(), position));
+        match (inside, marked.is_some()) {
+            (true, false) => {
+                commands.entity(entity).insert(InIonStorm);
+            }
+            (false, true) => {
+                commands.entity(entity).remove::<InIonStorm>();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `IonStorm`s from the `Scenario`, drifting and marking everything inside with `InIonStorm`.
+pub struct IonStormPlugin;
+impl Plugin for IonStormPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<IonStorm>()
+            .register_type::<InIonStorm>()
+            .add_startup_system(spawn_storms)
+            .add_system(drift_storms)
+            // Before the gameplay systems despawn anything, so markers never land on dead entities
+            .add_system_to_stage(CoreStage::PreUpdate, track_exposure);
+    }
+}
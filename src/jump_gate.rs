@@ -0,0 +1,202 @@
+//! Jump gates bringing reinforcements onto the battlefield. A gate charges up with an imploding
+//! portal and a growing light, flashes once the drone jumps in, and lets it cruise in at high
+//! speed. Every wave of arrivals is announced by a `JumpInEvent` for the HUD and audio cues.
+
+use bevy::prelude::*;
+use bevy_hanabi::*;
+
+use crate::{
+    components::{Fraction, Lifetime},
+    drone::{Drone, SpawnDroneEvent},
+    light_budget::DynamicLight,
+};
+
+/// Seconds from the gate opening to the drone jumping in
+const CHARGE_TIME: f32 = 1.5;
+/// Seconds the gate stays open, including the fading flash
+const GATE_LIFETIME: f32 = 3.0;
+/// Drones jump in at this speed, in m/s
+const ENTRY_SPEED: f32 = 250.0;
+/// Light intensity at the peak of the flash
+const FLASH_INTENSITY: f32 = 2_000_000.0;
+/// Rate of the exponential fade after the flash, per second
+const FLASH_DECAY: f32 = 4.0;
+/// Portal particles start this far from the gate center, in meters
+const PORTAL_RADIUS: f32 = 30.0;
+/// Gates of the same faction opened within this time are the same wave, in seconds
+const WAVE_GAP: f32 = 5.0;
+
+/// Emit this event to bring the `drone` in through a gate at the `transform`, facing the way
+/// it arrives.
+pub struct OpenJumpGateEvent {
+    pub drone: Drone,
+    pub fraction: Fraction,
+    pub transform: Transform,
+}
+
+/// Emitted when a wave of the `fraction` starts jumping in, at the `position` of its first gate.
+pub struct JumpInEvent {
+    pub fraction: Fraction,
+    pub position: Vec3,
+}
+
+#[derive(Component)]
+struct JumpGate {
+    /// Drone to bring in once the gate is charged, `None` after that
+    drone: Option<Drone>,
+    fraction: Fraction,
+    /// Seconds since the gate opened
+    age: f32,
+}
+
+#[derive(Resource)]
+struct PortalEffect(Handle<EffectAsset>);
+
+/// Relative brightness of the gate light `age` seconds after it opened: grows while the gate
+/// charges, peaks when the drone jumps in and fades out quickly after that.
+fn flash(age: f32) -> f32 {
+    if age < CHARGE_TIME {
+        0.3 * (age / CHARGE_TIME).powi(2)
+    } else {
+        (-(age - CHARGE_TIME) * FLASH_DECAY).exp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{flash, CHARGE_TIME, GATE_LIFETIME};
+
+    #[test]
+    fn test_flash() {
+        assert_eq!(flash(0.0), 0.0);
+        assert!(flash(CHARGE_TIME / 2.0) < flash(CHARGE_TIME * 0.9));
+        assert_eq!(flash(CHARGE_TIME), 1.0);
+        // nothing brighter than the flash itself
+        assert!(flash(CHARGE_TIME * 0.99) < 0.5);
+        assert!(flash(CHARGE_TIME + 0.5) < flash(CHARGE_TIME));
+        // the light is almost gone before the gate is
+        assert!(flash(GATE_LIFETIME) < 0.01);
+    }
+}
+
+fn setup_jump_gates(mut commands: Commands, mut effects: ResMut<Assets<EffectAsset>>) {
+    let mut gradient = Gradient::new();
+    gradient.add_key(0.0, Vec4::new(0.3, 0.5, 1.0, 0.0));
+    gradient.add_key(0.5, Vec4::new(0.5, 0.7, 1.0, 0.8));
+    gradient.add_key(1.0, Vec4::new(1.0, 1.0, 1.0, 1.0));
+
+    let mut size_gradient = Gradient::new();
+    size_gradient.add_key(0.0, Vec2::splat(0.5));
+    size_gradient.add_key(1.0, Vec2::splat(1.5));
+
+    // Particles fall from a sphere into the gate center right when it's charged
+    let portal = effects.add(
+        EffectAsset {
+            capacity: 512,
+            spawner: Spawner::once(256.0.into(), true),
+            ..default()
+        }
+        .init(PositionSphereModifier {
+            radius: PORTAL_RADIUS,
+            speed: (-PORTAL_RADIUS / CHARGE_TIME).into(),
+            dimension: ShapeDimension::Surface,
+            ..default()
+        })
+        .init(ParticleLifetimeModifier {
+            lifetime: CHARGE_TIME,
+        })
+        .render(BillboardModifier)
+        .render(SizeOverLifetimeModifier {
+            gradient: size_gradient,
+        })
+        .render(ColorOverLifetimeModifier { gradient }),
+    );
+    commands.insert_resource(PortalEffect(portal));
+}
+
+fn open_gates(
+    mut commands: Commands,
+    time: Res<Time>,
+    portal: Res<PortalEffect>,
+    mut ev_open_gate: EventReader<OpenJumpGateEvent>,
+    mut ev_jump_in: EventWriter<JumpInEvent>,
+    // Time of the last gate opened by each faction
+    mut last_opened: Local<Vec<(Fraction, f32)>>,
+) {
+    let now = time.elapsed_seconds();
+    for ev in ev_open_gate.iter() {
+        commands
+            .spawn(PointLightBundle {
+                point_light: PointLight {
+                    intensity: 0.0,
+                    range: 500.0,
+                    color: Color::rgb(0.5, 0.7, 1.0),
+                    ..default()
+                },
+                transform: ev.transform,
+                ..default()
+            })
+            .insert(DynamicLight)
+            .insert(JumpGate {
+                drone: Some(ev.drone),
+                fraction: ev.fraction,
+                age: 0.0,
+            })
+            .insert(Lifetime(GATE_LIFETIME))
+            .insert(Name::new("Jump Gate"))
+            .with_children(|parent| {
+                parent.spawn(ParticleEffectBundle::new(portal.0.clone()));
+            });
+
+        let last = last_opened.iter_mut().find(|(f, _)| *f == ev.fraction);
+        let new_wave = last.as_ref().map_or(true, |(_, at)| now - at > WAVE_GAP);
+        match last {
+            Some((_, at)) => *at = now,
+            None => last_opened.push((ev.fraction, now)),
+        }
+        if new_wave {
+            ev_jump_in.send(JumpInEvent {
+                fraction: ev.fraction,
+                position: ev.transform.translation,
+            });
+        }
+    }
+}
+
+fn charge_gates(
+    time: Res<Time>,
+    mut gates: Query<(&mut JumpGate, &mut PointLight, &Transform)>,
+    mut ev_spawn_drone: EventWriter<SpawnDroneEvent>,
+) {
+    for (mut gate, mut light, transform) in gates.iter_mut() {
+        gate.age += time.delta_seconds();
+        light.intensity = FLASH_INTENSITY * flash(gate.age);
+
+        if gate.age >= CHARGE_TIME {
+            if let Some(drone) = gate.drone.take() {
+                ev_spawn_drone.send(SpawnDroneEvent {
+                    drone,
+                    fraction: gate.fraction,
+                    transform: *transform,
+                    prefab: None,
+                    entry_speed: ENTRY_SPEED,
+                    modifiers: default(),
+                    hardpoints: Vec::new(),
+                });
+            }
+        }
+    }
+}
+
+/// Drones arriving through jump gates, opened with `OpenJumpGateEvent`s.
+/// Particle effects are expected to be set up by the `ProjectilePlugin`.
+pub struct JumpGatePlugin;
+impl Plugin for JumpGatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<OpenJumpGateEvent>()
+            .add_event::<JumpInEvent>()
+            .add_startup_system(setup_jump_gates)
+            .add_system(open_gates)
+            .add_system(charge_gates.after(open_gates));
+    }
+}
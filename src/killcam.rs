@@ -0,0 +1,408 @@
+//! Kill-cam: once the player is destroyed, the world freezes and the last seconds before the death
+//! are replayed in slow motion, while the camera orbits the wreck. Then the player may respawn.
+//!
+//! Only root rigid bodies around the player are recorded, their children just move along.
+//! Entities despawned since, like projectiles that have hit, aren't replayed.
+
+use bevy::{prelude::*, transform::TransformSystem};
+use bevy_rapier3d::prelude::*;
+use std::collections::VecDeque;
+
+use crate::{
+    components::HitPoints,
+    localization::Localization,
+    player::{Cruise, Player, PlayerCamera},
+    projectile::DamageEvent,
+    scenario,
+};
+
+/// Seconds of the history kept for the replay
+const RECORD_TIME: f32 = 3.0;
+/// Entities farther from the player aren't recorded, in meters
+const RECORD_RADIUS: f32 = 300.0;
+/// Replay speed relative to the real time
+const REPLAY_SPEED: f32 = 0.25;
+/// Distance from the camera to the wreck, in meters
+const ORBIT_DISTANCE: f32 = 30.0;
+const ORBIT_HEIGHT: f32 = 8.0;
+/// In rad/s
+const ORBIT_SPEED: f32 = 0.4;
+
+/// Transforms of the recorded entities at the given time
+#[derive(Default)]
+struct Frame {
+    time: f32,
+    transforms: Vec<(Entity, Transform)>,
+}
+
+/// Ring buffer of the recent frames, frames older than `RECORD_TIME` are reused for the new ones.
+#[derive(Resource, Default)]
+struct History {
+    frames: VecDeque<Frame>,
+}
+
+impl History {
+    fn push(&mut self, time: f32, transforms: impl Iterator<Item = (Entity, Transform)>) {
+        let mut frame = match self.frames.front() {
+            Some(oldest) if time - oldest.time > RECORD_TIME => self.frames.pop_front().unwrap(),
+            _ => Frame::default(),
+        };
+        frame.time = time;
+        frame.transforms.clear();
+        frame.transforms.extend(transforms);
+        self.frames.push_back(frame);
+
+        while matches!(self.frames.front(), Some(oldest) if time - oldest.time > RECORD_TIME) {
+            self.frames.pop_front();
+        }
+    }
+
+    /// Seconds between the oldest and the most recent frames
+    fn duration(&self) -> f32 {
+        match (self.frames.front(), self.frames.back()) {
+            (Some(first), Some(last)) => last.time - first.time,
+            _ => 0.0,
+        }
+    }
+
+    /// Frames around `time` since the oldest frame and the weight of the later one
+    fn sample(&self, time: f32) -> Option<(&Frame, &Frame, f32)> {
+        let time = self.frames.front()?.time + time;
+        let next = self
+            .frames
+            .iter()
+            .position(|frame| frame.time >= time)
+            .unwrap_or(self.frames.len() - 1);
+        let (prev, next) = (&self.frames[next.saturating_sub(1)], &self.frames[next]);
+        let span = next.time - prev.time;
+        let weight = if span > 0.0 {
+            ((time - prev.time) / span).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        Some((prev, next, weight))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{History, RECORD_TIME};
+    use bevy::prelude::{Entity, Transform};
+
+    fn frame(x: f32) -> impl Iterator<Item = (Entity, Transform)> {
+        std::iter::once((Entity::from_raw(0), Transform::from_xyz(x, 0.0, 0.0)))
+    }
+
+    #[test]
+    fn test_history() {
+        let mut history = History::default();
+        assert!(history.sample(0.0).is_none());
+
+        for i in 0..=40 {
+            let time = i as f32 * 0.1;
+            history.push(time, frame(time));
+        }
+        // old frames are dropped
+        assert!((history.duration() - RECORD_TIME).abs() < 1e-4);
+        assert!((history.frames[0].time - 1.0).abs() < 1e-4);
+
+        let (prev, next, weight) = history.sample(0.25).unwrap();
+        assert!((prev.time - 1.2).abs() < 1e-4);
+        assert!((next.time - 1.3).abs() < 1e-4);
+        assert!((weight - 0.5).abs() < 1e-3);
+
+        // the last frame is held after the end
+        let (prev, next, weight) = history.sample(10.0).unwrap();
+        assert!((prev.time - 3.9).abs() < 1e-4);
+        assert!((next.time - 4.0).abs() < 1e-4);
+        assert_eq!(weight, 1.0);
+    }
+}
+
+/// Kill-cam of the player's death, exists until the respawn
+#[derive(Resource)]
+struct Replay {
+    /// Seconds since the oldest frame in the replay time
+    time: f32,
+    /// Name of whoever destroyed the player
+    killer: Option<String>,
+    /// The player's position at the death, the camera orbits it
+    wreck: Vec3,
+    /// Camera angle around the wreck, in radians
+    orbit: f32,
+    /// The replay is over and the world is back to the moment of death
+    finished: bool,
+}
+
+/// Where the player spawned the first time, to respawn there
+#[derive(Resource, Default)]
+struct SpawnPoint(Transform);
+
+fn remember_spawn(
+    mut commands: Commands,
+    camera: Query<&PlayerCamera, Added<PlayerCamera>>,
+    ships: Query<&Transform>,
+) {
+    // The camera rig is spawned once with the player, while the ship may change later
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    if let Ok(transform) = ships.get(camera.target) {
+        commands.insert_resource(SpawnPoint(*transform));
+    }
+}
+
+fn record(
+    time: Res<Time>,
+    mut history: ResMut<History>,
+    player: Query<(&Transform, &HitPoints), With<Player>>,
+    bodies: Query<(Entity, &Transform), (With<RigidBody>, Without<Parent>, Without<Player>)>,
+) {
+    let Ok((player, hp)) = player.get_single() else {
+        return;
+    };
+    if hp.dead() || time.delta_seconds() == 0.0 {
+        return;
+    }
+    let center = player.translation;
+    let nearby = bodies
+        .iter()
+        .filter(|(_, t)| t.translation.distance_squared(center) < RECORD_RADIUS * RECORD_RADIUS)
+        .map(|(entity, t)| (entity, *t));
+    history.push(time.elapsed_seconds(), nearby);
+}
+
+/// Freezes the world once the player is destroyed and starts the replay
+fn start_kill_cam(
+    mut commands: Commands,
+    mut time: ResMut<Time>,
+    mut ev_damage: EventReader<DamageEvent>,
+    player: Query<(Entity, &Transform), With<Player>>,
+    names: Query<&Name>,
+) {
+    let Ok((player, transform)) = player.get_single() else {
+        return;
+    };
+    let Some(ev) = ev_damage
+        .iter()
+        .find(|ev| ev.target == player && ev.destroyed)
+    else {
+        return;
+    };
+
+    info!("Player is destroyed by {:?}", ev.attacker);
+    time.pause();
+    commands.insert_resource(Replay {
+        time: 0.0,
+        killer: ev
+            .attacker
+            .and_then(|e| names.get(e).ok())
+            .map(|name| name.to_string()),
+        wreck: transform.translation,
+        orbit: 0.0,
+        finished: false,
+    });
+}
+
+/// Moves recorded entities through the history and the camera around the wreck.
+/// Runs after the physics, so it doesn't override the replayed transforms.
+fn replay(
+    time: Res<Time>,
+    history: Res<History>,
+    replay: Option<ResMut<Replay>>,
+    mut transforms: Query<&mut Transform, (Without<Player>, Without<PlayerCamera>)>,
+    mut camera: Query<&mut Transform, With<PlayerCamera>>,
+) {
+    let Some(mut replay) = replay else {
+        return;
+    };
+    // the world is paused, so the real time drives the replay
+    let dt = time.raw_delta_seconds();
+
+    replay.orbit += ORBIT_SPEED * dt;
+    if let Ok(mut camera) = camera.get_single_mut() {
+        let offset =
+            Quat::from_rotation_y(replay.orbit) * Vec3::new(0.0, ORBIT_HEIGHT, ORBIT_DISTANCE);
+        *camera =
+            Transform::from_translation(replay.wreck + offset).looking_at(replay.wreck, Vec3::Y);
+    }
+
+    if replay.finished {
+        return;
+    }
+    replay.time += REPLAY_SPEED * dt;
+    // the last frame is the moment of death, so the world ends up where it was frozen
+    replay.finished = replay.time >= history.duration();
+    let Some((prev, next, weight)) = history.sample(replay.time) else {
+        return;
+    };
+    for (index, (entity, from)) in prev.transforms.iter().enumerate() {
+        // entities are usually recorded in the same order
+        let to = match next.transforms.get(index) {
+            Some((e, to)) if e == entity => to,
+            _ => match next.transforms.iter().find(|(e, _)| e == entity) {
+                Some((_, to)) => to,
+                None => from,
+            },
+        };
+        if let Ok(mut transform) = transforms.get_mut(*entity) {
+            transform.translation = from.translation.lerp(to.translation, weight);
+            transform.rotation = from.rotation.slerp(to.rotation, weight);
+        }
+    }
+}
+
+/// Enter skips the replay and then respawns the player
+fn respawn(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut time: ResMut<Time>,
+    mut history: ResMut<History>,
+    mut replay: Option<ResMut<Replay>>,
+    spawn: Res<SpawnPoint>,
+    mut player: Query<(&mut Transform, &mut Velocity, &mut HitPoints, &mut Cruise), With<Player>>,
+) {
+    let Some(replay) = replay.as_mut() else {
+        return;
+    };
+    if !keys.just_pressed(KeyCode::Return) {
+        return;
+    }
+    if !replay.finished {
+        replay.time = history.duration();
+        return;
+    }
+
+    if let Ok((mut transform, mut velocity, mut hp, mut cruise)) = player.get_single_mut() {
+        *transform = spawn.0;
+        *velocity = Velocity::default();
+        cruise.0 = None;
+        hp.restore();
+    }
+    history.frames.clear();
+    commands.remove_resource::<Replay>();
+    time.unpause();
+}
+
+#[derive(Component)]
+struct KillCamScreen;
+
+#[derive(Component)]
+struct KillCamText;
+
+fn setup_screen(mut commands: Commands, assets: Res<AssetServer>) {
+    // Hidden banner at the top of the screen, shown while the player is dead
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Percent(0.0),
+                    right: Val::Percent(0.0),
+                    top: Val::Percent(10.0),
+                    ..default()
+                },
+                justify_content: JustifyContent::Center,
+                display: Display::None,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(KillCamScreen)
+        .insert(Name::new("Kill-cam Screen"))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(10.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn(
+                            TextBundle::from_section(
+                                "",
+                                TextStyle {
+                                    font: assets.load("fonts/FiraMono-Medium.ttf"),
+                                    font_size: 24.0,
+                                    color: Color::WHITE,
+                                },
+                            )
+                            .with_text_alignment(TextAlignment::CENTER),
+                        )
+                        .insert(KillCamText);
+                });
+        });
+}
+
+fn update_screen(
+    replay: Option<Res<Replay>>,
+    localization: Res<Localization>,
+    mut screen: Query<&mut Style, With<KillCamScreen>>,
+    mut text: Query<&mut Text, With<KillCamText>>,
+) {
+    let Ok(mut style) = screen.get_single_mut() else {
+        return;
+    };
+    let Some(replay) = replay else {
+        if style.display != Display::None {
+            style.display = Display::None;
+        }
+        return;
+    };
+    if style.display != Display::Flex {
+        style.display = Display::Flex;
+    }
+
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+    let mut lines = vec![localization.get("killcam-destroyed").to_owned()];
+    if let Some(killer) = &replay.killer {
+        lines.push(localization.format("killcam-killer", &[("killer", killer)]));
+    }
+    lines.push(
+        localization
+            .get(if replay.finished {
+                "killcam-respawn"
+            } else {
+                "killcam-skip"
+            })
+            .to_owned(),
+    );
+    text.sections[0].value = lines.join("\n");
+}
+
+/// Slow motion replay of the player's death, followed by the respawn screen.
+/// Expects the `PlayerPlugin`.
+pub struct KillCamPlugin;
+impl Plugin for KillCamPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<History>()
+            .init_resource::<SpawnPoint>()
+            .add_startup_system(setup_screen)
+            // Enter restarts the game from the scenario picker instead
+            .add_system(respawn.with_run_criteria(scenario::picker_closed))
+            .add_system(update_screen.after(respawn))
+            .add_system_to_stage(CoreStage::PostUpdate, remember_spawn)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                record.before(TransformSystem::TransformPropagate),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                start_kill_cam
+                    .after(record)
+                    .before(TransformSystem::TransformPropagate),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                replay
+                    .after(start_kill_cam)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
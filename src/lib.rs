@@ -0,0 +1,140 @@
+//! Space combat simulation built with Bevy and Rapier.
+//!
+//! Every feature comes as a plugin, so binaries pick the ones they need: the game adds all of
+//! them on top of `DefaultPlugins`, while a headless server or benchmark can skip rendering,
+//! HUD and audio. Plugins and the types used to interact with them are re-exported by the
+//! [`prelude`].
+//!
+//! Gameplay plugins expect `RapierPhysicsPlugin` to be added by the app, with the gravity of the
+//! battlefield, zero in open space. Only `Ballistic` projectiles fall under it.
+
+pub mod activity;
+pub mod aiming;
+pub mod audio_cues;
+pub mod boss;
+pub mod bubble_shield;
+pub mod callsign;
+pub mod collider_setup;
+pub mod collision_warning;
+pub mod combat_log;
+pub mod components;
+#[cfg(feature = "devtools")]
+pub mod devtools;
+pub mod display;
+pub mod drone;
+pub mod economy;
+pub mod effect_culling;
+pub mod escort;
+pub mod evasion;
+pub mod gimbal;
+pub mod gun;
+pub mod hazard;
+pub mod hud;
+pub mod impact;
+pub mod ion_storm;
+pub mod jump_gate;
+pub mod killcam;
+pub mod light_budget;
+pub mod localization;
+pub mod model_animation;
+pub mod modifier;
+pub mod music;
+pub mod navigation;
+pub mod objective;
+pub mod observer;
+pub mod planet;
+pub mod player;
+pub mod post_process;
+pub mod prefab;
+pub mod profile;
+pub mod projectile;
+pub mod reinforcements;
+pub mod route;
+pub mod salvage;
+pub mod scenario;
+pub mod scene_setup;
+pub mod settings;
+pub mod shield_effect;
+pub mod ship_switch;
+pub mod skirmish;
+pub mod skybox;
+pub mod spring;
+pub mod target_range;
+pub mod telemetry;
+#[cfg(test)]
+mod testing;
+pub mod thruster;
+pub mod trail;
+pub mod trigger;
+pub mod turret;
+pub mod veterancy;
+pub mod weapon;
+
+/// Plugins with the components, resources and events to drive them.
+pub mod prelude {
+    #[cfg(feature = "devtools")]
+    pub use crate::devtools::DevToolsPlugin;
+    pub use crate::{
+        activity::{ActivityPlugin, ActivitySettings, Dormant},
+        aiming::{AimingPlugin, AimingSettings, FireControl, GunLayer, LeadQuality},
+        audio_cues::{AudioCue, AudioCueEvent, AudioCuesPlugin},
+        boss::{Boss, BossConfig, BossPhase, BossPlugin, ShieldGenerator, SpinalBeam},
+        bubble_shield::{BubbleShieldPlugin, ShieldProjector},
+        callsign::Callsigns,
+        collider_setup::ColliderSetupPlugin,
+        collision_warning::{CollisionWarning, CollisionWarningPlugin},
+        combat_log::{CombatLog, CombatLogPlugin, LogEntry, LogEvent},
+        components::{
+            ComponentsPlugin, Damage, Fraction, HitPoints, Lifetime, Pooled, Respawnable,
+        },
+        display::{DisplayMode, DisplayPlugin, DisplaySettings, ShadowQuality},
+        drone::{Drone, DronePlugin, SpawnDroneEvent},
+        economy::{EconomyPlugin, Inventory, Item, PurchaseEvent},
+        effect_culling::EffectCullingPlugin,
+        escort::{EscortConfig, EscortMission, EscortPlugin, MissionEndedEvent},
+        evasion::{Evasion, EvasionPlugin},
+        gimbal::{Gimbal, GimbalPlugin},
+        gun::{Ammo, GunPlugin, ShotFiredEvent},
+        hazard::{GravityWell, HazardPlugin, Reactor},
+        hud::{HighlightWidgetEvent, HudPlugin, RadioMessage, RadioMessageEvent},
+        impact::{ImpactPlugin, SurfaceMaterial},
+        ion_storm::{InIonStorm, IonStorm, IonStormPlugin},
+        jump_gate::{JumpGatePlugin, JumpInEvent, OpenJumpGateEvent},
+        killcam::KillCamPlugin,
+        light_budget::LightBudgetPlugin,
+        localization::LocalizationPlugin,
+        model_animation::{AnimationStates, ModelAnimationPlugin, PlayAnimation},
+        modifier::{Modifier, ModifierPlugin, Modifiers, Op, Stat},
+        music::MusicPlugin,
+        navigation::{NavPath, NavigationPlugin},
+        objective::{ObjectivePlugin, ObjectiveWonEvent, Objectives, ObjectivesConfig},
+        observer::ObserverPlugin,
+        planet::{PlanetConfig, PlanetMaterial, PlanetPlugin},
+        player::{Player, PlayerAction, PlayerActionEvent, PlayerCamera, PlayerPlugin},
+        post_process::{PostEffects, PostProcessPlugin},
+        prefab::{PrefabPlugin, ShipBundle, ShipPrefab, ShipRig},
+        profile::ProfilePlugin,
+        projectile::{
+            Armor, Ballistic, CriticalZone, DamageEvent, DamageSystems, ExplosionEvent,
+            ProjectilePlugin, Shield,
+        },
+        reinforcements::{BattleEndedEvent, Reinforcements, ReinforcementsPlugin},
+        route::{Route, RoutePlugin},
+        salvage::{Salvage, SalvageCollectedEvent, SalvagePlugin, WreckField},
+        scenario::{Scenario, ScenarioPlugin},
+        scene_setup::{GltfExtrasApp, SceneSetupPlugin, SetupRequired},
+        shield_effect::{ShieldEffect, ShieldEffectPlugin, ShieldMaterial},
+        ship_switch::{Controllable, ShipSwitchPlugin, SwitchShipEvent},
+        skirmish::{SkirmishConfig, SkirmishPlugin},
+        skybox::{Distant, SkyboxPlugin},
+        spring::{Spring, SpringPlugin},
+        target_range::{ShootingTarget, TargetRange, TargetRangePlugin},
+        telemetry::TelemetryPlugin,
+        thruster::{FlightControl, Thruster, ThrusterPlugin},
+        trail::TrailPlugin,
+        trigger::{ScenarioMessageEvent, TriggerActionEvent, TriggerConfig, TriggerPlugin},
+        turret::{Deployment, SpawnTurretEvent, TurretKind, TurretPlugin},
+        veterancy::{Veterancy, VeterancyPlugin},
+        weapon::{FlakCannon, MachineGun, MissileBattery, Railgun, RocketLauncher},
+    };
+}
@@ -0,0 +1,140 @@
+use bevy::{
+    pbr::PointLightShadowMap,
+    prelude::*,
+    render::{
+        primitives::{Frustum, Sphere},
+        view::VisibilitySystems,
+    },
+    transform::TransformSystem,
+};
+
+use crate::{display::SecondaryCamera, settings::Settings};
+
+/// Limits the number of simultaneously rendered dynamic lights, like the ones carried by rockets.
+#[derive(Resource, Clone, Debug)]
+pub struct LightBudget {
+    pub max_lights: usize,
+}
+
+impl Default for LightBudget {
+    fn default() -> Self {
+        Self { max_lights: 8 }
+    }
+}
+
+/// Marks a short-living light managed by the `LightBudget`.
+/// Lights beyond the budget, out of the view or farther than `DisplaySettings::light_distance`
+/// are hidden, so their sources are rendered only with emissive materials.
+#[derive(Component)]
+pub struct DynamicLight;
+
+/// Enables the closest lights that affect the visible area within the distance limit
+fn apply_light_budget(
+    budget: Res<LightBudget>,
+    settings: Res<Settings>,
+    // Either the player or the observer camera
+    camera: Query<(&GlobalTransform, &Frustum), (With<Camera3d>, Without<SecondaryCamera>)>,
+    mut lights: Query<(Entity, &GlobalTransform, &PointLight, &mut Visibility), With<DynamicLight>>,
+    mut candidates: Local<Vec<(Entity, bool, f32)>>,
+) {
+    let Ok((camera_transform, frustum)) = camera.get_single() else {
+        return;
+    };
+
+    let max_distance_squared = settings.display.light_distance.powi(2);
+    candidates.clear();
+    candidates.extend(lights.iter().map(|(entity, transform, light, _)| {
+        let sphere = Sphere {
+            center: transform.translation().into(),
+            radius: light.range,
+        };
+        let distance = transform
+            .translation()
+            .distance_squared(camera_transform.translation());
+        let visible = distance <= max_distance_squared && frustum.intersects_sphere(&sphere, false);
+        (entity, visible, distance)
+    }));
+    // Visible lights go first, then sorted by distance
+    candidates.sort_by(|a, b| b.1.cmp(&a.1).then(a.2.total_cmp(&b.2)));
+
+    for (index, &(entity, visible, _)) in candidates.iter().enumerate() {
+        let enabled = visible && index < budget.max_lights;
+        if let Ok((_, _, _, mut visibility)) = lights.get_mut(entity) {
+            if visibility.is_visible != enabled {
+                visibility.is_visible = enabled;
+            }
+        }
+    }
+}
+
+/// Lets the closest visible point lights cast shadows, as many as the shadow quality allows.
+/// Dynamic lights are considered only if the quality allows their shadows.
+fn apply_shadow_budget(
+    settings: Res<Settings>,
+    camera: Query<&GlobalTransform, (With<Camera3d>, Without<SecondaryCamera>)>,
+    mut lights: Query<(
+        Entity,
+        &GlobalTransform,
+        &mut PointLight,
+        &Visibility,
+        Option<&DynamicLight>,
+    )>,
+    mut candidates: Local<Vec<(Entity, f32)>>,
+) {
+    let Ok(camera_transform) = camera.get_single() else {
+        return;
+    };
+    let quality = settings.display.shadows;
+
+    candidates.clear();
+    candidates.extend(
+        lights
+            .iter()
+            .filter(|(_, _, _, visibility, dynamic)| {
+                visibility.is_visible && (dynamic.is_none() || quality.dynamic_shadows())
+            })
+            .map(|(entity, transform, _, _, _)| {
+                let distance = transform
+                    .translation()
+                    .distance_squared(camera_transform.translation());
+                (entity, distance)
+            }),
+    );
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+    candidates.truncate(quality.max_shadow_lights());
+
+    for (entity, _, mut light, _, _) in lights.iter_mut() {
+        let shadows = candidates.iter().any(|&(e, _)| e == entity);
+        if light.shadows_enabled != shadows {
+            light.shadows_enabled = shadows;
+        }
+    }
+}
+
+fn apply_shadow_map(settings: Res<Settings>, mut shadow_map: ResMut<PointLightShadowMap>) {
+    let size = settings.display.shadows.shadow_map_size();
+    if settings.is_changed() && shadow_map.size != size {
+        shadow_map.size = size;
+    }
+}
+
+/// Keeps the number of visible `DynamicLight`s within the `LightBudget` resource and the number
+/// of shadow-casting point lights within `DisplaySettings::shadows`.
+pub struct LightBudgetPlugin;
+impl Plugin for LightBudgetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LightBudget>()
+            .init_resource::<Settings>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                apply_light_budget
+                    .after(TransformSystem::TransformPropagate)
+                    .before(VisibilitySystems::VisibilityPropagate),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                apply_shadow_budget.after(apply_light_budget),
+            )
+            .add_system(apply_shadow_map);
+    }
+}
@@ -0,0 +1,213 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::{BoxedFuture, HashMap},
+};
+
+use crate::settings::Settings;
+
+/// Language used when the selected one lacks some strings.
+const FALLBACK_LANGUAGE: &str = "en";
+
+/// Strings of a single language, loaded from `assets/locales/<language>.lang`.
+///
+/// The file format is a simple key-value list:
+///
+/// ```text
+/// # Comments start with '#'
+/// hud-select-target = Press 'T' to select a target.
+/// hud-distance = Distance to target: {distance}m
+/// ```
+///
+/// Values may contain `{name}` placeholders, see [`Localization::format`], and `\n` for line breaks.
+#[derive(TypeUuid, Default, Debug)]
+#[uuid = "6b5b0a1e-3f0c-4bde-9a53-2d1c8f0e7a41"]
+pub struct Translations(HashMap<String, String>);
+
+impl Translations {
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut strings = HashMap::default();
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("line {}: expected 'key = value'", line_number + 1));
+            };
+            strings.insert(key.trim().to_owned(), value.trim().replace("\\n", "\n"));
+        }
+        Ok(Self(strings))
+    }
+}
+
+#[derive(Default)]
+struct TranslationsLoader;
+
+impl AssetLoader for TranslationsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let text = std::str::from_utf8(bytes)?;
+            let translations = Translations::parse(text).map_err(bevy::asset::Error::msg)?;
+            load_context.set_default_asset(LoadedAsset::new(translations));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["lang"]
+    }
+}
+
+/// Localized strings for the language from [`Settings`].
+/// Keys missing in both the selected and the fallback languages are returned as is.
+#[derive(Resource, Default)]
+pub struct Localization {
+    language: Handle<Translations>,
+    fallback: Handle<Translations>,
+    strings: HashMap<String, String>,
+}
+
+impl Localization {
+    /// Returns localized string for the `key`.
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.strings.get(key).map_or(key, |s| s.as_str())
+    }
+
+    /// Returns localized string for the `key` with `{name}` placeholders replaced by `args`.
+    ///
+    /// ```ignore
+    /// localization.format("hud-distance", &[("distance", &format!("{distance:.2}"))])
+    /// ```
+    pub fn format(&self, key: &str, args: &[(&str, &dyn std::fmt::Display)]) -> String {
+        let mut result = self.get(key).to_owned();
+        for (name, value) in args {
+            result = result.replace(&format!("{{{name}}}"), &value.to_string());
+        }
+        result
+    }
+}
+
+/// Annotates text which first section is a localization key.
+/// Such texts are updated automatically once language is changed.
+#[derive(Component)]
+pub struct LocalizedText(pub String);
+
+impl LocalizedText {
+    pub fn new(key: &str) -> Self {
+        Self(key.to_owned())
+    }
+}
+
+fn load_language(
+    settings: Res<Settings>,
+    assets: Res<AssetServer>,
+    mut localization: ResMut<Localization>,
+) {
+    if settings.is_changed() {
+        let path = format!("locales/{}.lang", settings.language);
+        let handle = assets.load(path.as_str());
+        if handle != localization.language {
+            info!("Language: {}", settings.language);
+            localization.language = handle;
+        }
+        if localization.fallback == Handle::default() {
+            localization.fallback =
+                assets.load(format!("locales/{FALLBACK_LANGUAGE}.lang").as_str());
+        }
+    }
+}
+
+/// Rebuilds localized strings once language files are loaded or hot-reloaded.
+fn update_strings(
+    mut ev_translations: EventReader<AssetEvent<Translations>>,
+    translations: Res<Assets<Translations>>,
+    mut localization: ResMut<Localization>,
+) {
+    let needs_update = ev_translations.iter().any(|event| match event {
+        AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+            *handle == localization.language || *handle == localization.fallback
+        }
+        AssetEvent::Removed { .. } => false,
+    });
+    // Language could be switched to the already loaded one, so the handle change is also checked
+    if !needs_update && !localization.is_changed() {
+        return;
+    }
+
+    let mut strings = HashMap::default();
+    for handle in [&localization.fallback, &localization.language] {
+        if let Some(translations) = translations.get(handle) {
+            strings.extend(translations.0.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+    }
+    localization.bypass_change_detection().strings = strings;
+    localization.set_changed();
+}
+
+fn update_texts(
+    localization: Res<Localization>,
+    mut texts: Query<(&LocalizedText, &mut Text)>,
+    added: Query<(), Added<LocalizedText>>,
+) {
+    if !localization.is_changed() && added.is_empty() {
+        return;
+    }
+
+    for (localized, mut text) in texts.iter_mut() {
+        if let Some(section) = text.sections.first_mut() {
+            section.value = localization.get(&localized.0).to_owned();
+        }
+    }
+}
+
+/// Translations for the language from `Settings`, applied to texts with localization keys.
+pub struct LocalizationPlugin;
+impl Plugin for LocalizationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Settings>()
+            .init_resource::<Localization>()
+            .add_asset::<Translations>()
+            .init_asset_loader::<TranslationsLoader>()
+            .add_system(load_language)
+            .add_system(update_strings.after(load_language))
+            .add_system(update_texts.after(update_strings));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_translations() {
+        let translations = Translations::parse(
+            "# comment\n\nhud-hp = Hit Points: {hp}%\n  multiline = one\\ntwo  \n",
+        )
+        .unwrap();
+        assert_eq!(translations.0.len(), 2);
+        assert_eq!(translations.0["hud-hp"], "Hit Points: {hp}%");
+        assert_eq!(translations.0["multiline"], "one\ntwo");
+
+        assert!(Translations::parse("no separator").is_err());
+    }
+
+    #[test]
+    fn format_placeholders() {
+        let mut localization = Localization::default();
+        localization
+            .strings
+            .insert("hud-target".to_owned(), "{name}: {distance}m".to_owned());
+
+        assert_eq!(
+            localization.format("hud-target", &[("name", &"Drone"), ("distance", &12.5)]),
+            "Drone: 12.5m"
+        );
+        assert_eq!(localization.get("missing-key"), "missing-key");
+    }
+}
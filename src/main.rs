@@ -1,52 +1,98 @@
 use bevy::prelude::*;
 use bevy::scene::SceneInstance;
-use bevy::time::FixedTimestep;
-use bevy_inspector_egui::WorldInspectorPlugin;
 use bevy_rapier3d::prelude::*;
-use rand::Rng;
-
-pub mod aiming;
-pub mod collider_setup;
-pub mod drone;
-pub mod gun;
-pub mod player;
-pub mod projectile;
-pub mod scene_setup;
-pub mod skybox;
-pub mod turret;
-pub mod weapon;
+
+use bevy_space_sim::{
+    collider_setup, components, drone, prelude::*, reinforcements, scene_setup, skirmish, turret,
+};
 
 fn main() {
+    let skirmish = SkirmishConfig::from_args(std::env::args().skip(1));
+    let observe = std::env::args().any(|arg| arg == "--observe");
+    let telemetry = TelemetryPlugin::from_args(std::env::args().skip(1));
+    let scenario = Scenario::from_args(std::env::args().skip(1)).unwrap_or_default();
+    let callsigns = Callsigns::from_args(std::env::args().skip(1));
+    let escort = scenario.escort.clone();
+    let objectives = scenario.objectives.clone();
+    let display = DisplayPlugin::load();
     let mut app = App::new();
 
-    app.add_plugins(DefaultPlugins)
-        .add_plugin(WorldInspectorPlugin::new())
+    app.add_plugins(DefaultPlugins.set(display.window_plugin()))
+        .add_plugin(display)
         .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
         .insert_resource(RapierConfiguration {
-            gravity: Vec3::ZERO, // disable gravity at all
+            // Zero in open space, only ballistic projectiles fall under it
+            gravity: Vec3::from(scenario.environment.gravity),
             ..default()
         })
-        .add_plugin(scene_setup::SceneSetupPlugin)
-        .add_plugin(collider_setup::ColliderSetupPlugin)
-        .add_plugin(skybox::SkyboxPlugin)
-        .add_plugin(projectile::ProjectilePlugin)
-        .add_plugin(aiming::AimingPlugin)
-        .add_plugin(gun::GunPlugin)
-        .add_plugin(player::PlayerPlugin)
-        .add_plugin(turret::TurretPlugin)
-        .add_plugin(drone::DronePlugin)
-        .add_startup_system(setup_env)
-        .add_system_set(
-            SystemSet::new()
-                .with_run_criteria(FixedTimestep::step(5.0))
-                .with_system(spawn_baloon),
-        )
+        .add_plugin(SceneSetupPlugin)
+        .add_plugin(ModelAnimationPlugin)
+        .add_plugin(ColliderSetupPlugin)
+        .add_plugin(SkyboxPlugin)
+        .add_plugin(PlanetPlugin)
+        .add_plugin(ComponentsPlugin)
+        .add_plugin(ProjectilePlugin)
+        .add_plugin(TrailPlugin)
+        .add_plugin(LightBudgetPlugin)
+        .add_plugin(EffectCullingPlugin)
+        .add_plugin(ActivityPlugin)
+        .add_plugin(ImpactPlugin)
+        .add_plugin(AimingPlugin)
+        .add_plugin(GunPlugin)
+        .add_plugin(GimbalPlugin)
+        .add_plugin(ShieldEffectPlugin)
+        .add_plugin(BubbleShieldPlugin)
+        .add_plugin(LocalizationPlugin)
+        .add_plugin(HudPlugin)
+        .add_plugin(ProfilePlugin)
+        .add_plugin(MusicPlugin)
+        .add_plugin(AudioCuesPlugin)
+        .add_plugin(TurretPlugin)
+        .insert_resource(callsigns)
+        .add_plugin(DronePlugin)
+        .add_plugin(ReinforcementsPlugin)
+        .add_plugin(SalvagePlugin)
+        .add_plugin(CombatLogPlugin)
+        .add_plugin(RoutePlugin)
+        .add_plugin(HazardPlugin)
+        .add_plugin(IonStormPlugin)
+        .add_plugin(BossPlugin)
+        .add_plugin(ScenarioPlugin)
+        .add_plugin(TriggerPlugin)
+        .add_plugin(TargetRangePlugin)
+        .insert_resource(scenario)
         .insert_resource(Msaa { samples: 4 })
         .add_system(update_msaa)
         .add_system(bevy::window::close_on_esc);
 
-    #[cfg(debug_assertions)]
-    app.add_plugin(RapierDebugRenderPlugin::default());
+    // Observer has no player and follows the action on its own
+    if observe {
+        app.add_plugin(ObserverPlugin);
+    } else {
+        app.add_plugin(PlayerPlugin)
+            .add_plugin(EconomyPlugin)
+            .add_plugin(CollisionWarningPlugin)
+            .add_plugin(KillCamPlugin)
+            .add_plugin(ShipSwitchPlugin);
+    }
+
+    if let Some(config) = objectives {
+        app.insert_resource(config).add_plugin(ObjectivePlugin);
+    }
+
+    if let Some(telemetry) = telemetry {
+        app.add_plugin(telemetry);
+    }
+
+    // Skirmish and escort mission replace the default scene
+    match (skirmish, escort) {
+        (Some(config), _) => app.insert_resource(config).add_plugin(SkirmishPlugin),
+        (None, Some(config)) => app.insert_resource(config).add_plugin(EscortPlugin),
+        (None, None) => app.add_startup_system(setup_env),
+    };
+
+    #[cfg(feature = "devtools")]
+    app.add_plugin(DevToolsPlugin);
 
     app.run();
 }
@@ -54,44 +100,22 @@ fn main() {
 fn setup_env(
     mut commands: Commands,
     mut ev_spawn_turret: EventWriter<turret::SpawnTurretEvent>,
-    mut ev_spawn_drone: EventWriter<drone::SpawnDroneEvent>,
+    mut battle: ResMut<reinforcements::Reinforcements>,
     asset_server: Res<AssetServer>,
+    scenario: Res<Scenario>,
 ) {
-    commands
-        .spawn(SceneBundle {
-            scene: asset_server.load("models/spaceship_v1.glb#Scene0"),
-            ..default()
-        })
-        .insert(Restitution::coefficient(1.0))
-        .insert(TransformBundle::from(Transform::from_scale(
-            2.0 * Vec3::ONE, // adjust model size for realizm
-        )))
-        .insert(scene_setup::SetupRequired::new(
-            move |commands, entities| {
-                let mut root: Option<Entity> = None;
-                let mut mesh_source: Option<Entity> = None;
-                for entity in entities {
-                    if entity.contains::<SceneInstance>() {
-                        root = Some(entity.id());
-                    }
-                    if entity.contains::<Handle<Mesh>>() {
-                        mesh_source = Some(entity.id());
-                    }
-                }
-
-                commands
-                    .entity(root.unwrap())
-                    .insert(collider_setup::ConvexDecomposition {
-                        mesh_source: mesh_source.unwrap(),
-                        parameters: VHACDParameters {
-                            concavity: 0.06,
-                            ..default()
-                        },
-                    });
-            },
-        ))
-        .insert(projectile::HitPoints::new(2000))
-        .insert(Name::new("Spaceship"));
+    // Capital ship cruises along the scenario route, if there is one, with turrets mounted on it
+    let route = scenario.capital_ship_route.as_ref().map(|r| r.route());
+    let start = route.as_ref().and_then(|r| r.start()).unwrap_or_default();
+    let ship = skirmish::spawn_capital_ship(&mut commands, &asset_server, start);
+    let cruising = route.is_some();
+    if let Some(route) = route {
+        commands
+            .entity(ship)
+            .insert(RigidBody::KinematicPositionBased)
+            .insert(Velocity::default())
+            .insert(route);
+    }
 
     commands
         .spawn(SceneBundle {
@@ -100,6 +124,7 @@ fn setup_env(
         })
         .insert(Restitution::coefficient(1.0))
         .insert(RigidBody::Dynamic)
+        .insert(collider_setup::MassOverride::Mass(200_000.0))
         .insert(TransformBundle::from(Transform {
             translation: Vec3::new(0.0, 100.0, -300.0),
             rotation: Quat::from_rotation_y(std::f32::consts::PI),
@@ -142,28 +167,60 @@ fn setup_env(
                 });
             },
         ))
-        .insert(projectile::HitPoints::new(2000))
+        .insert(components::HitPoints::new(2000))
         .insert(Name::new("Artillery Platform"));
 
-    for (drone, position) in [
-        (drone::Drone::Infiltrator, Vec3::new(-1600.0, 10.0, 0.0)),
-        (drone::Drone::Infiltrator, Vec3::new(-1500.0, 10.0, 50.0)),
-        (drone::Drone::Infiltrator, Vec3::new(-1600.0, 10.0, 100.0)),
-        (drone::Drone::Praetor, Vec3::new(1600.0, 10.0, 100.0)),
-        (drone::Drone::Praetor, Vec3::new(1500.0, 10.0, 50.0)),
-        (drone::Drone::Praetor, Vec3::new(1600.0, 10.0, 0.0)),
-    ] {
-        ev_spawn_drone.send(drone::SpawnDroneEvent {
-            drone,
-            transform: Transform::from_translation(position),
-        });
+    // Drones arrive from both sides and keep coming until their reinforcements run out,
+    // unless the scenario brings its own teams
+    if scenario.teams.is_empty() {
+        battle.pools.push(reinforcements::ReinforcementPool::new(
+            components::Fraction::Drones,
+            30,
+            6,
+            vec![drone::Drone::Infiltrator, drone::Drone::Praetor],
+        ));
+        for x in [-1600.0, 1600.0] {
+            commands
+                .spawn(TransformBundle::from(
+                    Transform::from_xyz(x, 10.0, 50.0).looking_at(Vec3::ZERO, Vec3::Y),
+                ))
+                .insert(reinforcements::SpawnPoint {
+                    fraction: components::Fraction::Drones,
+                    radius: 100.0,
+                })
+                .insert(Name::new("Drone Spawn Point"));
+        }
     }
 
     let pos = 25.0;
-    for (x, z) in [(-pos, -pos), (pos, -pos), (-pos, pos), (pos, pos)] {
+    let kinds = [
+        turret::TurretKind::Flak,
+        turret::TurretKind::Railgun,
+        turret::TurretKind::MissileBattery,
+        turret::TurretKind::Flak,
+    ];
+    for ((x, z), kind) in [(-pos, -pos), (pos, -pos), (-pos, pos), (pos, pos)]
+        .into_iter()
+        .zip(kinds)
+    {
+        let position = Vec3::new(x, -3.0, z);
+        // Turrets ride along with the cruising ship, relative to it, so the offset and the turret
+        // size are scaled along with it. Otherwise they stand on their own.
+        let (transform, parent) = if cruising {
+            let scale = skirmish::CAPITAL_SHIP_SCALE;
+            let transform = Transform::from_translation(position / scale)
+                .with_scale(Vec3::splat(scale.recip()));
+            (transform, Some(ship))
+        } else {
+            (Transform::from_translation(position), None)
+        };
         ev_spawn_turret.send(turret::SpawnTurretEvent {
-            transform: Transform::from_translation(Vec3::new(x, -3.0, z)),
+            kind,
+            prefab: None,
+            transform,
+            parent,
             rotation_speed: 120_f32.to_radians(),
+            fraction: components::Fraction::Turrets,
         });
     }
 
@@ -181,54 +238,6 @@ fn setup_env(
     });
 }
 
-fn spawn_baloon(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    assets: Res<AssetServer>,
-    mut baloon_number: Local<u32>,
-) {
-    let mut rng = rand::thread_rng();
-    let position = loop {
-        let position = Vec3 {
-            x: rng.gen_range(-100.0..100.0),
-            z: rng.gen_range(-100.0..100.0),
-            y: 2.0,
-        };
-        // Regenerate position if it is inside safe area (where space ship is located)
-        if position.x.abs() > 10.0 && position.z.abs() > 10.0 {
-            break position;
-        }
-    };
-
-    let radius = 3.0;
-    commands
-        .spawn(PbrBundle {
-            mesh: meshes.add(Mesh::from(shape::UVSphere {
-                radius,
-                sectors: 64,
-                stacks: 32,
-            })),
-            material: materials.add(StandardMaterial {
-                base_color_texture: assets.load("textures/aim2.png").into(),
-                ..default()
-            }),
-            transform: Transform::from_translation(position)
-                .with_rotation(Quat::from_rotation_x(std::f32::consts::PI * 0.5)),
-            ..default()
-        })
-        .insert(Velocity {
-            linvel: Vec3::Y * rng.gen_range(1.0..5.0),
-            angvel: Vec3::Y * rng.gen_range(-2.0..2.0),
-        })
-        .insert(Collider::ball(radius))
-        .insert(RigidBody::Dynamic)
-        .insert(projectile::Lifetime(60.0))
-        .insert(projectile::HitPoints::new(20))
-        .insert(Name::new(format!("Shooting target #{}", *baloon_number)));
-    *baloon_number += 1;
-}
-
 fn update_msaa(keys: Res<Input<KeyCode>>, mut msaa: ResMut<Msaa>) {
     if keys.just_pressed(KeyCode::M) {
         // Unfortunately, WGPU currently only supports 1 or 4 samples.
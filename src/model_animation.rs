@@ -0,0 +1,273 @@
+//! Playback of animations authored in glTF models, like landing gear, radar dishes or turret
+//! deploy. Clips are looked up by name in the glTF file the scene was loaded from.
+//!
+//! Bevy's `AnimationPlayer` switches clips instantly, so switching is smoothed here by blending
+//! from the pose the model had at the moment of the switch into the new clip.
+
+use bevy::{animation::animation_player, gltf::Gltf, prelude::*, scene::SceneInstance};
+
+/// Seconds to blend from the previous pose into a newly started clip
+const BLEND_TIME: f32 = 0.3;
+
+/// Animation players of the spawned scene and the glTF file with their clips.
+/// Inserted on the scene root once the scene is spawned.
+#[derive(Component)]
+pub struct SceneAnimations {
+    gltf: Handle<Gltf>,
+    players: Vec<Entity>,
+    /// Name of the clip that is played right now
+    current: Option<String>,
+}
+
+/// Plays the named clip of the scene, inserted on the scene root. Removed once the clip is started,
+/// which may take a few frames until the model is loaded.
+#[derive(Component, Clone)]
+pub struct PlayAnimation {
+    pub name: String,
+    /// Restarts the clip once it ends, otherwise it stops on the last frame
+    pub looped: bool,
+}
+
+impl PlayAnimation {
+    pub fn once(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            looped: false,
+        }
+    }
+
+    pub fn looped(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            looped: true,
+        }
+    }
+}
+
+/// Looped idle and active clips of the scene, e.g. a radar dish that spins up while tracking a target.
+/// Toggling `is_active` blends to the other clip.
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct AnimationStates {
+    pub idle: String,
+    pub active: String,
+    pub is_active: bool,
+}
+
+impl AnimationStates {
+    pub fn new(idle: impl Into<String>, active: impl Into<String>) -> Self {
+        Self {
+            idle: idle.into(),
+            active: active.into(),
+            is_active: false,
+        }
+    }
+}
+
+/// Transition to a new clip, on the entity with the `AnimationPlayer`
+#[derive(Component)]
+struct Blend {
+    /// Local transforms of the animated entities at the moment of the switch
+    from: Vec<(Entity, Transform)>,
+    elapsed: f32,
+}
+
+/// Weight of the new clip, eased in and out to hide the start and end of the transition
+fn blend_weight(elapsed: f32, duration: f32) -> f32 {
+    let t = (elapsed / duration.max(f32::EPSILON)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn blend_transform(from: &Transform, to: &Transform, weight: f32) -> Transform {
+    Transform {
+        translation: from.translation.lerp(to.translation, weight),
+        rotation: from.rotation.slerp(to.rotation, weight),
+        scale: from.scale.lerp(to.scale, weight),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{blend_transform, blend_weight};
+    use bevy::prelude::{Quat, Transform, Vec3};
+
+    #[test]
+    fn test_blend() {
+        assert_eq!(blend_weight(0.0, 0.3), 0.0);
+        assert_eq!(blend_weight(0.15, 0.3), 0.5);
+        assert_eq!(blend_weight(1.0, 0.3), 1.0);
+        // zero duration switches instantly
+        assert_eq!(blend_weight(0.0, 0.0), 1.0);
+
+        let from = Transform::IDENTITY;
+        let to = Transform::from_xyz(2.0, 0.0, 0.0).with_rotation(Quat::from_rotation_y(1.0));
+        let half = blend_transform(&from, &to, 0.5);
+        assert_eq!(half.translation, Vec3::new(1.0, 0.0, 0.0));
+        assert!((half.rotation.to_axis_angle().1 - 0.5).abs() < 1e-5);
+        assert_eq!(blend_transform(&from, &to, 1.0).translation, to.translation);
+    }
+}
+
+/// Scene nodes, to follow animation paths
+type Nodes<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Transform,
+        Option<&'static Children>,
+        Option<&'static Name>,
+    ),
+>;
+
+/// Entities animated by `clip` with their current transforms, found by names from the `player`
+/// entity like the `AnimationPlayer` does
+fn current_pose(player: Entity, clip: &AnimationClip, nodes: &Nodes) -> Vec<(Entity, Transform)> {
+    clip.curves()
+        .keys()
+        .filter_map(|path| {
+            // the first name is the player itself
+            path.parts.iter().skip(1).try_fold(player, |entity, part| {
+                let (_, children, _) = nodes.get(entity).ok()?;
+                children?.iter().copied().find(
+                    |&child| matches!(nodes.get(child), Ok((_, _, Some(name))) if name == part),
+                )
+            })
+        })
+        .filter_map(|entity| nodes.get(entity).ok().map(|(t, _, _)| (entity, *t)))
+        .collect()
+}
+
+fn find_animations(
+    mut commands: Commands,
+    scenes: Query<(Entity, &Handle<Scene>, &SceneInstance), Without<SceneAnimations>>,
+    animated: Query<(), With<AnimationPlayer>>,
+    server: Res<AssetServer>,
+    scene_manager: Res<SceneSpawner>,
+) {
+    for (entity, handle, instance) in scenes.iter() {
+        if !scene_manager.instance_is_ready(**instance) {
+            continue;
+        }
+        let players: Vec<_> = scene_manager
+            .iter_instance_entities(**instance)
+            .filter(|&e| animated.contains(e))
+            .collect();
+        // scenes without animations are marked too, so they aren't checked every frame
+        let gltf = match server.get_handle_path(handle) {
+            Some(path) if !players.is_empty() => server.load(path.path()),
+            _ => Handle::default(),
+        };
+        commands.entity(entity).insert(SceneAnimations {
+            gltf,
+            players,
+            current: None,
+        });
+    }
+}
+
+fn switch_states(
+    mut commands: Commands,
+    states: Query<(Entity, &AnimationStates), Changed<AnimationStates>>,
+) {
+    for (entity, states) in states.iter() {
+        let name = if states.is_active {
+            &states.active
+        } else {
+            &states.idle
+        };
+        commands
+            .entity(entity)
+            .insert(PlayAnimation::looped(name.as_str()));
+    }
+}
+
+fn play_animations(
+    mut commands: Commands,
+    mut scenes: Query<(Entity, &PlayAnimation, &mut SceneAnimations)>,
+    gltfs: Res<Assets<Gltf>>,
+    clips: Res<Assets<AnimationClip>>,
+    mut players: Query<&mut AnimationPlayer>,
+    nodes: Nodes,
+) {
+    for (entity, play, mut animations) in scenes.iter_mut() {
+        if animations.players.is_empty() {
+            debug!("{entity:?} has no animations to play '{}'", play.name);
+            commands.entity(entity).remove::<PlayAnimation>();
+            continue;
+        }
+        let Some(gltf) = gltfs.get(&animations.gltf) else {
+            continue;
+        };
+        let Some(handle) = gltf.named_animations.get(&play.name) else {
+            debug!("{entity:?} has no animation '{}'", play.name);
+            commands.entity(entity).remove::<PlayAnimation>();
+            continue;
+        };
+        let Some(clip) = clips.get(handle) else {
+            continue;
+        };
+        commands.entity(entity).remove::<PlayAnimation>();
+
+        // clips are played by the player on the node their paths start from
+        let root = clip.curves().keys().find_map(|path| path.parts.first());
+        let player = animations
+            .players
+            .iter()
+            .copied()
+            .find(|&e| matches!(nodes.get(e), Ok((_, _, name)) if name.is_some() && name == root))
+            .unwrap_or(animations.players[0]);
+        let Ok(mut player_state) = players.get_mut(player) else {
+            continue;
+        };
+
+        if animations.current.as_ref() != Some(&play.name) {
+            let from = current_pose(player, clip, &nodes);
+            commands.entity(player).insert(Blend { from, elapsed: 0.0 });
+            player_state.start(handle.clone());
+            animations.current = Some(play.name.clone());
+        }
+        if play.looped {
+            player_state.repeat();
+        } else {
+            player_state.stop_repeating();
+        }
+    }
+}
+
+/// Mixes the pose before the switch into the one the `AnimationPlayer` just applied
+fn blend_animations(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut blends: Query<(Entity, &mut Blend)>,
+    mut transforms: Query<&mut Transform>,
+) {
+    for (entity, mut blend) in blends.iter_mut() {
+        blend.elapsed += time.delta_seconds();
+        let weight = blend_weight(blend.elapsed, BLEND_TIME);
+        for (animated, from) in blend.from.iter() {
+            if let Ok(mut transform) = transforms.get_mut(*animated) {
+                *transform = blend_transform(from, &transform, weight);
+            }
+        }
+        if weight >= 1.0 {
+            commands.entity(entity).remove::<Blend>();
+        }
+    }
+}
+
+/// Finds animations of spawned glTF scenes and plays them on `PlayAnimation` and `AnimationStates`.
+pub struct ModelAnimationPlugin;
+impl Plugin for ModelAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<AnimationStates>()
+            .add_system(find_animations)
+            .add_system(switch_states.before(play_animations))
+            .add_system(play_animations.after(find_animations))
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                blend_animations
+                    .after(animation_player)
+                    .before(bevy::transform::TransformSystem::TransformPropagate),
+            );
+    }
+}
@@ -0,0 +1,194 @@
+//! Stat modifiers like +20% rate of fire, -15% heat per shot or +1 damage. They are kept in the
+//! `Modifiers` component of a gun or a ship and stack with the ones of its ancestors, so a ship
+//! upgrade affects all its guns. Base stats are never changed, modifiers are applied on top of
+//! them whenever the stats are computed, so upgrades and loot need no code of their own.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    components::{Damage, HitPoints},
+    gun::{Gun, Heat},
+    projectile::{DamageSystems, Hitscan, Owner},
+};
+
+/// Percentage modifiers of a stat scale it by at most this factor altogether
+const MAX_SCALE: f32 = 4.0;
+/// ...and by at least this one, so stats never drop to zero
+const MIN_SCALE: f32 = 0.1;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Reflect, FromReflect)]
+pub enum Stat {
+    /// Shots per second of a gun
+    RateOfFire,
+    /// Muzzle speed of a gun
+    ProjectileSpeed,
+    /// Effective range of a gun
+    EffectiveRange,
+    /// Barrel heat of a gun per shot
+    HeatPerShot,
+    /// Damage of projectiles, applied from the modifiers of the ship firing them
+    Damage,
+    /// Maximum hit points of a ship, applied once they are set up, e.g. when it's spawned
+    HitPoints,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug, Reflect, FromReflect)]
+pub enum Op {
+    /// Added to the base value
+    Flat(f32),
+    /// Percent of the value after flat bonuses, e.g. 20 for +20% or -15 for -15%
+    Percent(f32),
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug, Reflect, FromReflect)]
+pub struct Modifier {
+    pub stat: Stat,
+    pub op: Op,
+}
+
+/// Modifiers of a gun or a ship, applied on top of the base stats.
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct Modifiers(Vec<Modifier>);
+
+impl Modifiers {
+    pub fn push(&mut self, modifier: Modifier) {
+        self.0.push(modifier);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Modifier> {
+        self.0.iter()
+    }
+
+    /// `base` value of the `stat` with the modifiers applied. Flat bonuses are summed up first,
+    /// then percentages stack multiplicatively within `MIN_SCALE..=MAX_SCALE`.
+    pub fn apply(&self, stat: Stat, base: f32) -> f32 {
+        let mut flat = 0.0;
+        let mut scale = 1.0;
+        for modifier in self.0.iter().filter(|m| m.stat == stat) {
+            match modifier.op {
+                Op::Flat(value) => flat += value,
+                Op::Percent(percent) => scale *= 1.0 + percent / 100.0,
+            }
+        }
+        ((base + flat) * scale.clamp(MIN_SCALE, MAX_SCALE)).max(0.0)
+    }
+}
+
+impl FromIterator<Modifier> for Modifiers {
+    fn from_iter<T: IntoIterator<Item = Modifier>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl Extend<Modifier> for Modifiers {
+    fn extend<T: IntoIterator<Item = Modifier>>(&mut self, iter: T) {
+        self.0.extend(iter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Modifier, Modifiers, Op, Stat};
+
+    fn modifiers(ops: &[(Stat, Op)]) -> Modifiers {
+        ops.iter()
+            .map(|&(stat, op)| Modifier { stat, op })
+            .collect()
+    }
+
+    #[test]
+    fn test_stacking() {
+        assert_eq!(Modifiers::default().apply(Stat::RateOfFire, 5.0), 5.0);
+
+        let modifiers = modifiers(&[
+            (Stat::RateOfFire, Op::Percent(100.0)),
+            (Stat::RateOfFire, Op::Percent(50.0)),
+            (Stat::RateOfFire, Op::Flat(1.0)),
+            (Stat::Damage, Op::Flat(1.0)),
+        ]);
+        // flat bonus first, then both percentages multiplied
+        assert_eq!(modifiers.apply(Stat::RateOfFire, 2.0), 9.0);
+        assert_eq!(modifiers.apply(Stat::Damage, 2.0), 3.0);
+        assert_eq!(modifiers.apply(Stat::HeatPerShot, 2.0), 2.0);
+    }
+
+    #[test]
+    fn test_limits() {
+        let boosted = modifiers(&[(Stat::ProjectileSpeed, Op::Percent(300.0)); 3]);
+        assert_eq!(boosted.apply(Stat::ProjectileSpeed, 10.0), 40.0);
+        let nerfed = modifiers(&[(Stat::HeatPerShot, Op::Percent(-50.0)); 5]);
+        assert_eq!(nerfed.apply(Stat::HeatPerShot, 10.0), 1.0);
+        let negative = modifiers(&[(Stat::Damage, Op::Flat(-5.0))]);
+        assert_eq!(negative.apply(Stat::Damage, 2.0), 0.0);
+    }
+}
+
+/// Updates guns once their own modifiers or the ones of their ancestors change
+fn modify_guns(
+    mut guns: Query<(Entity, &mut Gun, Option<&mut Heat>)>,
+    changed: Query<(), Changed<Modifiers>>,
+    modifiers: Query<&Modifiers>,
+    parent_query: Query<&Parent>,
+) {
+    for (entity, mut gun, heat) in guns.iter_mut() {
+        let lineage = || std::iter::once(entity).chain(parent_query.iter_ancestors(entity));
+        if !gun.is_added() && !lineage().any(|e| changed.contains(e)) {
+            continue;
+        }
+        let stacked: Modifiers = lineage()
+            .filter_map(|e| modifiers.get(e).ok())
+            .flat_map(|m| m.iter().copied())
+            .collect();
+        if let Some(mut heat) = heat {
+            heat.set_modifiers(&stacked);
+        }
+        gun.set_modifiers(stacked);
+    }
+}
+
+/// Applies damage modifiers of the ships to the projectiles they fire
+fn modify_projectiles(
+    mut projectiles: Query<(&Owner, Option<&mut Damage>, Option<&mut Hitscan>), Added<Owner>>,
+    modifiers: Query<&Modifiers>,
+) {
+    let modify = |modifiers: &Modifiers, damage: &mut u32| {
+        *damage = modifiers.apply(Stat::Damage, *damage as f32).round() as u32;
+    };
+    for (owner, damage, hitscan) in projectiles.iter_mut() {
+        let Ok(modifiers) = modifiers.get(owner.0) else {
+            continue;
+        };
+        if let Some(mut damage) = damage {
+            modify(modifiers, &mut damage.0);
+        }
+        if let Some(mut hitscan) = hitscan {
+            modify(modifiers, &mut hitscan.damage);
+        }
+    }
+}
+
+/// Scales the maximum hit points of ships once they get them. Restored hit points keep the
+/// modified maximum, so they are modified only once.
+fn modify_hit_points(mut ships: Query<(&mut HitPoints, &Modifiers), Added<HitPoints>>) {
+    for (mut hp, modifiers) in ships.iter_mut() {
+        let maximum = modifiers
+            .apply(Stat::HitPoints, hp.maximum() as f32)
+            .round() as u32;
+        if maximum != hp.maximum() {
+            *hp = HitPoints::new(maximum.max(1));
+        }
+    }
+}
+
+/// Applies `Modifiers` to the stats of guns, ships and the projectiles they fire.
+pub struct ModifierPlugin;
+impl Plugin for ModifierPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Modifiers>()
+            .add_system(modify_guns)
+            .add_system(modify_hit_points)
+            .add_system(modify_projectiles.before(DamageSystems));
+    }
+}
@@ -0,0 +1,213 @@
+use std::path::Path;
+
+use bevy::{asset::LoadState, prelude::*};
+
+use crate::{aiming::GunLayer, player::Player, projectile::DamageEvent, settings::Settings};
+
+/// Threat added by every hostile aiming at the player
+const TARGETING_THREAT: f32 = 0.2;
+/// Threat added by every hit on the player
+const DAMAGE_THREAT: f32 = 0.1;
+/// Decay speed of the threat from hits, per second
+const DAMAGE_THREAT_DECAY: f32 = 0.05;
+/// Layer weight change per second, so it takes 2 seconds to fade in or out completely
+const CROSSFADE_SPEED: f32 = 0.5;
+
+/// Music tracks are played simultaneously and mixed according to the threat level,
+/// so all of them are expected to have the same tempo and length. The tracks aren't shipped with
+/// the game yet, layers without them in `assets/music/` are skipped, and the soundtrack is off
+/// until at least one is there.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MusicLayer {
+    Ambient,
+    Tension,
+    Combat,
+}
+
+impl MusicLayer {
+    const ALL: [MusicLayer; 3] = [MusicLayer::Ambient, MusicLayer::Tension, MusicLayer::Combat];
+
+    fn path(self) -> &'static str {
+        match self {
+            MusicLayer::Ambient => "music/ambient.ogg",
+            MusicLayer::Tension => "music/tension.ogg",
+            MusicLayer::Combat => "music/combat.ogg",
+        }
+    }
+
+    /// Whether the track of the layer is in `assets`
+    fn shipped(self) -> bool {
+        Path::new("assets").join(self.path()).exists()
+    }
+
+    /// Layer weight for the given threat level, from 0 (muted) to 1
+    fn weight(self, threat: f32) -> f32 {
+        match self {
+            // Ambient is kept quietly under the combat layer
+            MusicLayer::Ambient if threat >= 0.6 => 0.3,
+            MusicLayer::Ambient => 1.0,
+            MusicLayer::Tension if threat >= 0.2 => 1.0,
+            MusicLayer::Combat if threat >= 0.6 => 1.0,
+            MusicLayer::Tension | MusicLayer::Combat => 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MusicSettings {
+    /// Master volume of the music
+    pub volume: f32,
+    pub ambient_volume: f32,
+    pub tension_volume: f32,
+    pub combat_volume: f32,
+}
+
+impl MusicSettings {
+    pub fn layer_volume(&self, layer: MusicLayer) -> f32 {
+        let volume = match layer {
+            MusicLayer::Ambient => self.ambient_volume,
+            MusicLayer::Tension => self.tension_volume,
+            MusicLayer::Combat => self.combat_volume,
+        };
+        self.volume * volume
+    }
+}
+
+impl Default for MusicSettings {
+    fn default() -> Self {
+        Self {
+            volume: 0.5,
+            ambient_volume: 1.0,
+            tension_volume: 1.0,
+            combat_volume: 1.0,
+        }
+    }
+}
+
+/// Combat intensity around the player, from 0 for calm to 1 for heavy fight.
+#[derive(Resource, Default)]
+pub struct ThreatLevel {
+    pub value: f32,
+    /// Part of the threat from recent hits on the player
+    recent_damage: f32,
+}
+
+struct LayerPlayback {
+    layer: MusicLayer,
+    track: Handle<AudioSource>,
+    /// Playback starts once the track is loaded
+    sink: Option<Handle<AudioSink>>,
+    weight: f32,
+}
+
+#[derive(Resource)]
+struct MusicLayers(Vec<LayerPlayback>);
+
+fn load_music(mut commands: Commands, assets: Res<AssetServer>) {
+    let layers = MusicLayer::ALL
+        .into_iter()
+        .filter(|layer| layer.shipped())
+        .map(|layer| LayerPlayback {
+            layer,
+            track: assets.load(layer.path()),
+            sink: None,
+            weight: 0.0,
+        })
+        .collect();
+    commands.insert_resource(MusicLayers(layers));
+}
+
+/// Starts all layers together once their tracks are loaded, to keep them in sync, and drops the
+/// ones with broken tracks
+fn start_music(
+    assets: Res<AssetServer>,
+    audio: Res<Audio>,
+    sinks: Res<Assets<AudioSink>>,
+    mut layers: ResMut<MusicLayers>,
+) {
+    if layers.0.iter().all(|playback| playback.sink.is_some()) {
+        return;
+    }
+    layers.0.retain(|playback| {
+        let failed = assets.get_load_state(&playback.track) == LoadState::Failed;
+        if failed {
+            warn!("Music track '{}' failed to load", playback.layer.path());
+        }
+        !failed
+    });
+    let loaded = layers
+        .0
+        .iter()
+        .all(|playback| assets.get_load_state(&playback.track) == LoadState::Loaded);
+    if !loaded {
+        return;
+    }
+    for playback in layers.0.iter_mut() {
+        let sink = audio.play_with_settings(
+            playback.track.clone(),
+            PlaybackSettings::LOOP.with_volume(0.0),
+        );
+        // Strong handle is required to control the playback
+        playback.sink = Some(sinks.get_handle(sink));
+    }
+}
+
+fn update_threat(
+    time: Res<Time>,
+    mut threat: ResMut<ThreatLevel>,
+    mut ev_damage: EventReader<DamageEvent>,
+    player: Query<Entity, With<Player>>,
+    gun_layers: Query<&GunLayer>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+
+    let hits = ev_damage.iter().filter(|ev| ev.target == player).count();
+    let recent_damage = threat.recent_damage + DAMAGE_THREAT * hits as f32;
+    threat.recent_damage =
+        (recent_damage - DAMAGE_THREAT_DECAY * time.delta_seconds()).clamp(0.0, 1.0);
+
+    let targeting = gun_layers
+        .iter()
+        .filter(|gun_layer| gun_layer.target() == Some(player))
+        .count();
+    threat.value = (TARGETING_THREAT * targeting as f32 + threat.recent_damage).min(1.0);
+}
+
+fn crossfade(
+    time: Res<Time>,
+    threat: Res<ThreatLevel>,
+    settings: Res<Settings>,
+    sinks: Res<Assets<AudioSink>>,
+    mut layers: ResMut<MusicLayers>,
+) {
+    let max_step = CROSSFADE_SPEED * time.delta_seconds();
+    for playback in layers.0.iter_mut() {
+        let target = playback.layer.weight(threat.value);
+        playback.weight += (target - playback.weight).clamp(-max_step, max_step);
+
+        // Sink appears only once the track is playing
+        if let Some(sink) = playback.sink.as_ref().and_then(|sink| sinks.get(sink)) {
+            sink.set_volume(playback.weight * settings.music.layer_volume(playback.layer));
+        }
+    }
+}
+
+/// Layered soundtrack, mixed according to the threat to the player. Only the threat is tracked
+/// while there are no music tracks in `assets`.
+pub struct MusicPlugin;
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Settings>()
+            .init_resource::<ThreatLevel>()
+            .add_system(update_threat);
+        if !MusicLayer::ALL.into_iter().any(MusicLayer::shipped) {
+            info!("No music tracks in assets/music, the soundtrack is off");
+            return;
+        }
+        app.add_startup_system(load_music)
+            .add_system(start_music)
+            .add_system(crossfade.after(update_threat));
+    }
+}
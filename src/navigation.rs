@@ -0,0 +1,350 @@
+//! Coarse navigation around large obstacles like the capital ship or the artillery platform.
+//! Obstacles are approximated by their bounding spheres with points around them, which form a
+//! visibility graph together with the start and the goal. The shortest path over it is found by
+//! A*, with the visibility checked by ray casts against the actual colliders. Cores of gravity
+//! wells are avoided the same way, as spheres without colliders.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::{aiming::GunLayer, hazard::GravityWell};
+
+/// Colliders with a smaller bounding sphere, like drones or projectiles, are flown around by
+/// the local steering, in meters
+const MIN_OBSTACLE_RADIUS: f32 = 20.0;
+/// Distance kept from the bounding spheres of obstacles, in meters
+const CLEARANCE: f32 = 20.0;
+/// Graph nodes are pushed out from the bounding sphere, so segments between neighbour nodes
+/// keep the clearance too. Neighbour directions are 55 degrees apart, and 1 / cos(27.5) < 1.15.
+const NODE_SPREAD: f32 = 1.15;
+/// Targets mounted on obstacles are partially inside them, so rays stop this short of the goal,
+/// in meters
+const GOAL_TOLERANCE: f32 = 10.0;
+/// Waypoints closer than this are reached, in meters
+const WAYPOINT_RADIUS: f32 = 30.0;
+/// Seconds between path updates, as obstacles and targets move
+const REPLAN_INTERVAL: f32 = 1.0;
+/// Path updates of navigators spawned at once are spread over this many parts of the interval
+const STAGGER_SLOTS: u32 = 8;
+/// Each path update takes lots of ray casts, so the rest wait for the next frames
+const MAX_REPLANS_PER_FRAME: usize = 4;
+
+/// Graph nodes around an obstacle: towards the faces and the corners of a cube
+const NODE_DIRECTIONS: [Vec3; 14] = [
+    Vec3::X,
+    Vec3::NEG_X,
+    Vec3::Y,
+    Vec3::NEG_Y,
+    Vec3::Z,
+    Vec3::NEG_Z,
+    Vec3::new(1.0, 1.0, 1.0),
+    Vec3::new(1.0, 1.0, -1.0),
+    Vec3::new(1.0, -1.0, 1.0),
+    Vec3::new(1.0, -1.0, -1.0),
+    Vec3::new(-1.0, 1.0, 1.0),
+    Vec3::new(-1.0, 1.0, -1.0),
+    Vec3::new(-1.0, -1.0, 1.0),
+    Vec3::new(-1.0, -1.0, -1.0),
+];
+
+/// Detour around large obstacles towards the target of the `GunLayer` on the same entity.
+/// Empty while the target is in direct sight, or there is no way around.
+#[derive(Component, Default)]
+pub struct NavPath {
+    /// Waypoints to fly through before heading to the target directly
+    waypoints: Vec<Vec3>,
+    /// Seconds left before the next path update, staggered by the entity on the first one
+    replan_in: Option<f32>,
+}
+
+impl NavPath {
+    /// Waypoint to fly to instead of the target, if any
+    pub fn waypoint(&self) -> Option<Vec3> {
+        self.waypoints.first().copied()
+    }
+}
+
+/// Bounding sphere of a large collider
+#[derive(Clone, Copy, Debug)]
+struct Obstacle {
+    center: Vec3,
+    radius: f32,
+}
+
+impl Obstacle {
+    fn from_collider(collider: &Collider, transform: &GlobalTransform) -> Option<Self> {
+        // the shape is already scaled, only its center should be moved
+        let sphere = collider.raw.compute_local_bounding_sphere();
+        if sphere.radius() < MIN_OBSTACLE_RADIUS {
+            return None;
+        }
+        let (_, rotation, translation) = transform.to_scale_rotation_translation();
+        let center = sphere.center();
+        Some(Self {
+            center: translation + rotation * Vec3::new(center.x, center.y, center.z),
+            radius: sphere.radius(),
+        })
+    }
+
+    /// Whether the segment from `a` to `b` might be free of this obstacle: it misses the sphere,
+    /// or starts or ends inside it, like at a target mounted on the obstacle
+    fn might_pass(&self, a: Vec3, b: Vec3) -> bool {
+        self.contains(a) || self.contains(b) || self.misses(a, b)
+    }
+
+    fn contains(&self, point: Vec3) -> bool {
+        point.distance_squared(self.center) < self.radius * self.radius
+    }
+
+    /// Whether the segment from `a` to `b` doesn't cross the sphere
+    fn misses(&self, a: Vec3, b: Vec3) -> bool {
+        let ab = b - a;
+        let t = ((self.center - a).dot(ab) / ab.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+        (a + ab * t).distance(self.center) > self.radius
+    }
+}
+
+/// Shortest path from `start` to `goal` through the nodes around `obstacles`, where `is_clear`
+/// tells whether a straight segment between two points is free. Segments crossing bounding
+/// spheres are never checked, so only few of the node pairs take a ray cast.
+/// Returns the waypoints in between, empty if the goal is in direct sight, or `None` if there
+/// is no way around.
+fn find_path(
+    start: Vec3,
+    goal: Vec3,
+    obstacles: &[Obstacle],
+    mut is_clear: impl FnMut(Vec3, Vec3) -> bool,
+) -> Option<Vec<Vec3>> {
+    if obstacles.iter().all(|o| o.might_pass(start, goal)) && is_clear(start, goal) {
+        return Some(Vec::new());
+    }
+
+    // the start is the first node, and the goal is the second one
+    let mut nodes = vec![start, goal];
+    for obstacle in obstacles {
+        let distance = (obstacle.radius + CLEARANCE) * NODE_SPREAD;
+        nodes.extend(
+            NODE_DIRECTIONS
+                .iter()
+                .map(|direction| obstacle.center + direction.normalize() * distance)
+                .filter(|&node| !obstacles.iter().any(|o| o.contains(node))),
+        );
+    }
+
+    // A* with edges checked only once they are reached, as each check is a ray cast
+    let estimate = |node: usize| nodes[node].distance(goal);
+    let mut cost = vec![f32::INFINITY; nodes.len()];
+    let mut previous = vec![None; nodes.len()];
+    let mut closed = vec![false; nodes.len()];
+    cost[0] = 0.0;
+    loop {
+        let current = (0..nodes.len())
+            .filter(|&node| !closed[node] && cost[node].is_finite())
+            .min_by(|&a, &b| (cost[a] + estimate(a)).total_cmp(&(cost[b] + estimate(b))))?;
+        if current == 1 {
+            break;
+        }
+        closed[current] = true;
+
+        for next in 0..nodes.len() {
+            if closed[next] {
+                continue;
+            }
+            let (a, b) = (nodes[current], nodes[next]);
+            let next_cost = cost[current] + a.distance(b);
+            if next_cost < cost[next]
+                && obstacles.iter().all(|o| o.might_pass(a, b))
+                && is_clear(a, b)
+            {
+                cost[next] = next_cost;
+                previous[next] = Some(current);
+            }
+        }
+    }
+
+    let mut waypoints: Vec<_> = std::iter::successors(previous[1], |&node| previous[node])
+        .take_while(|&node| node != 0)
+        .map(|node| nodes[node])
+        .collect();
+    waypoints.reverse();
+    Some(waypoints)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_path, Obstacle, CLEARANCE};
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_direct_path() {
+        let obstacles = [Obstacle {
+            center: Vec3::new(100.0, 0.0, 0.0),
+            radius: 50.0,
+        }];
+        let is_clear = |a, b| obstacles.iter().all(|o| o.misses(a, b));
+        let path = find_path(Vec3::Z * 200.0, Vec3::NEG_Z * 200.0, &obstacles, is_clear);
+        assert_eq!(path, Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_path_around_obstacle() {
+        let obstacles = [
+            Obstacle {
+                center: Vec3::ZERO,
+                radius: 50.0,
+            },
+            // just a bit aside, so it doesn't block the way on its own
+            Obstacle {
+                center: Vec3::new(300.0, 0.0, 0.0),
+                radius: 50.0,
+            },
+        ];
+        let is_clear = |a, b| obstacles.iter().all(|o| o.misses(a, b));
+        let start = Vec3::Z * 200.0;
+        let goal = Vec3::NEG_Z * 200.0;
+        let path = find_path(start, goal, &obstacles, is_clear).expect("path is found");
+        assert!(!path.is_empty());
+        // waypoints keep the clearance, and no leg crosses obstacles
+        for waypoint in path.iter() {
+            assert!(
+                waypoint.length() >= 50.0 + CLEARANCE,
+                "{waypoint} is too close"
+            );
+        }
+        let points: Vec<_> = std::iter::once(start)
+            .chain(path.iter().copied())
+            .chain(std::iter::once(goal))
+            .collect();
+        for leg in points.windows(2) {
+            assert!(obstacles.iter().all(|o| o.misses(leg[0], leg[1])));
+        }
+    }
+
+    #[test]
+    fn test_blocked_segments_not_cast() {
+        let obstacles = [
+            Obstacle {
+                center: Vec3::ZERO,
+                radius: 50.0,
+            },
+            Obstacle {
+                center: Vec3::new(0.0, 0.0, -300.0),
+                radius: 50.0,
+            },
+        ];
+        let mut casts = 0;
+        let is_clear = |a, b| {
+            casts += 1;
+            assert!(obstacles.iter().all(|o| o.might_pass(a, b)));
+            obstacles.iter().all(|o| o.misses(a, b))
+        };
+        let path = find_path(Vec3::Z * 200.0, Vec3::NEG_Z * 500.0, &obstacles, is_clear);
+        assert!(path.is_some());
+        // far fewer than all pairs of 30 nodes
+        assert!(casts < 30 * 29 / 4, "{casts} casts");
+    }
+
+    #[test]
+    fn test_no_path() {
+        let obstacles = [Obstacle {
+            center: Vec3::ZERO,
+            radius: 50.0,
+        }];
+        // the goal is walled in
+        let is_clear = |_, b: Vec3| b.length() > 1.0;
+        assert_eq!(
+            find_path(Vec3::Z * 200.0, Vec3::ZERO, &obstacles, is_clear),
+            None
+        );
+    }
+}
+
+fn plan_paths(
+    time: Res<Time>,
+    rapier_context: Res<RapierContext>,
+    mut navigators: Query<(Entity, &GlobalTransform, &GunLayer, &mut NavPath)>,
+    targets: Query<&GlobalTransform>,
+    colliders: Query<(&Collider, &GlobalTransform)>,
+    wells: Query<(&GlobalTransform, &GravityWell)>,
+) {
+    // Cores of gravity wells have no colliders, so they are checked apart from the ray casts
+    let hazards: Vec<_> = wells
+        .iter()
+        .filter_map(|(transform, well)| {
+            Some(Obstacle {
+                center: transform.translation(),
+                radius: well.avoid_radius()?,
+            })
+        })
+        .collect();
+    let is_obstacle = |entity: Entity| {
+        colliders.get(entity).map_or(false, |(collider, _)| {
+            collider.raw.compute_local_bounding_sphere().radius() >= MIN_OBSTACLE_RADIUS
+        })
+    };
+    let filter = QueryFilter::new().exclude_sensors().predicate(&is_obstacle);
+    let is_clear = |from: Vec3, to: Vec3| {
+        let distance = from.distance(to);
+        if !hazards.iter().all(|hazard| hazard.misses(from, to)) {
+            return false;
+        }
+        distance <= GOAL_TOLERANCE
+            || rapier_context
+                .cast_ray(
+                    from,
+                    (to - from) / distance,
+                    distance - GOAL_TOLERANCE,
+                    true,
+                    filter,
+                )
+                .is_none()
+    };
+    // Collected once some path is updated, and only once per frame
+    let mut obstacles = None;
+    let mut replans = 0;
+
+    for (entity, transform, gun_layer, mut path) in navigators.iter_mut() {
+        let position = transform.translation();
+        let reached = path.waypoint().map_or(false, |waypoint| {
+            waypoint.distance(position) < WAYPOINT_RADIUS
+        });
+        if reached {
+            path.waypoints.remove(0);
+        }
+
+        // Navigators spawned at once get different first delays, so updates are spread over frames
+        let replan_in = path.replan_in.get_or_insert_with(|| {
+            (entity.index() % STAGGER_SLOTS) as f32 / STAGGER_SLOTS as f32 * REPLAN_INTERVAL
+        });
+        *replan_in -= time.delta_seconds();
+        // Over the budget, the update is due again on the next frame
+        if *replan_in > 0.0 || replans >= MAX_REPLANS_PER_FRAME {
+            continue;
+        }
+        *replan_in = REPLAN_INTERVAL;
+        replans += 1;
+
+        let Some(goal) = gun_layer.target().and_then(|e| targets.get(e).ok()) else {
+            path.waypoints.clear();
+            continue;
+        };
+        let obstacles = obstacles.get_or_insert_with(|| {
+            colliders
+                .iter()
+                .filter_map(|(collider, transform)| Obstacle::from_collider(collider, transform))
+                .chain(hazards.iter().copied())
+                .collect()
+        });
+        // Without a way around, the target is approached directly
+        path.waypoints =
+            find_path(position, goal.translation(), obstacles, is_clear).unwrap_or_default();
+    }
+}
+
+/// Paths around large obstacles for entities with `NavPath`, towards their `GunLayer` targets.
+pub struct NavigationPlugin;
+impl Plugin for NavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(plan_paths.after(crate::aiming::gun_layer));
+    }
+}
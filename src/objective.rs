@@ -0,0 +1,425 @@
+//! King of the hill: objective zones accrue control points every second to the faction with the
+//! most ships inside, and the first faction to reach the score wins. Zones and the scores are
+//! shown on the HUD, while the zones themselves are tinted with the color of their holder.
+
+use bevy::{
+    pbr::{NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+};
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    components::{Fraction, HitPoints},
+    hud::{self, HudAnchor, HudWidget, Indicator, PanelBackground},
+    localization::Localization,
+    player::Player,
+    settings::Settings,
+};
+
+/// Number of cells in the score bars
+const BAR_CELLS: usize = 20;
+/// Opacity of the zone volumes, they only hint where the borders are
+const ZONE_ALPHA: f32 = 0.08;
+
+/// Objectives mode parameters, usually a part of the `Scenario`.
+#[derive(Resource, Deserialize, Clone, Debug, PartialEq)]
+pub struct ObjectivesConfig {
+    pub zones: Vec<ZoneConfig>,
+    /// Control points per second for every held zone
+    pub points_per_second: f32,
+    /// Control points to win
+    pub score_to_win: f32,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct ZoneConfig {
+    /// World position in meters
+    pub position: [f32; 3],
+    pub shape: ZoneShape,
+}
+
+/// Volume of the zone, in meters.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ZoneShape {
+    Sphere { radius: f32 },
+    Box { half_extents: [f32; 3] },
+}
+
+impl ZoneShape {
+    /// Whether the point at the `offset` from the zone center is inside
+    pub fn contains(self, offset: Vec3) -> bool {
+        match self {
+            ZoneShape::Sphere { radius } => offset.length() <= radius,
+            ZoneShape::Box { half_extents } => offset.abs().cmple(half_extents.into()).all(),
+        }
+    }
+
+    fn collider(self) -> Collider {
+        match self {
+            ZoneShape::Sphere { radius } => Collider::ball(radius),
+            ZoneShape::Box {
+                half_extents: [x, y, z],
+            } => Collider::cuboid(x, y, z),
+        }
+    }
+
+    fn mesh(self) -> Mesh {
+        match self {
+            ZoneShape::Sphere { radius } => Mesh::from(shape::UVSphere {
+                radius,
+                sectors: 32,
+                stacks: 16,
+            }),
+            ZoneShape::Box { half_extents } => {
+                let size = Vec3::from(half_extents) * 2.0;
+                Mesh::from(shape::Box::new(size.x, size.y, size.z))
+            }
+        }
+    }
+}
+
+/// Zone held by the faction with the most ships inside.
+#[derive(Component)]
+pub struct ObjectiveZone {
+    /// Label shown on the HUD, like `A`
+    pub name: String,
+    /// Sensor volume. It's not a `Collider` component, so ray casts and projectiles ignore it.
+    sensor: Collider,
+    /// Ships of each faction inside
+    pub ships: Vec<(Fraction, u32)>,
+    pub holder: Option<Fraction>,
+}
+
+/// Emitted once when a faction reaches the score to win.
+pub struct ObjectiveWonEvent(pub Fraction);
+
+/// Control points of each faction and the winner, once there is one.
+#[derive(Resource, Clone, Debug)]
+pub struct Objectives {
+    pub scores: Vec<(Fraction, f32)>,
+    pub winner: Option<Fraction>,
+}
+
+impl Default for Objectives {
+    fn default() -> Self {
+        Self {
+            scores: Fraction::ALL.iter().map(|&f| (f, 0.0)).collect(),
+            winner: None,
+        }
+    }
+}
+
+impl Objectives {
+    /// Adds `points` to the `fraction` score, returns true if it's enough to win
+    fn add(&mut self, fraction: Fraction, points: f32, score_to_win: f32) -> bool {
+        let Some((_, score)) = self.scores.iter_mut().find(|(f, _)| *f == fraction) else {
+            return false;
+        };
+        *score = (*score + points).min(score_to_win);
+        *score >= score_to_win
+    }
+}
+
+/// Faction with the most ships in the zone, `None` for an empty or contested one
+fn holder(ships: &[(Fraction, u32)]) -> Option<Fraction> {
+    let most = ships.iter().map(|(_, count)| *count).max().unwrap_or(0);
+    let mut leaders = ships.iter().filter(|(_, count)| *count == most);
+    match (leaders.next(), leaders.next()) {
+        (Some(&(fraction, _)), None) if most > 0 => Some(fraction),
+        _ => None,
+    }
+}
+
+/// Label of the zone at the `index` in the config: letters from `A` to `Z`, then numbers
+fn zone_label(index: usize) -> String {
+    match u8::try_from(index) {
+        Ok(letter @ 0..=25) => char::from(b'A' + letter).to_string(),
+        _ => (index + 1).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{holder, zone_label, Objectives, ObjectivesConfig, ZoneShape};
+    use crate::components::Fraction;
+
+    #[test]
+    fn test_holder() {
+        assert_eq!(holder(&[]), None);
+        assert_eq!(
+            holder(&[(Fraction::Drones, 0), (Fraction::Turrets, 0)]),
+            None
+        );
+        assert_eq!(
+            holder(&[(Fraction::Drones, 3), (Fraction::Turrets, 1)]),
+            Some(Fraction::Drones)
+        );
+        // contested
+        assert_eq!(
+            holder(&[(Fraction::Drones, 2), (Fraction::Turrets, 2)]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_zone_label() {
+        assert_eq!(zone_label(0), "A");
+        assert_eq!(zone_label(25), "Z");
+        // out of letters
+        assert_eq!(zone_label(26), "27");
+        assert_eq!(zone_label(300), "301");
+    }
+
+    #[test]
+    fn test_scores() {
+        let mut objectives = Objectives::default();
+        assert!(!objectives.add(Fraction::Turrets, 60.0, 100.0));
+        assert!(!objectives.add(Fraction::Drones, 90.0, 100.0));
+        assert!(objectives.add(Fraction::Turrets, 60.0, 100.0));
+        // capped at the score to win
+        assert_eq!(
+            objectives.scores,
+            vec![(Fraction::Drones, 90.0), (Fraction::Turrets, 100.0)]
+        );
+    }
+
+    #[test]
+    fn test_parse_objectives() {
+        let config: ObjectivesConfig = serde_json::from_str(
+            r#"{
+                "zones": [
+                    {"position": [0, 0, 0], "shape": {"sphere": {"radius": 200}}},
+                    {"position": [0, 0, -500], "shape": {"box": {"half_extents": [100, 50, 100]}}}
+                ],
+                "points_per_second": 1,
+                "score_to_win": 300
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.zones[0].shape, ZoneShape::Sphere { radius: 200.0 });
+        assert_eq!(
+            config.zones[1].shape,
+            ZoneShape::Box {
+                half_extents: [100.0, 50.0, 100.0]
+            }
+        );
+    }
+}
+
+fn setup_zones(
+    mut commands: Commands,
+    config: Res<ObjectivesConfig>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (index, zone) in config.zones.iter().enumerate() {
+        let name = zone_label(index);
+        commands
+            .spawn(PbrBundle {
+                mesh: meshes.add(zone.shape.mesh()),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgba(1.0, 1.0, 1.0, ZONE_ALPHA),
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    cull_mode: None,
+                    ..default()
+                }),
+                transform: Transform::from_translation(Vec3::from(zone.position)),
+                ..default()
+            })
+            .insert(NotShadowCaster)
+            .insert(NotShadowReceiver)
+            .insert(Name::new(format!("Objective {name}")))
+            .insert(ObjectiveZone {
+                name,
+                sensor: zone.shape.collider(),
+                ships: Vec::new(),
+                holder: None,
+            });
+    }
+}
+
+/// Counts ships of each faction inside zones, each ship once however many colliders it has
+fn count_ships(
+    rapier_context: Res<RapierContext>,
+    mut zones: Query<(&mut ObjectiveZone, &GlobalTransform)>,
+    ships: Query<(&Fraction, &HitPoints)>,
+    parent_query: Query<&Parent>,
+    mut inside: Local<Vec<Entity>>,
+) {
+    let filter = QueryFilter::new().exclude_sensors();
+    for (mut zone, transform) in zones.iter_mut() {
+        let (_, rotation, translation) = transform.to_scale_rotation_translation();
+        inside.clear();
+        rapier_context.intersections_with_shape(
+            translation,
+            rotation,
+            &zone.sensor,
+            filter,
+            |entity| {
+                let root = parent_query.iter_ancestors(entity).last().unwrap_or(entity);
+                if !inside.contains(&root) {
+                    inside.push(root);
+                }
+                true
+            },
+        );
+
+        let counts: Vec<_> = Fraction::ALL
+            .iter()
+            .map(|&fraction| {
+                let count = inside
+                    .iter()
+                    .filter_map(|&e| ships.get(e).ok())
+                    .filter(|(f, hp)| **f == fraction && !hp.dead())
+                    .count();
+                (fraction, count as u32)
+            })
+            .collect();
+        if zone.ships != counts {
+            zone.holder = holder(&counts);
+            zone.ships = counts;
+        }
+    }
+}
+
+fn update_scores(
+    time: Res<Time>,
+    config: Res<ObjectivesConfig>,
+    mut objectives: ResMut<Objectives>,
+    zones: Query<&ObjectiveZone>,
+    mut ev_won: EventWriter<ObjectiveWonEvent>,
+) {
+    if objectives.winner.is_some() {
+        return;
+    }
+    let points = config.points_per_second * time.delta_seconds();
+    for holder in zones.iter().filter_map(|zone| zone.holder) {
+        if objectives.add(holder, points, config.score_to_win) {
+            info!("Objectives are won by {holder:?}");
+            objectives.winner = Some(holder);
+            ev_won.send(ObjectiveWonEvent(holder));
+            break;
+        }
+    }
+}
+
+/// Tints zones with the color of their holders
+fn color_zones(
+    settings: Res<Settings>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    zones: Query<(&ObjectiveZone, &Handle<StandardMaterial>), Changed<ObjectiveZone>>,
+    player: Query<&Fraction, With<Player>>,
+) {
+    let own = player.get_single().ok().copied();
+    for (zone, handle) in zones.iter() {
+        let mut color = match zone.holder {
+            Some(holder) => settings.accessibility.faction_color(holder, own),
+            None => Color::WHITE,
+        };
+        color.set_a(ZONE_ALPHA);
+        if let Some(material) = materials.get_mut(handle) {
+            material.base_color = color;
+        }
+    }
+}
+
+#[derive(Component)]
+struct ObjectivesText;
+
+fn setup_objectives_hud(mut commands: Commands, assets: Res<AssetServer>) {
+    let background = Color::rgba(0.1, 0.1, 0.1, 0.6);
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            background_color: background.into(),
+            ..default()
+        })
+        .insert(HudWidget::essential(HudAnchor::TopLeft))
+        .insert(PanelBackground(background))
+        .insert(Name::new("Objectives"))
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: assets.load("fonts/FiraMono-Medium.ttf"),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                ))
+                .insert(ObjectivesText);
+        });
+}
+
+fn update_objectives_hud(
+    config: Res<ObjectivesConfig>,
+    objectives: Res<Objectives>,
+    localization: Res<Localization>,
+    settings: Res<Settings>,
+    zones: Query<&ObjectiveZone>,
+    player: Query<&Fraction, With<Player>>,
+    mut text: Query<&mut Text, With<ObjectivesText>>,
+) {
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+
+    let mut lines = vec![localization.get("objectives-title").to_owned()];
+    for &(fraction, score) in objectives.scores.iter() {
+        let bar = hud::text_bar(score / config.score_to_win, BAR_CELLS, '#');
+        lines.push(localization.format(
+            "objectives-score",
+            &[
+                ("faction", &localization.get(fraction.key())),
+                ("bar", &bar),
+                ("score", &(score as u32)),
+                ("goal", &(config.score_to_win as u32)),
+            ],
+        ));
+    }
+    for zone in zones.iter() {
+        let holder = match zone.holder {
+            Some(holder) => localization.get(holder.key()).to_owned(),
+            None if zone.ships.iter().any(|(_, count)| *count > 0) => {
+                localization.get("objectives-contested").to_owned()
+            }
+            None => localization.get("objectives-neutral").to_owned(),
+        };
+        lines.push(localization.format(
+            "objectives-zone",
+            &[("zone", &zone.name), ("holder", &holder)],
+        ));
+    }
+    if let Some(winner) = objectives.winner {
+        let faction = localization.get(winner.key());
+        lines.push(localization.format("objectives-won", &[("faction", &faction)]));
+        let indicator = if player.get_single().ok() == Some(&winner) {
+            Indicator::Good
+        } else {
+            Indicator::Danger
+        };
+        text.sections[0].style.color = settings.accessibility.color(indicator);
+    }
+    text.sections[0].value = lines.join("\n");
+}
+
+/// Objective zones from the `ObjectivesConfig` resource, with the scores shown on the HUD.
+pub struct ObjectivePlugin;
+impl Plugin for ObjectivePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Objectives>()
+            .add_event::<ObjectiveWonEvent>()
+            .add_startup_system(setup_zones)
+            .add_startup_system(setup_objectives_hud)
+            .add_system(count_ships)
+            .add_system(update_scores.after(count_ships))
+            .add_system(color_zones.after(count_ships))
+            .add_system(update_objectives_hud.after(update_scores));
+    }
+}
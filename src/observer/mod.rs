@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+use crate::projectile::{DamageEvent, DamageSystems};
+
+mod overlay;
+
+/// Hits older than this are not considered as current action, in seconds
+const ACTION_WINDOW: f32 = 5.0;
+/// Max number of remembered hits, the oldest ones are forgotten first
+const MAX_ACTIONS: usize = 512;
+/// Hits within this distance belong to the same engagement, in meters
+const ENGAGEMENT_RADIUS: f32 = 200.0;
+/// Destruction is way more interesting than a single hit
+const DESTRUCTION_WEIGHT: f32 = 10.0;
+/// Seconds between looking for the hottest spot, so the camera doesn't jump between engagements
+const RETARGET_INTERVAL: f32 = 1.0;
+/// Camera keeps this distance from the focus point, in meters
+const CAMERA_DISTANCE: f32 = 250.0;
+/// Rate at which the focus point catches up with the action, 1/s
+const FOLLOW_RATE: f32 = 1.0;
+/// Camera slowly flies around the focus point, rad/s
+const ORBIT_SPEED: f32 = 0.1;
+
+/// Camera that follows the action on its own, when there is no player.
+#[derive(Component, Default)]
+struct ObserverCamera {
+    focus: Vec3,
+    /// Point the focus is moving to
+    target: Vec3,
+    orbit_angle: f32,
+}
+
+/// Recent hit, weighted by how interesting it is
+struct Action {
+    time: f32,
+    position: Vec3,
+    weight: f32,
+}
+
+/// Weighted center of the engagement with the largest total weight of `actions`
+fn hottest_spot<'a>(actions: impl Iterator<Item = &'a Action> + Clone) -> Option<Vec3> {
+    let engagement = |center: Vec3| {
+        actions
+            .clone()
+            .filter(move |a| a.position.distance_squared(center) < ENGAGEMENT_RADIUS.powi(2))
+    };
+
+    let (center, _) = actions
+        .clone()
+        .map(|action| {
+            let weight: f32 = engagement(action.position).map(|a| a.weight).sum();
+            (action.position, weight)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    let (sum, weight) = engagement(center).fold((Vec3::ZERO, 0.0), |(sum, weight), a| {
+        (sum + a.position * a.weight, weight + a.weight)
+    });
+    Some(sum / weight)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hottest_spot, Action};
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_hottest_spot() {
+        let action = |x, weight| Action {
+            time: 0.0,
+            position: Vec3::new(x, 0.0, 0.0),
+            weight,
+        };
+        assert_eq!(hottest_spot(std::iter::empty()), None);
+
+        // two small hits are outweighted by a destruction far away from them
+        let actions = [action(0.0, 1.0), action(10.0, 1.0), action(1000.0, 10.0)];
+        assert_eq!(
+            hottest_spot(actions.iter()),
+            Some(Vec3::new(1000.0, 0.0, 0.0))
+        );
+
+        // weighted center of the engagement
+        let actions = [action(0.0, 1.0), action(100.0, 3.0), action(1000.0, 1.0)];
+        assert_eq!(
+            hottest_spot(actions.iter()),
+            Some(Vec3::new(75.0, 0.0, 0.0))
+        );
+    }
+}
+
+fn setup_camera(mut commands: Commands) {
+    commands
+        .spawn(Camera3dBundle::default())
+        .insert(ObserverCamera::default())
+        .insert(Name::new("Observer"));
+}
+
+fn follow_action(
+    time: Res<Time>,
+    mut ev_damage: EventReader<DamageEvent>,
+    mut camera: Query<(&mut Transform, &mut ObserverCamera)>,
+    mut actions: Local<VecDeque<Action>>,
+    mut retarget_in: Local<f32>,
+) {
+    let Ok((mut transform, mut camera)) = camera.get_single_mut() else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+
+    actions.extend(ev_damage.iter().map(|ev| Action {
+        time: now,
+        position: ev.position,
+        weight: if ev.destroyed {
+            DESTRUCTION_WEIGHT
+        } else {
+            1.0
+        },
+    }));
+    while actions.len() > MAX_ACTIONS
+        || matches!(actions.front(), Some(a) if now - a.time > ACTION_WINDOW)
+    {
+        actions.pop_front();
+    }
+
+    *retarget_in -= time.delta_seconds();
+    if *retarget_in <= 0.0 {
+        *retarget_in = RETARGET_INTERVAL;
+        // Keep looking at the last engagement while nothing happens
+        if let Some(spot) = hottest_spot(actions.iter()) {
+            camera.target = spot;
+        }
+    }
+
+    let delta = time.delta_seconds();
+    camera.focus = camera
+        .focus
+        .lerp(camera.target, (FOLLOW_RATE * delta).min(1.0));
+    camera.orbit_angle += ORBIT_SPEED * delta;
+
+    let offset = Quat::from_rotation_y(camera.orbit_angle) * Vec3::new(0.0, 0.3, 1.0).normalize();
+    *transform = Transform::from_translation(camera.focus + offset * CAMERA_DISTANCE)
+        .looking_at(camera.focus, Vec3::Y);
+}
+
+/// Replaces the player with a camera that follows the most intense engagement,
+/// and shows per-faction battle statistics.
+pub struct ObserverPlugin;
+impl Plugin for ObserverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(overlay::OverlayPlugin)
+            .add_startup_system(setup_camera)
+            .add_system(follow_action.after(DamageSystems));
+    }
+}
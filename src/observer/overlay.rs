@@ -0,0 +1,203 @@
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+use crate::{
+    aiming::GunLayer,
+    components::Fraction,
+    hud::{HudAnchor, HudWidget},
+    localization::Localization,
+    projectile::{DamageEvent, DamageSystems},
+};
+
+/// Damage dealt during this time is used for DPS, in seconds
+const DPS_WINDOW: f32 = 5.0;
+/// Seconds between unit count samples
+const HISTORY_INTERVAL: f32 = 5.0;
+/// Number of unit count samples in the graph
+const HISTORY_SIZE: usize = 24;
+const GRAPH_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Battle statistics of a single faction.
+/// Units are armed entities of the faction, i.e. ones with a `GunLayer`.
+#[derive(Default)]
+struct FactionStats {
+    units: usize,
+    losses: u32,
+    /// Recent damage dealt by the faction with the time it was dealt
+    damage: VecDeque<(f32, u32)>,
+    /// Unit count sampled every `HISTORY_INTERVAL`
+    history: VecDeque<usize>,
+}
+
+impl FactionStats {
+    fn record_damage(&mut self, now: f32, damage: u32) {
+        self.damage.push_back((now, damage));
+        while matches!(self.damage.front(), Some(&(time, _)) if now - time > DPS_WINDOW) {
+            self.damage.pop_front();
+        }
+    }
+
+    /// Damage per second over the last `DPS_WINDOW`
+    fn dps(&self, now: f32) -> f32 {
+        let damage: u32 = self
+            .damage
+            .iter()
+            .filter(|(time, _)| now - time <= DPS_WINDOW)
+            .map(|(_, damage)| damage)
+            .sum();
+        damage as f32 / DPS_WINDOW
+    }
+
+    fn sample_history(&mut self) {
+        if self.history.len() == HISTORY_SIZE {
+            self.history.pop_front();
+        }
+        self.history.push_back(self.units);
+    }
+}
+
+/// Text graph like `▁▃▅█` of `values`, scaled to the largest one
+fn graph(values: impl Iterator<Item = usize> + Clone) -> String {
+    let max = values.clone().max().unwrap_or_default().max(1);
+    values
+        .map(|value| GRAPH_LEVELS[value * (GRAPH_LEVELS.len() - 1) / max])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{graph, FactionStats, DPS_WINDOW};
+
+    #[test]
+    fn test_dps() {
+        let mut stats = FactionStats::default();
+        assert_eq!(stats.dps(0.0), 0.0);
+        stats.record_damage(0.0, 50);
+        stats.record_damage(1.0, 50);
+        assert_eq!(stats.dps(1.0), 100.0 / DPS_WINDOW);
+        // old damage doesn't count anymore
+        assert_eq!(stats.dps(DPS_WINDOW + 0.5), 50.0 / DPS_WINDOW);
+    }
+
+    #[test]
+    fn test_graph() {
+        assert_eq!(graph(std::iter::empty()), "");
+        assert_eq!(graph([0, 7, 14].into_iter()), "▁▄█");
+        assert_eq!(graph([0, 0].into_iter()), "▁▁");
+    }
+}
+
+#[derive(Resource, Default)]
+struct BattleStats([FactionStats; 2]);
+
+impl BattleStats {
+    fn faction(&mut self, fraction: Fraction) -> &mut FactionStats {
+        &mut self.0[fraction as usize]
+    }
+}
+
+#[derive(Component)]
+struct OverlayText;
+
+fn setup_overlay(mut commands: Commands, assets: Res<AssetServer>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            background_color: Color::rgba(0.1, 0.1, 0.1, 0.6).into(),
+            ..default()
+        })
+        .insert(HudWidget::essential(HudAnchor::TopRight))
+        .insert(Name::new("Battle Statistics"))
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: assets.load("fonts/FiraMono-Medium.ttf"),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                ))
+                .insert(OverlayText);
+        });
+}
+
+fn track_damage(
+    time: Res<Time>,
+    mut stats: ResMut<BattleStats>,
+    mut ev_damage: EventReader<DamageEvent>,
+    fractions: Query<&Fraction>,
+    units: Query<(), With<GunLayer>>,
+) {
+    let now = time.elapsed_seconds();
+    for ev in ev_damage.iter() {
+        if let Some(&fraction) = ev.attacker.and_then(|e| fractions.get(e).ok()) {
+            stats.faction(fraction).record_damage(now, ev.damage);
+        }
+        if let Ok(&fraction) = fractions.get(ev.target) {
+            if ev.destroyed && units.contains(ev.target) {
+                stats.faction(fraction).losses += 1;
+            }
+        }
+    }
+}
+
+fn count_units(
+    time: Res<Time>,
+    mut stats: ResMut<BattleStats>,
+    units: Query<&Fraction, With<GunLayer>>,
+    mut sample_in: Local<f32>,
+) {
+    for fraction in Fraction::ALL {
+        stats.faction(fraction).units = units.iter().filter(|&&f| f == fraction).count();
+    }
+
+    *sample_in -= time.delta_seconds();
+    if *sample_in <= 0.0 {
+        *sample_in = HISTORY_INTERVAL;
+        stats.0.iter_mut().for_each(FactionStats::sample_history);
+    }
+}
+
+fn update_overlay(
+    time: Res<Time>,
+    stats: Res<BattleStats>,
+    localization: Res<Localization>,
+    mut text: Query<&mut Text, With<OverlayText>>,
+) {
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+
+    let mut lines = vec![localization.get("observer-title").to_owned()];
+    for fraction in Fraction::ALL {
+        let faction = &stats.0[fraction as usize];
+        lines.push(localization.format(
+            "observer-faction",
+            &[
+                ("faction", &localization.get(fraction.key())),
+                ("units", &faction.units),
+                ("dps", &format!("{:.0}", faction.dps(now))),
+                ("losses", &faction.losses),
+            ],
+        ));
+        lines.push(graph(faction.history.iter().copied()));
+    }
+    text.sections[0].value = lines.join("\n");
+}
+
+/// Per-faction unit counts, DPS and losses over time.
+pub(super) struct OverlayPlugin;
+impl Plugin for OverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BattleStats>()
+            .add_startup_system(setup_overlay)
+            .add_system(track_damage.after(DamageSystems))
+            .add_system(count_units)
+            .add_system(update_overlay.after(track_damage).after(count_units));
+    }
+}
@@ -0,0 +1,217 @@
+//! Planets and moons far beyond the battlefield, placed on the sky by the `Scenario`. They are
+//! drawn by `PlanetMaterial`, lit from the sun direction of the environment with a soft
+//! terminator between the day and the night side and the atmosphere glowing at the rim.
+//! Like the skybox, they are kept around the camera, so flying never brings them any closer, and
+//! they are drawn behind everything else regardless of the depth, so they never hide a ship.
+
+use bevy::{
+    pbr::{MaterialPipeline, MaterialPipelineKey, NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        mesh::MeshVertexBufferLayout,
+        render_resource::{
+            AsBindGroup, CompareFunction, RenderPipelineDescriptor, ShaderRef,
+            SpecializedMeshPipelineError,
+        },
+        view::NoFrustumCulling,
+    },
+};
+use serde::Deserialize;
+
+use crate::{
+    scenario::Scenario,
+    skybox::{Distant, SkyboxPlugin},
+};
+
+/// Planets are this far from the camera, within the skybox, in meters
+const PLANET_DISTANCE: f32 = 4500.0;
+/// Bigger planets would come too close to the battlefield, in degrees
+const MAX_PLANET_SIZE: f32 = 60.0;
+/// Planets are sorted by depth in the opaque pass, shifted by this much per planet to be drawn
+/// one after another before the battlefield. Planets are never this far apart in depth, in meters
+const PLANET_DEPTH_BIAS: f32 = 2.0 * PLANET_DISTANCE;
+
+/// Planet or moon on the sky, a part of the `Scenario`.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct PlanetConfig {
+    pub name: String,
+    /// Direction from the battlefield to the center of the body
+    pub direction: [f32; 3],
+    /// Angular diameter on the sky, in degrees, up to `MAX_PLANET_SIZE`
+    pub size: f32,
+    /// Equirectangular map of the surface in `assets`, the surface is plain `color` without one
+    pub texture: Option<String>,
+    /// sRGB color of the surface, tinting the texture
+    pub color: [f32; 3],
+    /// sRGB color of the atmosphere glowing at the rim, airless moons have none
+    pub atmosphere: Option<[f32; 3]>,
+}
+
+impl Default for PlanetConfig {
+    fn default() -> Self {
+        Self {
+            name: String::from("Planet"),
+            direction: [0.0, -0.5, -1.0],
+            size: 30.0,
+            texture: None,
+            color: [0.4, 0.5, 0.6],
+            atmosphere: Some([0.4, 0.6, 1.0]),
+        }
+    }
+}
+
+impl PlanetConfig {
+    /// Transform of the unit sphere looking the `size` on the sky, relative to the camera
+    pub fn transform(&self) -> Transform {
+        let direction = Vec3::from(self.direction)
+            .try_normalize()
+            .unwrap_or(Vec3::NEG_Z);
+        let half_size = self.size.clamp(0.0, MAX_PLANET_SIZE).to_radians() / 2.0;
+        Transform::from_translation(direction * PLANET_DISTANCE)
+            .with_scale(Vec3::splat(PLANET_DISTANCE * half_size.sin()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PlanetConfig, PLANET_DISTANCE};
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_transform() {
+        let planet = PlanetConfig {
+            direction: [0.0, -2.0, 0.0],
+            size: 60.0,
+            ..PlanetConfig::default()
+        };
+        let transform = planet.transform();
+        let center = Vec3::NEG_Y * PLANET_DISTANCE;
+        assert!(transform.translation.distance(center) < 1e-2);
+        // the edge is seen at 30 degrees off the center
+        assert!((transform.scale.x - PLANET_DISTANCE / 2.0).abs() < 1e-2);
+
+        // too big to stay out of the battlefield
+        let huge = PlanetConfig {
+            size: 170.0,
+            ..PlanetConfig::default()
+        };
+        assert!((huge.transform().scale.x - transform.scale.x).abs() < 1e-2);
+        let nowhere = PlanetConfig {
+            direction: [0.0; 3],
+            ..PlanetConfig::default()
+        };
+        assert!(nowhere.transform().translation.z < 0.0);
+    }
+}
+
+/// Surface lit by the sun with a night side and a glowing rim of the atmosphere.
+#[derive(AsBindGroup, TypeUuid, Clone, Debug)]
+#[uuid = "e7a2c5d1-4b8f-4f63-8d19-3a6b0f2c9e57"]
+pub struct PlanetMaterial {
+    #[uniform(0)]
+    pub color: Color,
+    /// Transparent for bodies without atmosphere
+    #[uniform(0)]
+    pub atmosphere: Color,
+    /// Towards the sun in world space
+    #[uniform(0)]
+    pub sun_direction: Vec3,
+    #[texture(1)]
+    #[sampler(2)]
+    pub texture: Option<Handle<Image>>,
+    /// Draw order in the opaque pass, higher goes first
+    pub depth_bias: f32,
+}
+
+impl Material for PlanetMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/planet.wgsl".into()
+    }
+
+    fn depth_bias(&self) -> f32 {
+        self.depth_bias
+    }
+
+    // Drawn over the skybox and under the ships, whatever the depth
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayout,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        if let Some(depth_stencil) = descriptor.depth_stencil.as_mut() {
+            depth_stencil.depth_write_enabled = false;
+            depth_stencil.depth_compare = CompareFunction::Always;
+        }
+        Ok(())
+    }
+}
+
+fn spawn_planets(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<PlanetMaterial>>,
+    scenario: Option<Res<Scenario>>,
+) {
+    let Some(scenario) = scenario.filter(|scenario| !scenario.planets.is_empty()) else {
+        return;
+    };
+    let sun_direction = Vec3::from(scenario.environment.sun_direction).normalize_or_zero();
+    let mesh = meshes.add(Mesh::from(shape::UVSphere {
+        radius: 1.0,
+        sectors: 64,
+        stacks: 32,
+    }));
+
+    commands
+        .spawn(SpatialBundle::default())
+        .insert(Distant)
+        .insert(Name::new("Planets"))
+        .with_children(|parent| {
+            for (index, planet) in scenario.planets.iter().enumerate() {
+                let [r, g, b] = planet.color;
+                let atmosphere = planet
+                    .atmosphere
+                    .map_or(Color::NONE, |[r, g, b]| Color::rgb(r, g, b));
+                let material = PlanetMaterial {
+                    color: Color::rgb(r, g, b),
+                    atmosphere,
+                    sun_direction,
+                    texture: planet
+                        .texture
+                        .as_deref()
+                        .map(|path| asset_server.load(path)),
+                    // Later planets are drawn over the earlier ones
+                    depth_bias: PLANET_DEPTH_BIAS * (scenario.planets.len() - index) as f32,
+                };
+                parent
+                    .spawn(MaterialMeshBundle {
+                        mesh: mesh.clone(),
+                        material: materials.add(material),
+                        transform: planet.transform(),
+                        ..default()
+                    })
+                    // Far beyond the far plane the frustum is culled at
+                    .insert(NoFrustumCulling)
+                    .insert(NotShadowCaster)
+                    .insert(NotShadowReceiver)
+                    .insert(Name::new(planet.name.clone()));
+            }
+        });
+}
+
+/// Planets and moons of the `Scenario` drawn with `PlanetMaterial`.
+pub struct PlanetPlugin;
+impl Plugin for PlanetPlugin {
+    fn build(&self, app: &mut App) {
+        // Keeps planets around the camera
+        if !app.is_plugin_added::<SkyboxPlugin>() {
+            app.add_plugin(SkyboxPlugin);
+        }
+        app.add_plugin(MaterialPlugin::<PlanetMaterial>::default())
+            .add_startup_system(spawn_planets);
+    }
+}
@@ -1,204 +1,752 @@
-use bevy::{input::mouse::MouseWheel, pbr::wireframe, prelude::*, render::camera};
+use bevy::{
+    ecs::schedule::ShouldRun, input::mouse::MouseWheel, pbr::wireframe, prelude::*, render::camera,
+};
 use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
 
-use crate::{gun, projectile::HitPoints, weapon};
+use crate::{
+    aiming,
+    collision_warning::CollisionWarning,
+    components,
+    gun::{self, Projectile},
+    ion_storm::{self, InIonStorm},
+    modifier::Modifiers,
+    prefab::{PrefabPlugin, ShipBundle},
+    scenario::Scenario,
+    settings::Settings,
+    ship_switch,
+};
 
+/// Player's ship, with its collider, hit points and weapons as children.
 #[derive(Component)]
-struct Player;
+pub struct Player;
 
+/// Camera rig following the player's ship, a separate entity from the ship itself.
 #[derive(Component)]
-struct PrimaryWeapon;
+pub struct PlayerCamera {
+    /// Ship the camera follows
+    pub target: Entity,
+    /// Camera position in the ship frame, the first-person view from the ship's center by default
+    pub offset: Vec3,
+}
 
+/// Hull of the ship the player spawned in, hidden while the camera is inside of it.
 #[derive(Component)]
-struct SecondaryWeapon;
+struct PlayerModel;
 
-fn setup_player(mut commands: Commands) {
-    // Create a player entity with a camera
-    commands
-        .spawn(Camera3dBundle {
-            transform: Transform::from_xyz(0.0, 0.0, 10.0),
-            ..default()
-        })
-        .insert(Player)
-        .insert(Name::new("Player"))
-        .with_children(|parent| {
-            let rate_of_fire = 6.7;
-            parent.spawn((
-                PrimaryWeapon,
-                weapon::MachineGun::new(rate_of_fire),
-                TransformBundle::from(Transform::from_translation(-Vec3::Z + 0.2 * Vec3::X)),
-            ));
-            parent.spawn((
-                PrimaryWeapon,
-                weapon::MachineGun::new(rate_of_fire),
-                TransformBundle::from(Transform::from_translation(-Vec3::Z - 0.2 * Vec3::X)),
-            ));
-            parent.spawn((
-                PrimaryWeapon,
-                weapon::MachineGun::new(rate_of_fire),
-                TransformBundle::from(Transform::from_translation(-Vec3::Z - 0.2 * Vec3::Y)),
-            ));
-
-            parent.spawn((
-                SecondaryWeapon,
-                weapon::RocketLauncher::new(rate_of_fire),
-                TransformBundle::from(Transform::from_translation(-Vec3::Z)),
-            ));
-        });
+/// Requested forward speed as a fraction of the maximum one, negative for moving backward
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Throttle(pub f32);
+
+/// Requested forward speed maintained by the flight model in m/s, negative for moving backward.
+/// Set with R to the current speed or with V to the locked target's one and adjusted with X/Z.
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct Cruise(pub Option<f32>);
+
+/// Player speed in m/s with the boost
+pub(crate) const MAX_SPEED: f32 = 100.0;
+/// Angular speed limit of the decoupled flight, in rad/s
+const MAX_SPIN: f32 = std::f32::consts::TAU;
+/// Acceleration of the decoupled flight as a fraction of the commanded speed per second
+const THRUST_RESPONSE: f32 = 0.5;
+/// Angular acceleration of the decoupled flight as a fraction of the commanded rate per second
+const SPIN_RESPONSE: f32 = 1.0;
+
+/// Rockets per launcher, refilled in the shop
+const ROCKET_AMMO: u32 = 60;
+/// The ship is considered moving along its axis faster than this, in m/s
+const ACTION_SPEED: f32 = 1.0;
+
+/// Flight assist mode, toggled with C.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub enum FlightAssist {
+    /// Arcade flight, rotation and drift are counteracted as soon as the controls are released
+    #[default]
+    Coupled,
+    /// Newtonian flight, controls accelerate the ship and it keeps moving and spinning until
+    /// countered manually
+    Decoupled,
+}
+
+impl FlightAssist {
+    fn next(self) -> Self {
+        match self {
+            FlightAssist::Coupled => FlightAssist::Decoupled,
+            FlightAssist::Decoupled => FlightAssist::Coupled,
+        }
+    }
+
+    /// New linear or angular velocity for the `command`ed one. Coupled flight follows it right
+    /// away, while decoupled accelerates by `response` of it per second up to the `max` speed.
+    fn velocity(self, current: Vec3, command: Vec3, response: f32, max: f32, dt: f32) -> Vec3 {
+        match self {
+            FlightAssist::Coupled => command,
+            FlightAssist::Decoupled => (current + command * response * dt).clamp_length_max(max),
+        }
+    }
 }
 
 #[derive(Component)]
-struct ConsoleText;
+pub struct PrimaryWeapon;
 
-fn setup_hud(mut commands: Commands, assets: Res<AssetServer>) {
-    // root UI node that covers all screen
-    commands
-        .spawn(NodeBundle {
-            style: Style {
-                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                align_items: AlignItems::Center, // vertical alignment
-                justify_content: JustifyContent::Center, // horizontal alignment
-                ..default()
-            },
-            background_color: Color::NONE.into(),
-            ..default()
+#[derive(Component)]
+pub struct SecondaryWeapon;
+
+#[derive(Component)]
+pub struct HeavyWeapon;
+
+/// Pointer state of the debug UI, so the player doesn't steer or zoom while interacting with it.
+/// Updated by `devtools` and always free without them.
+#[derive(Resource, Default)]
+pub struct UiPointer {
+    pub wants_input: bool,
+    pub is_used: bool,
+    /// The cursor is over a clickable part of the HUD, like the cockpit radar, which takes clicks
+    pub over_hud: bool,
+}
+
+/// How the cursor offset from the screen center is mapped to the turn rate.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ResponseCurve {
+    #[default]
+    Linear,
+    /// Gentle near the center for fine aiming, reaching the linear rate at the screen edge
+    Exponential,
+}
+
+/// Mouse steering, applied to both the mouse guidance (toggled by Space) and dragging with LMB.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MouseSettings {
+    /// Turn rate in degrees per frame for each pixel of the cursor offset
+    pub sensitivity: f32,
+    /// Radius around the screen center where the cursor doesn't turn the ship, in pixels
+    pub deadzone: f32,
+    pub curve: ResponseCurve,
+    pub invert_x: bool,
+    pub invert_y: bool,
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        Self {
+            sensitivity: 0.005,
+            deadzone: 20.0,
+            curve: ResponseCurve::default(),
+            invert_x: false,
+            invert_y: false,
+        }
+    }
+}
+
+impl MouseSettings {
+    /// Yaw and pitch in radians for the cursor `offset` from the screen center, where the Y axis
+    /// is up. `edge` is the distance from the center to the screen edge in pixels.
+    fn turn(&self, offset: Vec2, edge: f32) -> Vec2 {
+        let distance = offset.length();
+        if distance <= self.deadzone {
+            return Vec2::ZERO;
+        }
+        let response = match self.curve {
+            ResponseCurve::Linear => distance,
+            ResponseCurve::Exponential => distance * (distance / edge.max(1.0)).min(1.0),
+        };
+        // cursor to the left turns the ship left, so the yaw is positive
+        let mut turn = Vec2::new(-offset.x, offset.y) / distance * response;
+        if self.invert_x {
+            turn.x = -turn.x;
+        }
+        if self.invert_y {
+            turn.y = -turn.y;
+        }
+        (turn * self.sensitivity).to_radians()
+    }
+}
+
+/// Flight control options, so the ship handles like in other sims the player is used to.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct FlightSettings {
+    /// Lateral strafe circles around the primary locked target, keeping the distance and the nose
+    /// on it. Toggled with O.
+    pub orbit_strafe: bool,
+    /// Yaw added per unit of the commanded roll, negative to yaw away from the roll
+    pub roll_to_yaw: f32,
+    /// Roll added per unit of the commanded yaw, banking into turns
+    pub yaw_to_roll: f32,
+}
+
+impl FlightSettings {
+    /// Couples the roll and the yaw of the commanded `spin`: X - pitch, Y - yaw, Z - roll
+    fn couple(&self, spin: Vec3) -> Vec3 {
+        Vec3::new(
+            spin.x,
+            spin.y + spin.z * self.roll_to_yaw,
+            spin.z + spin.y * self.yaw_to_roll,
+        )
+    }
+}
+
+/// Rotation around the `target` moving a ship at the `position` by the `distance` in meters to
+/// its `right`, keeping the distance to the target. `None` if the target is right on the way.
+fn orbit(position: Vec3, target: Vec3, right: Vec3, distance: f32) -> Option<Quat> {
+    let offset = position - target;
+    let axis = offset.cross(right).try_normalize()?;
+    Some(Quat::from_axis_angle(axis, distance / offset.length()))
+}
+
+/// Player's team, spawn point and loadout, a part of the `Scenario`.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct PlayerConfig {
+    pub team: components::Fraction,
+    /// Name of the scenario spawn point, the player starts next to the capital ship without one
+    pub spawn_point: Option<String>,
+    pub loadout: LoadoutConfig,
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        Self {
+            team: components::Fraction::Turrets,
+            spawn_point: None,
+            loadout: LoadoutConfig::default(),
+        }
+    }
+}
+
+/// Weapons the player's ship starts with, out of the hardpoints of its prefab.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct LoadoutConfig {
+    /// Primary weapons, up to 3
+    pub machine_guns: usize,
+    /// Rockets per launcher, no launchers without them
+    pub rockets: u32,
+    pub railgun: bool,
+}
+
+impl Default for LoadoutConfig {
+    fn default() -> Self {
+        Self {
+            machine_guns: 3,
+            rockets: ROCKET_AMMO,
+            railgun: true,
+        }
+    }
+}
+
+/// Something the player does with the ship, reported for tutorials.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayerAction {
+    /// Moving forward or backward
+    Move,
+    /// Moving sideways, up or down
+    Strafe,
+    MouseGuidance,
+    LockTarget,
+    FirePrimary,
+    FireSecondary,
+    FireHeavy,
+}
+
+/// Emitted once the player starts doing something, or for every salvo fired.
+pub struct PlayerActionEvent(pub PlayerAction);
+
+/// Movement of the ship with the `velocity` in its own frame
+fn movement_actions(velocity: Vec3) -> Vec<PlayerAction> {
+    let mut actions = Vec::new();
+    if velocity.z.abs() > ACTION_SPEED {
+        actions.push(PlayerAction::Move);
+    }
+    if velocity.x.abs() > ACTION_SPEED || velocity.y.abs() > ACTION_SPEED {
+        actions.push(PlayerAction::Strafe);
+    }
+    actions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        movement_actions, orbit, FlightAssist, FlightSettings, MouseSettings, PlayerAction,
+        ResponseCurve, TargetQueue, MAX_LOCKED_TARGETS,
+    };
+    use bevy::prelude::{Entity, Vec2, Vec3};
+
+    #[test]
+    fn test_mouse_turn() {
+        let mut settings = MouseSettings::default();
+        assert_eq!(settings.turn(Vec2::new(10.0, 10.0), 500.0), Vec2::ZERO);
+
+        let right = settings.turn(Vec2::new(200.0, 0.0), 500.0);
+        assert!(right.x < 0.0 && right.y == 0.0);
+        let up = settings.turn(Vec2::new(0.0, 200.0), 500.0);
+        assert!(up.x == 0.0 && up.y > 0.0);
+
+        settings.invert_y = true;
+        assert_eq!(settings.turn(Vec2::new(0.0, 200.0), 500.0), -up);
+
+        // exponential curve is slower near the center and the same at the edge
+        let linear = settings.turn(Vec2::new(500.0, 0.0), 500.0);
+        settings.curve = ResponseCurve::Exponential;
+        assert!(settings.turn(Vec2::new(200.0, 0.0), 500.0).x > right.x);
+        assert_eq!(settings.turn(Vec2::new(500.0, 0.0), 500.0), linear);
+    }
+
+    #[test]
+    fn test_coupling() {
+        let settings = FlightSettings {
+            roll_to_yaw: 0.5,
+            yaw_to_roll: -0.25,
+            ..Default::default()
+        };
+        assert_eq!(settings.couple(Vec3::Z * 2.0), Vec3::new(0.0, 1.0, 2.0));
+        assert_eq!(settings.couple(Vec3::Y * 4.0), Vec3::new(0.0, 4.0, -1.0));
+        // pitch is left alone
+        assert_eq!(settings.couple(Vec3::X), Vec3::X);
+    }
+
+    #[test]
+    fn test_orbit() {
+        let target = Vec3::NEG_Z * 100.0;
+        let quarter = std::f32::consts::FRAC_PI_2 * 100.0;
+        let rotation = orbit(Vec3::ZERO, target, Vec3::X, quarter).unwrap();
+        let position = target + rotation * (Vec3::ZERO - target);
+        assert!(position.abs_diff_eq(Vec3::new(100.0, 0.0, -100.0), 1e-3));
+        // the ship turns along, so the target stays ahead
+        assert!((rotation * Vec3::NEG_Z).abs_diff_eq(Vec3::NEG_X, 1e-3));
+
+        assert!(orbit(Vec3::ZERO, Vec3::X * 100.0, Vec3::X, quarter).is_none());
+    }
+
+    #[test]
+    fn test_movement_actions() {
+        assert!(movement_actions(Vec3::splat(0.5)).is_empty());
+        assert_eq!(movement_actions(Vec3::NEG_Z * 10.0), [PlayerAction::Move]);
+        assert_eq!(
+            movement_actions(Vec3::new(0.0, -5.0, 10.0)),
+            [PlayerAction::Move, PlayerAction::Strafe]
+        );
+    }
+
+    #[test]
+    fn test_flight_assist() {
+        let drift = Vec3::X * 10.0;
+        let command = Vec3::NEG_Z * 20.0;
+        assert_eq!(
+            FlightAssist::Coupled.velocity(drift, command, 0.5, 100.0, 0.1),
+            command
+        );
+        assert_eq!(
+            FlightAssist::Coupled.velocity(drift, Vec3::ZERO, 0.5, 100.0, 0.1),
+            Vec3::ZERO
+        );
+
+        // drift is kept until countered
+        let decoupled = FlightAssist::Decoupled;
+        assert_eq!(
+            decoupled.velocity(drift, Vec3::ZERO, 0.5, 100.0, 0.1),
+            drift
+        );
+        let velocity = decoupled.velocity(drift, command, 0.5, 100.0, 0.1);
+        assert_eq!(velocity, Vec3::new(10.0, 0.0, -1.0));
+        let velocity = decoupled.velocity(velocity, command * 100.0, 0.5, 100.0, 1.0);
+        assert!((velocity.length() - 100.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_focus_target() {
+        let mut queue = TargetQueue::default();
+        let targets: Vec<_> = (0..MAX_LOCKED_TARGETS as u32)
+            .map(Entity::from_raw)
+            .collect();
+        targets.iter().for_each(|&target| queue.toggle(target));
+
+        // already locked target becomes the primary one
+        queue.focus(targets[0]);
+        assert_eq!(queue.primary(), Some(targets[0]));
+        assert_eq!(queue.iter().count(), MAX_LOCKED_TARGETS);
+
+        // a new one replaces the oldest
+        let new = Entity::from_raw(100);
+        queue.focus(new);
+        assert_eq!(queue.primary(), Some(new));
+        assert!(!queue.iter().any(|e| e == targets[1]));
+    }
+}
+
+/// Mouse steering for the current frame, applied by `move_player`
+#[derive(Resource, Default)]
+struct MouseSteering {
+    /// Yaw and pitch in radians
+    turn: Vec2,
+    /// Ship follows the cursor without holding the mouse button, toggled with Space
+    guidance: bool,
+    /// Ship follows the cursor while the left mouse button is held
+    held: bool,
+}
+
+/// Loadout to fit the weapons of the ship to, once they are mounted from its prefab
+#[derive(Component)]
+struct PendingLoadout(LoadoutConfig);
+
+fn setup_player(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    scenario: Option<Res<Scenario>>,
+) {
+    let config = scenario
+        .as_ref()
+        .map(|scenario| scenario.player.clone())
+        .unwrap_or_default();
+    let transform = config
+        .spawn_point
+        .as_ref()
+        .and_then(|name| {
+            let point = scenario.as_ref()?.spawn_point(name);
+            if point.is_none() {
+                warn!("Player spawn point {name} isn't in the scenario");
+            }
+            point
         })
+        .unwrap_or_else(|| Transform::from_xyz(0.0, 0.0, 10.0));
+    let ship = commands
+        .spawn(ShipBundle::new(
+            assets.load("ships/player.ship.ron"),
+            transform,
+        ))
+        .insert(Player)
+        .insert(Name::new("Player"))
+        .insert(Velocity::default())
+        .insert(Throttle::default())
+        .insert(FlightAssist::default())
+        .insert(Cruise::default())
+        .insert(CollisionWarning::default())
+        .insert(TargetQueue::default())
+        .insert(components::KinematicPush::default())
+        // death is handled by the kill-cam, which respawns the player
+        .insert(components::Respawnable)
+        .insert(RigidBody::KinematicPositionBased)
+        .insert(config.team)
+        .insert(Modifiers::default())
+        .insert(ship_switch::Controllable)
+        .insert(PendingLoadout(config.loadout))
         .with_children(|parent| {
-            // Aim in the middle of the screen
-            parent.spawn(ImageBundle {
-                style: Style {
-                    size: Size::new(Val::Px(40.0), Val::Px(40.0)),
-                    ..default()
-                },
-                image: assets.load("UI/aim.png").into(),
-                ..default()
-            });
-
-            // Semi-transparent section in the left bottom corner for in-game infromation
             parent
-                .spawn(NodeBundle {
-                    style: Style {
-                        size: Size::new(Val::Percent(25.0), Val::Percent(25.0)),
-                        position_type: PositionType::Absolute,
-                        position: UiRect {
-                            right: Val::Px(10.0),
-                            bottom: Val::Px(10.0),
-                            ..default()
-                        },
-                        align_items: AlignItems::FlexStart, // vertical alignment to the top
-                        justify_content: JustifyContent::FlexStart, // horizontal alignment to the left
-                        padding: UiRect::all(Val::Px(5.0)),
-                        flex_wrap: FlexWrap::Wrap,
+                .spawn(PbrBundle {
+                    mesh: meshes.add(Mesh::from(shape::Box::new(1.0, 0.3, 2.0))),
+                    material: materials.add(StandardMaterial {
+                        base_color: Color::rgb(0.5, 0.5, 0.55),
+                        metallic: 0.8,
+                        perceptual_roughness: 0.4,
                         ..default()
-                    },
-                    background_color: Color::rgba(0.7, 0.7, 0.7, 0.3).into(),
+                    }),
+                    visibility: Visibility::INVISIBLE,
                     ..default()
                 })
-                .with_children(|parent| {
-                    parent
-                        .spawn(TextBundle::from_section(
-                            "",
-                            TextStyle {
-                                font: assets.load("fonts/FiraMono-Medium.ttf"),
-                                font_size: 20.0,
-                                color: Color::WHITE,
-                            },
-                        ))
-                        .insert(ConsoleText);
-                });
+                .insert(PlayerModel);
         })
-        .insert(Name::new("UI"));
+        .id();
+
+    commands
+        .spawn(Camera3dBundle {
+            transform,
+            ..default()
+        })
+        .insert(PlayerCamera {
+            target: ship,
+            offset: Vec3::ZERO,
+        })
+        .insert(Name::new("Player Camera"));
 }
 
-fn move_player(
-    time: Res<Time>,
+/// Drops the weapons beyond the loadout once the prefab has mounted them, and binds the rest to
+/// the controls
+fn fit_loadout(
+    mut commands: Commands,
+    ships: Query<(Entity, &PendingLoadout)>,
+    children: Query<&Children>,
+    guns: Query<&gun::Gun>,
+) {
+    for (ship, PendingLoadout(loadout)) in ships.iter() {
+        let weapons: Vec<_> = children
+            .iter_descendants(ship)
+            .filter_map(|entity| Some((entity, guns.get(entity).ok()?.projectile())))
+            .collect();
+        if weapons.is_empty() {
+            // not built yet
+            continue;
+        }
+        commands.entity(ship).remove::<PendingLoadout>();
+
+        let mut machine_guns = 0;
+        for (weapon, projectile) in weapons {
+            let mut weapon = commands.entity(weapon);
+            match projectile {
+                Projectile::Bullet | Projectile::FlakShell
+                    if machine_guns < loadout.machine_guns =>
+                {
+                    machine_guns += 1;
+                    weapon.insert(PrimaryWeapon).insert(Modifiers::default());
+                }
+                Projectile::Rocket if loadout.rockets > 0 => {
+                    weapon
+                        .insert(SecondaryWeapon)
+                        .insert(gun::Trigger::semi_automatic())
+                        .insert(gun::Ammo::new(loadout.rockets));
+                }
+                Projectile::Slug if loadout.railgun => {
+                    weapon.insert(HeavyWeapon);
+                }
+                _ => weapon.despawn_recursive(),
+            }
+        }
+    }
+}
+
+/// Keeps the camera at its offset from the ship, hiding the hull while the camera is inside
+fn follow_player(
+    ships: Query<&Transform, Without<PlayerCamera>>,
+    mut cameras: Query<(&PlayerCamera, &mut Transform)>,
+    mut models: Query<(&Parent, &mut Visibility), With<PlayerModel>>,
+) {
+    for (camera, mut transform) in cameras.iter_mut() {
+        let Ok(ship) = ships.get(camera.target) else {
+            continue;
+        };
+        let follow = ship.mul_transform(Transform::from_translation(camera.offset));
+        if *transform != follow {
+            *transform = follow;
+        }
+        for (ship, mut visibility) in models.iter_mut() {
+            let visible = ship.get() != camera.target || camera.offset != Vec3::ZERO;
+            if visibility.is_visible != visible {
+                visibility.is_visible = visible;
+            }
+        }
+    }
+}
+
+pub(crate) fn steer_with_mouse(
     keys: Res<Input<KeyCode>>,
     mouse: Res<Input<MouseButton>>,
-    mut mouse_guidance: Local<bool>,
     mut windows: ResMut<Windows>,
-    mut egui: ResMut<bevy_inspector_egui::bevy_egui::EguiContext>,
-    mut player_transform: Query<&mut Transform, With<Player>>,
+    ui_pointer: Res<UiPointer>,
+    settings: Res<Settings>,
+    mut steering: ResMut<MouseSteering>,
 ) {
-    let mut camera_speed = 10.0;
+    steering.turn = Vec2::ZERO;
+
+    // Enable mouse guidance if Space is pressed
+    if keys.just_released(KeyCode::Space) {
+        steering.guidance = !steering.guidance;
+    }
+
+    steering.held = !ui_pointer.is_used && !ui_pointer.over_hud && mouse.pressed(MouseButton::Left);
+    if steering.guidance || steering.held {
+        let window = windows.primary_mut();
+        // egui sets it's own icon, so we override cursor it on every frame
+        window.set_cursor_icon(if steering.guidance {
+            CursorIcon::Crosshair
+        } else {
+            CursorIcon::Default
+        });
+
+        if let Some(pos) = window.cursor_position() {
+            let center = Vec2::new(window.width() / 2.0, window.height() / 2.0);
+            steering.turn = settings.mouse.turn(pos - center, center.min_element());
+        }
+    }
+}
+
+fn cruise_control(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    mut player: Query<(&Transform, &Velocity, &mut Cruise), With<Player>>,
+    locked: Query<Entity, With<LockedTarget>>,
+    velocities: Query<&Velocity>,
+    parents: Query<&Parent>,
+) {
+    let (transform, velocity, mut cruise) = player.single_mut();
+    if keys.just_pressed(KeyCode::R) {
+        cruise.0 = match cruise.0 {
+            Some(_) => None,
+            None => Some(-(transform.rotation.inverse() * velocity.linvel).z),
+        };
+        info!("Cruise control: {:?}", cruise.0);
+    }
+    if keys.just_pressed(KeyCode::V) {
+        // Ships consist of several parts, the velocity is kept by the root rigid body
+        let target_velocity = locked.get_single().ok().and_then(|target| {
+            std::iter::once(target)
+                .chain(parents.iter_ancestors(target))
+                .find_map(|entity| velocities.get(entity).ok())
+        });
+        if let Some(target_velocity) = target_velocity {
+            cruise.0 = Some(target_velocity.linvel.length().min(MAX_SPEED));
+            info!("Matched target speed: {:?}", cruise.0);
+        }
+    }
+
+    let Some(speed) = cruise.0 else {
+        return;
+    };
+    let mut rate = MAX_SPEED / 10.0;
     if keys.pressed(KeyCode::LShift) {
-        camera_speed *= 10.0;
+        rate = MAX_SPEED;
     }
-    let camepa_step = camera_speed * time.delta_seconds();
+    let mut change = 0.0;
+    if keys.pressed(KeyCode::X) {
+        change += rate * time.delta_seconds();
+    }
+    if keys.pressed(KeyCode::Z) {
+        change -= rate * time.delta_seconds();
+    }
+    if change != 0.0 {
+        cruise.0 = Some((speed + change).clamp(-MAX_SPEED, MAX_SPEED));
+    }
+}
+
+fn toggle_orbit_strafe(keys: Res<Input<KeyCode>>, mut settings: ResMut<Settings>) {
+    if keys.just_pressed(KeyCode::O) {
+        settings.flight.orbit_strafe = !settings.flight.orbit_strafe;
+        info!("Orbit strafe: {}", settings.flight.orbit_strafe);
+    }
+}
 
-    let mut translation = Vec3::ZERO;
+fn move_player(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    steering: Res<MouseSteering>,
+    mut player: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &mut Throttle,
+            &mut FlightAssist,
+            &Cruise,
+            &CollisionWarning,
+            &TargetQueue,
+            &mut components::KinematicPush,
+        ),
+        With<Player>,
+    >,
+    targets: Query<&GlobalTransform>,
+) {
+    let (mut transform, mut velocity, mut throttle, mut assist, cruise, warning, queue, mut push) =
+        player.single_mut();
+    if keys.just_pressed(KeyCode::C) {
+        *assist = assist.next();
+        info!("Flight assist: {:?}", *assist);
+    }
+
+    let dt = time.delta_seconds();
+    if dt <= 0.0 {
+        return;
+    }
+
+    let mut speed = MAX_SPEED / 10.0;
+    if keys.pressed(KeyCode::LShift) {
+        speed = MAX_SPEED;
+    }
+
+    // Commanded velocity in the ship's frame
+    let mut thrust = Vec3::ZERO;
     if keys.pressed(KeyCode::W) {
         // strafe up
-        translation.y += camepa_step;
+        thrust.y += speed;
     }
     if keys.pressed(KeyCode::S) {
         // strafe down
-        translation.y -= camepa_step;
+        thrust.y -= speed;
     }
     if keys.pressed(KeyCode::A) {
         // strafe right
-        translation.x -= camepa_step;
+        thrust.x -= speed;
     }
     if keys.pressed(KeyCode::D) {
         // strafe left
-        translation.x += camepa_step;
+        thrust.x += speed;
     }
     if keys.pressed(KeyCode::X) {
         // move forward
-        translation.z -= camepa_step;
+        thrust.z -= speed;
     }
     if keys.pressed(KeyCode::Z) {
         // move backward
-        translation.z += camepa_step;
+        thrust.z += speed;
     }
 
-    let mut rotation = Quat::IDENTITY;
+    // Lateral strafe around the locked target is a rotation of both the position and the heading
+    let mut orbit_rotation = Quat::IDENTITY;
+    let mut orbit_velocity = Vec3::ZERO;
+    let orbit_target = queue
+        .primary()
+        .filter(|_| settings.flight.orbit_strafe && thrust.x != 0.0)
+        .and_then(|target| targets.get(target).ok());
+    if let Some(target) = orbit_target {
+        let target = target.translation();
+        let position = transform.translation;
+        if let Some(rotation) = orbit(position, target, transform.right(), thrust.x * dt) {
+            let offset = position - target;
+            orbit_velocity = (rotation * offset - offset) / dt;
+            orbit_rotation = rotation;
+            thrust.x = 0.0;
+        }
+    }
+
+    // Commanded angular velocity in the ship's frame: X - pitch, Y - yaw, Z - roll
+    let mut spin = Vec3::new(steering.turn.y, steering.turn.x, 0.0) / dt;
     if keys.pressed(KeyCode::Q) {
         // rotate counter clockwise
-        rotation *= Quat::from_rotation_z(std::f32::consts::TAU * time.delta_seconds());
+        spin.z += std::f32::consts::TAU;
     }
     if keys.pressed(KeyCode::E) {
-        // rotate counter clockwise
-        rotation *= Quat::from_rotation_z(-std::f32::consts::TAU * time.delta_seconds());
+        // rotate clockwise
+        spin.z -= std::f32::consts::TAU;
     }
+    let spin = settings.flight.couple(spin);
 
-    // Enable mouse guidance if Space is pressed
-    if keys.just_released(KeyCode::Space) {
-        *mouse_guidance = !*mouse_guidance;
-    }
-
-    let click_guidance = !egui.ctx_mut().is_using_pointer() && mouse.pressed(MouseButton::Left);
-    if *mouse_guidance || click_guidance {
-        let window = windows.primary_mut();
-        // egui sets it's own icon, so we override cursor it on every frame
-        window.set_cursor_icon(if *mouse_guidance {
-            CursorIcon::Crosshair
-        } else {
-            CursorIcon::Default
-        });
-
-        if let Some(pos) = window.cursor_position() {
-            let center = Vec2::new(window.width() / 2.0, window.height() / 2.0);
-            let offset = center - pos;
-            // Safe zone around screen center for mouse_guidance mode
-            if click_guidance || offset.length_squared() > 400.0 {
-                rotation *= Quat::from_rotation_y(0.005 * offset.x.to_radians());
-                rotation *= Quat::from_rotation_x(-0.005 * offset.y.to_radians());
-            }
-        }
+    throttle.0 = cruise.0.unwrap_or(-thrust.z) / MAX_SPEED;
+    if let Some(cruise) = cruise.0 {
+        let forward_speed = -(transform.rotation.inverse() * velocity.linvel).z;
+        thrust.z = -match *assist {
+            FlightAssist::Coupled => cruise,
+            // thrust is proportional to the speed error, so the speed settles without overshooting
+            FlightAssist::Decoupled => cruise - forward_speed,
+        };
     }
 
-    let mut transform = player_transform.single_mut();
-    transform.rotate_local(rotation);
-    translation = transform.rotation * translation;
-    transform.translation += translation;
+    // Movement is kinematic, so the ship is moved by its velocity
+    let spin = transform.rotation * spin;
+    velocity.angvel = assist.velocity(velocity.angvel, spin, SPIN_RESPONSE, MAX_SPIN, dt);
+    transform.rotation =
+        (orbit_rotation * Quat::from_scaled_axis(velocity.angvel * dt) * transform.rotation)
+            .normalize();
+    let thrust = transform.rotation * thrust + orbit_velocity;
+    let linvel = assist.velocity(velocity.linvel, thrust, THRUST_RESPONSE, MAX_SPEED, dt);
+    // Pushes like recoil aren't commanded: decoupled flight keeps them for good, while coupled
+    // flight adds them on top of the command and cancels them out over time
+    let linvel = linvel + push.0;
+    push.0 = match *assist {
+        FlightAssist::Coupled => push.0 * (1.0 - THRUST_RESPONSE * dt).max(0.0),
+        FlightAssist::Decoupled => Vec3::ZERO,
+    };
+    velocity.linvel = warning.brake(linvel);
+    transform.translation += velocity.linvel * dt;
 }
 
 fn zoom_camera(
     mut scroll: EventReader<MouseWheel>,
-    mut projection: Query<&mut camera::Projection, With<Camera3d>>,
-    mut egui: ResMut<bevy_inspector_egui::bevy_egui::EguiContext>,
+    mut projection: Query<&mut camera::Projection, With<PlayerCamera>>,
+    ui_pointer: Res<UiPointer>,
 ) {
-    if egui.ctx_mut().wants_pointer_input() {
+    if ui_pointer.wants_input {
         return;
     }
 
@@ -227,106 +775,311 @@ fn primary_weapon_shoot(
     }
 }
 
-fn secondary_weapon_shoot(
+/// Railgun charges while F is held and fires on its release once charged
+fn heavy_weapon_shoot(
     keys: Res<Input<KeyCode>>,
-    mut triggers: Query<&mut gun::Trigger, With<SecondaryWeapon>>,
+    mut triggers: Query<&mut gun::Trigger, With<HeavyWeapon>>,
 ) {
-    if keys.just_pressed(KeyCode::LControl) {
+    if keys.pressed(KeyCode::F) {
         for mut trigger in triggers.iter_mut() {
             trigger.pull();
         }
     }
 }
 
-/// Annotates current locked target.
+/// Launchers fire a salvo on every press of LControl, each rocket of the salvo is guided to the
+/// next target from the `TargetQueue`. Runs between the trigger latch and the firing, so the
+/// salvo is guided from the frame it's fired.
+fn secondary_weapon_shoot(
+    keys: Res<Input<KeyCode>>,
+    mut player: Query<&mut TargetQueue, With<Player>>,
+    mut launchers: Query<(&mut gun::Trigger, &mut gun::Guidance), With<SecondaryWeapon>>,
+) {
+    let mut queue = player.single_mut();
+    for (mut trigger, mut guidance) in launchers.iter_mut() {
+        if trigger.pressed() {
+            guidance.0 = queue.next_missile_target();
+        }
+        if keys.pressed(KeyCode::LControl) {
+            trigger.pull();
+        }
+    }
+}
+
+/// Maximum number of targets player can lock at once
+pub const MAX_LOCKED_TARGETS: usize = 4;
+
+/// Annotates current primary locked target.
 #[derive(Component)]
 pub struct LockedTarget;
 
+/// Targets locked by the player, from the oldest to the most recent one.
+/// The most recent target is the primary one and is annotated with `LockedTarget`.
+#[derive(Component, Default)]
+pub struct TargetQueue {
+    targets: Vec<Entity>,
+    next_missile: usize,
+}
+
+impl TargetQueue {
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.targets.iter().copied()
+    }
+
+    pub fn primary(&self) -> Option<Entity> {
+        self.targets.last().copied()
+    }
+
+    /// Unlocks the `target` once it's out of the battle, e.g. returned to the pool
+    pub fn unlock(&mut self, target: Entity) {
+        self.targets.retain(|&e| e != target);
+    }
+
+    /// Locks a new target or unlocks already locked one. The oldest target is unlocked once queue is full.
+    fn toggle(&mut self, target: Entity) {
+        if let Some(index) = self.targets.iter().position(|&e| e == target) {
+            self.targets.remove(index);
+        } else {
+            if self.targets.len() == MAX_LOCKED_TARGETS {
+                self.targets.remove(0);
+            }
+            self.targets.push(target);
+        }
+    }
+
+    /// Makes the `target` the primary one, locking it if it isn't locked yet
+    fn focus(&mut self, target: Entity) {
+        if let Some(index) = self.targets.iter().position(|&e| e == target) {
+            self.targets.remove(index);
+        } else if self.targets.len() == MAX_LOCKED_TARGETS {
+            self.targets.remove(0);
+        }
+        self.targets.push(target);
+    }
+
+    /// Cycles through all locked targets, so a salvo is distributed across them
+    fn next_missile_target(&mut self) -> Option<Entity> {
+        if self.targets.is_empty() {
+            return None;
+        }
+        let target = self.targets[self.next_missile % self.targets.len()];
+        self.next_missile = (self.next_missile + 1) % self.targets.len();
+        Some(target)
+    }
+}
+
 fn select_target(
-    mut commands: Commands,
     rapier_context: Res<RapierContext>,
-    camera: Query<&Transform, With<Camera>>,
-    targets: Query<Entity, With<LockedTarget>>,
-    children: Query<&Children>,
-    with_mesh: Query<&Handle<Mesh>>,
+    mut player: Query<(Entity, &Transform, &mut TargetQueue, Option<&InIonStorm>), With<Player>>,
     keys: Res<Input<KeyCode>>,
 ) {
     if keys.just_pressed(KeyCode::T) {
-        let transform = camera.single();
+        let (player, transform, mut queue, in_storm) = player.single_mut();
+        // Jammed sensors can't lock targets far away
+        let range = in_storm.map_or(Real::MAX, |_| ion_storm::SENSOR_RANGE);
         if let Some((entity, _)) = rapier_context.cast_ray(
             transform.translation,
             transform.forward(),
-            Real::MAX,
+            range,
             false,
-            QueryFilter::default(),
+            QueryFilter::default().exclude_collider(player),
         ) {
-            fn iter_hierarchy(
-                entity: Entity,
-                children_query: &Query<&Children>,
-                f: &mut impl FnMut(Entity),
-            ) {
-                (f)(entity);
-                if let Ok(children) = children_query.get(entity) {
-                    for child in children.iter().copied() {
-                        iter_hierarchy(child, children_query, f);
-                    }
-                }
-            }
+            queue.toggle(entity);
+        }
+    }
+}
 
-            // Select a new target and highlight it via Wireframe
-            if !targets.contains(entity) {
-                commands.entity(entity).insert(LockedTarget);
-                iter_hierarchy(entity, &children, &mut |entity| {
-                    if with_mesh.contains(entity) {
-                        commands.entity(entity).insert(wireframe::Wireframe);
-                    }
-                });
-            }
+/// How long allies prioritize the target designated by the player, in seconds
+const DESIGNATION_DURATION: f32 = 15.0;
+
+/// Order to focus fire of allies on the entity, issued from the HUD by clicking a radar blip or
+/// a target bracket.
+pub struct FocusFireEvent(pub Entity);
+
+/// Orders all allied turrets and drones to attack the primary locked target, or the one picked
+/// on the HUD, which becomes the primary one
+fn focus_fire(
+    keys: Res<Input<KeyCode>>,
+    mut ev_focus: EventReader<FocusFireEvent>,
+    mut player: Query<(&mut TargetQueue, &components::Fraction), With<Player>>,
+    mut ev_designate: EventWriter<aiming::DesignateTargetEvent>,
+) {
+    let (mut queue, &fraction) = player.single_mut();
+    let mut target = None;
+    if keys.just_pressed(KeyCode::G) {
+        target = queue.primary();
+    }
+    if let Some(FocusFireEvent(picked)) = ev_focus.iter().last() {
+        queue.focus(*picked);
+        target = Some(*picked);
+    }
+    if let Some(target) = target {
+        info!("Target designated: {target:?}");
+        ev_designate.send(aiming::DesignateTargetEvent {
+            target,
+            fraction,
+            duration: DESIGNATION_DURATION,
+        });
+    }
+}
 
-            // Remove previous target selection if any.
-            // This order also unselects previous target on a repeated select.
-            for prev_target in targets.iter() {
-                commands.entity(prev_target).remove::<LockedTarget>();
-                iter_hierarchy(prev_target, &children, &mut |entity| {
-                    commands.entity(entity).remove::<wireframe::Wireframe>();
-                });
+/// Unlocks destroyed targets
+fn prune_target_queue(mut player: Query<&mut TargetQueue>, entities: Query<Entity>) {
+    for mut queue in player.iter_mut() {
+        if queue.targets.iter().any(|&e| !entities.contains(e)) {
+            queue.targets.retain(|&e| entities.contains(e));
+        }
+    }
+}
+
+/// Keeps `LockedTarget` on the primary target and highlights all locked targets via Wireframe
+fn update_lock_markers(
+    mut commands: Commands,
+    player: Query<&TargetQueue, (With<Player>, Changed<TargetQueue>)>,
+    locked: Query<Entity, With<LockedTarget>>,
+    children: Query<&Children>,
+    with_mesh: Query<&Handle<Mesh>>,
+    entities: Query<Entity>,
+    mut highlighted: Local<Vec<Entity>>,
+) {
+    let Ok(queue) = player.get_single() else {
+        return;
+    };
+
+    fn iter_hierarchy(
+        entity: Entity,
+        children_query: &Query<&Children>,
+        f: &mut impl FnMut(Entity),
+    ) {
+        (f)(entity);
+        if let Ok(children) = children_query.get(entity) {
+            for child in children.iter().copied() {
+                iter_hierarchy(child, children_query, f);
             }
         }
     }
+
+    for entity in locked.iter() {
+        if queue.primary() != Some(entity) {
+            commands.entity(entity).remove::<LockedTarget>();
+        }
+    }
+    if let Some(primary) = queue.primary() {
+        commands.entity(primary).insert(LockedTarget);
+    }
+
+    // Remove highlight from unlocked targets, that still exist
+    for &prev_target in highlighted.iter() {
+        if entities.contains(prev_target) && !queue.iter().any(|e| e == prev_target) {
+            iter_hierarchy(prev_target, &children, &mut |entity| {
+                commands.entity(entity).remove::<wireframe::Wireframe>();
+            });
+        }
+    }
+    for target in queue.iter() {
+        if !highlighted.contains(&target) {
+            iter_hierarchy(target, &children, &mut |entity| {
+                if with_mesh.contains(entity) {
+                    commands.entity(entity).insert(wireframe::Wireframe);
+                }
+            });
+        }
+    }
+    *highlighted = queue.iter().collect();
 }
 
-fn show_selected_target_info(
-    player: Query<&GlobalTransform, With<Player>>,
-    target: Query<(Option<&Name>, &GlobalTransform, Option<&HitPoints>), With<LockedTarget>>,
-    mut console: Query<&mut Text, With<ConsoleText>>,
+/// Reports what the player does with the ship as `PlayerActionEvent`s
+fn report_actions(
+    steering: Res<MouseSteering>,
+    player: Query<(&Transform, &Velocity, &TargetQueue), With<Player>>,
+    mut ev_shot_fired: EventReader<gun::ShotFiredEvent>,
+    weapons: Query<(
+        Option<&PrimaryWeapon>,
+        Option<&SecondaryWeapon>,
+        Option<&HeavyWeapon>,
+    )>,
+    mut ongoing: Local<Vec<PlayerAction>>,
+    mut ev_action: EventWriter<PlayerActionEvent>,
 ) {
-    let mut console = console.single_mut();
-    if let Ok((name, transform, hp)) = target.get_single() {
-        let player_pos = player.single().translation();
-        let distance = player_pos.distance(transform.translation());
+    let Ok((transform, velocity, queue)) = player.get_single() else {
+        return;
+    };
 
-        let name = name.map_or("-- Unknown --", |name| name.as_str());
-        console.sections[0].value = format!("Selected: {name}\nDistance to target: {distance:.2}m");
+    // Ongoing actions are reported once they start
+    let mut actions = movement_actions(transform.rotation.inverse() * velocity.linvel);
+    if steering.guidance || steering.held {
+        actions.push(PlayerAction::MouseGuidance);
+    }
+    if queue.primary().is_some() {
+        actions.push(PlayerAction::LockTarget);
+    }
+    for &action in actions.iter().filter(|action| !ongoing.contains(action)) {
+        ev_action.send(PlayerActionEvent(action));
+    }
+    *ongoing = actions;
 
-        if let Some(hp) = hp {
-            console.sections[0].value += &format!("\nHit Points: {}%", hp.percent());
+    let mut fired = Vec::new();
+    for ev in ev_shot_fired.iter() {
+        let action = match weapons.get(ev.gun) {
+            Ok((Some(_), _, _)) => PlayerAction::FirePrimary,
+            Ok((_, Some(_), _)) => PlayerAction::FireSecondary,
+            Ok((_, _, Some(_))) => PlayerAction::FireHeavy,
+            _ => continue,
+        };
+        if !fired.contains(&action) {
+            fired.push(action);
         }
-    } else {
-        console.sections[0].value = String::from("Press 'T' to select a target.");
     }
+    ev_action.send_batch(fired.into_iter().map(PlayerActionEvent));
 }
 
+/// Controls are disabled while the player is dead
+fn player_alive(player: Query<&components::HitPoints, With<Player>>) -> ShouldRun {
+    match player.get_single() {
+        Ok(hp) if hp.dead() => ShouldRun::No,
+        _ => ShouldRun::Yes,
+    }
+}
+
+/// Player's ship with its target selection and weapons, and the camera following it.
 pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(setup_player)
-            .add_startup_system(setup_hud)
+        if !app.is_plugin_added::<PrefabPlugin>() {
+            app.add_plugin(PrefabPlugin);
+        }
+        app.init_resource::<UiPointer>()
+            .init_resource::<MouseSteering>()
+            .add_event::<FocusFireEvent>()
+            .add_event::<PlayerActionEvent>()
+            .register_type::<Throttle>()
+            .register_type::<FlightAssist>()
+            .register_type::<Cruise>()
+            .add_startup_system(setup_player)
+            .add_system(fit_loadout)
             .add_plugin(wireframe::WireframePlugin)
-            .add_system(select_target)
-            .add_system(show_selected_target_info)
-            .add_system(move_player)
-            .add_system(zoom_camera)
-            .add_system(primary_weapon_shoot)
-            .add_system(secondary_weapon_shoot);
+            .add_system(prune_target_queue.after(select_target))
+            .add_system(update_lock_markers.after(prune_target_queue))
+            // Runs while the player is dead too, the kill-cam moves the camera on top of it
+            .add_system(follow_player.after(move_player))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(player_alive)
+                    .with_system(select_target)
+                    .with_system(focus_fire.after(select_target).before(prune_target_queue))
+                    .with_system(steer_with_mouse.before(move_player))
+                    .with_system(cruise_control.before(move_player))
+                    .with_system(toggle_orbit_strafe.before(move_player))
+                    .with_system(move_player)
+                    .with_system(zoom_camera)
+                    .with_system(primary_weapon_shoot)
+                    .with_system(
+                        secondary_weapon_shoot
+                            .after(gun::TriggerSystems)
+                            .before(gun::FireSystems),
+                    )
+                    .with_system(heavy_weapon_shoot)
+                    .with_system(report_actions.after(move_player)),
+            );
     }
 }
@@ -0,0 +1,399 @@
+//! Screen effects driven by gameplay: a red vignette growing with the hull damage, a chromatic
+//! aberration and distortion pulse on nearby explosions, speed lines while boosting, the view
+//! bent around gravity wells and shimmering in the heat haze behind engine plumes and big
+//! explosions.
+//! They are applied to the 3D scene texture by `PostProcessMaterial`, drawn over the window by
+//! the display upscaling, and toggled one by one in `DisplaySettings::post_effects`.
+
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    render::render_resource::{AsBindGroup, ShaderRef},
+    sprite::{Material2d, Material2dPlugin},
+};
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::HitPoints,
+    display::SecondaryCamera,
+    player::{self, Player},
+    projectile::{ExplosionEffect, ExplosionEvent},
+    settings::Settings,
+    thruster::Thruster,
+};
+
+/// Time for the explosion pulse to fade out, in seconds
+const PULSE_TIME: f32 = 0.4;
+/// Speed lines start at this fraction of the max player speed, i.e. only with the boost
+const SPEED_LINES_FROM: f32 = 0.2;
+/// Number of heat haze spots on the screen at once, must match the shader
+pub const MAX_HAZES: usize = 8;
+/// Heat of a big explosion shimmers for this time, in seconds
+const BLAST_HAZE_TIME: f32 = 1.5;
+/// Radius of the heat haze around a big explosion, in meters
+const BLAST_HAZE_RADIUS: f32 = 25.0;
+/// Haze spots smaller than this on the screen aren't worth distorting, in screen heights
+const MIN_HAZE_RADIUS: f32 = 0.005;
+
+/// Post effects that can be turned on and off. All of them are off by default, as any of them
+/// makes the 3D scene render offscreen.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct PostEffectSettings {
+    pub vignette: bool,
+    pub aberration: bool,
+    pub distortion: bool,
+    pub speed_lines: bool,
+    pub heat_haze: bool,
+}
+
+impl PostEffectSettings {
+    /// The scene is rendered to a texture for post-processing only if some effect is enabled
+    pub fn any(&self) -> bool {
+        self.vignette || self.aberration || self.distortion || self.speed_lines || self.heat_haze
+    }
+}
+
+/// Current strength of the gameplay driven effects, from 0 to 1.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct PostEffects {
+    /// Lost part of the player hull
+    pub damage: f32,
+    /// Shock of the latest nearby explosion, fading out
+    pub pulse: f32,
+    pub speed: f32,
+    /// Gravity lens around a well: the center in UV coordinates, the radius in screen heights,
+    /// and the strength. Zero strength for none.
+    pub lens: Vec4,
+    /// Heat haze spots behind engine plumes and around big explosions, the biggest first, in
+    /// the same format as the `lens`
+    pub haze: [Vec4; MAX_HAZES],
+}
+
+/// Full screen material with the 3D scene as the `source` texture.
+#[derive(AsBindGroup, TypeUuid, Clone, Default)]
+#[uuid = "2f6b3c1e-8d4a-4f7b-9e52-6a1d0c3b7e94"]
+pub struct PostProcessMaterial {
+    /// Vectors go first, as they are aligned to 16 bytes in the uniform
+    #[uniform(0)]
+    lens: Vec4,
+    #[uniform(0)]
+    haze: [Vec4; MAX_HAZES],
+    #[uniform(0)]
+    vignette: f32,
+    #[uniform(0)]
+    aberration: f32,
+    #[uniform(0)]
+    distortion: f32,
+    #[uniform(0)]
+    speed_lines: f32,
+    /// Seconds since startup, to animate ripples and lines
+    #[uniform(0)]
+    time: f32,
+    #[texture(1)]
+    #[sampler(2)]
+    source: Handle<Image>,
+}
+
+impl PostProcessMaterial {
+    pub fn new(source: Handle<Image>) -> Self {
+        Self {
+            source,
+            ..default()
+        }
+    }
+}
+
+impl Material2d for PostProcessMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/post_process.wgsl".into()
+    }
+}
+
+/// Strength of the shock from the `effect` exploded `distance` meters away
+fn explosion_pulse(effect: ExplosionEffect, distance: f32) -> f32 {
+    let (strength, radius) = match effect {
+        ExplosionEffect::Big => (1.0, 200.0),
+        ExplosionEffect::Small | ExplosionEffect::Debug => (0.4, 50.0),
+    };
+    strength * (1.0 - distance / radius).max(0.0)
+}
+
+/// Speed lines grow from `SPEED_LINES_FROM` to the max player speed
+fn speed_lines(speed: f32) -> f32 {
+    let fraction = speed / player::MAX_SPEED;
+    ((fraction - SPEED_LINES_FROM) / (1.0 - SPEED_LINES_FROM)).clamp(0.0, 1.0)
+}
+
+/// Spot of a sphere on the screen: the center in UV coordinates, the radius in screen heights and
+/// the `strength`. `None` if the center is behind the camera.
+pub fn screen_spot(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    center: Vec3,
+    radius: f32,
+    strength: f32,
+) -> Option<Vec4> {
+    let ndc = camera.world_to_ndc(camera_transform, center)?;
+    let edge = camera.world_to_ndc(camera_transform, center + camera_transform.up() * radius)?;
+    if !(0.0..=1.0).contains(&ndc.z) {
+        return None;
+    }
+    // UV origin is in the top left corner, while NDC one is in the center with Y up
+    let uv = Vec2::new(ndc.x + 1.0, 1.0 - ndc.y) / 2.0;
+    Some(Vec4::new(
+        uv.x,
+        uv.y,
+        (edge.y - ndc.y).abs() / 2.0,
+        strength,
+    ))
+}
+
+/// Up to `MAX_HAZES` spots that look the biggest and the strongest, padded with zero ones
+fn strongest_hazes(mut spots: Vec<Vec4>) -> [Vec4; MAX_HAZES] {
+    spots.retain(|spot| spot.z >= MIN_HAZE_RADIUS && spot.w > 0.0);
+    spots.sort_by(|a, b| (b.z * b.w).total_cmp(&(a.z * a.w)));
+    let mut hazes = [Vec4::ZERO; MAX_HAZES];
+    for (haze, spot) in hazes.iter_mut().zip(spots) {
+        *haze = spot;
+    }
+    hazes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{explosion_pulse, speed_lines, strongest_hazes, MAX_HAZES};
+    use crate::{player::MAX_SPEED, projectile::ExplosionEffect};
+    use bevy::prelude::Vec4;
+
+    #[test]
+    fn test_explosion_pulse() {
+        assert_eq!(explosion_pulse(ExplosionEffect::Big, 0.0), 1.0);
+        assert!(
+            explosion_pulse(ExplosionEffect::Big, 100.0)
+                > explosion_pulse(ExplosionEffect::Small, 10.0)
+        );
+        // too far to be felt
+        assert_eq!(explosion_pulse(ExplosionEffect::Small, 100.0), 0.0);
+    }
+
+    #[test]
+    fn test_speed_lines() {
+        // cruising without the boost
+        assert_eq!(speed_lines(MAX_SPEED / 10.0), 0.0);
+        assert_eq!(speed_lines(MAX_SPEED), 1.0);
+        assert_eq!(speed_lines(-2.0 * MAX_SPEED), 0.0);
+    }
+
+    #[test]
+    fn test_strongest_hazes() {
+        assert_eq!(strongest_hazes(Vec::new()), [Vec4::ZERO; MAX_HAZES]);
+
+        let small = Vec4::new(0.5, 0.5, 0.02, 1.0);
+        let big = Vec4::new(0.2, 0.3, 0.1, 0.5);
+        // too small on the screen
+        let tiny = Vec4::new(0.5, 0.5, 0.001, 1.0);
+        let hazes = strongest_hazes(vec![small, tiny, big]);
+        assert_eq!(hazes[..3], [big, small, Vec4::ZERO]);
+
+        // only the biggest ones are kept
+        let many = (1..=2 * MAX_HAZES).map(|i| Vec4::new(0.5, 0.5, 0.01 * i as f32, 1.0));
+        let hazes = strongest_hazes(many.collect());
+        assert_eq!(hazes[0].z, 0.01 * (2 * MAX_HAZES) as f32);
+        assert!(hazes.iter().all(|haze| haze.z > 0.01 * MAX_HAZES as f32));
+    }
+}
+
+fn track_player(
+    time: Res<Time>,
+    mut effects: ResMut<PostEffects>,
+    mut ev_explosion: EventReader<ExplosionEvent>,
+    player: Query<(&GlobalTransform, &HitPoints, &Velocity), With<Player>>,
+) {
+    let player = player.get_single().ok();
+    let mut pulse = (effects.pulse - time.delta_seconds() / PULSE_TIME).max(0.0);
+    for ev in ev_explosion.iter() {
+        if let Some((transform, _, _)) = player {
+            let distance = ev.position.distance(transform.translation());
+            pulse = pulse.max(explosion_pulse(ev.effect, distance));
+        }
+    }
+
+    let (damage, speed) = player.map_or((0.0, 0.0), |(_, hp, velocity)| {
+        let damage = 1.0 - hp.percent() as f32 / 100.0;
+        (damage, speed_lines(velocity.linvel.length()))
+    });
+    // Compared first, so systems watching the resource aren't triggered every frame
+    if effects.pulse != pulse || effects.damage != damage || effects.speed != speed {
+        effects.damage = damage;
+        effects.pulse = pulse;
+        effects.speed = speed;
+    }
+}
+
+/// Heat haze behind engine plumes and around big explosions, the biggest spots on the screen only
+fn track_heat(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut effects: ResMut<PostEffects>,
+    mut ev_explosion: EventReader<ExplosionEvent>,
+    // Positions of recent big explosions with seconds since they went off
+    mut blasts: Local<Vec<(Vec3, f32)>>,
+    camera: Query<(&Camera, &GlobalTransform), (With<Camera3d>, Without<SecondaryCamera>)>,
+    thrusters: Query<(&Thruster, &GlobalTransform)>,
+) {
+    for (_, age) in blasts.iter_mut() {
+        *age += time.delta_seconds();
+    }
+    blasts.retain(|&(_, age)| age < BLAST_HAZE_TIME);
+    blasts.extend(
+        ev_explosion
+            .iter()
+            .filter(|ev| ev.effect == ExplosionEffect::Big)
+            .map(|ev| (ev.position, 0.0)),
+    );
+
+    let camera = camera.get_single().ok();
+    let haze = match camera {
+        Some((camera, camera_transform)) if settings.display.post_effects.heat_haze => {
+            let blasts = blasts.iter().filter_map(|&(position, age)| {
+                let strength = 1.0 - age / BLAST_HAZE_TIME;
+                screen_spot(
+                    camera,
+                    camera_transform,
+                    position,
+                    BLAST_HAZE_RADIUS,
+                    strength,
+                )
+            });
+            let plumes = thrusters.iter().filter_map(|(thruster, transform)| {
+                let length = thruster.plume_length();
+                if length <= 0.0 {
+                    return None;
+                }
+                // Plumes are stretched in the thruster's space, scaled with the ship
+                let start = transform.translation();
+                let exhaust = -thruster.direction.normalize_or_zero();
+                let end = transform.transform_point(exhaust * length);
+                let radius = start.distance(end) / 2.0;
+                let strength = thruster.throttle();
+                screen_spot(
+                    camera,
+                    camera_transform,
+                    (start + end) / 2.0,
+                    radius,
+                    strength,
+                )
+            });
+            strongest_hazes(blasts.chain(plumes).collect())
+        }
+        _ => [Vec4::ZERO; MAX_HAZES],
+    };
+    if effects.haze != haze {
+        effects.haze = haze;
+    }
+}
+
+fn update_material(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    effects: Res<PostEffects>,
+    mut ev_images: EventReader<AssetEvent<Image>>,
+    mut materials: ResMut<Assets<PostProcessMaterial>>,
+    handles: Query<&Handle<PostProcessMaterial>>,
+) {
+    let toggles = settings.display.post_effects;
+    let enabled = |enabled: bool, value: f32| if enabled { value } else { 0.0 };
+    let vignette = enabled(toggles.vignette, effects.damage);
+    let aberration = enabled(toggles.aberration, effects.pulse);
+    let distortion = enabled(toggles.distortion, effects.pulse);
+    let speed_lines = enabled(toggles.speed_lines, effects.speed);
+    let lens = if toggles.distortion {
+        effects.lens
+    } else {
+        Vec4::ZERO
+    };
+    let haze = if toggles.heat_haze {
+        effects.haze
+    } else {
+        [Vec4::ZERO; MAX_HAZES]
+    };
+    let animated = distortion > 0.0 || speed_lines > 0.0 || haze[0].w > 0.0;
+
+    // Resized scene texture is a new one on the GPU, so the material has to be rebound to it
+    let resized: Vec<_> = ev_images
+        .iter()
+        .filter_map(|ev| match ev {
+            AssetEvent::Modified { handle } => Some(handle.clone()),
+            _ => None,
+        })
+        .collect();
+
+    for handle in handles.iter() {
+        let Some(material) = materials.get(handle) else {
+            continue;
+        };
+        let unchanged = material.vignette == vignette
+            && material.aberration == aberration
+            && material.distortion == distortion
+            && material.speed_lines == speed_lines
+            && material.lens == lens
+            && material.haze == haze;
+        if unchanged && !animated && !resized.contains(&material.source) {
+            continue;
+        }
+        if let Some(material) = materials.get_mut(handle) {
+            material.vignette = vignette;
+            material.aberration = aberration;
+            material.distortion = distortion;
+            material.speed_lines = speed_lines;
+            material.lens = lens;
+            material.haze = haze;
+            material.time = time.elapsed_seconds();
+        }
+    }
+}
+
+fn post_process_controls(keys: Res<Input<KeyCode>>, mut settings: ResMut<Settings>) {
+    if keys.just_pressed(KeyCode::F1) {
+        let effects = &mut settings.display.post_effects;
+        effects.vignette = !effects.vignette;
+        info!("Vignette: {}", effects.vignette);
+    }
+    if keys.just_pressed(KeyCode::F2) {
+        let effects = &mut settings.display.post_effects;
+        effects.aberration = !effects.aberration;
+        info!("Chromatic aberration: {}", effects.aberration);
+    }
+    if keys.just_pressed(KeyCode::F3) {
+        let effects = &mut settings.display.post_effects;
+        effects.distortion = !effects.distortion;
+        info!("Distortion: {}", effects.distortion);
+    }
+    if keys.just_pressed(KeyCode::F4) {
+        let effects = &mut settings.display.post_effects;
+        effects.speed_lines = !effects.speed_lines;
+        info!("Speed lines: {}", effects.speed_lines);
+    }
+    if keys.just_pressed(KeyCode::Scroll) {
+        let effects = &mut settings.display.post_effects;
+        effects.heat_haze = !effects.heat_haze;
+        info!("Heat haze: {}", effects.heat_haze);
+    }
+}
+
+/// `PostProcessMaterial` and the gameplay events driving it, toggled with F1-F4 and Scroll Lock.
+pub struct PostProcessPlugin;
+impl Plugin for PostProcessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(Material2dPlugin::<PostProcessMaterial>::default())
+            .init_resource::<Settings>()
+            .init_resource::<PostEffects>()
+            .add_event::<ExplosionEvent>()
+            .add_system(track_player)
+            .add_system(track_heat)
+            .add_system(update_material.after(track_player).after(track_heat))
+            .add_system(post_process_controls);
+    }
+}
@@ -0,0 +1,591 @@
+//! Ship prefabs, `.ship.ron` files in `assets/ships/` bundling everything a ship is built from:
+//! the model, how its collider is made, hardpoints with weapons, hit points, thruster layout and
+//! the default skills of its AI.
+//!
+//! Drones, turrets and the player's ship are all spawned with a [`ShipBundle`], which is built
+//! once its prefab is loaded. Roles add only their own controls on top, and rig the model nodes
+//! the prefab doesn't know about with a [`ShipRig`], like turret joints.
+
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    ecs::{system::EntityCommands, world::EntityRef},
+    prelude::*,
+    reflect::TypeUuid,
+    scene::SceneInstance,
+};
+use bevy_rapier3d::prelude::{Collider, ColliderMassProperties};
+use serde::Deserialize;
+use std::{borrow::Cow, sync::Arc};
+
+use crate::{
+    aiming::{GunLayer, LeadQuality},
+    bubble_shield::ShieldProjector,
+    collider_setup, components,
+    gimbal::Gimbal,
+    gun,
+    hazard::Reactor,
+    projectile::{CriticalZone, Shield},
+    scene_setup::SetupRequired,
+    shield_effect::ShieldEffect,
+    thruster, weapon,
+};
+
+/// Thrusters placed around the hull are hit as balls of this radius, in meters
+const HULL_THRUSTER_RADIUS: f32 = 0.3;
+
+/// How the collider of the ship is made.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub enum ColliderShape {
+    /// Convex hull of the model nodes with the name prefix
+    ConvexHull(String),
+    /// Ball with the radius, for ships without a model
+    Ball(f32),
+    /// Parts of the model get own colliders and hit points from the `ShipRig`, like the turret
+    /// head rotating on its body. The ship itself has neither, nor a gun layer.
+    Parts,
+}
+
+/// Weapon mounted on a hardpoint.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum WeaponConfig {
+    MachineGun {
+        rate_of_fire: f32,
+    },
+    RocketLauncher {
+        rate_of_fire: f32,
+        /// Unlimited without it
+        #[serde(default)]
+        ammo: Option<u32>,
+    },
+    Railgun {
+        charge_time: f32,
+    },
+    /// Fires from its barrels, so it's mounted with `Mount::Barrels`
+    FlakCannon {
+        rate_of_fire: f32,
+    },
+    /// Fires from its barrels, so it's mounted with `Mount::Barrels`
+    MissileBattery {
+        salvos_per_second: f32,
+    },
+}
+
+impl WeaponConfig {
+    /// Inserts the weapon firing from the `barrels`, or from the entity itself without any
+    fn mount(self, commands: &mut EntityCommands, barrels: Vec<Entity>) {
+        match self {
+            Self::MachineGun { rate_of_fire } => {
+                commands.insert(weapon::MachineGun::new(rate_of_fire));
+            }
+            Self::RocketLauncher { rate_of_fire, ammo } => {
+                commands.insert(weapon::RocketLauncher::new(rate_of_fire));
+                if let Some(ammo) = ammo {
+                    commands.insert(gun::Ammo::new(ammo));
+                }
+            }
+            Self::Railgun { charge_time } => {
+                commands.insert(weapon::Railgun::new(charge_time));
+                if !barrels.is_empty() {
+                    commands.insert(gun::MultiBarrel::new(barrels));
+                }
+            }
+            Self::FlakCannon { rate_of_fire } => {
+                commands.insert(weapon::FlakCannon::new(barrels, rate_of_fire));
+            }
+            Self::MissileBattery { salvos_per_second } => {
+                commands.insert(weapon::MissileBattery::new(barrels, salvos_per_second));
+            }
+        }
+    }
+}
+
+/// Where the weapon of a hardpoint is mounted.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub enum Mount {
+    /// On every model node with the name prefix, each one is a gun on its own
+    Nodes(String),
+    /// On the first model node with the `carrier` name prefix, or on the ship itself without one,
+    /// firing from all nodes with the `barrels` name prefix
+    Barrels {
+        barrels: String,
+        #[serde(default)]
+        carrier: Option<String>,
+    },
+    /// On a child of the ship at the offset, for ships without a model
+    Offset([f32; 3]),
+}
+
+/// Gimbal of the node carrying the weapon, see `Gimbal`.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct GimbalConfig {
+    /// In degrees
+    pub max_angle: f32,
+    /// In degrees per second
+    pub speed: f32,
+}
+
+impl GimbalConfig {
+    pub fn gimbal(&self) -> Gimbal {
+        Gimbal::new(self.max_angle.to_radians(), self.speed.to_radians())
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct Hardpoint {
+    pub mount: Mount,
+    pub weapon: WeaponConfig,
+    /// Weapons without it are fixed
+    #[serde(default)]
+    pub gimbal: Option<GimbalConfig>,
+}
+
+impl Hardpoint {
+    /// Mounts the weapon on the entity, see `WeaponConfig::mount`
+    fn mount(&self, commands: &mut EntityCommands, barrels: Vec<Entity>) {
+        self.weapon.mount(commands, barrels);
+        if let Some(gimbal) = self.gimbal {
+            commands.insert(gimbal.gimbal());
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ShieldConfig {
+    pub capacity: u32,
+    /// Per second
+    pub recharge_rate: f32,
+    /// Radius of the shield bubble drawn around the ship, in meters
+    #[serde(default = "ShieldConfig::default_radius")]
+    pub radius: f32,
+}
+
+impl ShieldConfig {
+    fn default_radius() -> f32 {
+        8.0
+    }
+}
+
+/// Bubble shield projected over the ship and its allies around, see `ShieldProjector`.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct BubbleShieldConfig {
+    /// In meters
+    pub radius: f32,
+}
+
+/// Weak spot of the ship taking extra damage, see `CriticalZone`. Zones are placed by the prefab
+/// rather than by model nodes, so they don't depend on how the model is made.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct CriticalZoneConfig {
+    /// Like "cockpit" or "reactor"
+    pub name: String,
+    /// Center of the zone relative to the ship, in meters
+    pub offset: [f32; 3],
+    /// In meters
+    pub radius: f32,
+    /// Damage of hits on the zone is multiplied by it
+    pub multiplier: f32,
+}
+
+/// Main engines of ships flown by the AI.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct ThrusterLayout {
+    /// Thrust is shared between the model nodes with the name prefix
+    #[serde(default)]
+    pub nodes: String,
+    /// Half width and half length of the hull in meters, to place main engines and attitude
+    /// thrusters around it for models without thruster nodes, see `hull_thrusters`
+    #[serde(default)]
+    pub hull: Option<(f32, f32)>,
+    /// In newtons
+    pub max_thrust: f32,
+    /// In degrees per second
+    pub max_rotation_speed: f32,
+}
+
+/// Default skills of the gun layer, see `GunLayer`.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct AiConfig {
+    pub lead: LeadQuality,
+    pub aim_error: f32,
+    pub reaction_delay: f32,
+}
+
+impl AiConfig {
+    pub fn gun_layer(&self) -> GunLayer {
+        GunLayer {
+            lead: self.lead,
+            aim_error: self.aim_error,
+            reaction_delay: self.reaction_delay,
+            ..default()
+        }
+    }
+}
+
+/// Ship prefab loaded from a `.ship.ron` file.
+#[derive(Deserialize, TypeUuid, Clone, Debug, PartialEq)]
+#[uuid = "6d2b1f9a-3c47-4e0b-9a85-2f6c1e7d4b30"]
+pub struct ShipPrefab {
+    /// glTF scene, like "models/praetor.glb#Scene0". Ships without one have no visible hull.
+    #[serde(default)]
+    pub model: Option<String>,
+    pub collider: ColliderShape,
+    pub hit_points: u32,
+    #[serde(default)]
+    pub shield: Option<ShieldConfig>,
+    #[serde(default)]
+    pub bubble_shield: Option<BubbleShieldConfig>,
+    /// Overrides the mass computed from the collider, in kg
+    #[serde(default)]
+    pub mass: Option<f32>,
+    #[serde(default)]
+    pub hardpoints: Vec<Hardpoint>,
+    #[serde(default)]
+    pub thrusters: Option<ThrusterLayout>,
+    #[serde(default)]
+    pub critical_zones: Vec<CriticalZoneConfig>,
+    /// Ships without it aren't flown by the AI by default
+    #[serde(default)]
+    pub ai: Option<AiConfig>,
+}
+
+impl ShipPrefab {
+    /// Inserts fresh hit points, shields and gun layer, also to restore a ship that is reused
+    pub fn insert_stats(&self, ship: &mut EntityCommands) {
+        if self.collider != ColliderShape::Parts {
+            ship.insert(components::HitPoints::new(self.hit_points));
+            if let Some(ai) = &self.ai {
+                ship.insert(ai.gun_layer());
+            }
+        }
+        if let Some(shield) = self.shield {
+            ship.insert(Shield::new(shield.capacity, shield.recharge_rate))
+                .insert(ShieldEffect::new(shield.radius));
+        }
+        if let Some(bubble) = self.bubble_shield {
+            ship.insert(ShieldProjector::new(bubble.radius));
+        }
+    }
+}
+
+#[derive(Default)]
+struct ShipPrefabLoader;
+
+impl AssetLoader for ShipPrefabLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let prefab: ShipPrefab = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(prefab));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ship.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ColliderShape, CriticalZoneConfig, GimbalConfig, Mount, ShipPrefab, WeaponConfig};
+
+    #[test]
+    fn test_parse() {
+        let prefab: ShipPrefab = ron::from_str(
+            r#"(
+                collider: Ball(0.5),
+                hit_points: 100,
+                hardpoints: [
+                    (mount: Offset((0.0, 0.0, -1.0)), weapon: MachineGun(rate_of_fire: 5.0), gimbal: Some((max_angle: 5.0, speed: 30.0))),
+                    (mount: Barrels(barrels: "Muzzle", carrier: Some("Head")), weapon: FlakCannon(rate_of_fire: 5.0)),
+                ],
+                critical_zones: [
+                    (name: "reactor", offset: (0.0, 0.0, 1.5), radius: 0.5, multiplier: 3.0),
+                ],
+            )"#,
+        )
+        .unwrap();
+        assert_eq!(prefab.model, None);
+        assert_eq!(prefab.collider, ColliderShape::Ball(0.5));
+        assert_eq!(prefab.hardpoints[0].mount, Mount::Offset([0.0, 0.0, -1.0]));
+        assert_eq!(
+            prefab.hardpoints[0].gimbal,
+            Some(GimbalConfig {
+                max_angle: 5.0,
+                speed: 30.0
+            })
+        );
+        assert_eq!(prefab.hardpoints[1].gimbal, None);
+        assert_eq!(
+            prefab.hardpoints[1].weapon,
+            WeaponConfig::FlakCannon { rate_of_fire: 5.0 }
+        );
+        assert_eq!(
+            prefab.critical_zones,
+            vec![CriticalZoneConfig {
+                name: String::from("reactor"),
+                offset: [0.0, 0.0, 1.5],
+                radius: 0.5,
+                multiplier: 3.0,
+            }]
+        );
+        assert_eq!(prefab.ai, None);
+    }
+
+    #[test]
+    fn test_shipped_prefabs() {
+        let files = std::fs::read_dir("assets/ships").unwrap();
+        for path in files.map(|file| file.unwrap().path()) {
+            let text = std::fs::read_to_string(&path).unwrap();
+            if let Err(err) = ron::from_str::<ShipPrefab>(&text) {
+                panic!("{}: {err}", path.display());
+            }
+        }
+    }
+}
+
+/// Role-specific setup of the model nodes, run right after the one of the prefab.
+#[derive(Component, Clone)]
+pub struct ShipRig(Arc<dyn Fn(&mut Commands, &[EntityRef], &ShipPrefab) + Send + Sync + 'static>);
+
+impl ShipRig {
+    pub fn new<F>(setup_fn: F) -> Self
+    where
+        F: Fn(&mut Commands, &[EntityRef], &ShipPrefab) + Send + Sync + 'static,
+    {
+        Self(Arc::new(setup_fn))
+    }
+}
+
+/// Hardpoints mounted on top of the ones of the prefab, inserted along with the `ShipBundle`,
+/// like weapons of the drones of later waves.
+#[derive(Component, Clone, Default)]
+pub struct ExtraHardpoints(pub Vec<Hardpoint>);
+
+/// Ship waiting for its prefab to be loaded
+#[derive(Component, Default)]
+struct Unbuilt;
+
+/// Ship built from the prefab once it's loaded, roles insert their own components along with it.
+#[derive(Bundle)]
+pub struct ShipBundle {
+    pub prefab: Handle<ShipPrefab>,
+    pub spatial: SpatialBundle,
+    unbuilt: Unbuilt,
+}
+
+impl ShipBundle {
+    pub fn new(prefab: Handle<ShipPrefab>, transform: Transform) -> Self {
+        Self {
+            prefab,
+            spatial: SpatialBundle::from_transform(transform),
+            unbuilt: Unbuilt,
+        }
+    }
+}
+
+/// Angular velocity limit of ships flown by the AI, in rad/s
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct MaxRotationSpeed(pub f32);
+
+/// Main engine force of ships flown by the AI, in newtons
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct MaxThrust(pub f32);
+
+/// Named model nodes, GLTF primitives are skipped as they are named after their meshes
+fn named_nodes<'w>(entities: &[EntityRef<'w>]) -> Vec<(Entity, &'w Name)> {
+    entities
+        .iter()
+        .filter(|e| !e.contains::<Handle<Mesh>>())
+        .filter_map(|e| Some((e.id(), e.get::<Name>()?)))
+        .collect()
+}
+
+/// Assembles the ship from the model nodes once its scene is loaded
+fn setup_model(prefab: &ShipPrefab, commands: &mut Commands, entities: &[EntityRef]) {
+    let Some(root) = entities.iter().find(|e| e.contains::<SceneInstance>()) else {
+        return;
+    };
+    let root = root.id();
+    let nodes = named_nodes(entities);
+    let with_prefix = |prefix: &str| -> Vec<Entity> {
+        nodes
+            .iter()
+            .filter(|(_, name)| name.starts_with(prefix))
+            .map(|&(entity, _)| entity)
+            .collect()
+    };
+
+    if let ColliderShape::ConvexHull(prefix) = &prefab.collider {
+        commands
+            .entity(root)
+            .insert(collider_setup::ConvexHull::new(with_prefix(prefix)));
+    }
+
+    for hardpoint in prefab.hardpoints.iter() {
+        match &hardpoint.mount {
+            Mount::Nodes(prefix) => {
+                for node in with_prefix(prefix) {
+                    hardpoint.mount(&mut commands.entity(node), Vec::new());
+                }
+            }
+            Mount::Barrels { barrels, carrier } => {
+                let barrels = with_prefix(barrels);
+                for &barrel in barrels.iter() {
+                    commands.entity(barrel).insert(gun::Barrel);
+                }
+                let carrier = match carrier {
+                    Some(prefix) => with_prefix(prefix).first().copied(),
+                    None => Some(root),
+                };
+                match carrier {
+                    Some(carrier) => hardpoint.mount(&mut commands.entity(carrier), barrels),
+                    None => warn!("No carrier node for {:?}", hardpoint.weapon),
+                }
+            }
+            // Spawned along with the ship
+            Mount::Offset(_) => {}
+        }
+    }
+
+    // Main engine thrust is shared between the thruster nodes
+    if let Some(layout) = prefab.thrusters.as_ref().filter(|l| !l.nodes.is_empty()) {
+        let thrusters = with_prefix(&layout.nodes);
+        for &entity in thrusters.iter() {
+            commands.entity(entity).insert(thruster::Thruster::new(
+                layout.max_thrust / thrusters.len() as f32,
+            ));
+        }
+    }
+}
+
+fn build_ships(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    prefabs: Res<Assets<ShipPrefab>>,
+    ships: Query<
+        (
+            Entity,
+            &Handle<ShipPrefab>,
+            Option<&ShipRig>,
+            Option<&ExtraHardpoints>,
+        ),
+        With<Unbuilt>,
+    >,
+) {
+    for (entity, handle, rig, extra) in ships.iter() {
+        let Some(prefab) = prefabs.get(handle) else {
+            continue;
+        };
+        let prefab = match extra {
+            Some(ExtraHardpoints(hardpoints)) => {
+                let mut prefab = prefab.clone();
+                prefab.hardpoints.extend(hardpoints.iter().cloned());
+                Cow::Owned(prefab)
+            }
+            None => Cow::Borrowed(prefab),
+        };
+        let mut ship = commands.entity(entity);
+        ship.remove::<Unbuilt>();
+
+        prefab.insert_stats(&mut ship);
+        if let ColliderShape::Ball(radius) = prefab.collider {
+            ship.insert(Collider::ball(radius));
+        }
+        if let Some(mass) = prefab.mass {
+            ship.insert(collider_setup::MassOverride::Mass(mass));
+        }
+        if let Some(layout) = &prefab.thrusters {
+            ship.insert(MaxThrust(layout.max_thrust))
+                .insert(MaxRotationSpeed(layout.max_rotation_speed.to_radians()));
+            if let Some((half_width, half_length)) = layout.hull {
+                let thrusters =
+                    thruster::hull_thrusters(half_width, half_length, layout.max_thrust);
+                ship.with_children(|parent| {
+                    for (offset, thruster) in thrusters {
+                        parent
+                            .spawn(TransformBundle::from(Transform::from_translation(offset)))
+                            .insert(thruster)
+                            .insert(Collider::ball(HULL_THRUSTER_RADIUS))
+                            .insert(ColliderMassProperties::Density(0.0))
+                            .insert(Name::new("Thruster"));
+                    }
+                });
+            }
+        }
+
+        let offsets: Vec<_> = prefab
+            .hardpoints
+            .iter()
+            .filter_map(|hardpoint| match hardpoint.mount {
+                Mount::Offset(offset) => Some((Vec3::from(offset), hardpoint)),
+                _ => None,
+            })
+            .collect();
+        if !offsets.is_empty() {
+            ship.with_children(|parent| {
+                for (offset, hardpoint) in offsets {
+                    let transform = Transform::from_translation(offset);
+                    hardpoint.mount(
+                        &mut parent.spawn(TransformBundle::from(transform)),
+                        Vec::new(),
+                    );
+                }
+            });
+        }
+        // Ships with a reactor blow up with a blast pushing everything around
+        if prefab
+            .critical_zones
+            .iter()
+            .any(|zone| zone.name == "reactor")
+        {
+            ship.insert(Reactor);
+        }
+        if !prefab.critical_zones.is_empty() {
+            ship.with_children(|parent| {
+                for zone in prefab.critical_zones.iter() {
+                    let transform = Transform::from_translation(Vec3::from(zone.offset));
+                    parent
+                        .spawn(TransformBundle::from(transform))
+                        .insert(CriticalZone {
+                            multiplier: zone.multiplier,
+                        })
+                        .insert(Collider::ball(zone.radius))
+                        // Zones are inside the hull, so they don't add any mass to the ship
+                        .insert(ColliderMassProperties::Density(0.0))
+                        .insert(Name::new(zone.name.clone()));
+                }
+            });
+        }
+
+        if let Some(model) = &prefab.model {
+            let prefab = prefab.clone().into_owned();
+            let rig = rig.cloned();
+            ship.insert(assets.load::<Scene, _>(model.as_str()))
+                .insert(SetupRequired::new(move |commands, entities| {
+                    setup_model(&prefab, commands, entities);
+                    if let Some(ShipRig(rig)) = &rig {
+                        rig(commands, entities, &prefab);
+                    }
+                }));
+        }
+    }
+}
+
+/// Loads `.ship.ron` prefabs and builds `ShipBundle`s from them.
+pub struct PrefabPlugin;
+impl Plugin for PrefabPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<ShipPrefab>()
+            .init_asset_loader::<ShipPrefabLoader>()
+            .register_type::<MaxRotationSpeed>()
+            .register_type::<MaxThrust>()
+            .add_system(build_ships);
+    }
+}
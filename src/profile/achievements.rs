@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use super::{save_profile, Profile, Stats};
+use crate::{components::Fraction, player::Player, projectile::DamageEvent};
+
+/// Time window for `Achievement::DroneSweep`, in seconds
+const SWEEP_WINDOW: f32 = 10.0;
+const SWEEP_KILLS: usize = 3;
+const UNTOUCHABLE_KILLS: u32 = 5;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Achievement {
+    /// Destroy anything
+    FirstBlood,
+    /// Destroy 3 drones in 10 seconds
+    DroneSweep,
+    /// Destroy 5 drones in a row without taking damage
+    Untouchable,
+    /// Keep 50% accuracy after 200 shots
+    Sharpshooter,
+    /// Destroy 100 targets
+    Veteran,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 5] = [
+        Achievement::FirstBlood,
+        Achievement::DroneSweep,
+        Achievement::Untouchable,
+        Achievement::Sharpshooter,
+        Achievement::Veteran,
+    ];
+
+    /// Localization key of the name, the description has the `-desc` suffix
+    pub fn key(self) -> &'static str {
+        match self {
+            Achievement::FirstBlood => "achievement-first-blood",
+            Achievement::DroneSweep => "achievement-drone-sweep",
+            Achievement::Untouchable => "achievement-untouchable",
+            Achievement::Sharpshooter => "achievement-sharpshooter",
+            Achievement::Veteran => "achievement-veteran",
+        }
+    }
+
+    /// Whether achievement conditions are met by cumulative stats.
+    /// Always false for achievements that depend on gameplay events.
+    fn reached(self, stats: &Stats) -> bool {
+        match self {
+            Achievement::FirstBlood => stats.total_kills() >= 1,
+            Achievement::Sharpshooter => stats.shots_fired >= 200 && stats.accuracy() >= 0.5,
+            Achievement::Veteran => stats.total_kills() >= 100,
+            Achievement::DroneSweep | Achievement::Untouchable => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Achievement, Stats};
+
+    #[test]
+    fn test_stats_achievements() {
+        let mut stats = Stats::default();
+        assert!(!Achievement::FirstBlood.reached(&stats));
+
+        stats.kills.insert("Turret".to_owned(), 1);
+        assert!(Achievement::FirstBlood.reached(&stats));
+        assert!(!Achievement::Veteran.reached(&stats));
+
+        stats.shots_fired = 100;
+        stats.shots_hit = 100;
+        assert!(!Achievement::Sharpshooter.reached(&stats));
+        stats.shots_fired = 200;
+        assert!(Achievement::Sharpshooter.reached(&stats));
+    }
+}
+
+/// Emitted once when the achievement is unlocked for the first time.
+pub struct AchievementUnlockedEvent(pub Achievement);
+
+fn unlock(
+    profile: &mut Profile,
+    ev_unlocked: &mut EventWriter<AchievementUnlockedEvent>,
+    achievement: Achievement,
+) {
+    if profile.achievements.insert(achievement) {
+        info!("Achievement unlocked: {achievement:?}");
+        ev_unlocked.send(AchievementUnlockedEvent(achievement));
+        // Save right away, as unlocks are rare and shouldn't be lost
+        save_profile(profile);
+    }
+}
+
+#[derive(Default)]
+struct CombatState {
+    /// Times of recent drone kills, for `Achievement::DroneSweep`
+    drone_kills: VecDeque<f32>,
+    /// Drones destroyed since the player was hit last time
+    kills_without_damage: u32,
+}
+
+fn combat_achievements(
+    time: Res<Time>,
+    mut profile: ResMut<Profile>,
+    mut ev_damage: EventReader<DamageEvent>,
+    mut ev_unlocked: EventWriter<AchievementUnlockedEvent>,
+    player: Query<Entity, With<Player>>,
+    fractions: Query<&Fraction>,
+    mut state: Local<CombatState>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+
+    for ev in ev_damage.iter() {
+        if ev.target == player {
+            state.kills_without_damage = 0;
+            continue;
+        }
+        let is_drone = matches!(fractions.get(ev.target), Ok(Fraction::Drones));
+        if ev.attacker != Some(player) || !ev.destroyed || !is_drone {
+            continue;
+        }
+
+        state.drone_kills.push_back(now);
+        state
+            .drone_kills
+            .retain(|&kill_time| now - kill_time <= SWEEP_WINDOW);
+        if state.drone_kills.len() >= SWEEP_KILLS {
+            unlock(&mut profile, &mut ev_unlocked, Achievement::DroneSweep);
+        }
+
+        state.kills_without_damage += 1;
+        if state.kills_without_damage >= UNTOUCHABLE_KILLS {
+            unlock(&mut profile, &mut ev_unlocked, Achievement::Untouchable);
+        }
+    }
+}
+
+fn stats_achievements(
+    mut profile: ResMut<Profile>,
+    mut ev_unlocked: EventWriter<AchievementUnlockedEvent>,
+) {
+    for achievement in Achievement::ALL {
+        if !profile.achievements.contains(&achievement) && achievement.reached(&profile.stats) {
+            unlock(&mut profile, &mut ev_unlocked, achievement);
+        }
+    }
+}
+
+pub(super) struct AchievementsPlugin;
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AchievementUnlockedEvent>()
+            .add_system(combat_achievements.after(super::track_damage))
+            .add_system(stats_achievements.after(super::track_damage));
+    }
+}
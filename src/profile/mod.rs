@@ -0,0 +1,225 @@
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use crate::{
+    drone::Drone,
+    player::Player,
+    projectile::{DamageEvent, DamageSystems, Owner},
+    salvage::SalvageCollectedEvent,
+    thruster::Thruster,
+};
+use achievements::Achievement;
+
+pub mod achievements;
+mod stats_screen;
+
+/// Profile file is stored in the working directory
+const PROFILE_PATH: &str = "profile.json";
+/// Profile is saved periodically, so progress isn't lost if the game crashes
+const SAVE_INTERVAL: f32 = 30.0;
+
+/// Cumulative player statistics over all sessions.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Stats {
+    /// Number of destroyed entities by their name
+    pub kills: BTreeMap<String, u32>,
+    pub shots_fired: u64,
+    pub shots_hit: u64,
+    pub deaths: u32,
+    /// Total time in game, in seconds
+    pub playtime: f64,
+    pub best_wave: u32,
+    /// Total value of the salvage collected from wrecks
+    pub salvage: u64,
+}
+
+impl Stats {
+    pub fn total_kills(&self) -> u32 {
+        self.kills.values().sum()
+    }
+
+    /// Ratio of hits to fired shots, from 0 to 1
+    pub fn accuracy(&self) -> f32 {
+        if self.shots_fired == 0 {
+            return 0.0;
+        }
+        // Explosions might hit several targets with one shot
+        (self.shots_hit as f32 / self.shots_fired as f32).min(1.0)
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Profile {
+    pub stats: Stats,
+    pub achievements: BTreeSet<Achievement>,
+}
+
+impl Profile {
+    /// Loads profile from the `path` or creates a new one if it doesn't exist or is broken
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => {
+                info!("Creating new profile, can't read {}: {err}", path.display());
+                return Profile::default();
+            }
+        };
+        serde_json::from_str(&content).unwrap_or_else(|err| {
+            warn!("Creating new profile, {} is broken: {err}", path.display());
+            Profile::default()
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Profile, Stats};
+
+    #[test]
+    fn test_accuracy() {
+        let mut stats = Stats::default();
+        assert_eq!(stats.accuracy(), 0.0);
+        stats.shots_fired = 4;
+        stats.shots_hit = 1;
+        assert_eq!(stats.accuracy(), 0.25);
+        stats.shots_hit = 5;
+        assert_eq!(stats.accuracy(), 1.0);
+    }
+
+    #[test]
+    fn test_profile_serialization() {
+        let mut profile = Profile::default();
+        profile.stats.kills.insert("Drone::Praetor".to_owned(), 3);
+        profile.stats.shots_fired = 100;
+        profile.stats.playtime = 12.5;
+
+        let json = serde_json::to_string(&profile).unwrap();
+        assert_eq!(serde_json::from_str::<Profile>(&json).unwrap(), profile);
+
+        // missing fields are filled with defaults, so old profiles are still readable
+        let profile: Profile = serde_json::from_str(r#"{"stats":{"deaths":2}}"#).unwrap();
+        assert_eq!(profile.stats.deaths, 2);
+        assert_eq!(profile.stats.shots_fired, 0);
+    }
+}
+
+/// Name used to group kills: the root entity name without the instance number
+fn kill_name(name: &str) -> &str {
+    name.split(" #").next().unwrap_or(name).trim()
+}
+
+fn track_shots(
+    mut profile: ResMut<Profile>,
+    player: Query<Entity, With<Player>>,
+    projectiles: Query<&Owner, Added<Owner>>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    let shots = projectiles.iter().filter(|owner| owner.0 == player).count();
+    if shots > 0 {
+        profile.stats.shots_fired += shots as u64;
+    }
+}
+
+fn track_damage(
+    mut profile: ResMut<Profile>,
+    mut ev_damage: EventReader<DamageEvent>,
+    player: Query<Entity, With<Player>>,
+    names: Query<&Name>,
+    drones: Query<&Drone>,
+    parent_query: Query<&Parent>,
+    thrusters: Query<(), With<Thruster>>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+
+    for ev in ev_damage.iter() {
+        if ev.target == player && ev.destroyed {
+            profile.stats.deaths += 1;
+        }
+        if ev.attacker != Some(player) {
+            continue;
+        }
+        profile.stats.shots_hit += 1;
+        // Shooting off a thruster cripples the ship, but it's not a kill yet
+        if ev.destroyed && !thrusters.contains(ev.target) {
+            // Turrets and ships consist of several parts, so kills are counted by the root entity
+            let root = parent_query
+                .iter_ancestors(ev.target)
+                .last()
+                .unwrap_or(ev.target);
+            // Drones have individual callsigns, so their kills are grouped by the type
+            let name = match drones.get(root) {
+                Ok(drone) => drone.class_name(),
+                Err(_) => names
+                    .get(root)
+                    .map_or("Unknown", |name| kill_name(name.as_str())),
+            };
+            *profile.stats.kills.entry(name.to_owned()).or_default() += 1;
+        }
+    }
+}
+
+fn track_salvage(
+    mut profile: ResMut<Profile>,
+    mut ev_collected: EventReader<SalvageCollectedEvent>,
+) {
+    for ev in ev_collected.iter() {
+        profile.stats.salvage += ev.value as u64;
+    }
+}
+
+fn track_playtime(time: Res<Time>, mut profile: ResMut<Profile>) {
+    profile.stats.playtime += time.delta_seconds_f64();
+}
+
+fn save_profile(profile: &Profile) {
+    if let Err(err) = profile.save(PROFILE_PATH) {
+        error!("Failed to save profile to {PROFILE_PATH}: {err}");
+    }
+}
+
+fn autosave(time: Res<Time>, profile: Res<Profile>, mut since_save: Local<f32>) {
+    *since_save += time.delta_seconds();
+    if *since_save >= SAVE_INTERVAL {
+        *since_save = 0.0;
+        save_profile(&profile);
+    }
+}
+
+fn save_on_exit(profile: Res<Profile>, ev_exit: EventReader<AppExit>) {
+    if !ev_exit.is_empty() {
+        save_profile(&profile);
+    }
+}
+
+/// Player profile with lifetime statistics and achievements, saved periodically and on exit.
+pub struct ProfilePlugin;
+impl Plugin for ProfilePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Profile::load(PROFILE_PATH))
+            .add_event::<SalvageCollectedEvent>()
+            .add_plugin(achievements::AchievementsPlugin)
+            .add_plugin(stats_screen::StatsScreenPlugin)
+            .add_system(track_shots)
+            .add_system(track_damage.after(DamageSystems))
+            .add_system(track_salvage)
+            .add_system(track_playtime)
+            .add_system(autosave)
+            // `AppExit` is sent at the `PostUpdate` stage when the last window is closed
+            .add_system_to_stage(CoreStage::Last, save_on_exit);
+    }
+}
@@ -0,0 +1,134 @@
+use bevy::prelude::*;
+
+use super::{achievements::Achievement, Profile};
+use crate::localization::Localization;
+
+const SCREEN_WIDTH: f32 = 480.0;
+
+#[derive(Component)]
+struct StatsScreen;
+
+#[derive(Component)]
+struct StatsText;
+
+fn setup_stats_screen(mut commands: Commands, assets: Res<AssetServer>) {
+    let font = assets.load("fonts/FiraMono-Medium.ttf");
+
+    // Hidden panel in the middle of the screen, shown on demand
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(SCREEN_WIDTH), Val::Auto),
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Percent(50.0),
+                    top: Val::Percent(20.0),
+                    ..default()
+                },
+                margin: UiRect {
+                    left: Val::Px(-SCREEN_WIDTH / 2.0),
+                    ..default()
+                },
+                padding: UiRect::all(Val::Px(10.0)),
+                display: Display::None,
+                ..default()
+            },
+            background_color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+            ..default()
+        })
+        .insert(StatsScreen)
+        .insert(Name::new("Stats Screen"))
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font,
+                        font_size: 24.0,
+                        color: Color::WHITE,
+                    },
+                ))
+                .insert(StatsText);
+        });
+}
+
+fn toggle_stats_screen(
+    keys: Res<Input<KeyCode>>,
+    mut screen: Query<&mut Style, With<StatsScreen>>,
+) {
+    if !keys.just_pressed(KeyCode::Tab) {
+        return;
+    }
+    let Ok(mut style) = screen.get_single_mut() else {
+        return;
+    };
+
+    style.display = match style.display {
+        Display::None => Display::Flex,
+        Display::Flex => Display::None,
+    };
+}
+
+fn update_stats_screen(
+    profile: Res<Profile>,
+    localization: Res<Localization>,
+    screen: Query<&Style, With<StatsScreen>>,
+    mut text: Query<&mut Text, With<StatsText>>,
+) {
+    let Ok(style) = screen.get_single() else {
+        return;
+    };
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+    if style.display == Display::None {
+        return;
+    }
+
+    let stats = &profile.stats;
+    let playtime = stats.playtime as u64;
+    let mut lines = vec![
+        localization.get("stats-title").to_owned(),
+        localization.format(
+            "stats-playtime",
+            &[
+                ("hours", &(playtime / 3600)),
+                ("minutes", &format!("{:02}", playtime / 60 % 60)),
+            ],
+        ),
+        localization.format("stats-kills", &[("kills", &stats.total_kills())]),
+    ];
+    lines.extend(stats.kills.iter().map(|(name, count)| {
+        localization.format("stats-kill", &[("name", name), ("count", count)])
+    }));
+    lines.extend([
+        localization.format(
+            "stats-shots",
+            &[("fired", &stats.shots_fired), ("hit", &stats.shots_hit)],
+        ),
+        localization.format(
+            "stats-accuracy",
+            &[("accuracy", &format!("{:.1}", 100.0 * stats.accuracy()))],
+        ),
+        localization.format("stats-deaths", &[("deaths", &stats.deaths)]),
+        localization.format("stats-best-wave", &[("wave", &stats.best_wave)]),
+        localization.format("stats-salvage", &[("salvage", &stats.salvage)]),
+        localization.format(
+            "stats-achievements",
+            &[
+                ("unlocked", &profile.achievements.len()),
+                ("total", &Achievement::ALL.len()),
+            ],
+        ),
+    ]);
+    text.sections[0].value = lines.join("\n");
+}
+
+pub(super) struct StatsScreenPlugin;
+impl Plugin for StatsScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_stats_screen)
+            .add_system(toggle_stats_screen)
+            .add_system(update_stats_screen.after(toggle_stats_screen));
+    }
+}
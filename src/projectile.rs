@@ -1,89 +1,258 @@
+use bevy::ecs::entity::{EntityMap, MapEntities, MapEntitiesError};
+use bevy::ecs::reflect::ReflectMapEntities;
+use bevy::ecs::system::SystemParam;
 use bevy::pbr::NotShadowCaster;
 use bevy::pbr::NotShadowReceiver;
 use bevy::prelude::*;
+use bevy::render::RenderApp;
+use bevy::utils::HashMap;
 use bevy_hanabi::*;
 use bevy_rapier3d::prelude::*;
 
-/// Entity lifetime in seconds, after which entity should be destroyed
-#[derive(Component, Clone)]
-pub struct Lifetime(pub f32);
+use crate::collider_setup::{FitPrimitive, MassOverride, PartShape};
+use crate::components::{Damage, Fraction, HitPoints, Lifetime, Pooled, Respawnable};
+use crate::ion_storm::InIonStorm;
+use crate::scene_setup::GltfExtrasApp;
 
-fn lifetime(mut commands: Commands, time: Res<Time>, mut query: Query<(Entity, &mut Lifetime)>) {
-    for (entity, mut lifetime) in query.iter_mut() {
-        lifetime.0 -= time.delta_seconds();
-        if lifetime.0 <= 0.0 {
-            commands.entity(entity).despawn_recursive();
+/// Entity that fired the projectile, usually the root entity of a ship or a turret.
+/// Projectiles never hit their owners.
+#[derive(Component, Clone, Copy, Reflect)]
+#[reflect(Component, MapEntities)]
+pub struct Owner(pub Entity);
+
+// Reflection creates components from the world before applying the reflected value,
+// so components referencing entities get a placeholder, like `Parent` does.
+impl FromWorld for Owner {
+    fn from_world(_world: &mut World) -> Self {
+        Self(Entity::from_raw(u32::MAX))
+    }
+}
+
+impl MapEntities for Owner {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        // Owner might be outside of the scene, then it's kept as is
+        if let Ok(owner) = entity_map.get(self.0) {
+            self.0 = owner;
+        }
+        Ok(())
+    }
+}
+
+/// Steers projectile towards the target keeping its speed.
+/// Projectile flies straight once the target is destroyed.
+#[derive(Component, Clone, Reflect)]
+#[reflect(Component, MapEntities)]
+pub struct Homing {
+    pub target: Entity,
+    /// Maximum turn rate in rad/s
+    pub turn_rate: f32,
+}
+
+impl FromWorld for Homing {
+    fn from_world(_world: &mut World) -> Self {
+        Self {
+            target: Entity::from_raw(u32::MAX),
+            turn_rate: 0.0,
+        }
+    }
+}
+
+impl MapEntities for Homing {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        if let Ok(target) = entity_map.get(self.target) {
+            self.target = target;
+        }
+        Ok(())
+    }
+}
+
+// Targets without a collider are out of the battle, like pooled drones waiting for a respawn
+fn homing(
+    time: Res<Time>,
+    mut projectiles: Query<(&Homing, &GlobalTransform, &mut Velocity)>,
+    targets: Query<&GlobalTransform, With<Collider>>,
+) {
+    for (homing, transform, mut velocity) in projectiles.iter_mut() {
+        let Ok(target) = targets.get(homing.target) else {
+            continue;
+        };
+
+        let speed = velocity.linvel.length();
+        let current = velocity.linvel.normalize_or_zero();
+        let desired = (target.translation() - transform.translation()).normalize_or_zero();
+        let angle = current.angle_between(desired);
+        if speed == 0.0 || desired == Vec3::ZERO || angle <= f32::EPSILON {
+            continue;
         }
+
+        let max_turn = homing.turn_rate * time.delta_seconds();
+        let turn = Quat::from_rotation_arc(current, desired);
+        let direction = Quat::IDENTITY.slerp(turn, (max_turn / angle).min(1.0)) * current;
+        velocity.linvel = direction * speed;
     }
 }
 
-#[derive(Component, Clone)]
-pub struct Damage(pub u32);
+/// Unpropelled projectile, like a bullet or a shell, that drops along a ballistic arc under the
+/// gravity of the `RapierConfiguration`. Other bodies get zero `GravityScale`, so ships and
+/// wrecks keep floating.
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct Ballistic;
 
+/// Self-propelled projectile, followed by the missile cam when launched by the player.
 #[derive(Component, Clone, Default, Reflect)]
 #[reflect(Component)]
-pub struct HitPoints {
+pub struct Rocket;
+
+/// Only `Ballistic` projectiles fall under the physics gravity
+fn scale_gravity(
+    mut commands: Commands,
+    bodies: Query<(Entity, Option<&Ballistic>), (Added<RigidBody>, Without<GravityScale>)>,
+) {
+    for (entity, ballistic) in bodies.iter() {
+        let scale = if ballistic.is_some() { 1.0 } else { 0.0 };
+        commands.entity(entity).insert(GravityScale(scale));
+    }
+}
+
+/// Emitted every time a projectile hits something, even if the target has no `HitPoints`.
+pub struct DamageEvent {
+    pub target: Entity,
+    pub attacker: Option<Entity>,
+    /// Attacker position at the moment of hit,
+    /// or the point projectile came from if the attacker doesn't exist anymore
+    pub attacker_position: Vec3,
+    /// Hit point in world coordinates
+    pub position: Vec3,
+    pub damage: u32,
+    /// Part of the `damage` absorbed by the target's shield
+    pub absorbed: u32,
+    /// Target is destroyed by this hit. It's despawned at the end of the stage,
+    /// so systems running after `DamageSystems` still can access it.
+    pub destroyed: bool,
+}
+
+/// Emitted for every played explosion, like a rocket hitting its target or a flak shell going
+/// off near one.
+pub struct ExplosionEvent {
+    pub effect: ExplosionEffect,
+    pub position: Vec3,
+}
+
+/// Label of systems that apply damage and emit `DamageEvent`s
+#[derive(SystemLabel)]
+pub struct DamageSystems;
+
+/// Energy shield that absorbs damage before `HitPoints` and recharges once not hit for a while.
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+#[cfg_attr(feature = "devtools", derive(bevy_inspector_egui::Inspectable))]
+pub struct Shield {
     maximum: u32,
-    current: u32,
+    #[cfg_attr(feature = "devtools", inspectable(min = 0.0))]
+    current: f32,
+    /// Recharge speed in points per second
+    #[cfg_attr(feature = "devtools", inspectable(min = 0.0))]
+    recharge_rate: f32,
+    /// Seconds left before recharge starts
+    #[cfg_attr(feature = "devtools", inspectable(read_only))]
+    recharge_delay: f32,
 }
 
-impl HitPoints {
-    pub fn new(maximum: u32) -> Self {
-        HitPoints {
+impl Shield {
+    /// Seconds without hits before the shield starts to recharge
+    const RECHARGE_DELAY: f32 = 3.0;
+
+    pub fn new(maximum: u32, recharge_rate: f32) -> Self {
+        Self {
             maximum,
-            current: maximum,
+            current: maximum as f32,
+            recharge_rate,
+            recharge_delay: 0.0,
         }
     }
     pub fn percent(&self) -> u32 {
-        100 * self.current / self.maximum
+        (100.0 * self.current / self.maximum.max(1) as f32) as u32
     }
-    pub fn dead(&self) -> bool {
-        self.current == 0
+    /// Absorbs as much `damage` as possible and returns the rest
+    pub fn absorb(&mut self, damage: u32) -> u32 {
+        self.recharge_delay = Self::RECHARGE_DELAY;
+        let absorbed = self.current.min(damage as f32);
+        self.current -= absorbed;
+        damage - absorbed as u32
     }
-    pub fn hit(&mut self, damage: u32) -> &mut Self {
-        self.current = self.current.saturating_sub(damage);
-        self
+    fn recharge(&mut self, seconds: f32) {
+        if self.recharge_delay > 0.0 {
+            self.recharge_delay -= seconds;
+        } else {
+            self.current = (self.current + self.recharge_rate * seconds).min(self.maximum as f32);
+        }
+    }
+}
+
+/// Plating that ignores a part of every hit, e.g. of a stowed turret. Applied before the `Shield`.
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct Armor {
+    /// Part of the damage that is ignored, from 0 to 1
+    pub reduction: f32,
+}
+
+impl Armor {
+    pub fn new(reduction: f32) -> Self {
+        Self { reduction }
+    }
+
+    /// Damage that gets through the armor
+    pub fn reduce(&self, damage: u32) -> u32 {
+        (damage as f32 * (1.0 - self.reduction.clamp(0.0, 1.0))).round() as u32
+    }
+}
+
+/// Shields don't recharge inside ion storms
+fn recharge_shields(time: Res<Time>, mut shields: Query<&mut Shield, Without<InIonStorm>>) {
+    for mut shield in shields.iter_mut() {
+        if shield.current < shield.maximum as f32 || shield.recharge_delay > 0.0 {
+            shield.recharge(time.delta_seconds());
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::HitPoints;
+    use super::{Armor, Shield};
 
     #[test]
-    fn test_new_hp_always_100() {
-        assert_eq!(HitPoints::new(1).percent(), 100);
-        assert_eq!(HitPoints::new(2).percent(), 100);
-        assert_eq!(HitPoints::new(111).percent(), 100);
+    fn test_shield_absorb_and_recharge() {
+        let mut shield = Shield::new(10, 5.0);
+        assert_eq!(shield.absorb(4), 0);
+        assert_eq!(shield.percent(), 60);
+        assert_eq!(shield.absorb(10), 4);
+        assert_eq!(shield.percent(), 0);
+
+        // no recharge right after the hit
+        shield.recharge(1.0);
+        assert_eq!(shield.percent(), 0);
+        shield.recharge(Shield::RECHARGE_DELAY);
+        shield.recharge(1.0);
+        assert_eq!(shield.percent(), 50);
+        shield.recharge(10.0);
+        assert_eq!(shield.percent(), 100);
     }
 
     #[test]
-    fn test_hp_hit() {
-        assert_eq!(HitPoints::new(1).hit(0).percent(), 100);
-        assert_eq!(HitPoints::new(1).hit(1).percent(), 0);
-        assert_eq!(HitPoints::new(1).hit(10).percent(), 0);
-        assert_eq!(HitPoints::new(50).hit(25).percent(), 50);
-        assert_eq!(HitPoints::new(100).hit(0).percent(), 100);
-        assert_eq!(HitPoints::new(100).hit(1).percent(), 99);
-        assert_eq!(HitPoints::new(100).hit(99).percent(), 1);
-        assert_eq!(HitPoints::new(100).hit(100).percent(), 0);
-        assert_eq!(HitPoints::new(100).hit(101).percent(), 0);
-
-        assert!(!HitPoints::new(1).hit(0).dead());
-        assert!(HitPoints::new(1).hit(1).dead());
-        assert!(HitPoints::new(1).hit(10).dead());
-        assert!(!HitPoints::new(50).hit(25).dead());
-        assert!(!HitPoints::new(100).hit(0).dead());
-        assert!(!HitPoints::new(100).hit(1).dead());
-        assert!(!HitPoints::new(100).hit(99).dead());
-        assert!(HitPoints::new(100).hit(100).dead());
-        assert!(HitPoints::new(100).hit(101).dead());
+    fn test_armor_reduce() {
+        assert_eq!(Armor::default().reduce(10), 10);
+        assert_eq!(Armor::new(0.5).reduce(10), 5);
+        assert_eq!(Armor::new(0.5).reduce(1), 1);
+        assert_eq!(Armor::new(2.0).reduce(10), 0);
     }
 }
 
 /// Entity explosion effect. If set - entity will be destroyed on collision
 /// with spawning a corresponding effect.
-#[derive(Component, Copy, Clone, PartialEq, Eq)]
+#[derive(Component, Copy, Clone, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
 pub enum ExplosionEffect {
     Debug,
     Small,
@@ -245,51 +414,421 @@ fn setup(mut commands: Commands, mut effects: ResMut<Assets<EffectAsset>>) {
         .insert(Name::new("ExplosionEffect::Small"));
 }
 
+/// Weak spot of a ship, like a cockpit or a reactor, with its own small collider.
+/// Hits there are taken by the closest ancestor with `HitPoints`, with damage multiplied.
+#[derive(Component, Clone, Reflect)]
+#[reflect(Component)]
+pub struct CriticalZone {
+    pub multiplier: f32,
+}
+
+impl Default for CriticalZone {
+    fn default() -> Self {
+        Self { multiplier: 1.0 }
+    }
+}
+
+/// Critical zones get a ball collider around their meshes, that doesn't add any mass to the ship
+fn setup_critical_zones(
+    mut commands: Commands,
+    zones: Query<Entity, (Added<CriticalZone>, Without<Collider>)>,
+) {
+    for entity in zones.iter() {
+        commands
+            .entity(entity)
+            .insert(FitPrimitive {
+                mesh_source: entity,
+                shape: PartShape::Ball,
+            })
+            .insert(MassOverride::Density(0.0));
+    }
+}
+
+type Targets<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Option<&'static mut HitPoints>,
+        Option<&'static mut Shield>,
+        Option<&'static CriticalZone>,
+        Option<&'static Armor>,
+        Option<&'static Respawnable>,
+        Option<&'static Pooled>,
+    ),
+    Without<Damage>,
+>;
+
+/// Entity that takes the hit on `collider` and the damage multiplier,
+/// `None` if the collider can't be hit, e.g. it's another projectile.
+fn resolve_hit(
+    collider: Entity,
+    targets: &Targets,
+    parent_query: &Query<&Parent>,
+) -> Option<(Entity, f32)> {
+    let (_, _, zone, _, _, _) = targets.get(collider).ok()?;
+    match zone {
+        None => Some((collider, 1.0)),
+        Some(zone) => parent_query
+            .iter_ancestors(collider)
+            .find(|&e| matches!(targets.get(e), Ok((Some(_), _, _, _, _, _))))
+            .map(|target| (target, zone.multiplier)),
+    }
+}
+
+/// Damages shield first and then hit points, destroying the target once it's dead.
+/// Destroyed targets are despawned unless `kept`, like `Respawnable` ones waiting for the respawn
+/// and `Pooled` ones returned to their pool.
+/// Returns damage that got through the armor, damage absorbed by the shield and whether
+/// the target is destroyed.
+pub(crate) fn apply_damage(
+    commands: &mut Commands,
+    target: Entity,
+    damage: u32,
+    hp: Option<Mut<HitPoints>>,
+    shield: Option<Mut<Shield>>,
+    armor: Option<&Armor>,
+    kept: bool,
+) -> (u32, u32, bool) {
+    let damage = armor.map_or(damage, |armor| armor.reduce(damage));
+    let rest = match shield {
+        Some(mut shield) => shield.absorb(damage),
+        None => damage,
+    };
+    let destroyed = hp.map_or(false, |mut hp| !hp.dead() && hp.hit(rest).dead());
+    if destroyed && !kept {
+        commands.entity(target).despawn_recursive();
+    }
+    (damage, damage - rest, destroyed)
+}
+
+/// Explosion effects to play, and the writer to report them as `ExplosionEvent`s
+#[derive(SystemParam)]
+struct Explosions<'w, 's> {
+    effects: Query<
+        'w,
+        's,
+        (
+            &'static ExplosionEffect,
+            &'static mut ParticleEffect,
+            &'static mut Transform,
+        ),
+    >,
+    ev_explosion: EventWriter<'w, 's, ExplosionEvent>,
+}
+
+/// Moves the explosion effect of the given type to `position` and restarts it
+fn play_explosion(explosions: &mut Explosions, explosive: ExplosionEffect, position: Vec3) {
+    explosions.ev_explosion.send(ExplosionEvent {
+        effect: explosive,
+        position,
+    });
+
+    // Match effect by it's type or use `Debug` if can't find
+    let explosions = &mut explosions.effects;
+    let mut explosion = explosions
+        .iter_mut()
+        .find(|(&effect, _, _)| effect == explosive);
+    if explosion.is_none() {
+        explosion = explosions
+            .iter_mut()
+            .find(|(&effect, _, _)| effect == ExplosionEffect::Debug);
+    }
+
+    let (_, mut effect, mut effect_transform) = explosion.unwrap();
+    effect_transform.translation = position;
+    // Spawner is initialized by the particle system, which isn't running without a renderer
+    if let Some(spawner) = effect.maybe_spawner() {
+        spawner.reset();
+    }
+}
+
 fn hit_collision(
     mut commands: Commands,
     mut collisions: EventReader<CollisionEvent>,
-    projectiles: Query<&Damage>,
-    mut targets: Query<&mut HitPoints>,
+    projectiles: Query<(&Damage, &GlobalTransform, Option<&Velocity>, Option<&Owner>)>,
+    attackers: Query<&GlobalTransform>,
+    parent_query: Query<&Parent>,
+    mut targets: Targets,
+    mut ev_damage: EventWriter<DamageEvent>,
 ) {
+    // A projectile hits only once, a critical zone is preferred over the hull it's inside of
+    let mut hits = HashMap::new();
     for event in collisions.iter() {
         if let CollisionEvent::Started(first, second, _) = event {
-            for (projectile, target) in [(first, second), (second, first)] {
-                if let (Ok(damage), Ok(mut hp)) =
-                    (projectiles.get(*projectile), targets.get_mut(*target))
-                {
-                    if hp.hit(damage.0).dead() {
-                        commands.entity(*target).despawn_recursive();
-                    }
+            for (projectile, collider) in [(*first, *second), (*second, *first)] {
+                let Ok((_, _, _, owner)) = projectiles.get(projectile) else {
+                    continue;
+                };
+                let Some((target, multiplier)) = resolve_hit(collider, &targets, &parent_query)
+                else {
+                    continue;
+                };
+                if matches!(owner, Some(owner) if owner.0 == target) {
+                    continue;
+                }
+                let hit = hits.entry(projectile).or_insert((target, multiplier));
+                if multiplier > hit.1 {
+                    *hit = (target, multiplier);
+                }
+            }
+        }
+    }
+
+    for (projectile, (target, multiplier)) in hits {
+        let (damage, transform, velocity, owner) = projectiles.get(projectile).unwrap();
+        let (hp, shield, _, armor, respawn, pooled) = targets.get_mut(target).unwrap();
+        let kept = respawn.is_some() || pooled.is_some();
+
+        let attacker = owner.map(|owner| owner.0);
+        let attacker_position = match attacker.and_then(|e| attackers.get(e).ok()) {
+            Some(attacker_transform) => attacker_transform.translation(),
+            None => transform.translation() - velocity.map(|v| v.linvel).unwrap_or_default(),
+        };
+        let damage = (damage.0 as f32 * multiplier).round() as u32;
+        let (damage, absorbed, destroyed) =
+            apply_damage(&mut commands, target, damage, hp, shield, armor, kept);
+        ev_damage.send(DamageEvent {
+            target,
+            attacker,
+            attacker_position,
+            position: transform.translation(),
+            damage,
+            absorbed,
+            destroyed,
+        });
+    }
+}
+
+/// Detonates the projectile once it passes within `radius` of a hostile,
+/// damaging everything within `blast_radius` with shrapnel.
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct ProximityFuse {
+    pub radius: f32,
+    pub blast_radius: f32,
+    /// Shrapnel damage at the blast center, decreases linearly to zero at `blast_radius`
+    pub damage: u32,
+    /// Entities of this fraction don't trigger the fuse
+    pub friendly: Option<Fraction>,
+}
+
+fn proximity_fuse(
+    mut commands: Commands,
+    rapier_context: Res<RapierContext>,
+    shells: Query<
+        (
+            Entity,
+            &ProximityFuse,
+            &ExplosionEffect,
+            &Transform,
+            Option<&Owner>,
+        ),
+        Without<ParticleEffect>,
+    >,
+    fractions: Query<&Fraction>,
+    mut targets: Query<
+        (
+            &GlobalTransform,
+            Option<&mut HitPoints>,
+            Option<&mut Shield>,
+            Option<&Armor>,
+            Option<&Respawnable>,
+            Option<&Pooled>,
+        ),
+        (Without<Damage>, Without<CriticalZone>),
+    >,
+    mut explosions: Explosions,
+    mut ev_damage: EventWriter<DamageEvent>,
+) {
+    let filter = QueryFilter::new().exclude_sensors();
+    for (shell, fuse, &explosive, transform, owner) in shells.iter() {
+        let position = transform.translation;
+
+        let mut triggered = false;
+        rapier_context.intersections_with_shape(
+            position,
+            Quat::IDENTITY,
+            &Collider::ball(fuse.radius),
+            filter,
+            |entity| {
+                triggered = fractions
+                    .get(entity)
+                    .map_or(false, |&fraction| Some(fraction) != fuse.friendly);
+                // stop at the first hostile
+                !triggered
+            },
+        );
+        if !triggered {
+            continue;
+        }
+
+        let mut hits = Vec::new();
+        rapier_context.intersections_with_shape(
+            position,
+            Quat::IDENTITY,
+            &Collider::ball(fuse.blast_radius),
+            filter,
+            |entity| {
+                hits.push(entity);
+                true
+            },
+        );
+
+        let attacker = owner.map(|owner| owner.0);
+        let attacker_position = attacker
+            .and_then(|e| targets.get(e).ok())
+            .map_or(position, |(attacker_transform, _, _, _, _, _)| {
+                attacker_transform.translation()
+            });
+        for target in hits {
+            if attacker == Some(target) {
+                continue;
+            }
+            let Ok((target_transform, hp, shield, armor, respawn, pooled)) =
+                targets.get_mut(target)
+            else {
+                continue;
+            };
+
+            let target_position = target_transform.translation();
+            let distance = target_position.distance(position);
+            let damage = (fuse.damage as f32 * (1.0 - distance / fuse.blast_radius)).ceil() as u32;
+            if damage == 0 {
+                continue;
+            }
+            let kept = respawn.is_some() || pooled.is_some();
+            let (damage, absorbed, destroyed) =
+                apply_damage(&mut commands, target, damage, hp, shield, armor, kept);
+            ev_damage.send(DamageEvent {
+                target,
+                attacker,
+                attacker_position,
+                position: target_position,
+                damage,
+                absorbed,
+                destroyed,
+            });
+        }
+
+        play_explosion(&mut explosions, explosive, position);
+        commands.entity(shell).despawn_recursive();
+    }
+}
+
+/// Instantaneous shot along the entity's Y axis, that damages up to `penetration` targets on its
+/// way. The entity itself is the beam, it's stretched to the last hit and then fades by `Lifetime`.
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct Hitscan {
+    pub range: f32,
+    pub damage: u32,
+    /// Number of colliders the shot passes through, including ones without `HitPoints`
+    pub penetration: usize,
+}
+
+fn hitscan(
+    mut commands: Commands,
+    rapier_context: Res<RapierContext>,
+    mut shots: Query<
+        (
+            Entity,
+            &Hitscan,
+            &ExplosionEffect,
+            &mut Transform,
+            Option<&Owner>,
+        ),
+        Without<ParticleEffect>,
+    >,
+    parent_query: Query<&Parent>,
+    mut targets: Targets,
+    mut explosions: Explosions,
+    mut ev_damage: EventWriter<DamageEvent>,
+) {
+    let filter = QueryFilter::new().exclude_sensors();
+    for (shot, hitscan, &explosive, mut transform, owner) in shots.iter_mut() {
+        let origin = transform.translation;
+        let direction = transform.up();
+        let attacker = owner.map(|owner| owner.0);
+
+        let mut hits = Vec::new();
+        rapier_context.intersections_with_ray(
+            origin,
+            direction,
+            hitscan.range,
+            true,
+            filter,
+            |entity, intersection| {
+                // the shot starts inside the owner, e.g. at the muzzle of a turret
+                let own = std::iter::once(entity)
+                    .chain(parent_query.iter_ancestors(entity))
+                    .any(|e| Some(e) == attacker);
+                if !own {
+                    hits.push((entity, intersection.toi));
                 }
+                true
+            },
+        );
+        hits.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        // Critical zones are a part of the target, so they don't count for the penetration
+        let mut struck: Vec<(Entity, f32, f32)> = Vec::new();
+        for (collider, distance) in hits {
+            let Some((target, multiplier)) = resolve_hit(collider, &targets, &parent_query) else {
+                continue;
+            };
+            match struck.iter_mut().find(|(e, _, _)| *e == target) {
+                Some(hit) => hit.1 = hit.1.max(multiplier),
+                None => struck.push((target, multiplier, distance)),
             }
         }
+        struck.truncate(hitscan.penetration);
+
+        for &(target, multiplier, distance) in struck.iter() {
+            let (hp, shield, _, armor, respawn, pooled) = targets.get_mut(target).unwrap();
+            let kept = respawn.is_some() || pooled.is_some();
+            let damage = (hitscan.damage as f32 * multiplier).round() as u32;
+            let (damage, absorbed, destroyed) =
+                apply_damage(&mut commands, target, damage, hp, shield, armor, kept);
+            ev_damage.send(DamageEvent {
+                target,
+                attacker,
+                attacker_position: origin,
+                position: origin + direction * distance,
+                damage,
+                absorbed,
+                destroyed,
+            });
+        }
+
+        let length = match struck.last() {
+            Some(&(_, _, distance)) => {
+                play_explosion(&mut explosions, explosive, origin + direction * distance);
+                distance
+            }
+            None => hitscan.range,
+        };
+        transform.translation = origin + direction * length / 2.0;
+        transform.scale.y = length;
+        commands.entity(shot).remove::<Hitscan>();
     }
 }
 
 fn explosive_collision(
     mut commands: Commands,
     mut collisions: EventReader<CollisionEvent>,
-    mut explosions: Query<(&ExplosionEffect, &mut ParticleEffect, &mut Transform)>,
-    explosives: Query<(&ExplosionEffect, &Transform), Without<ParticleEffect>>,
+    mut explosions: Explosions,
+    explosives: Query<(&ExplosionEffect, &Transform, Option<&Owner>), Without<ParticleEffect>>,
 ) {
     for event in collisions.iter() {
         if let CollisionEvent::Started(first, second, _) = event {
-            for entity in [first, second] {
-                // If collided entity is explosive
-                if let Ok((&explosive, transform)) = explosives.get(*entity) {
-                    // Match effect by it's type or use `Debug` if can't find
-                    let mut explosion = explosions
-                        .iter_mut()
-                        .find(|(&effect, _, _)| effect == explosive);
-                    if explosion.is_none() {
-                        explosion = explosions
-                            .iter_mut()
-                            .find(|(&effect, _, _)| effect == ExplosionEffect::Debug);
+            for (entity, other) in [(first, second), (second, first)] {
+                // If collided entity is explosive and it's not a collision with own shooter
+                if let Ok((&explosive, transform, owner)) = explosives.get(*entity) {
+                    if matches!(owner, Some(owner) if owner.0 == *other) {
+                        continue;
                     }
 
-                    let (_, mut effect, mut effect_transform) = explosion.unwrap();
-                    effect_transform.translation = transform.translation;
-                    effect.maybe_spawner().unwrap().reset();
+                    play_explosion(&mut explosions, explosive, transform.translation);
 
                     // destroy every explosive entity on collision
                     commands.entity(*entity).despawn_recursive();
@@ -299,14 +838,44 @@ fn explosive_collision(
     }
 }
 
+/// Projectile lifetime, homing and damage dealt by hits and explosions, reported as `DamageEvent`s.
 pub struct ProjectilePlugin;
 impl Plugin for ProjectilePlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(HanabiPlugin)
+        // Particles need a renderer, headless apps (e.g. tests) only keep the effect assets
+        if app.get_sub_app(RenderApp).is_ok() {
+            app.add_plugin(HanabiPlugin);
+        } else {
+            app.add_asset::<EffectAsset>();
+        }
+        app.add_event::<DamageEvent>()
+            .add_event::<ExplosionEvent>()
             .add_startup_system(setup)
-            .add_system(lifetime)
-            .add_system(hit_collision)
+            .add_system(homing)
+            .add_system(scale_gravity)
+            .add_system(hit_collision.label(DamageSystems))
+            .add_system(recharge_shields)
             .add_system(explosive_collision)
-            .register_type::<HitPoints>();
+            .add_system(proximity_fuse.label(DamageSystems))
+            .add_system(hitscan.label(DamageSystems))
+            .register_type::<Owner>()
+            .register_type::<Homing>()
+            .register_type::<Ballistic>()
+            .register_type::<Rocket>()
+            .register_type::<Shield>()
+            .register_type::<Armor>()
+            .register_type::<ExplosionEffect>()
+            .register_type::<ProximityFuse>()
+            .register_type::<Hitscan>()
+            .register_type::<CriticalZone>()
+            .add_system(setup_critical_zones)
+            // `{"critical": 2.5}` makes the node a critical zone with the given damage multiplier
+            .register_gltf_property("critical", |commands, value| {
+                if let Some(multiplier) = value.as_f64() {
+                    commands.insert(CriticalZone {
+                        multiplier: multiplier as f32,
+                    });
+                }
+            });
     }
 }
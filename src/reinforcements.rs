@@ -0,0 +1,282 @@
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+use serde::Deserialize;
+
+use crate::{
+    components::Fraction,
+    drone::Drone,
+    jump_gate::{JumpGatePlugin, OpenJumpGateEvent},
+    projectile::{DamageEvent, DamageSystems},
+    scenario::Scenario,
+};
+
+/// Seconds between spawns of the same pool, so reinforcements arrive one by one
+const SPAWN_COOLDOWN: f32 = 2.0;
+
+/// Reinforcement tickets of a single faction.
+#[derive(Clone, Debug)]
+pub struct ReinforcementPool {
+    pub fraction: Fraction,
+    /// Drones left to fight, including the ones already on the field
+    pub tickets: u32,
+    /// Max number of drones on the field at once
+    pub field_size: usize,
+    /// Drone types picked randomly for reinforcements
+    pub drones: Vec<Drone>,
+    /// Seconds left before the next spawn
+    cooldown: f32,
+}
+
+impl ReinforcementPool {
+    pub fn new(fraction: Fraction, tickets: u32, field_size: usize, drones: Vec<Drone>) -> Self {
+        Self {
+            fraction,
+            tickets,
+            field_size,
+            drones,
+            // Gives drones spawned along with the pool time to appear on the field
+            cooldown: SPAWN_COOLDOWN,
+        }
+    }
+
+    /// Consumes a ticket for a destroyed drone, returns true if the pool has just run out
+    fn lose(&mut self) -> bool {
+        let was_empty = self.tickets == 0;
+        self.tickets = self.tickets.saturating_sub(1);
+        !was_empty && self.tickets == 0
+    }
+
+    /// Number of drones to spawn to refill the field, given `alive` drones of the faction
+    fn missing(&self, alive: usize) -> usize {
+        self.field_size
+            .min(self.tickets as usize)
+            .saturating_sub(alive)
+    }
+}
+
+/// Team taking part in the battle with its reinforcements, a part of the `Scenario`.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct TeamConfig {
+    pub team: Fraction,
+    pub tickets: u32,
+    pub field_size: usize,
+    pub drones: Vec<Drone>,
+}
+
+impl TeamConfig {
+    pub fn pool(&self) -> ReinforcementPool {
+        ReinforcementPool::new(
+            self.team,
+            self.tickets,
+            self.field_size,
+            self.drones.clone(),
+        )
+    }
+}
+
+/// Named place where the team enters the battle, a part of the `Scenario`.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct SpawnPointConfig {
+    pub name: String,
+    pub team: Fraction,
+    /// World position in meters
+    pub position: [f32; 3],
+    /// Ships spawned here face this point, the origin by default
+    #[serde(default)]
+    pub look_at: [f32; 3],
+    /// Drones appear at random positions within this radius, in meters
+    #[serde(default = "SpawnPointConfig::default_radius")]
+    pub radius: f32,
+}
+
+impl SpawnPointConfig {
+    fn default_radius() -> f32 {
+        100.0
+    }
+
+    pub fn transform(&self) -> Transform {
+        let position = Vec3::from(self.position);
+        let look_at = Vec3::from(self.look_at);
+        if position == look_at {
+            return Transform::from_translation(position);
+        }
+        Transform::from_translation(position).looking_at(look_at, Vec3::Y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Fraction, ReinforcementPool, SpawnPointConfig};
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_tickets() {
+        let mut pool = ReinforcementPool::new(Fraction::Drones, 3, 2, vec![]);
+        assert_eq!(pool.missing(0), 2);
+        assert_eq!(pool.missing(2), 0);
+
+        assert!(!pool.lose());
+        assert!(!pool.lose());
+        // one ticket left - the field isn't refilled beyond it
+        assert_eq!(pool.missing(0), 1);
+        assert!(pool.lose());
+        assert_eq!(pool.missing(0), 0);
+
+        // drones spawned outside of the pool don't make it run out twice
+        assert!(!pool.lose());
+        assert_eq!(pool.tickets, 0);
+    }
+
+    #[test]
+    fn test_spawn_point() {
+        let point: SpawnPointConfig =
+            serde_json::from_str(r#"{"name": "North", "team": "drones", "position": [0, 0, 100]}"#)
+                .unwrap();
+        assert_eq!(point.team, Fraction::Drones);
+        assert_eq!(point.radius, 100.0);
+        // faces the origin
+        let transform = point.transform();
+        assert!(transform.forward().abs_diff_eq(Vec3::NEG_Z, 1e-5));
+
+        let point = SpawnPointConfig {
+            position: [0.0; 3],
+            ..point
+        };
+        assert!(!point.transform().rotation.is_nan());
+    }
+}
+
+/// Reinforcement pools of the factions taking part in the battle.
+/// Battle ends when any of the pools runs out of tickets.
+#[derive(Resource, Default, Clone, Debug)]
+pub struct Reinforcements {
+    pub pools: Vec<ReinforcementPool>,
+    over: bool,
+}
+
+/// Place where reinforcements of the `fraction` jump in, heading along its forward direction.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct SpawnPoint {
+    pub fraction: Fraction,
+    /// Drones appear at random positions within this radius, in meters
+    pub radius: f32,
+}
+
+/// Emitted once when the `defeated` faction runs out of reinforcements.
+pub struct BattleEndedEvent {
+    pub defeated: Fraction,
+}
+
+/// Spawn points and reinforcement pools of the `Scenario` teams
+fn spawn_scenario_teams(
+    mut commands: Commands,
+    scenario: Option<Res<Scenario>>,
+    mut reinforcements: ResMut<Reinforcements>,
+) {
+    let Some(scenario) = scenario else {
+        return;
+    };
+    for point in scenario.spawn_points.iter() {
+        commands
+            .spawn(TransformBundle::from(point.transform()))
+            .insert(SpawnPoint {
+                fraction: point.team,
+                radius: point.radius,
+            })
+            .insert(Name::new(point.name.clone()));
+    }
+    reinforcements
+        .pools
+        .extend(scenario.teams.iter().map(TeamConfig::pool));
+}
+
+fn consume_tickets(
+    mut reinforcements: ResMut<Reinforcements>,
+    mut ev_damage: EventReader<DamageEvent>,
+    mut ev_battle_ended: EventWriter<BattleEndedEvent>,
+    drones: Query<&Fraction, With<Drone>>,
+) {
+    for ev in ev_damage.iter().filter(|ev| ev.destroyed) {
+        let Ok(&fraction) = drones.get(ev.target) else {
+            continue;
+        };
+        let was_over = reinforcements.over;
+        let Some(pool) = reinforcements
+            .pools
+            .iter_mut()
+            .find(|p| p.fraction == fraction)
+        else {
+            continue;
+        };
+        if pool.lose() && !was_over {
+            info!("Battle is over, {fraction:?} ran out of reinforcements");
+            reinforcements.over = true;
+            ev_battle_ended.send(BattleEndedEvent { defeated: fraction });
+        }
+    }
+}
+
+fn refill_field(
+    time: Res<Time>,
+    mut reinforcements: ResMut<Reinforcements>,
+    mut ev_open_gate: EventWriter<OpenJumpGateEvent>,
+    drones: Query<&Fraction, With<Drone>>,
+    spawn_points: Query<(&GlobalTransform, &SpawnPoint)>,
+) {
+    if reinforcements.over {
+        return;
+    }
+    let mut rng = rand::thread_rng();
+
+    for pool in reinforcements.pools.iter_mut() {
+        pool.cooldown -= time.delta_seconds();
+        if pool.cooldown > 0.0 {
+            continue;
+        }
+        let alive = drones.iter().filter(|&&f| f == pool.fraction).count();
+        if pool.missing(alive) == 0 {
+            continue;
+        }
+
+        let points: Vec<_> = spawn_points
+            .iter()
+            .filter(|(_, point)| point.fraction == pool.fraction)
+            .collect();
+        let (Some((transform, point)), Some(&drone)) =
+            (points.choose(&mut rng), pool.drones.choose(&mut rng))
+        else {
+            continue;
+        };
+
+        let offset = Vec3::new(
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+        ) * point.radius;
+        let (_, rotation, translation) = transform.to_scale_rotation_translation();
+        ev_open_gate.send(OpenJumpGateEvent {
+            drone,
+            fraction: pool.fraction,
+            transform: Transform::from_translation(translation + offset).with_rotation(rotation),
+        });
+        pool.cooldown = SPAWN_COOLDOWN;
+    }
+}
+
+/// Keeps the field filled with drones from per-faction ticket pools, until one of them runs out.
+/// Reinforcements arrive through jump gates.
+pub struct ReinforcementsPlugin;
+impl Plugin for ReinforcementsPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<JumpGatePlugin>() {
+            app.add_plugin(JumpGatePlugin);
+        }
+        app.init_resource::<Reinforcements>()
+            .register_type::<SpawnPoint>()
+            .add_event::<BattleEndedEvent>()
+            .add_startup_system(spawn_scenario_teams)
+            .add_system(consume_tickets.after(DamageSystems))
+            .add_system(refill_field.after(consume_tickets));
+    }
+}
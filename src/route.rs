@@ -0,0 +1,171 @@
+use bevy::prelude::*;
+
+/// Scripted movement through `waypoints` at a constant speed, for kinematic bodies like a
+/// cruising capital ship. Children, e.g. mounted turrets, move along with the entity.
+/// The entity turns towards the direction of movement at `turn_rate`.
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct Route {
+    pub waypoints: Vec<Vec3>,
+    /// Cruise speed in m/s
+    pub speed: f32,
+    /// In rad/s
+    pub turn_rate: f32,
+    /// Starts over from the first waypoint after the last one, otherwise stops there
+    pub looped: bool,
+    /// Index of the waypoint the entity is heading to
+    next: usize,
+}
+
+impl Route {
+    pub fn new(waypoints: Vec<Vec3>, speed: f32) -> Self {
+        Self {
+            waypoints,
+            speed,
+            turn_rate: 0.2,
+            looped: false,
+            next: 0,
+        }
+    }
+
+    pub fn looped(mut self) -> Self {
+        self.looped = true;
+        self
+    }
+
+    /// The last waypoint is reached, never true for looped routes
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.waypoints.len()
+    }
+
+    /// Distance in meters from `position` to the last waypoint along the route
+    pub fn remaining_distance(&self, position: Vec3) -> f32 {
+        let Some(&next) = self.waypoints.get(self.next) else {
+            return 0.0;
+        };
+        let rest: f32 = self.waypoints[self.next..]
+            .windows(2)
+            .map(|segment| segment[0].distance(segment[1]))
+            .sum();
+        position.distance(next) + rest
+    }
+
+    /// Transform at the first waypoint facing the second one, to spawn the entity at the start
+    pub fn start(&self) -> Option<Transform> {
+        let start = *self.waypoints.first()?;
+        let transform = Transform::from_translation(start);
+        Some(match self.waypoints.get(1) {
+            Some(&next) if next != start => transform.looking_at(next, Vec3::Y),
+            _ => transform,
+        })
+    }
+}
+
+/// Moves `position` by `distance` along `waypoints` starting from the `next` one.
+/// Returns the new position and the index of the waypoint it's heading to afterwards.
+fn advance(
+    mut position: Vec3,
+    waypoints: &[Vec3],
+    mut next: usize,
+    mut distance: f32,
+    looped: bool,
+) -> (Vec3, usize) {
+    while let Some(&waypoint) = waypoints.get(next) {
+        let to_waypoint = waypoint - position;
+        let length = to_waypoint.length();
+        if length > distance {
+            return (position + to_waypoint / length * distance, next);
+        }
+        distance -= length;
+        position = waypoint;
+        next += 1;
+        if looped && next == waypoints.len() {
+            next = 0;
+            // a single waypoint or several at the same place can't be looped around
+            if waypoints.iter().all(|&w| w == waypoint) {
+                break;
+            }
+        }
+    }
+    (position, next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{advance, Route};
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_advance() {
+        let square = [Vec3::ZERO, Vec3::X, Vec3::X + Vec3::Z, Vec3::Z];
+        assert_eq!(
+            advance(Vec3::ZERO, &square, 1, 0.5, false),
+            (Vec3::X * 0.5, 1)
+        );
+        // passes a waypoint within a single step
+        let (position, next) = advance(Vec3::ZERO, &square, 1, 1.5, false);
+        assert!(position.distance(Vec3::new(1.0, 0.0, 0.5)) < 1e-6);
+        assert_eq!(next, 2);
+
+        // stops at the end, unless looped
+        assert_eq!(advance(Vec3::Z, &square, 4, 1.0, false), (Vec3::Z, 4));
+        let (position, next) = advance(Vec3::X + Vec3::Z, &square, 3, 1.5, true);
+        assert!(position.distance(Vec3::Z * 0.5) < 1e-6);
+        assert_eq!(next, 0);
+
+        // nowhere to go around
+        assert_eq!(advance(Vec3::X, &[Vec3::X], 0, 1.0, true), (Vec3::X, 0));
+    }
+
+    #[test]
+    fn test_remaining_distance() {
+        let mut route = Route::new(
+            vec![Vec3::ZERO, Vec3::X * 10.0, Vec3::new(10.0, 0.0, 5.0)],
+            1.0,
+        );
+        assert_eq!(route.remaining_distance(Vec3::ZERO), 15.0);
+        route.next = 1;
+        assert_eq!(route.remaining_distance(Vec3::X * 4.0), 11.0);
+        route.next = 3;
+        assert!(route.is_finished());
+        assert_eq!(route.remaining_distance(Vec3::X), 0.0);
+    }
+}
+
+fn follow_route(time: Res<Time>, mut routes: Query<(&mut Transform, &mut Route)>) {
+    for (mut transform, mut route) in routes.iter_mut() {
+        if route.is_finished() || route.speed <= 0.0 {
+            continue;
+        }
+
+        let distance = route.speed * time.delta_seconds();
+        let (position, next) = advance(
+            transform.translation,
+            &route.waypoints,
+            route.next,
+            distance,
+            route.looped,
+        );
+        let direction = position - transform.translation;
+        transform.translation = position;
+        route.next = next;
+
+        // there is no facing for vertical movement, the last one is kept
+        if direction.cross(Vec3::Y).length_squared() > f32::EPSILON {
+            let facing = transform.looking_at(position + direction, Vec3::Y).rotation;
+            let angle = transform.rotation.angle_between(facing);
+            let max_turn = route.turn_rate * time.delta_seconds();
+            transform.rotation = transform
+                .rotation
+                .slerp(facing, (max_turn / angle.max(f32::EPSILON)).min(1.0));
+        }
+    }
+}
+
+/// Kinematic entities following scripted `Route`s.
+pub struct RoutePlugin;
+impl Plugin for RoutePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Route>().add_system(follow_route);
+    }
+}
@@ -0,0 +1,214 @@
+//! Wreck field left after fights: hulls of destroyed drones stay in space as debris, up to a cap
+//! with the oldest wrecks cleaned up first. Some wrecks carry salvage, which the player pulls in
+//! with a tractor beam held on L, so it pays to control the space after a fight.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use rand::Rng;
+
+use crate::{
+    drone::Drone,
+    player::Player,
+    projectile::{DamageEvent, DamageSystems},
+};
+
+/// Chance of a wreck to carry salvage
+const SALVAGE_CHANCE: f64 = 0.3;
+/// Wrecks tumble up to this fast, in rad/s
+const TUMBLE_SPEED: f32 = 0.5;
+/// Collider of wrecks of drones without one on the hull, in meters
+const WRECK_RADIUS: f32 = 3.0;
+/// Salvage farther than this isn't reached by the tractor beam, in meters
+const BEAM_RANGE: f32 = 300.0;
+/// Half-angle of the tractor beam cone around the nose
+const BEAM_ANGLE: f32 = 0.26; // 15 degrees
+/// Salvage is pulled at this speed relative to the player, in m/s
+const BEAM_SPEED: f32 = 40.0;
+/// Salvage closer than this is collected, in meters
+const COLLECT_DISTANCE: f32 = 10.0;
+
+/// Wreck of a destroyed drone drifting in space. Wrecks have no lifetime, the oldest ones are
+/// removed once there are more than `WreckField::max_debris`.
+#[derive(Component)]
+pub struct Wreck {
+    /// Time the drone was destroyed, in seconds since startup
+    spawned: f32,
+}
+
+/// Value of the wreck once collected with the tractor beam
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Salvage {
+    pub value: u32,
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct WreckField {
+    /// Max number of wrecks in space at once
+    pub max_debris: usize,
+}
+
+impl Default for WreckField {
+    fn default() -> Self {
+        Self { max_debris: 128 }
+    }
+}
+
+/// Emitted when the player collects salvage worth the `value`
+pub struct SalvageCollectedEvent {
+    pub value: u32,
+}
+
+/// Items beyond the `max` number, the oldest ones first by their time of appearance
+fn oldest<T>(mut items: Vec<(T, f32)>, max: usize) -> impl Iterator<Item = T> {
+    let excess = items.len().saturating_sub(max);
+    items.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+    items.into_iter().take(excess).map(|(item, _)| item)
+}
+
+/// Whether the salvage `to_salvage` away is caught by the beam along the `forward` direction
+fn in_beam(to_salvage: Vec3, forward: Vec3) -> bool {
+    to_salvage.length() < BEAM_RANGE && to_salvage.angle_between(forward) < BEAM_ANGLE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{in_beam, oldest, BEAM_RANGE};
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_oldest() {
+        let items = vec![("b", 2.0), ("a", 1.0), ("d", 4.0), ("c", 3.0)];
+        assert_eq!(oldest(items.clone(), 2).collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(oldest(items.clone(), 4).count(), 0);
+        assert_eq!(oldest(items, 10).count(), 0);
+    }
+
+    #[test]
+    fn test_in_beam() {
+        let forward = Vec3::NEG_Z;
+        assert!(in_beam(Vec3::NEG_Z * 100.0, forward));
+        assert!(in_beam(Vec3::new(10.0, 0.0, -100.0), forward));
+        assert!(!in_beam(Vec3::new(50.0, 0.0, -100.0), forward));
+        assert!(!in_beam(Vec3::Z * 100.0, forward));
+        assert!(!in_beam(Vec3::NEG_Z * (BEAM_RANGE + 1.0), forward));
+    }
+}
+
+/// Leaves the hulls of destroyed drones in space, some of them carrying salvage. Pooled drones
+/// are reused, so their wrecks take over the model, the collider and the motion at the moment of
+/// destruction.
+fn spawn_debris(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut ev_damage: EventReader<DamageEvent>,
+    drones: Query<
+        (
+            &GlobalTransform,
+            Option<&Handle<Scene>>,
+            Option<&Collider>,
+            Option<&Velocity>,
+        ),
+        With<Drone>,
+    >,
+) {
+    let mut rng = rand::thread_rng();
+    for ev in ev_damage.iter().filter(|ev| ev.destroyed) {
+        let Ok((transform, scene, collider, velocity)) = drones.get(ev.target) else {
+            continue;
+        };
+        let tumble = Vec3::new(
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+        );
+        let mut wreck = commands.spawn(SceneBundle {
+            scene: scene.cloned().unwrap_or_default(),
+            transform: transform.compute_transform(),
+            ..default()
+        });
+        wreck
+            .insert(RigidBody::Dynamic)
+            .insert(
+                collider
+                    .cloned()
+                    .unwrap_or_else(|| Collider::ball(WRECK_RADIUS)),
+            )
+            .insert(Velocity {
+                linvel: velocity.map(|v| v.linvel).unwrap_or_default(),
+                angvel: tumble * TUMBLE_SPEED,
+            })
+            // Wrecks slowly settle around the place of the fight instead of drifting away
+            .insert(Damping {
+                linear_damping: 0.2,
+                angular_damping: 0.1,
+            })
+            .insert(Wreck {
+                spawned: time.elapsed_seconds(),
+            })
+            .insert(Name::new("Wreck"));
+        if rng.gen_bool(SALVAGE_CHANCE) {
+            wreck.insert(Salvage {
+                value: rng.gen_range(5..=25),
+            });
+        }
+    }
+}
+
+fn cleanup_debris(mut commands: Commands, field: Res<WreckField>, wrecks: Query<(Entity, &Wreck)>) {
+    if wrecks.iter().len() <= field.max_debris {
+        return;
+    }
+    let wrecks = wrecks
+        .iter()
+        .map(|(entity, w)| (entity, w.spawned))
+        .collect();
+    for entity in oldest(wrecks, field.max_debris) {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Pulls salvage within the beam cone towards the player while L is held and collects it
+fn tractor_beam(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    player: Query<(&GlobalTransform, Option<&Velocity>), With<Player>>,
+    mut salvage: Query<(Entity, &GlobalTransform, &Salvage, &mut Velocity), Without<Player>>,
+    mut ev_collected: EventWriter<SalvageCollectedEvent>,
+) {
+    if !keys.pressed(KeyCode::L) {
+        return;
+    }
+    let Ok((transform, player_velocity)) = player.get_single() else {
+        return;
+    };
+    let position = transform.translation();
+    let player_velocity = player_velocity.map(|v| v.linvel).unwrap_or_default();
+
+    for (entity, salvage_transform, salvage, mut velocity) in salvage.iter_mut() {
+        let to_salvage = salvage_transform.translation() - position;
+        if !in_beam(to_salvage, transform.forward()) {
+            continue;
+        }
+        if to_salvage.length() < COLLECT_DISTANCE {
+            commands.entity(entity).despawn_recursive();
+            ev_collected.send(SalvageCollectedEvent {
+                value: salvage.value,
+            });
+        } else {
+            velocity.linvel = player_velocity - to_salvage.normalize() * BEAM_SPEED;
+        }
+    }
+}
+
+/// Wrecks of destroyed drones persisting in space, and the tractor beam collecting `Salvage`
+/// among it, reported as `SalvageCollectedEvent`s.
+pub struct SalvagePlugin;
+impl Plugin for SalvagePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WreckField>()
+            .add_event::<SalvageCollectedEvent>()
+            .add_system(spawn_debris.after(DamageSystems))
+            .add_system(cleanup_debris.after(spawn_debris))
+            .add_system(tractor_beam);
+    }
+}
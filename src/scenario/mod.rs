@@ -0,0 +1,275 @@
+//! Battlefield description loaded from a JSON file, see `assets/scenarios/` for examples.
+//! Scenarios are passed as `--scenario=<path>` or picked from the menu opened with Y.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::{
+    boss::BossConfig,
+    escort::EscortConfig,
+    hazard::HazardConfig,
+    ion_storm::IonStormConfig,
+    objective::ObjectivesConfig,
+    planet::PlanetConfig,
+    player::PlayerConfig,
+    reinforcements::{SpawnPointConfig, TeamConfig},
+    route::Route,
+    trigger::TriggerConfig,
+};
+
+mod picker;
+
+pub use picker::{picker_closed, ScenarioPicker};
+
+/// Scripted parts of the default scene, passed as `--scenario=<path>`.
+#[derive(Resource, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Scenario {
+    /// Shown in the scenario picker, the file name is used without one
+    pub name: Option<String>,
+    /// Skybox, lighting and gravity of the battlefield
+    pub environment: EnvironmentConfig,
+    /// Named places where teams enter the battle, the player may start at one of them
+    pub spawn_points: Vec<SpawnPointConfig>,
+    /// Reinforcements of teams, they arrive through spawn points of their team.
+    /// The default scene has drones attacking from both sides without any.
+    pub teams: Vec<TeamConfig>,
+    /// Team, spawn point and loadout of the player
+    pub player: PlayerConfig,
+    /// Route of the capital ship, it stays at the origin without one
+    pub capital_ship_route: Option<RouteConfig>,
+    /// Escort mission, replaces the default scene
+    pub escort: Option<EscortConfig>,
+    /// Objective zones to hold, on top of any other scene
+    pub objectives: Option<ObjectivesConfig>,
+    /// Black holes and anomalies on the battlefield
+    pub hazards: Vec<HazardConfig>,
+    /// Ion storms drifting across the battlefield
+    pub storms: Vec<IonStormConfig>,
+    /// Planets and moons on the sky, later ones are drawn over the earlier ones
+    pub planets: Vec<PlanetConfig>,
+    /// Dreadnought fought in phases, on top of any other scene
+    pub boss: Option<BossConfig>,
+    /// Scripted mission: actions fired once the player enters an area, a timer runs out or
+    /// a named entity is destroyed
+    pub triggers: Vec<TriggerConfig>,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct EnvironmentConfig {
+    /// KTX2 cubemap in `assets`, the space is pitch black without one
+    pub skybox: Option<String>,
+    /// sRGB color of the ambient light
+    pub ambient_color: [f32; 3],
+    pub ambient_brightness: f32,
+    /// Gravity near a planet in m/s², bullets and shells drop along ballistic arcs under it.
+    /// Ships keep their course on their own.
+    pub gravity: [f32; 3],
+    /// Direction towards the sun, lighting the ships and the day side of planets and moons
+    pub sun_direction: [f32; 3],
+}
+
+impl Default for EnvironmentConfig {
+    fn default() -> Self {
+        Self {
+            skybox: Some(String::from("textures/background_astc.ktx2")),
+            ambient_color: [210.0 / 255.0, 220.0 / 255.0, 240.0 / 255.0],
+            ambient_brightness: 0.3,
+            gravity: [0.0; 3],
+            sun_direction: [1.0, 0.4, -0.6],
+        }
+    }
+}
+
+impl EnvironmentConfig {
+    pub fn ambient_light(&self) -> AmbientLight {
+        let [r, g, b] = self.ambient_color;
+        AmbientLight {
+            color: Color::rgb(r, g, b),
+            brightness: self.ambient_brightness,
+        }
+    }
+
+    /// Directional light shining from the `sun_direction`
+    pub fn sun_light(&self) -> DirectionalLightBundle {
+        let to_sun = Vec3::from(self.sun_direction)
+            .try_normalize()
+            .unwrap_or(Vec3::Y);
+        // Any up but the direction itself
+        let up = if to_sun.y.abs() > 0.99 {
+            Vec3::Z
+        } else {
+            Vec3::Y
+        };
+        DirectionalLightBundle {
+            transform: Transform::default().looking_at(-to_sun, up),
+            ..default()
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct RouteConfig {
+    /// World positions in meters
+    pub waypoints: Vec<[f32; 3]>,
+    /// Cruise speed in m/s
+    pub speed: f32,
+    #[serde(default)]
+    pub looped: bool,
+}
+
+impl RouteConfig {
+    pub fn route(&self) -> Route {
+        let route = Route::new(
+            self.waypoints.iter().copied().map(Vec3::from).collect(),
+            self.speed,
+        );
+        if self.looped {
+            route.looped()
+        } else {
+            route
+        }
+    }
+}
+
+impl Scenario {
+    /// Transform of the spawn point with the `name`
+    pub fn spawn_point(&self, name: &str) -> Option<Transform> {
+        self.spawn_points
+            .iter()
+            .find(|point| point.name == name)
+            .map(SpawnPointConfig::transform)
+    }
+
+    /// Parses command line arguments, returns `None` unless `--scenario=<path>` is passed.
+    /// A broken scenario is reported and replaced with the default one.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        let arg = args
+            .into_iter()
+            .find(|arg| arg.starts_with("--scenario="))?;
+        Some(Self::load(&arg["--scenario=".len()..]))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => {
+                error!("Can't read scenario {}: {err}", path.display());
+                return Self::default();
+            }
+        };
+        serde_json::from_str(&content).unwrap_or_else(|err| {
+            error!("Scenario {} is broken: {err}", path.display());
+            Self::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RouteConfig, Scenario};
+    use crate::{boss::BeamConfig, components::Fraction, planet::PlanetConfig, turret::TurretKind};
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_parse_scenario() {
+        let scenario: Scenario = serde_json::from_str(
+            r#"{"capital_ship_route": {"waypoints": [[0, 0, 0], [0, 0, -500]], "speed": 10}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            scenario.capital_ship_route,
+            Some(RouteConfig {
+                waypoints: vec![[0.0, 0.0, 0.0], [0.0, 0.0, -500.0]],
+                speed: 10.0,
+                looped: false,
+            })
+        );
+        assert_eq!(
+            serde_json::from_str::<Scenario>("{}").unwrap(),
+            Scenario::default()
+        );
+    }
+
+    #[test]
+    fn test_parse_battlefield() {
+        let scenario: Scenario = serde_json::from_str(
+            r#"{
+                "environment": {"skybox": null, "gravity": [0, -9.81, 0]},
+                "spawn_points": [{"name": "Home", "team": "turrets", "position": [0, 0, 500]}],
+                "teams": [
+                    {"team": "drones", "tickets": 10, "field_size": 3, "drones": ["praetor"]}
+                ],
+                "player": {"team": "drones", "spawn_point": "Home", "loadout": {"railgun": false}}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(scenario.environment.skybox, None);
+        // the rest of the environment is kept as is
+        assert_eq!(scenario.environment.ambient_brightness, 0.3);
+        assert_eq!(scenario.teams[0].pool().tickets, 10);
+        assert_eq!(scenario.player.team, Fraction::Drones);
+        assert!(!scenario.player.loadout.railgun);
+        assert_eq!(scenario.player.loadout.machine_guns, 3);
+
+        let home = scenario.spawn_point("Home").unwrap();
+        assert_eq!(home.translation, Vec3::Z * 500.0);
+        assert!(scenario.spawn_point("Away").is_none());
+    }
+
+    #[test]
+    fn test_parse_boss() {
+        let scenario: Scenario = serde_json::from_str(
+            r#"{"boss": {
+                "position": [0, 0, -1000],
+                "turrets": [{"kind": "railgun", "offset": [0, -3, 0]}],
+                "beam": {"charge_time": 2}
+            }}"#,
+        )
+        .unwrap();
+        let boss = scenario.boss.unwrap();
+        assert_eq!(boss.turrets[0].kind, TurretKind::Railgun);
+        assert_eq!(boss.beam.charge_time, 2.0);
+        // the rest is taken from the default dreadnought
+        assert_eq!(boss.name, "Dreadnought");
+        assert_eq!(boss.beam.range, BeamConfig::default().range);
+        assert!(!boss.shield_generators.is_empty());
+    }
+
+    #[test]
+    fn test_parse_planets() {
+        let scenario: Scenario = serde_json::from_str(
+            r#"{
+                "environment": {"sun_direction": [0, 1, 0]},
+                "planets": [
+                    {"name": "Moon", "direction": [1, 0, 0], "size": 5, "atmosphere": null},
+                    {"texture": "textures/planet.png"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(scenario.environment.sun_direction, [0.0, 1.0, 0.0]);
+        // the sun straight above shines down
+        let sun = scenario.environment.sun_light().transform;
+        assert!(sun.forward().distance(Vec3::NEG_Y) < 1e-5);
+        let moon = &scenario.planets[0];
+        assert_eq!(moon.name, "Moon");
+        assert_eq!(moon.atmosphere, None);
+        // the rest is taken from the default planet
+        let planet = &scenario.planets[1];
+        assert_eq!(planet.texture.as_deref(), Some("textures/planet.png"));
+        assert_eq!(planet.size, PlanetConfig::default().size);
+        assert!(planet.atmosphere.is_some());
+    }
+}
+
+/// Scenario picker, restarting the game with the picked one.
+pub struct ScenarioPlugin;
+impl Plugin for ScenarioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(picker::PickerPlugin);
+    }
+}
@@ -0,0 +1,227 @@
+use bevy::{app::AppExit, ecs::schedule::ShouldRun, prelude::*};
+use std::path::{Path, PathBuf};
+
+use super::Scenario;
+use crate::localization::Localization;
+
+const SCENARIOS_DIR: &str = "assets/scenarios";
+const SCREEN_WIDTH: f32 = 480.0;
+
+/// Scenario file found on start.
+struct ScenarioEntry {
+    name: String,
+    path: PathBuf,
+}
+
+/// Whether the scenario picker is open. It takes the arrow keys and Enter then, so other screens
+/// should leave them alone.
+#[derive(Resource, Default)]
+pub struct ScenarioPicker {
+    pub open: bool,
+}
+
+/// Run criteria for systems taking the keys of the picker while it's closed
+pub fn picker_closed(picker: Option<Res<ScenarioPicker>>) -> ShouldRun {
+    match picker {
+        Some(picker) if picker.open => ShouldRun::No,
+        _ => ShouldRun::Yes,
+    }
+}
+
+/// Scenarios to pick from, the default battle without a scenario goes first.
+#[derive(Resource, Default)]
+struct ScenarioList {
+    entries: Vec<ScenarioEntry>,
+    /// 0 for the default battle, `entries` follow it
+    selected: usize,
+}
+
+/// Scenario files in the `dir` sorted by their names, broken ones are listed too to report them
+/// once picked
+fn find_scenarios(dir: impl AsRef<Path>) -> Vec<ScenarioEntry> {
+    let Ok(files) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut entries: Vec<_> = files
+        .filter_map(|file| Some(file.ok()?.path()))
+        .filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+        .map(|path| {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let name = Scenario::load(&path)
+                .name
+                .unwrap_or_else(|| stem.into_owned());
+            ScenarioEntry { name, path }
+        })
+        .collect();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    entries
+}
+
+/// Command line `args` to restart the game with the scenario at the `path`, or without any
+fn restart_args(args: impl IntoIterator<Item = String>, path: Option<&Path>) -> Vec<String> {
+    let mut args: Vec<_> = args
+        .into_iter()
+        .filter(|arg| !arg.starts_with("--scenario="))
+        .collect();
+    if let Some(path) = path {
+        args.push(format!("--scenario={}", path.display()));
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::restart_args;
+    use std::path::Path;
+
+    #[test]
+    fn test_restart_args() {
+        let args = ["--observe", "--scenario=old.json"].map(String::from);
+        assert_eq!(
+            restart_args(args.clone(), Some(Path::new("new.json"))),
+            ["--observe", "--scenario=new.json"]
+        );
+        assert_eq!(restart_args(args, None), ["--observe"]);
+    }
+}
+
+#[derive(Component)]
+struct PickerScreen;
+
+#[derive(Component)]
+struct PickerText;
+
+fn setup_picker_screen(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.insert_resource(ScenarioList {
+        entries: find_scenarios(SCENARIOS_DIR),
+        selected: 0,
+    });
+    let font = assets.load("fonts/FiraMono-Medium.ttf");
+
+    // Hidden panel in the middle of the screen, shown on demand
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(SCREEN_WIDTH), Val::Auto),
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Percent(50.0),
+                    top: Val::Percent(20.0),
+                    ..default()
+                },
+                margin: UiRect {
+                    left: Val::Px(-SCREEN_WIDTH / 2.0),
+                    ..default()
+                },
+                padding: UiRect::all(Val::Px(10.0)),
+                display: Display::None,
+                ..default()
+            },
+            background_color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+            ..default()
+        })
+        .insert(PickerScreen)
+        .insert(Name::new("Scenario Picker"))
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font,
+                        font_size: 24.0,
+                        color: Color::WHITE,
+                    },
+                ))
+                .insert(PickerText);
+        });
+}
+
+/// Opens the picker with Y, Enter restarts the game with the selected scenario
+fn picker_controls(
+    keys: Res<Input<KeyCode>>,
+    mut list: ResMut<ScenarioList>,
+    mut picker: ResMut<ScenarioPicker>,
+    mut screen: Query<&mut Style, With<PickerScreen>>,
+    mut ev_exit: EventWriter<AppExit>,
+) {
+    let Ok(mut style) = screen.get_single_mut() else {
+        return;
+    };
+    if keys.just_pressed(KeyCode::Y) {
+        style.display = match style.display {
+            Display::None => Display::Flex,
+            Display::Flex => Display::None,
+        };
+    }
+    let open = style.display == Display::Flex;
+    if picker.open != open {
+        picker.open = open;
+    }
+    if !open {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Up) && list.selected > 0 {
+        list.selected -= 1;
+    }
+    if keys.just_pressed(KeyCode::Down) && list.selected < list.entries.len() {
+        list.selected += 1;
+    }
+    if !keys.just_pressed(KeyCode::Return) {
+        return;
+    }
+
+    let path = list
+        .selected
+        .checked_sub(1)
+        .map(|index| list.entries[index].path.as_path());
+    let args = restart_args(std::env::args().skip(1), path);
+    let restarted =
+        std::env::current_exe().and_then(|exe| std::process::Command::new(exe).args(&args).spawn());
+    match restarted {
+        Ok(_) => {
+            info!("Restarting with {args:?}");
+            ev_exit.send(AppExit);
+        }
+        Err(err) => error!("Failed to restart with {args:?}: {err}"),
+    }
+}
+
+fn update_picker_screen(
+    list: Res<ScenarioList>,
+    localization: Res<Localization>,
+    screen: Query<&Style, With<PickerScreen>>,
+    mut text: Query<&mut Text, With<PickerText>>,
+) {
+    let Ok(style) = screen.get_single() else {
+        return;
+    };
+    let Ok(mut text) = text.get_single_mut() else {
+        return;
+    };
+    if style.display == Display::None {
+        return;
+    }
+
+    let names = std::iter::once(localization.get("scenario-default"))
+        .chain(list.entries.iter().map(|entry| entry.name.as_str()));
+    let mut lines = vec![localization.get("scenario-title").to_owned()];
+    lines.extend(names.enumerate().map(|(index, name)| {
+        let marker = if index == list.selected { '>' } else { ' ' };
+        format!("{marker} {name}")
+    }));
+    lines.push(localization.get("scenario-hint").to_owned());
+    text.sections[0].value = lines.join("\n");
+}
+
+/// Menu to restart the game with another scenario from `assets/scenarios/`.
+pub(super) struct PickerPlugin;
+impl Plugin for PickerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScenarioList>()
+            .init_resource::<ScenarioPicker>()
+            .add_event::<AppExit>()
+            .add_startup_system(setup_picker_screen)
+            .add_system(picker_controls)
+            .add_system(update_picker_screen.after(picker_controls));
+    }
+}
@@ -1,11 +1,18 @@
 /// Inspired by https://github.com/nicopap/bevy-scene-hook
-use bevy::{asset::LoadState, ecs::world::EntityRef, prelude::*, scene::SceneInstance};
+use bevy::{
+    asset::LoadState,
+    ecs::{system::EntityCommands, world::EntityRef},
+    gltf::GltfExtras,
+    prelude::*,
+    scene::SceneInstance,
+    utils::HashMap,
+};
 
 /// Component to attach setup function that will be invoked once scene is loaded.
 ///
 /// Example:
 ///
-/// ```
+/// ```ignore
 /// commands
 ///     .spawn(SceneBundle {
 ///         scene: asset_server.load("my_scene.glb#Scene0"),
@@ -27,6 +34,8 @@ use bevy::{asset::LoadState, ecs::world::EntityRef, prelude::*, scene::SceneInst
 ///             });
 ///     }));
 /// ```
+///
+/// For the simple cases like the one above consider using [`SceneMap`] instead.
 #[derive(Component)]
 pub struct SetupRequired(Box<dyn Fn(&mut Commands, &[EntityRef]) + Send + Sync + 'static>);
 
@@ -36,10 +45,209 @@ impl SetupRequired {
     }
 }
 
+type Predicate = Box<dyn Fn(&EntityRef) -> bool + Send + Sync + 'static>;
+type Action = Box<dyn Fn(&mut EntityCommands) + Send + Sync + 'static>;
+
+/// Declarative set of rules applied to every entity of the loaded scene. Converts into [`SetupRequired`].
+/// All matched rules are applied in the order they were added.
+///
+/// Example:
+///
+/// ```ignore
+/// commands
+///     .spawn(SceneBundle {
+///         scene: asset_server.load("my_scene.glb#Scene0"),
+///         ..default()
+///     })
+///     .insert(SetupRequired::from(
+///         SceneMap::new()
+///             .on_name_prefix("Muzzle", |e| {
+///                 e.insert(Muzzle);
+///             })
+///             .on_extras("component", "Body", |e| {
+///                 e.insert(Body);
+///             })
+///             .on_has::<Handle<Mesh>>(|e| {
+///                 e.insert(NotShadowCaster);
+///             }),
+///     ));
+/// ```
+#[derive(Default)]
+pub struct SceneMap {
+    rules: Vec<(Predicate, Action)>,
+}
+
+impl SceneMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a rule with an arbitrary predicate.
+    pub fn on<P, A>(mut self, predicate: P, action: A) -> Self
+    where
+        P: Fn(&EntityRef) -> bool + Send + Sync + 'static,
+        A: Fn(&mut EntityCommands) + Send + Sync + 'static,
+    {
+        self.rules.push((Box::new(predicate), Box::new(action)));
+        self
+    }
+
+    /// Matches GLTF Nodes by name prefix.
+    /// Entities with `Handle<Mesh>` are skipped, as GLTF primitives are named after their meshes, not nodes.
+    pub fn on_name_prefix<A>(self, prefix: &str, action: A) -> Self
+    where
+        A: Fn(&mut EntityCommands) + Send + Sync + 'static,
+    {
+        let prefix = prefix.to_owned();
+        self.on(
+            move |e| {
+                !e.contains::<Handle<Mesh>>()
+                    && matches!(e.get::<Name>(), Some(name) if name.starts_with(&prefix))
+            },
+            action,
+        )
+    }
+
+    /// Matches entities that have component `T`.
+    pub fn on_has<T: Component, A>(self, action: A) -> Self
+    where
+        A: Fn(&mut EntityCommands) + Send + Sync + 'static,
+    {
+        self.on(|e| e.contains::<T>(), action)
+    }
+
+    /// Matches entities with GLTF extras (custom properties, e.g. authored in Blender) where `key` is equal to `value`.
+    pub fn on_extras<A>(self, key: &str, value: impl Into<serde_json::Value>, action: A) -> Self
+    where
+        A: Fn(&mut EntityCommands) + Send + Sync + 'static,
+    {
+        let key = key.to_owned();
+        let value = value.into();
+        self.on(
+            move |e| matches!(parse_extras(e), Some(extras) if extras.get(&key) == Some(&value)),
+            action,
+        )
+    }
+
+    fn apply(&self, commands: &mut Commands, entities: &[EntityRef]) {
+        for entity in entities {
+            for (predicate, action) in self.rules.iter() {
+                if predicate(entity) {
+                    action(&mut commands.entity(entity.id()));
+                }
+            }
+        }
+    }
+}
+
+impl From<SceneMap> for SetupRequired {
+    fn from(scene_map: SceneMap) -> Self {
+        SetupRequired::new(move |commands, entities| scene_map.apply(commands, entities))
+    }
+}
+
+/// Parses GLTF extras as a JSON object, if entity has any.
+pub fn parse_extras(entity: &EntityRef) -> Option<serde_json::Map<String, serde_json::Value>> {
+    match serde_json::from_str(&entity.get::<GltfExtras>()?.value) {
+        Ok(serde_json::Value::Object(map)) => Some(map),
+        _ => None,
+    }
+}
+
+type PropertyHandler = Box<dyn Fn(&mut EntityCommands, &serde_json::Value) + Send + Sync + 'static>;
+
+/// Components and properties that can be assigned to GLTF nodes via extras (custom properties in Blender).
+/// Registered handlers are applied to every scene with `SetupRequired` right before the setup function.
+///
+/// - `{"component": "Barrel"}` or `{"component": ["Barrel", "Joint"]}` inserts components registered by name
+/// - `{"hp": 200}` invokes handler registered for the "hp" property with `200` as an argument
+///
+/// Unknown components and properties are ignored, as DCC tools tend to export a lot of own properties.
+#[derive(Resource, Default)]
+pub struct GltfExtrasRegistry {
+    components: HashMap<String, Action>,
+    properties: HashMap<String, PropertyHandler>,
+}
+
+impl GltfExtrasRegistry {
+    fn apply(
+        &self,
+        commands: &mut EntityCommands,
+        extras: &serde_json::Map<String, serde_json::Value>,
+    ) {
+        for (key, value) in extras.iter() {
+            if key == "component" {
+                let names = match value {
+                    serde_json::Value::String(name) => vec![name.as_str()],
+                    serde_json::Value::Array(names) => {
+                        names.iter().filter_map(|n| n.as_str()).collect()
+                    }
+                    _ => vec![],
+                };
+                for name in names {
+                    match self.components.get(name) {
+                        Some(insert) => insert(commands),
+                        None => debug!("Unknown GLTF component '{name}' for {:?}", commands.id()),
+                    }
+                }
+            } else if let Some(handler) = self.properties.get(key) {
+                handler(commands, value);
+            }
+        }
+    }
+}
+
+/// Extends `App` with GLTF extras registration, see [`GltfExtrasRegistry`].
+pub trait GltfExtrasApp {
+    /// Registers component `T` to be inserted for nodes with `{"component": "<name>"}` extras.
+    fn register_gltf_component<T, F>(&mut self, name: &str, constructor: F) -> &mut Self
+    where
+        T: Component,
+        F: Fn() -> T + Send + Sync + 'static;
+
+    /// Registers handler to be invoked for nodes with `{"<key>": <value>}` extras.
+    fn register_gltf_property<F>(&mut self, key: &str, handler: F) -> &mut Self
+    where
+        F: Fn(&mut EntityCommands, &serde_json::Value) + Send + Sync + 'static;
+}
+
+impl GltfExtrasApp for App {
+    fn register_gltf_component<T, F>(&mut self, name: &str, constructor: F) -> &mut Self
+    where
+        T: Component,
+        F: Fn() -> T + Send + Sync + 'static,
+    {
+        self.init_resource::<GltfExtrasRegistry>()
+            .world
+            .resource_mut::<GltfExtrasRegistry>()
+            .components
+            .insert(
+                name.to_owned(),
+                Box::new(move |commands| {
+                    commands.insert(constructor());
+                }),
+            );
+        self
+    }
+
+    fn register_gltf_property<F>(&mut self, key: &str, handler: F) -> &mut Self
+    where
+        F: Fn(&mut EntityCommands, &serde_json::Value) + Send + Sync + 'static,
+    {
+        self.init_resource::<GltfExtrasRegistry>()
+            .world
+            .resource_mut::<GltfExtrasRegistry>()
+            .properties
+            .insert(key.to_owned(), Box::new(handler));
+        self
+    }
+}
+
 fn setup_scene(
     scenes: Query<(Entity, &Handle<Scene>, &SceneInstance, &SetupRequired)>,
     server: Res<AssetServer>,
     scene_manager: Res<SceneSpawner>,
+    extras_registry: Res<GltfExtrasRegistry>,
     world: &World,
     mut commands: Commands,
 ) {
@@ -52,15 +260,22 @@ fn setup_scene(
                 // storing result of filtering allows us to handle lifetime problems and
                 // workaround `Box<dyn Iterator<Item = EntityRef>>` in function type declaration
                 .collect();
+            for entity in entities.iter() {
+                if let Some(extras) = parse_extras(entity) {
+                    extras_registry.apply(&mut commands.entity(entity.id()), &extras);
+                }
+            }
             setup.0(&mut commands, &entities);
             commands.entity(entity).remove::<SetupRequired>();
         }
     }
 }
 
+/// Runs `SetupRequired` functions and glTF extras handlers once scenes are loaded.
 pub struct SceneSetupPlugin;
 impl Plugin for SceneSetupPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(setup_scene);
+        app.init_resource::<GltfExtrasRegistry>()
+            .add_system(setup_scene);
     }
 }
@@ -0,0 +1,31 @@
+use bevy::prelude::*;
+
+use crate::{display, hud, music, player};
+
+/// User-facing settings grouped by subsystem.
+/// Systems that depend on settings are expected to react on `Res<Settings>::is_changed()`.
+#[derive(Resource, Clone, Debug)]
+pub struct Settings {
+    pub hud: hud::HudSettings,
+    pub accessibility: hud::AccessibilitySettings,
+    pub music: music::MusicSettings,
+    pub display: display::DisplaySettings,
+    pub mouse: player::MouseSettings,
+    pub flight: player::FlightSettings,
+    /// Language code matching a file in `assets/locales`
+    pub language: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            hud: hud::HudSettings::default(),
+            accessibility: hud::AccessibilitySettings::default(),
+            music: music::MusicSettings::default(),
+            display: display::DisplaySettings::default(),
+            mouse: player::MouseSettings::default(),
+            flight: player::FlightSettings::default(),
+            language: String::from("en"),
+        }
+    }
+}
@@ -0,0 +1,291 @@
+//! Energy shields drawn as a translucent bubble around the ship, glowing at the rim while charged
+//! and rippling at recent hits. Hits absorbed by the `Shield` are taken from `DamageEvent`s into a
+//! small buffer of every bubble, which feeds the ripples of its `ShieldMaterial`. Bubble shields of
+//! `ShieldProjector`s are drawn with the same material and ripple where they absorb shots.
+
+use bevy::{
+    pbr::{MaterialPipeline, MaterialPipelineKey, NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        mesh::MeshVertexBufferLayout,
+        render_resource::{
+            AsBindGroup, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError,
+        },
+    },
+};
+
+use crate::projectile::{DamageEvent, DamageSystems, Shield};
+
+/// Number of hits rippling at the same time on a shield, must match the shader
+const MAX_IMPACTS: usize = 4;
+/// Ripple of a hit spreads and fades out in this time, in seconds
+const RIPPLE_TIME: f32 = 0.8;
+
+/// Draws the `Shield` of the entity as a bubble of the radius.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct ShieldEffect {
+    /// In meters
+    pub radius: f32,
+}
+
+impl ShieldEffect {
+    pub fn new(radius: f32) -> Self {
+        Self { radius }
+    }
+}
+
+/// Fresnel bubble with ripples spreading from up to `MAX_IMPACTS` recent hits.
+#[derive(AsBindGroup, TypeUuid, Clone, Debug)]
+#[uuid = "b3f41c26-7d0e-4a8f-9e52-6c1d8a2f7e94"]
+pub struct ShieldMaterial {
+    #[uniform(0)]
+    pub color: Color,
+    /// Directions to the hits in world space with the ripple progress in `w`, from 0 right after
+    /// the hit to 1 once faded out
+    #[uniform(0)]
+    pub impacts: [Vec4; MAX_IMPACTS],
+    /// From 0 for a depleted shield to 1 for a full one
+    #[uniform(0)]
+    pub charge: f32,
+}
+
+impl ShieldMaterial {
+    pub fn new(color: Color) -> Self {
+        Self {
+            color,
+            impacts: [Vec4::W; MAX_IMPACTS],
+            charge: 1.0,
+        }
+    }
+}
+
+impl Material for ShieldMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/shield.wgsl".into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        AlphaMode::Blend
+    }
+
+    // Hits are seen from the inside as well, like by the player in the cockpit
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayout,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        descriptor.primitive.cull_mode = None;
+        Ok(())
+    }
+}
+
+/// Recent hits on the shield bubble: directions in its local space with the ripple progress.
+/// A new hit takes the place of the oldest one.
+#[derive(Component, Debug)]
+pub(crate) struct ShieldImpacts([(Vec3, f32); MAX_IMPACTS]);
+
+impl ShieldImpacts {
+    pub(crate) fn new() -> Self {
+        Self([(Vec3::Y, 1.0); MAX_IMPACTS])
+    }
+
+    fn push(&mut self, direction: Vec3) {
+        if let Some(oldest) = self.0.iter_mut().max_by(|a, b| a.1.total_cmp(&b.1)) {
+            *oldest = (direction, 0.0);
+        }
+    }
+
+    /// Starts a ripple at the world `position` on the bubble with the `transform`
+    pub(crate) fn hit(&mut self, transform: &GlobalTransform, position: Vec3) {
+        let local = transform.affine().inverse().transform_point3(position);
+        // Hazards hit the ship as a whole, at its center
+        if let Some(direction) = local.try_normalize() {
+            self.push(direction);
+        }
+    }
+
+    fn tick(&mut self, seconds: f32) {
+        for (_, progress) in self.0.iter_mut() {
+            *progress = (*progress + seconds / RIPPLE_TIME).min(1.0);
+        }
+    }
+
+    /// `ShieldMaterial::impacts` of the bubble turned by the `rotation`. Faded hits are all the
+    /// same, so the material is left alone once the ripples are gone.
+    fn uniforms(&self, rotation: Quat) -> [Vec4; MAX_IMPACTS] {
+        self.0.map(|(direction, progress)| {
+            if progress < 1.0 {
+                (rotation * direction).extend(progress)
+            } else {
+                Vec4::W
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ShieldImpacts, MAX_IMPACTS, RIPPLE_TIME};
+    use bevy::prelude::{Quat, Vec3, Vec4};
+
+    #[test]
+    fn test_impacts() {
+        let mut impacts = ShieldImpacts::new();
+        assert_eq!(impacts.uniforms(Quat::IDENTITY), [Vec4::W; MAX_IMPACTS]);
+
+        for direction in [Vec3::X, Vec3::Y, Vec3::Z, -Vec3::X] {
+            impacts.push(direction);
+            impacts.tick(0.1);
+        }
+        // the oldest hit is replaced
+        impacts.push(-Vec3::Y);
+        let uniforms = impacts.uniforms(Quat::IDENTITY);
+        assert!(uniforms.contains(&(-Vec3::Y).extend(0.0)));
+        assert!(!uniforms.iter().any(|u| u.truncate() == Vec3::X));
+
+        // turned with the bubble
+        let uniforms = impacts.uniforms(Quat::from_rotation_z(std::f32::consts::PI));
+        assert!(uniforms[0].truncate().distance(Vec3::Y) < 1e-5);
+
+        impacts.tick(RIPPLE_TIME);
+        assert_eq!(impacts.uniforms(Quat::IDENTITY), [Vec4::W; MAX_IMPACTS]);
+    }
+}
+
+/// Bubble drawing the `ShieldEffect` it's attached to.
+#[derive(Component)]
+struct ShieldBubble;
+
+#[derive(Resource)]
+struct ShieldAssets {
+    mesh: Handle<Mesh>,
+}
+
+fn setup_shields(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    commands.insert_resource(ShieldAssets {
+        // Unit sphere, scaled to the radius of each shield
+        mesh: meshes.add(Mesh::from(shape::UVSphere {
+            radius: 1.0,
+            sectors: 48,
+            stacks: 24,
+        })),
+    });
+}
+
+/// Spawns a bubble for every new shield effect, and removes it once the effect is gone
+fn spawn_bubbles(
+    mut commands: Commands,
+    shield_assets: Res<ShieldAssets>,
+    mut materials: ResMut<Assets<ShieldMaterial>>,
+    effects: Query<(Entity, &ShieldEffect, &GlobalTransform), Added<ShieldEffect>>,
+    bubbles: Query<(Entity, &Parent), With<ShieldBubble>>,
+    has_effect: Query<(), With<ShieldEffect>>,
+) {
+    for (bubble, parent) in bubbles.iter() {
+        if !has_effect.contains(parent.get()) {
+            commands.entity(bubble).despawn_recursive();
+        }
+    }
+
+    for (entity, effect, transform) in effects.iter() {
+        // Pooled drones get the effect anew on every spawn, while their bubble is kept
+        if bubbles.iter().any(|(_, parent)| parent.get() == entity) {
+            continue;
+        }
+        let (scale, _, _) = transform.to_scale_rotation_translation();
+        let bubble = commands
+            .spawn(MaterialMeshBundle {
+                mesh: shield_assets.mesh.clone(),
+                // Own material per bubble for its own ripples
+                material: materials.add(ShieldMaterial::new(Color::rgba(0.4, 0.8, 1.0, 0.5))),
+                transform: Transform::from_scale(Vec3::splat(effect.radius) / scale),
+                ..default()
+            })
+            .insert(ShieldBubble)
+            .insert(ShieldImpacts::new())
+            .insert(NotShadowCaster)
+            .insert(NotShadowReceiver)
+            .insert(Name::new("Shield Effect"))
+            .id();
+        commands.entity(entity).add_child(bubble);
+    }
+}
+
+/// Hits absorbed by shields start ripples on their bubbles
+fn record_impacts(
+    mut ev_damage: EventReader<DamageEvent>,
+    parent_query: Query<&Parent>,
+    has_effect: Query<(), With<ShieldEffect>>,
+    mut bubbles: Query<(&Parent, &GlobalTransform, &mut ShieldImpacts), With<ShieldBubble>>,
+) {
+    for ev in ev_damage.iter().filter(|ev| ev.absorbed > 0) {
+        // Colliders might be attached to children of the shielded ship
+        let Some(ship) = std::iter::once(ev.target)
+            .chain(parent_query.iter_ancestors(ev.target))
+            .find(|&e| has_effect.contains(e))
+        else {
+            continue;
+        };
+        let bubble = bubbles
+            .iter_mut()
+            .find(|(parent, _, _)| parent.get() == ship);
+        if let Some((_, transform, mut impacts)) = bubble {
+            impacts.hit(transform, ev.position);
+        }
+    }
+}
+
+/// Feeds the charge and the ripples to the materials, touching only the ones that change
+fn update_materials(
+    time: Res<Time>,
+    shields: Query<&Shield>,
+    mut bubbles: Query<(
+        &Parent,
+        &GlobalTransform,
+        &Handle<ShieldMaterial>,
+        &mut ShieldImpacts,
+        Option<&ShieldBubble>,
+    )>,
+    mut materials: ResMut<Assets<ShieldMaterial>>,
+) {
+    for (parent, transform, handle, mut impacts, effect) in bubbles.iter_mut() {
+        impacts.tick(time.delta_seconds());
+        let (_, rotation, _) = transform.to_scale_rotation_translation();
+        let uniforms = impacts.uniforms(rotation);
+        // Parked drones have no shield, and their bubbles are hidden anyway. Bubble shields stay
+        // up at full charge until their projector falls.
+        let charge = match effect {
+            Some(_) => shields
+                .get(parent.get())
+                .map_or(0.0, |shield| shield.percent() as f32 / 100.0),
+            None => 1.0,
+        };
+
+        let Some(material) = materials.get(handle) else {
+            continue;
+        };
+        if material.impacts != uniforms || material.charge != charge {
+            if let Some(material) = materials.get_mut(handle) {
+                material.impacts = uniforms;
+                material.charge = charge;
+            }
+        }
+    }
+}
+
+/// Bubbles of the `ShieldEffect`s and bubble shields drawn with `ShieldMaterial`.
+pub struct ShieldEffectPlugin;
+impl Plugin for ShieldEffectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(MaterialPlugin::<ShieldMaterial>::default())
+            .register_type::<ShieldEffect>()
+            .add_event::<DamageEvent>()
+            .add_startup_system(setup_shields)
+            .add_system(spawn_bubbles)
+            .add_system(record_impacts.after(DamageSystems))
+            .add_system(update_materials.after(record_impacts));
+    }
+}
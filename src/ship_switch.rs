@@ -0,0 +1,297 @@
+//! Control transfer between friendly ships. N takes over the primary locked target if it's a
+//! friendly ship, or the nearest one otherwise, and once the player's ship is destroyed the control
+//! passes to the nearest one instead of the kill-cam.
+//!
+//! The camera rig is retargeted, the player's flight components and weapon bindings move to the
+//! new ship, while the old one is flown by the AI again.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    aiming::{FireControl, GunLayer, LeadQuality},
+    collision_warning::CollisionWarning,
+    components::{Fraction, HitPoints, KinematicPush, Respawnable},
+    drone::{Drone, DronePilot},
+    gun::{Gun, Projectile, Trigger},
+    player::{
+        Cruise, FlightAssist, HeavyWeapon, Player, PlayerCamera, PrimaryWeapon, SecondaryWeapon,
+        TargetQueue, Throttle,
+    },
+    projectile::{DamageEvent, DamageSystems},
+    thruster::FlightControl,
+};
+
+/// Ship the player can take control of, flown by the AI otherwise.
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct Controllable;
+
+/// Transfers the player's control to the ship.
+pub struct SwitchShipEvent(pub Entity);
+
+/// Skills of the gun layer of the ship flown by the player, restored once the player leaves it.
+#[derive(Component)]
+struct AiPilot {
+    lead: LeadQuality,
+    aim_error: f32,
+    reaction_delay: f32,
+}
+
+impl AiPilot {
+    fn new(gun_layer: &GunLayer) -> Self {
+        Self {
+            lead: gun_layer.lead,
+            aim_error: gun_layer.aim_error,
+            reaction_delay: gun_layer.reaction_delay,
+        }
+    }
+
+    fn gun_layer(&self) -> GunLayer {
+        GunLayer {
+            lead: self.lead,
+            aim_error: self.aim_error,
+            reaction_delay: self.reaction_delay,
+            ..default()
+        }
+    }
+}
+
+/// Ship to take over from the player at the `position`: the `preferred` one if it's among
+/// `candidates`, or the nearest one. Candidates are alive friendly ships with their positions.
+fn successor(
+    position: Vec3,
+    preferred: Option<Entity>,
+    candidates: impl Iterator<Item = (Entity, Vec3)>,
+) -> Option<Entity> {
+    let mut nearest = None;
+    let mut nearest_distance = f32::MAX;
+    for (entity, candidate) in candidates {
+        if Some(entity) == preferred {
+            return preferred;
+        }
+        let distance = candidate.distance_squared(position);
+        if distance < nearest_distance {
+            nearest = Some(entity);
+            nearest_distance = distance;
+        }
+    }
+    nearest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::successor;
+    use bevy::prelude::{Entity, Vec3};
+
+    #[test]
+    fn test_successor() {
+        let near = (Entity::from_raw(1), Vec3::X * 10.0);
+        let far = (Entity::from_raw(2), Vec3::X * 100.0);
+        assert_eq!(
+            successor(Vec3::ZERO, None, [far, near].into_iter()),
+            Some(near.0)
+        );
+        assert_eq!(
+            successor(Vec3::ZERO, Some(far.0), [near, far].into_iter()),
+            Some(far.0)
+        );
+        // locked target isn't a friendly ship
+        assert_eq!(
+            successor(
+                Vec3::ZERO,
+                Some(Entity::from_raw(3)),
+                [far, near].into_iter()
+            ),
+            Some(near.0)
+        );
+        assert_eq!(successor(Vec3::ZERO, None, std::iter::empty()), None);
+    }
+}
+
+type Candidates<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static GlobalTransform,
+        &'static Fraction,
+        &'static HitPoints,
+    ),
+    (With<Controllable>, Without<Player>),
+>;
+
+/// Alive friendly ships of the `fraction`, with their positions
+fn friendly_ships(candidates: &Candidates, fraction: Fraction) -> Vec<(Entity, Vec3)> {
+    candidates
+        .iter()
+        .filter(|(_, _, &f, hp)| f == fraction && !hp.dead())
+        .map(|(entity, transform, _, _)| (entity, transform.translation()))
+        .collect()
+}
+
+fn switch_controls(
+    keys: Res<Input<KeyCode>>,
+    player: Query<(&GlobalTransform, &Fraction, &TargetQueue), With<Player>>,
+    candidates: Candidates,
+    mut ev_switch: EventWriter<SwitchShipEvent>,
+) {
+    if !keys.just_pressed(KeyCode::N) {
+        return;
+    }
+    let Ok((transform, &fraction, queue)) = player.get_single() else {
+        return;
+    };
+    let ships = friendly_ships(&candidates, fraction);
+    if let Some(ship) = successor(transform.translation(), queue.primary(), ships.into_iter()) {
+        ev_switch.send(SwitchShipEvent(ship));
+    }
+}
+
+/// Destroyed player takes over the nearest friendly ship, if there is any
+fn switch_on_death(
+    mut ev_damage: EventReader<DamageEvent>,
+    player: Query<(Entity, &GlobalTransform, &Fraction), With<Player>>,
+    candidates: Candidates,
+    mut ev_switch: EventWriter<SwitchShipEvent>,
+) {
+    let Ok((player, transform, &fraction)) = player.get_single() else {
+        return;
+    };
+    if !ev_damage
+        .iter()
+        .any(|ev| ev.target == player && ev.destroyed)
+    {
+        return;
+    }
+    let ships = friendly_ships(&candidates, fraction);
+    if let Some(ship) = successor(transform.translation(), None, ships.into_iter()) {
+        ev_switch.send(SwitchShipEvent(ship));
+    }
+}
+
+fn switch_ship(
+    mut commands: Commands,
+    mut ev_switch: EventReader<SwitchShipEvent>,
+    player: Query<(Entity, &FlightAssist, &CollisionWarning, &HitPoints), With<Player>>,
+    mut cameras: Query<&mut PlayerCamera>,
+    pilots: Query<(
+        Option<&GunLayer>,
+        Option<&AiPilot>,
+        Option<&FireControl>,
+        Option<&FlightControl>,
+        Option<&Drone>,
+    )>,
+    children: Query<&Children>,
+    guns: Query<&Gun>,
+) {
+    let Some(SwitchShipEvent(ship)) = ev_switch.iter().last() else {
+        return;
+    };
+    let Ok((old, &assist, warning, hp)) = player.get_single() else {
+        return;
+    };
+    if *ship == old {
+        return;
+    }
+    info!("Switched to {ship:?}");
+
+    // The destroyed ship was kept for the kill-cam, which isn't needed anymore
+    if hp.dead() {
+        commands.entity(old).despawn_recursive();
+    } else {
+        let (_, pilot, fire_control, _, drone) = pilots.get(old).unwrap_or_default();
+        commands
+            .entity(old)
+            .remove::<(
+                Player,
+                Throttle,
+                Cruise,
+                FlightAssist,
+                CollisionWarning,
+                TargetQueue,
+                KinematicPush,
+                Respawnable,
+                AiPilot,
+            )>()
+            .insert(pilot.map_or_else(GunLayer::default, AiPilot::gun_layer))
+            .insert(RigidBody::Dynamic);
+        if fire_control.is_none() {
+            commands.entity(old).insert(FireControl::default());
+        }
+        // Drones keep their AI while flown by the player, other ships get the one of drones
+        if drone.is_none() {
+            commands
+                .entity(old)
+                .insert(Drone::default())
+                .insert(DronePilot::default());
+        }
+        for gun in children.iter_descendants(old).filter(|&e| guns.contains(e)) {
+            // The AI holds the triggers of launchers as of any other gun
+            commands
+                .entity(gun)
+                .remove::<(PrimaryWeapon, SecondaryWeapon, HeavyWeapon)>()
+                .insert(Trigger::default());
+        }
+    }
+
+    let (gun_layer, _, _, flight_control, _) = pilots.get(*ship).unwrap_or_default();
+    let mut new = commands.entity(*ship);
+    if let Some(gun_layer) = gun_layer {
+        // Drones are steered by their gun layers, so without one the AI lets go of the ship
+        new.remove::<GunLayer>().insert(AiPilot::new(gun_layer));
+    }
+    if flight_control.is_some() {
+        new.insert(FlightControl::default());
+    }
+    new.insert(Player)
+        .insert(Throttle::default())
+        .insert(Cruise::default())
+        .insert(assist)
+        .insert(CollisionWarning {
+            auto_brake: warning.auto_brake,
+            ..default()
+        })
+        .insert(TargetQueue::default())
+        .insert(KinematicPush::default())
+        .insert(Respawnable)
+        // Flown by `move_player` directly, like the ship the player spawned in
+        .insert(RigidBody::KinematicPositionBased);
+    for gun in children.iter_descendants(*ship) {
+        let Ok(gun_kind) = guns.get(gun).map(Gun::projectile) else {
+            continue;
+        };
+        match gun_kind {
+            Projectile::Bullet | Projectile::FlakShell => {
+                commands.entity(gun).insert(PrimaryWeapon);
+            }
+            Projectile::Rocket => {
+                commands
+                    .entity(gun)
+                    .insert(SecondaryWeapon)
+                    .insert(Trigger::semi_automatic());
+            }
+            Projectile::Slug => {
+                commands.entity(gun).insert(HeavyWeapon);
+            }
+        }
+    }
+
+    for mut camera in cameras.iter_mut() {
+        camera.target = *ship;
+    }
+}
+
+/// Switching between `Controllable` ships with N or on death. Expects the `PlayerPlugin`.
+pub struct ShipSwitchPlugin;
+impl Plugin for ShipSwitchPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Controllable>()
+            .add_event::<SwitchShipEvent>()
+            .add_event::<DamageEvent>()
+            .add_system(switch_controls)
+            .add_system(switch_on_death.after(DamageSystems))
+            // Before the kill-cam looks for the destroyed player at the end of the frame
+            .add_system(switch_ship.after(switch_controls).after(switch_on_death));
+    }
+}
@@ -0,0 +1,330 @@
+use bevy::{prelude::*, scene::SceneInstance};
+use bevy_rapier3d::prelude::*;
+use std::f32::consts::PI;
+
+use crate::{
+    collider_setup,
+    components::{Fraction, HitPoints},
+    drone::{Drone, SpawnDroneEvent},
+    player::PlayerCamera,
+    reinforcements::{ReinforcementPool, Reinforcements, SpawnPoint},
+    scene_setup,
+    turret::{SpawnTurretEvent, TurretKind},
+};
+
+/// Max number of drones of a side on the field at once, the rest arrive as reinforcements
+const FIELD_SIZE: usize = 12;
+/// Turrets keep at least this distance from the capital ship center, in meters
+const CAPITAL_SHIP_CLEARANCE: f32 = 60.0;
+/// Reinforcements arrive this far behind the side center, in meters
+const SPAWN_POINT_OFFSET: f32 = 300.0;
+/// The player starts this far behind the side center, in meters
+const PLAYER_OFFSET: f32 = 150.0;
+/// The capital ship model is scaled up to look realistic, which scales everything mounted on it
+pub const CAPITAL_SHIP_SCALE: f32 = 2.0;
+
+/// Forces of a single side of the skirmish.
+#[derive(Clone, Debug)]
+pub struct FleetBudget {
+    /// Total number of drones, including reinforcements
+    pub drones: u32,
+    pub turrets: u32,
+    pub capital_ship: bool,
+}
+
+impl Default for FleetBudget {
+    fn default() -> Self {
+        Self {
+            drones: 30,
+            turrets: 6,
+            capital_ship: true,
+        }
+    }
+}
+
+/// Two opposing fleets fighting autonomously, the player joins one of the sides.
+#[derive(Resource, Clone, Debug)]
+pub struct SkirmishConfig {
+    /// Budget of the `Fraction::Drones` side
+    pub drones_side: FleetBudget,
+    /// Budget of the `Fraction::Turrets` side
+    pub turrets_side: FleetBudget,
+    /// Distance between side centers, in meters
+    pub separation: f32,
+    /// Min distance between units in formation, in meters
+    pub spacing: f32,
+    pub player_side: Fraction,
+}
+
+impl Default for SkirmishConfig {
+    fn default() -> Self {
+        Self {
+            drones_side: FleetBudget::default(),
+            turrets_side: FleetBudget::default(),
+            separation: 2000.0,
+            spacing: 50.0,
+            player_side: Fraction::Turrets,
+        }
+    }
+}
+
+impl SkirmishConfig {
+    /// Parses command line arguments, returns `None` unless `--skirmish` is passed.
+    /// The player joins the turrets side by default, `--side=drones` switches it.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        let mut enabled = false;
+        let mut player_side = Fraction::Turrets;
+        for arg in args {
+            match arg.as_str() {
+                "--skirmish" => enabled = true,
+                "--side=drones" => player_side = Fraction::Drones,
+                "--side=turrets" => player_side = Fraction::Turrets,
+                _ => {}
+            }
+        }
+        enabled.then(|| Self {
+            player_side,
+            ..default()
+        })
+    }
+
+    /// Sides with their budgets and centers
+    fn sides(&self) -> [(Fraction, &FleetBudget, Vec3); 2] {
+        let offset = Vec3::X * self.separation / 2.0;
+        [
+            (Fraction::Drones, &self.drones_side, -offset),
+            (Fraction::Turrets, &self.turrets_side, offset),
+        ]
+    }
+}
+
+/// Initial placement of a fleet, all units face the enemy.
+#[derive(Debug)]
+struct FleetLayout {
+    capital_ship: Option<Transform>,
+    turrets: Vec<Transform>,
+    drones: Vec<Transform>,
+    spawn_point: Vec3,
+}
+
+fn layout_fleet(budget: &FleetBudget, center: Vec3, enemy: Vec3, spacing: f32) -> FleetLayout {
+    let forward = (enemy - center).normalize();
+    let right = forward.cross(Vec3::Y).normalize();
+    let facing = |position: Vec3| {
+        Transform::from_translation(position).looking_at(position + forward, Vec3::Y)
+    };
+
+    // Turrets are evenly distributed on a ring around the capital ship,
+    // which is wide enough to keep the spacing between neighbours
+    let turrets = budget.turrets as usize;
+    let ring_radius = if turrets > 1 {
+        spacing / (2.0 * (PI / turrets as f32).sin())
+    } else {
+        0.0
+    }
+    .max(CAPITAL_SHIP_CLEARANCE);
+    let turrets = (0..turrets)
+        .map(|i| {
+            let angle = 2.0 * PI * i as f32 / turrets as f32;
+            facing(center + (forward * angle.cos() + right * angle.sin()) * ring_radius)
+        })
+        .collect();
+
+    // Drones form a square grid in front of the turrets, the rest of them wait as reinforcements
+    let drones = (budget.drones as usize).min(FIELD_SIZE);
+    let columns = ((drones as f32).sqrt().ceil() as usize).max(1);
+    let drones = (0..drones)
+        .map(|i| {
+            let (row, column) = (i / columns, i % columns);
+            let lateral = (column as f32 - (columns - 1) as f32 / 2.0) * spacing;
+            let depth = ring_radius + spacing * (row + 1) as f32;
+            facing(center + forward * depth + right * lateral)
+        })
+        .collect();
+
+    FleetLayout {
+        capital_ship: budget.capital_ship.then(|| facing(center)),
+        turrets,
+        drones,
+        spawn_point: center - forward * SPAWN_POINT_OFFSET,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{layout_fleet, FleetBudget, CAPITAL_SHIP_CLEARANCE, FIELD_SIZE};
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_fleet_layout_spacing() {
+        let budget = FleetBudget {
+            drones: 40,
+            turrets: 20,
+            capital_ship: true,
+        };
+        let spacing = 50.0;
+        let layout = layout_fleet(&budget, Vec3::ZERO, Vec3::X * 1000.0, spacing);
+        assert_eq!(layout.turrets.len(), 20);
+        // drones beyond the field size arrive later as reinforcements
+        assert_eq!(layout.drones.len(), FIELD_SIZE);
+
+        let positions: Vec<_> = layout
+            .turrets
+            .iter()
+            .chain(layout.drones.iter())
+            .map(|t| t.translation)
+            .collect();
+        for (i, a) in positions.iter().enumerate() {
+            assert!(a.length() >= CAPITAL_SHIP_CLEARANCE - 0.01);
+            for b in &positions[i + 1..] {
+                assert!(a.distance(*b) >= spacing - 0.01, "{a} is too close to {b}");
+            }
+        }
+        // drones face the enemy
+        assert!(layout
+            .drones
+            .iter()
+            .all(|t| t.forward().dot(Vec3::X) > 0.99));
+    }
+}
+
+/// Spawns the spaceship used as a capital ship, it has no guns on its own
+pub fn spawn_capital_ship(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    transform: Transform,
+) -> Entity {
+    commands
+        .spawn(SceneBundle {
+            scene: assets.load("models/spaceship_v1.glb#Scene0"),
+            ..default()
+        })
+        .insert(Restitution::coefficient(1.0))
+        .insert(TransformBundle::from(
+            transform.with_scale(Vec3::splat(CAPITAL_SHIP_SCALE)),
+        ))
+        .insert(scene_setup::SetupRequired::new(
+            move |commands, entities| {
+                let mut root: Option<Entity> = None;
+                let mut mesh_source: Option<Entity> = None;
+                for entity in entities {
+                    if entity.contains::<SceneInstance>() {
+                        root = Some(entity.id());
+                    }
+                    if entity.contains::<Handle<Mesh>>() {
+                        mesh_source = Some(entity.id());
+                    }
+                }
+
+                commands
+                    .entity(root.unwrap())
+                    .insert(collider_setup::ConvexDecomposition {
+                        mesh_source: mesh_source.unwrap(),
+                        parameters: VHACDParameters {
+                            concavity: 0.06,
+                            ..default()
+                        },
+                    });
+            },
+        ))
+        .insert(HitPoints::new(2000))
+        .insert(Name::new("Spaceship"))
+        .id()
+}
+
+fn setup_skirmish(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    config: Res<SkirmishConfig>,
+    mut battle: ResMut<Reinforcements>,
+    mut ev_spawn_drone: EventWriter<SpawnDroneEvent>,
+    mut ev_spawn_turret: EventWriter<SpawnTurretEvent>,
+) {
+    let sides = config.sides();
+    for (index, &(fraction, budget, center)) in sides.iter().enumerate() {
+        let enemy = sides[1 - index].2;
+        let layout = layout_fleet(budget, center, enemy, config.spacing);
+
+        if let Some(transform) = layout.capital_ship {
+            let ship = spawn_capital_ship(&mut commands, &assets, transform);
+            commands.entity(ship).insert(fraction);
+        }
+        for (transform, &kind) in layout
+            .turrets
+            .into_iter()
+            .zip(TurretKind::ALL.iter().cycle())
+        {
+            ev_spawn_turret.send(SpawnTurretEvent {
+                kind,
+                prefab: None,
+                transform,
+                parent: None,
+                rotation_speed: 120_f32.to_radians(),
+                fraction,
+            });
+        }
+
+        let drones = vec![Drone::Infiltrator, Drone::Praetor];
+        for (transform, &drone) in layout.drones.into_iter().zip(drones.iter().cycle()) {
+            ev_spawn_drone.send(SpawnDroneEvent {
+                drone,
+                fraction,
+                transform,
+                prefab: None,
+                entry_speed: 0.0,
+                modifiers: default(),
+                hardpoints: Vec::new(),
+            });
+        }
+        if budget.drones > 0 {
+            battle.pools.push(ReinforcementPool::new(
+                fraction,
+                budget.drones,
+                FIELD_SIZE,
+                drones,
+            ));
+            commands
+                .spawn(TransformBundle::from(
+                    Transform::from_translation(layout.spawn_point).looking_at(enemy, Vec3::Y),
+                ))
+                .insert(SpawnPoint {
+                    fraction,
+                    radius: config.spacing * 2.0,
+                })
+                .insert(Name::new("Spawn Point"));
+        }
+    }
+}
+
+/// Moves the player to the joined side, behind its fleet
+fn join_side(
+    config: Res<SkirmishConfig>,
+    camera: Query<&PlayerCamera, Added<PlayerCamera>>,
+    mut ships: Query<(&mut Transform, &mut Fraction)>,
+) {
+    // The camera rig is spawned once with the player, while the ship may change later
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    let Ok((mut transform, mut fraction)) = ships.get_mut(camera.target) else {
+        return;
+    };
+    let sides = config.sides();
+    let Some(index) = sides.iter().position(|side| side.0 == config.player_side) else {
+        return;
+    };
+    let (center, enemy) = (sides[index].2, sides[1 - index].2);
+
+    *fraction = config.player_side;
+    let position = center - (enemy - center).normalize() * PLAYER_OFFSET;
+    *transform = Transform::from_translation(position).looking_at(enemy, Vec3::Y);
+}
+
+/// Two fleets laid out from the `SkirmishConfig` resource, fighting until one side runs out of
+/// reinforcements.
+pub struct SkirmishPlugin;
+impl Plugin for SkirmishPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_skirmish).add_system(join_side);
+    }
+}
@@ -18,6 +18,13 @@ use bevy::{
     },
 };
 
+use crate::{display::SecondaryCamera, scenario::Scenario};
+
+/// Part of the sky, kept centered on the main camera so that it never gets any closer, like the
+/// skybox and planets.
+#[derive(Component)]
+pub struct Distant;
+
 #[derive(Debug, Clone, TypeUuid)]
 #[uuid = "9509a0f8-3c05-48ee-a13e-a93226c7f488"]
 struct CubemapMaterial {
@@ -29,6 +36,11 @@ impl Material for CubemapMaterial {
         "shaders/cubemap_unlit.wgsl".into()
     }
 
+    // Sorted before everything else in the opaque pass, so planets are drawn over it
+    fn depth_bias(&self) -> f32 {
+        f32::MAX
+    }
+
     fn specialize(
         _pipeline: &MaterialPipeline<Self>,
         descriptor: &mut RenderPipelineDescriptor,
@@ -115,7 +127,22 @@ fn setup(
     mut cubemap_materials: ResMut<Assets<CubemapMaterial>>,
     asset_server: Res<AssetServer>,
     render_device: Res<RenderDevice>,
+    scenario: Option<Res<Scenario>>,
 ) {
+    let environment = scenario
+        .map(|scenario| scenario.environment.clone())
+        .unwrap_or_default();
+    // Setup ambient light
+    // NOTE: The ambient light is used to scale how bright the environment map is so with a bright
+    // environment map, use an appropriate colour and brightness to match
+    commands.insert_resource(environment.ambient_light());
+    commands
+        .spawn(environment.sun_light())
+        .insert(Name::new("Sun"));
+    let Some(skybox) = environment.skybox else {
+        return;
+    };
+
     // Cubemap is generated by https://github.com/petrocket/spacescape, http://alexcpeterson.com/spacescape/
     // And encoded to ktx2 with ASTC encoding and zstd compression using https://github.com/KhronosGroup/KTX-Software:
     // `toktx --encode astc --astc_blk_d 4x4 --zcmp 19 --cubemap background posx.png negx.png posy.png negy.png posz.png negz.png`
@@ -124,7 +151,7 @@ fn setup(
         CompressedImageFormats::from_features(render_device.features())
             .contains(CompressedImageFormats::ASTC_LDR)
     );
-    let skybox_image = asset_server.load("textures/background_astc.ktx2");
+    let skybox_image = asset_server.load(skybox.as_str());
 
     // Raw PNG also can be used with conversion to the cubemap using ImageMagick (see Unity coordinate system):
     // `convert posx.png negx.png posy.png negy.png posz.png negz.png -gravity center -append cubemap.png`
@@ -143,7 +170,6 @@ fn setup(
     // }
     // ```
 
-    // TODO: consider setting skybox as a child to the camera
     commands
         .spawn(MaterialMeshBundle::<CubemapMaterial> {
             mesh: meshes.add(Mesh::from(shape::Cube { size: 10000.0 })),
@@ -152,21 +178,32 @@ fn setup(
             }),
             ..default()
         })
+        .insert(Distant)
         .insert(Name::new("Skybox"));
+}
 
-    // Setup ambient light
-    // NOTE: The ambient light is used to scale how bright the environment map is so with a bright
-    // environment map, use an appropriate colour and brightness to match
-    commands.insert_resource(AmbientLight {
-        color: Color::rgb_u8(210, 220, 240),
-        brightness: 0.3,
-    });
+fn follow_camera(
+    camera: Query<&GlobalTransform, (With<Camera3d>, Without<SecondaryCamera>)>,
+    mut distant: Query<&mut Transform, With<Distant>>,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    let center = camera.translation();
+    for mut transform in distant.iter_mut() {
+        if transform.translation != center {
+            transform.translation = center;
+        }
+    }
 }
 
+/// Cubemap skybox around the scene with the ambient light and the sun, as set by the `Scenario`.
+/// The skybox and other `Distant` entities follow the main camera.
 pub struct SkyboxPlugin;
 impl Plugin for SkyboxPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(MaterialPlugin::<CubemapMaterial>::default())
-            .add_startup_system(setup);
+            .add_startup_system(setup)
+            .add_system(follow_camera);
     }
 }
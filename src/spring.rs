@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+
+/// Critically damped spring is within 1% of its rest position after `SETTLE_FACTOR / omega`
+const SETTLE_FACTOR: f32 = 6.6;
+
+/// Cosmetic animation that springs the entity back to its translation after a `kick`, without
+/// overshooting. Only the offset is animated, so the entity still can be moved by other systems.
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct Spring {
+    /// Seconds to settle back after a kick
+    pub settle_time: f32,
+    /// Displacement from the rest translation, in parent space
+    offset: Vec3,
+    velocity: Vec3,
+    /// Part of the `offset` that is already applied to the `Transform`
+    applied: Vec3,
+}
+
+impl Spring {
+    pub fn new(settle_time: f32) -> Self {
+        Self {
+            settle_time,
+            ..default()
+        }
+    }
+
+    /// Displaces the entity by `offset` in parent space, it springs back from there
+    pub fn kick(&mut self, offset: Vec3) {
+        self.offset += offset;
+    }
+}
+
+/// Offset and velocity of a critically damped spring with angular frequency `omega` after `dt`.
+/// Exact solution, so it's stable with any frame time.
+fn spring_step(offset: Vec3, velocity: Vec3, omega: f32, dt: f32) -> (Vec3, Vec3) {
+    let decay = (-omega * dt).exp();
+    let c = velocity + omega * offset;
+    (
+        (offset + c * dt) * decay,
+        (velocity - c * omega * dt) * decay,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{spring_step, SETTLE_FACTOR};
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_spring_step() {
+        let settle_time = 0.5;
+        let omega = SETTLE_FACTOR / settle_time;
+        let (mut offset, mut velocity) = (Vec3::Z, Vec3::ZERO);
+        for _ in 0..30 {
+            (offset, velocity) = spring_step(offset, velocity, omega, settle_time / 30.0);
+            // critically damped spring never overshoots
+            assert!(offset.z >= 0.0);
+        }
+        assert!(offset.z < 0.02);
+
+        // a single long step ends up in the same place
+        let (single, _) = spring_step(Vec3::Z, Vec3::ZERO, omega, settle_time);
+        assert!((single.z - offset.z).abs() < 1e-4);
+    }
+}
+
+fn animate_springs(time: Res<Time>, mut springs: Query<(&mut Transform, &mut Spring)>) {
+    for (mut transform, mut spring) in springs.iter_mut() {
+        if spring.offset == Vec3::ZERO && spring.applied == Vec3::ZERO {
+            continue;
+        }
+
+        let omega = SETTLE_FACTOR / spring.settle_time.max(f32::EPSILON);
+        let (offset, velocity) =
+            spring_step(spring.offset, spring.velocity, omega, time.delta_seconds());
+        // snap to the rest position once the movement isn't visible anymore
+        let (offset, velocity) = if offset.length_squared() < 1e-8 {
+            (Vec3::ZERO, Vec3::ZERO)
+        } else {
+            (offset, velocity)
+        };
+
+        transform.translation += offset - spring.applied;
+        spring.offset = offset;
+        spring.velocity = velocity;
+        spring.applied = offset;
+    }
+}
+
+/// Spring animations, see [`Spring`].
+pub struct SpringPlugin;
+impl Plugin for SpringPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Spring>().add_system(animate_springs);
+    }
+}
@@ -0,0 +1,203 @@
+//! Practice range with balloons drifting up around the capital ship. Balloons that leave the
+//! practice volume are brought back to where they appear instead of being despawned, and their
+//! number is capped, so a long idle session doesn't pile up physics bodies.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use rand::Rng;
+
+use crate::{components::HitPoints, impact::SurfaceMaterial};
+
+/// Radius of balloons, in meters
+const BALLOON_RADIUS: f32 = 3.0;
+/// Balloons don't appear closer than this to the center along X or Z, where the ship is, in meters
+const SAFE_AREA: f32 = 10.0;
+
+/// Practice volume, a box around the `center`.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct TargetRange {
+    pub center: Vec3,
+    /// Balloons leaving the box of these half sizes are recycled, in meters
+    pub half_extents: Vec3,
+    /// Balloons aren't spawned beyond this count
+    pub max_targets: usize,
+    /// Seconds between new balloons
+    pub spawn_interval: f32,
+}
+
+impl Default for TargetRange {
+    fn default() -> Self {
+        Self {
+            center: Vec3::new(0.0, 2.0, 0.0),
+            half_extents: Vec3::new(120.0, 100.0, 120.0),
+            max_targets: 12,
+            spawn_interval: 5.0,
+        }
+    }
+}
+
+impl TargetRange {
+    pub fn contains(&self, point: Vec3) -> bool {
+        ((point - self.center).abs() - self.half_extents).max_element() <= 0.0
+    }
+
+    /// Random point at the height of the center, outside of the safe area
+    fn spawn_position(&self, rng: &mut impl Rng) -> Vec3 {
+        let reach = self.half_extents.x.min(self.half_extents.z) - BALLOON_RADIUS;
+        // Ranges too small to leave room outside of the safe area get balloons at their corner,
+        // as ones spawned outside of the range would be recycled right away
+        if reach <= SAFE_AREA {
+            let corner = reach.max(0.0);
+            return self.center + Vec3::new(corner, 0.0, corner);
+        }
+        loop {
+            let offset = Vec3 {
+                x: rng.gen_range(-reach..=reach),
+                y: 0.0,
+                z: rng.gen_range(-reach..=reach),
+            };
+            if offset.x.abs() > SAFE_AREA && offset.z.abs() > SAFE_AREA {
+                return self.center + offset;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TargetRange, SAFE_AREA};
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_contains() {
+        let range = TargetRange {
+            center: Vec3::new(0.0, 50.0, 0.0),
+            half_extents: Vec3::new(10.0, 50.0, 10.0),
+            ..Default::default()
+        };
+        assert!(range.contains(Vec3::new(0.0, 0.0, 0.0)));
+        assert!(range.contains(Vec3::new(-10.0, 100.0, 10.0)));
+        assert!(!range.contains(Vec3::new(0.0, 101.0, 0.0)));
+        assert!(!range.contains(Vec3::new(11.0, 50.0, 0.0)));
+    }
+
+    #[test]
+    fn test_spawn_position() {
+        let range = TargetRange::default();
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let position = range.spawn_position(&mut rng);
+            assert!(range.contains(position));
+            assert!(position.x.abs() > SAFE_AREA && position.z.abs() > SAFE_AREA);
+        }
+
+        // no room outside of the safe area
+        let small = TargetRange {
+            half_extents: Vec3::splat(SAFE_AREA),
+            ..TargetRange::default()
+        };
+        assert!(small.contains(small.spawn_position(&mut rng)));
+    }
+}
+
+/// Balloon of the practice range.
+#[derive(Component)]
+pub struct ShootingTarget;
+
+#[derive(Resource)]
+struct BalloonAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+fn setup_balloons(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    assets: Res<AssetServer>,
+) {
+    commands.insert_resource(BalloonAssets {
+        mesh: meshes.add(Mesh::from(shape::UVSphere {
+            radius: BALLOON_RADIUS,
+            sectors: 64,
+            stacks: 32,
+        })),
+        material: materials.add(StandardMaterial {
+            base_color_texture: assets.load("textures/aim2.png").into(),
+            ..default()
+        }),
+    });
+}
+
+/// Balloons drift up and spin slowly
+fn random_velocity(rng: &mut impl Rng) -> Velocity {
+    Velocity {
+        linvel: Vec3::Y * rng.gen_range(1.0..5.0),
+        angvel: Vec3::Y * rng.gen_range(-2.0..2.0),
+    }
+}
+
+fn spawn_balloons(
+    mut commands: Commands,
+    time: Res<Time>,
+    range: Res<TargetRange>,
+    balloon_assets: Res<BalloonAssets>,
+    balloons: Query<(), With<ShootingTarget>>,
+    mut next_spawn: Local<f32>,
+    mut balloon_number: Local<u32>,
+) {
+    *next_spawn -= time.delta_seconds();
+    if *next_spawn > 0.0 {
+        return;
+    }
+    *next_spawn = range.spawn_interval;
+    if balloons.iter().count() >= range.max_targets {
+        return;
+    }
+
+    let mut rng = rand::thread_rng();
+    let position = range.spawn_position(&mut rng);
+    commands
+        .spawn(PbrBundle {
+            mesh: balloon_assets.mesh.clone(),
+            material: balloon_assets.material.clone(),
+            transform: Transform::from_translation(position)
+                .with_rotation(Quat::from_rotation_x(std::f32::consts::PI * 0.5)),
+            ..default()
+        })
+        .insert(random_velocity(&mut rng))
+        .insert(Collider::ball(BALLOON_RADIUS))
+        .insert(RigidBody::Dynamic)
+        .insert(HitPoints::new(20))
+        .insert(SurfaceMaterial::Fabric)
+        .insert(ShootingTarget)
+        .insert(Name::new(format!("Shooting target #{}", *balloon_number)));
+    *balloon_number += 1;
+}
+
+/// Balloons out of the practice volume are brought back, as good as new
+fn recycle_balloons(
+    range: Res<TargetRange>,
+    mut balloons: Query<(&mut Transform, &mut Velocity, &mut HitPoints), With<ShootingTarget>>,
+) {
+    let mut rng = rand::thread_rng();
+    for (mut transform, mut velocity, mut hp) in balloons.iter_mut() {
+        if range.contains(transform.translation) {
+            continue;
+        }
+        transform.translation = range.spawn_position(&mut rng);
+        *velocity = random_velocity(&mut rng);
+        hp.restore();
+    }
+}
+
+/// Balloons to shoot at around the capital ship, configured by the `TargetRange` resource.
+pub struct TargetRangePlugin;
+impl Plugin for TargetRangePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TargetRange>()
+            .add_startup_system(setup_balloons)
+            .add_system(spawn_balloons)
+            .add_system(recycle_balloons);
+    }
+}
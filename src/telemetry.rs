@@ -0,0 +1,234 @@
+use bevy::{
+    diagnostic::{
+        Diagnostic, DiagnosticId, Diagnostics, EntityCountDiagnosticsPlugin,
+        FrameTimeDiagnosticsPlugin,
+    },
+    prelude::*,
+    utils::Instant,
+};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+
+use crate::{
+    aiming::GunLayer,
+    components::Fraction,
+    projectile::{DamageEvent, DamageSystems, Owner},
+};
+
+/// Telemetry file used when `--telemetry` is passed without a path
+const DEFAULT_PATH: &str = "telemetry.jsonl";
+/// Seconds between records
+const RECORD_INTERVAL: f32 = 1.0;
+
+/// Time spent in the `Update` stage, where most of the gameplay systems run
+pub const UPDATE_TIME: DiagnosticId =
+    DiagnosticId::from_u128(142087251093712450869326451023648790112);
+/// Time spent from the beginning of `Update` till the end of `PostUpdate`, including physics
+pub const SIMULATION_TIME: DiagnosticId =
+    DiagnosticId::from_u128(204519835672109843275610398462175093384);
+
+/// Metrics of a single `RECORD_INTERVAL`, written as a line of JSON.
+#[derive(Serialize, Debug, Default)]
+struct TelemetryRecord {
+    /// Seconds since the start
+    time: f32,
+    frames: u32,
+    /// Average frame time, in milliseconds
+    frame_time: f32,
+    /// Armed units, i.e. entities with a `GunLayer`, by their fraction
+    units: BTreeMap<String, usize>,
+    shots_fired: usize,
+    hits: usize,
+    kills: usize,
+    /// Averaged values of all registered Bevy diagnostics by their names
+    diagnostics: BTreeMap<String, f64>,
+}
+
+#[derive(Resource)]
+struct Telemetry {
+    /// `None` if the file can't be written, so telemetry is disabled
+    file: Option<BufWriter<File>>,
+    /// Metrics collected since the last record
+    record: TelemetryRecord,
+    since_record: f32,
+}
+
+#[derive(Resource, Default)]
+struct StageTimer {
+    update_start: Option<Instant>,
+}
+
+#[derive(StageLabel)]
+enum TelemetryStage {
+    BeforeUpdate,
+    AfterUpdate,
+    AfterPostUpdate,
+}
+
+fn setup_diagnostics(mut diagnostics: ResMut<Diagnostics>) {
+    diagnostics.add(Diagnostic::new(UPDATE_TIME, "update_time", 20).with_suffix("ms"));
+    diagnostics.add(Diagnostic::new(SIMULATION_TIME, "simulation_time", 20).with_suffix("ms"));
+}
+
+fn start_update(mut timer: ResMut<StageTimer>) {
+    timer.update_start = Some(Instant::now());
+}
+
+fn finish_update(timer: Res<StageTimer>, mut diagnostics: ResMut<Diagnostics>) {
+    if let Some(start) = timer.update_start {
+        diagnostics.add_measurement(UPDATE_TIME, || start.elapsed().as_secs_f64() * 1000.0);
+    }
+}
+
+fn finish_simulation(timer: Res<StageTimer>, mut diagnostics: ResMut<Diagnostics>) {
+    if let Some(start) = timer.update_start {
+        diagnostics.add_measurement(SIMULATION_TIME, || start.elapsed().as_secs_f64() * 1000.0);
+    }
+}
+
+fn count_shots(mut telemetry: ResMut<Telemetry>, projectiles: Query<(), Added<Owner>>) {
+    let shots = projectiles.iter().count();
+    if shots > 0 {
+        telemetry.record.shots_fired += shots;
+    }
+}
+
+fn count_hits(mut telemetry: ResMut<Telemetry>, mut ev_damage: EventReader<DamageEvent>) {
+    for ev in ev_damage.iter() {
+        telemetry.record.hits += 1;
+        if ev.destroyed {
+            telemetry.record.kills += 1;
+        }
+    }
+}
+
+fn write_record(
+    time: Res<Time>,
+    diagnostics: Res<Diagnostics>,
+    mut telemetry: ResMut<Telemetry>,
+    units: Query<&Fraction, With<GunLayer>>,
+) {
+    let telemetry = &mut *telemetry;
+    telemetry.record.frames += 1;
+    telemetry.since_record += time.delta_seconds();
+    if telemetry.since_record < RECORD_INTERVAL {
+        return;
+    }
+    let Some(file) = telemetry.file.as_mut() else {
+        return;
+    };
+
+    let mut record = std::mem::take(&mut telemetry.record);
+    record.time = time.elapsed_seconds();
+    record.frame_time = 1000.0 * telemetry.since_record / record.frames as f32;
+    for fraction in units.iter() {
+        *record.units.entry(format!("{fraction:?}")).or_default() += 1;
+    }
+    record.diagnostics = diagnostics
+        .iter()
+        .filter_map(|d| Some((d.name.to_string(), d.average()?)))
+        .collect();
+    telemetry.since_record = 0.0;
+
+    let result = serde_json::to_writer(&mut *file, &record)
+        .map_err(std::io::Error::from)
+        .and_then(|_| writeln!(file))
+        .and_then(|_| file.flush());
+    if let Err(err) = result {
+        error!("Telemetry is disabled, failed to write a record: {err}");
+        telemetry.file = None;
+    }
+}
+
+/// Records per-second simulation metrics into a JSON Lines file, to compare balance and performance
+/// between runs.
+pub struct TelemetryPlugin {
+    pub path: PathBuf,
+}
+
+impl TelemetryPlugin {
+    /// Parses command line arguments, returns `None` unless `--telemetry` or `--telemetry=<path>`
+    /// is passed.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        args.into_iter().find_map(|arg| {
+            let path = match arg.strip_prefix("--telemetry")? {
+                "" => DEFAULT_PATH,
+                path => path.strip_prefix('=')?,
+            };
+            Some(Self { path: path.into() })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TelemetryPlugin, DEFAULT_PATH};
+    use std::path::Path;
+
+    #[test]
+    fn test_from_args() {
+        let parse = |args: &[&str]| {
+            TelemetryPlugin::from_args(args.iter().map(|arg| arg.to_string()))
+                .map(|plugin| plugin.path)
+        };
+        assert_eq!(parse(&["--skirmish"]), None);
+        assert_eq!(parse(&["--telemetry"]).unwrap(), Path::new(DEFAULT_PATH));
+        assert_eq!(
+            parse(&["--skirmish", "--telemetry=runs/a.jsonl"]).unwrap(),
+            Path::new("runs/a.jsonl")
+        );
+        assert_eq!(parse(&["--telemetryfoo"]), None);
+    }
+}
+
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        let file = match File::create(&self.path) {
+            Ok(file) => Some(BufWriter::new(file)),
+            Err(err) => {
+                error!(
+                    "Telemetry is disabled, can't create {}: {err}",
+                    self.path.display()
+                );
+                None
+            }
+        };
+
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugin(FrameTimeDiagnosticsPlugin);
+        }
+        if !app.is_plugin_added::<EntityCountDiagnosticsPlugin>() {
+            app.add_plugin(EntityCountDiagnosticsPlugin);
+        }
+        app.insert_resource(Telemetry {
+            file,
+            record: TelemetryRecord::default(),
+            since_record: 0.0,
+        })
+        .init_resource::<StageTimer>()
+        .add_stage_before(
+            CoreStage::Update,
+            TelemetryStage::BeforeUpdate,
+            SystemStage::single(start_update),
+        )
+        .add_stage_after(
+            CoreStage::Update,
+            TelemetryStage::AfterUpdate,
+            SystemStage::single(finish_update),
+        )
+        .add_stage_after(
+            CoreStage::PostUpdate,
+            TelemetryStage::AfterPostUpdate,
+            SystemStage::single(finish_simulation),
+        )
+        .add_startup_system(setup_diagnostics)
+        .add_system(count_shots)
+        .add_system(count_hits.after(DamageSystems))
+        .add_system_to_stage(CoreStage::Last, write_record);
+    }
+}
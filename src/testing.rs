@@ -0,0 +1,256 @@
+//! Headless app with the gameplay plugins, to check how they play together in tests.
+//!
+//! Models aren't loaded without a renderer, so units that are assembled from glTF scenes get
+//! stand-in colliders and guns here.
+
+use bevy::{
+    asset::{AssetPlugin, LoadState},
+    hierarchy::HierarchyPlugin,
+    prelude::*,
+    scene::ScenePlugin,
+    time::TimeUpdateStrategy,
+    transform::TransformPlugin,
+    utils::{Duration, Instant},
+};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    aiming::{self, FireControl, GunLayer},
+    components::{self, Fraction, HitPoints},
+    drone::{self, Drone, SpawnDroneEvent},
+    gun,
+    prefab::ShipPrefab,
+    projectile, turret, weapon,
+};
+
+/// Duration of a single `TestApp::advance` tick, in seconds
+pub const TICK: f32 = 1.0 / 60.0;
+/// Loaded prefabs are added to the assets and built into ships within this many updates
+const BUILD_UPDATES: u32 = 10;
+
+pub struct TestApp {
+    pub app: App,
+    /// Simulated time, advanced by exactly `TICK` every update
+    now: Instant,
+}
+
+impl TestApp {
+    pub fn new() -> Self {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(TransformPlugin)
+            .add_plugin(HierarchyPlugin)
+            .add_plugin(AssetPlugin::default())
+            .add_plugin(ScenePlugin)
+            .add_asset::<Mesh>()
+            .add_asset::<StandardMaterial>()
+            .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+            .insert_resource(RapierConfiguration {
+                gravity: Vec3::ZERO,
+                ..default()
+            })
+            .add_plugin(components::ComponentsPlugin)
+            .add_plugin(projectile::ProjectilePlugin)
+            .add_plugin(aiming::AimingPlugin)
+            .add_plugin(gun::GunPlugin)
+            .add_plugin(turret::TurretPlugin)
+            .add_plugin(drone::DronePlugin);
+
+        let now = Instant::now();
+        app.insert_resource(TimeUpdateStrategy::ManualInstant(now));
+        // Runs startup systems, the first update has no time delta
+        app.update();
+        Self { app, now }
+    }
+
+    /// Runs `ticks` updates, each `TICK` seconds long
+    pub fn advance(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.now += Duration::from_secs_f32(TICK);
+            self.app
+                .insert_resource(TimeUpdateStrategy::ManualInstant(self.now));
+            self.app.update();
+        }
+    }
+
+    /// Runs updates for at least `seconds` of simulated time
+    pub fn advance_secs(&mut self, seconds: f32) {
+        self.advance((seconds / TICK).ceil() as u32);
+    }
+
+    /// Hit points left in percent, `None` once the entity is destroyed
+    pub fn hit_points(&self, entity: Entity) -> Option<u32> {
+        let hp = self.app.world.get::<HitPoints>(entity)?;
+        Some(hp.percent())
+    }
+
+    /// Stationary ball-shaped target without a fraction, like the shooting targets of the demo
+    pub fn spawn_balloon(&mut self, position: Vec3, hit_points: u32) -> Entity {
+        self.app
+            .world
+            .spawn(TransformBundle::from(Transform::from_translation(position)))
+            .insert(Collider::ball(3.0))
+            .insert(RigidBody::Dynamic)
+            .insert(Velocity::default())
+            .insert(HitPoints::new(hit_points))
+            .insert(Name::new("Balloon"))
+            .id()
+    }
+
+    /// Fixed machine gun that lays itself like a turret does, but never rotates.
+    /// Fire is controlled by the default `FireControl`, so it shoots at targets straight ahead.
+    pub fn spawn_gun_platform(
+        &mut self,
+        transform: Transform,
+        fraction: Fraction,
+        rate_of_fire: f32,
+    ) -> Entity {
+        self.app
+            .world
+            .spawn(TransformBundle::from(transform))
+            .insert(GunLayer::default())
+            .insert(FireControl::default())
+            .insert(fraction)
+            .insert(weapon::MachineGun::new(rate_of_fire))
+            .insert(Name::new("Gun Platform"))
+            .id()
+    }
+
+    /// Runs updates without advancing the simulated time until the ship is built from its
+    /// prefab, which is loaded in the background
+    fn wait_until_built(&mut self, entity: Entity) {
+        let prefab = self
+            .app
+            .world
+            .get::<Handle<ShipPrefab>>(entity)
+            .cloned()
+            .expect("ship has a prefab");
+        loop {
+            let state = self
+                .app
+                .world
+                .resource::<AssetServer>()
+                .get_load_state(&prefab);
+            assert_ne!(
+                state,
+                LoadState::Failed,
+                "prefab of {entity:?} isn't loaded"
+            );
+            if state == LoadState::Loaded {
+                break;
+            }
+            self.app.update();
+        }
+        for _ in 0..BUILD_UPDATES {
+            if self.app.world.get::<HitPoints>(entity).is_some() {
+                return;
+            }
+            self.app.update();
+        }
+        panic!("{entity:?} isn't built from its prefab");
+    }
+
+    /// Spawns a drone the way the game does, with a stand-in collider as its model isn't loaded
+    pub fn spawn_drone(
+        &mut self,
+        drone: Drone,
+        fraction: Fraction,
+        transform: Transform,
+    ) -> Entity {
+        let mut drones = self.app.world.query_filtered::<Entity, With<Drone>>();
+        let existing: Vec<_> = drones.iter(&self.app.world).collect();
+
+        self.app.world.send_event(SpawnDroneEvent {
+            drone,
+            fraction,
+            transform,
+            prefab: None,
+            entry_speed: 0.0,
+            modifiers: default(),
+            hardpoints: Vec::new(),
+        });
+        self.advance(1);
+
+        let entity = drones
+            .iter(&self.app.world)
+            .find(|e| !existing.contains(e))
+            .expect("drone is spawned");
+        self.wait_until_built(entity);
+        self.app
+            .world
+            .entity_mut(entity)
+            .insert(Collider::ball(5.0));
+        entity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TestApp;
+    use crate::{
+        components::{Fraction, HitPoints},
+        drone::Drone,
+    };
+    use bevy::prelude::{Transform, Vec3};
+    use bevy_rapier3d::prelude::Collider;
+
+    #[test]
+    fn test_turret_kills_stationary_balloon() {
+        let mut test = TestApp::new();
+        test.spawn_gun_platform(Transform::IDENTITY, Fraction::Turrets, 10.0);
+        // Platform faces -Z, which is forward in Bevy
+        let balloon = test.spawn_balloon(Vec3::new(0.0, 0.0, -100.0), 20);
+
+        test.advance_secs(1.0);
+        let hp = test.hit_points(balloon).expect("balloon is alive");
+        assert!(hp < 100, "balloon isn't hit, {hp}% left");
+
+        test.advance_secs(4.0);
+        assert_eq!(test.hit_points(balloon), None);
+    }
+
+    #[test]
+    fn test_turret_holds_fire_beyond_effective_range() {
+        let mut test = TestApp::new();
+        test.spawn_gun_platform(Transform::IDENTITY, Fraction::Turrets, 10.0);
+        // Bullets would reach it before expiring, but the machine gun range is 1 km
+        let balloon = test.spawn_balloon(Vec3::new(0.0, 0.0, -1500.0), 20);
+
+        test.advance_secs(10.0);
+        assert_eq!(test.hit_points(balloon), Some(100));
+    }
+
+    #[test]
+    fn test_turret_holds_fire_at_own_fraction() {
+        let mut test = TestApp::new();
+        test.spawn_gun_platform(Transform::IDENTITY, Fraction::Turrets, 10.0);
+        let drone = test.spawn_drone(
+            Drone::Infiltrator,
+            Fraction::Turrets,
+            Transform::from_xyz(0.0, 0.0, -100.0),
+        );
+
+        test.advance_secs(2.0);
+        assert_eq!(test.hit_points(drone), Some(100));
+    }
+
+    #[test]
+    fn test_destroyed_drone_returns_to_pool() {
+        let mut test = TestApp::new();
+        test.spawn_gun_platform(Transform::IDENTITY, Fraction::Turrets, 10.0);
+        let drone = test.spawn_drone(
+            Drone::Infiltrator,
+            Fraction::Drones,
+            Transform::from_xyz(0.0, 0.0, -100.0),
+        );
+        test.app.world.entity_mut(drone).insert(HitPoints::new(1));
+
+        test.advance_secs(2.0);
+        assert_eq!(test.hit_points(drone), None);
+        // Parked out of the battle instead of being despawned
+        let world = &test.app.world;
+        assert!(world.get::<Drone>(drone).is_none());
+        assert!(world.get::<Collider>(drone).is_none());
+        assert!(world.get::<Transform>(drone).unwrap().translation.y < -1000.0);
+    }
+}
@@ -0,0 +1,494 @@
+use bevy::{
+    pbr::{NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+};
+use bevy_rapier3d::prelude::*;
+
+use crate::{
+    collider_setup::{FitPrimitive, MassOverride, PartShape},
+    components::HitPoints,
+    scene_setup::GltfExtrasApp,
+};
+
+/// Passes of the thrust allocation, a handful is enough for a few thrusters to settle
+const ALLOCATION_SWEEPS: usize = 8;
+/// Thrusters are subsystems that can be shot off the ship
+const THRUSTER_HIT_POINTS: u32 = 50;
+/// Size of the engine plume at full throttle, in meters
+const PLUME_LENGTH: f32 = 3.0;
+const PLUME_RADIUS: f32 = 0.3;
+/// Plumes are hidden below this throttle
+const MIN_PLUME_THROTTLE: f32 = 0.02;
+/// Part of the afterburner capacity it has to recharge before burning again once it's emptied
+const AFTERBURNER_RELIGHT: f32 = 0.3;
+/// Force of every reaction control thruster of `hull_thrusters`, relative to the main engines
+const RCS_THRUST_SHARE: f32 = 0.25;
+
+/// Engine nozzle, usually a GLTF node of the ship model.
+/// Pushes the ship along `direction` from the thruster's position, so off-center thrusters
+/// also turn the ship.
+#[derive(Component, Clone, Reflect)]
+#[reflect(Component)]
+pub struct Thruster {
+    /// Force at full throttle, in newtons
+    pub max_force: f32,
+    /// Direction of the thrust in the thruster's local space
+    pub direction: Vec3,
+    /// From 0 to 1, set by the flight control
+    throttle: f32,
+    /// Thrust multiplier of the burning afterburner, 1 without it
+    boost: f32,
+}
+
+impl Default for Thruster {
+    fn default() -> Self {
+        Self::new(0.0)
+    }
+}
+
+impl Thruster {
+    /// Thruster pushing forward, i.e. along -Z of its node
+    pub fn new(max_force: f32) -> Self {
+        Self {
+            max_force,
+            direction: Vec3::NEG_Z,
+            throttle: 0.0,
+            boost: 1.0,
+        }
+    }
+
+    pub fn throttle(&self) -> f32 {
+        self.throttle
+    }
+
+    /// Length of the engine plume in the thruster's local space, zero while it's hidden
+    pub fn plume_length(&self) -> f32 {
+        if self.throttle < MIN_PLUME_THROTTLE {
+            return 0.0;
+        }
+        PLUME_LENGTH * self.throttle * self.boost
+    }
+}
+
+/// Limited thrust boost of the ship, burned on `FlightControl::boost` and recharging otherwise.
+#[derive(Component, Clone, Reflect)]
+#[reflect(Component)]
+pub struct Afterburner {
+    /// Thrust multiplier while burning
+    pub thrust_scale: f32,
+    /// Seconds of burn when full
+    pub capacity: f32,
+    /// Seconds of burn regained per second
+    pub recharge_rate: f32,
+    /// Seconds of burn left
+    fuel: f32,
+    burning: bool,
+    /// Emptied afterburner doesn't burn until it's recharged to `AFTERBURNER_RELIGHT`
+    depleted: bool,
+}
+
+impl Default for Afterburner {
+    fn default() -> Self {
+        Self::new(2.5, 3.0, 0.5)
+    }
+}
+
+impl Afterburner {
+    pub fn new(thrust_scale: f32, capacity: f32, recharge_rate: f32) -> Self {
+        Self {
+            thrust_scale,
+            capacity,
+            recharge_rate,
+            fuel: capacity,
+            burning: false,
+            depleted: false,
+        }
+    }
+
+    pub fn burning(&self) -> bool {
+        self.burning
+    }
+
+    /// From 0 for an empty afterburner to 1 for a full one
+    pub fn level(&self) -> f32 {
+        if self.capacity > 0.0 {
+            self.fuel / self.capacity
+        } else {
+            0.0
+        }
+    }
+
+    /// Multiplier of the linear engine thrust, turning isn't boosted
+    fn scale(&self) -> f32 {
+        if self.burning {
+            self.thrust_scale
+        } else {
+            1.0
+        }
+    }
+
+    /// Burns for `seconds` while it's `wanted` and there is fuel left, recharges otherwise
+    fn update(&mut self, wanted: bool, seconds: f32) {
+        self.burning = wanted && !self.depleted && self.fuel > 0.0;
+        if self.burning {
+            self.fuel = (self.fuel - seconds).max(0.0);
+            self.depleted = self.fuel == 0.0;
+        } else {
+            self.fuel = (self.fuel + self.recharge_rate * seconds).min(self.capacity);
+            if self.fuel >= self.capacity * AFTERBURNER_RELIGHT {
+                self.depleted = false;
+            }
+        }
+    }
+}
+
+/// Thrusters of the ship, collected from its descendants.
+/// Ships without thrusters get the commanded force as is, once they had some but lost all
+/// of them they can't move anymore.
+#[derive(Component, Default)]
+pub struct Thrusters(Vec<Entity>);
+
+/// Glowing exhaust behind a `Thruster`, its child, stretched with the throttle.
+#[derive(Component)]
+struct Plume;
+
+#[derive(Resource)]
+struct PlumeAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+/// Force and torque in world space requested from the ship's thrusters.
+/// The closest achievable thrust is applied as `ExternalForce`.
+#[derive(Component, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct FlightControl {
+    /// In newtons
+    pub force: Vec3,
+    /// In newton meters
+    pub torque: Vec3,
+    /// Burns the `Afterburner`, if the ship has one
+    pub boost: bool,
+}
+
+/// Thrusters around a hull of `half_width` and `half_length` in meters for models without thruster
+/// nodes, with their offsets from the center: two main engines at the back sharing `max_thrust`,
+/// and reaction control thrusters at the nose, the tail and the sides to turn, strafe and brake.
+pub fn hull_thrusters(half_width: f32, half_length: f32, max_thrust: f32) -> Vec<(Vec3, Thruster)> {
+    let (x, z) = (half_width, half_length);
+    let rcs_force = max_thrust * RCS_THRUST_SHARE;
+    let thruster = |direction: Vec3, max_force: f32| Thruster {
+        direction,
+        ..Thruster::new(max_force)
+    };
+    let mut thrusters = vec![
+        (
+            Vec3::new(x / 2.0, 0.0, z),
+            thruster(Vec3::NEG_Z, max_thrust / 2.0),
+        ),
+        (
+            Vec3::new(-x / 2.0, 0.0, z),
+            thruster(Vec3::NEG_Z, max_thrust / 2.0),
+        ),
+        (Vec3::new(x / 2.0, 0.0, -z), thruster(Vec3::Z, rcs_force)),
+        (Vec3::new(-x / 2.0, 0.0, -z), thruster(Vec3::Z, rcs_force)),
+    ];
+    // Pairs at the nose and the tail pitch and yaw, the ones on the sides roll
+    for offset in [Vec3::new(0.0, 0.0, -z), Vec3::new(0.0, 0.0, z)] {
+        for direction in [Vec3::X, Vec3::NEG_X, Vec3::Y, Vec3::NEG_Y] {
+            thrusters.push((offset, thruster(direction, rcs_force)));
+        }
+    }
+    for offset in [Vec3::new(x, 0.0, 0.0), Vec3::new(-x, 0.0, 0.0)] {
+        for direction in [Vec3::Y, Vec3::NEG_Y] {
+            thrusters.push((offset, thruster(direction, rcs_force)));
+        }
+    }
+    thrusters
+}
+
+/// Throttles from 0 to 1 for `thrusters`, given as their (force, torque) at full throttle, so
+/// together they produce `force` and `torque` as close as possible.
+/// Solved as bounded least squares by projected coordinate descent, with torque divided by
+/// `lever` to weigh it against force.
+fn allocate_thrust(thrusters: &[(Vec3, Vec3)], force: Vec3, torque: Vec3, lever: f32) -> Vec<f32> {
+    let scale = lever.max(f32::EPSILON).recip();
+    let mut throttles = vec![0.0; thrusters.len()];
+    let mut residual_force = force;
+    let mut residual_torque = torque * scale;
+    for _ in 0..ALLOCATION_SWEEPS {
+        for (throttle, &(thruster_force, thruster_torque)) in throttles.iter_mut().zip(thrusters) {
+            let thruster_torque = thruster_torque * scale;
+            let norm = thruster_force.length_squared() + thruster_torque.length_squared();
+            if norm <= f32::EPSILON {
+                continue;
+            }
+            let step =
+                (thruster_force.dot(residual_force) + thruster_torque.dot(residual_torque)) / norm;
+            let updated = (*throttle + step).clamp(0.0, 1.0);
+            residual_force -= thruster_force * (updated - *throttle);
+            residual_torque -= thruster_torque * (updated - *throttle);
+            *throttle = updated;
+        }
+    }
+    throttles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{allocate_thrust, hull_thrusters, Afterburner, AFTERBURNER_RELIGHT};
+    use bevy::prelude::Vec3;
+
+    #[test]
+    fn test_allocate_thrust() {
+        // two main engines on the sides, pushing forward
+        let left = (Vec3::NEG_Z, Vec3::X.cross(Vec3::NEG_Z));
+        let right = (Vec3::NEG_Z, Vec3::NEG_X.cross(Vec3::NEG_Z));
+        let throttles = allocate_thrust(&[left, right], Vec3::NEG_Z, Vec3::ZERO, 1.0);
+        assert!((throttles[0] - 0.5).abs() < 1e-3);
+        assert!((throttles[1] - 0.5).abs() < 1e-3);
+
+        // thrusters can't pull, so there is nothing to brake with
+        let throttles = allocate_thrust(&[left, right], Vec3::Z, Vec3::ZERO, 1.0);
+        assert_eq!(throttles, vec![0.0, 0.0]);
+
+        // a single engine left trades thrust for not spinning the ship
+        let throttles = allocate_thrust(&[left], Vec3::NEG_Z, Vec3::ZERO, 1.0);
+        assert!((throttles[0] - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_hull_thrusters() {
+        let thrusters = hull_thrusters(2.0, 4.0, 1000.0);
+        let layout: Vec<_> = thrusters
+            .iter()
+            .map(|(offset, thruster)| {
+                let force = thruster.direction * thruster.max_force;
+                (force, offset.cross(force))
+            })
+            .collect();
+        let main_thrust: f32 = layout.iter().map(|(force, _)| -force.z.min(0.0)).sum();
+        assert!((main_thrust - 1000.0).abs() < 1e-3);
+
+        // the ship can be pushed and turned every way
+        for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+            for direction in [axis, -axis] {
+                assert!(layout.iter().any(|(force, _)| force.dot(direction) > 0.0));
+                assert!(layout.iter().any(|(_, torque)| torque.dot(direction) > 0.0));
+            }
+        }
+
+        // turning in place doesn't push the ship anywhere
+        let throttles = allocate_thrust(&layout, Vec3::ZERO, Vec3::Y * 1000.0, 4.0);
+        let force: Vec3 = layout
+            .iter()
+            .zip(throttles)
+            .map(|((force, _), throttle)| *force * throttle)
+            .sum();
+        assert!(force.length() < 1.0);
+    }
+
+    #[test]
+    fn test_afterburner() {
+        let mut afterburner = Afterburner::new(2.0, 2.0, 1.0);
+        afterburner.update(true, 1.5);
+        assert!(afterburner.burning());
+        assert_eq!(afterburner.scale(), 2.0);
+        assert_eq!(afterburner.level(), 0.25);
+        // burns out
+        afterburner.update(true, 1.0);
+        assert_eq!(afterburner.level(), 0.0);
+        afterburner.update(true, 0.1);
+        assert!(!afterburner.burning());
+        assert_eq!(afterburner.scale(), 1.0);
+        // and relights only once recharged enough, even if it's held all the time
+        afterburner.update(true, 2.0 * AFTERBURNER_RELIGHT - 0.15);
+        assert!(!afterburner.burning());
+        afterburner.update(true, 0.1);
+        assert!(!afterburner.burning());
+        afterburner.update(true, 0.1);
+        assert!(afterburner.burning());
+        // recharges to the capacity
+        afterburner.update(false, 10.0);
+        assert_eq!(afterburner.level(), 1.0);
+    }
+}
+
+fn setup_plume_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(PlumeAssets {
+        // Unit sphere, stretched to the plume size by `update_plumes`
+        mesh: meshes.add(Mesh::from(shape::UVSphere {
+            radius: 1.0,
+            sectors: 16,
+            stacks: 8,
+        })),
+        // HDR color for the bloom, unlit materials would ignore the emissive one
+        material: materials.add(StandardMaterial {
+            base_color: Color::BLACK,
+            emissive: Color::rgb_linear(2.0, 4.0, 12.0),
+            ..default()
+        }),
+    });
+}
+
+/// Thrusters get a small collider, hit points of their own and a plume, and join the closest
+/// ancestor with `Thrusters`
+fn setup_thrusters(
+    mut commands: Commands,
+    plume: Res<PlumeAssets>,
+    thrusters: Query<(Entity, Option<&HitPoints>, Option<&Collider>), Added<Thruster>>,
+    parent_query: Query<&Parent>,
+    mut ships: Query<&mut Thrusters>,
+) {
+    for (entity, hp, collider) in thrusters.iter() {
+        // The mesh is nested, so it isn't fitted into the thruster collider with its own mesh
+        commands.entity(entity).with_children(|parent| {
+            parent
+                .spawn(SpatialBundle::INVISIBLE_IDENTITY)
+                .insert(Plume)
+                .insert(Name::new("Plume"))
+                .with_children(|parent| {
+                    parent
+                        .spawn(PbrBundle {
+                            mesh: plume.mesh.clone(),
+                            material: plume.material.clone(),
+                            ..default()
+                        })
+                        .insert(NotShadowCaster)
+                        .insert(NotShadowReceiver);
+                });
+        });
+        if hp.is_none() {
+            commands
+                .entity(entity)
+                .insert(HitPoints::new(THRUSTER_HIT_POINTS));
+        }
+        if collider.is_none() {
+            commands
+                .entity(entity)
+                .insert(FitPrimitive {
+                    mesh_source: entity,
+                    shape: PartShape::Ball,
+                })
+                .insert(MassOverride::Density(0.0));
+        }
+        if let Some(mut ship) = parent_query
+            .iter_ancestors(entity)
+            .find_map(|e| ships.get_mut(e).ok())
+        {
+            ship.0.push(entity);
+        }
+    }
+}
+
+pub fn burn_afterburners(time: Res<Time>, mut ships: Query<(&FlightControl, &mut Afterburner)>) {
+    for (control, mut afterburner) in ships.iter_mut() {
+        afterburner.update(control.boost, time.delta_seconds());
+    }
+}
+
+pub fn flight_control(
+    mut ships: Query<(
+        &FlightControl,
+        &Thrusters,
+        &GlobalTransform,
+        &mut ExternalForce,
+        Option<&Afterburner>,
+    )>,
+    mut thrusters: Query<(&mut Thruster, &GlobalTransform)>,
+) {
+    for (control, ship_thrusters, transform, mut external_force, afterburner) in ships.iter_mut() {
+        // Afterburner multiplies the linear thrust the engines give, so the allocation is the
+        // same, while the ship keeps turning as fast as without it
+        let boost = afterburner.map_or(1.0, Afterburner::scale);
+        if ship_thrusters.0.is_empty() {
+            external_force.force = control.force * boost;
+            external_force.torque = control.torque;
+            continue;
+        }
+
+        let center = transform.translation();
+        let mut layout = Vec::with_capacity(ship_thrusters.0.len());
+        let mut lever = 0.0;
+        for &entity in ship_thrusters.0.iter() {
+            // destroyed thrusters are gone
+            let Ok((thruster, thruster_transform)) = thrusters.get(entity) else {
+                continue;
+            };
+            let direction = thruster_transform
+                .affine()
+                .transform_vector3(thruster.direction)
+                .normalize_or_zero();
+            let offset = thruster_transform.translation() - center;
+            let force = direction * thruster.max_force;
+            layout.push((entity, force, offset.cross(force)));
+            lever += offset.length();
+        }
+        lever /= layout.len().max(1) as f32;
+
+        let full_thrust: Vec<_> = layout.iter().map(|&(_, f, t)| (f, t)).collect();
+        let throttles = allocate_thrust(&full_thrust, control.force, control.torque, lever);
+
+        external_force.force = Vec3::ZERO;
+        external_force.torque = Vec3::ZERO;
+        for ((entity, force, torque), throttle) in layout.into_iter().zip(throttles) {
+            external_force.force += force * throttle * boost;
+            external_force.torque += torque * throttle;
+            if let Ok((mut thruster, _)) = thrusters.get_mut(entity) {
+                thruster.throttle = throttle;
+                thruster.boost = boost;
+            }
+        }
+    }
+}
+
+/// Plumes point against the thrust and grow with the throttle, and longer with the afterburner
+fn update_plumes(
+    thrusters: Query<(&Thruster, &Children)>,
+    mut plumes: Query<(&mut Transform, &mut Visibility), With<Plume>>,
+) {
+    for (thruster, children) in thrusters.iter() {
+        let mut iter = plumes.iter_many_mut(children.iter());
+        while let Some((mut transform, mut visibility)) = iter.fetch_next() {
+            let exhaust = -thruster.direction.normalize_or_zero();
+            let length = thruster.plume_length();
+            let visible = length > 0.0 && exhaust != Vec3::ZERO;
+            if visibility.is_visible != visible {
+                visibility.is_visible = visible;
+            }
+            if !visible {
+                continue;
+            }
+            *transform = Transform {
+                translation: exhaust * length * 0.5,
+                rotation: Quat::from_rotation_arc(Vec3::Y, exhaust),
+                scale: Vec3::new(PLUME_RADIUS, length * 0.5, PLUME_RADIUS),
+            };
+        }
+    }
+}
+
+/// Ship propulsion by individual thrusters, that are allocated to follow the `FlightControl`.
+pub struct ThrusterPlugin;
+impl Plugin for ThrusterPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Thruster>()
+            .register_type::<FlightControl>()
+            .register_type::<Afterburner>()
+            .add_startup_system(setup_plume_assets)
+            .add_system(setup_thrusters)
+            .add_system(burn_afterburners.before(flight_control))
+            .add_system(flight_control.after(setup_thrusters))
+            .add_system(update_plumes.after(flight_control))
+            // `{"thruster": 50000}` makes the node a thruster with the given max force in newtons
+            .register_gltf_property("thruster", |commands, value| {
+                if let Some(max_force) = value.as_f64() {
+                    commands.insert(Thruster::new(max_force as f32));
+                }
+            });
+    }
+}
@@ -0,0 +1,183 @@
+use bevy::prelude::*;
+use bevy_hanabi::*;
+
+use crate::effect_culling::NoEffectCulling;
+
+/// Particle trail left behind the projectile to make shots readable at distance.
+/// Emitters are pooled, so projectiles fired above the pool size fly without a trail.
+#[derive(Component, Copy, Clone, PartialEq, Eq, Default, Reflect)]
+#[reflect(Component)]
+pub enum Trail {
+    /// Dense smoke behind rockets
+    Smoke,
+    /// Faint glow behind bullets and shells
+    #[default]
+    Tracer,
+}
+
+impl Trail {
+    /// Maximum number of simultaneously active emitters of this kind
+    fn pool_size(self) -> usize {
+        match self {
+            Trail::Smoke => 32,
+            Trail::Tracer => 128,
+        }
+    }
+}
+
+/// Pooled particle emitter that follows the `projectile` while it exists.
+#[derive(Component)]
+struct TrailEmitter {
+    kind: Trail,
+    projectile: Option<Entity>,
+}
+
+#[derive(Resource)]
+struct TrailEffects {
+    smoke: Handle<EffectAsset>,
+    tracer: Handle<EffectAsset>,
+}
+
+impl TrailEffects {
+    fn get(&self, kind: Trail) -> Handle<EffectAsset> {
+        match kind {
+            Trail::Smoke => self.smoke.clone(),
+            Trail::Tracer => self.tracer.clone(),
+        }
+    }
+}
+
+fn setup_trails(mut commands: Commands, mut effects: ResMut<Assets<EffectAsset>>) {
+    let mut color_gradient = Gradient::new();
+    color_gradient.add_key(0.0, Vec4::new(1.0, 0.8, 0.5, 0.8));
+    color_gradient.add_key(0.1, Vec4::new(0.6, 0.6, 0.6, 0.5));
+    color_gradient.add_key(1.0, Vec4::new(0.4, 0.4, 0.4, 0.0));
+
+    let mut size_gradient = Gradient::new();
+    size_gradient.add_key(0.0, Vec2::splat(0.2));
+    size_gradient.add_key(1.0, Vec2::splat(1.0));
+
+    let smoke = effects.add(
+        EffectAsset {
+            capacity: 2048,
+            spawner: Spawner::rate(200.0.into()),
+            ..default()
+        }
+        .init(PositionSphereModifier {
+            radius: 0.1,
+            speed: 0.3.into(),
+            dimension: ShapeDimension::Volume,
+            ..default()
+        })
+        .init(ParticleLifetimeModifier { lifetime: 2.0 })
+        .render(BillboardModifier)
+        .render(SizeOverLifetimeModifier {
+            gradient: size_gradient,
+        })
+        .render(ColorOverLifetimeModifier {
+            gradient: color_gradient,
+        }),
+    );
+
+    let mut color_gradient = Gradient::new();
+    color_gradient.add_key(0.0, Vec4::new(1.0, 0.9, 0.6, 0.6));
+    color_gradient.add_key(1.0, Vec4::new(1.0, 0.5, 0.2, 0.0));
+
+    let tracer = effects.add(
+        EffectAsset {
+            capacity: 512,
+            spawner: Spawner::rate(120.0.into()),
+            ..default()
+        }
+        .init(PositionSphereModifier {
+            radius: 0.05,
+            speed: 0.0.into(),
+            dimension: ShapeDimension::Volume,
+            ..default()
+        })
+        .init(ParticleLifetimeModifier { lifetime: 0.3 })
+        .render(BillboardModifier)
+        .render(SizeOverLifetimeModifier {
+            gradient: Gradient::constant(Vec2::splat(0.15)),
+        })
+        .render(ColorOverLifetimeModifier {
+            gradient: color_gradient,
+        }),
+    );
+
+    commands.insert_resource(TrailEffects { smoke, tracer });
+}
+
+/// Assigns a free emitter from the pool to every new projectile with a trail
+fn attach_trails(
+    mut commands: Commands,
+    effects: Res<TrailEffects>,
+    projectiles: Query<(Entity, &Trail, &Transform), Added<Trail>>,
+    mut emitters: Query<(&mut TrailEmitter, &mut ParticleEffect, &mut Transform), Without<Trail>>,
+) {
+    for (projectile, &kind, transform) in projectiles.iter() {
+        let free_emitter = emitters
+            .iter_mut()
+            .find(|(emitter, _, _)| emitter.kind == kind && emitter.projectile.is_none());
+        if let Some((mut emitter, mut effect, mut emitter_transform)) = free_emitter {
+            emitter.projectile = Some(projectile);
+            emitter_transform.translation = transform.translation;
+            if let Some(spawner) = effect.maybe_spawner() {
+                spawner.set_active(true);
+            }
+            continue;
+        }
+
+        let pool_size = emitters
+            .iter()
+            .filter(|(emitter, _, _)| emitter.kind == kind)
+            .count();
+        if pool_size < kind.pool_size() {
+            commands
+                .spawn(ParticleEffectBundle {
+                    transform: Transform::from_translation(transform.translation),
+                    ..ParticleEffectBundle::new(effects.get(kind))
+                })
+                .insert(TrailEmitter {
+                    kind,
+                    projectile: Some(projectile),
+                })
+                // Trails stretch along the whole flight, so the emitter alone says little
+                .insert(NoEffectCulling)
+                .insert(Name::new("Trail Emitter"));
+        }
+    }
+}
+
+/// Moves emitters with their projectiles and returns them to the pool once projectiles are gone.
+/// Emitters are not parented to projectiles, so already emitted particles fade out on their own.
+fn follow_projectiles(
+    projectiles: Query<&Transform, With<Trail>>,
+    mut emitters: Query<(&mut TrailEmitter, &mut ParticleEffect, &mut Transform), Without<Trail>>,
+) {
+    for (mut emitter, mut effect, mut transform) in emitters.iter_mut() {
+        let Some(projectile) = emitter.projectile else {
+            continue;
+        };
+
+        if let Ok(projectile_transform) = projectiles.get(projectile) {
+            transform.translation = projectile_transform.translation;
+        } else {
+            emitter.projectile = None;
+            if let Some(spawner) = effect.maybe_spawner() {
+                spawner.set_active(false);
+            }
+        }
+    }
+}
+
+/// Particle trails of projectiles, drawn by pooled emitters.
+pub struct TrailPlugin;
+impl Plugin for TrailPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_trails)
+            .register_type::<Trail>()
+            .add_system(attach_trails)
+            .add_system(follow_projectiles.after(attach_trails));
+    }
+}
@@ -0,0 +1,618 @@
+//! Scenario triggers, so missions are authored in data: once a condition is met, like the player
+//! entering an area, a timer running out or a named entity being destroyed, the trigger fires its
+//! actions, like spawning a wave, showing a message, changing the objective or ending the mission.
+//! The objective and the outcome are shown on the HUD.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::Velocity;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    drone::Drone,
+    escort::{MissionEndedEvent, MissionOutcome},
+    hud::{
+        HighlightWidgetEvent, HudAnchor, HudWidget, Indicator, PanelBackground, RadioMessage,
+        RadioMessageEvent,
+    },
+    jump_gate::{JumpGatePlugin, OpenJumpGateEvent},
+    localization::Localization,
+    objective::ZoneShape,
+    player::{Player, PlayerAction, PlayerActionEvent},
+    projectile::{DamageEvent, DamageSystems},
+    scenario::Scenario,
+    settings::Settings,
+};
+
+/// The player is docked once slower than this, in m/s
+const DOCKING_SPEED: f32 = 2.0;
+
+/// Scripted reaction to the battle, a part of the `Scenario`.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct TriggerConfig {
+    pub condition: Condition,
+    pub actions: Vec<Action>,
+    /// Fires every time the condition is met anew, only once by default
+    #[serde(default)]
+    pub repeat: bool,
+    /// Referred to by `after` of other triggers
+    #[serde(default)]
+    pub name: Option<String>,
+    /// The trigger is armed once the one with this name fires, so missions and tutorials go
+    /// step by step. Timers count from that moment.
+    #[serde(default)]
+    pub after: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    /// The player enters the volume at the `position`, in meters
+    AreaEnter {
+        position: [f32; 3],
+        shape: ZoneShape,
+    },
+    /// Seconds since the mission start or the trigger being armed, or between firings of repeated
+    /// triggers
+    Timer { seconds: f32 },
+    /// Entity with the `name` is destroyed
+    Destroyed { name: String },
+    /// The player starts doing something with the ship, like locking a target or firing
+    PlayerAction { action: PlayerAction },
+    /// The player comes to a stop within the `distance` of the entity with the `name`, in meters
+    Docked { name: String, distance: f32 },
+}
+
+/// Texts are localization keys, or shown as they are without a translation.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Drones jump in at the named spawn point of the `Scenario`, for its team
+    SpawnWave {
+        spawn_point: String,
+        drones: Vec<Drone>,
+    },
+    /// Notification shown for a few seconds
+    Message {
+        text: String,
+    },
+    /// Radio line queued at the top of the HUD, or a banner without a speaker
+    Radio(RadioMessage),
+    /// Objective shown on the HUD until the next one
+    Objective {
+        text: String,
+    },
+    /// Highlights the HUD widget with the name, like "Target Panel", clears it without one
+    Highlight {
+        widget: Option<String>,
+    },
+    EndMission {
+        success: bool,
+    },
+}
+
+/// Emitted by triggers firing, or to run an `Action` right away.
+pub struct TriggerActionEvent(pub Action);
+
+/// Emitted for scripted messages, with the text to show.
+pub struct ScenarioMessageEvent(pub String);
+
+/// What the conditions are checked against in the current frame.
+#[derive(Default)]
+struct Frame<'a> {
+    /// Seconds since the mission start
+    elapsed: f32,
+    player: Option<Vec3>,
+    player_speed: f32,
+    actions: Vec<PlayerAction>,
+    /// Names of entities destroyed in the frame
+    destroyed: Vec<&'a str>,
+    /// Positions of entities to dock at
+    docks: Vec<(&'a str, Vec3)>,
+}
+
+struct Trigger {
+    config: TriggerConfig,
+    armed: bool,
+    fired: bool,
+    /// Player was in the area or docked in the previous frame, so staying there doesn't fire it
+    /// again
+    inside: bool,
+    /// Mission time to fire the timer at
+    time: f32,
+}
+
+impl Trigger {
+    fn new(config: TriggerConfig) -> Self {
+        let armed = config.after.is_none();
+        let mut trigger = Self {
+            config,
+            armed: false,
+            fired: false,
+            inside: false,
+            time: 0.0,
+        };
+        if armed {
+            trigger.arm(0.0);
+        }
+        trigger
+    }
+
+    /// Starts checking the condition at the mission time `elapsed`
+    fn arm(&mut self, elapsed: f32) {
+        self.armed = true;
+        if let Condition::Timer { seconds } = self.config.condition {
+            self.time = elapsed + seconds;
+        }
+    }
+
+    /// Checks the condition, returns true if the trigger fires
+    fn update(&mut self, frame: &Frame) -> bool {
+        let met = match &self.config.condition {
+            Condition::AreaEnter { position, shape } => {
+                let was_inside = self.inside;
+                self.inside = frame.player.map_or(false, |player| {
+                    shape.contains(player - Vec3::from(*position))
+                });
+                self.inside && !was_inside
+            }
+            Condition::Timer { seconds } => {
+                let met = frame.elapsed >= self.time;
+                if met {
+                    self.time += seconds.max(f32::EPSILON);
+                }
+                met
+            }
+            Condition::Destroyed { name } => frame.destroyed.contains(&name.as_str()),
+            Condition::PlayerAction { action } => frame.actions.contains(action),
+            Condition::Docked { name, distance } => {
+                let was_docked = self.inside;
+                let dock = frame.docks.iter().find(|(dock, _)| *dock == name.as_str());
+                self.inside = match (frame.player, dock) {
+                    (Some(player), Some((_, dock))) => {
+                        frame.player_speed < DOCKING_SPEED && player.distance(*dock) <= *distance
+                    }
+                    _ => false,
+                };
+                self.inside && !was_docked
+            }
+        };
+        if !met || (self.fired && !self.config.repeat) {
+            return false;
+        }
+        self.fired = true;
+        true
+    }
+}
+
+/// Triggers of the `Scenario` and the mission state they drive.
+#[derive(Resource, Default)]
+struct Mission {
+    triggers: Vec<Trigger>,
+    elapsed: f32,
+    objective: Option<String>,
+    outcome: Option<MissionOutcome>,
+}
+
+impl Mission {
+    /// Arms triggers following the fired ones and checks the armed ones, returns the actions of
+    /// the fired triggers
+    fn update(&mut self, frame: &Frame) -> Vec<Action> {
+        // Triggers fired in this frame arm the next ones only in the next frame
+        let fired: Vec<_> = self
+            .triggers
+            .iter()
+            .filter(|trigger| trigger.fired)
+            .filter_map(|trigger| trigger.config.name.clone())
+            .collect();
+
+        let mut actions = Vec::new();
+        for trigger in self.triggers.iter_mut() {
+            if !trigger.armed {
+                let after = trigger.config.after.as_ref();
+                if !after.map_or(false, |after| fired.contains(after)) {
+                    continue;
+                }
+                trigger.arm(frame.elapsed);
+            }
+            if trigger.update(frame) {
+                actions.extend(trigger.config.actions.iter().cloned());
+            }
+        }
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Condition, Frame, Mission, Trigger, TriggerConfig};
+    use crate::{objective::ZoneShape, player::PlayerAction};
+    use bevy::prelude::Vec3;
+
+    fn config(condition: Condition, repeat: bool) -> TriggerConfig {
+        TriggerConfig {
+            condition,
+            actions: vec![],
+            repeat,
+            name: None,
+            after: None,
+        }
+    }
+
+    fn trigger(condition: Condition, repeat: bool) -> Trigger {
+        Trigger::new(config(condition, repeat))
+    }
+
+    #[test]
+    fn test_area_enter() {
+        let area = Condition::AreaEnter {
+            position: [0.0, 0.0, -100.0],
+            shape: ZoneShape::Sphere { radius: 50.0 },
+        };
+        let mut trigger = trigger(area, true);
+        let outside = Frame {
+            player: Some(Vec3::ZERO),
+            ..Default::default()
+        };
+        let inside = Frame {
+            player: Some(Vec3::NEG_Z * 80.0),
+            ..Default::default()
+        };
+        assert!(!trigger.update(&outside));
+        assert!(trigger.update(&inside));
+        // staying inside isn't entering
+        assert!(!trigger.update(&inside));
+        assert!(!trigger.update(&outside));
+        assert!(trigger.update(&inside));
+    }
+
+    #[test]
+    fn test_timer() {
+        let frame = |elapsed| Frame {
+            elapsed,
+            ..Default::default()
+        };
+        let mut once = trigger(Condition::Timer { seconds: 10.0 }, false);
+        assert!(!once.update(&frame(5.0)));
+        assert!(once.update(&frame(10.0)));
+        assert!(!once.update(&frame(25.0)));
+
+        let mut repeated = trigger(Condition::Timer { seconds: 10.0 }, true);
+        assert!(repeated.update(&frame(10.0)));
+        assert!(!repeated.update(&frame(15.0)));
+        assert!(repeated.update(&frame(20.0)));
+    }
+
+    #[test]
+    fn test_destroyed() {
+        let name = String::from("Artillery Platform");
+        let mut trigger = trigger(Condition::Destroyed { name }, false);
+        assert!(!trigger.update(&Frame::default()));
+        let frame = Frame {
+            destroyed: vec!["Alpha-1", "Artillery Platform"],
+            ..Default::default()
+        };
+        assert!(trigger.update(&frame));
+    }
+
+    #[test]
+    fn test_docked() {
+        let dock = Condition::Docked {
+            name: String::from("Artillery Platform"),
+            distance: 100.0,
+        };
+        let mut trigger = trigger(dock, false);
+        let frame = |position: Vec3, speed| Frame {
+            player: Some(position),
+            player_speed: speed,
+            docks: vec![("Artillery Platform", Vec3::ZERO)],
+            ..Default::default()
+        };
+        assert!(!trigger.update(&frame(Vec3::X * 200.0, 0.0)));
+        // passing by isn't docking
+        assert!(!trigger.update(&frame(Vec3::X * 50.0, 30.0)));
+        assert!(trigger.update(&frame(Vec3::X * 50.0, 0.5)));
+    }
+
+    #[test]
+    fn test_steps() {
+        let action = |text: &str| Action::Message {
+            text: text.to_owned(),
+        };
+        let lock = Condition::PlayerAction {
+            action: PlayerAction::LockTarget,
+        };
+        let mut first = config(lock, false);
+        first.name = Some(String::from("lock"));
+        first.actions = vec![action("locked")];
+        let mut second = config(Condition::Timer { seconds: 5.0 }, false);
+        second.after = first.name.clone();
+        second.actions = vec![action("done")];
+        let mut mission = Mission {
+            triggers: vec![Trigger::new(first), Trigger::new(second)],
+            ..Default::default()
+        };
+
+        let frame = |elapsed, actions| Frame {
+            elapsed,
+            actions,
+            ..Default::default()
+        };
+        // the timer counts from the previous step, not the mission start
+        assert!(mission.update(&frame(10.0, vec![])).is_empty());
+        assert_eq!(
+            mission.update(&frame(20.0, vec![PlayerAction::LockTarget])),
+            [action("locked")]
+        );
+        assert!(mission.update(&frame(21.0, vec![])).is_empty());
+        assert_eq!(mission.update(&frame(26.0, vec![])), [action("done")]);
+    }
+
+    #[test]
+    fn test_parse_trigger() {
+        let config: TriggerConfig = serde_json::from_str(
+            r#"{
+                "condition": {"destroyed": {"name": "Artillery Platform"}},
+                "actions": [
+                    {"message": {"text": "The platform is lost"}},
+                    {"radio": {
+                        "speaker": "Command", "text": "Fall back!", "voice": "voice/fall_back.ogg"
+                    }},
+                    {"spawn_wave": {"spawn_point": "North", "drones": ["praetor", "praetor"]}},
+                    {"end_mission": {"success": false}}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert!(!config.repeat);
+        assert_eq!(config.actions.len(), 4);
+    }
+}
+
+fn setup_mission(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    scenario: Option<Res<Scenario>>,
+) {
+    let Some(scenario) = scenario else {
+        return;
+    };
+    if scenario.triggers.is_empty() {
+        return;
+    }
+    commands.insert_resource(Mission {
+        triggers: scenario
+            .triggers
+            .iter()
+            .cloned()
+            .map(Trigger::new)
+            .collect(),
+        ..default()
+    });
+
+    // Shown once there is an objective or the mission is over
+    let background = Color::rgba(0.1, 0.1, 0.1, 0.6);
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            background_color: background.into(),
+            visibility: Visibility::INVISIBLE,
+            ..default()
+        })
+        .insert(HudWidget::essential(HudAnchor::TopLeft))
+        .insert(PanelBackground(background))
+        .insert(MissionPanel)
+        .insert(Name::new("Mission"))
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: assets.load("fonts/FiraMono-Medium.ttf"),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                ))
+                .insert(MissionText);
+        });
+}
+
+fn evaluate_triggers(
+    time: Res<Time>,
+    mission: Option<ResMut<Mission>>,
+    mut ev_damage: EventReader<DamageEvent>,
+    mut ev_player_action: EventReader<PlayerActionEvent>,
+    player: Query<(&GlobalTransform, Option<&Velocity>), With<Player>>,
+    names: Query<(&Name, &GlobalTransform)>,
+    mut ev_action: EventWriter<TriggerActionEvent>,
+) {
+    let Some(mut mission) = mission else {
+        return;
+    };
+    if mission.outcome.is_some() {
+        return;
+    }
+    mission.elapsed += time.delta_seconds();
+
+    let player = player.get_single().ok();
+    let docks: Vec<_> = mission
+        .triggers
+        .iter()
+        .filter_map(|trigger| match &trigger.config.condition {
+            Condition::Docked { name, .. } if trigger.armed => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    let frame = Frame {
+        elapsed: mission.elapsed,
+        player: player.map(|(transform, _)| transform.translation()),
+        player_speed: player
+            .and_then(|(_, velocity)| velocity)
+            .map_or(0.0, |velocity| velocity.linvel.length()),
+        actions: ev_player_action.iter().map(|ev| ev.0).collect(),
+        destroyed: ev_damage
+            .iter()
+            .filter(|ev| ev.destroyed)
+            .filter_map(|ev| names.get(ev.target).ok())
+            .map(|(name, _)| name.as_str())
+            .collect(),
+        // Named entities are only looked through while there is a dock to look for
+        docks: if docks.is_empty() {
+            Vec::new()
+        } else {
+            names
+                .iter()
+                .filter(|(name, _)| docks.contains(&name.as_str()))
+                .map(|(name, transform)| (name.as_str(), transform.translation()))
+                .collect()
+        },
+    };
+    let actions = mission.update(&frame);
+    ev_action.send_batch(actions.into_iter().map(TriggerActionEvent));
+}
+
+fn run_actions(
+    scenario: Option<Res<Scenario>>,
+    mission: Option<ResMut<Mission>>,
+    mut ev_action: EventReader<TriggerActionEvent>,
+    mut ev_open_gate: EventWriter<OpenJumpGateEvent>,
+    mut ev_mission_ended: EventWriter<MissionEndedEvent>,
+) {
+    let (Some(scenario), Some(mut mission)) = (scenario, mission) else {
+        return;
+    };
+    let mut rng = rand::thread_rng();
+
+    for TriggerActionEvent(action) in ev_action.iter() {
+        match action {
+            Action::SpawnWave {
+                spawn_point,
+                drones,
+            } => {
+                let mut points = scenario.spawn_points.iter();
+                let Some(point) = points.find(|point| &point.name == spawn_point) else {
+                    warn!("Spawn point {spawn_point} isn't in the scenario");
+                    continue;
+                };
+                let transform = point.transform();
+                for &drone in drones.iter() {
+                    let offset = Vec3::new(
+                        rng.gen_range(-1.0..1.0),
+                        rng.gen_range(-1.0..1.0),
+                        rng.gen_range(-1.0..1.0),
+                    ) * point.radius;
+                    ev_open_gate.send(OpenJumpGateEvent {
+                        drone,
+                        fraction: point.team,
+                        transform: transform.with_translation(transform.translation + offset),
+                    });
+                }
+            }
+            Action::Objective { text } => mission.objective = Some(text.clone()),
+            Action::EndMission { success } => {
+                if mission.outcome.is_some() {
+                    continue;
+                }
+                let outcome = if *success {
+                    MissionOutcome::Completed
+                } else {
+                    MissionOutcome::Failed
+                };
+                info!("Mission is over: {outcome:?}");
+                mission.outcome = Some(outcome);
+                ev_mission_ended.send(MissionEndedEvent(outcome));
+            }
+            Action::Message { .. } | Action::Radio(_) | Action::Highlight { .. } => {}
+        }
+    }
+}
+
+/// Passes actions shown on the HUD over to it
+fn show_actions(
+    mut ev_action: EventReader<TriggerActionEvent>,
+    mut ev_message: EventWriter<ScenarioMessageEvent>,
+    mut ev_radio: EventWriter<RadioMessageEvent>,
+    mut ev_highlight: EventWriter<HighlightWidgetEvent>,
+) {
+    for TriggerActionEvent(action) in ev_action.iter() {
+        match action {
+            Action::Message { text } => ev_message.send(ScenarioMessageEvent(text.clone())),
+            Action::Radio(message) => ev_radio.send(RadioMessageEvent(message.clone())),
+            Action::Highlight { widget } => ev_highlight.send(HighlightWidgetEvent(widget.clone())),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Component)]
+struct MissionPanel;
+
+#[derive(Component)]
+struct MissionText;
+
+fn update_mission_hud(
+    mission: Option<Res<Mission>>,
+    localization: Res<Localization>,
+    settings: Res<Settings>,
+    mut panel: Query<&mut Visibility, With<MissionPanel>>,
+    mut text: Query<&mut Text, With<MissionText>>,
+) {
+    let Some(mission) = mission else {
+        return;
+    };
+    let (Ok(mut visibility), Ok(mut text)) = (panel.get_single_mut(), text.get_single_mut()) else {
+        return;
+    };
+
+    let mut lines = Vec::new();
+    if let Some(objective) = &mission.objective {
+        let objective = localization.get(objective);
+        lines.push(localization.format("mission-objective", &[("objective", &objective)]));
+    }
+    if let Some(outcome) = mission.outcome {
+        lines.push(localization.get(outcome.key()).to_owned());
+        let indicator = if outcome.is_success() {
+            Indicator::Good
+        } else {
+            Indicator::Danger
+        };
+        let color = settings.accessibility.color(indicator);
+        if text.sections[0].style.color != color {
+            text.sections[0].style.color = color;
+        }
+    }
+    let visible = !lines.is_empty();
+    if visibility.is_visible != visible {
+        visibility.is_visible = visible;
+    }
+    // Mission time ticks every frame, so the text is only touched once it changes
+    let value = lines.join("\n");
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
+
+/// `TriggerConfig`s of the `Scenario`, with the mission objective and outcome on the HUD.
+pub struct TriggerPlugin;
+impl Plugin for TriggerPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<JumpGatePlugin>() {
+            app.add_plugin(JumpGatePlugin);
+        }
+        app.add_event::<TriggerActionEvent>()
+            .add_event::<ScenarioMessageEvent>()
+            .add_event::<RadioMessageEvent>()
+            .add_event::<HighlightWidgetEvent>()
+            .add_event::<PlayerActionEvent>()
+            .add_event::<MissionEndedEvent>()
+            .add_event::<DamageEvent>()
+            .add_startup_system(setup_mission)
+            .add_system(evaluate_triggers.after(DamageSystems))
+            .add_system(run_actions.after(evaluate_triggers))
+            .add_system(show_actions.after(evaluate_triggers))
+            .add_system(update_mission_hud.after(run_actions));
+    }
+}
@@ -1,14 +1,95 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
+use serde::Deserialize;
 
 use crate::{
-    aiming, collider_setup, gun, projectile::HitPoints, scene_setup::SetupRequired, weapon,
+    aiming, collider_setup,
+    components::{Fraction, HitPoints},
+    gun::{self, ShotFiredEvent},
+    model_animation::PlayAnimation,
+    prefab::{AiConfig, PrefabPlugin, ShipBundle, ShipPrefab, ShipRig},
+    projectile::Armor,
+    spring::{Spring, SpringPlugin},
+    veterancy::{Veterancy, VeterancyPlugin},
 };
 
+/// Seconds to deploy a stowed turret, and to stow it back
+const DEPLOY_TIME: f32 = 2.0;
+/// Seconds without a target in range before the turret stows itself
+const STOW_DELAY: f32 = 10.0;
+/// Part of the damage that stowed turrets ignore
+const STOWED_ARMOR: f32 = 0.5;
+/// Height of the turret body when stowed, relative to the deployed one
+const STOWED_HEIGHT: f32 = 0.4;
+/// The head has the hit points of the prefab
+const BODY_HIT_POINTS: u32 = 200;
+/// Recoil of the gun knocks the barrels up by this angle for every N·s of its impulse, in radians
+const RECOIL_KICK: f32 = 1e-4;
+
+/// Turret archetypes, built from the same model and laid the same way, but armed differently
+#[derive(Component, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Reflect)]
+#[reflect(Component)]
+#[serde(rename_all = "snake_case")]
+pub enum TurretKind {
+    /// Rapid-firing flak cannon, good against swarms of small drones
+    #[default]
+    Flak,
+    /// Slow but hard-hitting railgun, every shot has to be charged
+    Railgun,
+    /// Launches salvos of homing missiles
+    MissileBattery,
+}
+
+impl TurretKind {
+    pub const ALL: [TurretKind; 3] = [Self::Flak, Self::Railgun, Self::MissileBattery];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Flak => "Flak Turret",
+            Self::Railgun => "Railgun Turret",
+            Self::MissileBattery => "Missile Battery",
+        }
+    }
+
+    /// Prefab with the model and weapons of the kind
+    fn prefab(self) -> &'static str {
+        match self {
+            Self::Flak => "ships/flak_turret.ship.ron",
+            Self::Railgun => "ships/railgun_turret.ship.ron",
+            Self::MissileBattery => "ships/missile_battery.ship.ron",
+        }
+    }
+
+    /// Distance in meters the barrels recoil by and seconds they take to return
+    fn barrel_recoil(self) -> (f32, f32) {
+        match self {
+            Self::Flak => (0.8, 0.15),
+            Self::Railgun => (2.5, 1.0),
+            Self::MissileBattery => (0.5, 0.4),
+        }
+    }
+
+    /// RGB multiplier of the model materials, to tell turrets apart
+    fn tint(self) -> Option<Vec3> {
+        match self {
+            Self::Flak => None,
+            Self::Railgun => Some(Vec3::new(0.6, 0.8, 1.0)),
+            Self::MissileBattery => Some(Vec3::new(1.0, 0.6, 0.5)),
+        }
+    }
+}
+
 /// Emit this event to spawn a turret with specified parameters
 pub struct SpawnTurretEvent {
+    pub kind: TurretKind,
+    /// Overrides the prefab of the `kind`
+    pub prefab: Option<Handle<ShipPrefab>>,
+    /// Relative to the `parent`, if any
     pub transform: Transform,
+    /// Ship or platform the turret is mounted on, it moves along with it
+    pub parent: Option<Entity>,
     /// Rotation speed in rad/s
     pub rotation_speed: f32,
+    pub fraction: Fraction,
 }
 
 /// Links turret main entity with joints that will be used for turret orientation.
@@ -21,7 +102,8 @@ struct TurretJoints(Vec<Entity>);
 /// Due to strange magic inside scene setup from GLTF, the only axis that can be rotated without artifacts is Y.
 /// Which means that joint's parent's Y axis should oriented in the direction of the intended joint's rotation.
 /// In other words, Joint always rotates around parent's Y.
-#[derive(Component)]
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
 struct Joint {
     rotation_speed: f32,
 }
@@ -29,49 +111,193 @@ struct Joint {
 #[derive(Bundle)]
 struct TurretBundle {
     gun_layer: aiming::GunLayer,
+    fire_control: aiming::FireControl,
+    veterancy: Veterancy,
     joints: TurretJoints,
 }
 
 impl TurretBundle {
-    fn new(joints: Vec<Entity>) -> Self {
+    fn new(joints: Vec<Entity>, gun_layer: aiming::GunLayer) -> Self {
         Self {
-            gun_layer: aiming::GunLayer::default(),
+            gun_layer,
+            fire_control: aiming::FireControl::default(),
+            veterancy: Veterancy::default(),
             joints: TurretJoints(joints),
         }
     }
 }
 
+/// Turrets start stowed: lowered into the base, they can't fire, but shrug off a part of the damage.
+/// Once a target is in range they deploy over `DEPLOY_TIME`, being vulnerable and still not
+/// firing meanwhile, and stow again after `STOW_DELAY` without targets.
+/// Plays "Deploy" and "Stow" animations of the model, if it has any.
+#[derive(Component, Clone, Reflect)]
+#[reflect(Component)]
+pub struct Deployment {
+    /// From 0 when stowed to 1 when deployed
+    progress: f32,
+    deploying: bool,
+    /// Seconds since the last target in range
+    idle: f32,
+    /// Ordered to stay stowed regardless of targets
+    hold_stowed: bool,
+}
+
+impl Default for Deployment {
+    fn default() -> Self {
+        Self {
+            progress: 0.0,
+            deploying: false,
+            idle: STOW_DELAY,
+            hold_stowed: false,
+        }
+    }
+}
+
+impl Deployment {
+    pub fn is_deployed(&self) -> bool {
+        self.progress >= 1.0
+    }
+
+    pub fn is_stowed(&self) -> bool {
+        self.progress <= 0.0
+    }
+
+    /// Orders the turret to stow and stay stowed until `deploy` is called
+    pub fn stow(&mut self) {
+        self.hold_stowed = true;
+    }
+
+    /// Orders the turret to deploy, it stows again once there are no targets for a while
+    pub fn deploy(&mut self) {
+        self.hold_stowed = false;
+        self.idle = 0.0;
+    }
+
+    fn armor(&self) -> f32 {
+        if self.is_stowed() {
+            STOWED_ARMOR
+        } else {
+            0.0
+        }
+    }
+
+    /// Moves towards the deployed state while a target was in range recently.
+    /// Returns whether the direction has changed, i.e. deploying or stowing has just started.
+    fn update(&mut self, target_in_range: bool, seconds: f32) -> bool {
+        self.idle = if target_in_range {
+            0.0
+        } else {
+            self.idle + seconds
+        };
+        let deploying = !self.hold_stowed && self.idle < STOW_DELAY;
+        let step = if deploying { seconds } else { -seconds } / DEPLOY_TIME;
+        self.progress = (self.progress + step).clamp(0.0, 1.0);
+        std::mem::replace(&mut self.deploying, deploying) != deploying
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Deployment, DEPLOY_TIME, STOW_DELAY};
+
+    #[test]
+    fn test_deployment() {
+        let mut deployment = Deployment::default();
+        assert!(deployment.is_stowed());
+        assert!(!deployment.update(false, 1.0));
+        assert!(deployment.is_stowed());
+
+        // deploys once a target is in range, even if it's gone right after
+        assert!(deployment.update(true, DEPLOY_TIME / 2.0));
+        assert!(!deployment.is_stowed() && !deployment.is_deployed());
+        assert!(!deployment.update(false, DEPLOY_TIME / 2.0));
+        assert!(deployment.is_deployed());
+
+        // stows after a while without targets
+        assert!(deployment.update(false, STOW_DELAY));
+        assert!(!deployment.is_deployed());
+        deployment.update(false, DEPLOY_TIME);
+        assert!(deployment.is_stowed());
+
+        // stays stowed when ordered to
+        deployment.stow();
+        deployment.update(true, DEPLOY_TIME);
+        assert!(deployment.is_stowed());
+        deployment.deploy();
+        deployment.update(false, DEPLOY_TIME);
+        assert!(deployment.is_deployed());
+    }
+}
+
+/// Turret body that is lowered into the base while stowed, `deployed` is its translation
+/// when deployed
+#[derive(Component)]
+struct Lift {
+    deployed: Vec3,
+}
+
 #[derive(Resource)]
-struct TurretScene(Handle<Scene>);
+struct TurretPrefabs(HashMap<TurretKind, Handle<ShipPrefab>>);
 
 fn load_turret_resources(mut commands: Commands, assets: Res<AssetServer>) {
-    commands.insert_resource(TurretScene(assets.load("models/turret.glb#Scene0")));
+    let prefabs = TurretKind::ALL
+        .into_iter()
+        .map(|kind| (kind, assets.load(kind.prefab())))
+        .collect();
+    commands.insert_resource(TurretPrefabs(prefabs));
 }
 
 #[derive(Component)]
 struct TurretBody;
 
+/// Cosmetic recoil of the muzzle on every shot, `parts` are moved back together and spring
+/// forward by their `Spring`
+#[derive(Component)]
+struct BarrelRecoil {
+    parts: Vec<Entity>,
+    distance: f32,
+}
+
+/// Mesh that is waiting for its material to be tinted with the color of the turret kind
+#[derive(Component)]
+struct Tint(TurretKind);
+
+// Weapons are mounted on the head by the prefab, while the turret rigs the joints and parts
 fn spawn_turret(
     mut commands: Commands,
-    turret_scene: Res<TurretScene>,
+    prefabs: Res<TurretPrefabs>,
     mut ev_spawn_turret: EventReader<SpawnTurretEvent>,
 ) {
     for ev in ev_spawn_turret.iter() {
+        let kind = ev.kind;
         let rotation_speed = ev.rotation_speed;
-        commands
-            .spawn(SceneBundle {
-                scene: turret_scene.0.clone(),
-                transform: ev.transform,
-                ..default()
-            })
-            .insert(SetupRequired::new(move |commands, entities| {
+        let fraction = ev.fraction;
+        let prefab = ev
+            .prefab
+            .clone()
+            .unwrap_or_else(|| prefabs.0[&kind].clone());
+        let turret = commands
+            .spawn(ShipBundle::new(prefab, ev.transform))
+            .insert(ShipRig::new(move |commands, entities, prefab| {
                 let mut collider_parts = vec![];
+                // Head rotates independently of the body, so it has it's own collider
+                let mut head_collider_parts = vec![];
                 let mut joints = vec![];
                 let mut barrels = vec![];
 
                 let mut head: Option<Entity> = None;
                 let mut body: Option<Entity> = None;
 
+                if kind.tint().is_some() {
+                    entities
+                        .iter()
+                        .filter(|e| e.contains::<Handle<StandardMaterial>>())
+                        .for_each(|e| {
+                            commands.entity(e.id()).insert(Tint(kind));
+                        });
+                }
+
                 entities
                     .iter()
                     // Skip entities with `Handle<Mesh>` as we should operate only with GLTF's Nodes
@@ -80,7 +306,6 @@ fn spawn_turret(
                     .filter_map(|e| e.get::<Name>().map(|name| (e.id(), name)))
                     .for_each(|(entity, name)| {
                         if name.starts_with("Muzzle") {
-                            commands.entity(entity).insert(gun::Barrel);
                             barrels.push(entity);
                         } else if name.starts_with("Body") {
                             commands.entity(entity).insert(Joint { rotation_speed });
@@ -90,33 +315,122 @@ fn spawn_turret(
                         } else if name.starts_with("Head") {
                             commands.entity(entity).insert(Joint { rotation_speed });
                             joints.push(entity);
+                            head_collider_parts
+                                .push((entity, collider_setup::PartShape::ConvexHull));
                             head = Some(entity);
                         }
                     });
 
+                // Barrel segments right behind the muzzle recoil together with it
+                let (distance, settle_time) = kind.barrel_recoil();
+                let nodes: Vec<_> = entities
+                    .iter()
+                    .filter(|e| !e.contains::<Handle<Mesh>>())
+                    .filter_map(|e| Some((e.id(), e.get::<Name>()?, e.get::<Transform>()?)))
+                    .filter(|(_, name, _)| name.starts_with("Muzzle") || name.starts_with("Barrel"))
+                    .collect();
+                for &muzzle in barrels.iter() {
+                    let Some((_, _, muzzle_transform)) =
+                        nodes.iter().find(|(e, _, _)| *e == muzzle)
+                    else {
+                        continue;
+                    };
+                    let parts: Vec<_> = nodes
+                        .iter()
+                        .filter(|(_, _, transform)| {
+                            let offset = transform.translation - muzzle_transform.translation;
+                            offset.truncate().length() < 0.1
+                        })
+                        .map(|&(entity, _, _)| entity)
+                        .collect();
+                    for &part in parts.iter() {
+                        commands.entity(part).insert(Spring::new(settle_time));
+                    }
+                    commands
+                        .entity(muzzle)
+                        .insert(BarrelRecoil { parts, distance });
+                }
+
                 if let Some(body) = body {
+                    if let Some(transform) = entities
+                        .iter()
+                        .find(|e| e.id() == body)
+                        .and_then(|e| e.get::<Transform>())
+                    {
+                        // turrets start stowed
+                        let mut stowed = *transform;
+                        stowed.translation.y *= STOWED_HEIGHT;
+                        commands.entity(body).insert(stowed).insert(Lift {
+                            deployed: transform.translation,
+                        });
+                    }
                     commands
                         .entity(body)
                         .insert(TurretBody)
-                        .insert(HitPoints::new(200))
+                        .insert(HitPoints::new(BODY_HIT_POINTS))
+                        .insert(Armor::new(STOWED_ARMOR))
                         .insert(collider_setup::ConvexHull::new(collider_parts))
                         // should set fraction twice - near collider and near GunLayer
-                        .insert(aiming::Fraction::Turrets);
+                        .insert(fraction);
                 };
 
                 if let Some(head) = head {
+                    let gun_layer = prefab
+                        .ai
+                        .as_ref()
+                        .map_or_else(aiming::GunLayer::default, AiConfig::gun_layer);
                     commands
                         .entity(head)
-                        .insert(TurretBundle::new(joints))
-                        .insert(weapon::FlakCannon::new(barrels, 5.0))
+                        .insert(TurretBundle::new(joints, gun_layer))
+                        .insert(kind)
+                        .insert(Deployment::default())
+                        .insert(HitPoints::new(prefab.hit_points))
+                        .insert(Armor::new(STOWED_ARMOR))
+                        .insert(collider_setup::CompoundCollider::new(head_collider_parts))
                         // should set fraction twice - near collider and near GunLayer
-                        .insert(aiming::Fraction::Turrets);
+                        .insert(fraction);
                 }
             }))
-            .insert(Name::new("Turret"));
+            .insert(Name::new(kind.name()))
+            .id();
+        if let Some(parent) = ev.parent {
+            commands.entity(parent).add_child(turret);
+        }
     }
 }
 
+/// Replaces materials of tinted meshes with tinted copies, shared between turrets of the same kind
+fn apply_tint(
+    mut commands: Commands,
+    mut meshes: Query<(Entity, &mut Handle<StandardMaterial>, &Tint)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut cache: Local<HashMap<(Handle<StandardMaterial>, TurretKind), Handle<StandardMaterial>>>,
+) {
+    for (entity, mut material, tint) in meshes.iter_mut() {
+        let Some(tint_rgb) = tint.0.tint() else {
+            continue;
+        };
+        let key = (material.clone(), tint.0);
+        let tinted = match cache.get(&key) {
+            Some(handle) => handle.clone(),
+            None => {
+                let Some(mut copy) = materials.get(&material).cloned() else {
+                    // not loaded yet
+                    continue;
+                };
+                copy.base_color *= tint_rgb;
+                let handle = materials.add(copy);
+                cache.insert(key, handle.clone());
+                handle
+            }
+        };
+        *material = tinted;
+        commands.entity(entity).remove::<Tint>();
+    }
+}
+
+// Joints are animated every frame towards the aiming direction cached by the gun layer,
+// so only the aiming solution itself follows the gun layer update rate
 fn orientation(
     turrets: Query<(&aiming::GunLayer, &TurretJoints)>,
     transforms: Query<&GlobalTransform, With<Children>>,
@@ -142,25 +456,161 @@ fn orientation(
     }
 }
 
-fn fire_control(mut turrets: Query<(&aiming::GunLayer, &mut gun::Trigger)>) {
-    for (gun_layer, mut gun_trigger) in turrets.iter_mut() {
-        // let's say for simplicity that target is 7m size
-        let threshold = (7.0 / gun_layer.distance).max(0.1);
-        let range = 3000.0;
-        if gun_layer.distance != 0.0 && gun_layer.angle < threshold && gun_layer.distance < range {
-            gun_trigger.pull();
+fn barrel_recoil(
+    mut ev_shot_fired: EventReader<ShotFiredEvent>,
+    recoils: Query<&BarrelRecoil>,
+    mut springs: Query<(&Transform, &mut Spring)>,
+) {
+    for ev in ev_shot_fired.iter() {
+        let Ok(recoil) = recoils.get(ev.barrel) else {
+            continue;
+        };
+        for &part in recoil.parts.iter() {
+            if let Ok((transform, mut spring)) = springs.get_mut(part) {
+                spring.kick(transform.back() * recoil.distance);
+            }
         }
     }
 }
 
+/// Turrets have no rigid body to be pushed by the `gun::Recoil`, it knocks their aim up instead,
+/// which the joints then bring back towards the target
+fn recoil_kick(
+    mut ev_shot_fired: EventReader<ShotFiredEvent>,
+    turrets: Query<(&gun::Recoil, &TurretJoints)>,
+    barrels: Query<&GlobalTransform>,
+    transforms: Query<&GlobalTransform, With<Children>>,
+    mut joints: Query<(&mut Transform, &Parent), With<Joint>>,
+) {
+    for ev in ev_shot_fired.iter() {
+        let Ok((recoil, turret_joints)) = turrets.get(ev.gun) else {
+            continue;
+        };
+        let Ok(barrel) = barrels.get(ev.barrel) else {
+            continue;
+        };
+        let forward = barrel.forward();
+        // The first joint turns the turret around its base, so its axis is the turret's up
+        let pivots: Vec<_> = turret_joints
+            .0
+            .iter()
+            .filter_map(|&joint| {
+                let (_, parent) = joints.get(joint).ok()?;
+                Some((joint, transforms.get(parent.get()).ok()?.up()))
+            })
+            .collect();
+        let Some(&(_, up)) = pivots.first() else {
+            continue;
+        };
+        for (joint, pivot) in pivots {
+            // Only joints raising the barrels take part, the one turning around `up` doesn't
+            let raise = pivot.cross(forward).dot(up);
+            if let Ok((mut transform, _)) = joints.get_mut(joint) {
+                transform.rotate_y(raise * recoil.0 * RECOIL_KICK);
+            }
+        }
+    }
+}
+
+/// Homing missiles are guided to the target the gun layer is aiming at
+fn missile_guidance(
+    mut turrets: Query<(&aiming::GunLayer, &mut gun::Guidance), Changed<aiming::GunLayer>>,
+) {
+    for (gun_layer, mut guidance) in turrets.iter_mut() {
+        if guidance.0 != gun_layer.target() {
+            guidance.0 = gun_layer.target();
+        }
+    }
+}
+
+/// Deploys turrets with a target in the range of their guns and stows idle ones.
+/// The head is the one that is deployed, while its parent body is lowered and armored with it.
+fn deploy_turrets(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut turrets: Query<(
+        Entity,
+        &aiming::GunLayer,
+        &gun::Gun,
+        &mut Deployment,
+        &mut Armor,
+    )>,
+    mut bodies: Query<(&mut Transform, &Lift, &mut Armor), Without<Deployment>>,
+    parent_query: Query<&Parent>,
+    scenes: Query<(), With<Handle<Scene>>>,
+) {
+    for (entity, gun_layer, gun, mut deployment, mut armor) in turrets.iter_mut() {
+        let target_in_range =
+            gun_layer.target().is_some() && gun_layer.distance < gun.effective_range();
+        let progress = deployment.progress;
+        if deployment.update(target_in_range, time.delta_seconds()) {
+            // animations are played on the turret scene, which may be mounted on a ship scene
+            if let Some(scene) = parent_query
+                .iter_ancestors(entity)
+                .find(|&e| scenes.contains(e))
+            {
+                let clip = if deployment.deploying {
+                    "Deploy"
+                } else {
+                    "Stow"
+                };
+                commands.entity(scene).insert(PlayAnimation::once(clip));
+            }
+        }
+        if deployment.progress == progress {
+            continue;
+        }
+
+        armor.reduction = deployment.armor();
+        let Some((mut transform, lift, mut body_armor)) = parent_query
+            .get(entity)
+            .ok()
+            .and_then(|parent| bodies.get_mut(parent.get()).ok())
+        else {
+            continue;
+        };
+        body_armor.reduction = deployment.armor();
+        let height = STOWED_HEIGHT + (1.0 - STOWED_HEIGHT) * deployment.progress;
+        transform.translation.y = lift.deployed.y * height;
+    }
+}
+
+/// Turrets don't fire until fully deployed
+fn hold_fire(mut turrets: Query<(&Deployment, &mut aiming::FireControl), Changed<Deployment>>) {
+    for (deployment, mut fire_control) in turrets.iter_mut() {
+        let hold_fire = !deployment.is_deployed();
+        if fire_control.hold_fire != hold_fire {
+            fire_control.hold_fire = hold_fire;
+        }
+    }
+}
+
+/// Turrets spawned by `SpawnTurretEvent`, turning towards their targets and shooting at them.
 pub struct TurretPlugin;
 impl Plugin for TurretPlugin {
     fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<SpringPlugin>() {
+            app.add_plugin(SpringPlugin);
+        }
+        if !app.is_plugin_added::<VeterancyPlugin>() {
+            app.add_plugin(VeterancyPlugin);
+        }
+        if !app.is_plugin_added::<PrefabPlugin>() {
+            app.add_plugin(PrefabPlugin);
+        }
         app.add_startup_system(load_turret_resources)
+            .register_type::<Joint>()
+            .register_type::<TurretKind>()
+            .register_type::<Deployment>()
             .add_event::<SpawnTurretEvent>()
             .add_system(spawn_turret)
+            .add_system(apply_tint)
             //.add_system(orientation.after(targeting::gun_layer))
             .add_system(orientation.after(aiming::gun_layer))
-            .add_system(fire_control);
+            .add_system(missile_guidance.after(aiming::gun_layer))
+            .add_system(barrel_recoil)
+            .add_system(recoil_kick.after(orientation))
+            .add_system(deploy_turrets.after(aiming::gun_layer))
+            .add_system(hold_fire.after(deploy_turrets).before(aiming::fire_control));
     }
 }
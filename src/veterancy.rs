@@ -0,0 +1,149 @@
+//! Veterancy of AI gunners. Kills made by drones and turrets are counted, and the ranks earned
+//! with them reduce the aiming error and the reaction delay of their `GunLayer`s and improve
+//! their target leading, so survivors of long sessions become noticeably more dangerous than
+//! fresh reinforcements.
+
+use bevy::prelude::*;
+
+use crate::{
+    aiming::{GunLayer, LeadQuality},
+    projectile::{DamageEvent, DamageSystems},
+};
+
+/// Kills required for each rank above the rookie one
+const RANK_KILLS: [u32; 3] = [2, 5, 10];
+/// Largest aiming error of rookies, in radians
+const ROOKIE_AIM_ERROR: f32 = 0.03;
+/// Seconds rookies take to open fire at a new target
+const ROOKIE_REACTION_DELAY: f32 = 1.0;
+/// Part of the rookie aiming error and reaction delay left at each rank
+const RANK_SKILL: [f32; RANK_KILLS.len() + 1] = [1.0, 0.7, 0.45, 0.25];
+
+/// Kills of the entity with a `GunLayer`. Kills of turrets mounted on a ship are shared by all
+/// of them, as the ship is credited for the kill.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct Veterancy {
+    kills: u32,
+}
+
+impl Veterancy {
+    pub const MAX_RANK: usize = RANK_KILLS.len();
+
+    pub fn kills(&self) -> u32 {
+        self.kills
+    }
+
+    /// From 0 for rookies to `MAX_RANK`
+    pub fn rank(&self) -> usize {
+        RANK_KILLS
+            .iter()
+            .filter(|&&kills| self.kills >= kills)
+            .count()
+    }
+
+    /// Largest aiming error at the current rank, in radians
+    pub fn aim_error(&self) -> f32 {
+        ROOKIE_AIM_ERROR * RANK_SKILL[self.rank()]
+    }
+
+    /// Seconds to open fire at a new target at the current rank
+    pub fn reaction_delay(&self) -> f32 {
+        ROOKIE_REACTION_DELAY * RANK_SKILL[self.rank()]
+    }
+
+    /// Gunners lead targets at least this well at the current rank, so elites predict perfectly
+    pub fn min_lead(&self) -> LeadQuality {
+        match self.rank() {
+            0 => LeadQuality::None,
+            1 => LeadQuality::FirstOrder,
+            2 => LeadQuality::Quadratic,
+            _ => LeadQuality::Full,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec3;
+
+    use super::{LeadQuality, Veterancy};
+    use crate::gun::scatter;
+
+    #[test]
+    fn test_ranks() {
+        let rookie = Veterancy::default();
+        assert_eq!(rookie.rank(), 0);
+        assert_eq!(rookie.min_lead(), LeadQuality::None);
+
+        let ranks: Vec<_> = [1, 2, 4, 5, 10, 100]
+            .into_iter()
+            .map(|kills| Veterancy { kills }.rank())
+            .collect();
+        assert_eq!(ranks, [0, 1, 1, 2, 3, Veterancy::MAX_RANK]);
+
+        let veteran = Veterancy { kills: 10 };
+        assert!(veteran.aim_error() < rookie.aim_error());
+        assert!(veteran.reaction_delay() < rookie.reaction_delay());
+        assert_eq!(veteran.min_lead(), LeadQuality::Full);
+    }
+
+    #[test]
+    fn test_rank_scatter() {
+        // Widest miss out of many shots aimed with the aiming error of the rank
+        let widest_miss = |veterancy: Veterancy| {
+            (0..1000)
+                .map(|_| scatter(Vec3::Z, veterancy.aim_error()).angle_between(Vec3::Z))
+                .fold(0.0, f32::max)
+        };
+        let rookie = widest_miss(Veterancy::default());
+        let veteran = widest_miss(Veterancy { kills: 10 });
+        assert!(rookie <= Veterancy::default().aim_error() + 1e-4);
+        assert!(veteran <= Veterancy { kills: 10 }.aim_error() + 1e-4);
+        assert!(veteran < rookie);
+    }
+}
+
+fn track_kills(
+    mut ev_damage: EventReader<DamageEvent>,
+    mut gunners: Query<(&mut Veterancy, Option<&Name>)>,
+    children: Query<&Children>,
+) {
+    for ev in ev_damage.iter().filter(|ev| ev.destroyed) {
+        let Some(attacker) = ev.attacker else {
+            continue;
+        };
+        for entity in std::iter::once(attacker).chain(children.iter_descendants(attacker)) {
+            let Ok((mut veterancy, name)) = gunners.get_mut(entity) else {
+                continue;
+            };
+            let rank = veterancy.rank();
+            veterancy.kills += 1;
+            if veterancy.rank() > rank {
+                let name = name.map_or("Unknown", |n| n.as_str());
+                info!(
+                    "{name} ({entity:?}) is promoted to rank {}",
+                    veterancy.rank()
+                );
+            }
+        }
+    }
+}
+
+fn apply_veterancy(mut gunners: Query<(&Veterancy, &mut GunLayer), Changed<Veterancy>>) {
+    for (veterancy, mut gun_layer) in gunners.iter_mut() {
+        gun_layer.aim_error = veterancy.aim_error();
+        gun_layer.reaction_delay = veterancy.reaction_delay();
+        gun_layer.lead = gun_layer.lead.max(veterancy.min_lead());
+    }
+}
+
+/// Kills of AI gunners with `Veterancy` and the aiming skill they get with ranks.
+pub struct VeterancyPlugin;
+impl Plugin for VeterancyPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Veterancy>()
+            .add_system(track_kills.after(DamageSystems))
+            .add_system(apply_veterancy.after(track_kills));
+    }
+}
@@ -6,6 +6,7 @@ use crate::gun;
 pub struct FlakCannon {
     trigger: gun::Trigger,
     gun: gun::Gun,
+    heat: gun::Heat,
     barrels: gun::MultiBarrel,
 }
 
@@ -14,7 +15,54 @@ impl FlakCannon {
     pub fn new(barrels: Vec<Entity>, rate_of_fire: f32) -> Self {
         Self {
             trigger: gun::Trigger::default(),
-            gun: gun::Gun::new(rate_of_fire, gun::Projectile::Bullet, 200.0),
+            gun: gun::Gun::new(rate_of_fire, gun::Projectile::FlakShell, 200.0, 1500.0),
+            heat: gun::Heat::new(0.02, 0.08, 1_f32.to_radians()),
+            barrels: gun::MultiBarrel::new(barrels),
+        }
+    }
+}
+
+/// Hold-to-charge railgun, firing instantaneous shots that pass through several targets on the
+/// release of the trigger.
+/// Add `gun::MultiBarrel` to fire from several barrels at once.
+#[derive(Bundle)]
+pub struct Railgun {
+    trigger: gun::Trigger,
+    gun: gun::Gun,
+    charge: gun::Charge,
+    recoil: gun::Recoil,
+}
+
+impl Railgun {
+    /// The trigger has to be held for `charge_time` seconds for every shot,
+    /// which can be charged only after the cooldown of the previous one
+    pub fn new(charge_time: f32) -> Self {
+        Self {
+            trigger: gun::Trigger::default(),
+            // 4 seconds cooldown, while the speed is practically infinite for aiming
+            gun: gun::Gun::new(0.25, gun::Projectile::Slug, 100_000.0, 3000.0),
+            charge: gun::Charge::new(charge_time),
+            recoil: gun::Recoil(500.0),
+        }
+    }
+}
+
+/// Launches a salvo of homing missiles, one from each barrel
+#[derive(Bundle)]
+pub struct MissileBattery {
+    trigger: gun::Trigger,
+    gun: gun::Gun,
+    guidance: gun::Guidance,
+    barrels: gun::MultiBarrel,
+}
+
+impl MissileBattery {
+    /// Each entity in the `barrels` should have `Barrel` component
+    pub fn new(barrels: Vec<Entity>, salvos_per_second: f32) -> Self {
+        Self {
+            trigger: gun::Trigger::default(),
+            gun: gun::Gun::new(salvos_per_second, gun::Projectile::Rocket, 60.0, 2000.0),
+            guidance: gun::Guidance::default(),
             barrels: gun::MultiBarrel::new(barrels),
         }
     }
@@ -24,13 +72,15 @@ impl FlakCannon {
 pub struct MachineGun {
     trigger: gun::Trigger,
     gun: gun::Gun,
+    heat: gun::Heat,
 }
 
 impl MachineGun {
     pub fn new(rate_of_fire: f32) -> Self {
         Self {
             trigger: gun::Trigger::default(),
-            gun: gun::Gun::new(rate_of_fire, gun::Projectile::Bullet, 200.0),
+            gun: gun::Gun::new(rate_of_fire, gun::Projectile::Bullet, 200.0, 1000.0),
+            heat: gun::Heat::new(0.03, 0.1, 2_f32.to_radians()),
         }
     }
 }
@@ -39,13 +89,15 @@ impl MachineGun {
 pub struct RocketLauncher {
     trigger: gun::Trigger,
     gun: gun::Gun,
+    guidance: gun::Guidance,
 }
 
 impl RocketLauncher {
     pub fn new(rate_of_fire: f32) -> Self {
         Self {
             trigger: gun::Trigger::default(),
-            gun: gun::Gun::new(rate_of_fire, gun::Projectile::Rocket, 20.0),
+            gun: gun::Gun::new(rate_of_fire, gun::Projectile::Rocket, 20.0, 800.0),
+            guidance: gun::Guidance::default(),
         }
     }
 }